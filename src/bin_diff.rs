@@ -0,0 +1,214 @@
+use std::{collections::BTreeMap, fs, io};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::Serialize;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::fsops;
+
+#[derive(Debug, Error)]
+pub enum BinDiffError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("filesystem error: {0}")]
+    FsOps(#[from] fsops::FsOpsError),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, BinDiffError>;
+
+/// Managed `bin/` links keyed by command name, with each target's path inside its release.
+pub type Snapshot = BTreeMap<String, Utf8PathBuf>;
+
+/// Commands that appeared, disappeared, or moved within the release between two snapshots.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BinDiff {
+    pub added: Vec<BinEntry>,
+    pub removed: Vec<BinEntry>,
+    pub changed: Vec<BinChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BinEntry {
+    pub name: String,
+    pub target: Utf8PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BinChange {
+    pub name: String,
+    pub from: Utf8PathBuf,
+    pub to: Utf8PathBuf,
+}
+
+/// Records the managed links in `bin_dir`.
+///
+/// Targets are stored relative to their release directory (`../releases/<tag>/sub/cmd`
+/// becomes `sub/cmd`) so that a plain version bump does not count as a change.
+/// Returns an empty snapshot if `bin_dir` does not exist.
+///
+/// # Errors
+///
+/// Returns an error if the bin directory or a link cannot be read.
+pub fn snapshot(bin_dir: impl AsRef<Utf8Path>) -> Result<Snapshot> {
+    let mut links = Snapshot::new();
+    for link in fsops::managed_links(bin_dir)? {
+        let Some(name) = link.file_name() else {
+            continue;
+        };
+        let target = Utf8PathBuf::try_from(fs::read_link(&link)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        links.insert(name.to_string(), release_relative(&target));
+    }
+    Ok(links)
+}
+
+fn release_relative(target: &Utf8Path) -> Utf8PathBuf {
+    let mut components = target.components();
+    if components
+        .by_ref()
+        .any(|component| component.as_str() == "releases")
+    {
+        components.next();
+        components.collect()
+    } else {
+        target.to_path_buf()
+    }
+}
+
+impl BinDiff {
+    /// Compares the links before and after an update.
+    #[must_use]
+    pub fn between(before: &Snapshot, after: &Snapshot) -> Self {
+        let mut diff = Self::default();
+
+        for (name, target) in after {
+            match before.get(name) {
+                None => diff.added.push(BinEntry {
+                    name: name.clone(),
+                    target: target.clone(),
+                }),
+                Some(previous) if previous != target => diff.changed.push(BinChange {
+                    name: name.clone(),
+                    from: previous.clone(),
+                    to: target.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+
+        for (name, target) in before {
+            if !after.contains_key(name) {
+                diff.removed.push(BinEntry {
+                    name: name.clone(),
+                    target: target.clone(),
+                });
+            }
+        }
+
+        diff
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Logs one line per added, removed, or changed command.
+    pub fn log(&self) {
+        if self.is_empty() {
+            info!("bin/ commands unchanged");
+            return;
+        }
+        for entry in &self.added {
+            info!("bin/{} added (-> {})", entry.name, entry.target);
+        }
+        for entry in &self.removed {
+            warn!("bin/{} removed (was {})", entry.name, entry.target);
+        }
+        for change in &self.changed {
+            info!(
+                "bin/{} changed: {} -> {}",
+                change.name, change.from, change.to
+            );
+        }
+    }
+
+    /// Atomically writes the diff to `path` as pretty-printed JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The parent directory cannot be created
+    /// - Writing, syncing, or persisting the file fails
+    pub fn write_json(&self, path: impl AsRef<Utf8Path>) -> Result<()> {
+        fsops::write_json_atomic(path, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix;
+
+    use camino_tempfile::tempdir;
+
+    use super::*;
+
+    fn snapshot_of(entries: &[(&str, &str)]) -> Snapshot {
+        entries
+            .iter()
+            .map(|(name, target)| ((*name).to_string(), Utf8PathBuf::from(*target)))
+            .collect()
+    }
+
+    #[test]
+    fn test_snapshot_strips_release_prefix() {
+        let temp_dir = tempdir().unwrap();
+        let bin_dir = temp_dir.path().join("bin");
+        fs::create_dir_all(&bin_dir).unwrap();
+        unix::fs::symlink("../releases/v1.0.0/bin/myapp", bin_dir.join("myapp")).unwrap();
+
+        let snapshot = snapshot(&bin_dir).unwrap();
+
+        assert_eq!(snapshot, snapshot_of(&[("myapp", "bin/myapp")]));
+    }
+
+    #[test]
+    fn test_between_reports_added_removed_and_changed() {
+        let before = snapshot_of(&[("myapp", "myapp"), ("old", "old"), ("tool", "tool")]);
+        let after = snapshot_of(&[("myapp", "myapp"), ("new", "new"), ("tool", "bin/tool")]);
+
+        let diff = BinDiff::between(&before, &after);
+
+        assert_eq!(
+            diff.added,
+            vec![BinEntry {
+                name: "new".to_string(),
+                target: "new".into()
+            }]
+        );
+        assert_eq!(
+            diff.removed,
+            vec![BinEntry {
+                name: "old".to_string(),
+                target: "old".into()
+            }]
+        );
+        assert_eq!(
+            diff.changed,
+            vec![BinChange {
+                name: "tool".to_string(),
+                from: "tool".into(),
+                to: "bin/tool".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_between_identical_is_empty() {
+        let links = snapshot_of(&[("myapp", "myapp")]);
+        assert!(BinDiff::between(&links, &links).is_empty());
+    }
+}
@@ -0,0 +1,323 @@
+use std::{collections::BTreeSet, fmt::Write as _, io, sync::LazyLock};
+
+use camino::Utf8Path;
+use jiff::Timestamp;
+use regex::Regex;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{fsops, github::Release};
+
+static CVE_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\bCVE-\d{4}-\d{4,}\b").unwrap());
+
+/// Markup inside a line of release notes: images and links (keeping their
+/// text), HTML tags, and emphasis and code markers.
+static INLINE_MARKUP: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"!?\[([^\]]*)\]\([^)]*\)|<[^>\s][^>]*>|\*\*|__|`|\*([^*\s][^*]*)\*").unwrap()
+});
+
+/// Block markers at the start of a line of release notes: headings,
+/// blockquotes, and `*` or `+` list bullets.
+static BLOCK_MARKUP: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(?:#{1,6}\s+|>\s?|[*+]\s+)").unwrap());
+
+#[derive(Debug, Error)]
+pub enum ChangeRecordError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, ChangeRecordError>;
+
+/// Release notes as plain text for a terminal: Markdown markup and HTML comments
+/// stripped, runs of blank lines collapsed, and cut to `max_lines` lines, the
+/// last of which is `…` when anything was cut.
+#[must_use]
+pub fn plain_notes(markdown: &str, max_lines: usize) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_comment = false;
+    for line in markdown.lines() {
+        let line = line.trim_end();
+        if in_comment || line.trim_start().starts_with("<!--") {
+            in_comment = !line.contains("-->");
+            continue;
+        }
+        if line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~") {
+            continue;
+        }
+        let line = if BLOCK_MARKUP.is_match(line) {
+            let bullet = line.trim_start().starts_with(['*', '+']);
+            let rest = BLOCK_MARKUP.replace(line, "");
+            if bullet {
+                format!("- {rest}")
+            } else {
+                rest.into_owned()
+            }
+        } else {
+            line.to_string()
+        };
+        let line = INLINE_MARKUP
+            .replace_all(&line, |caps: &regex::Captures| {
+                caps.get(1)
+                    .or_else(|| caps.get(2))
+                    .map_or("", |text| text.as_str())
+                    .to_string()
+            })
+            .trim_end()
+            .to_string();
+        if line.is_empty() && lines.last().is_none_or(String::is_empty) {
+            continue;
+        }
+        lines.push(line);
+    }
+    if lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+
+    if lines.len() > max_lines {
+        lines.truncate(max_lines.saturating_sub(1));
+        lines.push("…".to_string());
+    }
+    lines.join("\n")
+}
+
+/// A description of a pending update, suitable for attaching to a change-management ticket.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangeRecord {
+    pub app: String,
+    pub repo: String,
+    pub current_tag: Option<String>,
+    pub target_tag: String,
+    pub release_url: Option<String>,
+    pub published_at: Option<Timestamp>,
+    pub prerelease: bool,
+    pub assets: Vec<AssetRecord>,
+    pub cve_references: Vec<String>,
+    pub release_notes: String,
+    pub generated_at: Timestamp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AssetRecord {
+    pub name: String,
+    pub size: u64,
+    /// Digest reported by GitHub (e.g., `sha256:...`), when available.
+    pub digest: Option<String>,
+}
+
+impl ChangeRecord {
+    /// Builds a change record for moving `app` from `current_tag` to `release`.
+    #[must_use]
+    pub fn new(app: &str, repo: &str, current_tag: Option<&str>, release: &Release) -> Self {
+        let release_notes = release.body.clone().unwrap_or_default();
+        let cve_references = CVE_PATTERN
+            .find_iter(&release_notes)
+            .map(|m| m.as_str().to_ascii_uppercase())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        Self {
+            app: app.to_string(),
+            repo: repo.to_string(),
+            current_tag: current_tag.map(str::to_string),
+            target_tag: release.tag_name.clone(),
+            release_url: release.html_url.clone(),
+            published_at: release.published_at,
+            prerelease: release.prerelease,
+            assets: release
+                .assets
+                .iter()
+                .map(|asset| AssetRecord {
+                    name: asset.name.clone(),
+                    size: asset.size,
+                    digest: asset.digest.clone(),
+                })
+                .collect(),
+            cve_references,
+            release_notes,
+            generated_at: Timestamp::now(),
+        }
+    }
+
+    /// Renders the record as a Markdown document.
+    #[must_use]
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let current = self.current_tag.as_deref().unwrap_or("(not installed)");
+
+        let _ = writeln!(out, "# Change record: {} {}", self.app, self.target_tag);
+        let _ = writeln!(out);
+        let _ = writeln!(out, "- **App:** {}", self.app);
+        let _ = writeln!(out, "- **Repository:** {}", self.repo);
+        let _ = writeln!(out, "- **Change:** {current} → {}", self.target_tag);
+        if let Some(published_at) = self.published_at {
+            let _ = writeln!(out, "- **Published:** {published_at}");
+        }
+        if self.prerelease {
+            let _ = writeln!(out, "- **Prerelease:** yes");
+        }
+        if let Some(url) = &self.release_url {
+            let _ = writeln!(out, "- **Release:** {url}");
+        }
+        let _ = writeln!(out, "- **Generated:** {}", self.generated_at);
+
+        let _ = writeln!(out);
+        let _ = writeln!(out, "## Security references");
+        let _ = writeln!(out);
+        if self.cve_references.is_empty() {
+            let _ = writeln!(out, "None found in the release notes.");
+        } else {
+            for cve in &self.cve_references {
+                let _ = writeln!(out, "- {cve}");
+            }
+        }
+
+        let _ = writeln!(out);
+        let _ = writeln!(out, "## Assets");
+        let _ = writeln!(out);
+        let _ = writeln!(out, "| Name | Size | Digest |");
+        let _ = writeln!(out, "| --- | --- | --- |");
+        for asset in &self.assets {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} |",
+                asset.name,
+                asset.size,
+                asset.digest.as_deref().unwrap_or("(not reported)")
+            );
+        }
+
+        let _ = writeln!(out);
+        let _ = writeln!(out, "## Release notes");
+        let _ = writeln!(out);
+        if self.release_notes.trim().is_empty() {
+            let _ = writeln!(out, "(none)");
+        } else {
+            let _ = writeln!(out, "{}", self.release_notes.trim_end());
+        }
+
+        out
+    }
+
+    /// Atomically writes the record to `path`.
+    ///
+    /// A `.json` extension produces pretty-printed JSON; anything else produces Markdown.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The parent directory cannot be created
+    /// - The record cannot be serialized to JSON
+    /// - Writing, syncing, or persisting the file fails
+    pub fn write(&self, path: impl AsRef<Utf8Path>) -> Result<()> {
+        let path = path.as_ref();
+        let contents = if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+        {
+            serde_json::to_string_pretty(self)? + "\n"
+        } else {
+            self.to_markdown()
+        };
+        fsops::write_atomic(path, contents.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use camino_tempfile::tempdir;
+
+    use super::*;
+    use crate::github::Asset;
+
+    fn release() -> Release {
+        Release {
+            tag_name: "v1.1.0".to_string(),
+            assets: vec![Asset {
+                name: "myapp-1.1.0.tar.gz".to_string(),
+                url: "https://api.github.com/repos/owner/repo/releases/assets/1".to_string(),
+                browser_download_url:
+                    "https://github.com/owner/repo/releases/download/v1.1.0/myapp-1.1.0.tar.gz"
+                        .to_string(),
+                size: 1024,
+                digest: Some("sha256:abc123".to_string()),
+                uploader: None,
+            }],
+            prerelease: false,
+            draft: false,
+            created_at: None,
+            published_at: None,
+            body: Some(
+                "Fixes cve-2025-1234 and CVE-2024-56789.\nAlso CVE-2025-1234 again.".to_string(),
+            ),
+            html_url: Some("https://github.com/owner/repo/releases/tag/v1.1.0".to_string()),
+            tarball_url: None,
+            zipball_url: None,
+        }
+    }
+
+    #[test]
+    fn test_new_extracts_unique_cve_references() {
+        let record = ChangeRecord::new("myapp", "owner/repo", Some("v1.0.0"), &release());
+
+        assert_eq!(
+            record.cve_references,
+            vec!["CVE-2024-56789", "CVE-2025-1234"]
+        );
+        assert_eq!(record.current_tag.as_deref(), Some("v1.0.0"));
+        assert_eq!(record.target_tag, "v1.1.0");
+        assert_eq!(record.assets[0].digest.as_deref(), Some("sha256:abc123"));
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let record = ChangeRecord::new("myapp", "owner/repo", None, &release());
+        let markdown = record.to_markdown();
+
+        assert!(markdown.starts_with("# Change record: myapp v1.1.0\n"));
+        assert!(markdown.contains("- **Change:** (not installed) → v1.1.0"));
+        assert!(markdown.contains("- CVE-2025-1234"));
+        assert!(markdown.contains("| myapp-1.1.0.tar.gz | 1024 | sha256:abc123 |"));
+        assert!(markdown.contains("Fixes cve-2025-1234"));
+    }
+
+    #[test]
+    fn test_plain_notes() {
+        let markdown = "## What's Changed\r\n\r\n<!-- generated -->\n* Fix **crash** in `sync` by @octo in [#12](https://github.com/o/r/pull/12)\n+ Support *zstd* archives\n\n\n> Note: snake_case_names stay\n```sh\nmyapp --help\n```\n";
+        assert_eq!(
+            plain_notes(markdown, 20),
+            "What's Changed\n\n- Fix crash in sync by @octo in #12\n- Support zstd archives\n\nNote: snake_case_names stay\nmyapp --help"
+        );
+        assert_eq!(plain_notes(markdown, 3), "What's Changed\n\n…");
+        assert_eq!(plain_notes("", 20), "");
+    }
+
+    #[test]
+    fn test_write_chooses_format_from_extension() {
+        let temp_dir = tempdir().unwrap();
+        let record = ChangeRecord::new("myapp", "owner/repo", Some("v1.0.0"), &release());
+
+        let json_path = temp_dir.path().join("record.json");
+        record.write(&json_path).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(json["target_tag"], "v1.1.0");
+        assert_eq!(json["cve_references"][1], "CVE-2025-1234");
+
+        let md_path = temp_dir.path().join("record.md");
+        record.write(&md_path).unwrap();
+        assert!(
+            fs::read_to_string(&md_path)
+                .unwrap()
+                .starts_with("# Change record")
+        );
+    }
+}
@@ -1,17 +1,49 @@
-use std::fs::{self, File};
+use std::{
+    borrow::Cow,
+    env,
+    fs::{self, File},
+    io::{self, IsTerminal as _, Write as _},
+    os::unix::fs::MetadataExt as _,
+    process::{Command, ExitCode, Stdio},
+    time::{Duration, Instant},
+};
 
-use anyhow::{anyhow, ensure};
-use camino::{Utf8Path, Utf8PathBuf};
+use anyhow::{Context as _, anyhow, bail, ensure};
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
 use camino_tempfile::NamedUtf8TempFile;
-use clap::{Parser, Subcommand};
-use jiff::Timestamp;
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::{Shell, engine::ArgValueCandidates, env::Shells};
+use jiff::{Timestamp, tz::TimeZone};
 use regex::Regex;
-use tracing::{info, info_span, warn};
+use reqwest::{
+    Url,
+    header::{AUTHORIZATION, HeaderMap},
+};
+use rustix::fs::Access;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tracing::{debug, info, info_span, warn};
 
 use crate::{
-    DEFAULT_GITHUB_HOST, DEFAULT_INSTALL_ROOT, download, extract, fsops, github, lock, restart,
+    DEFAULT_GITHUB_HOST, DEFAULT_INSTALL_ROOT, DEFAULT_RETAIN, DEFAULT_TIMEOUT,
+    bin_diff::{self, BinDiff},
+    bitbucket,
+    change_record::{self, ChangeRecord},
+    cluster, completion,
+    config::{self, AppConfig},
+    constraint::{self, BlockReason, VersionConstraint, VersionFilter},
+    download,
+    events::{EventFormat, Events},
+    extract, fsops, gh_auth, github,
+    history::{self, Entry as HistoryEntry},
+    json_feed, keyring_auth, lock, login, maintenance, manifest, notices, notify, pattern,
+    pending::{self, Pending},
+    pin::{self, Pin, PinnedError},
+    plan, privsep, proxy,
+    release_cache::{self, CachedRelease},
+    restart, s3, scan, self_update,
     state::{self, State},
-    verify, version,
+    tls::SpkiPin,
+    trust, verify, version,
 };
 
 fn validate_app_name(s: &str) -> Result<String, String> {
@@ -33,34 +65,171 @@ fn validate_app_name(s: &str) -> Result<String, String> {
     Ok(s.to_string())
 }
 
-#[derive(Parser, Debug)]
+/// Expands `~`, makes relative paths absolute, and resolves `.`/`..` components.
+///
+/// Components are resolved one at a time, so the existing part of the path is
+/// canonicalized with its symlinks followed before a `..` is applied, and only the
+/// part that does not exist yet is normalized lexically. Later filesystem
+/// operations then see the same absolute path.
+fn parse_path(s: &str) -> Result<Utf8PathBuf, String> {
+    if s.is_empty() {
+        return Err("path cannot be empty".to_string());
+    }
+
+    let expanded = if s == "~" || s.starts_with("~/") {
+        let home =
+            env::var("HOME").map_err(|_| "cannot expand '~': HOME is not set".to_string())?;
+        Utf8PathBuf::from(home).join(s[1..].trim_start_matches('/'))
+    } else if s.starts_with('~') {
+        return Err("'~user' paths are not supported; use an absolute path".to_string());
+    } else {
+        Utf8PathBuf::from(s)
+    };
+
+    let absolute = if expanded.is_absolute() {
+        expanded
+    } else {
+        let cwd =
+            env::current_dir().map_err(|e| format!("cannot resolve relative path {s:?}: {e}"))?;
+        let cwd = Utf8PathBuf::try_from(cwd).map_err(|_| {
+            format!("cannot resolve relative path {s:?}: current directory is not valid UTF-8")
+        })?;
+        cwd.join(expanded)
+    };
+
+    let mut resolved = Utf8PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Utf8Component::CurDir => {}
+            Utf8Component::ParentDir => {
+                resolved.pop();
+            }
+            Utf8Component::Normal(name) => {
+                resolved.push(name);
+                match resolved.canonicalize_utf8() {
+                    Ok(canonical) => resolved = canonical,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(format!("cannot resolve {resolved}: {e}")),
+                }
+            }
+            other => resolved.push(other),
+        }
+    }
+
+    Ok(resolved)
+}
+
+#[derive(Parser, Debug, Clone)]
 pub struct Args {
-    #[arg(long, value_parser = validate_app_name, help = "Application name (used for directory structure under install root)")]
-    pub app: String,
+    #[arg(long, value_parser = validate_app_name, add = ArgValueCandidates::new(completion::app_candidates), help = "Application name (used for directory structure under install root; required except for update-all)")]
+    pub app: Option<String>,
 
     #[arg(
         long,
         env = "DISTRONOMICON_INSTALL_ROOT",
-        default_value = DEFAULT_INSTALL_ROOT,
-        help = "Root directory for installations (creates <root>/<app>/{bin,releases,staging})"
+        value_parser = parse_path,
+        help = "Root directory for installations (creates <root>/<app>/{bin,releases,staging}) [default: /opt]"
+    )]
+    pub install_root: Option<Utf8PathBuf>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_SYSROOT",
+        value_parser = parse_path,
+        value_name = "DIR",
+        help = "Treat DIR as the filesystem root, such as a mounted image: the install root, state directory, license directory, and config file are resolved inside it, and restart and health commands are skipped"
+    )]
+    pub sysroot: Option<Utf8PathBuf>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_CONFIG",
+        value_parser = parse_path,
+        help = "TOML config file with per-app settings (default: /etc/distronomicon/<app>.toml if present, or /etc/distronomicon/apps.toml for update-all)"
+    )]
+    pub config: Option<Utf8PathBuf>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_PROFILE",
+        value_name = "NAME",
+        help = "Apply the [profiles.NAME] section of the config file over its other settings (e.g. prod or canary)"
+    )]
+    pub profile: Option<String>,
+
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "HTTP request timeout in seconds (applies to downloads, GitHub API, checksum verification) [default: 300]"
+    )]
+    pub http_timeout: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Retries for transient asset download failures [default: 3]"
+    )]
+    pub http_retries: Option<u32>,
+
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Abort an asset download if no data arrives for this many seconds"
+    )]
+    pub stall_timeout: Option<u64>,
+
+    #[arg(
+        long = "spki-pin",
+        value_name = "PIN",
+        help = "Require the TLS chain to contain a key with this SHA-256 SPKI hash (sha256//<base64>); repeatable"
+    )]
+    pub spki_pins: Vec<SpkiPin>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_PROXY_MODE",
+        value_name = "MODE",
+        help = "Where to take HTTP proxy settings from: env (this process's http_proxy/https_proxy/no_proxy), direct (no proxy), or system (/etc/environment) [default: env]"
     )]
-    pub install_root: Utf8PathBuf,
+    pub proxy_mode: Option<proxy::Mode>,
 
     #[arg(
         long,
-        default_value = "300",
-        help = "HTTP request timeout in seconds (applies to downloads, GitHub API, checksum verification)"
+        env = "DISTRONOMICON_STATE_BACKEND",
+        value_name = "BACKEND",
+        help = "Where to keep per-app state: json (<state-dir>/<app>/state.json) or sqlite (<state-dir>/state.db) [default: json]"
     )]
-    pub http_timeout: u64,
+    pub state_backend: Option<state::Backend>,
 
     #[arg(short, long, action = clap::ArgAction::Count, help = "Increase logging verbosity (-v for debug, -vv for trace)")]
     pub verbose: u8,
 
+    #[arg(
+        short,
+        long,
+        conflicts_with = "verbose",
+        help = "Only print errors and, for check, available updates (for cron jobs)"
+    )]
+    pub quiet: bool,
+
+    #[arg(
+        short,
+        long,
+        help = "Assume yes for confirmation prompts on destructive operations"
+    )]
+    pub yes: bool,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_EXPLAIN",
+        help = "When a command fails, also print which phase failed, what was checked, likely causes, and commands to try next"
+    )]
+    pub explain: bool,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
-#[derive(Subcommand, Debug)]
+#[derive(Subcommand, Debug, Clone)]
 pub enum Commands {
     #[command(about = "Check for updates without installing (updates cached state validators)")]
     Check(CheckArgs),
@@ -68,16 +237,81 @@ pub enum Commands {
     #[command(
         about = "Update to latest release (download, verify, extract, install, and optionally restart)"
     )]
-    Update(UpdateArgs),
+    Update(Box<UpdateArgs>),
 
     #[command(about = "Show currently installed version (derived from symlinks in bin directory)")]
     Version,
 
     #[command(about = "Forcibly remove the lock file (use with caution)")]
     Unlock(UnlockArgs),
+
+    #[command(about = "Remove an app's managed symlinks, releases, staging, and state")]
+    Uninstall(UninstallArgs),
+
+    #[command(about = "Update every app listed in a multi-app config file")]
+    UpdateAll(UpdateAllArgs),
+
+    #[command(
+        about = "Take over an app installed by hand under <root>/<app>/{releases,bin}, recording its state"
+    )]
+    Adopt(AdoptArgs),
+
+    #[command(about = "Switch back to the previously installed release")]
+    Rollback(RollbackArgs),
+
+    #[command(about = "List installed releases with install time, size, and which is active")]
+    List(ListArgs),
+
+    #[command(about = "List releases available on GitHub with their matching assets")]
+    ListRemote(ListRemoteArgs),
+
+    #[command(
+        about = "Show installed version, recorded state, lock, staging leftovers, and update availability"
+    )]
+    Status(StatusArgs),
+
+    #[command(
+        about = "Check permissions, disk space, GitHub access, and install layout before updating"
+    )]
+    Doctor(DoctorArgs),
+
+    #[command(about = "Remove staging directories left behind by interrupted updates")]
+    Gc(GcArgs),
+
+    #[command(about = "Delete old releases beyond the retention count without updating")]
+    Prune(PruneArgs),
+
+    #[command(
+        about = "Trim the install history and saved debug responses in an app's state directory"
+    )]
+    Maintenance(MaintenanceArgs),
+
+    #[command(about = "Show the journal of successful installs (tag, time, asset, checksum)")]
+    History(HistoryArgs),
+
+    #[command(about = "Hold an app at its installed release so update refuses to change it")]
+    Pin(PinArgs),
+
+    #[command(about = "Remove a pin so update may change the installed release again")]
+    Unpin(UnpinArgs),
+
+    #[command(
+        about = "Authorize with GitHub in a browser (device flow) and store the token for later runs"
+    )]
+    Login(LoginArgs),
+
+    #[command(about = "Replace this distronomicon binary with its latest release")]
+    SelfUpdate(SelfUpdateArgs),
+
+    #[command(about = "Print a shell completion script for bash, zsh, fish, elvish, or powershell")]
+    Completions(CompletionsArgs),
+
+    /// Makes the network requests of `update --network-user` as that user.
+    #[command(name = privsep::HELPER_COMMAND, hide = true)]
+    NetworkHelper,
 }
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone, Serialize, Deserialize)]
 pub struct GitHubConfig {
     #[arg(
         long = "github-token",
@@ -87,489 +321,6160 @@ pub struct GitHubConfig {
     )]
     pub token: Option<String>,
 
+    #[arg(
+        long = "github-token-file",
+        env = "GITHUB_TOKEN_FILE",
+        value_name = "PATH",
+        value_parser = parse_path,
+        help = "Read the GitHub API token from this file, which should be readable only by its owner"
+    )]
+    pub token_file: Option<Utf8PathBuf>,
+
+    #[arg(
+        long = "github-token-command",
+        env = "DISTRONOMICON_GITHUB_TOKEN_COMMAND",
+        value_name = "COMMAND",
+        help = "Shell command that prints the GitHub API token on stdout, such as a secrets manager CLI"
+    )]
+    pub token_command: Option<String>,
+
+    #[arg(
+        long = "github-token-keyring",
+        alias = "token-keyring",
+        env = "DISTRONOMICON_GITHUB_TOKEN_KEYRING",
+        value_name = "ENTRY",
+        help = "Read the GitHub API token from this OS keyring entry, in the Secret Service and then the kernel keyring (prefix secret-service: or keyutils: to use one)"
+    )]
+    pub token_keyring: Option<String>,
+
+    #[arg(
+        long = "github-token-from-gh",
+        env = "DISTRONOMICON_GITHUB_TOKEN_FROM_GH",
+        help = "Use the token the gh CLI is logged in with (gh auth token, or its hosts.yml)"
+    )]
+    pub token_from_gh: bool,
+
     #[arg(
         long = "github-host",
         env = "GITHUB_HOST",
-        default_value = DEFAULT_GITHUB_HOST,
-        help = "GitHub API hostname (use for GitHub Enterprise)"
+        help = "GitHub API hostname (use for GitHub Enterprise) [default: https://api.github.com]"
     )]
-    pub host: String,
+    pub host: Option<String>,
 
     #[arg(
         long = "allow-prerelease",
         env = "DISTRONOMICON_ALLOW_PRERELEASE",
-        help = "Include prerelease versions when checking for updates"
+        conflicts_with = "channel",
+        help = "Include every prerelease when checking for updates, the same as --channel prerelease"
     )]
     pub allow_prerelease: bool,
-}
 
-#[derive(Parser, Debug)]
-pub struct CheckArgs {
     #[arg(
         long,
-        env = "DISTRONOMICON_REPO",
-        help = "GitHub repository in owner/repo format (e.g., 'rust-lang/rust')"
+        env = "DISTRONOMICON_CHANNEL",
+        value_name = "CHANNEL",
+        help = "Release channel to follow: stable, rc (adds -rc tags), beta (adds -beta and -rc tags), or prerelease (every prerelease) [default: stable]"
     )]
-    pub repo: String,
+    pub channel: Option<github::Channel>,
 
     #[arg(
         long,
-        env = "STATE_DIRECTORY",
-        help = "Directory for storing state.json with ETags and timestamps"
+        env = "DISTRONOMICON_FALLBACK_TO_TAGS",
+        help = "When a GitHub repository has no releases, install the source tarball of its newest tag (needs --skip-verification)"
     )]
-    pub state_directory: Utf8PathBuf,
+    pub fallback_to_tags: bool,
 
-    #[command(flatten)]
-    pub github: GitHubConfig,
-}
+    #[arg(
+        long,
+        env = "DISTRONOMICON_RATE_LIMIT_WAIT",
+        value_name = "SECS",
+        help = "When GitHub rate limits a request, wait up to this many seconds in total for the limit to reset instead of failing [default: 0]"
+    )]
+    pub rate_limit_wait: Option<u64>,
 
-#[derive(Parser, Debug)]
-pub struct UpdateArgs {
     #[arg(
         long,
-        env = "DISTRONOMICON_REPO",
-        help = "GitHub repository in owner/repo format (e.g., 'rust-lang/rust')"
+        env = "DISTRONOMICON_SOURCE",
+        value_enum,
+        value_name = "SOURCE",
+        help = "Where releases are published: github, bitbucket for Bitbucket Cloud Downloads, json for a self-hosted JSON feed, or s3 for an S3-compatible bucket such as GCS [default: github]"
     )]
-    pub repo: String,
+    pub source: Option<Source>,
 
     #[arg(
         long,
-        env = "DISTRONOMICON_PATTERN",
-        help = "Regex pattern to match release asset filename (e.g., '.*\\.tar\\.gz$')"
+        env = "DISTRONOMICON_JSON_URL",
+        value_name = "URL",
+        required_if_eq("source", "json"),
+        help = "With --source json, URL of the feed describing the current release as {version, assets: [{name, url, sha256}]}"
     )]
-    pub pattern: String,
+    pub json_url: Option<String>,
 
     #[arg(
         long,
-        env = "STATE_DIRECTORY",
-        help = "Directory for storing state.json with ETags and timestamps"
+        env = "DISTRONOMICON_BUCKET_URL",
+        value_name = "URL",
+        required_if_eq("source", "s3"),
+        help = "With --source s3, endpoint URL of the bucket (e.g. https://my-bucket.s3.eu-west-1.amazonaws.com or https://storage.googleapis.com/my-bucket); --repo is the key prefix"
     )]
-    pub state_directory: Utf8PathBuf,
+    pub bucket_url: Option<String>,
 
     #[arg(
         long,
-        env = "DISTRONOMICON_CHECKSUM_PATTERN",
-        required_unless_present = "skip_verification",
-        help = "Regex pattern to match checksum file (e.g., 'SHA256SUMS'); required unless --skip-verification"
+        env = "AWS_REGION",
+        help = "Region for signing S3 requests (use 'auto' for GCS) [default: us-east-1]"
     )]
-    pub checksum_pattern: Option<String>,
+    pub s3_region: Option<String>,
 
-    #[command(flatten)]
-    pub github: GitHubConfig,
+    #[arg(
+        long,
+        env = "AWS_ACCESS_KEY_ID",
+        requires = "s3_secret_access_key",
+        help = "Access key ID for --source s3; without one the bucket is read anonymously"
+    )]
+    pub s3_access_key_id: Option<String>,
 
     #[arg(
         long,
-        env = "DISTRONOMICON_RESTART_COMMAND",
-        help = "Shell command to execute after successful update (e.g., 'systemctl restart myapp')"
+        env = "AWS_SECRET_ACCESS_KEY",
+        hide_env_values = true,
+        requires = "s3_access_key_id",
+        help = "Secret access key for --source s3 (or a GCS HMAC secret)"
     )]
-    pub restart_command: Option<String>,
+    pub s3_secret_access_key: Option<String>,
 
     #[arg(
         long,
-        env = "DISTRONOMICON_RETAIN",
-        default_value = "3",
-        help = "Number of old releases to keep after update (older releases are pruned)"
+        env = "AWS_SESSION_TOKEN",
+        hide_env_values = true,
+        help = "Session token for temporary S3 credentials"
     )]
-    pub retain: u32,
+    pub s3_session_token: Option<String>,
 
     #[arg(
         long,
-        help = "Skip checksum verification (not recommended; use only for testing)"
+        env = "BITBUCKET_USERNAME",
+        requires = "bitbucket_app_password",
+        help = "Bitbucket username for --source bitbucket"
     )]
-    pub skip_verification: bool,
+    pub bitbucket_username: Option<String>,
 
     #[arg(
         long,
-        help = "Forcibly remove lock file before starting update (use with caution)"
+        env = "BITBUCKET_APP_PASSWORD",
+        hide_env_values = true,
+        requires = "bitbucket_username",
+        help = "Bitbucket app password for --source bitbucket (needs repository read access)"
     )]
-    pub force_unlock: bool,
+    pub bitbucket_app_password: Option<String>,
 
     #[arg(
         long,
-        default_value = "30",
-        help = "Maximum seconds to wait for lock acquisition (default: 30)"
+        env = "DISTRONOMICON_TAG_PATTERN",
+        value_name = "REGEX",
+        help = "On GitHub, consider only releases whose tag matches this regex, such as ^cli-v for one component of a monorepo; with --source bitbucket or s3, the regex finding the version in download names or object keys, whose first capture group, or whole match, is the tag [default there: v?\\d+(?:\\.\\d+)+]"
     )]
-    pub lock_timeout: u64,
-}
+    pub tag_pattern: Option<String>,
 
-#[derive(Parser, Debug)]
-pub struct UnlockArgs {
     #[arg(
         long,
-        env = "STATE_DIRECTORY",
-        help = "Directory containing the lock file"
+        env = "DISTRONOMICON_VERSION_CONSTRAINT",
+        value_name = "RANGE",
+        help = "Only install releases whose tag is a version satisfying this semver range, such as ^1.4 to stay within 1.x from 1.4 on; the highest satisfying version is used"
     )]
-    pub state_directory: Utf8PathBuf,
-}
+    pub version_constraint: Option<String>,
 
-fn is_up_to_date(
-    current_tag: Option<&String>,
-    release_opt: Option<&github::Release>,
-    existing_state: Option<&State>,
-    was_modified: bool,
-) -> bool {
-    if !was_modified
-        && let (Some(current), Some(state)) = (current_tag, existing_state)
-        && *current == state.latest_tag
-    {
-        return true;
-    }
+    #[arg(
+        long,
+        env = "DISTRONOMICON_VERSION_PREFIX",
+        value_name = "PREFIX",
+        help = "Prefix to strip from tags before reading them as versions for --version-constraint, such as cli-v [default: everything before the first digit]"
+    )]
+    pub version_prefix: Option<String>,
 
-    if let (Some(current), Some(release)) = (current_tag, release_opt)
-        && *current == release.tag_name
-    {
-        return true;
-    }
+    #[arg(
+        long,
+        env = "DISTRONOMICON_MIN_RELEASE_AGE",
+        value_name = "AGE",
+        help = "Skip releases published more recently than AGE, such as 48h or 7d, so a compromised release can be pulled before it is installed"
+    )]
+    pub min_release_age: Option<github::ReleaseAge>,
 
-    false
+    #[arg(
+        long = "skip-version",
+        env = "DISTRONOMICON_SKIP_VERSIONS",
+        value_name = "TAG",
+        value_delimiter = ',',
+        help = "Never install this release, even when it is the latest, such as a known-bad v1.4.2; repeat or separate with commas for several"
+    )]
+    pub skip_versions: Vec<String>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_ALLOW_VERSIONS",
+        value_name = "REGEX",
+        help = "Only install releases whose tag matches this regex; check reports any other latest release as blocked"
+    )]
+    pub allow_versions: Option<String>,
 }
 
-async fn download_and_verify_asset(
-    release: &github::Release,
-    asset_pattern: &Regex,
-    checksum_pattern: Option<&Regex>,
-    github_token: Option<&str>,
-    http_client: reqwest::Client,
-    skip_verification: bool,
-) -> anyhow::Result<(NamedUtf8TempFile, String)> {
-    let asset = github::select_asset(&release.assets, asset_pattern)
-        .ok_or_else(|| anyhow!("No asset matching pattern"))?;
-    info!("Selected asset: {}", asset.name);
+/// Rolling-update coordination between the hosts of a cluster.
+#[derive(Parser, Debug, Clone)]
+pub struct ClusterArgs {
+    #[arg(
+        long = "cluster",
+        env = "DISTRONOMICON_CLUSTER",
+        value_name = "DIR|URL",
+        help = "Coordinate updates across hosts through lease files in a shared directory or an HTTP lease endpoint, so only --cluster-slots hosts switch and restart at a time"
+    )]
+    pub backend: Option<String>,
 
-    let downloaded_file = {
-        let _span = info_span!("download", url = %asset.url).entered();
-        download::fetch()
-            .url(&asset.url)
-            .maybe_token(github_token)
-            .client(http_client.clone())
-            .await?
-    };
+    #[arg(
+        long = "cluster-slots",
+        env = "DISTRONOMICON_CLUSTER_SLOTS",
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "How many hosts of the cluster may update at once [default: 1]"
+    )]
+    pub slots: Option<u32>,
 
-    if !skip_verification && let Some(checksum_regex) = checksum_pattern {
-        let _span = info_span!("verify", asset = %asset.name).entered();
-        let checksum_asset = github::select_asset(&release.assets, checksum_regex)
-            .ok_or_else(|| anyhow!("No checksum asset matching pattern"))?;
-        verify::fetch_and_verify_checksum(
-            &asset.name,
-            &checksum_asset.url,
-            github_token,
-            http_client,
-            downloaded_file.path(),
-        )
-        .await?;
-        info!("Checksum verified");
-    }
+    #[arg(
+        long = "cluster-member",
+        env = "DISTRONOMICON_CLUSTER_MEMBER",
+        value_name = "NAME",
+        help = "This host's name in the cluster [default: hostname]"
+    )]
+    pub member: Option<String>,
 
-    Ok((downloaded_file, asset.name.clone()))
-}
+    #[arg(
+        long = "cluster-timeout",
+        value_name = "SECS",
+        default_value = "3600",
+        help = "Maximum seconds to wait for a cluster update slot (default: 3600)"
+    )]
+    pub timeout: u64,
 
-fn install_release(
-    install_root: &Utf8Path,
-    app: &str,
-    tag: &str,
-    downloaded_file: &NamedUtf8TempFile,
-    asset_name: &str,
-) -> anyhow::Result<()> {
-    let staging_dir = fsops::make_staging(install_root, app, tag)?;
+    #[arg(
+        long = "cluster-lease-ttl",
+        value_name = "SECS",
+        default_value = "1800",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Seconds after which an unreleased slot, e.g. of a host whose health check failed, is free again (default: 1800)"
+    )]
+    pub lease_ttl: u64,
+}
 
-    {
-        let _span = info_span!("extract", archive = %asset_name, dest = %staging_dir).entered();
-        let temp_with_ext = staging_dir.join(asset_name);
-        fs::copy(downloaded_file.path(), &temp_with_ext)?;
-        extract::unpack(&temp_with_ext, &staging_dir)?;
-        fs::remove_file(&temp_with_ext)?;
+impl ClusterArgs {
+    fn merge_config(&mut self, config: &AppConfig) {
+        self.backend = self.backend.take().or_else(|| config.cluster.clone());
+        self.slots = self.slots.or(config.cluster_slots);
     }
 
-    {
-        let _span = info_span!("fsync", dir = %staging_dir).entered();
-        fsops::fsync_directory_tree(&staging_dir)?;
-        info!("Staged content synced to disk");
+    /// The coordinator for `app`, when a cluster backend is configured.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the backend is not a valid directory or URL.
+    fn coordinator(&self, app: &str) -> anyhow::Result<Option<cluster::Coordinator>> {
+        let Some(backend) = self.backend.as_deref() else {
+            return Ok(None);
+        };
+        Ok(Some(cluster::Coordinator {
+            backend: cluster::Backend::parse(backend)?,
+            app: app.to_string(),
+            member: self.member.clone().unwrap_or_else(cluster::hostname),
+            slots: self.slots.unwrap_or(1),
+            lease_ttl: Duration::from_secs(self.lease_ttl),
+        }))
     }
+}
 
-    let releases_dir = install_root.join(app).join("releases");
-    fs::create_dir_all(&releases_dir)?;
-    File::open(&releases_dir)?.sync_all()?;
-    let installed_dir = fsops::atomic_move(&staging_dir, &releases_dir, tag)?;
+/// A webhook told the outcome of each update.
+#[derive(Parser, Debug, Clone)]
+pub struct NotifyArgs {
+    #[arg(
+        long = "notify-url",
+        env = "DISTRONOMICON_NOTIFY_URL",
+        value_name = "URL",
+        help = "POST the outcome of each update that installs, downloads, or fails to URL (the JSON summary, or --notify-template rendered)"
+    )]
+    pub url: Option<String>,
 
-    {
-        let _span = info_span!("switch", tag = %tag).entered();
-        let bin_dir = install_root.join(app).join("bin");
-        fs::create_dir_all(&bin_dir)?;
-        fsops::link_binaries(&installed_dir, &bin_dir)?;
-        info!("Symlinks updated");
-    }
+    #[arg(
+        long = "notify-template",
+        env = "DISTRONOMICON_NOTIFY_TEMPLATE",
+        value_parser = parse_path,
+        value_name = "PATH",
+        help = "Template for the notification body, with {{ name }} placeholders for summary fields and {{ name | tojson }} for JSON values"
+    )]
+    pub template: Option<Utf8PathBuf>,
 
-    Ok(())
+    #[arg(
+        long = "notify-content-type",
+        value_name = "TYPE",
+        default_value = notify::DEFAULT_CONTENT_TYPE,
+        help = "Content-Type of the notification body"
+    )]
+    pub content_type: String,
 }
 
-fn finalize_update(
-    releases_dir: &Utf8Path,
-    state_path: &Utf8Path,
-    tag: &str,
-    validators_out: &github::ValidatorsOut,
-    restart_cmd: Option<&str>,
-    retain: usize,
-) -> anyhow::Result<()> {
-    let mut restart_failed = false;
-    if let Some(cmd) = restart_cmd {
-        let _span = info_span!("restart", command = %cmd).entered();
-        match restart::execute(cmd) {
-            Ok(()) => {
-                info!("Restart command succeeded");
-            }
-            Err(e) => {
-                warn!("Restart command failed: {}", e);
-                restart_failed = true;
-            }
-        }
+impl NotifyArgs {
+    fn merge_config(&mut self, config: &AppConfig) {
+        self.url = self.url.take().or_else(|| config.notify_url.clone());
+        self.template = self
+            .template
+            .take()
+            .or_else(|| config.notify_template.clone());
     }
 
-    {
-        let _span = info_span!("prune", retain = %retain).entered();
-        let (deleted, failed) = fsops::prune_old_releases(releases_dir, tag, retain)?;
-        if !deleted.is_empty() {
-            info!("Pruned {} old release(s): {:?}", deleted.len(), deleted);
-        }
+    /// The notifier, when a URL is configured; its template is read up front so a
+    /// broken one fails the run before anything is installed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL is invalid or the template cannot be read or parsed.
+    fn notifier(&self) -> anyhow::Result<Option<notify::Notifier>> {
+        let Some(url) = self.url.as_deref() else {
+            return Ok(None);
+        };
+        let url = Url::parse(url).map_err(|e| anyhow!("invalid --notify-url {url}: {e}"))?;
+        let template = self
+            .template
+            .as_deref()
+            .map(|path| {
+                notify::Template::load(path)
+                    .map_err(|e| anyhow!("invalid notification template {path}: {e}"))
+            })
+            .transpose()?;
+        Ok(Some(notify::Notifier {
+            url,
+            template,
+            content_type: self.content_type.clone(),
+        }))
+    }
+}
+
+/// Where releases are published: GitHub releases, the Downloads section of a
+/// Bitbucket Cloud repository, whose files are grouped into releases by the
+/// version in their names, a self-hosted JSON feed (see [`json_feed::Feed`]), or
+/// the objects under a key prefix of an S3-compatible bucket, grouped the same way.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    #[default]
+    Github,
+    Bitbucket,
+    Json,
+    S3,
+}
+
+/// A layout `adopt` can convert into distronomicon's own.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LegacyLayout {
+    /// `<app>/current` links to the release in `<app>/<tag>` or `<app>/releases/<tag>`.
+    Current,
+    /// The release's files sit directly in `<app>/`.
+    Flat,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct CheckArgs {
+    #[arg(
+        long,
+        env = "DISTRONOMICON_REPO",
+        help = "GitHub repository in owner/repo format (e.g., 'rust-lang/rust')"
+    )]
+    pub repo: Option<String>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_PATTERN",
+        help = "Regex pattern matching the asset update would download, whose size is reported [default: the archive that best suits this host]"
+    )]
+    pub pattern: Option<String>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_ASSET_NAME",
+        value_name = "NAME",
+        conflicts_with = "pattern",
+        help = "Exact file name of the asset update would download, instead of a --pattern regex"
+    )]
+    pub asset_name: Option<String>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_LIBC",
+        value_name = "LIBC",
+        help = "C library whose build update would prefer when the release has both: gnu or musl [default: this host's]"
+    )]
+    pub libc: Option<pattern::Libc>,
+
+    #[arg(
+        long,
+        env = "STATE_DIRECTORY",
+        value_parser = parse_path,
+        help = "Directory for storing state.json with ETags and timestamps"
+    )]
+    pub state_directory: Utf8PathBuf,
+
+    #[arg(
+        long,
+        value_parser = parse_path,
+        value_name = "PATH",
+        help = "When an update is available, write a change record for it (JSON if PATH ends in .json, Markdown otherwise)"
+    )]
+    pub export_change_record: Option<Utf8PathBuf>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = OutputFormat::Text,
+        help = "Print the result as text or as a JSON document (logs go to stderr)"
+    )]
+    pub output: OutputFormat,
+
+    #[arg(
+        long,
+        value_name = "CODE",
+        default_value_t = EXIT_UPDATE_AVAILABLE,
+        help = "Exit status when an update is available (0 to always exit successfully)"
+    )]
+    pub exit_code_on_update: u8,
+
+    #[arg(
+        long,
+        help = "Also hash the installed release's files and compare them against the manifest the release publishes"
+    )]
+    pub deep: bool,
+
+    #[arg(
+        long,
+        value_name = "REGEX",
+        default_value = manifest::DEFAULT_PATTERN,
+        help = "Release assets listing per-file SHA-256 digests for --deep (JSON, sha256sum output, or one <file>.sha256 per file)"
+    )]
+    pub manifest_pattern: String,
+
+    #[arg(
+        long,
+        help = "When an update is available, also print its release notes as plain text, cut to the first 20 lines"
+    )]
+    pub show_notes: bool,
+
+    #[command(flatten)]
+    pub github: GitHubConfig,
+}
+
+/// Lines of release notes `check --show-notes` prints.
+const NOTES_MAX_LINES: usize = 20;
+
+/// Default exit status of `check` when a newer release than the installed one exists.
+pub const EXIT_UPDATE_AVAILABLE: u8 = 2;
+
+/// How `check` and `update` report their result.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct UpdateArgs {
+    #[arg(
+        long,
+        env = "DISTRONOMICON_REPO",
+        help = "GitHub repository in owner/repo format (e.g., 'rust-lang/rust')"
+    )]
+    pub repo: Option<String>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_PATTERN",
+        help = "Regex pattern to match release asset filename (e.g., '.*\\.tar\\.gz$'); {os}, {arch}, {libc}, and {version} expand for this host [default: the archive that best suits this host]"
+    )]
+    pub pattern: Option<String>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_ASSET_NAME",
+        value_name = "NAME",
+        conflicts_with = "pattern",
+        help = "Exact file name of the release asset to download (e.g., 'myapp-linux-amd64.tar.gz'), instead of a --pattern regex"
+    )]
+    pub asset_name: Option<String>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_LIBC",
+        value_name = "LIBC",
+        help = "C library whose build to install when the release has both, such as -gnu and -musl archives matching --pattern: gnu or musl [default: musl if this host's /lib has its loader, otherwise gnu]"
+    )]
+    pub libc: Option<pattern::Libc>,
+
+    #[arg(
+        long,
+        env = "STATE_DIRECTORY",
+        value_parser = parse_path,
+        help = "Directory for storing state.json with ETags and timestamps"
+    )]
+    pub state_directory: Utf8PathBuf,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_CHECKSUM_PATTERN",
+        help = "Regex pattern to match checksum file (e.g., 'SHA256SUMS'); required unless --skip-verification"
+    )]
+    pub checksum_pattern: Option<String>,
+
+    #[command(flatten)]
+    pub github: GitHubConfig,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_RESTART_COMMAND",
+        help = "Shell command to execute after successful update (e.g., 'systemctl restart myapp')"
+    )]
+    pub restart_command: Option<String>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_HEALTH_COMMAND",
+        help = "Shell command that must succeed after the restart (e.g., 'curl -fsS http://localhost:8080/healthz'); retried until --health-timeout"
+    )]
+    pub health_command: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value = "60",
+        help = "Maximum seconds to wait for --health-command to succeed (default: 60)"
+    )]
+    pub health_timeout: u64,
+
+    #[command(flatten)]
+    pub cluster: ClusterArgs,
+
+    #[command(flatten)]
+    pub notify: NotifyArgs,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_SCAN_COMMAND",
+        help = "Shell command that must accept the downloaded asset before extraction (path in $1 and $DISTRONOMICON_ARTIFACT)"
+    )]
+    pub scan_command: Option<String>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_RETAIN",
+        help = "Number of old releases to keep after update (older releases are pruned) [default: 3]"
+    )]
+    pub retain: Option<u32>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_RETAIN_MAX_SIZE",
+        value_name = "SIZE",
+        help = "Also prune the oldest releases until releases/ fits in SIZE, such as 500MB or 20GiB (the active release is always kept)"
+    )]
+    pub retain_max_size: Option<fsops::ByteSize>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_TAG",
+        value_name = "TAG",
+        help = "Install this release tag instead of the latest (skips the conditional up-to-date check)"
+    )]
+    pub tag: Option<String>,
+
+    #[arg(
+        long,
+        help = "Skip checksum verification (not recommended; use only for testing)"
+    )]
+    pub skip_verification: bool,
+
+    #[arg(
+        long,
+        help = "Forcibly remove lock file before starting update (use with caution)"
+    )]
+    pub force_unlock: bool,
+
+    #[arg(
+        long,
+        help = "Replace releases/<tag> if it already exists (e.g., left behind by an interrupted run)"
+    )]
+    pub overwrite_existing_release: bool,
+
+    #[arg(
+        long,
+        help = "Allow updating from a different repo or GitHub host than the one recorded in state.json"
+    )]
+    pub accept_new_source: bool,
+
+    #[arg(
+        long,
+        help = "Install the release even if its tag is an older semver version than the installed one (e.g., after the newest release was yanked)"
+    )]
+    pub allow_downgrade: bool,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_TRUST_POLICY",
+        value_name = "POLICY",
+        help = "What to do when a release's uploader, download host, or checksum file differs from the one trusted at first install: warn, fail, or off [default: warn]"
+    )]
+    pub trust_policy: Option<trust::Policy>,
+
+    #[arg(
+        long = "mirror",
+        env = "DISTRONOMICON_MIRRORS",
+        value_name = "URL",
+        value_delimiter = ',',
+        help = "Base URL to download the asset from before the source, as URL/<asset name> ({tag} in URL is the release tag); repeatable, tried in order"
+    )]
+    pub mirrors: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Trust a changed publisher and record it for later updates"
+    )]
+    pub accept_new_publisher: bool,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_ON_MISSING_BINARY",
+        value_name = "POLICY",
+        help = "What to do when the new release lacks a command the previous one linked: keep (leave the old link and its release), remove, or fail [default: remove]"
+    )]
+    pub on_missing_binary: Option<fsops::MissingBinary>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_LINK_FILTER",
+        value_name = "FILTER",
+        help = "Which executable files in a release to link from bin/: all, runnable (starting with #! or an ELF header, skipping documentation marked executable), or elf (ELF binaries only) [default: all]"
+    )]
+    pub link_filter: Option<fsops::LinkFilter>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_MAX_LINK_DEPTH",
+        value_name = "DEPTH",
+        help = "Move executables nested more than DEPTH directories deep in a release into libexec/ before linking, so bin/ links stay short (a symlink is left at the old path)"
+    )]
+    pub max_link_depth: Option<u32>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_ZSTD_THREADS",
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Threads decoding a .tar.zst release made of several frames (as pzstd writes) [default: available cores, at most 4]"
+    )]
+    pub zstd_threads: Option<u32>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_EXTRACT_SANDBOX",
+        value_name = "MODE",
+        help = "Confine extraction with Landlock to reading the archive and writing the staging directory: auto (when the kernel supports it), require (fail otherwise), or off [default: auto]"
+    )]
+    pub extract_sandbox: Option<extract::Sandbox>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_NETWORK_USER",
+        value_name = "USER",
+        help = "When run as root, make GitHub API requests and downloads in a helper process running as this user (a name, uid, or uid:gid), keeping root for installing"
+    )]
+    pub network_user: Option<String>,
+
+    #[arg(
+        long = "required-bin",
+        env = "DISTRONOMICON_REQUIRED_BINS",
+        value_name = "NAME",
+        value_delimiter = ',',
+        help = "Command the release must link from bin/; if it is missing after activation, the previous release is restored before any restart (repeatable)"
+    )]
+    pub required_bins: Vec<String>,
+
+    /// Further assets to extract into subdirectories of the release; only set from
+    /// `[[extra_assets]]` in the config file.
+    #[arg(skip)]
+    pub extra_assets: Vec<config::ExtraAsset>,
+
+    /// Limits for trimming the state directory after an install; only set from
+    /// `[maintenance]` in the config file.
+    #[arg(skip)]
+    pub maintenance: config::MaintenanceSettings,
+
+    #[arg(
+        long,
+        help = "Show what would be downloaded, installed, pruned, and restarted without changing anything"
+    )]
+    pub dry_run: bool,
+
+    #[arg(
+        long,
+        value_parser = parse_path,
+        value_name = "DIR",
+        conflicts_with = "dry_run",
+        help = "Download and verify the asset into DIR without installing, restarting, or updating state"
+    )]
+    pub download_only: Option<Utf8PathBuf>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["dry_run", "download_only", "output", "events"],
+        help = "Print what the update would do as a JSON plan (steps, assets, and expected digests) without changing anything"
+    )]
+    pub plan: bool,
+
+    #[arg(
+        long,
+        value_parser = parse_path,
+        value_name = "PATH",
+        conflicts_with_all = ["dry_run", "download_only", "output", "events"],
+        help = "Like --plan, but write the plan to PATH for review and --apply-plan"
+    )]
+    pub plan_file: Option<Utf8PathBuf>,
+
+    #[arg(
+        long,
+        value_parser = parse_path,
+        value_name = "PATH",
+        conflicts_with_all = ["dry_run", "download_only", "plan", "plan_file", "tag"],
+        help = "Install exactly the release and assets of a plan written by --plan-file, failing if the installed release, an asset, its digest, or the restart or health command differs from the plan"
+    )]
+    pub apply_plan: Option<Utf8PathBuf>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = OutputFormat::Text,
+        conflicts_with = "dry_run",
+        help = "Print the result as text or as a JSON summary with phase durations (logs go to stderr)"
+    )]
+    pub output: OutputFormat,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        conflicts_with_all = ["dry_run", "output"],
+        help = "Stream a JSON line to stdout as each phase starts and ends, then the summary (logs go to stderr)"
+    )]
+    pub events: Option<EventFormat>,
+
+    #[arg(
+        long,
+        help = "Install a release even if its archive contains no executable files (removes all bin/ commands)"
+    )]
+    pub allow_no_executables: bool,
+
+    #[arg(
+        long,
+        value_parser = parse_path,
+        value_name = "PATH",
+        help = "Write the added, removed, and changed bin/ commands for this update to PATH as JSON"
+    )]
+    pub bin_diff_output: Option<Utf8PathBuf>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_LICENSE_DIR",
+        value_parser = parse_path,
+        value_name = "DIR",
+        help = "Copy LICENSE, NOTICE, and COPYING files of each installed release to DIR/<app>/<tag>/ for license audits"
+    )]
+    pub license_dir: Option<Utf8PathBuf>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_STAGING_MAX_AGE",
+        value_name = "SECS",
+        help = "Before updating, remove staging directories older than this many seconds"
+    )]
+    pub staging_max_age: Option<u64>,
+
+    #[arg(
+        long,
+        default_value = "30",
+        help = "Maximum seconds to wait for lock acquisition (default: 30)"
+    )]
+    pub lock_timeout: u64,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct HistoryArgs {
+    #[arg(
+        long,
+        env = "STATE_DIRECTORY",
+        value_parser = parse_path,
+        help = "Directory containing <app>/history.jsonl"
+    )]
+    pub state_directory: Utf8PathBuf,
+
+    #[arg(long, help = "Show only the N most recent installs")]
+    pub limit: Option<usize>,
+
+    #[arg(long, help = "Print the entries as a JSON array (logs go to stderr)")]
+    pub json: bool,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct PinArgs {
+    #[arg(
+        long,
+        env = "STATE_DIRECTORY",
+        value_parser = parse_path,
+        help = "Directory in which to write the pin marker (<dir>/<app>/pin.json)"
+    )]
+    pub state_directory: Utf8PathBuf,
+
+    #[arg(
+        long,
+        help = "Why the app is pinned (shown when update refuses to run)"
+    )]
+    pub reason: Option<String>,
+
+    #[arg(
+        long,
+        default_value = "30",
+        help = "Maximum seconds to wait for lock acquisition (default: 30)"
+    )]
+    pub lock_timeout: u64,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct UnpinArgs {
+    #[arg(
+        long,
+        env = "STATE_DIRECTORY",
+        value_parser = parse_path,
+        help = "Directory containing the pin marker"
+    )]
+    pub state_directory: Utf8PathBuf,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct LoginArgs {
+    #[arg(
+        long,
+        env = "STATE_DIRECTORY",
+        value_parser = parse_path,
+        help = "Directory in which to store the token (<dir>/github-tokens/<host>, mode 0600)"
+    )]
+    pub state_directory: Utf8PathBuf,
+
+    #[arg(
+        long = "github-host",
+        env = "GITHUB_HOST",
+        help = "GitHub API hostname (use for GitHub Enterprise) [default: https://api.github.com]"
+    )]
+    pub host: Option<String>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_GITHUB_CLIENT_ID",
+        help = "Client ID of the GitHub OAuth app to authorize (device flow must be enabled for it)"
+    )]
+    pub client_id: String,
+
+    #[arg(
+        long,
+        default_value = "repo",
+        help = "Space-separated OAuth scopes to request (repo is needed for private repositories)"
+    )]
+    pub scope: String,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct UnlockArgs {
+    #[arg(
+        long,
+        env = "STATE_DIRECTORY",
+        value_parser = parse_path,
+        help = "Directory containing the lock file"
+    )]
+    pub state_directory: Utf8PathBuf,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct UninstallArgs {
+    #[arg(
+        long,
+        env = "STATE_DIRECTORY",
+        value_parser = parse_path,
+        help = "Directory for storing state.json with ETags and timestamps"
+    )]
+    pub state_directory: Utf8PathBuf,
+
+    #[arg(
+        long,
+        help = "Keep state.json (e.g., to reinstall later without a full download)"
+    )]
+    pub keep_state: bool,
+
+    #[arg(long, help = "Keep release directories under <root>/<app>/releases")]
+    pub keep_releases: bool,
+
+    #[arg(long, help = "Print what would be removed without removing anything")]
+    pub dry_run: bool,
+
+    #[arg(
+        long,
+        default_value = "30",
+        help = "Maximum seconds to wait for lock acquisition (default: 30)"
+    )]
+    pub lock_timeout: u64,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct AdoptArgs {
+    #[arg(
+        long,
+        env = "DISTRONOMICON_REPO",
+        help = "Repository the app is released from in owner/repo format, recorded as its source"
+    )]
+    pub repo: Option<String>,
+
+    #[arg(
+        long,
+        env = "STATE_DIRECTORY",
+        value_parser = parse_path,
+        help = "Directory for storing state.json with ETags and timestamps"
+    )]
+    pub state_directory: Utf8PathBuf,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "LAYOUT",
+        help = "First restructure an install in another layout into releases/<tag> and bin/: current for a 'current' symlink to a release directory, flat for an app installed directly into its directory"
+    )]
+    pub from_layout: Option<LegacyLayout>,
+
+    #[arg(
+        long,
+        required_if_eq("from_layout", "flat"),
+        help = "With --from-layout flat, the tag of the installed release"
+    )]
+    pub tag: Option<String>,
+
+    #[arg(long, help = "Print what would be recorded without writing anything")]
+    pub dry_run: bool,
+
+    #[arg(
+        long,
+        default_value = "30",
+        help = "Maximum seconds to wait for lock acquisition (default: 30)"
+    )]
+    pub lock_timeout: u64,
+
+    #[command(flatten)]
+    pub github: GitHubConfig,
+}
+
+impl AdoptArgs {
+    /// The repository to record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no repository was given on the command line or in the config file.
+    pub fn repo(&self) -> anyhow::Result<&str> {
+        required(self.repo.as_deref(), "--repo", "repo")
+    }
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct UpdateAllArgs {
+    #[arg(
+        long,
+        env = "STATE_DIRECTORY",
+        value_parser = parse_path,
+        help = "Directory for storing per-app state.json files and locks"
+    )]
+    pub state_directory: Utf8PathBuf,
+
+    #[command(flatten)]
+    pub github: GitHubConfig,
+
+    #[arg(
+        long,
+        help = "Skip checksum verification for apps without a checksum_pattern (not recommended)"
+    )]
+    pub skip_verification: bool,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_LICENSE_DIR",
+        value_parser = parse_path,
+        value_name = "DIR",
+        help = "Copy LICENSE, NOTICE, and COPYING files of each installed release to DIR/<app>/<tag>/ for license audits"
+    )]
+    pub license_dir: Option<Utf8PathBuf>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_TRUST_POLICY",
+        value_name = "POLICY",
+        help = "What to do when a release's uploader, download host, or checksum file differs from the one trusted at first install: warn, fail, or off [default: warn]"
+    )]
+    pub trust_policy: Option<trust::Policy>,
+
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value = "60",
+        help = "Maximum seconds to wait for each app's health_command to succeed (default: 60)"
+    )]
+    pub health_timeout: u64,
+
+    #[command(flatten)]
+    pub cluster: ClusterArgs,
+
+    #[command(flatten)]
+    pub notify: NotifyArgs,
+
+    #[arg(
+        long,
+        default_value = "30",
+        help = "Maximum seconds to wait for each app's lock (default: 30)"
+    )]
+    pub lock_timeout: u64,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct RollbackArgs {
+    #[arg(
+        long,
+        env = "STATE_DIRECTORY",
+        value_parser = parse_path,
+        help = "Directory for storing state.json with ETags and timestamps"
+    )]
+    pub state_directory: Utf8PathBuf,
+
+    #[arg(
+        long,
+        add = ArgValueCandidates::new(completion::tag_candidates),
+        help = "Release tag to roll back to (default: the previously installed release)"
+    )]
+    pub to: Option<String>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_RESTART_COMMAND",
+        help = "Shell command to execute after switching releases (e.g., 'systemctl restart myapp')"
+    )]
+    pub restart_command: Option<String>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_LINK_FILTER",
+        value_name = "FILTER",
+        help = "Which executable files in the release to link from bin/: all, runnable, or elf [default: all]"
+    )]
+    pub link_filter: Option<fsops::LinkFilter>,
+
+    #[arg(
+        long,
+        default_value = "30",
+        help = "Maximum seconds to wait for lock acquisition (default: 30)"
+    )]
+    pub lock_timeout: u64,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ListArgs {
+    #[arg(
+        long,
+        env = "STATE_DIRECTORY",
+        value_parser = parse_path,
+        help = "Directory containing state.json (used for the active release's install time)"
+    )]
+    pub state_directory: Option<Utf8PathBuf>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct SelfUpdateArgs {
+    #[arg(
+        long,
+        default_value = self_update::DEFAULT_REPO,
+        help = "GitHub repository distronomicon's own releases are published to"
+    )]
+    pub repo: String,
+
+    #[arg(long, help = "Install this release tag instead of the latest")]
+    pub tag: Option<String>,
+
+    #[arg(
+        long,
+        value_parser = parse_path,
+        value_name = "PATH",
+        help = "Executable to replace [default: the running binary]"
+    )]
+    pub path: Option<Utf8PathBuf>,
+
+    #[arg(
+        long,
+        help = "Skip checksum verification (not recommended; use only for testing)"
+    )]
+    pub skip_verification: bool,
+
+    #[command(flatten)]
+    pub github: GitHubConfig,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct CompletionsArgs {
+    #[arg(value_enum, help = "Shell to generate completions for")]
+    pub shell: Shell,
+
+    #[arg(
+        long,
+        help = "Print a script that asks distronomicon for candidates at completion time, including app names and retained tags"
+    )]
+    pub dynamic: bool,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ListRemoteArgs {
+    #[arg(
+        long,
+        env = "DISTRONOMICON_REPO",
+        help = "GitHub repository in owner/repo format (e.g., 'rust-lang/rust')"
+    )]
+    pub repo: Option<String>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_PATTERN",
+        help = "Regex pattern to match release asset filename (shows matching assets per release)"
+    )]
+    pub pattern: Option<String>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_ASSET_NAME",
+        value_name = "NAME",
+        conflicts_with = "pattern",
+        help = "Exact file name of the release asset to show per release, instead of a --pattern regex"
+    )]
+    pub asset_name: Option<String>,
+
+    #[arg(
+        long,
+        default_value = "30",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Maximum number of releases to show"
+    )]
+    pub limit: u64,
+
+    #[command(flatten)]
+    pub github: GitHubConfig,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct StatusArgs {
+    #[arg(
+        long,
+        env = "DISTRONOMICON_REPO",
+        help = "GitHub repository in owner/repo format (e.g., 'rust-lang/rust'); the update check is skipped without it"
+    )]
+    pub repo: Option<String>,
+
+    #[arg(
+        long,
+        env = "STATE_DIRECTORY",
+        value_parser = parse_path,
+        help = "Directory containing state.json and the lock file"
+    )]
+    pub state_directory: Utf8PathBuf,
+
+    #[arg(long, help = "Only report local state; do not query GitHub")]
+    pub offline: bool,
+
+    #[command(flatten)]
+    pub github: GitHubConfig,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct DoctorArgs {
+    #[arg(
+        long,
+        env = "DISTRONOMICON_REPO",
+        help = "GitHub repository in owner/repo format (e.g., 'rust-lang/rust'); only API reachability is checked without it"
+    )]
+    pub repo: Option<String>,
+
+    #[arg(
+        long,
+        env = "STATE_DIRECTORY",
+        value_parser = parse_path,
+        help = "Directory for storing state.json with ETags and timestamps"
+    )]
+    pub state_directory: Utf8PathBuf,
+
+    #[arg(
+        long,
+        default_value = "100",
+        help = "Minimum free space in MiB required on the install root's filesystem"
+    )]
+    pub min_free_space: u64,
+
+    #[arg(
+        long,
+        default_value = "10000",
+        help = "Minimum free inodes required on the install root's filesystem, for releases of many small files (ignored where the filesystem has no inode limit)"
+    )]
+    pub min_free_inodes: u64,
+
+    #[command(flatten)]
+    pub github: GitHubConfig,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct GcArgs {
+    #[arg(
+        long,
+        env = "STATE_DIRECTORY",
+        value_parser = parse_path,
+        help = "Directory containing the lock file"
+    )]
+    pub state_directory: Utf8PathBuf,
+
+    #[arg(
+        long,
+        default_value = "86400",
+        value_name = "SECS",
+        help = "Only remove staging directories older than this many seconds"
+    )]
+    pub max_age: u64,
+
+    #[arg(long, help = "Print what would be removed without removing anything")]
+    pub dry_run: bool,
+
+    #[arg(
+        long,
+        default_value = "30",
+        help = "Maximum seconds to wait for lock acquisition (default: 30)"
+    )]
+    pub lock_timeout: u64,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct PruneArgs {
+    #[arg(
+        long,
+        env = "STATE_DIRECTORY",
+        value_parser = parse_path,
+        help = "Directory containing the lock file"
+    )]
+    pub state_directory: Utf8PathBuf,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_RETAIN",
+        help = "Number of releases to keep (the active release is always kept) [default: 3]"
+    )]
+    pub retain: Option<u32>,
+
+    #[arg(
+        long,
+        env = "DISTRONOMICON_RETAIN_MAX_SIZE",
+        value_name = "SIZE",
+        help = "Also delete the oldest releases until releases/ fits in SIZE, such as 500MB or 20GiB (the active and pinned releases are always kept)"
+    )]
+    pub retain_max_size: Option<fsops::ByteSize>,
+
+    #[arg(long, help = "Print what would be deleted without deleting anything")]
+    pub dry_run: bool,
+
+    #[arg(long, help = "Print the result as JSON (logs go to stderr)")]
+    pub json: bool,
+
+    #[arg(
+        long,
+        default_value = "30",
+        help = "Maximum seconds to wait for lock acquisition (default: 30)"
+    )]
+    pub lock_timeout: u64,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct MaintenanceArgs {
+    #[arg(
+        long,
+        env = "STATE_DIRECTORY",
+        value_parser = parse_path,
+        help = "Directory containing the lock file"
+    )]
+    pub state_directory: Utf8PathBuf,
+
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u32).range(1..),
+        value_name = "N",
+        help = "Keep the newest N install history entries [default: 1000]"
+    )]
+    pub history_keep: Option<u32>,
+
+    #[arg(
+        long,
+        value_name = "DAYS",
+        help = "Drop install history entries older than DAYS (the newest entry is always kept)"
+    )]
+    pub history_max_days: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "DAYS",
+        help = "Delete saved debug responses older than DAYS [default: 14]"
+    )]
+    pub debug_max_days: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "SIZE",
+        help = "Delete the oldest saved debug responses until they fit in SIZE, such as 16MiB [default: 16MiB]"
+    )]
+    pub debug_max_size: Option<fsops::ByteSize>,
+
+    #[arg(long, help = "Print what would be removed without removing anything")]
+    pub dry_run: bool,
+
+    #[arg(long, help = "Print the result as JSON (logs go to stderr)")]
+    pub json: bool,
+
+    #[arg(
+        long,
+        default_value = "30",
+        help = "Maximum seconds to wait for lock acquisition (default: 30)"
+    )]
+    pub lock_timeout: u64,
+}
+
+impl MaintenanceArgs {
+    /// The limits given on the command line, as config settings.
+    #[must_use]
+    pub fn settings(&self) -> config::MaintenanceSettings {
+        config::MaintenanceSettings {
+            history_keep: self.history_keep,
+            history_max_days: self.history_max_days,
+            debug_max_days: self.debug_max_days,
+            debug_max_size: self.debug_max_size,
+        }
+    }
+
+    fn merge_config(&mut self, config: &AppConfig) {
+        let settings = self.settings().or(config.maintenance);
+        self.history_keep = settings.history_keep;
+        self.history_max_days = settings.history_max_days;
+        self.debug_max_days = settings.debug_max_days;
+        self.debug_max_size = settings.debug_max_size;
+    }
+}
+
+impl ListRemoteArgs {
+    fn merge_config(&mut self, config: &AppConfig) {
+        self.repo = self.repo.take().or_else(|| config.repo.clone());
+        merge_asset_selector(&mut self.pattern, &mut self.asset_name, config);
+        self.github.merge_config(config);
+    }
+
+    /// The repository to list releases for.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no repository was given on the command line or in the config file.
+    pub fn repo(&self) -> anyhow::Result<&str> {
+        required(self.repo.as_deref(), "--repo", "repo")
+    }
+}
+
+fn required<'a>(value: Option<&'a str>, flag: &str, key: &str) -> anyhow::Result<&'a str> {
+    value.ok_or_else(|| {
+        anyhow!(
+            "{flag} is required (pass it on the command line or set `{key}` in the config file)"
+        )
+    })
+}
+
+impl Args {
+    /// Returns warnings for flags that are set but have no effect in this invocation.
+    ///
+    /// Called before the config file is merged so that only values given on the
+    /// command line or through environment variables are reported. Flag values that
+    /// can never be valid are rejected by clap while parsing instead.
+    #[must_use]
+    pub fn flag_warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let command = self.command.name();
+
+        let (uses_http, uses_downloads) = match &self.command {
+            Commands::Check(_)
+            | Commands::ListRemote(_)
+            | Commands::Status(_)
+            | Commands::Doctor(_)
+            | Commands::Login(_) => (true, false),
+            Commands::Update(_) | Commands::UpdateAll(_) | Commands::SelfUpdate(_) => (true, true),
+            _ => (false, false),
+        };
+
+        if !uses_http {
+            if self.http_timeout.is_some() {
+                warnings.push(format!("--http-timeout has no effect on {command}"));
+            }
+            if !self.spki_pins.is_empty() {
+                warnings.push(format!("--spki-pin has no effect on {command}"));
+            }
+            if self.proxy_mode.is_some() {
+                warnings.push(format!("--proxy-mode has no effect on {command}"));
+            }
+        }
+        if !uses_downloads {
+            if self.http_retries.is_some() {
+                warnings.push(format!("--http-retries has no effect on {command}"));
+            }
+            if self.stall_timeout.is_some() {
+                warnings.push(format!("--stall-timeout has no effect on {command}"));
+            }
+        }
+
+        match &self.command {
+            Commands::Update(update_args)
+                if update_args.skip_verification && update_args.checksum_pattern.is_some() =>
+            {
+                warnings.push(
+                    "--checksum-pattern is ignored because --skip-verification is set".to_string(),
+                );
+            }
+            Commands::Update(update_args)
+                if update_args.tag.is_some() && update_args.github.channel.is_some() =>
+            {
+                warnings.push("--channel is ignored because --tag is set".to_string());
+            }
+            Commands::Update(update_args)
+                if update_args.tag.is_some() && update_args.github.allow_prerelease =>
+            {
+                warnings.push("--allow-prerelease is ignored because --tag is set".to_string());
+            }
+            Commands::Update(update_args)
+                if update_args.download_only.is_some()
+                    && (update_args.restart_command.is_some()
+                        || update_args.bin_diff_output.is_some()) =>
+            {
+                warnings.push(
+                    "--restart-command and --bin-diff-output are ignored because --download-only is set"
+                        .to_string(),
+                );
+            }
+            Commands::Update(update_args) if update_args.dry_run && update_args.force_unlock => {
+                warnings.push("--force-unlock is ignored because --dry-run is set".to_string());
+            }
+            Commands::UpdateAll(_) if self.app.is_some() => {
+                warnings.push(
+                    "--app is ignored by update-all; apps are read from the config file"
+                        .to_string(),
+                );
+            }
+            _ => {}
+        }
+
+        warnings
+    }
+
+    /// Merges the app's TOML config file into the parsed arguments.
+    ///
+    /// Reads `--config` if given, otherwise `/etc/distronomicon/<app>.toml` when it
+    /// exists. Values already set on the command line or through environment
+    /// variables win over the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - An explicit `--config` file does not exist
+    /// - The config file cannot be read or parsed
+    /// - `--profile` names a profile the config file does not define
+    pub fn merge_config(&mut self) -> anyhow::Result<()> {
+        if matches!(self.command, Commands::UpdateAll(_)) {
+            return Ok(());
+        }
+
+        let path = match (&self.config, self.app.as_deref()) {
+            (Some(path), _) => path.clone(),
+            (None, Some(app)) => config::default_path(app),
+            (None, None) => return Ok(()),
+        };
+        let path = self.in_sysroot(&path);
+        let loaded = config::load(&path)?;
+
+        if self.config.is_some() {
+            ensure!(loaded.is_some(), "config file not found: {path}");
+        }
+
+        let loaded = match (loaded, self.profile.as_deref()) {
+            (Some(app_config), Some(profile)) => Some(
+                app_config
+                    .with_profile(profile)
+                    .ok_or_else(|| anyhow!("profile {profile:?} is not defined in {path}"))?,
+            ),
+            (None, Some(profile)) => {
+                bail!("--profile {profile} needs a config file, but {path} does not exist")
+            }
+            (loaded, None) => loaded,
+        };
+
+        if let Some(app_config) = loaded {
+            match self.profile.as_deref() {
+                Some(profile) => info!("Loaded config from {path} with profile {profile}"),
+                None => info!("Loaded config from {path}"),
+            }
+            self.apply_config(&app_config);
+        }
+
+        Ok(())
+    }
+
+    fn apply_config(&mut self, config: &AppConfig) {
+        self.install_root = self
+            .install_root
+            .take()
+            .or_else(|| config.install_root.clone());
+        self.http_timeout = self.http_timeout.or(config.http_timeout);
+        self.http_retries = self.http_retries.or(config.http_retries);
+        self.stall_timeout = self.stall_timeout.or(config.stall_timeout);
+        self.state_backend = self.state_backend.or(config.state_backend);
+        self.proxy_mode = self.proxy_mode.or(config.proxy_mode);
+        if self.spki_pins.is_empty() {
+            self.spki_pins.clone_from(&config.spki_pins);
+        }
+        self.command.merge_config(config);
+    }
+
+    /// Reports directories the command will write to whose owner does not match the
+    /// effective user.
+    ///
+    /// Running as root in a tree owned by another user leaves root-owned files that
+    /// break later runs as that user, and running as an unprivileged user in a tree
+    /// it cannot write fails with `EACCES` partway through the pipeline. Checked
+    /// after the config file is merged; read-only commands are not checked.
+    #[must_use]
+    pub fn ownership_warnings(&self) -> Vec<String> {
+        let state_directory = match &self.command {
+            Commands::Check(args) => &args.state_directory,
+            Commands::Update(args) => &args.state_directory,
+            Commands::UpdateAll(args) => &args.state_directory,
+            Commands::Rollback(args) => &args.state_directory,
+            Commands::Uninstall(args) => &args.state_directory,
+            Commands::Adopt(args) => &args.state_directory,
+            Commands::Pin(args) => &args.state_directory,
+            Commands::Unpin(args) => &args.state_directory,
+            Commands::Login(args) => &args.state_directory,
+            Commands::Unlock(args) => &args.state_directory,
+            Commands::Gc(args) => &args.state_directory,
+            Commands::Prune(args) => &args.state_directory,
+            Commands::Maintenance(args) => &args.state_directory,
+            _ => return Vec::new(),
+        };
+        let app_dir = self.app.as_deref().map(|app| self.install_root().join(app));
+        let bin_dir = app_dir.as_ref().map(|dir| dir.join("bin"));
+
+        let mut dirs = vec![
+            ("install root", self.install_root()),
+            ("state directory", state_directory.as_path()),
+        ];
+        dirs.extend(bin_dir.as_deref().map(|dir| ("bin directory", dir)));
+        ownership_warnings(&dirs)
+    }
+
+    /// Reads the GitHub token from its file, command, or `login`, if the subcommand
+    /// fetches releases. Called after the config file is merged, since it may name
+    /// them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token file or command does not yield a token.
+    pub fn resolve_token(&mut self) -> anyhow::Result<()> {
+        match self.command.github_mut() {
+            Some((github, state_directory)) => github.resolve_token(state_directory),
+            None => Ok(()),
+        }
+    }
+
+    /// Prints a status line on stdout unless `--quiet` was given.
+    pub fn say(&self, message: impl std::fmt::Display) {
+        if !self.quiet {
+            println!("{message}");
+        }
+    }
+
+    /// Whether stdout is reserved for machine-readable output, so logs must go to stderr.
+    #[must_use]
+    pub fn machine_output(&self) -> bool {
+        match &self.command {
+            Commands::Check(check_args) => check_args.output == OutputFormat::Json,
+            Commands::Update(update_args) => {
+                update_args.output == OutputFormat::Json || update_args.events.is_some()
+            }
+            Commands::Prune(prune_args) => prune_args.json,
+            Commands::Maintenance(maintenance_args) => maintenance_args.json,
+            Commands::History(history_args) => history_args.json,
+            Commands::Completions(_) | Commands::NetworkHelper => true,
+            _ => false,
+        }
+    }
+
+    /// The state store under `state_directory` for the selected backend.
+    #[must_use]
+    pub fn state_store(&self, state_directory: &Utf8Path) -> state::Store {
+        state::Store::new(self.state_backend.unwrap_or_default(), state_directory)
+    }
+
+    /// The HTTP request timeout, falling back to 300 seconds.
+    #[must_use]
+    pub fn http_timeout(&self) -> Duration {
+        self.http_timeout
+            .map_or(DEFAULT_TIMEOUT, Duration::from_secs)
+    }
+
+    /// Where HTTP clients take their proxy settings from, falling back to the
+    /// environment.
+    #[must_use]
+    pub fn proxy_mode(&self) -> proxy::Mode {
+        self.proxy_mode.unwrap_or_default()
+    }
+
+    fn http_settings(&self) -> HttpSettings {
+        HttpSettings {
+            timeout: self.http_timeout(),
+            spki_pins: self.spki_pins.clone(),
+            proxy_mode: self.proxy_mode(),
+        }
+    }
+
+    fn transfer_limits(&self, github: &GitHubConfig) -> TransferLimits {
+        TransferLimits {
+            max_retries: self.http_retries.unwrap_or(download::MAX_RETRIES),
+            stall_timeout: self.stall_timeout.map(Duration::from_secs),
+            rate_limit_wait: github.rate_limit_wait(),
+        }
+    }
+
+    /// The app being managed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `--app` was not given.
+    pub fn app(&self) -> anyhow::Result<&str> {
+        self.app
+            .as_deref()
+            .ok_or_else(|| anyhow!("--app is required for this command"))
+    }
+
+    /// The installation root, falling back to `/opt`.
+    #[must_use]
+    pub fn install_root(&self) -> &Utf8Path {
+        self.install_root
+            .as_deref()
+            .unwrap_or(Utf8Path::new(DEFAULT_INSTALL_ROOT))
+    }
+
+    /// `path` inside `--sysroot`, or unchanged without one.
+    #[must_use]
+    pub fn in_sysroot(&self, path: &Utf8Path) -> Utf8PathBuf {
+        match &self.sysroot {
+            Some(sysroot) => in_sysroot(sysroot, path),
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// Moves the install root and the subcommand's state and license directories
+    /// inside `--sysroot`, returning warnings for the settings it turns off.
+    ///
+    /// Called after the config file is merged, since it may name them; `update-all`
+    /// does the same for each app as it merges that app's settings.
+    pub fn apply_sysroot(&mut self) -> Vec<String> {
+        let Some(sysroot) = self.sysroot.clone() else {
+            return Vec::new();
+        };
+        if matches!(self.command, Commands::UpdateAll(_)) {
+            return Vec::new();
+        }
+        self.install_root = Some(in_sysroot(&sysroot, self.install_root()));
+        self.command.apply_sysroot(&sysroot)
+    }
+}
+
+fn sysroot_skips(flag: &str) -> String {
+    format!("{flag} is skipped with --sysroot because it would run on this host, not in the target")
+}
+
+/// Takes `pattern` and `asset_name` from the config file as a pair, unless either
+/// was given on the command line.
+fn merge_asset_selector(
+    pattern: &mut Option<String>,
+    asset_name: &mut Option<String>,
+    config: &AppConfig,
+) {
+    if pattern.is_none() && asset_name.is_none() {
+        pattern.clone_from(&config.pattern);
+        asset_name.clone_from(&config.asset_name);
+    }
+}
+
+/// A regex matching exactly the asset file name `name`.
+fn exact_name_pattern(name: &str) -> String {
+    format!("^{}$", regex::escape(name))
+}
+
+/// The asset pattern given by `--pattern`, with its placeholders expanded for
+/// `platform`, or by `--asset-name`; `None` when neither was.
+fn asset_pattern<'a>(
+    pattern: Option<&'a str>,
+    asset_name: Option<&str>,
+    platform: &pattern::Platform,
+) -> Option<Cow<'a, str>> {
+    match (pattern, asset_name) {
+        (Some(pattern), _) => Some(pattern::expand_for(pattern, platform)),
+        (None, Some(name)) => Some(Cow::Owned(exact_name_pattern(name))),
+        (None, None) => None,
+    }
+}
+
+/// `path` resolved inside `sysroot`: `/opt` under `/mnt/target` is
+/// `/mnt/target/opt`.
+fn in_sysroot(sysroot: &Utf8Path, path: &Utf8Path) -> Utf8PathBuf {
+    sysroot.join(path.strip_prefix("/").unwrap_or(path))
+}
+
+impl Commands {
+    /// The release source settings of subcommands that fetch releases, with the
+    /// state directory holding tokens stored by `login`.
+    fn github_mut(&mut self) -> Option<(&mut GitHubConfig, Option<&Utf8Path>)> {
+        match self {
+            Commands::Check(args) => Some((&mut args.github, Some(&args.state_directory))),
+            Commands::Update(args) => Some((&mut args.github, Some(&args.state_directory))),
+            // Each app's config may name its own token, so `update-all` falls back to
+            // the stored one per app.
+            Commands::UpdateAll(args) => Some((&mut args.github, None)),
+            Commands::Adopt(args) => Some((&mut args.github, Some(&args.state_directory))),
+            Commands::Status(args) => Some((&mut args.github, Some(&args.state_directory))),
+            Commands::Doctor(args) => Some((&mut args.github, Some(&args.state_directory))),
+            Commands::SelfUpdate(args) => Some((&mut args.github, None)),
+            Commands::ListRemote(args) => Some((&mut args.github, None)),
+            _ => None,
+        }
+    }
+
+    /// The subcommand name as typed on the command line.
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Commands::Check(_) => "check",
+            Commands::Update(_) => "update",
+            Commands::Version => "version",
+            Commands::Unlock(_) => "unlock",
+            Commands::Uninstall(_) => "uninstall",
+            Commands::UpdateAll(_) => "update-all",
+            Commands::Adopt(_) => "adopt",
+            Commands::Rollback(_) => "rollback",
+            Commands::List(_) => "list",
+            Commands::ListRemote(_) => "list-remote",
+            Commands::Status(_) => "status",
+            Commands::Doctor(_) => "doctor",
+            Commands::Gc(_) => "gc",
+            Commands::Prune(_) => "prune",
+            Commands::Maintenance(_) => "maintenance",
+            Commands::History(_) => "history",
+            Commands::Pin(_) => "pin",
+            Commands::Unpin(_) => "unpin",
+            Commands::Login(_) => "login",
+            Commands::SelfUpdate(_) => "self-update",
+            Commands::Completions(_) => "completions",
+            Commands::NetworkHelper => privsep::HELPER_COMMAND,
+        }
+    }
+
+    fn apply_sysroot(&mut self, sysroot: &Utf8Path) -> Vec<String> {
+        let state_directory = match self {
+            Commands::Update(update_args) => return update_args.apply_sysroot(sysroot),
+            Commands::Rollback(rollback_args) => {
+                rollback_args.state_directory = in_sysroot(sysroot, &rollback_args.state_directory);
+                return rollback_args
+                    .restart_command
+                    .take()
+                    .map(|_| sysroot_skips("--restart-command"))
+                    .into_iter()
+                    .collect();
+            }
+            Commands::SelfUpdate(_) => {
+                return vec!["--sysroot has no effect on self-update".to_string()];
+            }
+            Commands::List(list_args) => match &mut list_args.state_directory {
+                Some(state_directory) => state_directory,
+                None => return Vec::new(),
+            },
+            Commands::Check(args) => &mut args.state_directory,
+            Commands::Unlock(args) => &mut args.state_directory,
+            Commands::Uninstall(args) => &mut args.state_directory,
+            Commands::Adopt(args) => &mut args.state_directory,
+            Commands::Status(args) => &mut args.state_directory,
+            Commands::Doctor(args) => &mut args.state_directory,
+            Commands::Gc(args) => &mut args.state_directory,
+            Commands::Prune(args) => &mut args.state_directory,
+            Commands::Maintenance(args) => &mut args.state_directory,
+            Commands::History(args) => &mut args.state_directory,
+            Commands::Pin(args) => &mut args.state_directory,
+            Commands::Unpin(args) => &mut args.state_directory,
+            Commands::Login(args) => &mut args.state_directory,
+            Commands::UpdateAll(_)
+            | Commands::Version
+            | Commands::ListRemote(_)
+            | Commands::Completions(_)
+            | Commands::NetworkHelper => return Vec::new(),
+        };
+        *state_directory = in_sysroot(sysroot, state_directory);
+        Vec::new()
+    }
+
+    fn merge_config(&mut self, config: &AppConfig) {
+        match self {
+            Commands::Check(check_args) => check_args.merge_config(config),
+            Commands::Update(update_args) => update_args.merge_config(config),
+            Commands::ListRemote(list_remote_args) => list_remote_args.merge_config(config),
+            Commands::Status(status_args) => {
+                status_args.repo = status_args.repo.take().or_else(|| config.repo.clone());
+                status_args.github.merge_config(config);
+            }
+            Commands::Adopt(adopt_args) => {
+                adopt_args.repo = adopt_args.repo.take().or_else(|| config.repo.clone());
+                adopt_args.github.merge_config(config);
+            }
+            Commands::Prune(prune_args) => {
+                prune_args.retain = prune_args.retain.or(config.retain);
+                prune_args.retain_max_size = prune_args.retain_max_size.or(config.retain_max_size);
+            }
+            Commands::Maintenance(maintenance_args) => maintenance_args.merge_config(config),
+            Commands::Doctor(doctor_args) => {
+                doctor_args.repo = doctor_args.repo.take().or_else(|| config.repo.clone());
+                doctor_args.github.merge_config(config);
+            }
+            Commands::Rollback(rollback_args) => {
+                rollback_args.restart_command = rollback_args
+                    .restart_command
+                    .take()
+                    .or_else(|| config.restart_command.clone());
+                rollback_args.link_filter = rollback_args.link_filter.or(config.link_filter);
+            }
+            Commands::Version
+            | Commands::Unlock(_)
+            | Commands::Uninstall(_)
+            | Commands::UpdateAll(_)
+            | Commands::List(_)
+            | Commands::Gc(_)
+            | Commands::History(_)
+            | Commands::Pin(_)
+            | Commands::Unpin(_)
+            | Commands::Login(_)
+            | Commands::SelfUpdate(_)
+            | Commands::Completions(_)
+            | Commands::NetworkHelper => {}
+        }
+    }
+}
+
+impl GitHubConfig {
+    fn merge_config(&mut self, config: &AppConfig) {
+        // A token source given on the command line replaces every source in the file.
+        if self.token.is_none()
+            && self.token_file.is_none()
+            && self.token_command.is_none()
+            && self.token_keyring.is_none()
+            && !self.token_from_gh
+        {
+            self.token.clone_from(&config.github.token);
+            self.token_file.clone_from(&config.github.token_file);
+            self.token_command.clone_from(&config.github.token_command);
+            self.token_keyring.clone_from(&config.github.token_keyring);
+            self.token_from_gh = config.github.token_from_gh.unwrap_or(false);
+        }
+        self.host = self.host.take().or_else(|| config.github.host.clone());
+        // `--allow-prerelease` on the command line outranks a channel in the file.
+        if !self.allow_prerelease {
+            self.channel = self.channel.or(config.github.channel);
+        }
+        self.allow_prerelease |= config.github.allow_prerelease.unwrap_or(false);
+        self.fallback_to_tags |= config.github.fallback_to_tags.unwrap_or(false);
+        self.rate_limit_wait = self.rate_limit_wait.or(config.github.rate_limit_wait);
+        self.tag_pattern = self
+            .tag_pattern
+            .take()
+            .or_else(|| config.github.tag_pattern.clone());
+        self.version_constraint = self
+            .version_constraint
+            .take()
+            .or_else(|| config.github.version_constraint.clone());
+        self.version_prefix = self
+            .version_prefix
+            .take()
+            .or_else(|| config.github.version_prefix.clone());
+        self.min_release_age = self.min_release_age.or(config.github.min_release_age);
+        self.skip_versions
+            .extend(config.github.skip_versions.iter().cloned());
+        self.allow_versions = self
+            .allow_versions
+            .take()
+            .or_else(|| config.github.allow_versions.clone());
+    }
+
+    /// How long GitHub requests may wait in total for rate limits to reset.
+    #[must_use]
+    pub fn rate_limit_wait(&self) -> Duration {
+        Duration::from_secs(self.rate_limit_wait.unwrap_or_default())
+    }
+
+    /// The API base URL, falling back to the public API of the source, the feed
+    /// URL for a JSON feed, or the bucket URL for a bucket.
+    #[must_use]
+    pub fn host(&self) -> &str {
+        match self.source() {
+            Source::Json => self.json_url.as_deref().unwrap_or_default(),
+            Source::S3 => self.bucket_url.as_deref().unwrap_or_default(),
+            Source::Github => self.host.as_deref().unwrap_or(DEFAULT_GITHUB_HOST),
+            Source::Bitbucket => self
+                .host
+                .as_deref()
+                .unwrap_or(bitbucket::DEFAULT_BITBUCKET_HOST),
+        }
+    }
+
+    #[must_use]
+    pub fn source(&self) -> Source {
+        self.source.unwrap_or_default()
+    }
+
+    /// The release channel to follow, where `--allow-prerelease` means every
+    /// prerelease.
+    #[must_use]
+    pub fn channel(&self) -> github::Channel {
+        self.channel.unwrap_or(if self.allow_prerelease {
+            github::Channel::Prerelease
+        } else {
+            github::Channel::Stable
+        })
+    }
+
+    /// Identifies which release of `repo` these settings ask for, so a cached
+    /// release is only reused for the same question.
+    #[must_use]
+    pub fn release_query(&self, repo: &str) -> String {
+        format!(
+            "{:?} {} {repo} channel={} tags={} tag_pattern={} constraint={} prefix={} min_age={}",
+            self.source(),
+            self.host(),
+            self.channel(),
+            self.fallback_to_tags,
+            self.tag_pattern.as_deref().unwrap_or_default(),
+            self.version_constraint.as_deref().unwrap_or_default(),
+            self.version_prefix.as_deref().unwrap_or_default(),
+            self.min_release_age
+                .map(|min_age| min_age.to_string())
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Reads the token from `--github-token-file`, runs `--github-token-command`,
+    /// reads the OS keyring entry `--github-token-keyring`, or asks the `gh` CLI
+    /// with `--github-token-from-gh`, in that order, when no token was given
+    /// directly, so [`Self::token`] sees it. Without any of them, uses the
+    /// token `login` stored for the host in `state_directory`, if there is one.
+    /// Does nothing for other sources.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, the command fails, the keyring
+    /// has no such entry, any of them yields an empty token, `gh` has no token for
+    /// the host, or the stored token cannot be read.
+    pub fn resolve_token(&mut self, state_directory: Option<&Utf8Path>) -> anyhow::Result<()> {
+        if self.token.is_some() || self.source() != Source::Github {
+            return Ok(());
+        }
+
+        let token = if let Some(path) = &self.token_file {
+            let metadata =
+                fs::metadata(path).map_err(|e| anyhow!("cannot read token file {path}: {e}"))?;
+            if metadata.mode() & 0o077 != 0 {
+                warn!(
+                    "token file {path} is accessible to other users (mode {:o}); restrict it with chmod 600",
+                    metadata.mode() & 0o777
+                );
+            }
+            fs::read_to_string(path).map_err(|e| anyhow!("cannot read token file {path}: {e}"))?
+        } else if let Some(command) = &self.token_command {
+            let output = Command::new("/bin/sh")
+                .arg("-c")
+                .arg(command)
+                .stdin(Stdio::null())
+                .stderr(Stdio::inherit())
+                .output()
+                .map_err(|e| anyhow!("cannot run token command: {e}"))?;
+            ensure!(
+                output.status.success(),
+                "token command failed with {}",
+                output.status
+            );
+            String::from_utf8(output.stdout)
+                .map_err(|_| anyhow!("token command printed a token that is not UTF-8"))?
+        } else if let Some(entry) = &self.token_keyring {
+            keyring_auth::token(entry)?
+        } else if self.token_from_gh {
+            gh_auth::token(self.host())?
+        } else {
+            if let Some(state_directory) = state_directory {
+                self.token = login::load_token(state_directory, &gh_auth::hostname(self.host()))?;
+            }
+            return Ok(());
+        };
+
+        let token = token.trim();
+        ensure!(
+            !token.is_empty(),
+            "{} produced an empty GitHub token",
+            if self.token_file.is_some() {
+                "token file"
+            } else if self.token_command.is_some() {
+                "token command"
+            } else if self.token_keyring.is_some() {
+                "keyring entry"
+            } else {
+                "gh CLI"
+            }
+        );
+        self.token = Some(token.to_string());
+        Ok(())
+    }
+
+    /// The GitHub token, which is never sent to another source.
+    #[must_use]
+    pub fn token(&self) -> Option<&str> {
+        self.token
+            .as_deref()
+            .filter(|_| self.source() == Source::Github)
+    }
+
+    fn bitbucket_credentials(&self) -> Option<bitbucket::Credentials> {
+        Some(bitbucket::Credentials {
+            username: self.bitbucket_username.clone()?,
+            app_password: self.bitbucket_app_password.clone()?,
+        })
+    }
+
+    fn bucket(&self) -> anyhow::Result<s3::Bucket> {
+        let url = self.host();
+        let credentials = match (&self.s3_access_key_id, &self.s3_secret_access_key) {
+            (Some(access_key_id), Some(secret_access_key)) => Some(s3::Credentials {
+                access_key_id: access_key_id.clone(),
+                secret_access_key: secret_access_key.clone(),
+                session_token: self.s3_session_token.clone(),
+            }),
+            _ => None,
+        };
+        Ok(s3::Bucket {
+            url: Url::parse(url).map_err(|e| anyhow!("invalid bucket URL {url}: {e}"))?,
+            region: self
+                .s3_region
+                .clone()
+                .unwrap_or_else(|| s3::DEFAULT_REGION.to_string()),
+            credentials,
+        })
+    }
+
+    /// `--tag-pattern` as given, which GitHub uses to filter releases.
+    fn tag_filter(&self) -> anyhow::Result<Option<Regex>> {
+        Ok(self.tag_pattern.as_deref().map(Regex::new).transpose()?)
+    }
+
+    /// `--version-constraint` with `--version-prefix`, when given.
+    fn version_constraint(&self) -> anyhow::Result<Option<VersionConstraint>> {
+        Ok(self
+            .version_constraint
+            .as_deref()
+            .map(|constraint| VersionConstraint::new(constraint, self.version_prefix.as_deref()))
+            .transpose()?)
+    }
+
+    /// `--skip-version` and `--allow-versions`, reading versions with
+    /// `--version-prefix`.
+    fn version_filter(&self) -> anyhow::Result<VersionFilter> {
+        Ok(VersionFilter::new(
+            &self.skip_versions,
+            self.allow_versions.as_deref(),
+            self.version_prefix.as_deref(),
+        )?)
+    }
+
+    fn tag_pattern(&self) -> anyhow::Result<Regex> {
+        Ok(Regex::new(
+            self.tag_pattern
+                .as_deref()
+                .unwrap_or(bitbucket::DEFAULT_TAG_PATTERN),
+        )?)
+    }
+
+    /// Fails for commands that only talk to GitHub when another source is selected.
+    fn ensure_github(&self, command: &str) -> anyhow::Result<()> {
+        ensure!(
+            self.source() == Source::Github,
+            "{command} only supports GitHub releases; other sources work with check, update, update-all, and status"
+        );
+        Ok(())
+    }
+
+    /// The client for the source: `http_client` for GitHub, or for Bitbucket a
+    /// client sending the app password with every request, so asset downloads
+    /// and checksum files are authorized too.
+    fn source_client(
+        &self,
+        http: &HttpSettings,
+        http_client: reqwest::Client,
+    ) -> anyhow::Result<reqwest::Client> {
+        match (self.source(), self.bitbucket_credentials()) {
+            (Source::Bitbucket, Some(credentials)) => {
+                let mut headers = HeaderMap::new();
+                headers.insert(AUTHORIZATION, credentials.authorization()?);
+                crate::build_http_client_with_headers(
+                    http.timeout,
+                    &http.spki_pins,
+                    http.proxy_mode,
+                    headers,
+                )
+            }
+            _ => Ok(http_client),
+        }
+    }
+
+    /// Fetches the newest release from the source.
+    async fn fetch_latest(
+        &self,
+        repo: &str,
+        validators: github::Validators,
+        debug_dir: Option<&Utf8Path>,
+        http_client: reqwest::Client,
+    ) -> anyhow::Result<github::FetchResult> {
+        match self.source() {
+            Source::Github => Ok(github::fetch_latest()
+                .repo(repo)
+                .maybe_token(self.token())
+                .client(http_client)
+                .host(self.host())
+                .channel(self.channel())
+                .fallback_to_tags(self.fallback_to_tags)
+                .validators(validators)
+                .maybe_debug_dir(debug_dir)
+                .rate_limit_wait(self.rate_limit_wait())
+                .maybe_tag_pattern(self.tag_filter()?.as_ref())
+                .maybe_version_constraint(self.version_constraint()?.as_ref())
+                .maybe_min_release_age(self.min_release_age)
+                .await?),
+            Source::Bitbucket => {
+                bitbucket::fetch_latest()
+                    .repo(repo)
+                    .maybe_credentials(self.bitbucket_credentials().as_ref())
+                    .client(http_client)
+                    .host(self.host())
+                    .tag_pattern(&self.tag_pattern()?)
+                    .maybe_version_constraint(self.version_constraint()?.as_ref())
+                    .maybe_min_release_age(self.min_release_age)
+                    .channel(self.channel())
+                    .await
+            }
+            Source::Json => {
+                // An unchanged feed's release still ages, so it has to be read again.
+                let validators = if self.min_release_age.is_some() {
+                    github::Validators::default()
+                } else {
+                    validators
+                };
+                let result = json_feed::fetch_latest()
+                    .url(self.host())
+                    .validators(validators)
+                    .client(http_client)
+                    .await?;
+                // A feed describes a single release, so there is nothing to choose.
+                if let (Some(constraint), Some(release)) =
+                    (self.version_constraint()?, &result.release)
+                {
+                    ensure!(
+                        constraint.matches(&release.tag_name),
+                        "The feed's release {} does not satisfy '{constraint}'",
+                        release.tag_name
+                    );
+                }
+                if let (Some(channel), Some(release)) = (self.channel, &result.release) {
+                    ensure!(
+                        channel.includes(release),
+                        "The feed's release {} is not on the {channel} channel",
+                        release.tag_name
+                    );
+                }
+                if let (Some(min_age), Some(release)) = (self.min_release_age, &result.release)
+                    && !min_age.allows(release, Timestamp::now())
+                {
+                    return Err(github::GithubError::NoReleaseOldEnough { min_age }.into());
+                }
+                Ok(result)
+            }
+            Source::S3 => {
+                s3::fetch_latest()
+                    .bucket(&self.bucket()?)
+                    .prefix(repo)
+                    .client(http_client)
+                    .tag_pattern(&self.tag_pattern()?)
+                    .maybe_version_constraint(self.version_constraint()?.as_ref())
+                    .maybe_min_release_age(self.min_release_age)
+                    .channel(self.channel())
+                    .await
+            }
+        }
+    }
+
+    /// Fetches the release with `tag` from the source.
+    async fn fetch_tag(
+        &self,
+        repo: &str,
+        tag: &str,
+        debug_dir: Option<&Utf8Path>,
+        http_client: reqwest::Client,
+    ) -> anyhow::Result<github::Release> {
+        match self.source() {
+            Source::Github => Ok(github::fetch_tag()
+                .repo(repo)
+                .tag(tag)
+                .maybe_token(self.token())
+                .client(http_client)
+                .host(self.host())
+                .maybe_debug_dir(debug_dir)
+                .rate_limit_wait(self.rate_limit_wait())
+                .await?),
+            Source::Bitbucket => {
+                bitbucket::fetch_tag()
+                    .repo(repo)
+                    .tag(tag)
+                    .maybe_credentials(self.bitbucket_credentials().as_ref())
+                    .client(http_client)
+                    .host(self.host())
+                    .tag_pattern(&self.tag_pattern()?)
+                    .await
+            }
+            Source::Json => {
+                json_feed::fetch_tag()
+                    .url(self.host())
+                    .tag(tag)
+                    .client(http_client)
+                    .await
+            }
+            Source::S3 => {
+                s3::fetch_tag()
+                    .bucket(&self.bucket()?)
+                    .prefix(repo)
+                    .tag(tag)
+                    .client(http_client)
+                    .tag_pattern(&self.tag_pattern()?)
+                    .await
+            }
+        }
+    }
+}
+
+impl CheckArgs {
+    fn merge_config(&mut self, config: &AppConfig) {
+        self.repo = self.repo.take().or_else(|| config.repo.clone());
+        merge_asset_selector(&mut self.pattern, &mut self.asset_name, config);
+        self.libc = self.libc.or(config.libc);
+        self.github.merge_config(config);
+    }
+
+    /// The pattern of the asset `update` would download, derived like `update`'s.
+    #[must_use]
+    pub fn pattern(&self) -> Option<Cow<'_, str>> {
+        asset_pattern(
+            self.pattern.as_deref(),
+            self.asset_name.as_deref(),
+            &self.platform(),
+        )
+    }
+
+    /// The platform `update` would choose the asset for.
+    #[must_use]
+    pub fn platform(&self) -> pattern::Platform {
+        pattern::Platform::host().with_libc(self.libc)
+    }
+
+    /// The repository to check.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no repository was given on the command line or in the config file.
+    pub fn repo(&self) -> anyhow::Result<&str> {
+        required(self.repo.as_deref(), "--repo", "repo")
+    }
+}
+
+impl UpdateArgs {
+    /// Whether the run only reports what it would do: `--dry-run`, `--plan`, or
+    /// `--plan-file`.
+    fn previews(&self) -> bool {
+        self.dry_run || self.plan || self.plan_file.is_some()
+    }
+
+    fn extraction_limits(&self) -> extract::ExtractionLimits {
+        let mut limits = extract::ExtractionLimits::default();
+        if let Some(threads) = self.zstd_threads {
+            limits.decode_threads = threads as usize;
+        }
+        limits.sandbox = self.extract_sandbox.unwrap_or_default();
+        limits
+    }
+
+    /// Moves the state and license directories inside `sysroot` and drops the
+    /// restart and health commands, which would run on this host rather than in
+    /// the target.
+    fn apply_sysroot(&mut self, sysroot: &Utf8Path) -> Vec<String> {
+        self.state_directory = in_sysroot(sysroot, &self.state_directory);
+        self.license_dir = self
+            .license_dir
+            .as_deref()
+            .map(|dir| in_sysroot(sysroot, dir));
+
+        let mut warnings = Vec::new();
+        if self.restart_command.take().is_some() {
+            warnings.push(sysroot_skips("--restart-command"));
+        }
+        if self.health_command.take().is_some() {
+            warnings.push(sysroot_skips("--health-command"));
+        }
+        warnings
+    }
+
+    fn merge_config(&mut self, config: &AppConfig) {
+        self.repo = self.repo.take().or_else(|| config.repo.clone());
+        merge_asset_selector(&mut self.pattern, &mut self.asset_name, config);
+        self.libc = self.libc.or(config.libc);
+        self.checksum_pattern = self
+            .checksum_pattern
+            .take()
+            .or_else(|| config.checksum_pattern.clone());
+        self.restart_command = self
+            .restart_command
+            .take()
+            .or_else(|| config.restart_command.clone());
+        self.health_command = self
+            .health_command
+            .take()
+            .or_else(|| config.health_command.clone());
+        self.retain = self.retain.or(config.retain);
+        self.retain_max_size = self.retain_max_size.or(config.retain_max_size);
+        self.on_missing_binary = self.on_missing_binary.or(config.on_missing_binary);
+        self.link_filter = self.link_filter.or(config.link_filter);
+        self.max_link_depth = self.max_link_depth.or(config.max_link_depth);
+        self.zstd_threads = self.zstd_threads.or(config.zstd_threads);
+        self.extract_sandbox = self.extract_sandbox.or(config.extract_sandbox);
+        self.network_user = self
+            .network_user
+            .take()
+            .or_else(|| config.network_user.clone());
+        self.trust_policy = self.trust_policy.or(config.trust_policy);
+        self.cluster.merge_config(config);
+        self.notify.merge_config(config);
+        if self.required_bins.is_empty() {
+            self.required_bins.clone_from(&config.required_bins);
+        }
+        if self.mirrors.is_empty() {
+            self.mirrors.clone_from(&config.mirrors);
+        }
+        if self.extra_assets.is_empty() {
+            self.extra_assets.clone_from(&config.extra_assets);
+        }
+        self.maintenance = self.maintenance.or(config.maintenance);
+        self.license_dir = self
+            .license_dir
+            .take()
+            .or_else(|| config.license_dir.clone());
+        self.github.merge_config(config);
+    }
+
+    /// The repository to update from.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no repository was given on the command line or in the config file.
+    pub fn repo(&self) -> anyhow::Result<&str> {
+        required(self.repo.as_deref(), "--repo", "repo")
+    }
+
+    /// The asset pattern to match, with its platform placeholders expanded, or
+    /// `None` when none was given on the command line or in the config file and
+    /// the asset is chosen for the host platform.
+    #[must_use]
+    pub fn pattern(&self) -> Option<Cow<'_, str>> {
+        asset_pattern(
+            self.pattern.as_deref(),
+            self.asset_name.as_deref(),
+            &self.platform(),
+        )
+    }
+
+    /// The platform to choose the asset for: this host, with the C library
+    /// `--libc` names.
+    #[must_use]
+    pub fn platform(&self) -> pattern::Platform {
+        pattern::Platform::host().with_libc(self.libc)
+    }
+
+    /// The checksum file pattern, which is mandatory unless verification is skipped
+    /// or a JSON feed supplies each asset's digest.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no checksum pattern was configured and `--skip-verification`
+    /// was not given.
+    pub fn checksum_pattern(&self) -> anyhow::Result<Option<&str>> {
+        if self.skip_verification || self.github.source() == Source::Json {
+            return Ok(self.checksum_pattern.as_deref());
+        }
+        required(
+            self.checksum_pattern.as_deref(),
+            "--checksum-pattern",
+            "checksum_pattern",
+        )
+        .map(Some)
+        .map_err(|e| anyhow!("{e}; use --skip-verification to install without a checksum"))
+    }
+
+    /// Number of old releases to keep.
+    #[must_use]
+    pub fn retain(&self) -> u32 {
+        self.retain.unwrap_or(DEFAULT_RETAIN)
+    }
+
+    /// Which old releases to keep when pruning after an update.
+    #[must_use]
+    pub fn retention(&self) -> fsops::Retention {
+        fsops::Retention {
+            count: self.retain() as usize,
+            max_size: self.retain_max_size.map(|size| size.0),
+            pinned: None,
+        }
+    }
+
+    /// The user `--network-user` names, when this process runs as root and so
+    /// has privileges to drop.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the user does not exist or is root.
+    pub fn network_user(&self) -> anyhow::Result<Option<privsep::NetworkUser>> {
+        match self.network_user.as_deref() {
+            Some(user) if privsep::is_root() => Ok(Some(privsep::NetworkUser::lookup(user)?)),
+            Some(user) => {
+                debug!(
+                    "Not running as root, so network requests stay in this process, not {user}'s"
+                );
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Retry and stall limits applied to asset downloads.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct TransferLimits {
+    max_retries: u32,
+    stall_timeout: Option<Duration>,
+    rate_limit_wait: Duration,
+}
+
+/// What HTTP clients are built from, so the network helper builds the same ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HttpSettings {
+    timeout: Duration,
+    spki_pins: Vec<SpkiPin>,
+    proxy_mode: proxy::Mode,
+}
+
+impl HttpSettings {
+    fn client(&self) -> anyhow::Result<reqwest::Client> {
+        crate::build_pinned_http_client(self.timeout, &self.spki_pins, self.proxy_mode)
+    }
+}
+
+/// Makes the requests of an update to its release source: in this process, or,
+/// given a network user, in a helper process running as that user.
+struct Fetcher<'a> {
+    github: &'a GitHubConfig,
+    http: HttpSettings,
+    client: reqwest::Client,
+    mirrors: &'a [String],
+    limits: TransferLimits,
+    network_user: Option<privsep::NetworkUser>,
+}
+
+/// A request the network helper makes on behalf of a [`Fetcher`].
+#[derive(Debug, Serialize, Deserialize)]
+enum NetworkTask {
+    Latest {
+        repo: String,
+        validators: github::Validators,
+        /// Whether to save an unparseable response for the caller to collect.
+        #[serde(default)]
+        debug: bool,
+    },
+    Tag {
+        repo: String,
+        tag: String,
+        #[serde(default)]
+        debug: bool,
+    },
+    Asset {
+        tag: String,
+        asset: github::Asset,
+    },
+    Checksum {
+        asset_name: String,
+        url: String,
+    },
+}
+
+/// What the network helper reads on stdin: one task and the settings of the
+/// [`Fetcher`] that sent it.
+#[derive(Debug, Serialize, Deserialize)]
+struct NetworkJob {
+    github: GitHubConfig,
+    http: HttpSettings,
+    mirrors: Vec<String>,
+    limits: TransferLimits,
+    task: NetworkTask,
+}
+
+impl Fetcher<'_> {
+    /// Runs `task` in the helper, collecting what it leaves with `take` and the
+    /// responses it saved into `debug_dir`. The helper is waited for on a blocking
+    /// thread, keeping the runtime's workers free.
+    async fn delegate<T: DeserializeOwned + Send + 'static, R: Send + 'static>(
+        &self,
+        user: &privsep::NetworkUser,
+        task: NetworkTask,
+        debug_dir: Option<&Utf8Path>,
+        take: impl FnOnce(T, &Utf8Path) -> privsep::Result<R> + Send + 'static,
+    ) -> anyhow::Result<R> {
+        let job = NetworkJob {
+            github: self.github.clone(),
+            http: self.http.clone(),
+            mirrors: self.mirrors.to_vec(),
+            limits: self.limits,
+            task,
+        };
+        let user = user.clone();
+        let debug_dir = debug_dir.map(Utf8Path::to_path_buf);
+        Ok(tokio::task::spawn_blocking(move || {
+            privsep::call(&user, &job, debug_dir.as_deref(), take)
+        })
+        .await??)
+    }
+
+    /// Fetches the newest release of `repo`.
+    async fn latest(
+        &self,
+        repo: &str,
+        validators: github::Validators,
+        debug_dir: Option<&Utf8Path>,
+    ) -> anyhow::Result<github::FetchResult> {
+        match &self.network_user {
+            Some(user) => {
+                let task = NetworkTask::Latest {
+                    repo: repo.to_string(),
+                    validators,
+                    debug: debug_dir.is_some(),
+                };
+                self.delegate(user, task, debug_dir, |result, _| Ok(result))
+                    .await
+            }
+            None => {
+                self.github
+                    .fetch_latest(repo, validators, debug_dir, self.client.clone())
+                    .await
+            }
+        }
+    }
+
+    /// Fetches the release of `repo` tagged `tag`.
+    async fn tag(
+        &self,
+        repo: &str,
+        tag: &str,
+        debug_dir: Option<&Utf8Path>,
+    ) -> anyhow::Result<github::Release> {
+        match &self.network_user {
+            Some(user) => {
+                let task = NetworkTask::Tag {
+                    repo: repo.to_string(),
+                    tag: tag.to_string(),
+                    debug: debug_dir.is_some(),
+                };
+                self.delegate(user, task, debug_dir, |release, _| Ok(release))
+                    .await
+            }
+            None => {
+                self.github
+                    .fetch_tag(repo, tag, debug_dir, self.client.clone())
+                    .await
+            }
+        }
+    }
+
+    /// Downloads `asset` of the release tagged `tag`, returning the file and the
+    /// mirror it came from, if any.
+    async fn asset(
+        &self,
+        tag: &str,
+        asset: &github::Asset,
+    ) -> anyhow::Result<(NamedUtf8TempFile, Option<String>)> {
+        let (file, index) = match &self.network_user {
+            Some(user) => {
+                let task = NetworkTask::Asset {
+                    tag: tag.to_string(),
+                    asset: asset.clone(),
+                };
+                let owner = user.clone();
+                self.delegate(user, task, None, move |index, work_dir| {
+                    Ok((privsep::take_download(work_dir, &owner)?, index))
+                })
+                .await?
+            }
+            None => self.download(tag, asset).await?,
+        };
+        Ok((file, self.mirrors.get(index).cloned()))
+    }
+
+    async fn download(
+        &self,
+        tag: &str,
+        asset: &github::Asset,
+    ) -> anyhow::Result<(NamedUtf8TempFile, usize)> {
+        // Mirrors come first and never see the token; the source is the last resort.
+        // Whatever they serve is still verified against the source's checksums.
+        let candidates: Vec<_> = self
+            .mirrors
+            .iter()
+            .map(|mirror| download::Candidate {
+                url: download::mirror_url(mirror, tag, &asset.name),
+                token: None,
+            })
+            .chain([download::Candidate {
+                url: asset.url.clone(),
+                token: self.github.token(),
+            }])
+            .collect();
+        let (file, index) = download::fetch_first()
+            .candidates(&candidates)
+            .file_name(&asset.name)
+            .client(self.client.clone())
+            .max_retries(self.limits.max_retries)
+            .maybe_stall_timeout(self.limits.stall_timeout)
+            .rate_limit_wait(self.limits.rate_limit_wait)
+            .await?;
+        if index < self.mirrors.len() {
+            info!("Downloaded from mirror {}", candidates[index].url);
+        }
+        Ok((file, index))
+    }
+
+    /// Fetches the checksum file at `url` and returns the digest it lists for
+    /// `asset_name`.
+    async fn checksum(&self, asset_name: &str, url: &str) -> anyhow::Result<String> {
+        match &self.network_user {
+            Some(user) => {
+                let task = NetworkTask::Checksum {
+                    asset_name: asset_name.to_string(),
+                    url: url.to_string(),
+                };
+                self.delegate(user, task, None, |expected, _| Ok(expected))
+                    .await
+            }
+            None => Ok(verify::fetch_expected_checksum(
+                asset_name,
+                url,
+                self.github.token(),
+                self.client.clone(),
+            )
+            .await?),
+        }
+    }
+}
+
+impl NetworkJob {
+    /// Makes the request in this process and returns its answer.
+    async fn run(self) -> anyhow::Result<serde_json::Value> {
+        let NetworkJob {
+            github,
+            http,
+            mirrors,
+            limits,
+            task,
+        } = self;
+        let client = github.source_client(&http, http.client()?)?;
+        let fetcher = Fetcher {
+            github: &github,
+            http,
+            client,
+            mirrors: &mirrors,
+            limits,
+            network_user: None,
+        };
+        // The helper runs in its working directory, where `privsep::call` looks for
+        // the responses it saved.
+        let debug_dir = Utf8PathBuf::try_from(env::current_dir()?)?.join(privsep::DEBUG_DIR);
+        let debug_dir = |debug: bool| debug.then_some(debug_dir.as_path());
+        Ok(match task {
+            NetworkTask::Latest {
+                repo,
+                validators,
+                debug,
+            } => serde_json::to_value(fetcher.latest(&repo, validators, debug_dir(debug)).await?)?,
+            NetworkTask::Tag { repo, tag, debug } => {
+                serde_json::to_value(fetcher.tag(&repo, &tag, debug_dir(debug)).await?)?
+            }
+            NetworkTask::Asset { tag, asset } => {
+                let (file, index) = fetcher.download(&tag, &asset).await?;
+                file.persist(privsep::DOWNLOAD_FILE)?;
+                serde_json::to_value(index)?
+            }
+            NetworkTask::Checksum { asset_name, url } => {
+                serde_json::to_value(fetcher.checksum(&asset_name, &url).await?)?
+            }
+        })
+    }
+}
+
+/// How `install_release` treats an existing release directory and an extracted
+/// archive without executables, and where it reports its phases.
+struct InstallOptions {
+    overwrite_existing: bool,
+    allow_no_executables: bool,
+    on_missing_binary: fsops::MissingBinary,
+    link_filter: fsops::LinkFilter,
+    max_link_depth: Option<u32>,
+    extraction_limits: extract::ExtractionLimits,
+    required_bins: Vec<String>,
+    extras: Vec<ExtraDownload>,
+    events: Events,
+}
+
+/// A downloaded and verified extra asset and the release subdirectory it is
+/// extracted into.
+struct ExtraDownload {
+    file: NamedUtf8TempFile,
+    name: String,
+    dest: Utf8PathBuf,
+}
+
+/// Formats `timestamp` for people reading terminal output: in the local time zone
+/// (from `TZ` or the system setting) with its UTC offset, such as
+/// `2025-10-28 08:34:56 -04:00`. JSON output keeps RFC 3339 UTC timestamps.
+fn format_time(timestamp: Timestamp) -> String {
+    timestamp
+        .to_zoned(TimeZone::system())
+        .strftime("%Y-%m-%d %H:%M:%S %:z")
+        .to_string()
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Collects the outcome of `doctor` checks, printing each as it is recorded.
+/// With `--quiet`, only failures are printed.
+struct DoctorReport {
+    quiet: bool,
+    problems: usize,
+}
+
+impl DoctorReport {
+    fn ok(&self, message: impl std::fmt::Display) {
+        if !self.quiet {
+            println!("ok    {message}");
+        }
+    }
+
+    fn warn(&self, message: impl std::fmt::Display) {
+        if !self.quiet {
+            println!("warn  {message}");
+        }
+    }
+
+    fn fail(&mut self, message: impl std::fmt::Display) {
+        self.problems += 1;
+        println!("FAIL  {message}");
+    }
+}
+
+/// Compares the owner of each existing directory in `dirs` with the effective user.
+fn ownership_warnings(dirs: &[(&str, &Utf8Path)]) -> Vec<String> {
+    let euid = rustix::process::geteuid().as_raw();
+    dirs.iter()
+        .filter_map(|(label, dir)| {
+            let owner = fs::metadata(dir).ok()?.uid();
+            let writable =
+                rustix::fs::access(dir.as_std_path(), Access::WRITE_OK | Access::EXEC_OK).is_ok();
+            ownership_warning(label, dir, euid, owner, writable)
+        })
+        .collect()
+}
+
+fn ownership_warning(
+    label: &str,
+    dir: &Utf8Path,
+    euid: u32,
+    owner: u32,
+    writable: bool,
+) -> Option<String> {
+    if euid == 0 && owner != 0 {
+        Some(format!(
+            "running as root but {label} {dir} is owned by uid {owner}; files created now will be owned by root and later runs as uid {owner} will fail with permission denied (run as uid {owner}, or chown the tree to root if root should own it)"
+        ))
+    } else if euid != 0 && owner != euid && !writable {
+        Some(format!(
+            "running as uid {euid} but {label} {dir} is owned by uid {owner} and not writable; this run will fail with permission denied (run as uid {owner}, e.g. with sudo, or chown the directory to uid {euid})"
+        ))
+    } else {
+        None
+    }
+}
+
+/// Returns the closest ancestor of `path` (including itself) that exists.
+fn nearest_existing(path: &Utf8Path) -> &Utf8Path {
+    path.ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(Utf8Path::new("/"))
+}
+
+fn is_affirmative(answer: &str) -> bool {
+    matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+/// Asks for confirmation before a destructive operation.
+///
+/// Only prompts when both stdin and stderr are terminals and `--yes` was not given, so
+/// timers, cron jobs, and scripts are never blocked waiting for input.
+fn confirm(args: &Args, prompt: &str) -> anyhow::Result<()> {
+    if args.yes || !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        return Ok(());
+    }
+
+    eprint!("{prompt} [y/N] ");
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    ensure!(is_affirmative(&answer), "Aborted by user");
+
+    Ok(())
+}
+
+fn is_up_to_date(
+    current_tag: Option<&String>,
+    release_opt: Option<&github::Release>,
+    existing_state: Option<&State>,
+    was_modified: bool,
+) -> bool {
+    if !was_modified
+        && release_opt.is_none()
+        && let (Some(current), Some(state)) = (current_tag, existing_state)
+        && *current == state.latest_tag
+    {
+        return true;
+    }
+
+    if let (Some(current), Some(release)) = (current_tag, release_opt)
+        && *current == release.tag_name
+    {
+        return true;
+    }
+
+    false
+}
+
+/// The cached release of `app` if it answers `query`. A cache that cannot be read
+/// is ignored, since the release can always be fetched again.
+fn cached_release(state_directory: &Utf8Path, app: &str, query: &str) -> Option<CachedRelease> {
+    match release_cache::load(state_directory, app) {
+        Ok(cached) => cached.filter(|cached| cached.query == query),
+        Err(e) => {
+            warn!("Ignoring the cached release of {app}: {e}");
+            None
+        }
+    }
+}
+
+/// Caches the release a fetch returned, if the response can be revalidated or
+/// reused later.
+fn cache_release(
+    state_directory: &Utf8Path,
+    app: &str,
+    query: String,
+    fetch_result: &github::FetchResult,
+) {
+    let validators = &fetch_result.validators;
+    let Some(release) = fetch_result.release.as_ref().filter(|_| {
+        validators.etag.is_some()
+            || validators.last_modified.is_some()
+            || validators.max_age.is_some()
+    }) else {
+        return;
+    };
+
+    let cached = CachedRelease::new(query, validators, release.clone());
+    if let Err(e) = release_cache::save(state_directory, app, &cached) {
+        warn!("Failed to cache the release of {app}: {e}");
+    }
+}
+
+/// How a downloaded asset is checked before it is installed.
+#[derive(Debug, Clone, Copy)]
+enum Verification<'a> {
+    /// `--skip-verification`.
+    Skip,
+    /// Against the entry for the asset in the release's checksum asset matching the pattern.
+    ChecksumAsset(&'a Regex),
+    /// Against the SHA-256 the source lists for the asset itself.
+    AssetDigest,
+}
+
+/// Compares the publisher of `release` with the one trusted when `app` was first
+/// installed, returning the identity to record once the update is committed: the
+/// observed one on first use or when a change is accepted.
+fn check_publisher(
+    app: &str,
+    state_directory: &Utf8Path,
+    release: &github::Release,
+    asset_pattern: &Regex,
+    verification: &Verification<'_>,
+    policy: trust::Policy,
+    accept_new_publisher: bool,
+) -> anyhow::Result<Option<trust::Identity>> {
+    if policy == trust::Policy::Off {
+        return Ok(None);
+    }
+    // A missing asset is reported by the download that follows.
+    let Some(asset) = github::select_asset(&release.assets, Some(asset_pattern)) else {
+        return Ok(None);
+    };
+    let checksum_asset = match verification {
+        Verification::ChecksumAsset(checksum_regex) => {
+            github::select_asset(&release.assets, Some(checksum_regex))
+        }
+        Verification::Skip | Verification::AssetDigest => None,
+    };
+    let observed = trust::Identity::observe(
+        &release.tag_name,
+        asset,
+        checksum_asset,
+        matches!(verification, Verification::AssetDigest),
+    );
+
+    let Some(trusted) = trust::load(state_directory, app)? else {
+        return Ok(Some(observed));
+    };
+    let changes = trusted.identity.changes(&observed);
+    if changes.is_empty() {
+        return Ok(None);
+    }
+    let changes = changes.join("; ");
+    if accept_new_publisher {
+        warn!("Trusting the new publisher of {app}: {changes}");
+        return Ok(Some(observed));
+    }
+    ensure!(
+        policy != trust::Policy::Fail,
+        "The publisher of {app} changed since {} was trusted: {changes}; \
+         pass --accept-new-publisher if this change is intended",
+        trusted.tag
+    );
+    warn!(
+        "The publisher of {app} changed since {} was trusted: {changes}",
+        trusted.tag
+    );
+    Ok(None)
+}
+
+/// The SHA-256 a JSON feed lists for `asset`.
+fn asset_sha256(asset: &github::Asset) -> anyhow::Result<&str> {
+    asset.sha256().ok_or_else(|| {
+        anyhow!(
+            "The feed lists no sha256 for {}; use --skip-verification to install it anyway",
+            asset.name
+        )
+    })
+}
+
+/// The digest `asset` must have and where it comes from, or `None` when
+/// verification is skipped.
+async fn expected_digest(
+    fetcher: &Fetcher<'_>,
+    release: &github::Release,
+    asset: &github::Asset,
+    verification: Verification<'_>,
+) -> anyhow::Result<Option<(String, String)>> {
+    Ok(match verification {
+        Verification::ChecksumAsset(checksum_regex) => {
+            let checksum_asset = github::select_asset(&release.assets, Some(checksum_regex))
+                .ok_or_else(|| {
+                    github::NoMatchingAsset::new(
+                        "checksum asset",
+                        checksum_regex,
+                        &release.tag_name,
+                    )
+                })?;
+            let expected = fetcher.checksum(&asset.name, &checksum_asset.url).await?;
+            Some((expected, checksum_asset.name.clone()))
+        }
+        Verification::AssetDigest => {
+            Some((asset_sha256(asset)?.to_string(), "the feed".to_string()))
+        }
+        Verification::Skip => None,
+    })
+}
+
+async fn download_and_verify_asset(
+    fetcher: &Fetcher<'_>,
+    release: &github::Release,
+    asset_pattern: &Regex,
+    verification: Verification<'_>,
+    scan_command: Option<&str>,
+    events: &Events,
+) -> anyhow::Result<(NamedUtf8TempFile, String, Option<String>)> {
+    let asset = github::select_asset(&release.assets, Some(asset_pattern))
+        .ok_or_else(|| github::NoMatchingAsset::new("asset", asset_pattern, &release.tag_name))?;
+    info!("Selected asset: {}", asset.name);
+
+    let (downloaded_file, mirror) = {
+        let _span = info_span!("download", url = %asset.url).entered();
+        let phase = events.phase("download");
+        let result = fetcher.asset(&release.tag_name, asset).await;
+        phase.record(&result);
+        result?
+    };
+
+    match verification {
+        Verification::Skip => {}
+        Verification::ChecksumAsset(checksum_regex) => {
+            let _span = info_span!("verify", asset = %asset.name).entered();
+            let phase = events.phase("verify");
+            let checksum_asset = github::select_asset(&release.assets, Some(checksum_regex))
+                .ok_or_else(|| {
+                    github::NoMatchingAsset::new(
+                        "checksum asset",
+                        checksum_regex,
+                        &release.tag_name,
+                    )
+                })?;
+            let result = match fetcher.checksum(&asset.name, &checksum_asset.url).await {
+                Ok(expected) => {
+                    verify::verify_digest(&asset.name, downloaded_file.path(), &expected)
+                        .await
+                        .map_err(Into::into)
+                }
+                Err(e) => Err(e),
+            };
+            phase.record(&result);
+            result?;
+            info!("Checksum verified");
+        }
+        Verification::AssetDigest => {
+            let _span = info_span!("verify", asset = %asset.name).entered();
+            let phase = events.phase("verify");
+            let expected = asset_sha256(asset)?;
+            let result = verify::verify_digest(&asset.name, downloaded_file.path(), expected).await;
+            phase.record(&result);
+            result?;
+            info!("Checksum verified");
+        }
+    }
+
+    if let Some(cmd) = scan_command {
+        let _span = info_span!("scan", asset = %asset.name).entered();
+        let phase = events.phase("scan");
+        let result = scan::execute(cmd, downloaded_file.path());
+        phase.record(&result);
+        result?;
+        info!("Scan command accepted asset");
+    }
+
+    Ok((downloaded_file, asset.name.clone(), mirror))
+}
+
+/// Unpacks an extra asset on its own and moves the result to `dest` inside the
+/// staged release, refusing to merge it into a directory the main asset already
+/// provides.
+fn extract_extra(
+    install_root: &Utf8Path,
+    app: &str,
+    tag: &str,
+    extra: &ExtraDownload,
+    dest: &Utf8Path,
+    limits: &extract::ExtractionLimits,
+) -> anyhow::Result<()> {
+    ensure!(
+        fs::symlink_metadata(dest).is_err(),
+        "{} cannot be extracted into {}/: the release already contains it",
+        extra.name,
+        extra.dest
+    );
+    let work_dir = camino_tempfile::Builder::new()
+        .prefix(&format!("{tag}.extra."))
+        .tempdir_in(install_root.join(app).join("staging"))?;
+    let archive = work_dir.path().join(&extra.name);
+    fs::copy(extra.file.path(), &archive)?;
+    let unpacked = work_dir.path().join("unpacked");
+    fs::create_dir(&unpacked)?;
+    extract::ensure_space(&archive, &unpacked)?;
+    extract::unpack_with_limits(&archive, &unpacked, limits)?;
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(&unpacked, dest)?;
+    Ok(())
+}
+
+fn install_release(
+    install_root: &Utf8Path,
+    app: &str,
+    tag: &str,
+    previous_tag: Option<&str>,
+    downloaded_file: &NamedUtf8TempFile,
+    asset_name: &str,
+    options: &InstallOptions,
+) -> anyhow::Result<()> {
+    let staging_dir = fsops::make_staging(install_root, app, tag)?;
+
+    {
+        let _span = info_span!("extract", archive = %asset_name, dest = %staging_dir).entered();
+        let phase = options.events.phase("extract");
+        // The named copy lives beside the staging directory rather than in it, so
+        // a single top-level directory in the archive is still detected and stripped.
+        let archive_dir = camino_tempfile::Builder::new()
+            .prefix(&format!("{tag}.archive."))
+            .tempdir_in(install_root.join(app).join("staging"))?;
+        let temp_with_ext = archive_dir.path().join(asset_name);
+        fs::copy(downloaded_file.path(), &temp_with_ext)?;
+        let result = extract::ensure_space(&temp_with_ext, &staging_dir).and_then(|()| {
+            extract::unpack_with_limits(&temp_with_ext, &staging_dir, &options.extraction_limits)
+        });
+        phase.record(&result);
+        result?;
+    }
+
+    for extra in &options.extras {
+        let dest = staging_dir.join(&extra.dest);
+        let _span = info_span!("extract", archive = %extra.name, dest = %dest).entered();
+        let phase = options.events.phase("extract");
+        let result = extract_extra(
+            install_root,
+            app,
+            tag,
+            extra,
+            &dest,
+            &options.extraction_limits,
+        );
+        phase.record(&result);
+        if let Err(e) = result {
+            let _ = fs::remove_dir_all(&staging_dir);
+            return Err(e);
+        }
+        info!("Extracted {} into {}/", extra.name, extra.dest);
+    }
+
+    if let Some(max_depth) = options.max_link_depth {
+        let relocated = fsops::relocate_deep_executables(
+            &staging_dir,
+            max_depth as usize,
+            options.link_filter,
+        )?;
+        if !relocated.is_empty() {
+            info!(
+                "Moved {} executable(s) nested more than {max_depth} directories deep into {}/",
+                relocated.len(),
+                fsops::LIBEXEC_DIR
+            );
+        }
+    }
+
+    // Activating a release without executables would unlink every managed command
+    // as stale, leaving the app with nothing in bin/.
+    if fsops::discover_executables(&staging_dir, options.link_filter)?.is_empty() {
+        if options.allow_no_executables {
+            warn!("{asset_name} contains no executable files; bin/ will have no commands");
+        } else {
+            let _ = fs::remove_dir_all(&staging_dir);
+            bail!(
+                "{asset_name} contains no executable files; refusing to activate {tag} \
+                 (pass --allow-no-executables to install it anyway)"
+            );
+        }
+    }
+
+    {
+        let _span = info_span!("fsync", dir = %staging_dir).entered();
+        fsops::fsync_directory_tree(&staging_dir)?;
+        info!("Staged content synced to disk");
+    }
+
+    let releases_dir = install_root.join(app).join("releases");
+    fs::create_dir_all(&releases_dir)?;
+    File::open(&releases_dir)?.sync_all()?;
+    let installed_dir = if options.overwrite_existing {
+        fsops::replace_release(&staging_dir, &releases_dir, tag)?
+    } else {
+        match fsops::atomic_move(&staging_dir, &releases_dir, tag) {
+            Ok(dir) => dir,
+            Err(fsops::FsOpsError::AlreadyExists(path)) => {
+                bail!(
+                    "release already exists: {path} (use --overwrite-existing-release to replace it)"
+                );
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+
+    let bin_dir = install_root.join(app).join("bin");
+    let phase = options.events.phase("switch");
+    let result = activate_release(
+        &installed_dir,
+        &bin_dir,
+        tag,
+        options.on_missing_binary,
+        options.link_filter,
+    )
+    .and_then(|()| {
+        fsops::check_required_binaries(&installed_dir, &bin_dir, &options.required_bins)
+            .map_err(Into::into)
+    });
+    phase.record(&result);
+    if let Err(e) = result {
+        warn!("Activation of {tag} failed, rolling back: {e}");
+        rollback_activation(
+            &releases_dir,
+            &bin_dir,
+            tag,
+            previous_tag,
+            options.link_filter,
+        );
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+fn activate_release(
+    installed_dir: &Utf8Path,
+    bin_dir: &Utf8Path,
+    tag: &str,
+    on_missing_binary: fsops::MissingBinary,
+    link_filter: fsops::LinkFilter,
+) -> anyhow::Result<()> {
+    let _span = info_span!("switch", tag = %tag).entered();
+    fs::create_dir_all(bin_dir)?;
+    fsops::link_binaries(installed_dir, bin_dir, on_missing_binary, link_filter)?;
+    info!("Symlinks updated");
+    Ok(())
+}
+
+fn rollback_activation(
+    releases_dir: &Utf8Path,
+    bin_dir: &Utf8Path,
+    tag: &str,
+    previous_tag: Option<&str>,
+    link_filter: fsops::LinkFilter,
+) {
+    let _span = info_span!("rollback", tag = %tag).entered();
+
+    if previous_tag == Some(tag) {
+        warn!("Keeping release directory for {tag} because it was already active");
+        return;
+    }
+
+    match fsops::discard_release(releases_dir, tag) {
+        Ok(()) => info!("Removed release directory for {tag}"),
+        Err(e) => warn!("Failed to remove release directory for {tag}: {e}"),
+    }
+
+    // Links into the discarded release now dangle and are dropped, while links
+    // kept from older releases survive.
+    if let Some(previous) = previous_tag
+        && let Err(e) = fsops::link_binaries(
+            releases_dir.join(previous),
+            bin_dir,
+            fsops::MissingBinary::Keep,
+            link_filter,
+        )
+    {
+        warn!("Failed to restore symlinks for {previous}: {e}");
+    }
+}
+
+fn installed_state(
+    tag: &str,
+    previous_tag: Option<&str>,
+    validators_out: &github::ValidatorsOut,
+    repo: &str,
+    host: &str,
+) -> State {
+    let now = Timestamp::now();
+    State {
+        latest_tag: tag.to_string(),
+        etag: validators_out.etag.clone().unwrap_or_default(),
+        last_modified: validators_out.last_modified.clone().unwrap_or_default(),
+        installed_at: now,
+        previous_tag: previous_tag
+            .filter(|previous| *previous != tag)
+            .map(str::to_string),
+        repo: Some(repo.to_string()),
+        host: Some(host.to_string()),
+    }
+}
+
+/// Saves a staged update's state and install record, then clears the staged file.
+///
+/// The history entry is skipped if it is already the newest one, which happens when
+/// an earlier commit was interrupted after appending it.
+fn commit_pending(
+    app: &str,
+    state_directory: &Utf8Path,
+    store: &state::Store,
+    staged: &Pending,
+) -> anyhow::Result<()> {
+    let tag = &staged.state.latest_tag;
+    store.save(app, &staged.state)?;
+
+    let recorded = history::load(state_directory, app)
+        .ok()
+        .and_then(|mut entries| entries.pop())
+        .is_some_and(|last| last == staged.history);
+    if !recorded && let Err(e) = history::append(state_directory, app, &staged.history) {
+        warn!("Failed to record {tag} in install history: {e}");
+    }
+
+    pending::remove(state_directory, app)?;
+    info!("Committed state for {tag}");
+    Ok(())
+}
+
+/// Finishes or discards an update of `app` that was interrupted between staging its
+/// state and committing it.
+///
+/// If the staged release is the active one, the symlinks were switched and the state
+/// is committed now; otherwise the release was never activated and the staged state is
+/// dropped.
+fn recover_pending(
+    app: &str,
+    install_root: &Utf8Path,
+    state_directory: &Utf8Path,
+    store: &state::Store,
+) -> anyhow::Result<()> {
+    let Some(staged) = pending::load(state_directory, app)? else {
+        return Ok(());
+    };
+    let tag = &staged.state.latest_tag;
+
+    if version::current_tag(install_root, app)?.as_deref() == Some(tag.as_str()) {
+        warn!("Completing interrupted update of {app} to {tag}");
+        commit_pending(app, state_directory, store, &staged)?;
+    } else {
+        warn!("Discarding staged state for {tag}; the interrupted update never activated it");
+        pending::remove(state_directory, app)?;
+    }
+
+    Ok(())
+}
+
+/// Runs the restart command and prunes old releases, recording both in `summary`.
+///
+/// With `planned_prune` from an applied plan, only releases it lists are pruned.
+/// A failed restart is recorded rather than returned, so the caller can report the
+/// summary before failing.
+fn finalize_update(
+    releases_dir: &Utf8Path,
+    tag: &str,
+    restart_cmd: Option<&str>,
+    retention: fsops::Retention,
+    planned_prune: Option<&[String]>,
+    summary: &mut UpdateSummary,
+    events: &Events,
+) -> anyhow::Result<()> {
+    if let Some(cmd) = restart_cmd {
+        let _span = info_span!("restart", command = %cmd).entered();
+        let phase = events.phase("restart");
+        let started = Instant::now();
+        let result = restart::execute(cmd);
+        phase.record(&result);
+        let succeeded = match result {
+            Ok(()) => {
+                info!("Restart command succeeded");
+                true
+            }
+            Err(e) => {
+                warn!("Restart command failed: {}", e);
+                false
+            }
+        };
+        summary.durations_ms.restart = Some(elapsed_ms(started));
+        summary.restart = Some(RestartOutcome {
+            command: cmd.to_string(),
+            succeeded,
+        });
+    }
+
+    {
+        let _span = info_span!("prune", retain = %retention.count).entered();
+        let phase = events.phase("prune");
+        let started = Instant::now();
+        let result = fsops::releases_to_prune(releases_dir, tag, retention).map(|mut to_delete| {
+            if let Some(planned) = planned_prune {
+                for kept in planned.iter().filter(|kept| !to_delete.contains(kept)) {
+                    warn!("Not pruning {kept}, which the plan lists, since it is now kept");
+                }
+                to_delete.retain(|tag| planned.contains(tag));
+            }
+            fsops::remove_releases(releases_dir, to_delete)
+        });
+        phase.record(&result);
+        let (deleted, failed) = result?;
+        if !deleted.is_empty() {
+            info!("Pruned {} old release(s): {:?}", deleted.len(), deleted);
+        }
+        if !failed.is_empty() {
+            warn!("Failed to prune {} release(s): {:?}", failed.len(), failed);
+        }
+        summary.durations_ms.prune = Some(elapsed_ms(started));
+        summary.pruned = deleted;
+        summary.prune_failed = failed
+            .into_iter()
+            .map(|(tag, error)| PruneFailure { tag, error })
+            .collect();
+    }
+
+    Ok(())
+}
+
+/// Pause between attempts of the health command.
+const HEALTH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Runs the health command until it succeeds, giving up once `timeout` has passed.
+fn wait_healthy(cmd: &str, timeout: Duration, events: &Events) -> anyhow::Result<()> {
+    let _span = info_span!("health", command = %cmd).entered();
+    let phase = events.phase("health");
+    let started = Instant::now();
+    let result = loop {
+        match restart::execute(cmd) {
+            Ok(()) => break Ok(()),
+            Err(e) if started.elapsed() + HEALTH_INTERVAL > timeout => break Err(e),
+            Err(e) => {
+                debug!("Health check not passing yet: {e}");
+                std::thread::sleep(HEALTH_INTERVAL);
+            }
+        }
+    };
+    phase.record(&result);
+    result.map_err(|error| restart::PostInstallError::Health {
+        timeout_secs: timeout.as_secs(),
+        error,
+    })?;
+    info!("Health check passed");
+    Ok(())
+}
+
+/// How an `update` run ended.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum UpdateStatus {
+    #[default]
+    Updated,
+    UpToDate,
+    /// The latest release is blocked by `--skip-version` or `--allow-versions`.
+    Blocked,
+    Downloaded,
+    /// Only reported to `--notify-url`; a failed run prints an error instead.
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+struct RestartOutcome {
+    command: String,
+    succeeded: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct PruneFailure {
+    tag: String,
+    error: String,
+}
+
+/// Wall-clock time spent in each phase of an update, in milliseconds.
+#[derive(Debug, Default, Serialize)]
+struct PhaseDurations {
+    resolve: Option<u64>,
+    download: Option<u64>,
+    install: Option<u64>,
+    restart: Option<u64>,
+    prune: Option<u64>,
+    total: u64,
+}
+
+/// The result of an `update` run, printed by `--output json`.
+#[derive(Debug, Default, Serialize)]
+struct UpdateSummary {
+    app: String,
+    status: UpdateStatus,
+    previous_tag: Option<String>,
+    tag: Option<String>,
+    asset: Option<String>,
+    /// The mirror the asset came from, if not the source.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mirror: Option<String>,
+    sha256: Option<String>,
+    /// Why the latest release was not installed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blocked: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<Utf8PathBuf>,
+    pruned: Vec<String>,
+    prune_failed: Vec<PruneFailure>,
+    restart: Option<RestartOutcome>,
+    durations_ms: PhaseDurations,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl UpdateSummary {
+    /// Prints `text` in text mode (unless `--quiet`), or the summary as JSON or as
+    /// the final event, finishing the total duration from `started`.
+    fn report(
+        &mut self,
+        args: &Args,
+        update_args: &UpdateArgs,
+        started: Instant,
+        text: &str,
+    ) -> anyhow::Result<()> {
+        self.durations_ms.total = elapsed_ms(started);
+        if update_args.events.is_some() {
+            Events::new(update_args.events).result(self)?;
+            return Ok(());
+        }
+        match update_args.output {
+            OutputFormat::Text => args.say(text),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(self)?),
+        }
+        Ok(())
+    }
+}
+
+fn elapsed_ms(started: Instant) -> u64 {
+    u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Where GitHub responses that could not be parsed are saved for `app`.
+fn debug_dir(state_directory: &Utf8Path, app: &str) -> Utf8PathBuf {
+    state_directory.join(app).join("debug")
+}
+
+/// Describes a rate-limit budget as `<remaining>/<limit> remaining, resets at <time>`.
+fn describe_rate_limit(rate_limit: &github::RateLimit) -> String {
+    let mut description = match rate_limit.limit {
+        Some(limit) => format!("{}/{limit} remaining", rate_limit.remaining),
+        None => format!("{} remaining", rate_limit.remaining),
+    };
+    if let Some(reset) = rate_limit.reset {
+        description.push_str(&format!(", resets at {}", format_time(reset)));
+    }
+    description
+}
+
+/// Prints the one-line text summary of a `check` result, where `blocked` says
+/// why the latest release will not be installed.
+/// With `quiet`, only available updates are printed.
+fn print_check_result(
+    current_tag: Option<&str>,
+    release: Option<&github::Release>,
+    blocked: Option<&BlockReason>,
+    held: bool,
+    quiet: bool,
+) {
+    if let (Some(reason), Some(release)) = (blocked, release) {
+        match current_tag {
+            _ if quiet => {}
+            Some(current) => println!(
+                "update-blocked: {current} -> {} ({reason})",
+                release.tag_name
+            ),
+            None => println!("install-blocked: {} ({reason})", release.tag_name),
+        }
+        return;
+    }
+
+    match (current_tag, release) {
+        (Some(current), None) => {
+            if !quiet {
+                println!("up-to-date: {current}");
+            }
+        }
+        (Some(current), Some(release)) => {
+            if current == release.tag_name {
+                if !quiet {
+                    println!("up-to-date: {current}");
+                }
+            } else if held {
+                println!("update-held: {} -> {}", current, release.tag_name);
+            } else {
+                println!("update-available: {} -> {}", current, release.tag_name);
+            }
+        }
+        (None, Some(release)) => {
+            println!("install-available: {}", release.tag_name);
+        }
+        (None, None) => {
+            if !quiet {
+                println!("No version installed");
+            }
+        }
+    }
+}
+
+/// Compares the files of the installed `tag` against the manifest assets its
+/// release publishes.
+async fn check_drift(
+    args: &Args,
+    check_args: &CheckArgs,
+    app: &str,
+    tag: &str,
+    http_client: reqwest::Client,
+) -> anyhow::Result<manifest::Drift> {
+    let token = check_args.github.token();
+    let release = check_args
+        .github
+        .fetch_tag(
+            check_args.repo()?,
+            tag,
+            Some(&debug_dir(&check_args.state_directory, app)),
+            http_client.clone(),
+        )
+        .await?;
+
+    let pattern = Regex::new(&check_args.manifest_pattern)?;
+    let assets: Vec<_> = release
+        .assets
+        .iter()
+        .filter(|asset| pattern.is_match(&asset.name))
+        .collect();
+    ensure!(
+        !assets.is_empty(),
+        "Release {tag} has no manifest asset matching '{pattern}'"
+    );
+
+    let mut expected = manifest::Manifest::new();
+    for asset in assets {
+        let text = manifest::fetch_text(&asset.url, token, http_client.clone()).await?;
+        expected.extend(manifest::parse(&asset.name, &text)?);
+    }
+    let release_dir = args.install_root().join(app).join("releases").join(tag);
+    Ok(manifest::compare(&release_dir, &expected)?)
+}
+
+/// Prints the result of `check --deep`; drift is printed even with `--quiet`.
+fn print_drift(args: &Args, drift: &manifest::Drift, tag: &str) {
+    if drift.is_clean() {
+        args.say(format_args!(
+            "manifest: {} file(s) match {tag}",
+            drift.verified
+        ));
+        return;
+    }
+    println!(
+        "drift: {} file(s) differ from the {tag} manifest",
+        drift.modified.len() + drift.missing.len()
+    );
+    for path in &drift.modified {
+        println!("  modified: {path}");
+    }
+    for path in &drift.missing {
+        println!("  missing:  {path}");
+    }
+}
+
+/// Handles the `check` subcommand to query for updates without installing.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - State file cannot be read or written
+/// - GitHub API request fails
+/// - Network errors occur
+/// - The change record cannot be written
+/// - The JSON report cannot be serialized
+///
+/// On success, returns [`pin::EXIT_PINNED`] if the app is pinned,
+/// [`CheckArgs::exit_code_on_update`] if an update is available, and
+/// [`ExitCode::SUCCESS`] otherwise.
+pub async fn handle_check(
+    args: &Args,
+    check_args: &CheckArgs,
+    http_client: reqwest::Client,
+) -> anyhow::Result<ExitCode> {
+    let app = args.app()?;
+    let install_root = args.install_root();
+    let repo = check_args.repo()?;
+    let host = check_args.github.host();
+    let http_client = check_args
+        .github
+        .source_client(&args.http_settings(), http_client)?;
+    let store = args.state_store(&check_args.state_directory);
+    let mut existing_state = store.load(app)?;
+
+    if let Some(state) = existing_state.as_ref()
+        && state.source_changed(repo, host)
+    {
+        warn!(
+            "{app} was installed from {} but is now configured for {repo} on {host}",
+            state.source()
+        );
+        existing_state = None;
+    }
+
+    // A change record, release notes, and the JSON report need the full release,
+    // so skip the conditional request.
+    let needs_release = check_args.export_change_record.is_some()
+        || check_args.show_notes
+        || check_args.output == OutputFormat::Json;
+    let validators = if let Some(state) = existing_state.as_ref().filter(|_| !needs_release) {
+        github::Validators {
+            etag: Some(state.etag.clone()),
+            last_modified: state.if_modified_since(),
+        }
+    } else {
+        github::Validators {
+            etag: None,
+            last_modified: None,
+        }
+    };
+
+    let fetch_result = check_args
+        .github
+        .fetch_latest(
+            repo,
+            validators,
+            Some(&debug_dir(&check_args.state_directory, app)),
+            http_client.clone(),
+        )
+        .await?;
+    // The validators check sends are the installed release's, so update could not
+    // tell a new release from a 304 without this.
+    cache_release(
+        &check_args.state_directory,
+        app,
+        check_args.github.release_query(repo),
+        &fetch_result,
+    );
+
+    let current_tag = version::current_tag(install_root, app)?;
+    let pin = pin::load(&check_args.state_directory, app)?;
+    let latest_tag = fetch_result
+        .release
+        .as_ref()
+        .map(|release| release.tag_name.as_str());
+    let version_filter = check_args.github.version_filter()?;
+    // A blocked release is reported, but is no update that update would install.
+    let blocked = latest_tag
+        .filter(|&tag| Some(tag) != current_tag.as_deref())
+        .and_then(|tag| version_filter.blocked(tag));
+    let newer = latest_tag.is_some() && latest_tag != current_tag.as_deref() && blocked.is_none();
+    // update refuses to run while pinned, so a newer release is held rather than available.
+    let held = newer && pin.is_some();
+    let update_available = newer && !held;
+    // What update would download, so large updates can be scheduled deliberately.
+    let download = match fetch_result.release.as_ref().filter(|_| update_available) {
+        Some(release) => {
+            let asset_pattern = check_args
+                .pattern()
+                .map(|pattern| Regex::new(&pattern))
+                .transpose()?;
+            let asset = github::select_asset_for(
+                &release.assets,
+                asset_pattern.as_ref(),
+                &check_args.platform(),
+            );
+            if asset.is_none() {
+                debug!(
+                    "No asset of {} is selected; download size unknown",
+                    release.tag_name
+                );
+            }
+            asset
+        }
+        None => None,
+    };
+    let notes = fetch_result
+        .release
+        .as_ref()
+        .filter(|_| check_args.show_notes && update_available)
+        .map(|release| {
+            change_record::plain_notes(release.body.as_deref().unwrap_or_default(), NOTES_MAX_LINES)
+        });
+
+    let drift = match current_tag.as_deref() {
+        Some(tag) if check_args.deep => {
+            Some(check_drift(args, check_args, app, tag, http_client).await?)
+        }
+        None if check_args.deep => {
+            warn!("No version of {app} installed; skipping --deep");
+            None
+        }
+        _ => None,
+    };
+    let drifted = drift.as_ref().is_some_and(|drift| !drift.is_clean());
+
+    match check_args.output {
+        OutputFormat::Text => {
+            print_check_result(
+                current_tag.as_deref(),
+                fetch_result.release.as_ref(),
+                blocked.as_ref(),
+                held,
+                args.quiet,
+            );
+            if let Some(asset) = download {
+                println!("  download: {} ({})", asset.name, format_size(asset.size));
+            }
+            if let Some(notes) = &notes {
+                if notes.is_empty() {
+                    println!("  notes: (none)");
+                } else {
+                    println!("  notes:");
+                    for line in notes.lines() {
+                        if line.is_empty() {
+                            println!();
+                        } else {
+                            println!("    {line}");
+                        }
+                    }
+                }
+            }
+            if let Some(pin) = &pin {
+                println!("pinned: {}", pin.describe());
+            }
+            if args.verbose > 0
+                && let Some(rate_limit) = &fetch_result.rate_limit
+            {
+                println!("rate-limit: {}", describe_rate_limit(rate_limit));
+            }
+            if let (Some(drift), Some(tag)) = (&drift, current_tag.as_deref()) {
+                print_drift(args, drift, tag);
+            }
+        }
+        OutputFormat::Json => {
+            let release = fetch_result.release.as_ref();
+            let mut report = serde_json::json!({
+                "app": app,
+                "repo": repo,
+                "installed_tag": current_tag,
+                "latest_tag": latest_tag,
+                "update_available": update_available,
+                "held": held,
+                "blocked": blocked.as_ref().map(ToString::to_string),
+                "etag": fetch_result
+                    .validators
+                    .etag
+                    .as_deref()
+                    .or(existing_state.as_ref().map(|state| state.etag.as_str())),
+                "release_date": release.and_then(|release| release.published_at.or(release.created_at)),
+                "assets": release
+                    .map(|release| release.assets.iter().map(|asset| asset.name.as_str()).collect::<Vec<_>>())
+                    .unwrap_or_default(),
+                "download": download.map(|asset| serde_json::json!({
+                    "asset": asset.name,
+                    "size": asset.size,
+                })),
+                "pinned": pin,
+                "rate_limit": fetch_result.rate_limit,
+                "drift": drift,
+            });
+            if check_args.show_notes {
+                report["notes"] = notes.into();
+            }
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+    }
+
+    if let Some(path) = check_args.export_change_record.as_deref() {
+        match fetch_result.release.as_ref() {
+            Some(release) if update_available => {
+                ChangeRecord::new(app, repo, current_tag.as_deref(), release).write(path)?;
+                info!("Wrote change record to {path}");
+            }
+            _ => info!("No update available; change record not written"),
+        }
+    }
+
+    if let (Some(_current), Some(existing)) = (current_tag, existing_state) {
+        let etag_changed = fetch_result
+            .validators
+            .etag
+            .as_deref()
+            .is_none_or(|etag| !github::etag_matches(etag, &existing.etag));
+        let last_mod_changed = fetch_result
+            .validators
+            .last_modified
+            .as_ref()
+            .is_some_and(|last_modified| *last_modified != existing.last_modified);
+
+        if etag_changed || last_mod_changed {
+            let updated_state = State {
+                latest_tag: existing.latest_tag,
+                etag: fetch_result.validators.etag.unwrap_or(existing.etag),
+                last_modified: fetch_result
+                    .validators
+                    .last_modified
+                    .unwrap_or(existing.last_modified),
+                installed_at: existing.installed_at,
+                previous_tag: existing.previous_tag,
+                repo: existing.repo,
+                host: existing.host,
+            };
+            store.save(app, &updated_state)?;
+        }
+    }
+
+    ensure!(
+        !drifted,
+        "Installed files of {app} differ from the release manifest"
+    );
+    Ok(if pin.is_some() {
+        ExitCode::from(pin::EXIT_PINNED)
+    } else if update_available {
+        ExitCode::from(check_args.exit_code_on_update)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Handles the `update` subcommand to download, verify, extract, and install a new release.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Lock acquisition fails (another update in progress)
+/// - GitHub API request fails
+/// - No matching asset found for the pattern
+/// - Download fails or times out
+/// - Checksum verification fails
+/// - Archive extraction fails
+/// - Filesystem operations fail (staging, moving, symlinking)
+/// - Restart command fails (after successful installation)
+pub async fn handle_update(
+    args: &Args,
+    update_args: &UpdateArgs,
+    http_client: reqwest::Client,
+) -> anyhow::Result<()> {
+    let notifier = update_args.notify.notifier()?;
+    let mut summary = UpdateSummary {
+        app: args.app()?.to_string(),
+        ..UpdateSummary::default()
+    };
+    let result = run_update(args, update_args, http_client.clone(), &mut summary).await;
+
+    let Some(notifier) = notifier.filter(|_| !update_args.previews()) else {
+        return result;
+    };
+    match &result {
+        Err(e) if e.is::<PinnedError>() => return result,
+        Err(e) => {
+            summary.status = UpdateStatus::Failed;
+            summary.error = Some(format!("{e:#}"));
+        }
+        Ok(())
+            if matches!(
+                summary.status,
+                UpdateStatus::UpToDate | UpdateStatus::Blocked
+            ) =>
+        {
+            return result;
+        }
+        Ok(()) => {}
+    }
+    let mut context = serde_json::to_value(&summary)?;
+    context["hostname"] = cluster::hostname().into();
+    // The update itself is done (or has failed for its own reasons); a webhook
+    // outage must not turn it into a failure.
+    if let Err(e) = notifier.send(&http_client, &context).await {
+        warn!(
+            "Failed to send notification to {}: {e}",
+            notifier.url.host_str().unwrap_or_default()
+        );
+    }
+    result
+}
+
+async fn run_update(
+    args: &Args,
+    update_args: &UpdateArgs,
+    http_client: reqwest::Client,
+    summary: &mut UpdateSummary,
+) -> anyhow::Result<()> {
+    let app = args.app()?;
+    let install_root = args.install_root();
+    let repo = update_args.repo()?;
+    let host = update_args.github.host();
+    let http_client = update_args
+        .github
+        .source_client(&args.http_settings(), http_client)?;
+    let fetcher = Fetcher {
+        github: &update_args.github,
+        http: args.http_settings(),
+        client: http_client.clone(),
+        mirrors: &update_args.mirrors,
+        limits: args.transfer_limits(&update_args.github),
+        network_user: update_args.network_user()?,
+    };
+    if let Some(user) = &fetcher.network_user {
+        debug!("Making network requests as {}", user.name);
+    }
+    let plan = update_args
+        .apply_plan
+        .as_deref()
+        .map(plan::load)
+        .transpose()?;
+    let asset_pattern = update_args
+        .pattern()
+        .map(|pattern| Regex::new(&pattern))
+        .transpose()?;
+    // A plan names its assets exactly.
+    let extra_patterns = match &plan {
+        Some(plan) => plan
+            .extra_assets
+            .iter()
+            .map(|extra| Ok((Regex::new(&exact_name_pattern(&extra.name))?, extra.dest()?)))
+            .collect::<anyhow::Result<Vec<_>>>()?,
+        None => update_args
+            .extra_assets
+            .iter()
+            .map(|extra| Ok((Regex::new(&pattern::expand(&extra.pattern))?, &extra.dest)))
+            .collect::<anyhow::Result<Vec<_>>>()?,
+    };
+    let checksum_pattern = update_args
+        .checksum_pattern()?
+        .map(Regex::new)
+        .transpose()?;
+    let verification = match checksum_pattern.as_ref() {
+        _ if update_args.skip_verification => Verification::Skip,
+        Some(checksum_regex) => Verification::ChecksumAsset(checksum_regex),
+        None => Verification::AssetDigest,
+    };
+    let retain = update_args.retain();
+
+    let _span = info_span!("update", app = %app, repo = %repo).entered();
+    let events = Events::new(update_args.events);
+    let started = Instant::now();
+
+    if retain == 0 && !update_args.previews() && update_args.download_only.is_none() {
+        confirm(
+            args,
+            &format!(
+                "--retain 0 will delete every release of {app} except the active one. Continue?"
+            ),
+        )?;
+    }
+
+    if update_args.force_unlock && !update_args.previews() {
+        confirm(
+            args,
+            &format!("Forcibly remove the lock for {app}? A running update may be disrupted."),
+        )?;
+        info!("Force unlock requested, removing lock file");
+        lock::unlock(app, Some(&update_args.state_directory))?;
+    }
+
+    let timeout = Duration::from_secs(update_args.lock_timeout);
+    let lock_guard = if update_args.previews() {
+        None
+    } else {
+        Some(lock::acquire(
+            app,
+            Some(&update_args.state_directory),
+            Some(timeout),
+        )?)
+    };
+
+    // Pre-staging an artifact leaves the installed release alone, so a pin does not block it.
+    if update_args.download_only.is_none()
+        && let Some(pin) = pin::load(&update_args.state_directory, app)?
+    {
+        return Err(PinnedError {
+            app: app.to_string(),
+            pin,
+        }
+        .into());
+    }
+
+    if let Some(max_age) = update_args
+        .staging_max_age
+        .filter(|_| !update_args.previews())
+    {
+        let staging_dir = install_root.join(app).join("staging");
+        let (removed, failed) =
+            fsops::remove_stale_staging(&staging_dir, Duration::from_secs(max_age))?;
+        if !removed.is_empty() {
+            info!("Removed {} stale staging director(ies)", removed.len());
+        }
         if !failed.is_empty() {
-            warn!("Failed to prune {} release(s): {:?}", failed.len(), failed);
+            warn!(
+                "Failed to remove {} staging director(ies): {:?}",
+                failed.len(),
+                failed
+            );
+        }
+    }
+
+    let store = args.state_store(&update_args.state_directory);
+    if lock_guard.is_some() {
+        recover_pending(app, install_root, &update_args.state_directory, &store)?;
+    }
+    let mut existing_state = store.load(app)?;
+
+    // A new source brings a new publisher, so its identity is trusted afresh.
+    let mut accept_new_publisher = update_args.accept_new_publisher;
+    if let Some(state) = existing_state.as_ref()
+        && state.source_changed(repo, host)
+    {
+        ensure!(
+            update_args.accept_new_source,
+            "{app} was installed from {} but is now configured for {repo} on {host}; \
+             pass --accept-new-source if this change is intended",
+            state.source()
+        );
+        warn!(
+            "Switching {app} from {} to {repo} on {host}",
+            state.source()
+        );
+        existing_state = None;
+        accept_new_publisher = true;
+    }
+
+    let current_tag = version::current_tag(install_root, app)?;
+    summary.previous_tag.clone_from(&current_tag);
+    if let Some(plan) = &plan {
+        plan.check_source(app, repo, host, current_tag.as_deref())?;
+        plan.check_commands(
+            update_args.restart_command.as_deref(),
+            update_args.health_command.as_deref(),
+        )?;
+    }
+
+    let version_filter = update_args.github.version_filter()?;
+    let resolve_started = Instant::now();
+    let requested_tag = match &plan {
+        Some(plan) => Some(plan.tag.as_str()),
+        None => update_args.tag.as_deref(),
+    };
+    let fetch_result = if let Some(tag) = requested_tag {
+        ensure!(
+            Utf8Path::new(tag).file_name() == Some(tag),
+            "invalid release tag: {tag}"
+        );
+        if current_tag.as_deref() == Some(tag) {
+            summary.status = UpdateStatus::UpToDate;
+            summary.tag = Some(tag.to_string());
+            return summary.report(
+                args,
+                update_args,
+                started,
+                &format!("Already installed: {tag}"),
+            );
+        }
+        version_filter.check(tag)?;
+
+        let phase = events.phase("fetch");
+        let result = fetcher
+            .tag(
+                repo,
+                tag,
+                Some(&debug_dir(&update_args.state_directory, app)),
+            )
+            .await;
+        phase.record(&result);
+        let release = result?;
+
+        // Validators from the tag endpoint say nothing about /releases/latest, so
+        // record none and let the next untagged update make a full request.
+        github::FetchResult {
+            release: Some(release),
+            validators: github::ValidatorsOut {
+                etag: None,
+                last_modified: None,
+                max_age: None,
+            },
+            was_modified: true,
+            rate_limit: None,
+        }
+    } else {
+        let query = update_args.github.release_query(repo);
+        let cached = cached_release(&update_args.state_directory, app, &query);
+        let fetch_result = match cached {
+            Some(cached) if cached.is_fresh(Timestamp::now()) => {
+                debug!(
+                    "Using the cached release {} of {app} without asking {}",
+                    cached.release.tag_name,
+                    update_args.github.host()
+                );
+                cached.into_fetch_result()
+            }
+            cached => {
+                // The cache's validators describe the release it holds, so a 304
+                // means that release, whatever was installed.
+                let validators = match (&cached, existing_state.as_ref()) {
+                    (Some(cached), _) => cached.validators(),
+                    (None, Some(state)) => github::Validators {
+                        etag: Some(state.etag.clone()),
+                        last_modified: state.if_modified_since(),
+                    },
+                    (None, None) => github::Validators {
+                        etag: None,
+                        last_modified: None,
+                    },
+                };
+
+                let phase = events.phase("fetch");
+                let result = fetcher
+                    .latest(
+                        repo,
+                        validators,
+                        Some(&debug_dir(&update_args.state_directory, app)),
+                    )
+                    .await;
+                phase.record(&result);
+                let mut fetch_result = result?;
+
+                match cached {
+                    Some(mut cached) if fetch_result.release.is_none() => {
+                        let validators = &mut fetch_result.validators;
+                        validators.etag = validators.etag.take().or(cached.etag.clone());
+                        validators.last_modified = validators
+                            .last_modified
+                            .take()
+                            .or(cached.last_modified.clone());
+                        cached.revalidated(Timestamp::now(), validators.max_age);
+                        if !update_args.previews()
+                            && let Err(e) =
+                                release_cache::save(&update_args.state_directory, app, &cached)
+                        {
+                            warn!("Failed to cache the release of {app}: {e}");
+                        }
+                        fetch_result.release = Some(cached.release);
+                    }
+                    _ if !update_args.previews() => {
+                        cache_release(&update_args.state_directory, app, query, &fetch_result);
+                    }
+                    _ => {}
+                }
+                fetch_result
+            }
+        };
+
+        if is_up_to_date(
+            current_tag.as_ref(),
+            fetch_result.release.as_ref(),
+            existing_state.as_ref(),
+            fetch_result.was_modified,
+        ) {
+            summary.status = UpdateStatus::UpToDate;
+            summary.tag.clone_from(&current_tag);
+            summary.durations_ms.resolve = Some(elapsed_ms(resolve_started));
+            let text = match current_tag.as_ref() {
+                Some(tag) if lock_guard.as_ref().is_some_and(lock::LockGuard::waited) => {
+                    format!("Another update of {app} just completed, now up-to-date: {tag}")
+                }
+                Some(tag) => format!("Already up-to-date: {tag}"),
+                None => return Ok(()),
+            };
+            return summary.report(args, update_args, started, &text);
+        }
+
+        fetch_result
+    };
+
+    let mut release = fetch_result
+        .release
+        .ok_or_else(|| anyhow!("No release available"))?;
+    let platform = update_args.platform();
+    let asset_pattern = match (&plan, asset_pattern) {
+        // A plan names its asset exactly.
+        (Some(plan), _) => Regex::new(&exact_name_pattern(&plan.asset.name))?,
+        (None, Some(asset_pattern)) => {
+            match github::SourceArchive::from_pattern(asset_pattern.as_str()) {
+                Some(archive) => archive.select(repo, &mut release)?,
+                // Settled here, so every later step takes the build for `platform`.
+                None => {
+                    match github::select_asset_for(&release.assets, Some(&asset_pattern), &platform)
+                    {
+                        Some(asset) => Regex::new(&exact_name_pattern(&asset.name))?,
+                        None => asset_pattern,
+                    }
+                }
+            }
+        }
+        (None, None) => {
+            let asset =
+                github::select_asset_for(&release.assets, None, &platform).ok_or_else(|| {
+                    github::GithubError::NoPlatformAsset {
+                        tag: release.tag_name.clone(),
+                        platform,
+                    }
+                })?;
+            info!("No --pattern given; selected {} for {platform}", asset.name);
+            Regex::new(&exact_name_pattern(&asset.name))?
+        }
+    };
+    let tag = &release.tag_name;
+    summary.durations_ms.resolve = Some(elapsed_ms(resolve_started));
+    if let Some(reason) = version_filter.blocked(tag) {
+        summary.status = UpdateStatus::Blocked;
+        summary.tag.clone_from(&current_tag);
+        summary.blocked = Some(format!("{tag} {reason}"));
+        let text = match current_tag.as_deref() {
+            Some(current) => {
+                format!("Latest release {tag} is blocked ({reason}); keeping {current}")
+            }
+            None => format!("Latest release {tag} is blocked ({reason}); nothing installed"),
+        };
+        return summary.report(args, update_args, started, &text);
+    }
+    summary.tag = Some(tag.clone());
+
+    if let Some(current) = current_tag.as_deref() {
+        let prefix = update_args.github.version_prefix.as_deref();
+        match constraint::check_downgrade(current, tag, prefix) {
+            Err(_) if update_args.allow_downgrade => {
+                if !update_args.previews() {
+                    confirm(args, &format!("Downgrade {app} from {current} to {tag}?"))?;
+                }
+                warn!("Downgrading {app} from {current} to {tag}");
+            }
+            result => result?,
+        }
+    }
+
+    let new_publisher = check_publisher(
+        app,
+        &update_args.state_directory,
+        &release,
+        &asset_pattern,
+        &verification,
+        update_args.trust_policy.unwrap_or_default(),
+        accept_new_publisher,
+    )?;
+
+    if update_args.previews() {
+        let releases_dir = install_root.join(app).join("releases");
+        let asset =
+            github::select_asset(&release.assets, Some(&asset_pattern)).ok_or_else(|| {
+                github::NoMatchingAsset::new("asset", &asset_pattern, &release.tag_name)
+            })?;
+        let expected = expected_digest(&fetcher, &release, asset, verification).await?;
+        let extras = extra_patterns
+            .iter()
+            .map(|(pattern, dest)| {
+                let extra = github::select_asset(&release.assets, Some(pattern))
+                    .ok_or_else(|| anyhow!("No asset matching extra asset pattern {pattern}"))?;
+                Ok((extra, *dest))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        // The archives' sizes stand in for the size of the release they unpack to.
+        let incoming_size = asset.size + extras.iter().map(|(extra, _)| extra.size).sum::<u64>();
+        let prune = fsops::releases_to_prune_after(
+            &releases_dir,
+            tag,
+            incoming_size,
+            update_args.retention(),
+        )?;
+
+        if update_args.dry_run {
+            println!(
+                "Dry run: would update {app} from {} to {tag}",
+                current_tag.as_deref().unwrap_or("(none)")
+            );
+            println!(
+                "  download: {} ({}) from {}",
+                asset.name,
+                format_size(asset.size),
+                asset.browser_download_url
+            );
+            if !update_args.mirrors.is_empty() {
+                println!("  mirrors:  {}", update_args.mirrors.join(", "));
+            }
+            match &expected {
+                Some((sha256, source)) => println!("  verify:   sha256 {sha256} (from {source})"),
+                None => println!("  verify:   skipped (--skip-verification)"),
+            }
+            if let Some(cmd) = update_args.scan_command.as_deref() {
+                println!("  scan:     {cmd}");
+            }
+            println!("  extract:  {}", releases_dir.join(tag));
+            for (extra, dest) in &extras {
+                println!(
+                    "  extract:  {} ({}) into {}",
+                    extra.name,
+                    format_size(extra.size),
+                    releases_dir.join(tag).join(dest)
+                );
+            }
+            println!(
+                "  link:     executables in {} into {}",
+                releases_dir.join(tag),
+                install_root.join(app).join("bin")
+            );
+            if let Some(max_depth) = update_args.max_link_depth {
+                println!(
+                    "  relocate: executables more than {max_depth} directories deep into {}",
+                    releases_dir.join(tag).join(fsops::LIBEXEC_DIR)
+                );
+            }
+            if prune.is_empty() {
+                println!("  prune:    (nothing)");
+            } else {
+                println!("  prune:    {}", prune.join(", "));
+            }
+            println!(
+                "  restart:  {}",
+                update_args.restart_command.as_deref().unwrap_or("(none)")
+            );
+            if let Some(cmd) = update_args.health_command.as_deref() {
+                println!("  health:   {cmd}");
+            }
+            if let Some(backend) = update_args.cluster.backend.as_deref() {
+                println!(
+                    "  cluster:  {backend} ({} slot(s))",
+                    update_args.cluster.slots.unwrap_or(1)
+                );
+            }
+            if let Some(url) = update_args.notify.url.as_deref() {
+                println!("  notify:   {url}");
+            }
+            return Ok(());
+        }
+
+        let release_dir = releases_dir.join(tag);
+        let bin_dir = install_root.join(app).join("bin");
+        let mut steps = vec![plan::Step::new(
+            "download",
+            &asset.browser_download_url,
+            &asset.name,
+        )];
+        steps.extend(extras.iter().map(|(extra, _)| {
+            plan::Step::new("download", &extra.browser_download_url, &extra.name)
+        }));
+        steps.push(match &expected {
+            Some((sha256, source)) => plan::Step::new("verify", source, format!("sha256 {sha256}")),
+            None => plan::Step::new("verify", "(skipped)", "(none)"),
+        });
+        if let Some(cmd) = update_args.scan_command.as_deref() {
+            steps.push(plan::Step::new("scan", cmd, &asset.name));
+        }
+        steps.push(plan::Step::new("extract", &asset.name, &release_dir));
+        steps.extend(
+            extras.iter().map(|(extra, dest)| {
+                plan::Step::new("extract", &extra.name, release_dir.join(dest))
+            }),
+        );
+        if let Some(max_depth) = update_args.max_link_depth {
+            steps.push(plan::Step::new(
+                "relocate",
+                format!("executables more than {max_depth} directories deep"),
+                release_dir.join(fsops::LIBEXEC_DIR),
+            ));
+        }
+        steps.push(plan::Step::new("link", &release_dir, &bin_dir));
+        if let Some(cmd) = update_args.restart_command.as_deref() {
+            steps.push(plan::Step::new("restart", cmd, "exit status 0"));
+        }
+        if let Some(cmd) = update_args.health_command.as_deref() {
+            steps.push(plan::Step::new("health", cmd, "exit status 0"));
+        }
+        if !prune.is_empty() {
+            steps.push(plan::Step::new("prune", &releases_dir, prune.join(", ")));
+        }
+        // Extra assets are verified against the same checksum file or feed.
+        let mut extra_assets = Vec::with_capacity(extras.len());
+        for (extra, dest) in &extras {
+            let sha256 = expected_digest(&fetcher, &release, extra, verification).await?;
+            extra_assets.push(plan::PlannedAsset {
+                name: extra.name.clone(),
+                size: extra.size,
+                url: extra.browser_download_url.clone(),
+                sha256: sha256.map(|(sha256, _)| sha256),
+                dest: Some((*dest).clone()),
+            });
+        }
+
+        let planned = plan::Plan {
+            format_version: plan::FORMAT_VERSION,
+            app: app.to_string(),
+            repo: repo.to_string(),
+            host: host.to_string(),
+            created_at: Timestamp::now(),
+            from_tag: current_tag.clone(),
+            tag: tag.clone(),
+            asset: plan::PlannedAsset {
+                name: asset.name.clone(),
+                size: asset.size,
+                url: asset.browser_download_url.clone(),
+                sha256: expected.map(|(sha256, _)| sha256),
+                dest: None,
+            },
+            extra_assets,
+            restart_command: update_args.restart_command.clone(),
+            health_command: update_args.health_command.clone(),
+            prune,
+            steps,
+        };
+        match update_args.plan_file.as_deref() {
+            Some(path) => {
+                plan::save(path, &planned)?;
+                args.say(format_args!(
+                    "Wrote plan to update {app} from {} to {tag} to {path}",
+                    current_tag.as_deref().unwrap_or("(none)")
+                ));
+            }
+            None => println!("{}", serde_json::to_string_pretty(&planned)?),
+        }
+        return Ok(());
+    }
+
+    if let Some(plan) = &plan {
+        plan.check_prune(&fsops::releases_to_prune_after(
+            install_root.join(app).join("releases"),
+            tag,
+            plan.incoming_size(),
+            update_args.retention(),
+        )?)?;
+    }
+
+    info!("Updating to {tag}");
+
+    let download_started = Instant::now();
+    let (downloaded_file, asset_name, mirror) = download_and_verify_asset(
+        &fetcher,
+        &release,
+        &asset_pattern,
+        verification,
+        update_args.scan_command.as_deref(),
+        &events,
+    )
+    .await?;
+    let asset_sha256 = verify::sha256_file(downloaded_file.path())?;
+    if let Some(plan) = &plan {
+        plan.asset.check(
+            &asset_name,
+            fs::metadata(downloaded_file.path())?.len(),
+            &asset_sha256,
+        )?;
+    }
+    summary.durations_ms.download = Some(elapsed_ms(download_started));
+    summary.asset = Some(asset_name.clone());
+    summary.mirror = mirror;
+    summary.sha256 = Some(asset_sha256.clone());
+
+    let mut extras = Vec::with_capacity(extra_patterns.len());
+    for (pattern, dest) in &extra_patterns {
+        let (file, name, _) = download_and_verify_asset(
+            &fetcher,
+            &release,
+            pattern,
+            verification,
+            update_args.scan_command.as_deref(),
+            &events,
+        )
+        .await
+        .with_context(|| format!("Failed to fetch the extra asset for {dest}/"))?;
+        if let Some(planned) = plan
+            .as_ref()
+            .and_then(|plan| plan.extra_assets.get(extras.len()))
+        {
+            planned.check(
+                &name,
+                fs::metadata(file.path())?.len(),
+                &verify::sha256_file(file.path())?,
+            )?;
+        }
+        extras.push(ExtraDownload {
+            file,
+            name,
+            dest: dest.as_path().to_path_buf(),
+        });
+    }
+
+    if let Some(download_dir) = update_args.download_only.as_deref() {
+        fs::create_dir_all(download_dir)?;
+        let files = std::iter::once((&downloaded_file, &asset_name))
+            .chain(extras.iter().map(|extra| (&extra.file, &extra.name)));
+        for (file, name) in files {
+            let temp_file = NamedUtf8TempFile::new_in(download_dir)?;
+            fs::copy(file.path(), temp_file.path())?;
+            temp_file.as_file().sync_all()?;
+            temp_file
+                .persist(download_dir.join(name))
+                .map_err(|e| e.error)?;
+        }
+        File::open(download_dir)?.sync_all()?;
+        let dest = download_dir.join(&asset_name);
+        summary.status = UpdateStatus::Downloaded;
+        let text = format!("Downloaded {tag} to {dest} (sha256 {asset_sha256})");
+        summary.path = Some(dest);
+        return summary.report(args, update_args, started, &text);
+    }
+
+    // Hosts download in parallel but take turns switching, restarting, and passing
+    // the health check.
+    let lease = match update_args.cluster.coordinator(app)? {
+        Some(coordinator) => Some(
+            coordinator
+                .acquire(
+                    &http_client,
+                    Duration::from_secs(update_args.cluster.timeout),
+                )
+                .await?,
+        ),
+        None => None,
+    };
+
+    let bin_dir = install_root.join(app).join("bin");
+    let links_before = bin_diff::snapshot(&bin_dir)?;
+
+    // Staged before the symlinks switch so that a crash before the commit below is
+    // finished (or discarded) by the next run rather than leaving stale state.
+    let staged = Pending {
+        state: installed_state(
+            tag,
+            current_tag.as_deref(),
+            &fetch_result.validators,
+            repo,
+            host,
+        ),
+        history: HistoryEntry {
+            tag: tag.clone(),
+            installed_at: Timestamp::now(),
+            asset: asset_name.clone(),
+            sha256: asset_sha256,
+            previous_tag: current_tag.clone(),
+        },
+    };
+    pending::save(&update_args.state_directory, app, &staged)?;
+
+    let install_started = Instant::now();
+    if let Err(e) = install_release(
+        install_root,
+        app,
+        tag,
+        current_tag.as_deref(),
+        &downloaded_file,
+        &asset_name,
+        &InstallOptions {
+            overwrite_existing: update_args.overwrite_existing_release,
+            allow_no_executables: update_args.allow_no_executables,
+            on_missing_binary: update_args.on_missing_binary.unwrap_or_default(),
+            link_filter: update_args.link_filter.unwrap_or_default(),
+            max_link_depth: update_args.max_link_depth,
+            extraction_limits: update_args.extraction_limits(),
+            required_bins: update_args.required_bins.clone(),
+            extras,
+            events,
+        },
+    ) {
+        if let Err(remove_err) = pending::remove(&update_args.state_directory, app) {
+            warn!("Failed to discard staged state for {tag}: {remove_err}");
         }
+        return Err(e);
+    }
+    summary.durations_ms.install = Some(elapsed_ms(install_started));
+
+    let diff = BinDiff::between(&links_before, &bin_diff::snapshot(&bin_dir)?);
+    diff.log();
+    if let Some(path) = update_args.bin_diff_output.as_deref()
+        && let Err(e) = diff.write_json(path)
+    {
+        warn!("Failed to write bin diff to {path}: {e}");
+    }
+
+    commit_pending(app, &update_args.state_directory, &store, &staged)?;
+
+    if let Some(identity) = new_publisher {
+        let record = trust::Record {
+            identity,
+            tag: tag.clone(),
+            recorded_at: Timestamp::now(),
+        };
+        if let Err(e) = trust::save(&update_args.state_directory, app, &record) {
+            warn!("Failed to record the publisher of {app}: {e}");
+        }
+    }
+
+    let limits = maintenance::Limits::from(update_args.maintenance);
+    match maintenance::trim(&update_args.state_directory, app, &limits, false) {
+        Ok(report) if !report.is_empty() => info!(
+            "Dropped {} history entries and {} debug file(s) of {app}",
+            report.history_removed,
+            report.debug_removed.len()
+        ),
+        Ok(_) => {}
+        Err(e) => warn!("Failed to trim the state directory of {app}: {e}"),
+    }
+
+    let releases_dir = install_root.join(app).join("releases");
+    if let Some(license_dir) = update_args.license_dir.as_deref() {
+        let dest = license_dir.join(app).join(tag);
+        match notices::collect(&releases_dir.join(tag), &dest) {
+            Ok(files) if files.is_empty() => info!("No license or notice files in {tag}"),
+            Ok(files) => info!("Copied {} license and notice files to {dest}", files.len()),
+            Err(e) => warn!("Failed to copy license and notice files to {dest}: {e}"),
+        }
+    }
+    finalize_update(
+        &releases_dir,
+        tag,
+        update_args.restart_command.as_deref(),
+        update_args.retention(),
+        plan.as_ref().map(|plan| plan.prune.as_slice()),
+        summary,
+        &events,
+    )?;
+
+    if summary
+        .restart
+        .as_ref()
+        .is_some_and(|restart| !restart.succeeded)
+    {
+        // The JSON summary is still printed so pipelines see what was installed.
+        if update_args.output == OutputFormat::Json || update_args.events.is_some() {
+            summary.report(args, update_args, started, "")?;
+        }
+        return Err(restart::PostInstallError::Restart.into());
+    }
+
+    if let Some(cmd) = update_args.health_command.as_deref() {
+        wait_healthy(
+            cmd,
+            Duration::from_secs(update_args.health_timeout),
+            &events,
+        )?;
+    }
+    if let Some(lease) = lease
+        && let Err(e) = lease.release(&http_client).await
+    {
+        warn!("Failed to release the cluster update slot for {app}: {e:#}");
+    }
+
+    summary.report(
+        args,
+        update_args,
+        started,
+        &format!("Successfully updated to {tag}"),
+    )
+}
+
+/// Handles the `update-all` subcommand to update every app in a multi-app config.
+///
+/// Each app runs through the same pipeline as `update`, with its own lock and state
+/// under the shared state directory. A failure in one app does not stop the others;
+/// a summary is printed once every app has been attempted.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The config file cannot be found, read, or parsed
+/// - The config contains an invalid app name
+/// - Any app fails to update
+pub async fn handle_update_all(
+    args: &Args,
+    update_all_args: &UpdateAllArgs,
+    http_client: reqwest::Client,
+) -> anyhow::Result<()> {
+    let path = args.in_sysroot(
+        args.config
+            .as_deref()
+            .unwrap_or(Utf8Path::new(config::DEFAULT_APPS_CONFIG)),
+    );
+    let apps_config =
+        config::load_apps(&path)?.ok_or_else(|| anyhow!("config file not found: {path}"))?;
+
+    for name in apps_config.apps.keys() {
+        validate_app_name(name).map_err(|e| anyhow!("invalid app {name:?} in {path}: {e}"))?;
+    }
+    // Apps without the profile keep their own settings, so one profile can cover
+    // just the apps whose role differs.
+    if let Some(profile) = args.profile.as_deref() {
+        ensure!(
+            apps_config
+                .apps
+                .values()
+                .any(|app_config| app_config.profiles.contains_key(profile)),
+            "profile {profile:?} is not defined for any app in {path}"
+        );
+    }
+
+    let mut failures = Vec::new();
+    let mut pinned = Vec::new();
+    for (name, app_config) in &apps_config.apps {
+        let app_config = &args
+            .profile
+            .as_deref()
+            .and_then(|profile| app_config.with_profile(profile))
+            .unwrap_or_else(|| app_config.clone());
+        let mut update_args = UpdateArgs {
+            repo: None,
+            pattern: None,
+            asset_name: None,
+            libc: None,
+            state_directory: update_all_args.state_directory.clone(),
+            github: update_all_args.github.clone(),
+            checksum_pattern: None,
+            restart_command: None,
+            health_command: None,
+            health_timeout: update_all_args.health_timeout,
+            cluster: update_all_args.cluster.clone(),
+            notify: update_all_args.notify.clone(),
+            scan_command: None,
+            retain: None,
+            retain_max_size: None,
+            skip_verification: update_all_args.skip_verification,
+            force_unlock: false,
+            overwrite_existing_release: false,
+            allow_downgrade: false,
+            tag: None,
+            accept_new_source: false,
+            trust_policy: update_all_args.trust_policy,
+            mirrors: Vec::new(),
+            accept_new_publisher: false,
+            allow_no_executables: false,
+            on_missing_binary: None,
+            link_filter: None,
+            max_link_depth: None,
+            zstd_threads: None,
+            extract_sandbox: None,
+            network_user: None,
+            required_bins: Vec::new(),
+            extra_assets: Vec::new(),
+            maintenance: config::MaintenanceSettings::default(),
+            dry_run: false,
+            download_only: None,
+            plan: false,
+            plan_file: None,
+            apply_plan: None,
+            output: OutputFormat::Text,
+            events: None,
+            staging_max_age: None,
+            bin_diff_output: None,
+            license_dir: update_all_args.license_dir.clone(),
+            lock_timeout: update_all_args.lock_timeout,
+        };
+        update_args.merge_config(app_config);
+        if let Some(sysroot) = args.sysroot.as_deref() {
+            for warning in update_args.apply_sysroot(sysroot) {
+                warn!("{name}: {warning}");
+            }
+        }
+        if let Err(e) = update_args
+            .github
+            .resolve_token(Some(&update_args.state_directory))
+        {
+            warn!("Update failed for {name}: {e:#}");
+            failures.push((name, e));
+            continue;
+        }
+
+        let mut app_args = args.clone();
+        app_args.app = Some(name.clone());
+        app_args.apply_config(app_config);
+        app_args.install_root = Some(app_args.in_sysroot(app_args.install_root()));
+
+        let app_client = if app_config.http_timeout.is_some()
+            || !app_config.spki_pins.is_empty()
+            || app_config.proxy_mode.is_some()
+        {
+            crate::build_pinned_http_client(
+                app_args.http_timeout(),
+                &app_args.spki_pins,
+                app_args.proxy_mode(),
+            )?
+        } else {
+            http_client.clone()
+        };
+
+        match handle_update(&app_args, &update_args, app_client).await {
+            Ok(()) => {}
+            Err(e) if e.is::<PinnedError>() => {
+                info!("Skipping {name}: {e}");
+                pinned.push(name.as_str());
+            }
+            Err(e) => {
+                warn!("Update failed for {name}: {e:#}");
+                failures.push((name, e));
+            }
+        }
+    }
+
+    let total = apps_config.apps.len();
+    let mut summary = format!(
+        "Summary: {} succeeded, {} failed",
+        total - failures.len() - pinned.len(),
+        failures.len()
+    );
+    if !pinned.is_empty() {
+        summary.push_str(&format!(
+            ", {} pinned ({})",
+            pinned.len(),
+            pinned.join(", ")
+        ));
+    }
+    if !args.quiet || !failures.is_empty() {
+        println!("{summary}");
+    }
+    for (name, e) in &failures {
+        println!("  {name}: {e:#}");
+    }
+
+    ensure!(
+        failures.is_empty(),
+        "{} of {total} apps failed to update",
+        failures.len()
+    );
+    Ok(())
+}
+
+/// Handles the `adopt` subcommand to take over an app installed without distronomicon.
+///
+/// The app must already use distronomicon's layout: releases under
+/// `<root>/<app>/releases/<tag>/` and symlinks to them in `<root>/<app>/bin/`. The
+/// tag the links point to becomes the installed release, its directory's
+/// modification time the install time, and the next newest release the previous
+/// one. The links are recorded as managed, and `--repo` and the host as the source,
+/// so later updates and rollbacks treat the app like one they installed. No
+/// validators are recorded, so the first update fetches the latest release in full.
+///
+/// With `--from-layout`, an app in another layout is first restructured into this
+/// one without stopping it (see [`fsops::convert_current_layout`] and
+/// [`fsops::convert_flat_layout`]).
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The app already has recorded state
+/// - The layout cannot be converted
+/// - No symlink in `bin/` points into `releases/`
+/// - Lock acquisition fails
+/// - The state or link manifest cannot be written
+pub fn handle_adopt(args: &Args, adopt_args: &AdoptArgs) -> anyhow::Result<()> {
+    let app = args.app()?;
+    let install_root = args.install_root();
+    let repo = adopt_args.repo()?;
+    let host = adopt_args.github.host();
+    let bin_dir = install_root.join(app).join("bin");
+    let releases_dir = install_root.join(app).join("releases");
+
+    let store = args.state_store(&adopt_args.state_directory);
+    if let Some(state) = store.load(app)? {
+        bail!(
+            "{app} is already managed by distronomicon ({} from {})",
+            state.latest_tag,
+            state.source()
+        );
+    }
+
+    let timeout = Duration::from_secs(adopt_args.lock_timeout);
+    let _lock = if adopt_args.dry_run {
+        None
+    } else {
+        Some(lock::acquire(
+            app,
+            Some(&adopt_args.state_directory),
+            Some(timeout),
+        )?)
+    };
+
+    if let Some(layout) = adopt_args.from_layout {
+        let app_dir = install_root.join(app);
+        if adopt_args.dry_run {
+            let name = match layout {
+                LegacyLayout::Current => "current",
+                LegacyLayout::Flat => "flat",
+            };
+            println!("Would convert {app_dir} from the {name} layout into releases/ and bin/");
+            return Ok(());
+        }
+        let tag = match layout {
+            LegacyLayout::Current => fsops::convert_current_layout(&app_dir)?,
+            LegacyLayout::Flat => {
+                let tag = adopt_args
+                    .tag
+                    .as_deref()
+                    .ok_or_else(|| anyhow!("--from-layout flat requires --tag"))?;
+                ensure!(
+                    Utf8Path::new(tag).file_name() == Some(tag),
+                    "invalid release tag: {tag}"
+                );
+                fsops::convert_flat_layout(&app_dir, tag)?;
+                tag.to_string()
+            }
+        };
+        args.say(format_args!("Moved {app} {tag} into {releases_dir}/{tag}"));
+    }
+
+    let links = fsops::links_into_releases(&bin_dir, &releases_dir)?;
+    let tag = version::current_tag(install_root, app)?
+        .filter(|_| !links.is_empty())
+        .ok_or_else(|| {
+            anyhow!(
+                "No symlink in {bin_dir} points into {releases_dir}; adopt expects each \
+                 release in releases/<tag>/ with its commands linked from bin/"
+            )
+        })?;
+
+    let releases = fsops::list_releases(&releases_dir)?;
+    let installed_at = releases
+        .iter()
+        .find(|(release, _)| *release == tag)
+        .map(|(_, modified)| Timestamp::try_from(*modified))
+        .transpose()?
+        .ok_or_else(|| anyhow!("Release {tag} not found in {releases_dir}"))?;
+    let previous_tag = releases
+        .into_iter()
+        .map(|(release, _)| release)
+        .find(|release| *release != tag);
+
+    let state = State {
+        latest_tag: tag.clone(),
+        etag: String::new(),
+        last_modified: String::new(),
+        installed_at,
+        previous_tag,
+        repo: Some(repo.to_string()),
+        host: Some(host.to_string()),
+    };
+
+    if adopt_args.dry_run {
+        println!("Would adopt {app} at {tag} from {repo} on {host}");
+        println!("  installed_at:  {}", format_time(state.installed_at));
+        if let Some(previous_tag) = &state.previous_tag {
+            println!("  previous_tag:  {previous_tag}");
+        }
+        for link in &links {
+            println!("  manage link:   {link}");
+        }
+        return Ok(());
+    }
+
+    fsops::adopt_links(&bin_dir, &links)?;
+    store.save(app, &state)?;
+
+    args.say(format_args!(
+        "Adopted {app} at {tag} from {repo} ({} managed link(s))",
+        links.len()
+    ));
+    Ok(())
+}
+
+/// Handles the `rollback` subcommand to switch back to a previously installed release.
+///
+/// Rolls back to `--to` if given, otherwise to the `previous_tag` recorded in state,
+/// falling back to the most recently installed release other than the active one. The
+/// state file records the rolled-back tag as the latest so that `update` does not
+/// immediately reinstall the release that was just rolled back from.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Lock acquisition fails (another update in progress)
+/// - No version is installed or no other release is available
+/// - The requested release does not exist under `releases/`
+/// - Symlinks or the state file cannot be updated
+/// - Restart command fails (after the symlinks were switched)
+pub fn handle_rollback(args: &Args, rollback_args: &RollbackArgs) -> anyhow::Result<()> {
+    let app = args.app()?;
+    let install_root = args.install_root();
+    let _span = info_span!("rollback", app = %app).entered();
+
+    let timeout = Duration::from_secs(rollback_args.lock_timeout);
+    let _lock = lock::acquire(app, Some(&rollback_args.state_directory), Some(timeout))?;
+
+    let releases_dir = install_root.join(app).join("releases");
+    let bin_dir = install_root.join(app).join("bin");
+    let store = args.state_store(&rollback_args.state_directory);
+    recover_pending(app, install_root, &rollback_args.state_directory, &store)?;
+
+    let current = version::current_tag(install_root, app)?
+        .ok_or_else(|| anyhow!("No version of {app} is installed"))?;
+    let existing_state = store.load(app)?;
+
+    let target = match rollback_args.to.as_deref() {
+        Some(tag) => {
+            ensure!(
+                Utf8Path::new(tag).file_name() == Some(tag),
+                "invalid release tag: {tag}"
+            );
+            tag.to_string()
+        }
+        None => existing_state
+            .as_ref()
+            .and_then(|state| state.previous_tag.clone())
+            .filter(|tag| *tag != current && releases_dir.join(tag).is_dir())
+            .or_else(|| {
+                history::load(&rollback_args.state_directory, app)
+                    .ok()?
+                    .into_iter()
+                    .rev()
+                    .map(|entry| entry.tag)
+                    .find(|tag| *tag != current && releases_dir.join(tag).is_dir())
+            })
+            .or_else(|| {
+                fsops::list_releases(&releases_dir)
+                    .ok()?
+                    .into_iter()
+                    .map(|(tag, _)| tag)
+                    .find(|tag| *tag != current)
+            })
+            .ok_or_else(|| anyhow!("No previous release of {app} found in {releases_dir}"))?,
+    };
+
+    ensure!(target != current, "{target} is already active");
+    let target_dir = releases_dir.join(&target);
+    ensure!(
+        target_dir.is_dir(),
+        "Release {target} not found in {releases_dir}"
+    );
+    if constraint::check_downgrade(&current, &target, None).is_err() {
+        confirm(
+            args,
+            &format!("Downgrade {app} from {current} to {target}?"),
+        )?;
+    }
+
+    activate_release(
+        &target_dir,
+        &bin_dir,
+        &target,
+        fsops::MissingBinary::Remove,
+        rollback_args.link_filter.unwrap_or_default(),
+    )?;
+
+    if let Some(existing) = existing_state {
+        let updated_state = State {
+            latest_tag: target.clone(),
+            previous_tag: Some(current.clone()),
+            ..existing
+        };
+        store.save(app, &updated_state)?;
+    }
+
+    if let Some(cmd) = rollback_args.restart_command.as_deref() {
+        let _span = info_span!("restart", command = %cmd).entered();
+        restart::execute(cmd)
+            .map_err(|e| anyhow!("Rolled back to {target} but restart command failed: {e}"))?;
+        info!("Restart command succeeded");
+    }
+
+    args.say(format_args!("Rolled back {app} from {current} to {target}"));
+    Ok(())
+}
+
+/// Handles the `list` subcommand to show installed releases.
+///
+/// Prints one line per directory under `releases/`, newest first, with the install
+/// time and on-disk size. The active release is marked with `*`. Install times come
+/// from `state.json` for the active release when a state directory is given and from
+/// the directory's modification time otherwise.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The releases directory cannot be read
+/// - Symlink resolution fails
+/// - The state file exists but cannot be parsed
+pub fn handle_list(args: &Args, list_args: &ListArgs) -> anyhow::Result<()> {
+    let app = args.app()?;
+    let install_root = args.install_root();
+    let releases_dir = install_root.join(app).join("releases");
+
+    let releases = fsops::list_releases(&releases_dir)?;
+    if releases.is_empty() {
+        println!("No releases installed");
+        return Ok(());
     }
 
-    let now = Timestamp::now();
-    let new_state = State {
-        latest_tag: tag.to_string(),
-        etag: validators_out.etag.clone().unwrap_or_default(),
-        last_modified: validators_out
-            .last_modified
+    let current_tag = version::current_tag(install_root, app)?;
+    let existing_state = list_args
+        .state_directory
+        .as_ref()
+        .map(|dir| args.state_store(dir).load(app))
+        .transpose()?
+        .flatten();
+
+    let width = releases.iter().map(|(tag, _)| tag.len()).max().unwrap_or(0);
+    for (tag, modified) in releases {
+        let is_current = current_tag.as_deref() == Some(tag.as_str());
+        let installed_at = existing_state
             .as_ref()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(now),
-        installed_at: now,
+            .filter(|state| is_current && state.latest_tag == tag)
+            .map(|state| state.installed_at)
+            .map_or_else(|| Timestamp::try_from(modified), Ok)?;
+        let size = fsops::dir_size(releases_dir.join(&tag))?;
+
+        let marker = if is_current { '*' } else { ' ' };
+        println!(
+            "{marker} {tag:<width$}  {}  {}",
+            format_time(installed_at),
+            format_size(size)
+        );
+    }
+
+    Ok(())
+}
+
+/// Handles the `self-update` subcommand to replace the running binary with a new release.
+///
+/// Fetches the latest (or `--tag`) release of distronomicon itself, downloads the
+/// archive for this binary's target, verifies it against `sha256.sum`, extracts it to
+/// a temporary directory, and atomically renames the new executable over the old one.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The running executable's path cannot be determined
+/// - GitHub API request fails or has no matching asset
+/// - Download, checksum verification, or extraction fails
+/// - The archive does not contain a `distronomicon` executable
+/// - The executable cannot be replaced
+pub async fn handle_self_update(
+    args: &Args,
+    self_update_args: &SelfUpdateArgs,
+    http_client: reqwest::Client,
+) -> anyhow::Result<()> {
+    self_update_args.github.ensure_github("self-update")?;
+    let repo = self_update_args.repo.as_str();
+    let host = self_update_args.github.host();
+    let token = self_update_args.github.token.as_deref();
+    let target = match self_update_args.path.clone() {
+        Some(path) => path,
+        None => Utf8PathBuf::try_from(env::current_exe()?.canonicalize()?)?,
     };
-    state::save_atomic(state_path, &new_state)?;
+    let _span = info_span!("self-update", repo = %repo, path = %target).entered();
 
-    ensure!(
-        !restart_failed,
-        "Update completed but restart command failed"
-    );
+    let release = if let Some(tag) = self_update_args.tag.as_deref() {
+        github::fetch_tag()
+            .repo(repo)
+            .tag(tag)
+            .maybe_token(token)
+            .client(http_client.clone())
+            .host(host)
+            .rate_limit_wait(self_update_args.github.rate_limit_wait())
+            .await?
+    } else {
+        github::fetch_latest()
+            .repo(repo)
+            .maybe_token(token)
+            .client(http_client.clone())
+            .host(host)
+            .channel(self_update_args.github.channel())
+            .rate_limit_wait(self_update_args.github.rate_limit_wait())
+            .maybe_version_constraint(self_update_args.github.version_constraint()?.as_ref())
+            .await?
+            .release
+            .ok_or_else(|| anyhow!("No release found for {repo}"))?
+    };
+    let tag = &release.tag_name;
+
+    if self_update::is_current(tag) {
+        args.say(format_args!("Already up-to-date: {tag}"));
+        return Ok(());
+    }
+    info!("Updating {target} to {tag}");
+
+    let asset_pattern = Regex::new(&self_update::asset_pattern())?;
+    let checksum_pattern = Regex::new(self_update::CHECKSUM_PATTERN)?;
+    let verification = if self_update_args.skip_verification {
+        Verification::Skip
+    } else {
+        Verification::ChecksumAsset(&checksum_pattern)
+    };
+    let fetcher = Fetcher {
+        github: &self_update_args.github,
+        http: args.http_settings(),
+        client: http_client,
+        mirrors: &[],
+        limits: args.transfer_limits(&self_update_args.github),
+        network_user: None,
+    };
+    let (downloaded_file, asset_name, _) = download_and_verify_asset(
+        &fetcher,
+        &release,
+        &asset_pattern,
+        verification,
+        None,
+        &Events::disabled(),
+    )
+    .await?;
+
+    let extract_dir = camino_tempfile::tempdir()?;
+    {
+        let _span = info_span!("extract", archive = %asset_name).entered();
+        let archive = extract_dir.path().join(&asset_name);
+        fs::copy(downloaded_file.path(), &archive)?;
+        extract::unpack(&archive, extract_dir.path())?;
+        fs::remove_file(&archive)?;
+    }
+
+    let binary = fsops::discover_executables(extract_dir.path(), fsops::LinkFilter::All)?
+        .into_iter()
+        .find(|path| path.file_name() == Some(self_update::BINARY_NAME))
+        .ok_or_else(|| {
+            anyhow!(
+                "{asset_name} does not contain a {} executable",
+                self_update::BINARY_NAME
+            )
+        })?;
+    self_update::replace_executable(&extract_dir.path().join(binary), &target)?;
 
+    args.say(format_args!(
+        "Updated {target} from v{} to {tag}",
+        env!("CARGO_PKG_VERSION")
+    ));
     Ok(())
 }
 
-/// Handles the `check` subcommand to query for updates without installing.
+/// Handles the `list-remote` subcommand to show releases available on GitHub.
+///
+/// Prints one line per release with its tag, publish date, prerelease/draft status,
+/// and the assets matching `--pattern` (or the asset count without a pattern).
+/// Prereleases are only shown with `--allow-prerelease`.
 ///
 /// # Errors
 ///
 /// Returns an error if:
-/// - State file cannot be read or written
+/// - No repository is configured
+/// - The pattern is not a valid regex
 /// - GitHub API request fails
-/// - Network errors occur
-pub async fn handle_check(
+pub async fn handle_list_remote(
+    list_remote_args: &ListRemoteArgs,
+    http_client: reqwest::Client,
+) -> anyhow::Result<()> {
+    list_remote_args.github.ensure_github("list-remote")?;
+    let pattern = asset_pattern(
+        list_remote_args.pattern.as_deref(),
+        list_remote_args.asset_name.as_deref(),
+        &pattern::Platform::host(),
+    )
+    .map(|pattern| Regex::new(&pattern))
+    .transpose()?;
+
+    let mut releases = github::list_releases()
+        .repo(list_remote_args.repo()?)
+        .maybe_token(list_remote_args.github.token.as_deref())
+        .client(http_client)
+        .host(list_remote_args.github.host())
+        .limit(usize::try_from(list_remote_args.limit)?)
+        .await?;
+    let channel = list_remote_args.github.channel();
+    releases.retain(|release| channel.includes(release));
+    if let Some(tag_pattern) = list_remote_args.github.tag_filter()? {
+        releases.retain(|release| tag_pattern.is_match(&release.tag_name));
+    }
+    if let Some(constraint) = list_remote_args.github.version_constraint()? {
+        releases.retain(|release| constraint.matches(&release.tag_name));
+    }
+
+    if releases.is_empty() {
+        println!("No releases found");
+        return Ok(());
+    }
+
+    let width = releases
+        .iter()
+        .map(|release| release.tag_name.len())
+        .max()
+        .unwrap_or(0);
+    for release in &releases {
+        let published = release.published_at.or(release.created_at).map_or_else(
+            || "-".to_string(),
+            |t| {
+                t.to_zoned(TimeZone::system())
+                    .strftime("%Y-%m-%d")
+                    .to_string()
+            },
+        );
+        let status = if release.draft {
+            "draft"
+        } else if release.prerelease {
+            "prerelease"
+        } else {
+            ""
+        };
+        let assets = match &pattern {
+            Some(pattern) => {
+                let matching = release
+                    .assets
+                    .iter()
+                    .filter(|asset| pattern.is_match(&asset.name))
+                    .map(|asset| asset.name.as_str())
+                    .collect::<Vec<_>>();
+                if matching.is_empty() {
+                    "(no matching asset)".to_string()
+                } else {
+                    matching.join(", ")
+                }
+            }
+            None => format!("{} asset(s)", release.assets.len()),
+        };
+
+        println!(
+            "{:<width$}  {published:<10}  {status:<10}  {assets}",
+            release.tag_name
+        );
+    }
+
+    Ok(())
+}
+
+/// Handles the `status` subcommand to summarize local and remote state.
+///
+/// Prints the installed tag, the contents of state.json, whether the lock is held,
+/// leftover staging directories, and (unless `--offline` or no repository is
+/// configured) whether GitHub has a newer release. Never modifies any files.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The bin, staging, or state directory cannot be read
+/// - The lock file exists but cannot be opened
+/// - GitHub API request fails
+pub async fn handle_status(
     args: &Args,
-    check_args: &CheckArgs,
+    status_args: &StatusArgs,
     http_client: reqwest::Client,
 ) -> anyhow::Result<()> {
-    let state_path = check_args
-        .state_directory
-        .join(&args.app)
-        .join("state.json");
-    let existing_state = state::load(&state_path)?;
+    let app = args.app()?;
+    let install_root = args.install_root();
+    let store = args.state_store(&status_args.state_directory);
 
-    let validators = if let Some(state) = existing_state.as_ref() {
-        github::Validators {
-            etag: Some(state.etag.clone()),
-            last_modified: Some(state.last_modified.to_string()),
+    let current_tag = version::current_tag(install_root, app)?;
+    println!("app:       {app}");
+    println!("installed: {}", current_tag.as_deref().unwrap_or("(none)"));
+
+    match store.load(app)? {
+        Some(state) => {
+            println!("state:     {}", store.location(app));
+            println!("  latest_tag:    {}", state.latest_tag);
+            println!("  installed_at:  {}", format_time(state.installed_at));
+            println!(
+                "  previous_tag:  {}",
+                state.previous_tag.as_deref().unwrap_or("(none)")
+            );
+            println!("  source:        {}", state.source());
+            println!("  etag:          {}", state.etag);
+            let last_modified = match state.last_modified_at() {
+                Some(timestamp) => format_time(timestamp),
+                None if state.last_modified.is_empty() => "(none)".to_string(),
+                None => state.last_modified.clone(),
+            };
+            println!("  last_modified: {last_modified}");
         }
+        None => println!("state:     (none)"),
+    }
+
+    match pin::load(&status_args.state_directory, app)? {
+        Some(pin) => println!(
+            "pinned:    {} since {}",
+            pin.describe(),
+            format_time(pin.pinned_at)
+        ),
+        None => println!("pinned:    no"),
+    }
+
+    let lock_status = match lock::status(app, Some(&status_args.state_directory))? {
+        lock::LockStatus::Free => "free",
+        lock::LockStatus::Held => "held by another process",
+        lock::LockStatus::Stale => "stale lock file (run `unlock` to remove it)",
+    };
+    println!("lock:      {lock_status}");
+
+    let staging_dir = install_root.join(app).join("staging");
+    let leftovers = fsops::list_releases(&staging_dir)?;
+    if leftovers.is_empty() {
+        println!("staging:   clean");
     } else {
-        github::Validators {
-            etag: None,
-            last_modified: None,
+        println!("staging:   {} leftover", leftovers.len());
+        for (name, _) in &leftovers {
+            println!("  {}", staging_dir.join(name));
+        }
+    }
+
+    let repo = match (status_args.offline, status_args.repo.as_deref()) {
+        (true, _) => {
+            println!("remote:    skipped (--offline)");
+            return Ok(());
         }
+        (false, None) => {
+            println!("remote:    skipped (no --repo)");
+            return Ok(());
+        }
+        (false, Some(repo)) => repo,
     };
 
-    let fetch_result = github::fetch_latest()
-        .repo(&check_args.repo)
-        .maybe_token(check_args.github.token.as_deref())
-        .client(http_client)
-        .host(&check_args.github.host)
-        .allow_prerelease(check_args.github.allow_prerelease)
-        .validators(validators)
+    let http_client = status_args
+        .github
+        .source_client(&args.http_settings(), http_client)?;
+    let fetch_result = status_args
+        .github
+        .fetch_latest(repo, github::Validators::default(), None, http_client)
         .await?;
 
-    let current_tag = version::current_tag(&args.install_root, &args.app)?;
+    match (current_tag, fetch_result.release) {
+        (Some(current), Some(release)) if current != release.tag_name => {
+            println!(
+                "remote:    update-available: {current} -> {}",
+                release.tag_name
+            );
+        }
+        (Some(current), _) => println!("remote:    up-to-date: {current}"),
+        (None, Some(release)) => println!("remote:    install-available: {}", release.tag_name),
+        (None, None) => println!("remote:    no release found"),
+    }
 
-    match (current_tag.as_ref(), fetch_result.release) {
-        (Some(current), None) => {
-            println!("up-to-date: {current}");
+    Ok(())
+}
+
+/// Handles the `doctor` subcommand to diagnose problems before an update is attempted.
+///
+/// Checks that the app's install and state directories are writable and owned by
+/// the effective user (see [`Args::ownership_warnings`]), that the install root has at least `--min-free-space` MiB free, that the `bin/` symlinks
+/// point at one existing release consistent with state.json, and that the GitHub
+/// API (and repository, if configured) is reachable with the configured token.
+/// Each check prints one line; nothing is modified.
+///
+/// # Errors
+///
+/// Returns an error if any check fails, or if the bin, staging, or state directory
+/// cannot be read.
+pub async fn handle_doctor(
+    args: &Args,
+    doctor_args: &DoctorArgs,
+    http_client: reqwest::Client,
+) -> anyhow::Result<()> {
+    doctor_args.github.ensure_github("doctor")?;
+    let app = args.app()?;
+    let install_root = args.install_root();
+    let app_dir = install_root.join(app);
+    let state_dir = doctor_args.state_directory.join(app);
+    let mut report = DoctorReport {
+        quiet: args.quiet,
+        problems: 0,
+    };
+
+    for (label, dir) in [
+        ("install directory", &app_dir),
+        ("state directory", &state_dir),
+    ] {
+        let existing = nearest_existing(dir);
+        if !existing.is_dir() {
+            report.fail(format!("{label} {dir}: {existing} is not a directory"));
+            continue;
         }
-        (Some(current), Some(release)) => {
-            if *current == release.tag_name {
-                println!("up-to-date: {current}");
+        match rustix::fs::access(existing.as_std_path(), Access::WRITE_OK | Access::EXEC_OK) {
+            Ok(()) => report.ok(format!("{label} {dir} is writable")),
+            Err(e) => report.fail(format!(
+                "{label} {dir} is not writable ({existing}: {e}); fix its permissions or run as its owner"
+            )),
+        }
+    }
+    let bin_dir = app_dir.join("bin");
+    for warning in ownership_warnings(&[
+        ("install root", install_root),
+        ("state directory", &doctor_args.state_directory),
+        ("bin directory", &bin_dir),
+    ]) {
+        report.warn(warning);
+    }
+
+    let existing = nearest_existing(&app_dir);
+    match rustix::fs::statvfs(existing.as_std_path()) {
+        Ok(stat) => {
+            let free = stat.f_bavail.saturating_mul(stat.f_frsize);
+            let required = doctor_args.min_free_space.saturating_mul(1024 * 1024);
+            if free < required {
+                report.fail(format!(
+                    "only {} free on {existing} (need at least {} MiB); free space or lower --retain",
+                    format_size(free),
+                    doctor_args.min_free_space
+                ));
             } else {
-                println!("update-available: {} -> {}", current, release.tag_name);
+                report.ok(format!("{} free on {existing}", format_size(free)));
+            }
+            // Filesystems without a fixed inode table, such as btrfs, report none.
+            if stat.f_files > 0 && stat.f_favail < doctor_args.min_free_inodes {
+                report.fail(format!(
+                    "only {} inodes free on {existing} (need at least {}); remove files or lower --retain",
+                    stat.f_favail, doctor_args.min_free_inodes
+                ));
+            } else if stat.f_files > 0 {
+                report.ok(format!("{} inodes free on {existing}", stat.f_favail));
             }
         }
-        (None, Some(release)) => {
-            println!("install-available: {}", release.tag_name);
+        Err(e) => report.fail(format!("cannot read free space on {existing}: {e}")),
+    }
+
+    if bin_dir.is_dir() {
+        let links = fsops::managed_links(&bin_dir)?;
+        let kept = fsops::kept_links(&bin_dir)?;
+        let mut tags = Vec::new();
+        for link in &links {
+            let target = Utf8PathBuf::try_from(fs::read_link(link)?)?;
+            if !bin_dir.join(&target).exists() {
+                report.fail(format!(
+                    "{link} points to missing {target}; run `update --overwrite-existing-release` or `rollback`"
+                ));
+            }
+            let tag = target
+                .components()
+                .skip_while(|component| component.as_str() != "releases")
+                .nth(1)
+                .map(|component| component.as_str().to_string());
+            if kept.contains(link) {
+                report.warn(format!(
+                    "{link} was kept from {} because the active release lacks it",
+                    tag.as_deref().unwrap_or("(unknown release)")
+                ));
+                continue;
+            }
+            if let Some(tag) = tag
+                && !tags.contains(&tag)
+            {
+                tags.push(tag);
+            }
         }
-        (None, None) => {
-            println!("No version installed");
+
+        match tags.as_slice() {
+            [] => report.warn(format!("no managed symlinks in {bin_dir}")),
+            [tag] => report.ok(format!(
+                "{} symlink(s) in bin/ point to {tag}",
+                links.len().saturating_sub(kept.len())
+            )),
+            _ => report.fail(format!(
+                "bin/ symlinks point to different releases ({}); run `update` or `rollback` to relink",
+                tags.join(", ")
+            )),
+        }
+
+        let current_tag = version::current_tag(install_root, app)?;
+        if let Some(state) = args.state_store(&doctor_args.state_directory).load(app)?
+            && current_tag.as_deref() != Some(state.latest_tag.as_str())
+        {
+            report.warn(format!(
+                "recorded state says {} but bin/ points to {}",
+                state.latest_tag,
+                current_tag.as_deref().unwrap_or("(nothing)")
+            ));
         }
+    } else {
+        report.ok(format!("{app} is not installed yet"));
     }
 
-    if let (Some(_current), Some(existing)) = (current_tag, existing_state) {
-        let etag_changed = fetch_result.validators.etag.as_ref() != Some(&existing.etag);
-        let last_mod_changed = fetch_result.validators.last_modified.as_ref()
-            != Some(&existing.last_modified.to_string());
+    let staging_dir = app_dir.join("staging");
+    let leftovers = fsops::list_releases(&staging_dir)?;
+    if !leftovers.is_empty() {
+        report.warn(format!(
+            "{} leftover staging director{} under {staging_dir}; remove with `gc`",
+            leftovers.len(),
+            if leftovers.len() == 1 { "y" } else { "ies" }
+        ));
+    }
 
-        if etag_changed || last_mod_changed {
-            let updated_state = State {
-                latest_tag: existing.latest_tag,
-                etag: fetch_result.validators.etag.unwrap_or(existing.etag),
-                last_modified: fetch_result
-                    .validators
-                    .last_modified
-                    .and_then(|s| s.parse().ok())
-                    .unwrap_or(existing.last_modified),
-                installed_at: existing.installed_at,
-            };
-            state::save_atomic(&state_path, &updated_state)?;
+    match lock::status(app, Some(&doctor_args.state_directory))? {
+        lock::LockStatus::Free => {}
+        lock::LockStatus::Held => report.warn("lock is held by another process"),
+        lock::LockStatus::Stale => report.warn("stale lock file; remove it with `unlock`"),
+    }
+
+    let host = doctor_args.github.host();
+    let token = doctor_args.github.token.as_deref();
+    match github::probe()
+        .maybe_repo(doctor_args.repo.as_deref())
+        .maybe_token(token)
+        .client(http_client)
+        .host(host)
+        .await
+    {
+        Ok(probe) => {
+            report.ok(format!("GitHub API reachable at {host}"));
+            if let Some(repo) = doctor_args.repo.as_deref() {
+                let visibility = if probe.private == Some(true) {
+                    "private"
+                } else {
+                    "public"
+                };
+                report.ok(format!("{repo} is accessible ({visibility})"));
+            }
+
+            match (token, probe.scopes) {
+                (None, _) => report.ok("no token configured; anonymous rate limits apply"),
+                (Some(_), None) => {
+                    report.ok("token accepted (fine-grained token; scopes not reported)");
+                }
+                (Some(_), Some(scopes))
+                    if probe.private == Some(true) && !scopes.iter().any(|s| s == "repo") =>
+                {
+                    report.warn(format!(
+                        "token scopes ({}) lack `repo`, which private release assets require",
+                        scopes.join(", ")
+                    ));
+                }
+                (Some(_), Some(scopes)) => {
+                    report.ok(format!("token scopes: {}", scopes.join(", ")));
+                }
+            }
+
+            if let Some(remaining) = probe.rate_limit_remaining
+                && remaining < 10
+            {
+                report.warn(format!(
+                    "only {remaining} GitHub API request(s) left in this rate-limit window"
+                ));
+            }
         }
+        Err(github::GithubError::RateLimited { reset }) => report.fail(format!(
+            "GitHub API on {host} is rate limited until {}; configure a token for a higher limit",
+            format_time(reset)
+        )),
+        Err(github::GithubError::Unauthorized { status, .. }) => report.fail(format!(
+            "GitHub API on {host} rejected the request ({status}); check that the token is valid, unexpired, and allowed to read {}",
+            doctor_args.repo.as_deref().unwrap_or("the repository")
+        )),
+        Err(github::GithubError::NotFound { .. }) => report.fail(format!(
+            "{} not found on {host}; check --repo and --github-host, and that the token can see a private repository",
+            doctor_args.repo.as_deref().unwrap_or("the GitHub API")
+        )),
+        Err(e) => report.fail(format!(
+            "cannot reach {} on {host}: {e}; check network access, --github-host, and the token",
+            doctor_args.repo.as_deref().unwrap_or("the GitHub API")
+        )),
     }
 
+    ensure!(
+        report.problems == 0,
+        "doctor found {} problem(s)",
+        report.problems
+    );
+
     Ok(())
 }
 
-/// Handles the `update` subcommand to download, verify, extract, and install a new release.
+/// Handles the `version` subcommand to display the currently installed version.
 ///
 /// # Errors
 ///
 /// Returns an error if:
-/// - Lock acquisition fails (another update in progress)
-/// - GitHub API request fails
-/// - No matching asset found for the pattern
-/// - Download fails or times out
-/// - Checksum verification fails
-/// - Archive extraction fails
-/// - Filesystem operations fail (staging, moving, symlinking)
-/// - Restart command fails (after successful installation)
-pub async fn handle_update(
-    args: &Args,
-    update_args: &UpdateArgs,
-    http_client: reqwest::Client,
-) -> anyhow::Result<()> {
-    let _span = info_span!("update", app = %args.app, repo = %update_args.repo).entered();
+/// - Installation directory cannot be accessed
+/// - Symlink resolution fails
+pub fn handle_version(args: &Args) -> anyhow::Result<()> {
+    let app = args.app()?;
+    let install_root = args.install_root();
+    let current_tag = version::current_tag(install_root, app)?;
 
-    if update_args.force_unlock {
-        info!("Force unlock requested, removing lock file");
-        lock::unlock(&args.app, Some(&update_args.state_directory))?;
+    if args.verbose > 0 {
+        version::print_diagnostics(install_root, app, current_tag.as_deref())?;
+    } else if let Some(tag) = current_tag {
+        println!("{tag}");
+    }
+
+    Ok(())
+}
+
+/// Handles the `completions` subcommand by printing a completion script for
+/// `shell`, generated from the [`Args`] definition, to stdout.
+///
+/// With `--dynamic` the script instead calls back into the binary (with
+/// `COMPLETE=<shell>` set) on every completion, so candidates such as app names
+/// and retained tags come from the local filesystem.
+///
+/// # Errors
+///
+/// Returns an error if the script cannot be written to stdout.
+pub fn handle_completions(completions_args: &CompletionsArgs) -> anyhow::Result<()> {
+    let mut command = Args::command();
+    let name = command.get_name().to_string();
+    if completions_args.dynamic {
+        let shell = completions_args.shell.to_string();
+        let shells = Shells::builtins();
+        let completer = shells
+            .completer(&shell)
+            .ok_or_else(|| anyhow!("Dynamic completions are not supported for {shell}"))?;
+        completer.write_registration(
+            completion::COMPLETE_VAR,
+            &name,
+            &name,
+            &name,
+            &mut io::stdout(),
+        )?;
+    } else {
+        clap_complete::generate(
+            completions_args.shell,
+            &mut command,
+            name,
+            &mut io::stdout(),
+        );
     }
+    Ok(())
+}
 
-    let timeout = std::time::Duration::from_secs(update_args.lock_timeout);
-    let _lock = lock::acquire(&args.app, Some(&update_args.state_directory), Some(timeout))?;
+/// Handles the `gc` subcommand to remove leftover staging directories.
+///
+/// Holds the app lock while removing so that the staging directory of an update
+/// running concurrently is never touched.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The lock cannot be acquired within the timeout
+/// - The staging directory cannot be read
+/// - Any stale staging directory cannot be removed
+pub fn handle_gc(args: &Args, gc_args: &GcArgs) -> anyhow::Result<()> {
+    let app = args.app()?;
+    let staging_dir = args.install_root().join(app).join("staging");
+    let max_age = Duration::from_secs(gc_args.max_age);
 
-    let state_path = update_args
-        .state_directory
-        .join(&args.app)
-        .join("state.json");
-    let existing_state = state::load(&state_path)?;
+    if gc_args.dry_run {
+        for name in fsops::stale_staging(&staging_dir, max_age)? {
+            println!("Would remove {}", staging_dir.join(name));
+        }
+        return Ok(());
+    }
+
+    let timeout = Duration::from_secs(gc_args.lock_timeout);
+    let _lock = lock::acquire(app, Some(&gc_args.state_directory), Some(timeout))?;
+
+    let (removed, failed) = fsops::remove_stale_staging(&staging_dir, max_age)?;
+    args.say(format_args!(
+        "Removed {} stale staging director{} for {app}",
+        removed.len(),
+        if removed.len() == 1 { "y" } else { "ies" }
+    ));
+    ensure!(
+        failed.is_empty(),
+        "Failed to remove {} staging director(ies): {:?}",
+        failed.len(),
+        failed
+    );
+
+    Ok(())
+}
+
+/// Handles the `prune` subcommand to delete old releases outside of an update.
+///
+/// Keeps the `--retain` most recent releases plus the active one, exactly as
+/// `update` does after installing, then trims the oldest of those to fit
+/// `--retain-max-size`, never deleting the pinned release. With `--json`, prints an
+/// object with the app, retain count, size budget, `dry_run`, the `pruned` tags,
+/// and any `failed` deletions.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The user declines the `--retain 0` confirmation
+/// - The lock cannot be acquired within the timeout
+/// - The releases or bin directory cannot be read
+/// - Any release cannot be deleted
+pub fn handle_prune(args: &Args, prune_args: &PruneArgs) -> anyhow::Result<()> {
+    let app = args.app()?;
+    let install_root = args.install_root();
+    let releases_dir = install_root.join(app).join("releases");
+    let retain = prune_args.retain.unwrap_or(DEFAULT_RETAIN);
+    let retention = fsops::Retention {
+        count: retain as usize,
+        max_size: prune_args.retain_max_size.map(|size| size.0),
+        pinned: pin::load(&prune_args.state_directory, app)?.map(|pin| pin.tag),
+    };
+
+    let current_tag = version::current_tag(install_root, app)?.unwrap_or_default();
+
+    let (pruned, failed) = if prune_args.dry_run {
+        let pruned = fsops::releases_to_prune(&releases_dir, &current_tag, retention)?;
+        (pruned, Vec::new())
+    } else {
+        if retain == 0 {
+            confirm(
+                args,
+                &format!(
+                    "--retain 0 will delete every release of {app} except the active one. Continue?"
+                ),
+            )?;
+        }
+        let timeout = Duration::from_secs(prune_args.lock_timeout);
+        let _lock = lock::acquire(app, Some(&prune_args.state_directory), Some(timeout))?;
+        fsops::prune_old_releases(&releases_dir, &current_tag, retention)?
+    };
+
+    if prune_args.json {
+        let failed_json = failed
+            .iter()
+            .map(|(tag, error)| serde_json::json!({ "tag": tag, "error": error }))
+            .collect::<Vec<_>>();
+        let report = serde_json::json!({
+            "app": app,
+            "retain": retain,
+            "retain_max_size": prune_args.retain_max_size.map(|size| size.0),
+            "dry_run": prune_args.dry_run,
+            "pruned": pruned,
+            "failed": failed_json,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else if prune_args.dry_run {
+        for tag in &pruned {
+            println!("Would delete {}", releases_dir.join(tag));
+        }
+    } else {
+        args.say(format_args!("Pruned {} release(s) of {app}", pruned.len()));
+        for tag in &pruned {
+            args.say(format_args!("  {tag}"));
+        }
+    }
+
+    ensure!(
+        failed.is_empty(),
+        "Failed to delete {} release(s): {:?}",
+        failed.len(),
+        failed
+    );
+
+    Ok(())
+}
+
+/// Handles the `maintenance` subcommand to trim what accumulates in an app's state
+/// directory: the oldest `history.jsonl` entries and the responses saved under
+/// `debug/`, as `update` also does after each install. With `--json`, prints the
+/// app, `dry_run`, and the [`maintenance::Report`] fields.
+///
+/// # Errors
+///
+/// Returns an error if the lock cannot be acquired within the timeout, or the
+/// history or debug directory cannot be read or trimmed.
+pub fn handle_maintenance(args: &Args, maintenance_args: &MaintenanceArgs) -> anyhow::Result<()> {
+    let app = args.app()?;
+    let state_directory = &maintenance_args.state_directory;
+    let limits = maintenance::Limits::from(maintenance_args.settings());
+
+    let _lock = if maintenance_args.dry_run {
+        None
+    } else {
+        let timeout = Duration::from_secs(maintenance_args.lock_timeout);
+        Some(lock::acquire(app, Some(state_directory), Some(timeout))?)
+    };
+    let report = maintenance::trim(state_directory, app, &limits, maintenance_args.dry_run)?;
+
+    if maintenance_args.json {
+        let report = serde_json::json!({
+            "app": app,
+            "dry_run": maintenance_args.dry_run,
+            "history_removed": report.history_removed,
+            "debug_removed": report.debug_removed,
+            "debug_freed": report.debug_freed,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else if maintenance_args.dry_run {
+        if report.history_removed > 0 {
+            println!(
+                "Would drop {} entr{} from {}",
+                report.history_removed,
+                if report.history_removed == 1 {
+                    "y"
+                } else {
+                    "ies"
+                },
+                history::path(state_directory, app)
+            );
+        }
+        for path in &report.debug_removed {
+            println!("Would remove {path}");
+        }
+    } else {
+        args.say(format_args!(
+            "Dropped {} history entr{} and {} debug file(s) ({}) of {app}",
+            report.history_removed,
+            if report.history_removed == 1 {
+                "y"
+            } else {
+                "ies"
+            },
+            report.debug_removed.len(),
+            format_size(report.debug_freed)
+        ));
+    }
 
-    let validators = existing_state.as_ref().map_or_else(
-        || github::Validators {
-            etag: None,
-            last_modified: None,
-        },
-        |state| github::Validators {
-            etag: Some(state.etag.clone()),
-            last_modified: Some(state.last_modified.to_string()),
-        },
-    );
+    Ok(())
+}
 
-    let fetch_result = github::fetch_latest()
-        .repo(&update_args.repo)
-        .maybe_token(update_args.github.token.as_deref())
-        .client(http_client.clone())
-        .host(&update_args.github.host)
-        .allow_prerelease(update_args.github.allow_prerelease)
-        .validators(validators)
-        .await?;
+/// Handles the `history` subcommand to print an app's install journal.
+///
+/// Prints one line per successful install, oldest first: install time, tag, the
+/// tag it replaced, the asset name, and the asset's SHA-256. With `--json`, prints
+/// the entries as a JSON array instead.
+///
+/// # Errors
+///
+/// Returns an error if the journal cannot be read or contains an invalid line.
+pub fn handle_history(args: &Args, history_args: &HistoryArgs) -> anyhow::Result<()> {
+    let app = args.app()?;
+    let mut entries = history::load(&history_args.state_directory, app)?;
+    if let Some(limit) = history_args.limit {
+        entries.drain(..entries.len().saturating_sub(limit));
+    }
 
-    let current_tag = version::current_tag(&args.install_root, &args.app)?;
+    if history_args.json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
 
-    if is_up_to_date(
-        current_tag.as_ref(),
-        fetch_result.release.as_ref(),
-        existing_state.as_ref(),
-        fetch_result.was_modified,
-    ) {
-        if let Some(tag) = current_tag.as_ref() {
-            println!("Already up-to-date: {tag}");
-        }
+    if entries.is_empty() {
+        println!("No installs recorded for {app}");
         return Ok(());
     }
 
-    let release = fetch_result
-        .release
-        .ok_or_else(|| anyhow!("No release available"))?;
-    let tag = &release.tag_name;
+    let tag_width = entries.iter().map(|e| e.tag.len()).max().unwrap_or(0);
+    let previous_width = entries
+        .iter()
+        .map(|e| e.previous_tag.as_deref().map_or(1, str::len))
+        .max()
+        .unwrap_or(0);
+    for entry in &entries {
+        println!(
+            "{}  {:<tag_width$}  {:<previous_width$}  {}  sha256:{}",
+            format_time(entry.installed_at),
+            entry.tag,
+            entry.previous_tag.as_deref().unwrap_or("-"),
+            entry.asset,
+            entry.sha256
+        );
+    }
 
-    info!("Updating to {tag}");
+    Ok(())
+}
 
-    let asset_pattern = Regex::new(&update_args.pattern)?;
-    let checksum_pattern = update_args
-        .checksum_pattern
-        .as_ref()
-        .map(|p| Regex::new(p))
-        .transpose()?;
+/// Handles the `pin` subcommand to hold an app at its installed release.
+///
+/// Writes `<state-dir>/<app>/pin.json` under the app lock, so a pin never lands in
+/// the middle of an update. While pinned, `update` exits with
+/// [`pin::EXIT_PINNED`] and `update-all` skips the app.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - No release of the app is installed
+/// - Lock acquisition fails (another update in progress)
+/// - The pin file cannot be written
+pub fn handle_pin(args: &Args, pin_args: &PinArgs) -> anyhow::Result<()> {
+    let app = args.app()?;
+    let current_tag = version::current_tag(args.install_root(), app)?
+        .ok_or_else(|| anyhow!("No version of {app} is installed; nothing to pin"))?;
 
-    let (downloaded_file, asset_name) = download_and_verify_asset(
-        &release,
-        &asset_pattern,
-        checksum_pattern.as_ref(),
-        update_args.github.token.as_deref(),
-        http_client,
-        update_args.skip_verification,
-    )
-    .await?;
+    let timeout = Duration::from_secs(pin_args.lock_timeout);
+    let _lock = lock::acquire(app, Some(&pin_args.state_directory), Some(timeout))?;
 
-    install_release(
-        &args.install_root,
-        &args.app,
-        tag,
-        &downloaded_file,
-        &asset_name,
-    )?;
+    let pin = Pin {
+        tag: current_tag,
+        pinned_at: Timestamp::now(),
+        reason: pin_args.reason.clone(),
+    };
+    pin::save(&pin_args.state_directory, app, &pin)?;
 
-    let releases_dir = args.install_root.join(&args.app).join("releases");
-    finalize_update(
-        &releases_dir,
-        &state_path,
-        tag,
-        &fetch_result.validators,
-        update_args.restart_command.as_deref(),
-        update_args.retain as usize,
-    )?;
+    args.say(format_args!("Pinned {app} at {}", pin.describe()));
+    Ok(())
+}
 
-    println!("Successfully updated to {tag}");
+/// Handles the `unpin` subcommand to let `update` change the installed release again.
+///
+/// # Errors
+///
+/// Returns an error if the pin file exists but cannot be removed.
+pub fn handle_unpin(args: &Args, unpin_args: &UnpinArgs) -> anyhow::Result<()> {
+    let app = args.app()?;
+    if pin::remove(&unpin_args.state_directory, app)? {
+        args.say(format_args!("Unpinned {app}"));
+    } else {
+        args.say(format_args!("{app} is not pinned"));
+    }
     Ok(())
 }
 
-/// Handles the `version` subcommand to display the currently installed version.
+/// Handles the `login` subcommand: runs the GitHub device authorization flow and
+/// stores the token where later runs with the same state directory find it.
 ///
 /// # Errors
 ///
 /// Returns an error if:
-/// - Installation directory cannot be accessed
-/// - Symlink resolution fails
-pub fn handle_version(args: &Args) -> anyhow::Result<()> {
-    let current_tag = version::current_tag(&args.install_root, &args.app)?;
+/// - GitHub rejects the client ID or scopes
+/// - The user denies authorization or the code expires
+/// - The token cannot be written
+pub async fn handle_login(
+    args: &Args,
+    login_args: &LoginArgs,
+    http_client: reqwest::Client,
+) -> anyhow::Result<()> {
+    let host = login_args.host.as_deref().unwrap_or(DEFAULT_GITHUB_HOST);
+    let hostname = gh_auth::hostname(host);
+    let web_base = login::web_base(host);
 
-    if args.verbose > 0 {
-        version::print_diagnostics(&args.install_root, &args.app, current_tag.as_deref())?;
-    } else if let Some(tag) = current_tag {
-        println!("{tag}");
-    }
+    let code = login::request_code(
+        &http_client,
+        &web_base,
+        &login_args.client_id,
+        &login_args.scope,
+    )
+    .await?;
+    println!("First copy your one-time code: {}", code.user_code);
+    println!(
+        "Then open {} in a browser and enter it (the code expires in {} minutes)",
+        code.verification_uri,
+        code.expires_in / 60
+    );
+
+    let token = login::poll_token(&http_client, &web_base, &login_args.client_id, &code).await?;
+    let path = login::save_token(&login_args.state_directory, &hostname, &token)?;
 
+    args.say(format_args!(
+        "Logged in to {hostname}; token stored in {path}"
+    ));
     Ok(())
 }
 
@@ -583,14 +6488,130 @@ pub fn handle_version(args: &Args) -> anyhow::Result<()> {
 /// Returns an error if:
 /// - The lock file exists but cannot be removed
 pub fn handle_unlock(args: &Args, unlock_args: &UnlockArgs) -> anyhow::Result<()> {
-    info!("Removing lock file for app: {}", args.app);
-    lock::unlock(&args.app, Some(&unlock_args.state_directory))?;
-    println!("Lock file removed for app: {}", args.app);
+    let app = args.app()?;
+    confirm(
+        args,
+        &format!("Forcibly remove the lock for {app}? A running update may be disrupted."),
+    )?;
+    info!("Removing lock file for app: {app}");
+    lock::unlock(app, Some(&unlock_args.state_directory))?;
+    args.say(format_args!("Lock file removed for app: {app}"));
+    Ok(())
+}
+
+/// Handles the `uninstall` subcommand to remove everything distronomicon manages for an app.
+///
+/// Removes the managed symlinks (and link manifest) in the bin directory, the releases and staging
+/// directories, and the state file, then cleans up any directories left empty.
+/// Files in the bin directory that distronomicon did not create are left in place.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The user declines the confirmation prompt
+/// - Lock acquisition fails (another update in progress)
+/// - Any managed path cannot be removed
+pub fn handle_uninstall(args: &Args, uninstall_args: &UninstallArgs) -> anyhow::Result<()> {
+    let app = args.app()?;
+    let install_root = args.install_root();
+    let _span = info_span!("uninstall", app = %app).entered();
+
+    let app_dir = install_root.join(app);
+    let state_app_dir = uninstall_args.state_directory.join(app);
+
+    let bin_dir = app_dir.join("bin");
+    let mut targets = fsops::managed_links(&bin_dir)?;
+    targets.push(bin_dir.join(fsops::LINK_MANIFEST));
+    targets.push(bin_dir.join(fsops::KEPT_MANIFEST));
+    if !uninstall_args.keep_releases {
+        targets.push(app_dir.join("releases"));
+    }
+    targets.push(app_dir.join("staging"));
+    let store = args.state_store(&uninstall_args.state_directory);
+    let mut remove_state = false;
+    if !uninstall_args.keep_state {
+        targets.push(history::path(&uninstall_args.state_directory, app));
+        targets.push(pin::path(&uninstall_args.state_directory, app));
+        targets.push(trust::path(&uninstall_args.state_directory, app));
+        targets.push(pending::path(&uninstall_args.state_directory, app));
+        targets.push(release_cache::path(&uninstall_args.state_directory, app));
+        targets.push(debug_dir(&uninstall_args.state_directory, app));
+        // A JSON state file is removed like any other path; a shared sqlite
+        // database only loses this app's row.
+        if args.state_backend.unwrap_or_default() == state::Backend::Json {
+            targets.push(store.location(app));
+        } else {
+            remove_state = store.contains(app)?;
+        }
+    }
+    targets.retain(|path| path.symlink_metadata().is_ok());
+
+    if uninstall_args.dry_run {
+        for path in &targets {
+            println!("Would remove {path}");
+        }
+        if remove_state {
+            println!("Would remove {app}'s state from {}", store.location(app));
+        }
+        return Ok(());
+    }
+
+    confirm(
+        args,
+        &format!(
+            "Uninstall {app} and remove {} managed path(s)?",
+            targets.len() + usize::from(remove_state)
+        ),
+    )?;
+
+    let timeout = Duration::from_secs(uninstall_args.lock_timeout);
+    let lock = lock::acquire(app, Some(&uninstall_args.state_directory), Some(timeout))?;
+
+    for path in &targets {
+        if path.is_dir() && !path.is_symlink() {
+            fs::remove_dir_all(path)?;
+        } else {
+            fs::remove_file(path)?;
+        }
+        args.say(format_args!("Removed {path}"));
+    }
+    if remove_state && store.remove(app)? {
+        args.say(format_args!(
+            "Removed {app}'s state from {}",
+            store.location(app)
+        ));
+    }
+
+    drop(lock);
+
+    for dir in [bin_dir, app_dir.clone(), state_app_dir] {
+        let _ = fs::remove_dir(&dir);
+    }
+
+    args.say(format_args!("Uninstalled {app}"));
+    Ok(())
+}
+
+/// Handles the hidden `network-helper` subcommand that `update --network-user`
+/// runs as that user: reads one request on stdin, makes it, and writes the
+/// answer, or why it failed, on stdout.
+///
+/// # Errors
+///
+/// Returns an error if the request cannot be read or the answer written.
+pub async fn handle_network_helper() -> anyhow::Result<()> {
+    let job: NetworkJob = privsep::read_request()?;
+    let answer = job.run().await.map_err(|e| format!("{e:#}"));
+    privsep::write_answer(&answer)?;
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use std::os::unix::fs::symlink;
+
+    use camino_tempfile::tempdir;
+
     use super::*;
 
     #[test]
@@ -626,26 +6647,26 @@ mod tests {
         assert!(args.is_ok());
         let args = args.unwrap();
 
-        assert_eq!(args.app, "myapp");
-        assert_eq!(args.install_root, Utf8PathBuf::from("/custom/opt/myapp"));
+        assert_eq!(args.app().unwrap(), "myapp");
+        assert_eq!(args.install_root(), Utf8Path::new("/custom/opt/myapp"));
         assert_eq!(args.verbose, 2);
 
         if let Commands::Update(update_args) = args.command {
-            assert_eq!(update_args.repo, "owner/name");
-            assert_eq!(update_args.pattern, ".*\\.tar\\.gz");
+            assert_eq!(update_args.repo().unwrap(), "owner/name");
+            assert_eq!(update_args.pattern().unwrap(), ".*\\.tar\\.gz");
             assert_eq!(
                 update_args.state_directory,
                 Utf8PathBuf::from("/custom/state")
             );
             assert_eq!(update_args.checksum_pattern.as_deref(), Some("SHA256SUMS"));
             assert_eq!(update_args.github.token.as_deref(), Some("ghp_test123"));
-            assert_eq!(update_args.github.host, "github.example.com");
+            assert_eq!(update_args.github.host(), "github.example.com");
             assert!(update_args.github.allow_prerelease);
             assert_eq!(
                 update_args.restart_command.as_deref(),
                 Some("systemctl restart myapp")
             );
-            assert_eq!(update_args.retain, 5);
+            assert_eq!(update_args.retain(), 5);
             assert!(update_args.skip_verification);
             assert!(!update_args.force_unlock);
             assert_eq!(update_args.lock_timeout, 30);
@@ -670,17 +6691,17 @@ mod tests {
         assert!(args.is_ok());
         let args = args.unwrap();
 
-        assert_eq!(args.app, "myapp");
-        assert_eq!(args.install_root, Utf8PathBuf::from("/opt"));
+        assert_eq!(args.app().unwrap(), "myapp");
+        assert_eq!(args.install_root(), Utf8Path::new("/opt"));
         assert_eq!(args.verbose, 0);
 
         if let Commands::Check(check_args) = args.command {
-            assert_eq!(check_args.repo, "owner/name");
+            assert_eq!(check_args.repo().unwrap(), "owner/name");
             assert_eq!(
                 check_args.state_directory,
                 Utf8PathBuf::from("/var/lib/distronomicon/myapp")
             );
-            assert_eq!(check_args.github.host, "https://api.github.com");
+            assert_eq!(check_args.github.host(), "https://api.github.com");
             assert!(!check_args.github.allow_prerelease);
             assert!(check_args.github.token.is_none());
         } else {
@@ -688,6 +6709,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_apply_sysroot() {
+        let mut args = Args::try_parse_from([
+            "distronomicon",
+            "--app",
+            "myapp",
+            "--sysroot",
+            "/mnt/target",
+            "update",
+            "--repo",
+            "owner/name",
+            "--state-directory",
+            "/var/lib/distronomicon",
+            "--license-dir",
+            "/usr/share/licenses",
+            "--restart-command",
+            "systemctl restart myapp",
+        ])
+        .unwrap();
+
+        let warnings = args.apply_sysroot();
+
+        assert_eq!(args.install_root(), Utf8Path::new("/mnt/target/opt"));
+        assert_eq!(
+            warnings,
+            [
+                "--restart-command is skipped with --sysroot because it would run on this host, not in the target"
+            ]
+        );
+        let Commands::Update(update_args) = args.command else {
+            panic!("Expected Update command");
+        };
+        assert_eq!(
+            update_args.state_directory,
+            "/mnt/target/var/lib/distronomicon"
+        );
+        assert_eq!(
+            update_args.license_dir.as_deref(),
+            Some(Utf8Path::new("/mnt/target/usr/share/licenses"))
+        );
+        assert_eq!(update_args.restart_command, None);
+    }
+
     #[test]
     fn test_reject_app_name_with_slash() {
         let result = Args::try_parse_from([
@@ -786,6 +6850,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_is_affirmative() {
+        for answer in ["y", "Y", "yes", "YES\n", "  yes  "] {
+            assert!(is_affirmative(answer), "{answer:?} should confirm");
+        }
+        for answer in ["", "\n", "n", "no", "yep", "sure"] {
+            assert!(!is_affirmative(answer), "{answer:?} should not confirm");
+        }
+    }
+
     #[test]
     fn test_update_requires_checksum_pattern_unless_skip_verification() {
         let result = Args::try_parse_from([
@@ -801,7 +6875,10 @@ mod tests {
             "/var/lib/distronomicon",
         ]);
 
-        assert!(result.is_err());
+        let Commands::Update(update_args) = result.unwrap().command else {
+            panic!("Expected Update command");
+        };
+        assert!(update_args.checksum_pattern().is_err());
     }
 
     #[test]
@@ -843,4 +6920,290 @@ mod tests {
 
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_merge_config_fills_missing_values() {
+        let mut args = Args::try_parse_from([
+            "distronomicon",
+            "--app",
+            "myapp",
+            "update",
+            "--state-directory",
+            "/var/lib/distronomicon",
+            "--pattern",
+            "cli-.*",
+        ])
+        .unwrap();
+
+        let config = AppConfig {
+            repo: Some("owner/name".to_string()),
+            pattern: Some("config-.*".to_string()),
+            checksum_pattern: Some("SHA256SUMS".to_string()),
+            retain: Some(7),
+            github: config::GitHubSettings {
+                host: Some("https://github.example.com/api/v3".to_string()),
+                allow_prerelease: Some(true),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        args.command.merge_config(&config);
+
+        let Commands::Update(update_args) = args.command else {
+            panic!("Expected Update command");
+        };
+        assert_eq!(update_args.repo().unwrap(), "owner/name");
+        assert_eq!(update_args.pattern().unwrap(), "cli-.*");
+        assert_eq!(update_args.checksum_pattern().unwrap(), Some("SHA256SUMS"));
+        assert_eq!(update_args.retain(), 7);
+        assert_eq!(
+            update_args.github.host(),
+            "https://github.example.com/api/v3"
+        );
+        assert!(update_args.github.allow_prerelease);
+    }
+
+    #[test]
+    fn test_update_pattern_is_optional() {
+        let args = Args::try_parse_from([
+            "distronomicon",
+            "--app",
+            "myapp",
+            "update",
+            "--repo",
+            "owner/myapp",
+            "--state-directory",
+            "/var/lib/distronomicon",
+        ])
+        .unwrap();
+
+        let Commands::Update(update_args) = args.command else {
+            panic!("Expected Update command");
+        };
+        assert_eq!(update_args.pattern(), None);
+    }
+
+    #[test]
+    fn test_update_pattern_expands_platform_placeholders() {
+        let args = Args::try_parse_from([
+            "distronomicon",
+            "--app",
+            "myapp",
+            "update",
+            "--repo",
+            "owner/myapp",
+            "--pattern",
+            r"^myapp-{version}-{os}-{arch}\.tar\.gz$",
+            "--state-directory",
+            "/var/lib/distronomicon",
+        ])
+        .unwrap();
+
+        let Commands::Update(update_args) = args.command else {
+            panic!("Expected Update command");
+        };
+        let pattern = Regex::new(&update_args.pattern().unwrap()).unwrap();
+        assert!(pattern.is_match(&format!(
+            "myapp-v1.2.0-{}-{}.tar.gz",
+            std::env::consts::OS,
+            std::env::consts::ARCH
+        )));
+    }
+
+    #[test]
+    fn test_update_libc_overrides_host() {
+        let args = Args::try_parse_from([
+            "distronomicon",
+            "--app",
+            "myapp",
+            "update",
+            "--repo",
+            "owner/myapp",
+            "--pattern",
+            r"^myapp-{libc}\.tar\.gz$",
+            "--libc",
+            "musl",
+            "--state-directory",
+            "/var/lib/distronomicon",
+        ])
+        .unwrap();
+
+        let Commands::Update(update_args) = args.command else {
+            panic!("Expected Update command");
+        };
+        assert_eq!(update_args.platform().libc, Some(pattern::Libc::Musl));
+        assert_eq!(update_args.pattern().unwrap(), r"^myapp-musl\.tar\.gz$");
+    }
+
+    #[test]
+    fn test_parse_path_expands_home() {
+        let home = Utf8PathBuf::from(env::var("HOME").unwrap());
+        assert_eq!(
+            parse_path("~/distronomicon-test-missing/apps").unwrap(),
+            home.join("distronomicon-test-missing/apps")
+        );
+    }
+
+    #[test]
+    fn test_parse_path_makes_relative_absolute() {
+        let cwd = Utf8PathBuf::try_from(env::current_dir().unwrap()).unwrap();
+        assert_eq!(
+            parse_path("distronomicon-test-missing/apps").unwrap(),
+            cwd.join("distronomicon-test-missing/apps")
+        );
+    }
+
+    #[test]
+    fn test_parse_path_normalizes_components() {
+        assert_eq!(
+            parse_path("/distronomicon-test-missing/a/../b/./c").unwrap(),
+            Utf8PathBuf::from("/distronomicon-test-missing/b/c")
+        );
+    }
+
+    #[test]
+    fn test_parse_path_follows_symlinks_before_parent_components() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize_utf8().unwrap();
+        fs::create_dir_all(root.join("real/nested")).unwrap();
+        fs::create_dir(root.join("dir")).unwrap();
+        symlink(root.join("real/nested"), root.join("dir/link")).unwrap();
+
+        assert_eq!(
+            parse_path(root.join("dir/link/../state").as_str()).unwrap(),
+            root.join("real/state")
+        );
+        assert_eq!(
+            parse_path(root.join("dir/link/missing/../x").as_str()).unwrap(),
+            root.join("real/nested/x")
+        );
+    }
+
+    #[test]
+    fn test_parse_path_rejects_invalid() {
+        assert!(parse_path("").is_err());
+        assert!(parse_path("~other/apps").is_err());
+    }
+
+    #[test]
+    fn test_apply_config_http_overrides() {
+        let mut args = Args::try_parse_from([
+            "distronomicon",
+            "--app",
+            "myapp",
+            "--http-retries",
+            "1",
+            "update",
+            "--state-directory",
+            "/var/lib/distronomicon",
+        ])
+        .unwrap();
+
+        args.apply_config(&AppConfig {
+            http_timeout: Some(900),
+            http_retries: Some(5),
+            stall_timeout: Some(60),
+            github: config::GitHubSettings {
+                rate_limit_wait: Some(120),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        assert_eq!(args.http_timeout(), Duration::from_secs(900));
+        let Commands::Update(update_args) = &args.command else {
+            panic!("Expected Update command");
+        };
+        let limits = args.transfer_limits(&update_args.github);
+        assert_eq!(limits.max_retries, 1);
+        assert_eq!(limits.stall_timeout, Some(Duration::from_secs(60)));
+        assert_eq!(limits.rate_limit_wait, Duration::from_secs(120));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MiB");
+    }
+
+    #[test]
+    fn test_flag_warnings() {
+        let args = Args::try_parse_from([
+            "distronomicon",
+            "--app",
+            "myapp",
+            "--http-timeout",
+            "10",
+            "--stall-timeout",
+            "5",
+            "check",
+            "--state-directory",
+            "/var/lib/distronomicon",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.flag_warnings(),
+            vec!["--stall-timeout has no effect on check"]
+        );
+
+        let args = Args::try_parse_from([
+            "distronomicon",
+            "--app",
+            "myapp",
+            "update",
+            "--state-directory",
+            "/var/lib/distronomicon",
+            "--checksum-pattern",
+            "SHA256SUMS",
+            "--skip-verification",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.flag_warnings(),
+            vec!["--checksum-pattern is ignored because --skip-verification is set"]
+        );
+
+        let args = Args::try_parse_from([
+            "distronomicon",
+            "--app",
+            "myapp",
+            "--http-retries",
+            "2",
+            "version",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.flag_warnings(),
+            vec!["--http-retries has no effect on version"]
+        );
+    }
+
+    #[test]
+    fn test_ownership_warning() {
+        let dir = Utf8Path::new("/opt");
+
+        let warning = ownership_warning("install root", dir, 0, 1000, true).unwrap();
+        assert!(warning.starts_with("running as root but install root /opt is owned by uid 1000"));
+
+        let warning = ownership_warning("install root", dir, 1000, 0, false).unwrap();
+        assert!(warning.starts_with("running as uid 1000 but install root /opt is owned by uid 0"));
+
+        assert_eq!(ownership_warning("install root", dir, 0, 0, true), None);
+        assert_eq!(
+            ownership_warning("install root", dir, 1000, 1000, true),
+            None
+        );
+        assert_eq!(ownership_warning("install root", dir, 1000, 0, true), None);
+    }
+
+    #[test]
+    fn test_reject_zero_timeouts() {
+        for flag in ["--http-timeout", "--stall-timeout"] {
+            let result =
+                Args::try_parse_from(["distronomicon", "--app", "myapp", flag, "0", "version"]);
+            assert!(result.is_err(), "{flag} 0 should be rejected");
+        }
+    }
 }
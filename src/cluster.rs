@@ -0,0 +1,420 @@
+use std::{
+    fmt, fs,
+    io::{self, Write as _},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow, bail};
+use camino::{Utf8Path, Utf8PathBuf};
+use camino_tempfile::NamedUtf8TempFile;
+use jiff::{SignedDuration, Timestamp};
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+const MAX_POLL_DELAY: Duration = Duration::from_secs(15);
+
+/// Where hosts of a cluster coordinate their update slots.
+///
+/// A directory on shared storage (NFS, CephFS, ...) holds one lease file per slot at
+/// `<dir>/<app>/slot-<n>.json`, created with a hard link so that only one host can
+/// claim a slot even where `flock` does not reach across hosts.
+///
+/// An HTTP endpoint implements the lease protocol itself:
+/// `PUT <url>/<app>/<member>` with `{"slots": N, "ttl_secs": S}` answers 2xx when
+/// the member holds a slot and 409 or 423 while all slots are taken, and
+/// `DELETE <url>/<app>/<member>` releases it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Backend {
+    Directory(Utf8PathBuf),
+    Http(Url),
+}
+
+impl Backend {
+    /// Parses an `http://` or `https://` URL as a lease endpoint and anything else
+    /// as a directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an `http(s)://` value is not a valid URL.
+    pub fn parse(value: &str) -> Result<Self> {
+        if value.starts_with("http://") || value.starts_with("https://") {
+            let url = Url::parse(value.trim_end_matches('/'))
+                .map_err(|e| anyhow!("invalid cluster lease URL {value}: {e}"))?;
+            Ok(Self::Http(url))
+        } else {
+            Ok(Self::Directory(Utf8PathBuf::from(value)))
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Directory(dir) => write!(f, "{dir}"),
+            Self::Http(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+/// The contents of a lease file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeaseRecord {
+    pub member: String,
+    pub acquired_at: Timestamp,
+    pub expires_at: Timestamp,
+}
+
+/// One host's view of its app's update slots.
+#[derive(Debug, Clone)]
+pub struct Coordinator {
+    pub backend: Backend,
+    pub app: String,
+    /// This host's name among the cluster members.
+    pub member: String,
+    /// How many members may update at once.
+    pub slots: u32,
+    pub lease_ttl: Duration,
+}
+
+/// A held update slot.
+///
+/// Only [`Lease::release`] gives the slot back. A lease dropped without it (the
+/// update, restart, or health check failed) stays held until it expires, which
+/// pauses the rollout on the other hosts until someone looks.
+#[must_use = "a lease is held until released or expired"]
+pub struct Lease {
+    coordinator: Coordinator,
+    slot: Option<Utf8PathBuf>,
+    expires_at: Timestamp,
+    released: bool,
+}
+
+impl Coordinator {
+    /// Waits up to `timeout` for a free slot and claims it.
+    ///
+    /// Slots whose lease has expired are taken over, as is a slot this member
+    /// already holds from an earlier, failed run.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no slot frees up within `timeout`, the lease directory
+    /// cannot be written, or the lease endpoint fails.
+    pub async fn acquire(&self, client: &reqwest::Client, timeout: Duration) -> Result<Lease> {
+        let started = Instant::now();
+        let mut delay = Duration::from_secs(1);
+        let mut logged = false;
+        loop {
+            let now = Timestamp::now();
+            let expires_at = now + SignedDuration::try_from(self.lease_ttl)?;
+            let record = LeaseRecord {
+                member: self.member.clone(),
+                acquired_at: now,
+                expires_at,
+            };
+            let claimed = match &self.backend {
+                Backend::Directory(dir) => {
+                    self.claim_file(&dir.join(&self.app), &record)?.map(Some)
+                }
+                Backend::Http(url) => self.claim_http(client, url).await?.then_some(None),
+            };
+            if let Some(slot) = claimed {
+                info!(
+                    "Holding a cluster update slot for {} until {expires_at}",
+                    self.app
+                );
+                return Ok(Lease {
+                    coordinator: self.clone(),
+                    slot,
+                    expires_at,
+                    released: false,
+                });
+            }
+
+            if started.elapsed() >= timeout {
+                bail!(
+                    "all {} cluster update slot(s) for {} at {} stayed taken for {}s",
+                    self.slots,
+                    self.app,
+                    self.backend,
+                    timeout.as_secs()
+                );
+            }
+            if !logged {
+                info!(
+                    "Waiting for one of {} cluster update slot(s) for {}",
+                    self.slots, self.app
+                );
+                logged = true;
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_POLL_DELAY);
+        }
+    }
+
+    /// Claims the first free slot file under `dir`, returning it.
+    fn claim_file(&self, dir: &Utf8Path, record: &LeaseRecord) -> Result<Option<Utf8PathBuf>> {
+        fs::create_dir_all(dir)?;
+        let mut temp_file = NamedUtf8TempFile::new_in(dir)?;
+        temp_file.write_all((serde_json::to_string_pretty(record)? + "\n").as_bytes())?;
+        temp_file.as_file().sync_all()?;
+
+        let is_live =
+            |held: &LeaseRecord| held.member != self.member && held.expires_at > record.acquired_at;
+        for n in 0..self.slots {
+            let slot = dir.join(format!("slot-{n}.json"));
+            match fs::hard_link(temp_file.path(), &slot) {
+                Ok(()) => return Ok(Some(slot)),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+            if read_lease(&slot)?.as_ref().is_some_and(is_live) {
+                continue;
+            }
+
+            // Move the expired lease aside rather than deleting it, so that a lease
+            // another host claimed since it was read can be told apart and put back.
+            let aside = dir.join(format!(".slot-{n}.{}.expired", self.member));
+            match fs::rename(&slot, &aside) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+            match read_lease(&aside)? {
+                Some(held) if is_live(&held) => {
+                    let _ = fs::hard_link(&aside, &slot);
+                    let _ = fs::remove_file(&aside);
+                    continue;
+                }
+                Some(held) if held.member != self.member => warn!(
+                    "Took over cluster slot {slot} from {}, whose lease expired at {}",
+                    held.member, held.expires_at
+                ),
+                _ => {}
+            }
+            let _ = fs::remove_file(&aside);
+            match fs::hard_link(temp_file.path(), &slot) {
+                Ok(()) => return Ok(Some(slot)),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(None)
+    }
+
+    async fn claim_http(&self, client: &reqwest::Client, url: &Url) -> Result<bool> {
+        let response = client
+            .put(self.member_url(url))
+            .json(&serde_json::json!({
+                "slots": self.slots,
+                "ttl_secs": self.lease_ttl.as_secs(),
+            }))
+            .send()
+            .await?;
+        match response.status() {
+            status if status.is_success() => Ok(true),
+            StatusCode::CONFLICT | StatusCode::LOCKED => Ok(false),
+            _ => Err(response.error_for_status().err().map_or_else(
+                || anyhow!("unexpected response from cluster lease endpoint"),
+                Into::into,
+            )),
+        }
+    }
+
+    fn member_url(&self, url: &Url) -> String {
+        format!("{url}/{}/{}", self.app, self.member)
+    }
+}
+
+impl Lease {
+    /// When the lease lapses if it is not released.
+    #[must_use]
+    pub fn expires_at(&self) -> Timestamp {
+        self.expires_at
+    }
+
+    /// Gives the slot back so the next host can update.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lease file cannot be removed or the lease endpoint
+    /// fails. The lease then expires on its own.
+    pub async fn release(mut self, client: &reqwest::Client) -> Result<()> {
+        self.released = true;
+        match (&self.coordinator.backend, &self.slot) {
+            (Backend::Directory(_), Some(slot)) => {
+                // Another host may have taken over an expired lease in the meantime.
+                if read_lease(slot)?.is_some_and(|held| held.member == self.coordinator.member) {
+                    fs::remove_file(slot)?;
+                }
+            }
+            (Backend::Http(url), _) => {
+                let response = client
+                    .delete(self.coordinator.member_url(url))
+                    .send()
+                    .await?;
+                if response.status() != StatusCode::NOT_FOUND {
+                    response.error_for_status()?;
+                }
+            }
+            (Backend::Directory(_), None) => {}
+        }
+        info!(
+            "Released the cluster update slot for {}",
+            self.coordinator.app
+        );
+        Ok(())
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        if !self.released {
+            warn!(
+                "Keeping the cluster update slot for {} until {} so other hosts do not continue the rollout",
+                self.coordinator.app, self.expires_at
+            );
+        }
+    }
+}
+
+/// Reads a lease file, treating a missing or unreadable one as free.
+fn read_lease(path: &Utf8Path) -> Result<Option<LeaseRecord>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(serde_json::from_str(&contents).ok()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// This host's name, used as its cluster member name by default.
+#[must_use]
+pub fn hostname() -> String {
+    rustix::system::uname()
+        .nodename()
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use camino_tempfile::tempdir;
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{body_json, method, path},
+    };
+
+    use super::*;
+
+    fn coordinator(backend: Backend, member: &str, slots: u32) -> Coordinator {
+        Coordinator {
+            backend,
+            app: "myapp".to_string(),
+            member: member.to_string(),
+            slots,
+            lease_ttl: Duration::from_secs(1800),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_directory_slots_limit_concurrent_members() {
+        let temp_dir = tempdir().unwrap();
+        let backend = Backend::Directory(temp_dir.path().to_path_buf());
+        let client = reqwest::Client::new();
+
+        let a = coordinator(backend.clone(), "a", 2)
+            .acquire(&client, Duration::ZERO)
+            .await
+            .unwrap();
+        let _b = coordinator(backend.clone(), "b", 2)
+            .acquire(&client, Duration::ZERO)
+            .await
+            .unwrap();
+        let error = coordinator(backend.clone(), "c", 2)
+            .acquire(&client, Duration::ZERO)
+            .await
+            .err()
+            .unwrap();
+        assert!(
+            error
+                .to_string()
+                .starts_with("all 2 cluster update slot(s) for myapp"),
+            "{error}"
+        );
+
+        a.release(&client).await.unwrap();
+        let c = coordinator(backend, "c", 2)
+            .acquire(&client, Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(
+            read_lease(&temp_dir.path().join("myapp/slot-0.json"))
+                .unwrap()
+                .unwrap()
+                .member,
+            "c"
+        );
+        assert!(c.expires_at() > Timestamp::now());
+    }
+
+    #[tokio::test]
+    async fn test_directory_takes_over_expired_and_own_leases() {
+        let temp_dir = tempdir().unwrap();
+        let slot = temp_dir.path().join("myapp/slot-0.json");
+        fs::create_dir_all(slot.parent().unwrap()).unwrap();
+        let backend = Backend::Directory(temp_dir.path().to_path_buf());
+        let client = reqwest::Client::new();
+
+        let expired = LeaseRecord {
+            member: "a".to_string(),
+            acquired_at: Timestamp::from_second(1_000_000_000).unwrap(),
+            expires_at: Timestamp::from_second(1_000_001_800).unwrap(),
+        };
+        fs::write(&slot, serde_json::to_string(&expired).unwrap()).unwrap();
+        let lease = coordinator(backend.clone(), "b", 1)
+            .acquire(&client, Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(read_lease(&slot).unwrap().unwrap().member, "b");
+
+        // A failed run keeps its slot; the same member's next run takes it back.
+        drop(lease);
+        let lease = coordinator(backend, "b", 1)
+            .acquire(&client, Duration::ZERO)
+            .await
+            .unwrap();
+        lease.release(&client).await.unwrap();
+        assert!(!slot.exists());
+    }
+
+    #[tokio::test]
+    async fn test_http_lease_waits_while_busy() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(path("/leases/myapp/web-1"))
+            .and(body_json(serde_json::json!({"slots": 1, "ttl_secs": 1800})))
+            .respond_with(ResponseTemplate::new(409))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/leases/myapp/web-1"))
+            .respond_with(ResponseTemplate::new(201))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/leases/myapp/web-1"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let backend = Backend::parse(&format!("{}/leases/", mock_server.uri())).unwrap();
+        let client = reqwest::Client::new();
+        let lease = coordinator(backend, "web-1", 1)
+            .acquire(&client, Duration::from_secs(10))
+            .await
+            .unwrap();
+        lease.release(&client).await.unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+use std::{collections::BTreeSet, env, fs};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use clap_complete::engine::CompletionCandidate;
+
+use crate::{DEFAULT_INSTALL_ROOT, config, fsops, version};
+
+/// Environment variable that switches the binary into completion mode.
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+const APPS_CONFIG_FILE: &str = "apps.toml";
+
+/// Candidates for `--app`: every app with a config file, an entry in the multi-app
+/// config, a state directory, or a releases directory under the install root.
+#[must_use]
+pub fn app_candidates() -> Vec<CompletionCandidate> {
+    let line = command_line();
+    let state_directory = flag_value(&line, "--state-directory")
+        .or_else(|| env::var("STATE_DIRECTORY").ok())
+        .map(Utf8PathBuf::from);
+
+    app_names(
+        Utf8Path::new(config::DEFAULT_CONFIG_DIR),
+        state_directory.as_deref(),
+        &install_root(&line),
+    )
+    .into_iter()
+    .map(CompletionCandidate::new)
+    .collect()
+}
+
+/// Candidates for `rollback --to`: the releases retained for the app named by
+/// `--app`, newest first, with the active one marked.
+#[must_use]
+pub fn tag_candidates() -> Vec<CompletionCandidate> {
+    let line = command_line();
+    let Some(app) = flag_value(&line, "--app") else {
+        return Vec::new();
+    };
+
+    retained_tags(&install_root(&line), &app)
+        .into_iter()
+        .enumerate()
+        .map(|(order, (tag, active))| {
+            CompletionCandidate::new(tag)
+                .help(active.then(|| "active".into()))
+                .display_order(Some(order))
+        })
+        .collect()
+}
+
+/// Names of the apps known from `config_dir`, `state_directory` and `install_root`,
+/// sorted and deduplicated.
+#[must_use]
+pub fn app_names(
+    config_dir: &Utf8Path,
+    state_directory: Option<&Utf8Path>,
+    install_root: &Utf8Path,
+) -> Vec<String> {
+    let mut names = BTreeSet::new();
+
+    let apps_config = config_dir.join(APPS_CONFIG_FILE);
+    for path in dir_entries(config_dir) {
+        if path.extension() == Some("toml")
+            && path != apps_config
+            && let Some(stem) = path.file_stem()
+        {
+            names.insert(stem.to_string());
+        }
+    }
+    if let Ok(Some(apps)) = config::load_apps(&apps_config) {
+        names.extend(apps.apps.into_keys());
+    }
+
+    if let Some(state_directory) = state_directory {
+        for path in dir_entries(state_directory) {
+            if path.is_dir()
+                && let Some(name) = path.file_name()
+            {
+                names.insert(name.to_string());
+            }
+        }
+    }
+
+    for path in dir_entries(install_root) {
+        if path.join("releases").is_dir()
+            && let Some(name) = path.file_name()
+        {
+            names.insert(name.to_string());
+        }
+    }
+
+    names.into_iter().collect()
+}
+
+/// Tags retained under `<install_root>/<app>/releases`, newest first, each paired
+/// with whether it is the active release.
+#[must_use]
+pub fn retained_tags(install_root: &Utf8Path, app: &str) -> Vec<(String, bool)> {
+    let active = version::current_tag(install_root, app).ok().flatten();
+    fsops::list_releases(install_root.join(app).join("releases"))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(tag, _)| {
+            let is_active = active.as_deref() == Some(tag.as_str());
+            (tag, is_active)
+        })
+        .collect()
+}
+
+fn dir_entries(dir: &Utf8Path) -> Vec<Utf8PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| Utf8PathBuf::try_from(entry.ok()?.path()).ok())
+        .collect()
+}
+
+/// The command line being completed: the words after the `--` the shell passes.
+fn command_line() -> Vec<String> {
+    env::args().skip_while(|arg| arg != "--").skip(1).collect()
+}
+
+fn install_root(line: &[String]) -> Utf8PathBuf {
+    flag_value(line, "--install-root")
+        .or_else(|| env::var("DISTRONOMICON_INSTALL_ROOT").ok())
+        .unwrap_or_else(|| DEFAULT_INSTALL_ROOT.to_string())
+        .into()
+}
+
+/// The value given to `flag` as `--flag value` or `--flag=value`, last one wins.
+fn flag_value(line: &[String], flag: &str) -> Option<String> {
+    let mut value = None;
+    let mut words = line.iter();
+    while let Some(word) = words.next() {
+        if word == flag {
+            value = words.next().cloned();
+        } else if let Some(inline) = word
+            .strip_prefix(flag)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            value = Some(inline.to_string());
+        }
+    }
+    value.filter(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use camino_tempfile::tempdir;
+
+    use super::*;
+
+    fn words(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_flag_value() {
+        let line = words("distronomicon --app myapp rollback --to");
+        assert_eq!(flag_value(&line, "--app").as_deref(), Some("myapp"));
+        assert_eq!(flag_value(&line, "--to"), None);
+
+        let line = words("distronomicon --install-root=/srv --app-x y");
+        assert_eq!(flag_value(&line, "--install-root").as_deref(), Some("/srv"));
+        assert_eq!(flag_value(&line, "--app"), None);
+    }
+
+    #[test]
+    fn test_app_names_merges_sources() {
+        let temp_dir = tempdir().unwrap();
+        let config_dir = temp_dir.path().join("etc");
+        let state_dir = temp_dir.path().join("state");
+        let install_root = temp_dir.path().join("opt");
+
+        fs::create_dir_all(&config_dir).unwrap();
+        fs::write(config_dir.join("alpha.toml"), "").unwrap();
+        fs::write(config_dir.join("notes.txt"), "").unwrap();
+        fs::write(config_dir.join("apps.toml"), "[apps.beta]\n[apps.alpha]\n").unwrap();
+        fs::create_dir_all(state_dir.join("gamma")).unwrap();
+        fs::create_dir_all(install_root.join("delta/releases")).unwrap();
+        fs::create_dir_all(install_root.join("unmanaged/bin")).unwrap();
+
+        let names = app_names(&config_dir, Some(&state_dir), &install_root);
+
+        assert_eq!(names, ["alpha", "beta", "delta", "gamma"]);
+    }
+}
@@ -0,0 +1,681 @@
+use std::{collections::BTreeMap, fmt, fs, io};
+
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use thiserror::Error;
+
+use crate::{
+    extract, fsops,
+    github::{Channel, ReleaseAge},
+    pattern, proxy, state,
+    tls::SpkiPin,
+    trust,
+};
+
+/// Directory searched for `<app>.toml` when `--config` is not given.
+pub const DEFAULT_CONFIG_DIR: &str = "/etc/distronomicon";
+
+/// Multi-app config read by `update-all` when `--config` is not given.
+pub const DEFAULT_APPS_CONFIG: &str = "/etc/distronomicon/apps.toml";
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read config file {path}: {source}")]
+    Io {
+        path: Utf8PathBuf,
+        source: io::Error,
+    },
+
+    #[error("invalid config file {path}: {source}")]
+    Parse {
+        path: Utf8PathBuf,
+        source: toml::de::Error,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, ConfigError>;
+
+/// Per-app settings read from a TOML config file.
+///
+/// Every field is optional; values given on the command line (or through the
+/// corresponding environment variables) take precedence over the file.
+///
+/// Named `[profiles.<name>]` sections hold the same settings and are laid over the
+/// rest of the file when selected with `--profile`:
+///
+/// ```toml
+/// repo = "owner/myapp"
+/// retain = 3
+///
+/// [profiles.canary]
+/// retain = 1
+/// restart_command = "systemctl restart myapp"
+///
+/// [profiles.canary.github]
+/// allow_prerelease = true
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    pub repo: Option<String>,
+    pub pattern: Option<String>,
+    /// Exact asset file name, used instead of `pattern`.
+    pub asset_name: Option<String>,
+    /// C library whose build to prefer: `gnu` or `musl`.
+    pub libc: Option<pattern::Libc>,
+    pub checksum_pattern: Option<String>,
+    pub restart_command: Option<String>,
+    pub retain: Option<u32>,
+    pub retain_max_size: Option<fsops::ByteSize>,
+    pub install_root: Option<Utf8PathBuf>,
+    pub http_timeout: Option<u64>,
+    pub http_retries: Option<u32>,
+    pub stall_timeout: Option<u64>,
+    pub spki_pins: Vec<SpkiPin>,
+    pub state_backend: Option<state::Backend>,
+    pub proxy_mode: Option<proxy::Mode>,
+    pub health_command: Option<String>,
+    /// Shared directory or HTTP lease endpoint coordinating rolling updates.
+    pub cluster: Option<String>,
+    pub cluster_slots: Option<u32>,
+    /// Webhook receiving the outcome of each update.
+    pub notify_url: Option<String>,
+    pub notify_template: Option<Utf8PathBuf>,
+    pub on_missing_binary: Option<fsops::MissingBinary>,
+    pub link_filter: Option<fsops::LinkFilter>,
+    pub max_link_depth: Option<u32>,
+    /// Threads decoding a multi-frame `.tar.zst` release.
+    pub zstd_threads: Option<u32>,
+    /// How extraction is confined: `auto`, `require`, or `off`.
+    pub extract_sandbox: Option<extract::Sandbox>,
+    /// User `update` makes its network requests as when run as root.
+    pub network_user: Option<String>,
+    /// Commands each release must link from `bin/`.
+    pub required_bins: Vec<String>,
+    pub license_dir: Option<Utf8PathBuf>,
+    pub trust_policy: Option<trust::Policy>,
+    pub mirrors: Vec<String>,
+    /// Further assets of each release, each extracted into its own subdirectory.
+    pub extra_assets: Vec<ExtraAsset>,
+    pub github: GitHubSettings,
+    pub maintenance: MaintenanceSettings,
+    pub profiles: BTreeMap<String, AppConfig>,
+}
+
+impl AppConfig {
+    /// This config with the settings of profile `name` taking precedence, or `None`
+    /// if the file defines no such profile.
+    ///
+    /// An empty `restart_command` in the profile disables a restart command set
+    /// outside it.
+    #[must_use]
+    pub fn with_profile(&self, name: &str) -> Option<AppConfig> {
+        let profile = self.profiles.get(name)?;
+        // A profile naming any token source replaces all of them, so a profile's
+        // token file is not shadowed by a token set outside it.
+        let token_source = if profile.github.token.is_some()
+            || profile.github.token_file.is_some()
+            || profile.github.token_command.is_some()
+            || profile.github.token_keyring.is_some()
+            || profile.github.token_from_gh.is_some()
+        {
+            &profile.github
+        } else {
+            &self.github
+        };
+        // `pattern` and `asset_name` select the asset together, so a profile setting
+        // either replaces both.
+        let asset_selector = if profile.pattern.is_some() || profile.asset_name.is_some() {
+            profile
+        } else {
+            self
+        };
+        Some(AppConfig {
+            repo: profile.repo.clone().or_else(|| self.repo.clone()),
+            pattern: asset_selector.pattern.clone(),
+            asset_name: asset_selector.asset_name.clone(),
+            libc: profile.libc.or(self.libc),
+            checksum_pattern: profile
+                .checksum_pattern
+                .clone()
+                .or_else(|| self.checksum_pattern.clone()),
+            restart_command: match profile.restart_command.as_deref() {
+                Some("") => None,
+                Some(command) => Some(command.to_string()),
+                None => self.restart_command.clone(),
+            },
+            retain: profile.retain.or(self.retain),
+            retain_max_size: profile.retain_max_size.or(self.retain_max_size),
+            install_root: profile
+                .install_root
+                .clone()
+                .or_else(|| self.install_root.clone()),
+            http_timeout: profile.http_timeout.or(self.http_timeout),
+            http_retries: profile.http_retries.or(self.http_retries),
+            stall_timeout: profile.stall_timeout.or(self.stall_timeout),
+            spki_pins: if profile.spki_pins.is_empty() {
+                self.spki_pins.clone()
+            } else {
+                profile.spki_pins.clone()
+            },
+            state_backend: profile.state_backend.or(self.state_backend),
+            proxy_mode: profile.proxy_mode.or(self.proxy_mode),
+            health_command: profile
+                .health_command
+                .clone()
+                .or_else(|| self.health_command.clone()),
+            cluster: profile.cluster.clone().or_else(|| self.cluster.clone()),
+            cluster_slots: profile.cluster_slots.or(self.cluster_slots),
+            notify_url: profile
+                .notify_url
+                .clone()
+                .or_else(|| self.notify_url.clone()),
+            notify_template: profile
+                .notify_template
+                .clone()
+                .or_else(|| self.notify_template.clone()),
+            on_missing_binary: profile.on_missing_binary.or(self.on_missing_binary),
+            link_filter: profile.link_filter.or(self.link_filter),
+            max_link_depth: profile.max_link_depth.or(self.max_link_depth),
+            zstd_threads: profile.zstd_threads.or(self.zstd_threads),
+            extract_sandbox: profile.extract_sandbox.or(self.extract_sandbox),
+            network_user: profile
+                .network_user
+                .clone()
+                .or_else(|| self.network_user.clone()),
+            license_dir: profile
+                .license_dir
+                .clone()
+                .or_else(|| self.license_dir.clone()),
+            trust_policy: profile.trust_policy.or(self.trust_policy),
+            required_bins: if profile.required_bins.is_empty() {
+                self.required_bins.clone()
+            } else {
+                profile.required_bins.clone()
+            },
+            mirrors: if profile.mirrors.is_empty() {
+                self.mirrors.clone()
+            } else {
+                profile.mirrors.clone()
+            },
+            extra_assets: if profile.extra_assets.is_empty() {
+                self.extra_assets.clone()
+            } else {
+                profile.extra_assets.clone()
+            },
+            github: GitHubSettings {
+                token: token_source.token.clone(),
+                token_file: token_source.token_file.clone(),
+                token_command: token_source.token_command.clone(),
+                token_keyring: token_source.token_keyring.clone(),
+                token_from_gh: token_source.token_from_gh,
+                host: profile
+                    .github
+                    .host
+                    .clone()
+                    .or_else(|| self.github.host.clone()),
+                allow_prerelease: profile
+                    .github
+                    .allow_prerelease
+                    .or(self.github.allow_prerelease),
+                channel: profile.github.channel.or(self.github.channel),
+                fallback_to_tags: profile
+                    .github
+                    .fallback_to_tags
+                    .or(self.github.fallback_to_tags),
+                rate_limit_wait: profile
+                    .github
+                    .rate_limit_wait
+                    .or(self.github.rate_limit_wait),
+                tag_pattern: profile
+                    .github
+                    .tag_pattern
+                    .clone()
+                    .or_else(|| self.github.tag_pattern.clone()),
+                version_constraint: profile
+                    .github
+                    .version_constraint
+                    .clone()
+                    .or_else(|| self.github.version_constraint.clone()),
+                version_prefix: profile
+                    .github
+                    .version_prefix
+                    .clone()
+                    .or_else(|| self.github.version_prefix.clone()),
+                min_release_age: profile
+                    .github
+                    .min_release_age
+                    .or(self.github.min_release_age),
+                // A profile adds to the blocklist rather than lifting it.
+                skip_versions: self
+                    .github
+                    .skip_versions
+                    .iter()
+                    .chain(&profile.github.skip_versions)
+                    .cloned()
+                    .collect(),
+                allow_versions: profile
+                    .github
+                    .allow_versions
+                    .clone()
+                    .or_else(|| self.github.allow_versions.clone()),
+            },
+            maintenance: profile.maintenance.or(self.maintenance),
+            profiles: BTreeMap::new(),
+        })
+    }
+}
+
+/// Limits on what accumulates in an app's state directory, from the
+/// `[maintenance]` table; see [`crate::maintenance::Limits`] for the defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaintenanceSettings {
+    /// Newest install history entries kept.
+    pub history_keep: Option<u32>,
+    /// Days install history entries are kept.
+    pub history_max_days: Option<u64>,
+    /// Days saved debug responses are kept.
+    pub debug_max_days: Option<u64>,
+    /// Total size of saved debug responses kept.
+    pub debug_max_size: Option<fsops::ByteSize>,
+}
+
+impl MaintenanceSettings {
+    /// These settings, falling back to `base` for the ones left unset.
+    #[must_use]
+    pub fn or(self, base: Self) -> Self {
+        Self {
+            history_keep: self.history_keep.or(base.history_keep),
+            history_max_days: self.history_max_days.or(base.history_max_days),
+            debug_max_days: self.debug_max_days.or(base.debug_max_days),
+            debug_max_size: self.debug_max_size.or(base.debug_max_size),
+        }
+    }
+}
+
+/// A config file describing several apps, keyed by app name.
+///
+/// ```toml
+/// [apps.myapp]
+/// repo = "owner/myapp"
+/// pattern = 'myapp-.*\.tar\.gz'
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppsConfig {
+    pub apps: BTreeMap<String, AppConfig>,
+}
+
+/// An asset installed along with the main one, from an `[[extra_assets]]` table:
+///
+/// ```toml
+/// [[extra_assets]]
+/// pattern = 'myapp-plugins-.*\.tar\.gz'
+/// dest = "plugins"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExtraAsset {
+    /// Regex selecting the asset among the release's assets.
+    pub pattern: String,
+    /// Directory of the release the asset is extracted into.
+    pub dest: ReleaseSubdir,
+}
+
+/// A relative path inside a release directory, free of `..`, `.`, and absolute
+/// components.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ReleaseSubdir(Utf8PathBuf);
+
+impl ReleaseSubdir {
+    #[must_use]
+    pub fn as_path(&self) -> &Utf8Path {
+        &self.0
+    }
+}
+
+impl AsRef<Utf8Path> for ReleaseSubdir {
+    fn as_ref(&self) -> &Utf8Path {
+        &self.0
+    }
+}
+
+impl fmt::Display for ReleaseSubdir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl TryFrom<String> for ReleaseSubdir {
+    type Error = String;
+
+    fn try_from(s: String) -> std::result::Result<Self, String> {
+        let path = Utf8PathBuf::from(s);
+        let is_plain = path.components().count() > 0
+            && path
+                .components()
+                .all(|component| matches!(component, Utf8Component::Normal(_)));
+        if is_plain {
+            Ok(Self(path))
+        } else {
+            Err(format!(
+                "{path:?} must be a relative directory inside the release, without '..'"
+            ))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GitHubSettings {
+    pub token: Option<String>,
+    /// File holding the token, read when `token` is unset.
+    pub token_file: Option<Utf8PathBuf>,
+    /// Shell command printing the token, run when `token` and `token_file` are unset.
+    pub token_command: Option<String>,
+    /// OS keyring entry holding the token, read when no source above is set.
+    pub token_keyring: Option<String>,
+    /// Use the `gh` CLI's token when no other token source is set.
+    pub token_from_gh: Option<bool>,
+    pub host: Option<String>,
+    pub allow_prerelease: Option<bool>,
+    /// Release channel to follow; takes precedence over `allow_prerelease`.
+    pub channel: Option<Channel>,
+    pub fallback_to_tags: Option<bool>,
+    /// Seconds to wait for an exhausted GitHub rate limit to reset.
+    pub rate_limit_wait: Option<u64>,
+    /// Regex selecting release tags on GitHub, or finding them in Bitbucket and
+    /// bucket file names.
+    pub tag_pattern: Option<String>,
+    /// Semver range release versions must satisfy, such as `^1.4`.
+    pub version_constraint: Option<String>,
+    /// Prefix stripped from tags before reading them as versions.
+    pub version_prefix: Option<String>,
+    /// How long a release must have been published before it is installed.
+    pub min_release_age: Option<ReleaseAge>,
+    /// Known-bad release tags or versions that are never installed.
+    pub skip_versions: Vec<String>,
+    /// Regex every installed release tag must match.
+    pub allow_versions: Option<String>,
+}
+
+/// Returns the default config path for an app: `/etc/distronomicon/<app>.toml`.
+#[must_use]
+pub fn default_path(app: &str) -> Utf8PathBuf {
+    Utf8Path::new(DEFAULT_CONFIG_DIR).join(format!("{app}.toml"))
+}
+
+/// Loads an app config from a TOML file.
+///
+/// Returns `Ok(None)` if the file does not exist.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The file exists but cannot be read
+/// - The contents are not valid TOML or contain unknown keys
+pub fn load<P: AsRef<Utf8Path>>(path: P) -> Result<Option<AppConfig>> {
+    read(path.as_ref())
+}
+
+/// Loads a multi-app config from a TOML file.
+///
+/// Returns `Ok(None)` if the file does not exist.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The file exists but cannot be read
+/// - The contents are not valid TOML or contain unknown keys
+pub fn load_apps<P: AsRef<Utf8Path>>(path: P) -> Result<Option<AppsConfig>> {
+    read(path.as_ref())
+}
+
+fn read<T: DeserializeOwned>(path: &Utf8Path) -> Result<Option<T>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(source) => {
+            return Err(ConfigError::Io {
+                path: path.to_owned(),
+                source,
+            });
+        }
+    };
+
+    let config = toml::from_str(&contents).map_err(|source| ConfigError::Parse {
+        path: path.to_owned(),
+        source,
+    })?;
+    Ok(Some(config))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches;
+
+    use camino_tempfile::tempdir;
+    use camino_tempfile_ext::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn test_load_missing_file() {
+        let temp_dir = tempdir().unwrap();
+        let result = load(temp_dir.child("myapp.toml")).unwrap();
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn test_load_full_config() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.child("myapp.toml");
+        config_path
+            .write_str(
+                r#"
+repo = "owner/myapp"
+pattern = 'myapp-.*\.tar\.gz'
+checksum_pattern = "SHA256SUMS"
+restart_command = "systemctl restart myapp"
+retain = 5
+retain_max_size = "20GiB"
+install_root = "/srv"
+http_timeout = 900
+http_retries = 5
+stall_timeout = 60
+spki_pins = ["sha256//hb+cNSS4MdmqkhVs//LTRmEkUgFoxbr3JEURh/MTP+E="]
+state_backend = "json"
+proxy_mode = "system"
+on_missing_binary = "keep"
+link_filter = "runnable"
+max_link_depth = 2
+
+[github]
+token = "ghp_test"
+host = "https://github.example.com/api/v3"
+allow_prerelease = true
+min_release_age = "2d"
+"#,
+            )
+            .unwrap();
+
+        let config = load(&config_path).unwrap().expect("config should exist");
+
+        assert_eq!(config.repo.as_deref(), Some("owner/myapp"));
+        assert_eq!(config.pattern.as_deref(), Some(r"myapp-.*\.tar\.gz"));
+        assert_eq!(config.checksum_pattern.as_deref(), Some("SHA256SUMS"));
+        assert_eq!(
+            config.restart_command.as_deref(),
+            Some("systemctl restart myapp")
+        );
+        assert_eq!(config.retain, Some(5));
+        assert_eq!(config.retain_max_size, Some(fsops::ByteSize(20 << 30)));
+        assert_eq!(config.install_root, Some(Utf8PathBuf::from("/srv")));
+        assert_eq!(config.http_timeout, Some(900));
+        assert_eq!(config.http_retries, Some(5));
+        assert_eq!(config.stall_timeout, Some(60));
+        assert_eq!(
+            config.spki_pins,
+            vec![
+                "sha256//hb+cNSS4MdmqkhVs//LTRmEkUgFoxbr3JEURh/MTP+E="
+                    .parse()
+                    .unwrap()
+            ]
+        );
+        assert_eq!(config.state_backend, Some(state::Backend::Json));
+        assert_eq!(config.proxy_mode, Some(proxy::Mode::System));
+        assert_eq!(config.on_missing_binary, Some(fsops::MissingBinary::Keep));
+        assert_eq!(config.link_filter, Some(fsops::LinkFilter::Runnable));
+        assert_eq!(config.max_link_depth, Some(2));
+        assert_eq!(config.github.token.as_deref(), Some("ghp_test"));
+        assert_eq!(
+            config.github.host.as_deref(),
+            Some("https://github.example.com/api/v3")
+        );
+        assert_eq!(config.github.allow_prerelease, Some(true));
+        assert_eq!(
+            config.github.min_release_age,
+            Some(ReleaseAge(jiff::SignedDuration::from_hours(48)))
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_unknown_keys() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.child("myapp.toml");
+        config_path
+            .write_str("repository = \"owner/myapp\"\n")
+            .unwrap();
+
+        let result = load(&config_path);
+        assert_matches!(result, Err(ConfigError::Parse { .. }));
+    }
+
+    #[test]
+    fn test_load_rejects_invalid_spki_pin() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.child("myapp.toml");
+        config_path
+            .write_str("spki_pins = [\"sha256//tooshort\"]\n")
+            .unwrap();
+
+        let result = load(&config_path);
+        assert_matches!(result, Err(ConfigError::Parse { .. }));
+    }
+
+    #[test]
+    fn test_load_extra_assets() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.child("myapp.toml");
+        config_path
+            .write_str(
+                "[[extra_assets]]\npattern = 'plugins-.*\\.tar\\.gz'\ndest = \"share/plugins\"\n",
+            )
+            .unwrap();
+
+        let config = load(&config_path).unwrap().unwrap();
+        assert_eq!(config.extra_assets.len(), 1);
+        assert_eq!(config.extra_assets[0].pattern, r"plugins-.*\.tar\.gz");
+        assert_eq!(
+            config.extra_assets[0].dest.as_path(),
+            Utf8Path::new("share/plugins")
+        );
+
+        for dest in ["../plugins", "/opt/plugins", "./plugins", ""] {
+            config_path
+                .write_str(&format!(
+                    "[[extra_assets]]\npattern = 'plugins'\ndest = \"{dest}\"\n"
+                ))
+                .unwrap();
+            assert_matches!(load(&config_path), Err(ConfigError::Parse { .. }));
+        }
+    }
+
+    #[test]
+    fn test_load_apps() {
+        let temp_dir = tempdir().unwrap();
+        let config_path = temp_dir.child("apps.toml");
+        config_path
+            .write_str(
+                r#"
+[apps.first]
+repo = "owner/first"
+pattern = "first-.*"
+
+[apps.second]
+repo = "owner/second"
+pattern = "second-.*"
+restart_command = "systemctl restart second"
+"#,
+            )
+            .unwrap();
+
+        let config = load_apps(&config_path)
+            .unwrap()
+            .expect("config should exist");
+
+        assert_eq!(
+            config.apps.keys().collect::<Vec<_>>(),
+            vec!["first", "second"]
+        );
+        assert_eq!(
+            config.apps["second"].restart_command.as_deref(),
+            Some("systemctl restart second")
+        );
+    }
+
+    #[test]
+    fn test_with_profile_overrides_base() {
+        let config: AppConfig = toml::from_str(
+            r#"
+repo = "owner/myapp"
+retain = 3
+restart_command = "systemctl restart myapp"
+
+[github]
+token = "ghp_base"
+
+[profiles.canary]
+retain = 1
+
+[profiles.canary.github]
+allow_prerelease = true
+token_file = "/etc/distronomicon/canary-token"
+
+[profiles.quiet]
+restart_command = ""
+"#,
+        )
+        .unwrap();
+
+        let canary = config.with_profile("canary").unwrap();
+        assert_eq!(canary.repo.as_deref(), Some("owner/myapp"));
+        assert_eq!(canary.retain, Some(1));
+        assert_eq!(canary.github.allow_prerelease, Some(true));
+        assert_eq!(canary.github.token, None);
+        assert_eq!(
+            canary.github.token_file.as_deref(),
+            Some(Utf8Path::new("/etc/distronomicon/canary-token"))
+        );
+        assert_eq!(
+            canary.restart_command.as_deref(),
+            Some("systemctl restart myapp")
+        );
+        assert!(canary.profiles.is_empty());
+
+        let quiet = config.with_profile("quiet").unwrap();
+        assert_eq!(quiet.restart_command, None);
+        assert_eq!(quiet.github.token.as_deref(), Some("ghp_base"));
+        assert_eq!(config.with_profile("prod"), None);
+    }
+
+    #[test]
+    fn test_default_path() {
+        assert_eq!(
+            default_path("myapp"),
+            Utf8PathBuf::from("/etc/distronomicon/myapp.toml")
+        );
+    }
+}
@@ -0,0 +1,346 @@
+use std::fmt;
+
+use regex::Regex;
+use semver::{Version, VersionReq};
+use thiserror::Error;
+
+use crate::github::Release;
+
+#[derive(Debug, Error)]
+pub enum ConstraintError {
+    #[error("invalid version constraint '{constraint}': {source}")]
+    Invalid {
+        constraint: String,
+        #[source]
+        source: semver::Error,
+    },
+
+    #[error("invalid allowed versions pattern '{pattern}': {source}")]
+    InvalidAllowed {
+        pattern: String,
+        #[source]
+        source: regex::Error,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, ConstraintError>;
+
+/// Returned by `update` when the release it found is an older version than the
+/// installed one, such as after the newest release was yanked.
+#[derive(Debug, Error)]
+#[error(
+    "{candidate} is older than the installed {installed}; pass --allow-downgrade to install it anyway"
+)]
+pub struct DowngradeError {
+    pub installed: String,
+    pub candidate: String,
+}
+
+/// Returned by `update` when `--tag` names a release the [`VersionFilter`] blocks.
+#[derive(Debug, Error)]
+#[error("{tag} is blocked: {reason}")]
+pub struct BlockedError {
+    pub tag: String,
+    pub reason: BlockReason,
+}
+
+/// Reads `tag` as a semver version after stripping `prefix`, or without one,
+/// everything before the first digit (`v1.4.2`, `release-1.4.2`). Missing minor
+/// and patch numbers count as zero, so `v2.1` is `2.1.0`.
+///
+/// Returns `None` if `tag` lacks the prefix or is not a version.
+#[must_use]
+pub fn parse_version(tag: &str, prefix: Option<&str>) -> Option<Version> {
+    let text = match prefix {
+        Some(prefix) => tag.strip_prefix(prefix)?,
+        None => tag.trim_start_matches(|c: char| !c.is_ascii_digit()),
+    };
+    let end = text.find(['-', '+']).unwrap_or(text.len());
+    let padding = match text[..end].matches('.').count() {
+        0 => ".0.0",
+        1 => ".0",
+        _ => "",
+    };
+    Version::parse(&format!("{}{padding}{}", &text[..end], &text[end..])).ok()
+}
+
+/// Fails with [`DowngradeError`] when `candidate` is a lower version than
+/// `installed`. Tags that are not both versions cannot be ordered and pass.
+///
+/// # Errors
+///
+/// Returns `DowngradeError` if `candidate` is older than `installed`.
+pub fn check_downgrade(
+    installed: &str,
+    candidate: &str,
+    prefix: Option<&str>,
+) -> std::result::Result<(), DowngradeError> {
+    match (
+        parse_version(installed, prefix),
+        parse_version(candidate, prefix),
+    ) {
+        (Some(installed_version), Some(candidate_version))
+            if candidate_version < installed_version =>
+        {
+            Err(DowngradeError {
+                installed: installed.to_string(),
+                candidate: candidate.to_string(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// A semver range such as `^1.4` that release tags must satisfy, for staying
+/// within a major or minor series.
+///
+/// Tags are read as versions with [`parse_version`]; tags that do not parse never
+/// satisfy the constraint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionConstraint {
+    req: VersionReq,
+    prefix: Option<String>,
+}
+
+impl VersionConstraint {
+    /// Parses `constraint` in Cargo's range syntax (`^1.4`, `~2.0.3`,
+    /// `>=1.2, <1.5`, `1.*`).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConstraintError::Invalid` if `constraint` is not a valid range.
+    pub fn new(constraint: &str, prefix: Option<&str>) -> Result<Self> {
+        let req = VersionReq::parse(constraint).map_err(|source| ConstraintError::Invalid {
+            constraint: constraint.to_string(),
+            source,
+        })?;
+        Ok(Self {
+            req,
+            prefix: prefix.map(str::to_string),
+        })
+    }
+
+    /// The version `tag` names, or `None` if it lacks the prefix or is not a
+    /// version.
+    #[must_use]
+    pub fn version(&self, tag: &str) -> Option<Version> {
+        parse_version(tag, self.prefix.as_deref())
+    }
+
+    /// Whether `tag` names a version within the range.
+    #[must_use]
+    pub fn matches(&self, tag: &str) -> bool {
+        self.version(tag)
+            .is_some_and(|version| self.req.matches(&version))
+    }
+
+    /// The release with the highest version within the range, preferring the
+    /// newest of releases with equal versions.
+    #[must_use]
+    pub fn select(&self, releases: impl IntoIterator<Item = Release>) -> Option<Release> {
+        releases
+            .into_iter()
+            .filter_map(|release| {
+                let version = self.version(&release.tag_name)?;
+                self.req.matches(&version).then_some((version, release))
+            })
+            .max_by_key(|(version, release)| (version.clone(), release.created_at))
+            .map(|(_, release)| release)
+    }
+}
+
+impl fmt::Display for VersionConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.req.fmt(f)
+    }
+}
+
+/// Why a [`VersionFilter`] blocks a tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockReason {
+    /// The tag, or the version it names, is on the skip list.
+    Skipped,
+    /// The tag does not match the allowed versions pattern.
+    NotAllowed(String),
+}
+
+impl fmt::Display for BlockReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Skipped => f.write_str("listed in skip_versions"),
+            Self::NotAllowed(pattern) => {
+                write!(f, "does not match allow_versions '{pattern}'")
+            }
+        }
+    }
+}
+
+/// Known-bad releases that are never installed, even when they are the latest,
+/// and a regex every installed tag must match.
+///
+/// A skipped entry blocks the tag it names, or any tag naming the same version
+/// under [`parse_version`], so `1.4.2` also blocks `v1.4.2`.
+#[derive(Debug, Clone, Default)]
+pub struct VersionFilter {
+    skip: Vec<String>,
+    allow: Option<Regex>,
+    prefix: Option<String>,
+}
+
+impl VersionFilter {
+    /// # Errors
+    ///
+    /// Returns `ConstraintError::InvalidAllowed` if `allow` is not a valid regex.
+    pub fn new(skip: &[String], allow: Option<&str>, prefix: Option<&str>) -> Result<Self> {
+        let allow = allow
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|source| ConstraintError::InvalidAllowed {
+                    pattern: pattern.to_string(),
+                    source,
+                })
+            })
+            .transpose()?;
+        Ok(Self {
+            skip: skip.to_vec(),
+            allow,
+            prefix: prefix.map(str::to_string),
+        })
+    }
+
+    /// Why `tag` must not be installed, or `None` if it may be.
+    #[must_use]
+    pub fn blocked(&self, tag: &str) -> Option<BlockReason> {
+        let version = parse_version(tag, self.prefix.as_deref());
+        let skipped = self.skip.iter().any(|skip| {
+            skip == tag
+                || version.is_some() && parse_version(skip, self.prefix.as_deref()) == version
+        });
+        if skipped {
+            return Some(BlockReason::Skipped);
+        }
+        match &self.allow {
+            Some(allow) if !allow.is_match(tag) => {
+                Some(BlockReason::NotAllowed(allow.as_str().to_string()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Fails with [`BlockedError`] when `tag` is blocked.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BlockedError` if `tag` is skipped or not allowed.
+    pub fn check(&self, tag: &str) -> std::result::Result<(), BlockedError> {
+        match self.blocked(tag) {
+            Some(reason) => Err(BlockedError {
+                tag: tag.to_string(),
+                reason,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(tag: &str) -> Release {
+        serde_json::from_value(serde_json::json!({
+            "tag_name": tag,
+            "prerelease": false,
+            "draft": false,
+            "assets": []
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_version_strips_prefix_and_pads() {
+        let constraint = VersionConstraint::new("^1.4", None).unwrap();
+        assert_eq!(constraint.version("v1.4.2"), Some(Version::new(1, 4, 2)));
+        assert_eq!(
+            constraint.version("release-2.1"),
+            Some(Version::new(2, 1, 0))
+        );
+        assert_eq!(
+            constraint.version("v1.5-rc.1"),
+            Version::parse("1.5.0-rc.1").ok()
+        );
+        assert_eq!(constraint.version("nightly"), None);
+
+        let prefixed = VersionConstraint::new("^1.4", Some("cli-v")).unwrap();
+        assert_eq!(prefixed.version("cli-v1.4.0"), Some(Version::new(1, 4, 0)));
+        assert_eq!(prefixed.version("server-v1.4.0"), None);
+    }
+
+    #[test]
+    fn test_matches() {
+        let constraint = VersionConstraint::new("^1.4", None).unwrap();
+        assert!(constraint.matches("v1.4.0"));
+        assert!(constraint.matches("v1.9.3"));
+        assert!(!constraint.matches("v1.3.9"));
+        assert!(!constraint.matches("v2.0.0"));
+        assert!(!constraint.matches("v1.5.0-rc.1"));
+    }
+
+    #[test]
+    fn test_select_takes_highest_matching_version() {
+        let constraint = VersionConstraint::new("~1.4", None).unwrap();
+        let releases = ["v2.0.0", "v1.4.10", "v1.4.9", "v1.5.0"].map(release);
+
+        assert_eq!(
+            constraint.select(releases).map(|r| r.tag_name).as_deref(),
+            Some("v1.4.10")
+        );
+    }
+
+    #[test]
+    fn test_check_downgrade() {
+        assert!(check_downgrade("v1.4.0", "v1.5.0", None).is_ok());
+        assert!(check_downgrade("v1.4.0", "v1.4.0", None).is_ok());
+        assert!(check_downgrade("v1.4.0", "nightly", None).is_ok());
+        assert!(check_downgrade("v1.10.0", "v1.9.0-rc.1", None).is_err());
+
+        let error = check_downgrade("v1.4.0", "v1.3.9", None).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "v1.3.9 is older than the installed v1.4.0; pass --allow-downgrade to install it anyway"
+        );
+    }
+
+    #[test]
+    fn test_invalid_constraint() {
+        let error = VersionConstraint::new("^one", None).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("invalid version constraint '^one'")
+        );
+    }
+
+    #[test]
+    fn test_version_filter() {
+        let filter = VersionFilter::new(
+            &["1.4.2".to_string(), "nightly".to_string()],
+            Some(r"^v\d+\.\d+\.\d+$"),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(filter.blocked("v1.4.1"), None);
+        assert_eq!(filter.blocked("v1.4.2"), Some(BlockReason::Skipped));
+        assert_eq!(filter.blocked("nightly"), Some(BlockReason::Skipped));
+        assert_eq!(
+            filter.blocked("v1.5.0-rc.1"),
+            Some(BlockReason::NotAllowed(r"^v\d+\.\d+\.\d+$".to_string()))
+        );
+        assert_eq!(
+            filter.check("v1.4.2").unwrap_err().to_string(),
+            "v1.4.2 is blocked: listed in skip_versions"
+        );
+        assert!(VersionFilter::default().check("anything").is_ok());
+        assert!(VersionFilter::new(&[], Some("("), None).is_err());
+    }
+}
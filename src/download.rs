@@ -1,12 +1,15 @@
-use std::io::Write;
+use std::{io::Write, time::Duration};
 
 use camino_tempfile::NamedUtf8TempFile;
 use futures_util::StreamExt;
+use jiff::Timestamp;
+use reqwest::header::CONTENT_TYPE;
 use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
 use reqwest_retry::{RetryTransientMiddleware, policies::ExponentialBackoff};
 use thiserror::Error;
+use tracing::warn;
 
-use crate::DEFAULT_TIMEOUT;
+use crate::{DEFAULT_TIMEOUT, extract::ArchiveFormat, github};
 
 #[derive(Debug, Error)]
 pub enum DownloadError {
@@ -18,12 +21,70 @@ pub enum DownloadError {
 
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
+
+    #[error("Download stalled: no data received for {0:?}")]
+    Stalled(Duration),
+
+    #[error("Server sent Content-Type {0:?} instead of an archive (likely an error or login page)")]
+    UnexpectedContentType(String),
+
+    #[error("Downloaded data is not a {0} archive (missing file signature)")]
+    BadSignature(ArchiveFormat),
+
+    #[error("Rate limited until {0}")]
+    RateLimited(jiff::Timestamp),
+
+    #[error("No download URL to try")]
+    NoUrls,
 }
 
 pub type Result<T> = std::result::Result<T, DownloadError>;
 
-const MAX_RETRIES: u32 = 3;
+/// Default number of retries for transient download failures.
+pub const MAX_RETRIES: u32 = 3;
 
+/// Placeholder for the release tag in mirror base URLs.
+const TAG_PLACEHOLDER: &str = "{tag}";
+
+/// A URL to download from and the token it may be sent with.
+#[derive(Debug, Clone)]
+pub struct Candidate<'a> {
+    pub url: String,
+    pub token: Option<&'a str>,
+}
+
+/// The URL of `file_name` on the mirror at `base`, where `{tag}` in `base` stands
+/// for the release tag, e.g. `https://mirror.example.com/myapp/{tag}`.
+#[must_use]
+pub fn mirror_url(base: &str, tag: &str, file_name: &str) -> String {
+    format!(
+        "{}/{file_name}",
+        base.replace(TAG_PLACEHOLDER, tag).trim_end_matches('/')
+    )
+}
+
+/// Downloads `url` into a temporary file.
+///
+/// Transient failures are retried up to `max_retries` times with exponential backoff.
+/// If `stall_timeout` is set, the download is aborted when no response or body data
+/// arrives within that window, independent of the client's overall request timeout.
+///
+/// If `file_name` names a supported archive, the response is checked before anything
+/// is written: a text or JSON `Content-Type`, or leading bytes without the archive's
+/// signature, fail the download so an error page is never saved as the asset.
+///
+/// A response refused for an exhausted rate limit is retried once the limit resets
+/// if that is within `rate_limit_wait` in total.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - `DownloadError::Middleware` / `DownloadError::Request` - The request fails or returns non-2xx status
+/// - `DownloadError::Stalled` - No data was received within `stall_timeout`
+/// - `DownloadError::RateLimited` - A rate limit does not reset within `rate_limit_wait`
+/// - `DownloadError::UnexpectedContentType` - The response is served as text, HTML, or JSON
+/// - `DownloadError::BadSignature` - The body does not start with the archive's signature
+/// - `DownloadError::Io` - The temporary file cannot be written
 #[bon::builder(derive(IntoFuture(Box)))]
 pub async fn fetch(
     url: &str,
@@ -32,7 +93,12 @@ pub async fn fetch(
     client: reqwest::Client,
     #[builder(default = MAX_RETRIES)] max_retries: u32,
     retry_base: Option<u32>,
+    stall_timeout: Option<Duration>,
+    file_name: Option<&str>,
+    #[builder(default)] rate_limit_wait: Duration,
 ) -> Result<NamedUtf8TempFile> {
+    let format = file_name.and_then(ArchiveFormat::from_file_name);
+
     let mut retry_builder = ExponentialBackoff::builder();
     if let Some(base) = retry_base {
         retry_builder = retry_builder.base(base);
@@ -51,14 +117,54 @@ pub async fn fetch(
         request = request.header("Authorization", format!("Bearer {token}"));
     }
 
-    let response = request.send().await?.error_for_status()?;
+    let mut budget = rate_limit_wait;
+    let response = loop {
+        let attempt = request
+            .try_clone()
+            .expect("GET requests without a body can be cloned");
+        let response = with_stall_timeout(stall_timeout, attempt.send()).await??;
+        match github::rate_limited_until(response.status(), response.headers(), Timestamp::now()) {
+            Some(until) if github::wait_for_rate_limit(until, &mut budget).await => {}
+            Some(until) => return Err(DownloadError::RateLimited(until)),
+            None => break response.error_for_status()?,
+        }
+    };
+
+    if format.is_some()
+        && let Some(content_type) = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+        && is_error_page_type(content_type)
+    {
+        return Err(DownloadError::UnexpectedContentType(
+            content_type.to_string(),
+        ));
+    }
 
     let mut temp_file = NamedUtf8TempFile::new()?;
     let mut stream = response.bytes_stream();
 
-    while let Some(chunk) = stream.next().await {
+    // Held back until enough bytes arrive to check the archive signature.
+    let mut head = Vec::new();
+    while let Some(chunk) = with_stall_timeout(stall_timeout, stream.next()).await? {
         let chunk = chunk?;
-        temp_file.write_all(&chunk)?;
+        match format {
+            Some(format) if head.len() < ArchiveFormat::SIGNATURE_LEN => {
+                head.extend_from_slice(&chunk);
+                if head.len() >= ArchiveFormat::SIGNATURE_LEN {
+                    check_signature(format, &head)?;
+                    temp_file.write_all(&head)?;
+                }
+            }
+            _ => temp_file.write_all(&chunk)?,
+        }
+    }
+    if let Some(format) = format
+        && head.len() < ArchiveFormat::SIGNATURE_LEN
+    {
+        check_signature(format, &head)?;
+        temp_file.write_all(&head)?;
     }
 
     temp_file.as_file().sync_all()?;
@@ -66,9 +172,92 @@ pub async fn fetch(
     Ok(temp_file)
 }
 
+/// Downloads from the first of `candidates` that succeeds, returning the file and
+/// the index of the candidate it came from.
+///
+/// Each candidate is fetched like [`fetch`], retries included; an HTTP error
+/// status, timeout, stall, or a response that is not the expected archive moves on
+/// to the next one. A local I/O error stops immediately, since another mirror
+/// would not help.
+///
+/// # Errors
+///
+/// Returns the error of the last candidate if all of them fail, an I/O error, or
+/// `DownloadError::NoUrls` if there are no candidates.
+#[bon::builder(derive(IntoFuture(Box)))]
+pub async fn fetch_first(
+    candidates: &[Candidate<'_>],
+    #[builder(default = crate::build_http_client(DEFAULT_TIMEOUT).unwrap())]
+    client: reqwest::Client,
+    #[builder(default = MAX_RETRIES)] max_retries: u32,
+    retry_base: Option<u32>,
+    stall_timeout: Option<Duration>,
+    file_name: Option<&str>,
+    #[builder(default)] rate_limit_wait: Duration,
+) -> Result<(NamedUtf8TempFile, usize)> {
+    let mut last_error = DownloadError::NoUrls;
+    for (index, candidate) in candidates.iter().enumerate() {
+        let result = fetch()
+            .url(&candidate.url)
+            .maybe_token(candidate.token)
+            .client(client.clone())
+            .max_retries(max_retries)
+            .maybe_retry_base(retry_base)
+            .maybe_stall_timeout(stall_timeout)
+            .maybe_file_name(file_name)
+            .rate_limit_wait(rate_limit_wait)
+            .await;
+        match result {
+            Ok(file) => return Ok((file, index)),
+            Err(DownloadError::Io(e)) => return Err(DownloadError::Io(e)),
+            Err(e) => {
+                if index + 1 < candidates.len() {
+                    warn!(
+                        "Download from {} failed, trying the next URL: {e}",
+                        candidate.url
+                    );
+                }
+                last_error = e;
+            }
+        }
+    }
+    Err(last_error)
+}
+
+/// Whether `content_type` is a media type that error, login, and API pages are
+/// served as, which no release archive is.
+fn is_error_page_type(content_type: &str) -> bool {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    essence.starts_with("text/")
+        || essence == "application/json"
+        || essence == "application/xhtml+xml"
+}
+
+fn check_signature(format: ArchiveFormat, head: &[u8]) -> Result<()> {
+    if format.matches_signature(head) {
+        Ok(())
+    } else {
+        Err(DownloadError::BadSignature(format))
+    }
+}
+
+async fn with_stall_timeout<F: Future>(limit: Option<Duration>, future: F) -> Result<F::Output> {
+    match limit {
+        Some(limit) => tokio::time::timeout(limit, future)
+            .await
+            .map_err(|_| DownloadError::Stalled(limit)),
+        None => Ok(future.await),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::{fs, time::Duration};
+    use std::{assert_matches, fs, time::Duration};
 
     use reqwest_middleware::ClientBuilder;
     use wiremock::{
@@ -279,4 +468,168 @@ mod tests {
         let contents = fs::read(temp_file.path()).unwrap();
         assert_eq!(contents, body_content);
     }
+
+    #[tokio::test]
+    async fn test_stall_timeout_aborts_slow_download() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/asset.tar.gz"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_bytes(b"slow".to_vec())
+                    .set_delay(Duration::from_secs(5)),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let url = format!("{}/asset.tar.gz", mock_server.uri());
+        let result = fetch()
+            .url(&url)
+            .max_retries(0)
+            .stall_timeout(Duration::from_millis(100))
+            .await;
+
+        assert_matches!(result, Err(DownloadError::Stalled(_)));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_download() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/asset.tar.gz"))
+            .respond_with(
+                ResponseTemplate::new(403)
+                    .insert_header("x-ratelimit-remaining", "0")
+                    .insert_header("x-ratelimit-reset", "4102444800"),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let url = format!("{}/asset.tar.gz", mock_server.uri());
+        let result = fetch()
+            .url(&url)
+            .max_retries(0)
+            .rate_limit_wait(Duration::from_secs(60))
+            .await;
+
+        assert_matches!(
+            result,
+            Err(DownloadError::RateLimited(until)) if until.to_string() == "2100-01-01T00:00:00Z"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_html_served_for_archive() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/asset.tar.gz"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw("<html>Sign in</html>", "text/html; charset=utf-8"),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let url = format!("{}/asset.tar.gz", mock_server.uri());
+        let result = fetch().url(&url).file_name("asset.tar.gz").await;
+
+        assert_matches!(
+            result,
+            Err(DownloadError::UnexpectedContentType(content_type)) if content_type.starts_with("text/html")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_archive_without_signature() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/asset.zip"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"<html>oops</html>".to_vec()))
+            .mount(&mock_server)
+            .await;
+
+        let url = format!("{}/asset.zip", mock_server.uri());
+        let result = fetch().url(&url).file_name("asset.zip").await;
+
+        assert_matches!(result, Err(DownloadError::BadSignature(ArchiveFormat::Zip)));
+    }
+
+    #[tokio::test]
+    async fn test_accepts_archive_with_signature() {
+        let mock_server = MockServer::start().await;
+        let body_content = b"\x1f\x8b\x08\x00 rest of a gzip stream";
+
+        Mock::given(method("GET"))
+            .and(path("/asset.tgz"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw(body_content.to_vec(), "application/gzip"),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let url = format!("{}/asset.tgz", mock_server.uri());
+        let temp_file = fetch().url(&url).file_name("asset.tgz").await.unwrap();
+
+        assert_eq!(fs::read(temp_file.path()).unwrap(), body_content);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_first_fails_over_to_next_candidate() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/down/asset.bin"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/up/asset.bin"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"mirrored".to_vec()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let candidates = ["down", "up", "unused"].map(|dir| Candidate {
+            url: format!("{}/{dir}/asset.bin", mock_server.uri()),
+            token: None,
+        });
+        let (temp_file, index) = fetch_first()
+            .candidates(&candidates)
+            .max_retries(0)
+            .await
+            .unwrap();
+
+        assert_eq!(index, 1);
+        assert_eq!(fs::read(temp_file.path()).unwrap(), b"mirrored");
+    }
+
+    #[test]
+    fn test_mirror_url() {
+        assert_eq!(
+            mirror_url(
+                "https://mirror.example.com/myapp/{tag}/",
+                "v1.2.0",
+                "myapp.tar.gz"
+            ),
+            "https://mirror.example.com/myapp/v1.2.0/myapp.tar.gz"
+        );
+        assert_eq!(
+            mirror_url("https://mirror.example.com/dist", "v1.2.0", "myapp.tar.gz"),
+            "https://mirror.example.com/dist/myapp.tar.gz"
+        );
+    }
+
+    #[test]
+    fn test_error_page_types() {
+        assert!(is_error_page_type("text/html"));
+        assert!(is_error_page_type("Text/Plain; charset=utf-8"));
+        assert!(is_error_page_type("application/json"));
+        assert!(!is_error_page_type("application/octet-stream"));
+        assert!(!is_error_page_type("application/x-gtar"));
+    }
 }
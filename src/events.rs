@@ -0,0 +1,166 @@
+use std::{fmt, time::Instant};
+
+use jiff::Timestamp;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Format of the machine-readable progress stream written by `update --events`;
+/// `ndjson` writes one JSON object per line on stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum EventFormat {
+    Ndjson,
+}
+
+/// Emits progress events for each phase of an update, or nothing when disabled.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Events {
+    format: Option<EventFormat>,
+}
+
+impl Events {
+    #[must_use]
+    pub fn new(format: Option<EventFormat>) -> Self {
+        Self { format }
+    }
+
+    /// Events that are never written.
+    #[must_use]
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn enabled(&self) -> bool {
+        self.format.is_some()
+    }
+
+    /// Emits a `started` event for `phase` and returns a guard that reports how it ended.
+    ///
+    /// The phase counts as failed if the guard is dropped before
+    /// [`Phase::succeeded`] or [`Phase::failed`] is called, so a phase cut short by
+    /// `?` is still reported.
+    #[must_use]
+    pub fn phase(&self, phase: &'static str) -> Phase {
+        self.write(phase, "started", Map::new());
+        Phase {
+            events: *self,
+            name: phase,
+            started: Instant::now(),
+            finished: false,
+        }
+    }
+
+    /// Emits a final `result` event carrying `summary`'s fields.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `summary` does not serialize to a JSON object.
+    pub fn result(&self, summary: &impl Serialize) -> serde_json::Result<()> {
+        match serde_json::to_value(summary)? {
+            Value::Object(fields) => {
+                self.write("update", "result", fields);
+                Ok(())
+            }
+            other => Err(serde::ser::Error::custom(format!(
+                "expected an object, got {other}"
+            ))),
+        }
+    }
+
+    fn write(&self, phase: &str, event: &str, fields: Map<String, Value>) {
+        if let Some(EventFormat::Ndjson) = self.format {
+            println!("{}", event_line(Timestamp::now(), phase, event, fields));
+        }
+    }
+}
+
+/// A running phase; see [`Events::phase`].
+#[derive(Debug)]
+pub struct Phase {
+    events: Events,
+    name: &'static str,
+    started: Instant,
+    finished: bool,
+}
+
+impl Phase {
+    /// Emits a `succeeded` event with the phase's duration.
+    pub fn succeeded(mut self) {
+        self.finish("succeeded", None);
+    }
+
+    /// Emits a `failed` event with the phase's duration and `error`.
+    pub fn failed(mut self, error: &dyn fmt::Display) {
+        self.finish("failed", Some(error.to_string()));
+    }
+
+    /// Ends the phase as [`succeeded`](Self::succeeded) or [`failed`](Self::failed)
+    /// depending on `result`.
+    pub fn record<T, E: fmt::Display>(self, result: &Result<T, E>) {
+        match result {
+            Ok(_) => self.succeeded(),
+            Err(e) => self.failed(e),
+        }
+    }
+
+    fn finish(&mut self, event: &str, error: Option<String>) {
+        self.finished = true;
+        let mut fields = Map::new();
+        fields.insert(
+            "duration_ms".to_string(),
+            u64::try_from(self.started.elapsed().as_millis())
+                .unwrap_or(u64::MAX)
+                .into(),
+        );
+        if let Some(error) = error {
+            fields.insert("error".to_string(), error.into());
+        }
+        self.events.write(self.name, event, fields);
+    }
+}
+
+impl Drop for Phase {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.finish("failed", None);
+        }
+    }
+}
+
+fn event_line(timestamp: Timestamp, phase: &str, event: &str, fields: Map<String, Value>) -> Value {
+    let mut line = Map::new();
+    line.insert("timestamp".to_string(), timestamp.to_string().into());
+    line.insert("phase".to_string(), phase.into());
+    line.insert("event".to_string(), event.into());
+    line.extend(fields);
+    Value::Object(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_line_merges_fields() {
+        let mut fields = Map::new();
+        fields.insert("duration_ms".to_string(), 12.into());
+
+        let line = event_line(
+            "2025-10-28T12:00:00Z".parse().unwrap(),
+            "download",
+            "succeeded",
+            fields,
+        );
+
+        assert_eq!(
+            line,
+            serde_json::json!({
+                "timestamp": "2025-10-28T12:00:00Z",
+                "phase": "download",
+                "event": "succeeded",
+                "duration_ms": 12,
+            })
+        );
+    }
+}
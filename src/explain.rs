@@ -0,0 +1,570 @@
+use std::{error::Error as StdError, fmt, io};
+
+use crate::{
+    constraint::{BlockedError, DowngradeError},
+    download::DownloadError,
+    extract::ExtractError,
+    fsops::FsOpsError,
+    github::{GithubError, NoMatchingAsset},
+    lock::LockError,
+    pin::PinnedError,
+    restart::{PostInstallError, RestartError},
+    scan::ScanError,
+    state::StateError,
+    verify::VerifyError,
+};
+
+/// A human-oriented account of a failed command, printed under the error with
+/// `--explain`: the phase that failed, what it was checking, likely causes, and
+/// commands to try next.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation {
+    pub phase: &'static str,
+    pub checked: String,
+    pub causes: Vec<String>,
+    pub next: Vec<String>,
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "What failed:")?;
+        writeln!(f, "  phase:    {}", self.phase)?;
+        writeln!(f, "  checked:  {}", self.checked)?;
+        writeln!(f, "Likely causes:")?;
+        for cause in &self.causes {
+            writeln!(f, "  - {cause}")?;
+        }
+        if !self.next.is_empty() {
+            writeln!(f, "Try next:")?;
+            for command in &self.next {
+                writeln!(f, "  {command}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Explains `error` from the first error in its chain with a known type, or
+/// returns `None` when nothing in the chain is recognized.
+///
+/// Suggested commands name `app` when it is known.
+#[must_use]
+pub fn explain(error: &anyhow::Error, app: Option<&str>) -> Option<Explanation> {
+    let run = |args: &str| match app {
+        Some(app) => format!("distronomicon --app {app} {args}"),
+        None => format!("distronomicon {args}"),
+    };
+    error.chain().find_map(|cause| explain_one(cause, &run))
+}
+
+fn explain_one(
+    error: &(dyn StdError + 'static),
+    run: &dyn Fn(&str) -> String,
+) -> Option<Explanation> {
+    let explanation = |phase, checked: String, causes: &[&str], next: Vec<String>| Explanation {
+        phase,
+        checked,
+        causes: causes.iter().map(ToString::to_string).collect(),
+        next,
+    };
+
+    if let Some(e) = error.downcast_ref::<PinnedError>() {
+        return Some(explanation(
+            "pin check",
+            format!("whether {} is pinned", e.app),
+            &["the app was pinned with `pin` to hold it at its current release"],
+            vec![run("unpin"), run("history")],
+        ));
+    }
+    if let Some(LockError::Busy { timeout_secs }) = error.downcast_ref() {
+        return Some(explanation(
+            "lock",
+            format!("the app's update lock, waiting {timeout_secs}s for it"),
+            &[
+                "another update of the app is still running",
+                "an earlier update was killed and left its lock behind (only then is unlocking safe)",
+            ],
+            vec![run("status"), run("unlock")],
+        ));
+    }
+    if let Some(e) = error.downcast_ref::<DowngradeError>() {
+        return Some(explanation(
+            "version check",
+            format!(
+                "that {} is not an older version than the installed {}",
+                e.candidate, e.installed
+            ),
+            &[
+                "the newest release was yanked, so the latest release moved backwards",
+                "--tag names an older release",
+                "the tags do not sort as semver versions (see --version-prefix)",
+            ],
+            vec![run("list-remote"), run("update --allow-downgrade")],
+        ));
+    }
+    if let Some(e) = error.downcast_ref::<BlockedError>() {
+        return Some(explanation(
+            "version check",
+            format!("that {} is not blocked ({})", e.tag, e.reason),
+            &[
+                "--tag names a release listed in skip_versions or --skip-version",
+                "the tag does not match allow_versions or --allow-versions",
+            ],
+            vec![run("list-remote")],
+        ));
+    }
+    if let Some(e) = error.downcast_ref::<GithubError>() {
+        return Some(explain_github(e, run));
+    }
+    if let Some(e) = error.downcast_ref::<NoMatchingAsset>() {
+        return Some(explanation(
+            "select asset",
+            format!("the assets of {} against '{}'", e.tag, e.pattern),
+            &[
+                "the release names its assets differently from earlier releases",
+                "the release's assets are still being uploaded",
+                "the pattern is wrong for this platform or needs escaping (a literal `.` is `\\.`)",
+            ],
+            vec![
+                run(&format!("list-remote --limit 1 --pattern '{}'", e.pattern)),
+                run("list-remote --limit 1"),
+            ],
+        ));
+    }
+    if let Some(e) = error.downcast_ref::<DownloadError>() {
+        return Some(explain_download(e, run));
+    }
+    if let Some(e) = error.downcast_ref::<VerifyError>() {
+        return Some(explain_verify(e, run));
+    }
+    if let Some(e) = error.downcast_ref::<ScanError>() {
+        let checked = match e {
+            ScanError::Rejected { command, code, .. } => {
+                format!("the downloaded asset with `{command}` (exit code {code})")
+            }
+            ScanError::Io(_) => "that the scan command could be started".to_string(),
+        };
+        return Some(explanation(
+            "scan",
+            checked,
+            &[
+                "the scanner flagged the asset; its output is in the log above",
+                "the scan command is missing or not executable",
+            ],
+            vec![run("update --dry-run")],
+        ));
+    }
+    if let Some(e) = error.downcast_ref::<ExtractError>() {
+        return Some(explain_extract(e, run));
+    }
+    if let Some(e) = error.downcast_ref::<FsOpsError>() {
+        return Some(explain_install(e, run));
+    }
+    if let Some(e) = error.downcast_ref::<PostInstallError>() {
+        return Some(match e {
+            PostInstallError::Restart => explanation(
+                "restart",
+                "that the restart command exits successfully after the switch".to_string(),
+                &[
+                    "the service does not start with the new release; its own logs say why",
+                    "the restart command itself is wrong or lacks permission",
+                ],
+                vec![run("history"), run("rollback")],
+            ),
+            PostInstallError::Health { timeout_secs, .. } => explanation(
+                "health check",
+                format!("that the health command passes within {timeout_secs}s"),
+                &[
+                    "the new release starts but is unhealthy",
+                    "the service needs longer than --health-timeout to become ready",
+                ],
+                vec![run("rollback"), run("history")],
+            ),
+        });
+    }
+    if let Some(RestartError::CommandFailed { command, code, .. }) = error.downcast_ref() {
+        return Some(explanation(
+            "command",
+            format!("that `{command}` exits successfully (it exited with {code})"),
+            &["the command failed; its output is in the log above"],
+            vec![run("status")],
+        ));
+    }
+    if let Some(e) = error.downcast_ref::<StateError>() {
+        return Some(explanation(
+            "state",
+            "the app's saved state in the state directory".to_string(),
+            &[match e {
+                StateError::Serialization(_) => "the state file was edited by hand or truncated",
+                #[cfg(feature = "sqlite")]
+                StateError::Sqlite(_) => "the state database is locked or damaged",
+                _ => "the state directory is missing or not writable by this user",
+            }],
+            vec![run("doctor")],
+        ));
+    }
+    if let Some(e) = error.downcast_ref::<io::Error>()
+        && e.kind() == io::ErrorKind::PermissionDenied
+    {
+        return Some(explanation(
+            "filesystem",
+            "access to the install root and state directory".to_string(),
+            &["this user cannot write there; run as the owner of the install root or with sudo"],
+            vec![run("doctor")],
+        ));
+    }
+    None
+}
+
+fn explain_github(error: &GithubError, run: &dyn Fn(&str) -> String) -> Explanation {
+    let (checked, causes, next): (String, &[&str], Vec<String>) = match error {
+        GithubError::NotFound { url } => (
+            format!("GET {url}"),
+            &[
+                "the repository in --repo is misspelled",
+                "the repository is private and no token was given (GitHub answers 404, not 403)",
+                "the tag given with --tag does not exist",
+            ],
+            vec![run("doctor"), run("list-remote")],
+        ),
+        GithubError::Unauthorized { url, .. } => (
+            format!("GET {url} with the configured token"),
+            &[
+                "the token is expired or revoked",
+                "the token lacks access to the repository (needs `repo` for private ones)",
+                "the organization requires SSO authorization for the token",
+            ],
+            vec![run("doctor"), run("login")],
+        ),
+        GithubError::RateLimited { reset } => (
+            format!("the GitHub API rate limit, exhausted until {reset}"),
+            &[
+                "requests are unauthenticated (60 per hour per IP address)",
+                "other tools on this host share the token's budget",
+            ],
+            vec![run("login"), run("update --rate-limit-wait 3600")],
+        ),
+        GithubError::Parse { url, .. } => (
+            format!("the response from {url}"),
+            &[
+                "a proxy or captive portal answered instead of GitHub",
+                "--github-host points at something other than the GitHub API",
+            ],
+            vec![run("doctor")],
+        ),
+        GithubError::Network(_) => (
+            "the connection to the GitHub API".to_string(),
+            &[
+                "DNS, firewall, or proxy settings block the API host",
+                "a TLS-intercepting proxy is not trusted (see --proxy-mode and --spki-pin)",
+            ],
+            vec![run("doctor")],
+        ),
+        GithubError::NoReleases | GithubError::NoTags => (
+            "the repository's releases".to_string(),
+            &[
+                "the repository publishes no releases (see --fallback-to-tags)",
+                "every release is still a draft",
+            ],
+            vec![run("list-remote --channel prerelease")],
+        ),
+        GithubError::NoStableRelease => (
+            "the repository's stable releases".to_string(),
+            &["the repository only publishes prereleases (see --channel)"],
+            vec![run("list-remote --channel prerelease")],
+        ),
+        GithubError::NoMatchingRelease { pattern } => (
+            format!("release tags against '{pattern}'"),
+            &[
+                "--tag-pattern does not match the tags of this component",
+                "the matching releases are older than the newest 100 in the listing",
+            ],
+            vec![run("list-remote --channel prerelease")],
+        ),
+        GithubError::NoMatchingVersion { constraint } => (
+            format!("release versions against '{constraint}'"),
+            &[
+                "no release in the series has been published yet",
+                "the tags' prefix contains digits, so --version-prefix has to name it",
+                "the matching releases are older than the newest 100 in the listing",
+            ],
+            vec![run("list-remote --channel prerelease")],
+        ),
+        GithubError::NoReleaseOldEnough { min_age } => (
+            format!("release publish times against --min-release-age {min_age}"),
+            &[
+                "every release was published too recently; a later run will find one",
+                "the source records no publish time, or the tag fallback was used",
+            ],
+            vec![run("list-remote")],
+        ),
+        GithubError::NoChannelRelease { channel } => (
+            format!("the repository's releases on the {channel} channel"),
+            &[
+                "the repository publishes no release with a suffix that channel takes (see --channel)",
+                "the matching releases are older than the newest 100 in the listing",
+            ],
+            vec![run("list-remote --channel prerelease")],
+        ),
+        GithubError::InvalidChannel(channel) => (
+            format!("the release channel '{channel}'"),
+            &["the channel is not stable, rc, beta, or prerelease"],
+            Vec::new(),
+        ),
+        GithubError::InvalidReleaseAge(age) => (
+            format!("the release age '{age}'"),
+            &["the age is not a duration such as 48h, 7d, or 1w 12h"],
+            Vec::new(),
+        ),
+        GithubError::NoSourceArchive { tag } => (
+            format!("the source archive of {tag}"),
+            &["the tag came from --fallback-to-tags, which has no generated archive"],
+            vec![run("list-remote")],
+        ),
+        GithubError::NoPlatformAsset { tag, platform } => (
+            format!(
+                "the archives of {tag} for one naming no other OS or architecture than {platform}"
+            ),
+            &[
+                "the release ships no build for this platform",
+                "the release names platforms in an unusual way, so --pattern has to select the asset",
+            ],
+            vec![run("list-remote --limit 1")],
+        ),
+    };
+    Explanation {
+        phase: match error {
+            GithubError::NoPlatformAsset { .. } => "select asset",
+            _ => "fetch release",
+        },
+        checked,
+        causes: causes.iter().map(ToString::to_string).collect(),
+        next,
+    }
+}
+
+fn explain_download(error: &DownloadError, run: &dyn Fn(&str) -> String) -> Explanation {
+    let (checked, causes, next): (String, &[&str], Vec<String>) = match error {
+        DownloadError::Stalled(timeout) => (
+            format!("that data keeps arriving (no progress for {timeout:?})"),
+            &["the network or the download host is slow or congested"],
+            vec![run("update --stall-timeout 300")],
+        ),
+        DownloadError::UnexpectedContentType(content_type) => (
+            format!("the Content-Type of the response ({content_type})"),
+            &[
+                "the repository is private and the token is missing or lacks access",
+                "a proxy or captive portal served a login or error page",
+            ],
+            vec![run("doctor")],
+        ),
+        DownloadError::BadSignature(format) => (
+            format!("that the download starts like a {format} archive"),
+            &[
+                "a proxy or mirror served an error page",
+                "the asset's name does not match its format",
+            ],
+            vec![run("doctor")],
+        ),
+        DownloadError::RateLimited(until) => (
+            format!("the download host's rate limit, exhausted until {until}"),
+            &["downloads are unauthenticated or share a token's budget"],
+            vec![run("login"), run("update --rate-limit-wait 3600")],
+        ),
+        DownloadError::Io(_) => (
+            "writing the download to a temporary file".to_string(),
+            &["the temporary directory is full or not writable"],
+            vec![run("gc"), run("doctor")],
+        ),
+        DownloadError::Request(_) | DownloadError::Middleware(_) | DownloadError::NoUrls => (
+            "the connection to the download host".to_string(),
+            &[
+                "DNS, firewall, or proxy settings block the download host",
+                "the download host was unavailable for every retry (see --http-retries)",
+            ],
+            vec![run("doctor")],
+        ),
+    };
+    Explanation {
+        phase: "download",
+        checked,
+        causes: causes.iter().map(ToString::to_string).collect(),
+        next,
+    }
+}
+
+fn explain_verify(error: &VerifyError, run: &dyn Fn(&str) -> String) -> Explanation {
+    let (checked, causes, next): (String, &[&str], Vec<String>) = match error {
+        VerifyError::Mismatch { filename, .. } => (
+            format!("the SHA-256 of {filename} against the published checksum"),
+            &[
+                "the download was corrupted or truncated on the way",
+                "a mirror served a different file than the release",
+                "the asset was replaced after its checksum was published",
+            ],
+            vec![run("update --dry-run")],
+        ),
+        VerifyError::NotFound(asset) => (
+            format!("the checksum file for an entry for {asset}"),
+            &[
+                "--checksum-pattern selects a checksum file for a different asset",
+                "the release's checksum file omits this asset",
+            ],
+            vec![run("list-remote --limit 1")],
+        ),
+        VerifyError::ParseError(_) => (
+            "the checksum file's format".to_string(),
+            &["--checksum-pattern selects a file that is not a checksum list"],
+            vec![run("list-remote --limit 1")],
+        ),
+        VerifyError::Io(_) | VerifyError::Request(_) => (
+            "fetching the checksum file".to_string(),
+            &["the network failed while fetching the checksum file"],
+            vec![run("doctor")],
+        ),
+    };
+    Explanation {
+        phase: "verify",
+        checked,
+        causes: causes.iter().map(ToString::to_string).collect(),
+        next,
+    }
+}
+
+fn explain_extract(error: &ExtractError, run: &dyn Fn(&str) -> String) -> Explanation {
+    let (checked, causes, next): (String, &[&str], Vec<String>) = match error {
+        ExtractError::UnsupportedFormat | ExtractError::FormatDisabled(_) => (
+            "the archive format of the selected asset".to_string(),
+            &["--pattern matches a file that is not a supported archive"],
+            vec![run("list-remote --limit 1")],
+        ),
+        ExtractError::PathValidation(_) => (
+            "that every archive entry stays inside the release directory".to_string(),
+            &["the archive contains absolute paths, `..`, or symbolic links"],
+            vec![],
+        ),
+        ExtractError::LimitExceeded(_) => (
+            "the archive's size and file count against the extraction limits".to_string(),
+            &["the archive is unusually large, or is a decompression bomb"],
+            vec![],
+        ),
+        ExtractError::InsufficientSpace(_) => (
+            "the free inodes and bytes of the install root against the archive".to_string(),
+            &[
+                "the filesystem is out of inodes, such as from many retained releases of small files",
+                "the filesystem is nearly full",
+            ],
+            vec![run("gc"), run("prune"), run("doctor")],
+        ),
+        ExtractError::Sandbox(_) => (
+            "that extraction can be confined to the staging directory with Landlock".to_string(),
+            &[
+                "the kernel is older than 5.19 or was booted without Landlock in `lsm=`",
+                "--extract-sandbox require is set where confinement is unavailable",
+            ],
+            vec![run("update --extract-sandbox auto")],
+        ),
+        _ => (
+            "unpacking the archive into the staging directory".to_string(),
+            &[
+                "the download is corrupt",
+                "the staging directory's filesystem is full",
+            ],
+            vec![run("gc"), run("doctor")],
+        ),
+    };
+    Explanation {
+        phase: "extract",
+        checked,
+        causes: causes.iter().map(ToString::to_string).collect(),
+        next,
+    }
+}
+
+fn explain_install(error: &FsOpsError, run: &dyn Fn(&str) -> String) -> Explanation {
+    let (checked, causes, next): (String, &[&str], Vec<String>) = match error {
+        FsOpsError::AlreadyExists(path) => (
+            format!("that {path} does not exist yet"),
+            &["an earlier run installed this release but did not record it"],
+            vec![run("list"), run("update --overwrite-existing-release")],
+        ),
+        FsOpsError::MissingBinaries(names) => (
+            format!("that the new release still ships {}", names.join(", ")),
+            &["the upstream release dropped or renamed these commands"],
+            vec![run("update --on-missing-binary keep"), run("history")],
+        ),
+        FsOpsError::RequiredBinariesMissing(names) => (
+            format!("that the new release links {} from bin/", names.join(", ")),
+            &[
+                "the upstream release dropped or renamed these commands",
+                "--link-filter skips them",
+            ],
+            vec![run("history"), run("list-remote")],
+        ),
+        FsOpsError::UnsupportedLayout(_) => (
+            "the layout of the app's directory".to_string(),
+            &["the app was installed by hand rather than by distronomicon"],
+            vec![run("adopt --dry-run")],
+        ),
+        _ => (
+            "moving the release into place and switching bin/ links".to_string(),
+            &[
+                "the install root is full or not writable by this user",
+                "bin/ contains files distronomicon does not manage",
+            ],
+            vec![run("doctor"), run("status")],
+        ),
+    };
+    Explanation {
+        phase: "install",
+        checked,
+        causes: causes.iter().map(ToString::to_string).collect(),
+        next,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_verify_mismatch_under_context() {
+        let error = anyhow::Error::new(VerifyError::Mismatch {
+            filename: "myapp.tar.gz".to_string(),
+            expected: "aa".to_string(),
+            actual: "bb".to_string(),
+        })
+        .context("Failed to update");
+
+        let explanation = explain(&error, Some("myapp")).unwrap();
+        assert_eq!(explanation.phase, "verify");
+        assert_eq!(
+            explanation.checked,
+            "the SHA-256 of myapp.tar.gz against the published checksum"
+        );
+        assert_eq!(
+            explanation.next,
+            ["distronomicon --app myapp update --dry-run"]
+        );
+    }
+
+    #[test]
+    fn test_explain_lock_busy() {
+        let error = anyhow::Error::new(LockError::Busy { timeout_secs: 30 });
+
+        let explanation = explain(&error, None).unwrap();
+        assert_eq!(explanation.phase, "lock");
+        assert_eq!(
+            explanation.next,
+            ["distronomicon status", "distronomicon unlock"]
+        );
+        let rendered = explanation.to_string();
+        assert!(rendered.starts_with("What failed:\n  phase:    lock\n"));
+        assert!(rendered.contains("Try next:\n  distronomicon status\n"));
+    }
+
+    #[test]
+    fn test_explain_unknown_error() {
+        assert_eq!(explain(&anyhow::anyhow!("something else"), None), None);
+    }
+}
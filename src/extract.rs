@@ -1,13 +1,21 @@
 use std::{
+    fmt,
     fs::{self, File},
     io::{self, Read},
     os::unix::fs::PermissionsExt,
     path::{Component, Path},
+    thread,
 };
 
 use camino::Utf8Path;
 use thiserror::Error;
 
+#[cfg(feature = "zstd")]
+mod frames;
+mod sandbox;
+
+pub use sandbox::Sandbox;
+
 /// Limits for archive extraction to prevent zip bombs and resource exhaustion.
 #[derive(Debug, Clone, Copy)]
 pub struct ExtractionLimits {
@@ -19,6 +27,11 @@ pub struct ExtractionLimits {
     pub max_individual_file_bytes: u64,
     /// Maximum decompression ratio (uncompressed/compressed) (default: 100)
     pub max_decompression_ratio: u64,
+    /// Threads decoding a `.tar.zst` made of several frames at once (default: the
+    /// available cores, at most 4)
+    pub decode_threads: usize,
+    /// How extraction is confined to the destination directory (default: `auto`)
+    pub sandbox: Sandbox,
 }
 
 impl Default for ExtractionLimits {
@@ -28,6 +41,8 @@ impl Default for ExtractionLimits {
             max_file_count: 10_000,
             max_individual_file_bytes: 1024 * 1024 * 1024, // 1 GiB
             max_decompression_ratio: 100,
+            decode_threads: thread::available_parallelism().map_or(1, |n| n.get().min(4)),
+            sandbox: Sandbox::default(),
         }
     }
 }
@@ -36,12 +51,21 @@ impl Default for ExtractionLimits {
 pub enum ExtractError {
     #[error("unsupported archive format")]
     UnsupportedFormat,
+    #[error("{0} archives are not supported by this build (enable the `{0}` feature)")]
+    FormatDisabled(ArchiveFormat),
     #[error("path validation failed: {0}")]
     PathValidation(String),
     #[error("extraction limit exceeded: {0}")]
     LimitExceeded(String),
+    #[error("not enough space to unpack: {0}")]
+    InsufficientSpace(String),
+    #[error("cannot confine extraction: {0}")]
+    Sandbox(String),
+    #[error("unknown sandbox mode '{0}'; expected auto, require, or off")]
+    UnknownSandbox(String),
     #[error("I/O error: {0}")]
     Io(#[from] io::Error),
+    #[cfg(feature = "zip")]
     #[error("zip error: {0}")]
     Zip(#[from] zip::result::ZipError),
     #[error("decompression error: {0}")]
@@ -150,6 +174,7 @@ fn detect_and_strip_single_root(dest_dir: impl AsRef<Utf8Path>) -> Result<bool>
     Ok(true)
 }
 
+#[cfg(feature = "zip")]
 fn unpack_zip(
     src: impl AsRef<Utf8Path>,
     dest_dir: impl AsRef<Utf8Path>,
@@ -243,6 +268,30 @@ fn unpack_zip(
     Ok(())
 }
 
+/// Opens the decompressed stream of the tarball at `src`. A `.tar.zst` of several
+/// frames is decoded on `limits.decode_threads` threads.
+fn decompress(src: &Utf8Path, limits: &ExtractionLimits) -> Result<Box<dyn Read>> {
+    let file = File::open(src)?;
+
+    #[cfg(feature = "zstd")]
+    if limits.decode_threads > 1
+        && ArchiveFormat::from_file_name(src.as_str()) == Some(ArchiveFormat::TarZstd)
+        && let Ok(frames) = frames::scan(&file)
+        && frames::parallelizable(&frames)
+    {
+        return Ok(Box::new(frames::reader(
+            file,
+            frames,
+            limits.decode_threads,
+        )));
+    }
+    #[cfg(not(feature = "zstd"))]
+    let _ = limits;
+
+    let (reader, _format) = niffler::get_reader(Box::new(file))?;
+    Ok(reader)
+}
+
 fn unpack_tar(
     src: impl AsRef<Utf8Path>,
     dest_dir: impl AsRef<Utf8Path>,
@@ -251,15 +300,18 @@ fn unpack_tar(
     let src = src.as_ref();
     let dest_dir = dest_dir.as_ref();
 
-    let file = File::open(src)?;
-    let (reader, _format) = niffler::get_reader(Box::new(file))?;
-    let mut archive = tar::Archive::new(reader);
+    let mut archive = tar::Archive::new(decompress(src, limits)?);
 
     let mut total_bytes = 0u64;
     let mut file_count = 0usize;
 
     for entry in archive.entries()? {
         let mut entry = entry?;
+        // `git archive` (and so GitHub's source tarballs) leads with a global
+        // header recording the commit; it holds no file.
+        if entry.header().entry_type().is_pax_global_extensions() {
+            continue;
+        }
         let entry_path = entry.path()?;
 
         validate_path(&entry_path)?;
@@ -324,21 +376,185 @@ fn unpack_tar(
     Ok(())
 }
 
+/// What unpacking an archive takes on disk: an inode for each entry, and the
+/// uncompressed size of its files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArchiveUsage {
+    pub entries: u64,
+    pub bytes: u64,
+}
+
+#[cfg(feature = "zip")]
+fn zip_usage(src: &Utf8Path) -> Result<ArchiveUsage> {
+    let mut archive = zip::ZipArchive::new(File::open(src)?)?;
+    let mut usage = ArchiveUsage::default();
+    for i in 0..archive.len() {
+        usage.entries += 1;
+        usage.bytes += archive.by_index_raw(i)?.size();
+    }
+    Ok(usage)
+}
+
+fn tar_usage(src: &Utf8Path) -> Result<ArchiveUsage> {
+    let (reader, _format) = niffler::get_reader(Box::new(File::open(src)?))?;
+    let mut archive = tar::Archive::new(reader);
+    let mut usage = ArchiveUsage::default();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if entry.header().entry_type().is_pax_global_extensions() {
+            continue;
+        }
+        usage.entries += 1;
+        usage.bytes += entry.header().size()?;
+    }
+    Ok(usage)
+}
+
+/// Reads the entry headers of the archive at `src`, without extracting it, to
+/// tell what unpacking it takes.
+///
+/// # Errors
+///
+/// Returns an error if the format is unsupported or disabled, or the archive
+/// cannot be read.
+pub fn usage(src: impl AsRef<Utf8Path>) -> Result<ArchiveUsage> {
+    let src = src.as_ref();
+
+    match ArchiveFormat::from_file_name(src.as_str()) {
+        Some(format) if !format.is_enabled() => Err(ExtractError::FormatDisabled(format)),
+        #[cfg(feature = "zip")]
+        Some(ArchiveFormat::Zip) => zip_usage(src),
+        Some(_) => tar_usage(src),
+        None => Err(ExtractError::UnsupportedFormat),
+    }
+}
+
+/// Fails before anything is extracted when the filesystem of `dest_dir` has
+/// fewer free inodes than the archive at `src` has entries, or fewer free bytes
+/// than its files take. Filesystems without a fixed inode table, such as btrfs,
+/// report no inodes and are only checked for bytes.
+///
+/// # Errors
+///
+/// Returns `ExtractError::InsufficientSpace` if the archive does not fit, or an
+/// error if it cannot be read or the filesystem cannot be queried.
+pub fn ensure_space(src: impl AsRef<Utf8Path>, dest_dir: impl AsRef<Utf8Path>) -> Result<()> {
+    let dest_dir = dest_dir.as_ref();
+    let usage = usage(src)?;
+    let stat = rustix::fs::statvfs(dest_dir.as_std_path()).map_err(io::Error::from)?;
+
+    if stat.f_files > 0 && stat.f_favail < usage.entries {
+        return Err(ExtractError::InsufficientSpace(format!(
+            "the archive has {} entries but only {} inodes are free on {dest_dir}",
+            usage.entries, stat.f_favail
+        )));
+    }
+    let free = stat.f_bavail.saturating_mul(stat.f_frsize);
+    if free < usage.bytes {
+        return Err(ExtractError::InsufficientSpace(format!(
+            "the archive's files take {} bytes but only {free} are free on {dest_dir}",
+            usage.bytes
+        )));
+    }
+    Ok(())
+}
+
 fn ends_with_ignore_case(s: &str, suffix: &str) -> bool {
     s.len() >= suffix.len() && s[s.len() - suffix.len()..].eq_ignore_ascii_case(suffix)
 }
 
+/// An archive format [`unpack`] supports, identified by file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGzip,
+    TarBzip2,
+    TarXz,
+    TarZstd,
+}
+
+impl ArchiveFormat {
+    /// Identifies the format of `file_name` from its extension, ignoring case.
+    #[must_use]
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let has_suffix = |suffixes: &[&str]| {
+            suffixes
+                .iter()
+                .any(|suffix| ends_with_ignore_case(file_name, suffix))
+        };
+
+        if has_suffix(&[".zip"]) {
+            Some(Self::Zip)
+        } else if has_suffix(&[".tar.gz", ".tgz"]) {
+            Some(Self::TarGzip)
+        } else if has_suffix(&[".tar.bz2", ".tbz2"]) {
+            Some(Self::TarBzip2)
+        } else if has_suffix(&[".tar.xz", ".txz"]) {
+            Some(Self::TarXz)
+        } else if has_suffix(&[".tar.zst"]) {
+            Some(Self::TarZstd)
+        } else {
+            None
+        }
+    }
+
+    /// Longest signature of any format, the number of leading bytes
+    /// [`matches_signature`](Self::matches_signature) needs to decide.
+    pub const SIGNATURE_LEN: usize = 6;
+
+    /// Whether `head`, the first bytes of a file, starts with this format's signature.
+    #[must_use]
+    pub fn matches_signature(self, head: &[u8]) -> bool {
+        let signatures: &[&[u8]] = match self {
+            // Local file header, or the end-of-directory record of an empty archive.
+            Self::Zip => &[b"PK\x03\x04", b"PK\x05\x06"],
+            Self::TarGzip => &[b"\x1f\x8b"],
+            Self::TarBzip2 => &[b"BZh"],
+            Self::TarXz => &[b"\xfd7zXZ\x00"],
+            Self::TarZstd => &[b"\x28\xb5\x2f\xfd"],
+        };
+        signatures
+            .iter()
+            .any(|signature| head.starts_with(signature))
+    }
+
+    /// Whether this build can unpack the format. Each format but gzip needs the
+    /// cargo feature named by its [`Display`](fmt::Display) form.
+    #[must_use]
+    pub fn is_enabled(self) -> bool {
+        match self {
+            Self::Zip => cfg!(feature = "zip"),
+            Self::TarGzip => true,
+            Self::TarBzip2 => cfg!(feature = "bzip2"),
+            Self::TarXz => cfg!(feature = "xz"),
+            Self::TarZstd => cfg!(feature = "zstd"),
+        }
+    }
+}
+
+impl fmt::Display for ArchiveFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Zip => "zip",
+            Self::TarGzip => "gzip",
+            Self::TarBzip2 => "bzip2",
+            Self::TarXz => "xz",
+            Self::TarZstd => "zstd",
+        })
+    }
+}
+
 /// Extracts an archive to the specified directory with default limits.
 ///
 /// This is a convenience wrapper around `unpack_with_limits` that uses
 /// `ExtractionLimits::default()`.
 ///
 /// Supported formats:
-/// - Zip archives (`.zip`)
+/// - Zip archives (`.zip`, with the `zip` feature)
 /// - Tar with gzip (`.tar.gz`, `.tgz`)
-/// - Tar with bzip2 (`.tar.bz2`, `.tbz2`)
-/// - Tar with xz (`.tar.xz`, `.txz`)
-/// - Tar with zstd (`.tar.zst`)
+/// - Tar with bzip2 (`.tar.bz2`, `.tbz2`, with the `bzip2` feature)
+/// - Tar with xz (`.tar.xz`, `.txz`, with the `xz` feature)
+/// - Tar with zstd (`.tar.zst`, with the `zstd` feature)
 ///
 /// # Security
 ///
@@ -351,7 +567,7 @@ fn ends_with_ignore_case(s: &str, suffix: &str) -> bool {
 /// # Errors
 ///
 /// Returns an error if:
-/// - The archive format is unsupported
+/// - The archive format is unsupported, or its feature is not enabled
 /// - An entry path contains `..` or is absolute
 /// - An entry is a symbolic link or other unsupported type (device, pipe, etc.)
 /// - Extraction limits are exceeded (file count, size, decompression ratio)
@@ -364,11 +580,11 @@ pub fn unpack(src: impl AsRef<Utf8Path>, dest_dir: impl AsRef<Utf8Path>) -> Resu
 /// Extracts an archive to the specified directory with custom limits.
 ///
 /// Supported formats:
-/// - Zip archives (`.zip`)
+/// - Zip archives (`.zip`, with the `zip` feature)
 /// - Tar with gzip (`.tar.gz`, `.tgz`)
-/// - Tar with bzip2 (`.tar.bz2`, `.tbz2`)
-/// - Tar with xz (`.tar.xz`, `.txz`)
-/// - Tar with zstd (`.tar.zst`)
+/// - Tar with bzip2 (`.tar.bz2`, `.tbz2`, with the `bzip2` feature)
+/// - Tar with xz (`.tar.xz`, `.txz`, with the `xz` feature)
+/// - Tar with zstd (`.tar.zst`, with the `zstd` feature)
 ///
 /// # Security
 ///
@@ -377,14 +593,17 @@ pub fn unpack(src: impl AsRef<Utf8Path>, dest_dir: impl AsRef<Utf8Path>) -> Resu
 /// - Rejects symbolic links, device files, and named pipes
 /// - Only extracts regular files and directories
 /// - Enforces configurable limits to prevent zip bombs and resource exhaustion
+/// - Unless `limits.sandbox` is `off`, unpacks on a thread confined with Landlock
+///   to reading `src` and writing beneath `dest_dir`
 ///
 /// # Errors
 ///
 /// Returns an error if:
-/// - The archive format is unsupported
+/// - The archive format is unsupported, or its feature is not enabled
 /// - An entry path contains `..` or is absolute
 /// - An entry is a symbolic link or other unsupported type (device, pipe, etc.)
 /// - Extraction limits are exceeded (file count, size, decompression ratio)
+/// - `limits.sandbox` is `require` and extraction cannot be confined
 /// - I/O operations fail during extraction
 /// - The archive is corrupted or cannot be read
 pub fn unpack_with_limits(
@@ -393,22 +612,20 @@ pub fn unpack_with_limits(
     limits: &ExtractionLimits,
 ) -> Result<()> {
     let src = src.as_ref();
-    let path_str = src.as_str();
-
-    if ends_with_ignore_case(path_str, ".zip") {
-        unpack_zip(src, dest_dir, limits)
-    } else if ends_with_ignore_case(path_str, ".tar.gz")
-        || ends_with_ignore_case(path_str, ".tgz")
-        || ends_with_ignore_case(path_str, ".tar.bz2")
-        || ends_with_ignore_case(path_str, ".tbz2")
-        || ends_with_ignore_case(path_str, ".tar.xz")
-        || ends_with_ignore_case(path_str, ".txz")
-        || ends_with_ignore_case(path_str, ".tar.zst")
-    {
-        unpack_tar(src, dest_dir, limits)
-    } else {
-        Err(ExtractError::UnsupportedFormat)
-    }
+    let dest_dir = dest_dir.as_ref();
+
+    sandbox::run(
+        limits.sandbox,
+        src,
+        dest_dir,
+        || match ArchiveFormat::from_file_name(src.as_str()) {
+            Some(format) if !format.is_enabled() => Err(ExtractError::FormatDisabled(format)),
+            #[cfg(feature = "zip")]
+            Some(ArchiveFormat::Zip) => unpack_zip(src, dest_dir, limits),
+            Some(_) => unpack_tar(src, dest_dir, limits),
+            None => Err(ExtractError::UnsupportedFormat),
+        },
+    )
 }
 
 #[cfg(test)]
@@ -416,16 +633,16 @@ mod tests {
     use std::{
         assert_matches,
         fs::{self, File},
-        io::Write,
-        os::unix::fs as unix_fs,
-        process::Command,
     };
+    #[cfg(feature = "zip")]
+    use std::{io::Write, os::unix::fs as unix_fs, process::Command};
 
     use camino_tempfile::tempdir;
     use camino_tempfile_ext::prelude::*;
 
     use super::*;
 
+    #[cfg(feature = "zip")]
     #[test]
     fn test_absolute_path_zip_sanitized_within_dest() {
         let temp_dir = tempdir().unwrap();
@@ -452,6 +669,7 @@ mod tests {
         assert_eq!(fs::read(&extracted).unwrap(), b"evil content");
     }
 
+    #[cfg(feature = "zip")]
     #[test]
     fn test_reject_parent_traversal_zip() {
         let temp_dir = tempdir().unwrap();
@@ -540,6 +758,7 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "zip")]
     #[test]
     fn test_reject_symlink_zip() {
         let temp_dir = tempdir().unwrap();
@@ -609,6 +828,7 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "zip")]
     #[test]
     fn test_zip_single_root_stripped() {
         let temp_dir = tempdir().unwrap();
@@ -683,6 +903,52 @@ mod tests {
         assert!(!extract_dir.join("myapp-v1.0").exists());
     }
 
+    #[test]
+    fn test_tar_pax_global_header_skipped() {
+        let temp_dir = tempdir().unwrap();
+        let tar_gz_path = temp_dir.child("archive.tar.gz");
+
+        let file = File::create(&tar_gz_path).unwrap();
+        let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+        let mut tar = tar::Builder::new(encoder);
+
+        let data = b"52 comment=1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b\n";
+        let mut header = tar::Header::new_ustar();
+        header.set_entry_type(tar::EntryType::XGlobalHeader);
+        header.set_size(data.len() as u64);
+        header.set_cksum();
+        tar.append_data(&mut header, "pax_global_header", &data[..])
+            .unwrap();
+
+        let mut header = tar::Header::new_ustar();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        header.set_mode(0o755);
+        header.set_cksum();
+        tar.append_data(&mut header, "owner-myapp-1a2b3c4/", &[][..])
+            .unwrap();
+
+        let data = b"content";
+        let mut header = tar::Header::new_ustar();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        tar.append_data(&mut header, "owner-myapp-1a2b3c4/file.txt", &data[..])
+            .unwrap();
+
+        tar.into_inner().unwrap().finish().unwrap();
+
+        let extract_dir = temp_dir.child("extract");
+        extract_dir.create_dir_all().unwrap();
+
+        unpack(&tar_gz_path, &extract_dir).unwrap();
+
+        assert!(extract_dir.join("file.txt").exists());
+        assert!(!extract_dir.join("pax_global_header").exists());
+        assert!(!extract_dir.join("owner-myapp-1a2b3c4").exists());
+    }
+
+    #[cfg(feature = "zip")]
     #[test]
     fn test_zip_basic_extraction() {
         let temp_dir = tempdir().unwrap();
@@ -732,6 +998,7 @@ mod tests {
         assert_eq!(content, "Hello from tar.gz!");
     }
 
+    #[cfg(feature = "bzip2")]
     #[test]
     fn test_tar_bz2_extraction() {
         let temp_dir = tempdir().unwrap();
@@ -758,6 +1025,7 @@ mod tests {
         assert_eq!(content, "Hello from tar.bz2!");
     }
 
+    #[cfg(feature = "xz")]
     #[test]
     fn test_tar_xz_extraction() {
         let temp_dir = tempdir().unwrap();
@@ -790,6 +1058,7 @@ mod tests {
         assert_eq!(content, "Hello from tar.xz!");
     }
 
+    #[cfg(feature = "zstd")]
     #[test]
     fn test_tar_zst_extraction() {
         let temp_dir = tempdir().unwrap();
@@ -816,6 +1085,46 @@ mod tests {
         assert_eq!(content, "Hello from tar.zst!");
     }
 
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_multi_frame_tar_zst_extraction() {
+        let temp_dir = tempdir().unwrap();
+        let tar_zst_path = temp_dir.child("archive.tar.zst");
+
+        let mut tar = tar::Builder::new(Vec::new());
+        for i in 0..8 {
+            let data = format!("file {i}\n").repeat(2000);
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, format!("file{i}.txt"), data.as_bytes())
+                .unwrap();
+        }
+        let tarball = tar.into_inner().unwrap();
+        // Independent frames, as `pzstd` writes them.
+        let compressed: Vec<u8> = tarball
+            .chunks(5000)
+            .flat_map(|chunk| zstd::bulk::compress(chunk, 3).unwrap())
+            .collect();
+        fs::write(&tar_zst_path, compressed).unwrap();
+
+        let extract_dir = temp_dir.child("extract");
+        extract_dir.create_dir_all().unwrap();
+
+        let limits = ExtractionLimits {
+            decode_threads: 3,
+            ..Default::default()
+        };
+        unpack_with_limits(&tar_zst_path, &extract_dir, &limits).unwrap();
+
+        for i in 0..8 {
+            let content = fs::read_to_string(extract_dir.join(format!("file{i}.txt"))).unwrap();
+            assert_eq!(content, format!("file {i}\n").repeat(2000));
+        }
+    }
+
+    #[cfg(feature = "zip")]
     #[test]
     fn test_zip_file_count_limit_exceeded() {
         let temp_dir = tempdir().unwrap();
@@ -848,6 +1157,7 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "zip")]
     #[test]
     fn test_zip_individual_file_size_limit_exceeded() {
         let temp_dir = tempdir().unwrap();
@@ -879,6 +1189,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_archive_format_from_file_name() {
+        assert_eq!(
+            ArchiveFormat::from_file_name("app.ZIP"),
+            Some(ArchiveFormat::Zip)
+        );
+        assert_eq!(
+            ArchiveFormat::from_file_name("app-1.0.tgz"),
+            Some(ArchiveFormat::TarGzip)
+        );
+        assert_eq!(
+            ArchiveFormat::from_file_name("app.tar.zst"),
+            Some(ArchiveFormat::TarZstd)
+        );
+        assert_eq!(ArchiveFormat::from_file_name("app.tar"), None);
+    }
+
+    #[test]
+    fn test_archive_format_signatures() {
+        assert!(ArchiveFormat::TarGzip.matches_signature(b"\x1f\x8b\x08"));
+        assert!(ArchiveFormat::TarXz.matches_signature(b"\xfd7zXZ\x00\x00"));
+        assert!(ArchiveFormat::Zip.matches_signature(b"PK\x05\x06"));
+        assert!(!ArchiveFormat::TarBzip2.matches_signature(b"<!DOCTYPE html>"));
+        assert!(!ArchiveFormat::TarZstd.matches_signature(b""));
+    }
+
+    #[cfg(not(feature = "zip"))]
+    #[test]
+    fn test_zip_without_feature_is_rejected() {
+        let temp_dir = tempdir().unwrap();
+        let zip_path = temp_dir.child("archive.zip");
+        zip_path.write_binary(b"PK\x05\x06").unwrap();
+
+        let result = unpack(&zip_path, temp_dir.child("extract"));
+        assert_matches!(
+            result,
+            Err(ExtractError::FormatDisabled(ArchiveFormat::Zip))
+        );
+    }
+
+    #[cfg(feature = "zip")]
     #[test]
     fn test_zip_total_bytes_limit_exceeded() {
         let temp_dir = tempdir().unwrap();
@@ -912,6 +1263,7 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "zip")]
     #[test]
     fn test_zip_decompression_ratio_limit_exceeded() {
         let temp_dir = tempdir().unwrap();
@@ -1045,4 +1397,38 @@ mod tests {
             Err(ExtractError::LimitExceeded(msg)) if msg.contains("total extracted bytes")
         );
     }
+
+    #[test]
+    fn test_usage_counts_entries_and_bytes() {
+        let temp_dir = tempdir().unwrap();
+        let tar_gz_path = temp_dir.child("usage.tar.gz");
+
+        let file = File::create(&tar_gz_path).unwrap();
+        let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+        let mut tar = tar::Builder::new(encoder);
+
+        for i in 0..3 {
+            let mut header = tar::Header::new_gnu();
+            let data = b"content";
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, format!("app/file{i}.txt"), &data[..])
+                .unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+
+        assert_eq!(
+            usage(&tar_gz_path).unwrap(),
+            ArchiveUsage {
+                entries: 3,
+                bytes: 21
+            }
+        );
+        ensure_space(&tar_gz_path, temp_dir.path()).unwrap();
+        assert_matches!(
+            usage(temp_dir.child("usage.rar")),
+            Err(ExtractError::UnsupportedFormat)
+        );
+    }
 }
@@ -0,0 +1,243 @@
+//! Parallel decoding of zstd streams made of several independent frames, as
+//! written by `pzstd` or by concatenating `.zst` files. A single frame can only be
+//! decoded in order, so such streams keep the one-thread path.
+
+use std::{
+    fs::File,
+    io::{self, Cursor, Read},
+    os::unix::fs::FileExt,
+    sync::mpsc::{self, Receiver, SyncSender},
+    thread,
+};
+
+const FRAME_MAGIC: u32 = 0xFD2F_B528;
+const SKIPPABLE_MAGIC: u32 = 0x184D_2A50;
+
+/// Largest frame, compressed or decoded, held in memory whole; streams with a
+/// larger or unsized frame are decoded by one thread as they are read.
+pub(super) const MAX_FRAME_BYTES: u64 = 32 << 20;
+
+/// A data frame of a zstd stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Frame {
+    offset: u64,
+    len: u64,
+    /// Decoded size recorded in the frame header, if any.
+    content_size: Option<u64>,
+}
+
+impl Frame {
+    fn fits_in_memory(&self) -> bool {
+        self.len <= MAX_FRAME_BYTES
+            && self
+                .content_size
+                .is_some_and(|size| size <= MAX_FRAME_BYTES)
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("zstd: {message}"))
+}
+
+fn read_at<const N: usize>(file: &File, offset: u64) -> io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    file.read_exact_at(&mut buf, offset)?;
+    Ok(buf)
+}
+
+/// Finds the data frames of the zstd stream in `file` from their headers
+/// (RFC 8878), skipping skippable frames.
+pub(super) fn scan(file: &File) -> io::Result<Vec<Frame>> {
+    let end = file.metadata()?.len();
+    let mut frames = Vec::new();
+    let mut offset = 0;
+    while offset < end {
+        let magic = u32::from_le_bytes(read_at(file, offset)?);
+        let len = if magic == FRAME_MAGIC {
+            let frame = read_frame(file, offset)?;
+            frames.push(frame);
+            frame.len
+        } else if magic & 0xFFFF_FFF0 == SKIPPABLE_MAGIC {
+            8 + u64::from(u32::from_le_bytes(read_at(file, offset + 4)?))
+        } else {
+            return Err(invalid("unknown frame magic number"));
+        };
+        offset += len;
+    }
+    if offset > end {
+        return Err(invalid("truncated frame"));
+    }
+    Ok(frames)
+}
+
+fn read_frame(file: &File, offset: u64) -> io::Result<Frame> {
+    let [descriptor] = read_at(file, offset + 4)?;
+    let single_segment = descriptor & 0x20 != 0;
+    let dictionary_id_len = [0, 1, 2, 4][usize::from(descriptor & 0x03)];
+    let content_size_offset = offset + 5 + u64::from(!single_segment) + dictionary_id_len;
+    let content_size = match descriptor >> 6 {
+        0 if single_segment => Some(u64::from(read_at::<1>(file, content_size_offset)?[0])),
+        0 => None,
+        1 => Some(u64::from(u16::from_le_bytes(read_at(file, content_size_offset)?)) + 256),
+        2 => Some(u64::from(u32::from_le_bytes(read_at(
+            file,
+            content_size_offset,
+        )?))),
+        _ => Some(u64::from_le_bytes(read_at(file, content_size_offset)?)),
+    };
+    let content_size_len = match descriptor >> 6 {
+        0 => u64::from(single_segment),
+        1 => 2,
+        2 => 4,
+        _ => 8,
+    };
+
+    let mut block = content_size_offset + content_size_len;
+    loop {
+        let [a, b, c] = read_at(file, block)?;
+        let header = u32::from_le_bytes([a, b, c, 0]);
+        block += 3 + match (header >> 1) & 0x03 {
+            // An RLE block stores its one repeated byte.
+            1 => 1,
+            3 => return Err(invalid("reserved block type")),
+            _ => u64::from(header >> 3),
+        };
+        if header & 0x01 != 0 {
+            break;
+        }
+    }
+    let checksum_len = if descriptor & 0x04 == 0 { 0 } else { 4 };
+
+    Ok(Frame {
+        offset,
+        len: block + checksum_len - offset,
+        content_size,
+    })
+}
+
+/// Whether `frames` are worth decoding in parallel: more than one, each small
+/// enough to hold whole.
+pub(super) fn parallelizable(frames: &[Frame]) -> bool {
+    frames.len() > 1 && frames.iter().all(Frame::fits_in_memory)
+}
+
+fn decode(file: &File, frame: Frame) -> io::Result<Vec<u8>> {
+    // Both sizes are at most `MAX_FRAME_BYTES`, checked by `parallelizable`.
+    let mut compressed = vec![0; usize::try_from(frame.len).map_err(io::Error::other)?];
+    file.read_exact_at(&mut compressed, frame.offset)?;
+
+    let mut decoded = Vec::new();
+    zstd::stream::read::Decoder::with_buffer(&compressed[..])?
+        .single_frame()
+        .take(MAX_FRAME_BYTES + 1)
+        .read_to_end(&mut decoded)?;
+    if frame.content_size != Some(decoded.len() as u64) {
+        return Err(invalid("frame size does not match its header"));
+    }
+    Ok(decoded)
+}
+
+/// Decodes `frames` of `file` on up to `workers` threads at once, yielding the
+/// decoded bytes in stream order.
+pub(super) fn reader(file: File, frames: Vec<Frame>, workers: usize) -> FrameReader {
+    let workers = workers.max(1);
+    let (sender, receiver) = mpsc::sync_channel(workers);
+    thread::spawn(move || produce(&file, &frames, workers, &sender));
+    FrameReader {
+        frames: receiver,
+        current: Cursor::default(),
+    }
+}
+
+fn produce(
+    file: &File,
+    frames: &[Frame],
+    workers: usize,
+    sender: &SyncSender<io::Result<Vec<u8>>>,
+) {
+    for batch in frames.chunks(workers) {
+        let decoded: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = batch
+                .iter()
+                .map(|&frame| scope.spawn(move || decode(file, frame)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|_| Err(io::Error::other("zstd decoder thread panicked")))
+                })
+                .collect()
+        });
+        for result in decoded {
+            let failed = result.is_err();
+            // The reader is gone when unpacking stopped early.
+            if sender.send(result).is_err() || failed {
+                return;
+            }
+        }
+    }
+}
+
+/// The decoded stream of frames decoded by [`reader`].
+pub(super) struct FrameReader {
+    frames: Receiver<io::Result<Vec<u8>>>,
+    current: Cursor<Vec<u8>>,
+}
+
+impl Read for FrameReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.current.read(buf)?;
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            match self.frames.recv() {
+                Ok(frame) => self.current = Cursor::new(frame?),
+                Err(mpsc::RecvError) => return Ok(0),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Seek, Write};
+
+    use super::*;
+
+    #[test]
+    fn test_scan_and_decode_frames() {
+        let mut file = camino_tempfile::tempfile().unwrap();
+        let first = zstd::bulk::compress(&[b'a'; 5000], 3).unwrap();
+        let second = zstd::bulk::compress(b"tail", 3).unwrap();
+        file.write_all(&first).unwrap();
+        // A skippable frame, such as the index `pzstd` writes.
+        file.write_all(&[0x5E, 0x2A, 0x4D, 0x18, 2, 0, 0, 0, 0xAA, 0xBB])
+            .unwrap();
+        file.write_all(&second).unwrap();
+        file.rewind().unwrap();
+
+        let frames = scan(&file).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].offset, 0);
+        assert_eq!(frames[0].len, first.len() as u64);
+        assert_eq!(frames[0].content_size, Some(5000));
+        assert_eq!(frames[1].offset, first.len() as u64 + 10);
+        assert_eq!(frames[1].len, second.len() as u64);
+        assert!(parallelizable(&frames));
+
+        let mut decoded = Vec::new();
+        reader(file, frames, 2).read_to_end(&mut decoded).unwrap();
+        assert_eq!(decoded.len(), 5004);
+        assert!(decoded.ends_with(b"atail"));
+    }
+
+    #[test]
+    fn test_scan_rejects_garbage() {
+        let mut file = camino_tempfile::tempfile().unwrap();
+        file.write_all(b"not zstd at all").unwrap();
+        assert!(scan(&file).is_err());
+    }
+}
@@ -0,0 +1,156 @@
+//! Confinement of extraction with Landlock, so a bug in an archive parser cannot
+//! write outside the directory being unpacked into. Landlock restricts a single
+//! thread and the threads it spawns, so extraction runs on a thread of its own
+//! and the rest of the process keeps its access.
+
+use std::{fmt, str::FromStr, thread};
+
+use camino::Utf8Path;
+use serde::Deserialize;
+
+use super::{ExtractError, Result};
+
+/// How extraction is confined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sandbox {
+    /// Confine extraction when the kernel supports Landlock, and extract
+    /// unconfined otherwise.
+    #[default]
+    Auto,
+    /// Refuse to extract unless extraction can be confined.
+    Require,
+    /// Extract unconfined.
+    Off,
+}
+
+impl FromStr for Sandbox {
+    type Err = ExtractError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "require" => Ok(Self::Require),
+            "off" => Ok(Self::Off),
+            other => Err(ExtractError::UnknownSandbox(other.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Sandbox {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Auto => "auto",
+            Self::Require => "require",
+            Self::Off => "off",
+        })
+    }
+}
+
+/// Runs `unpack` on a thread that may only read `src` and write beneath
+/// `dest_dir`, as `mode` asks.
+pub(super) fn run<T: Send>(
+    mode: Sandbox,
+    src: &Utf8Path,
+    dest_dir: &Utf8Path,
+    unpack: impl FnOnce() -> Result<T> + Send,
+) -> Result<T> {
+    if mode == Sandbox::Off {
+        return unpack();
+    }
+    thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                confine(mode, src, dest_dir)?;
+                unpack()
+            })
+            .join()
+            .unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+    })
+}
+
+#[cfg(all(target_os = "linux", feature = "sandbox"))]
+fn confine(mode: Sandbox, src: &Utf8Path, dest_dir: &Utf8Path) -> Result<()> {
+    use landlock::{
+        ABI, Access, AccessFs, CompatLevel, Compatible, Ruleset, RulesetAttr, RulesetCreatedAttr,
+        RulesetStatus, path_beneath_rules,
+    };
+
+    // ABI 2 lets entries be moved between directories beneath `dest_dir`, which
+    // stripping a single top-level directory does. Kernels without it get no
+    // ruleset at all rather than one that breaks extraction.
+    let abi = ABI::V2;
+    let status = Ruleset::default()
+        .set_compatibility(CompatLevel::SoftRequirement)
+        .handle_access(AccessFs::from_all(abi))
+        .and_then(Ruleset::create)
+        .and_then(|ruleset| ruleset.add_rules(path_beneath_rules([src], AccessFs::from_read(abi))))
+        .and_then(|ruleset| {
+            ruleset.add_rules(path_beneath_rules([dest_dir], AccessFs::from_all(abi)))
+        })
+        .and_then(|ruleset| ruleset.restrict_self())
+        .map_err(|e| ExtractError::Sandbox(e.to_string()))?;
+
+    match status.ruleset {
+        RulesetStatus::FullyEnforced | RulesetStatus::PartiallyEnforced => Ok(()),
+        RulesetStatus::NotEnforced if mode == Sandbox::Require => Err(ExtractError::Sandbox(
+            "the kernel does not support Landlock ABI 2 (Linux 5.19 or later)".to_string(),
+        )),
+        RulesetStatus::NotEnforced => {
+            tracing::debug!("Landlock is unavailable; extracting without confinement");
+            Ok(())
+        }
+    }
+}
+
+#[cfg(not(all(target_os = "linux", feature = "sandbox")))]
+fn confine(mode: Sandbox, _src: &Utf8Path, _dest_dir: &Utf8Path) -> Result<()> {
+    if mode == Sandbox::Require {
+        return Err(ExtractError::Sandbox(
+            "this build cannot confine extraction (it needs Linux and the `sandbox` feature)"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use camino_tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_sandbox() {
+        assert_eq!("require".parse::<Sandbox>().unwrap(), Sandbox::Require);
+        assert_eq!(Sandbox::default().to_string(), "auto");
+        assert!("strict".parse::<Sandbox>().is_err());
+    }
+
+    #[test]
+    fn test_confined_thread_cannot_write_elsewhere() {
+        let temp_dir = tempdir().unwrap();
+        let src = temp_dir.path().join("archive.tar.gz");
+        let dest_dir = temp_dir.path().join("staging");
+        let outside = temp_dir.path().join("outside");
+        fs::write(&src, b"archive").unwrap();
+        fs::create_dir(&dest_dir).unwrap();
+
+        let confined = run(Sandbox::Auto, &src, &dest_dir, || {
+            assert_eq!(fs::read(&src)?, b"archive");
+            fs::create_dir(dest_dir.join("bin"))?;
+            fs::write(dest_dir.join("bin/myapp"), b"#!/bin/sh\n")?;
+            fs::rename(dest_dir.join("bin"), dest_dir.join("libexec"))?;
+            Ok(fs::write(&outside, b"escaped").is_err())
+        })
+        .unwrap();
+
+        assert!(dest_dir.join("libexec/myapp").exists());
+        // Without Landlock the write succeeds; with it, it must not have.
+        assert_eq!(confined, !outside.exists());
+        // The calling thread keeps its access.
+        fs::write(&outside, b"allowed").unwrap();
+    }
+}
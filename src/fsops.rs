@@ -1,13 +1,17 @@
 use std::{
     collections::{HashMap, HashSet},
+    fmt,
     fs::{self, File},
-    io::{self, ErrorKind},
+    io::{self, ErrorKind, Write as _},
     os::unix::fs::PermissionsExt,
+    str::FromStr,
+    time::{Duration, SystemTime},
 };
 
 use camino::{Utf8Path, Utf8PathBuf};
-use camino_tempfile::Builder;
+use camino_tempfile::{Builder, NamedUtf8TempFile};
 use rustix::fs::{CWD, RenameFlags, renameat_with};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::{info, warn};
 
@@ -17,10 +21,193 @@ pub enum FsOpsError {
     AlreadyExists(String),
     #[error("I/O error: {0}")]
     Io(#[from] io::Error),
+    #[error("new release is missing previously linked commands: {}", .0.join(", "))]
+    MissingBinaries(Vec<String>),
+    #[error("new release does not link required commands: {}", .0.join(", "))]
+    RequiredBinariesMissing(Vec<String>),
+    #[error("unknown missing-binary policy '{0}': expected 'keep', 'remove', or 'fail'")]
+    UnknownMissingBinary(String),
+    #[error("unknown link filter '{0}': expected 'all', 'runnable', or 'elf'")]
+    UnknownLinkFilter(String),
+    #[error(
+        "invalid size '{0}': expected a number of bytes with an optional unit such as 500MB or 20GiB"
+    )]
+    InvalidSize(String),
+    #[error("unsupported layout: {0}")]
+    UnsupportedLayout(String),
 }
 
 pub type Result<T> = std::result::Result<T, FsOpsError>;
 
+/// What [`link_binaries`] does with a managed link whose command the new release lacks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MissingBinary {
+    /// Keep the link pointing into the older release, which is then never pruned.
+    Keep,
+    /// Delete the link.
+    #[default]
+    Remove,
+    /// Refuse to link the new release.
+    Fail,
+}
+
+impl FromStr for MissingBinary {
+    type Err = FsOpsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "keep" => Ok(Self::Keep),
+            "remove" => Ok(Self::Remove),
+            "fail" => Ok(Self::Fail),
+            other => Err(FsOpsError::UnknownMissingBinary(other.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for MissingBinary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Keep => f.write_str("keep"),
+            Self::Remove => f.write_str("remove"),
+            Self::Fail => f.write_str("fail"),
+        }
+    }
+}
+
+/// Which files with an executable permission bit [`discover_executables`] returns.
+///
+/// Some archives mark every file executable, which would put READMEs and data
+/// files on `PATH`; the stricter filters look at the first bytes of each file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkFilter {
+    /// Every executable file.
+    #[default]
+    All,
+    /// Executable files starting with a `#!` interpreter line or an ELF header.
+    Runnable,
+    /// Executable ELF binaries only, skipping scripts and wrappers.
+    Elf,
+}
+
+impl LinkFilter {
+    const ELF_MAGIC: &[u8] = b"\x7fELF";
+
+    /// Whether the executable file at `path` passes the filter.
+    fn accepts(self, path: &Utf8Path) -> io::Result<bool> {
+        if self == Self::All {
+            return Ok(true);
+        }
+        let mut head = [0; 4];
+        let mut file = File::open(path)?;
+        let mut len = 0;
+        while len < head.len() {
+            match io::Read::read(&mut file, &mut head[len..])? {
+                0 => break,
+                n => len += n,
+            }
+        }
+        let head = &head[..len];
+        Ok(
+            head.starts_with(Self::ELF_MAGIC)
+                || (self == Self::Runnable && head.starts_with(b"#!")),
+        )
+    }
+}
+
+impl FromStr for LinkFilter {
+    type Err = FsOpsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "all" => Ok(Self::All),
+            "runnable" => Ok(Self::Runnable),
+            "elf" => Ok(Self::Elf),
+            other => Err(FsOpsError::UnknownLinkFilter(other.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for LinkFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::All => f.write_str("all"),
+            Self::Runnable => f.write_str("runnable"),
+            Self::Elf => f.write_str("elf"),
+        }
+    }
+}
+
+/// A size in bytes, written as a number with an optional unit: `B`, decimal `KB`,
+/// `MB`, `GB`, `TB`, or binary `KiB`, `MiB`, `GiB`, `TiB` (`K`, `M`, `G`, and `T`
+/// alone are binary too).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ByteSize(pub u64);
+
+impl FromStr for ByteSize {
+    type Err = FsOpsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || FsOpsError::InvalidSize(s.to_string());
+        let s = s.trim();
+        let split = s
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(s.len());
+        let (number, unit) = s.split_at(split);
+        let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+            "" | "b" => 1,
+            "kb" => 1000,
+            "mb" => 1000_u64.pow(2),
+            "gb" => 1000_u64.pow(3),
+            "tb" => 1000_u64.pow(4),
+            "k" | "kib" => 1 << 10,
+            "m" | "mib" => 1 << 20,
+            "g" | "gib" => 1 << 30,
+            "t" | "tib" => 1 << 40,
+            _ => return Err(invalid()),
+        };
+        let number: f64 = number.parse().map_err(|_| invalid())?;
+        #[allow(
+            clippy::cast_precision_loss,
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss
+        )]
+        let bytes = (number * multiplier as f64).round() as u64;
+        Ok(Self(bytes))
+    }
+}
+
+impl TryFrom<String> for ByteSize {
+    type Error = FsOpsError;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+/// Which releases [`prune_old_releases`] keeps besides the active one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Retention {
+    /// How many of the most recent releases to keep.
+    pub count: usize,
+    /// Beyond `count`, delete the oldest releases until the releases directory
+    /// takes at most this many bytes.
+    pub max_size: Option<u64>,
+    /// A release that is never deleted, such as the one the app is pinned at.
+    pub pinned: Option<String>,
+}
+
+impl From<usize> for Retention {
+    fn from(count: usize) -> Self {
+        Self {
+            count,
+            ..Self::default()
+        }
+    }
+}
+
 /// Represents a failed deletion attempt: (tag, `error_message`)
 type FailedDeletion = (String, String);
 
@@ -92,17 +279,140 @@ pub fn atomic_move(
     Ok(target)
 }
 
+/// Atomically replaces an existing release directory with a staged one.
+///
+/// If `<releases_dir>/<tag>` does not exist this behaves exactly like `atomic_move`.
+/// Otherwise the staged directory and the existing release are swapped in a single
+/// `renameat_with` call using `RENAME_EXCHANGE`, so the release path never disappears.
+/// The old contents, now sitting at the staging path, are deleted afterwards and the
+/// releases directory is fsynced.
+///
+/// # Errors
+///
+/// Returns `FsOpsError::Io` if:
+/// - The exchange or rename operation fails
+/// - The replaced release cannot be removed
+/// - The parent directory cannot be opened or synced
+pub fn replace_release(
+    src_dir: impl AsRef<Utf8Path>,
+    releases_dir: impl AsRef<Utf8Path>,
+    tag: &str,
+) -> Result<Utf8PathBuf> {
+    let src_dir = src_dir.as_ref();
+    let releases_dir = releases_dir.as_ref();
+    let target = releases_dir.join(tag);
+
+    if !target.exists() {
+        return atomic_move(src_dir, releases_dir, tag);
+    }
+
+    renameat_with(
+        CWD,
+        src_dir.as_std_path(),
+        CWD,
+        target.as_std_path(),
+        RenameFlags::EXCHANGE,
+    )
+    .map_err(io::Error::from)?;
+
+    File::open(releases_dir)?.sync_all()?;
+    info!("replaced existing release: {}", tag);
+
+    fs::remove_dir_all(src_dir)?;
+
+    Ok(target)
+}
+
+/// Removes a release directory that was moved into place but never activated.
+///
+/// Used to roll back a half-activated release so that later runs and pruning do not
+/// mistake it for a successfully installed version. Missing directories are ignored.
+/// The releases directory is fsynced afterwards.
+///
+/// # Errors
+///
+/// Returns `FsOpsError::Io` if:
+/// - The release directory exists but cannot be removed
+/// - The releases directory cannot be opened or synced
+pub fn discard_release(releases_dir: impl AsRef<Utf8Path>, tag: &str) -> Result<()> {
+    let releases_dir = releases_dir.as_ref();
+    let release_path = releases_dir.join(tag);
+
+    match fs::remove_dir_all(&release_path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(FsOpsError::Io(e)),
+    }
+
+    File::open(releases_dir)?.sync_all()?;
+
+    Ok(())
+}
+
+/// Atomically writes `contents` to `path`.
+///
+/// Creates the parent directory if needed, writes and syncs a temporary file in it,
+/// renames the file over `path`, then fsyncs the parent directory so the rename
+/// itself survives a crash.
+///
+/// # Errors
+///
+/// Returns an error if the parent directory cannot be created or synced, or the
+/// temporary file cannot be created, written, synced, or persisted.
+pub fn write_atomic(path: impl AsRef<Utf8Path>, contents: &[u8]) -> io::Result<()> {
+    let path = path.as_ref();
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_str().is_empty() => parent,
+        _ => Utf8Path::new("."),
+    };
+    fs::create_dir_all(parent)?;
+
+    let mut temp_file = NamedUtf8TempFile::new_in(parent)?;
+    temp_file.write_all(contents)?;
+    temp_file.as_file().sync_all()?;
+    temp_file.persist(path).map_err(|e| e.error)?;
+    File::open(parent)?.sync_all()?;
+
+    Ok(())
+}
+
+/// Atomically writes `value` to `path` as pretty-printed JSON with a trailing
+/// newline, using [`write_atomic`].
+///
+/// Generic over the caller's error type so serialization failures keep their own
+/// variant.
+///
+/// # Errors
+///
+/// Returns an error if `value` cannot be serialized or [`write_atomic`] fails.
+pub fn write_json_atomic<T, E>(path: impl AsRef<Utf8Path>, value: &T) -> std::result::Result<(), E>
+where
+    T: Serialize + ?Sized,
+    E: From<io::Error> + From<serde_json::Error>,
+{
+    let json = serde_json::to_string_pretty(value)? + "\n";
+    write_atomic(path, json.as_bytes())?;
+    Ok(())
+}
+
 /// Discovers all executable files within a directory tree.
 ///
 /// Recursively walks the directory and returns paths (relative to `dir`) of all files
-/// with the executable permission bit set on Unix systems. Non-executable files and
-/// permission errors are silently skipped.
+/// with the executable permission bit set on Unix systems that pass `filter`.
+/// Non-executable files and permission errors are silently skipped.
 ///
 /// # Errors
 ///
 /// Returns `FsOpsError::Io` if the root directory cannot be read or accessed.
-pub fn discover_executables(dir: impl AsRef<Utf8Path>) -> Result<Vec<Utf8PathBuf>> {
-    fn walk(base: &Utf8Path, current: &Utf8Path) -> io::Result<Vec<Utf8PathBuf>> {
+pub fn discover_executables(
+    dir: impl AsRef<Utf8Path>,
+    filter: LinkFilter,
+) -> Result<Vec<Utf8PathBuf>> {
+    fn walk(
+        base: &Utf8Path,
+        current: &Utf8Path,
+        filter: LinkFilter,
+    ) -> io::Result<Vec<Utf8PathBuf>> {
         let entries = fs::read_dir(current)?
             .filter_map(std::result::Result::ok)
             .filter_map(|entry| {
@@ -115,12 +425,13 @@ pub fn discover_executables(dir: impl AsRef<Utf8Path>) -> Result<Vec<Utf8PathBuf
 
         for (path, metadata) in entries {
             if metadata.is_dir() {
-                if let Ok(nested) = walk(base, &path) {
+                if let Ok(nested) = walk(base, &path, filter) {
                     executables.extend(nested);
                 }
             } else if metadata.is_file() {
                 let mode = metadata.permissions().mode();
                 if mode & 0o111 != 0
+                    && filter.accepts(&path).unwrap_or(false)
                     && let Ok(rel_path) = path.strip_prefix(base)
                 {
                     executables.push(rel_path.to_path_buf());
@@ -132,23 +443,77 @@ pub fn discover_executables(dir: impl AsRef<Utf8Path>) -> Result<Vec<Utf8PathBuf
     }
 
     let base = dir.as_ref();
-    let mut executables = walk(base, base)?;
+    let mut executables = walk(base, base, filter)?;
     executables.sort();
     Ok(executables)
 }
 
+/// Directory inside a release that [`relocate_deep_executables`] moves executables into.
+pub const LIBEXEC_DIR: &str = "libexec";
+
+/// Moves executables nested more than `max_depth` directories below `release_dir`
+/// into `release_dir/libexec/`, so the links [`link_binaries`] creates point at
+/// short targets.
+///
+/// A relative symlink is left at each original path, so anything invoking an
+/// executable there still finds it. Executables already directly in `libexec/`
+/// stay put, and one whose name is already taken in `libexec/` is left where it is
+/// with a warning. Returns the original relative paths of the moved executables.
+///
+/// # Errors
+///
+/// Returns `FsOpsError::Io` if executables cannot be discovered, `libexec/` cannot
+/// be created, or an executable cannot be moved or replaced with a symlink.
+pub fn relocate_deep_executables(
+    release_dir: impl AsRef<Utf8Path>,
+    max_depth: usize,
+    filter: LinkFilter,
+) -> Result<Vec<Utf8PathBuf>> {
+    let release_dir = release_dir.as_ref();
+    let libexec = release_dir.join(LIBEXEC_DIR);
+
+    let mut relocated = Vec::new();
+    for rel_path in discover_executables(release_dir, filter)? {
+        let depth = rel_path.components().count() - 1;
+        if depth <= max_depth || rel_path.parent() == Some(Utf8Path::new(LIBEXEC_DIR)) {
+            continue;
+        }
+        let Some(name) = rel_path.file_name() else {
+            continue;
+        };
+        let dest = libexec.join(name);
+        if dest.symlink_metadata().is_ok() {
+            warn!("Not relocating {rel_path}: {LIBEXEC_DIR}/{name} already exists");
+            continue;
+        }
+
+        fs::create_dir_all(&libexec)?;
+        let source = release_dir.join(&rel_path);
+        fs::rename(&source, &dest)?;
+        let target = format!("{}{LIBEXEC_DIR}/{name}", "../".repeat(depth));
+        std::os::unix::fs::symlink(target, &source)?;
+        relocated.push(rel_path);
+    }
+
+    Ok(relocated)
+}
+
 /// Creates symlinks in `bin_dir` for all executables found in `release_dir`.
 ///
-/// Discovers all executables in `release_dir` recursively and creates flattened symlinks
+/// Discovers the executables in `release_dir` that pass `filter` recursively and creates flattened symlinks
 /// in `bin_dir` that point to `../releases/<tag>/<relative_path>`. The tag is extracted
 /// from the last component of `release_dir`. Nested executables are flattened to the bin
 /// root using only their filename. Uses atomic temp+rename pattern for each symlink to
 /// ensure no partial state is visible.
 ///
-/// Before creating new symlinks, removes any stale symlinks from previous releases.
-/// A symlink is considered stale if it points to `../releases/*` and is not present
-/// in the current set of executables. Non-release symlinks (those not pointing to
-/// `../releases/*`) are preserved.
+/// Before creating new symlinks, handles stale symlinks from previous releases. A
+/// symlink is considered stale if it is listed in the bin directory's link manifest
+/// (see [`managed_links`]) and is not present in the current set of executables.
+/// `on_missing` decides whether stale links are removed, kept pointing at their older
+/// release (recorded in the kept-link manifest, see [`kept_links`]), or make the call
+/// fail before anything is changed. Links whose target no longer exists are always
+/// removed. Links distronomicon did not create are preserved. The manifests are
+/// rewritten atomically with the new set of links once they are in place.
 ///
 /// If multiple executables share the same filename (e.g., `tools/cli` and `bin/cli`),
 /// a warning is logged and the last executable processed will win. The warning includes
@@ -156,6 +521,9 @@ pub fn discover_executables(dir: impl AsRef<Utf8Path>) -> Result<Vec<Utf8PathBuf
 ///
 /// # Errors
 ///
+/// Returns `FsOpsError::MissingBinaries` if `on_missing` is [`MissingBinary::Fail`]
+/// and a linked command is missing from the release.
+///
 /// Returns `FsOpsError::Io` if:
 /// - Executables cannot be discovered
 /// - The tag cannot be extracted from `release_dir`
@@ -164,6 +532,8 @@ pub fn discover_executables(dir: impl AsRef<Utf8Path>) -> Result<Vec<Utf8PathBuf
 pub fn link_binaries(
     release_dir: impl AsRef<Utf8Path>,
     bin_dir: impl AsRef<Utf8Path>,
+    on_missing: MissingBinary,
+    filter: LinkFilter,
 ) -> Result<()> {
     let release_dir = release_dir.as_ref();
     let bin_dir = bin_dir.as_ref();
@@ -172,7 +542,7 @@ pub fn link_binaries(
         .file_name()
         .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "release_dir has no filename"))?;
 
-    let executables = discover_executables(release_dir)?;
+    let executables = discover_executables(release_dir, filter)?;
 
     let collision_map = executables
         .iter()
@@ -197,39 +567,49 @@ pub fn link_binaries(
         .filter_map(|path| path.file_name())
         .collect::<HashSet<_>>();
 
-    if bin_dir.exists() {
-        let existing_links = fs::read_dir(bin_dir)?
-            .filter_map(std::result::Result::ok)
-            .filter_map(|entry| {
-                let path = Utf8PathBuf::try_from(entry.path()).ok()?;
-                let metadata = entry.file_type().ok()?;
-                if metadata.is_symlink() {
-                    Some(path)
-                } else {
-                    None
-                }
-            })
+    let stale = managed_links(bin_dir)?
+        .into_iter()
+        .filter(|link_path| {
+            link_path
+                .file_name()
+                .is_some_and(|name| !current_names.contains(name))
+        })
+        .collect::<Vec<_>>();
+
+    if on_missing == MissingBinary::Fail {
+        let missing = stale
+            .iter()
+            .filter(|link_path| link_path.exists())
+            .filter_map(|link_path| link_path.file_name().map(str::to_string))
             .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return Err(FsOpsError::MissingBinaries(missing));
+        }
+    }
 
-        for link_path in existing_links {
-            if let Ok(target) = fs::read_link(&link_path) {
-                let target_str = target.to_string_lossy();
-                if target_str.starts_with("../releases/")
-                    && let Some(link_name) = link_path.file_name()
-                    && !current_names.contains(&link_name)
-                {
-                    let _ = fs::remove_file(&link_path);
-                }
-            }
+    let mut kept_names = Vec::new();
+    for link_path in &stale {
+        if on_missing == MissingBinary::Keep
+            && link_path.exists()
+            && let Some(name) = link_path.file_name()
+        {
+            let target = fs::read_link(link_path)?;
+            warn!(
+                "{name} is missing from {tag}; keeping bin/{name} -> {}",
+                target.display()
+            );
+            kept_names.push(name);
+        } else {
+            let _ = fs::remove_file(link_path);
         }
     }
 
-    for rel_path in executables {
+    for rel_path in &executables {
         let filename = rel_path
             .file_name()
             .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "executable has no filename"))?;
 
-        let target = Utf8PathBuf::from("../releases").join(tag).join(&rel_path);
+        let target = Utf8PathBuf::from("../releases").join(tag).join(rel_path);
         let temp_link = bin_dir.join(format!("{filename}.tmp"));
         let final_link = bin_dir.join(filename);
 
@@ -238,12 +618,443 @@ pub fn link_binaries(
         fs::rename(&temp_link, &final_link)?;
     }
 
+    let mut link_names = current_names
+        .into_iter()
+        .chain(kept_names.iter().copied())
+        .collect::<Vec<_>>();
+    link_names.sort_unstable();
+    write_manifest(bin_dir, LINK_MANIFEST, &link_names)?;
+    kept_names.sort_unstable();
+    write_manifest(bin_dir, KEPT_MANIFEST, &kept_names)?;
+
     let bin_file = File::open(bin_dir)?;
     bin_file.sync_all()?;
 
     Ok(())
 }
 
+/// Checks that each of `required` is a link in `bin_dir` to an executable of
+/// `release_dir`, as [`link_binaries`] leaves them once the release is active.
+///
+/// # Errors
+///
+/// Returns `FsOpsError::RequiredBinariesMissing` naming the commands that are
+/// missing, or linked into another release, and `FsOpsError::Io` if
+/// `release_dir` cannot be resolved.
+pub fn check_required_binaries(
+    release_dir: impl AsRef<Utf8Path>,
+    bin_dir: impl AsRef<Utf8Path>,
+    required: &[String],
+) -> Result<()> {
+    let release_dir = release_dir.as_ref().canonicalize_utf8()?;
+    let bin_dir = bin_dir.as_ref();
+
+    let missing: Vec<_> = required
+        .iter()
+        .filter(|name| {
+            !bin_dir
+                .join(name.as_str())
+                .canonicalize_utf8()
+                .is_ok_and(|target| target.starts_with(&release_dir) && target.is_file())
+        })
+        .cloned()
+        .collect();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(FsOpsError::RequiredBinariesMissing(missing))
+    }
+}
+
+/// Name of the manifest in each bin directory listing the links distronomicon created.
+pub const LINK_MANIFEST: &str = ".distronomicon-links";
+
+/// Name of the manifest listing managed links kept from an older release because the
+/// active release lacks the command.
+pub const KEPT_MANIFEST: &str = ".distronomicon-kept";
+
+fn write_manifest(bin_dir: &Utf8Path, file_name: &str, names: &[&str]) -> io::Result<()> {
+    let manifest = bin_dir.join(file_name);
+    let temp_manifest = bin_dir.join(format!("{file_name}.tmp"));
+
+    let mut file = File::create(&temp_manifest)?;
+    for name in names {
+        writeln!(file, "{name}")?;
+    }
+    file.sync_all()?;
+    fs::rename(&temp_manifest, &manifest)
+}
+
+/// Lists the symlinks in `bin_dir` that were created by distronomicon.
+///
+/// Managed links are read from the `.distronomicon-links` manifest written by
+/// [`link_binaries`]; entries that are no longer symlinks are skipped. For bin
+/// directories created before the manifest existed, falls back to treating any
+/// symlink that points into `../releases/` as managed. Returns an empty list if
+/// `bin_dir` does not exist.
+///
+/// # Errors
+///
+/// Returns `FsOpsError::Io` if the bin directory or manifest exists but cannot be read.
+pub fn managed_links(bin_dir: impl AsRef<Utf8Path>) -> Result<Vec<Utf8PathBuf>> {
+    let bin_dir = bin_dir.as_ref();
+
+    match fs::read_to_string(bin_dir.join(LINK_MANIFEST)) {
+        Ok(contents) => {
+            let mut links = contents
+                .lines()
+                .map(str::trim)
+                .filter(|name| !name.is_empty() && !name.contains('/'))
+                .map(|name| bin_dir.join(name))
+                .filter(|path| path.is_symlink())
+                .collect::<Vec<_>>();
+            links.sort();
+            links.dedup();
+            return Ok(links);
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    let entries = match fs::read_dir(bin_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut links = entries
+        .filter_map(std::result::Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_symlink()))
+        .filter_map(|entry| Utf8PathBuf::try_from(entry.path()).ok())
+        .filter(|path| {
+            fs::read_link(path)
+                .is_ok_and(|target| target.to_string_lossy().starts_with("../releases/"))
+        })
+        .collect::<Vec<_>>();
+    links.sort();
+
+    Ok(links)
+}
+
+/// Lists the symlinks in `bin_dir` that resolve to a file under `releases_dir`,
+/// whether they are relative or absolute, so links made by hand can be adopted.
+///
+/// Broken links are skipped. Returns an empty list if either directory does not
+/// exist.
+///
+/// # Errors
+///
+/// Returns `FsOpsError::Io` if either directory exists but cannot be read.
+pub fn links_into_releases(
+    bin_dir: impl AsRef<Utf8Path>,
+    releases_dir: impl AsRef<Utf8Path>,
+) -> Result<Vec<Utf8PathBuf>> {
+    let bin_dir = bin_dir.as_ref();
+
+    let entries = match fs::read_dir(bin_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let releases_dir = match fs::canonicalize(releases_dir.as_ref()) {
+        Ok(releases_dir) => releases_dir,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut links = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_symlink() {
+            continue;
+        }
+        let Ok(path) = Utf8PathBuf::try_from(entry.path()) else {
+            continue;
+        };
+        if fs::canonicalize(&path).is_ok_and(|target| target.starts_with(&releases_dir)) {
+            links.push(path);
+        }
+    }
+    links.sort();
+
+    Ok(links)
+}
+
+/// Records `links` in `bin_dir` as managed, so that later updates replace and remove
+/// them like links they created.
+///
+/// # Errors
+///
+/// Returns `FsOpsError::Io` if the manifest cannot be written or the directory
+/// cannot be synced.
+pub fn adopt_links(bin_dir: impl AsRef<Utf8Path>, links: &[Utf8PathBuf]) -> Result<()> {
+    let bin_dir = bin_dir.as_ref();
+    let mut names = links
+        .iter()
+        .filter_map(|link| link.file_name())
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+    write_manifest(bin_dir, LINK_MANIFEST, &names)?;
+    File::open(bin_dir)?.sync_all()?;
+    Ok(())
+}
+
+/// Moves the release a `current` symlink in `app_dir` points to into
+/// `releases/<tag>`, named after its directory, links its executables from `bin/`,
+/// and returns the tag.
+///
+/// The release may sit in `app_dir` itself or already in `releases/`. `current` is
+/// first repointed through a temporary `releases/<tag>` symlink to the old location,
+/// which is then exchanged with the directory, so `current` resolves at every
+/// moment. `current` is left pointing at the adopted release; other directories in
+/// `app_dir` are left alone.
+///
+/// # Errors
+///
+/// Returns `FsOpsError::UnsupportedLayout` if `current` is missing, not a symlink, or
+/// does not point to a directory in `app_dir` or `releases/`.
+///
+/// Returns `FsOpsError::Io` if the release cannot be moved or linked.
+pub fn convert_current_layout(app_dir: impl AsRef<Utf8Path>) -> Result<String> {
+    let app_dir = app_dir.as_ref().canonicalize_utf8()?;
+    let current = app_dir.join("current");
+    let releases_dir = app_dir.join("releases");
+
+    let unsupported = || {
+        FsOpsError::UnsupportedLayout(format!(
+            "{current} must be a symlink to a release directory in {app_dir} or {releases_dir}"
+        ))
+    };
+    let is_symlink = fs::symlink_metadata(&current).is_ok_and(|m| m.file_type().is_symlink());
+    if !is_symlink {
+        return Err(unsupported());
+    }
+    let target = current.canonicalize_utf8().map_err(|_| unsupported())?;
+    let (Some(parent), Some(tag)) = (target.parent(), target.file_name()) else {
+        return Err(unsupported());
+    };
+    if !target.is_dir() || (parent != app_dir && parent != releases_dir) {
+        return Err(unsupported());
+    }
+    let tag = tag.to_string();
+
+    if parent == app_dir {
+        fs::create_dir_all(&releases_dir)?;
+        let alias = releases_dir.join(&tag);
+        std::os::unix::fs::symlink(Utf8Path::new("..").join(&tag), &alias)?;
+        replace_with_symlink(&current, &Utf8Path::new("releases").join(&tag), false)?;
+        renameat_with(
+            CWD,
+            target.as_std_path(),
+            CWD,
+            alias.as_std_path(),
+            RenameFlags::EXCHANGE,
+        )
+        .map_err(io::Error::from)?;
+        fs::remove_file(&target)?;
+        File::open(&releases_dir)?.sync_all()?;
+        File::open(&app_dir)?.sync_all()?;
+    }
+
+    let bin_dir = app_dir.join("bin");
+    fs::create_dir_all(&bin_dir)?;
+    link_binaries(
+        releases_dir.join(&tag),
+        &bin_dir,
+        MissingBinary::Remove,
+        LinkFilter::All,
+    )?;
+
+    Ok(tag)
+}
+
+/// Restructures an app installed directly into `app_dir` into `releases/<tag>` and
+/// `bin/`.
+///
+/// The release is assembled from hard links in the staging directory and moved into
+/// place, so no file is copied and running processes are unaffected. The new `bin/`
+/// is built in staging as well, then each original entry is swapped for a symlink:
+/// executables to their link in `bin/`, so existing references follow later
+/// updates, and everything else to its copy in the adopted release. A `bin/`
+/// directory among the originals is exchanged with the new one in one rename.
+///
+/// # Errors
+///
+/// Returns `FsOpsError::UnsupportedLayout` if `app_dir` is empty, already has a
+/// `releases/` directory, or has a `bin` that is not a directory.
+///
+/// Returns `FsOpsError::Io` if the release cannot be assembled, linked, or swapped in.
+pub fn convert_flat_layout(app_dir: impl AsRef<Utf8Path>, tag: &str) -> Result<()> {
+    fn link_tree(src: &Utf8Path, dst: &Utf8Path) -> io::Result<()> {
+        let metadata = fs::symlink_metadata(src)?;
+        if metadata.file_type().is_symlink() {
+            std::os::unix::fs::symlink(fs::read_link(src)?, dst)?;
+        } else if metadata.is_dir() {
+            fs::create_dir(dst)?;
+            for entry in fs::read_dir(src)? {
+                let name = entry?.file_name();
+                let name = name.to_str().ok_or_else(|| {
+                    io::Error::new(ErrorKind::InvalidData, "file name is not UTF-8")
+                })?;
+                link_tree(&src.join(name), &dst.join(name))?;
+            }
+            fs::set_permissions(dst, metadata.permissions())?;
+        } else if metadata.is_file() {
+            fs::hard_link(src, dst)?;
+        }
+        Ok(())
+    }
+
+    let app_dir = app_dir.as_ref();
+    let releases_dir = app_dir.join("releases");
+    let staging_dir = app_dir.join("staging");
+    if releases_dir.exists() {
+        return Err(FsOpsError::UnsupportedLayout(format!(
+            "{app_dir} already has a releases directory"
+        )));
+    }
+
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(app_dir)? {
+        let entry = entry?;
+        let Ok(name) = entry.file_name().into_string() else {
+            return Err(io::Error::new(ErrorKind::InvalidData, "file name is not UTF-8").into());
+        };
+        if name != "staging" {
+            entries.push((name, entry.metadata()?));
+        }
+    }
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    if entries.is_empty() {
+        return Err(FsOpsError::UnsupportedLayout(format!("{app_dir} is empty")));
+    }
+    let has_bin = match entries.iter().find(|(name, _)| name == "bin") {
+        Some((_, metadata)) if metadata.is_dir() => true,
+        Some(_) => {
+            return Err(FsOpsError::UnsupportedLayout(format!(
+                "{app_dir}/bin is not a directory"
+            )));
+        }
+        None => false,
+    };
+
+    fs::create_dir_all(&staging_dir)?;
+    let staged = Builder::new()
+        .prefix(&format!("{tag}."))
+        .tempdir_in(&staging_dir)?
+        .keep();
+    for (name, _) in &entries {
+        link_tree(&app_dir.join(name), &staged.join(name))?;
+    }
+    fs::create_dir(&releases_dir)?;
+    let release_dir = atomic_move(&staged, &releases_dir, tag)?;
+
+    let staged_bin = Builder::new()
+        .prefix("bin.")
+        .tempdir_in(&staging_dir)?
+        .keep();
+    link_binaries(
+        &release_dir,
+        &staged_bin,
+        MissingBinary::Remove,
+        LinkFilter::All,
+    )?;
+    let commands = managed_links(&staged_bin)?
+        .into_iter()
+        .filter_map(|link| link.file_name().map(str::to_string))
+        .collect::<HashSet<_>>();
+
+    for (name, metadata) in &entries {
+        let path = app_dir.join(name);
+        if name == "bin" {
+            renameat_with(
+                CWD,
+                staged_bin.as_std_path(),
+                CWD,
+                path.as_std_path(),
+                RenameFlags::EXCHANGE,
+            )
+            .map_err(io::Error::from)?;
+            fs::remove_dir_all(&staged_bin)?;
+        } else if metadata.is_file()
+            && metadata.permissions().mode() & 0o111 != 0
+            && commands.contains(name)
+        {
+            replace_with_symlink(&path, &Utf8Path::new("bin").join(name), false)?;
+        } else {
+            let target = Utf8Path::new("releases").join(tag).join(name);
+            replace_with_symlink(&path, &target, metadata.is_dir())?;
+        }
+    }
+    if !has_bin {
+        fs::rename(&staged_bin, app_dir.join("bin"))?;
+    }
+    File::open(app_dir)?.sync_all()?;
+
+    Ok(())
+}
+
+/// Atomically replaces `path` with a symlink to `target`, exchanging it for the
+/// link if it is a directory and removing the directory afterwards.
+fn replace_with_symlink(path: &Utf8Path, target: &Utf8Path, is_dir: bool) -> io::Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "path has no filename"))?;
+    let temp_link = path.with_file_name(format!(".{name}.adopt"));
+    let _ = fs::remove_file(&temp_link);
+    std::os::unix::fs::symlink(target, &temp_link)?;
+    if is_dir {
+        renameat_with(
+            CWD,
+            temp_link.as_std_path(),
+            CWD,
+            path.as_std_path(),
+            RenameFlags::EXCHANGE,
+        )?;
+        fs::remove_dir_all(&temp_link)
+    } else {
+        fs::rename(&temp_link, path)
+    }
+}
+
+/// Lists the managed links in `bin_dir` that [`link_binaries`] kept pointing into an
+/// older release.
+///
+/// Returns an empty list if `bin_dir` or its kept-link manifest does not exist.
+///
+/// # Errors
+///
+/// Returns `FsOpsError::Io` if the manifest exists but cannot be read.
+pub fn kept_links(bin_dir: impl AsRef<Utf8Path>) -> Result<Vec<Utf8PathBuf>> {
+    let bin_dir = bin_dir.as_ref();
+
+    let contents = match fs::read_to_string(bin_dir.join(KEPT_MANIFEST)) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut links = contents
+        .lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty() && !name.contains('/'))
+        .map(|name| bin_dir.join(name))
+        .filter(|path| path.is_symlink())
+        .collect::<Vec<_>>();
+    links.sort();
+    Ok(links)
+}
+
+/// Returns the release tag a `bin/` link points into (`../releases/<tag>/...`).
+fn linked_release(link: &Utf8Path) -> Option<String> {
+    let target = Utf8PathBuf::try_from(fs::read_link(link).ok()?).ok()?;
+    let mut components = target.components();
+    components
+        .by_ref()
+        .find(|component| component.as_str() == "releases")?;
+    components.next().map(|tag| tag.as_str().to_string())
+}
+
 /// Recursively fsyncs all files and directories in a directory tree.
 ///
 /// Walks the directory tree, calling `sync_all()` on every file and directory to ensure
@@ -283,41 +1094,23 @@ pub fn fsync_directory_tree(path: impl AsRef<Utf8Path>) -> Result<()> {
     Ok(())
 }
 
-/// Prunes old releases from the releases directory, keeping only the most recent ones.
-///
-/// Sorts release directories by modification time (newest first) and deletes releases
-/// beyond the `retain` count. Always preserves `current_tag` regardless of its age.
-///
-/// # Arguments
-///
-/// * `releases_dir` - Path to the releases directory containing versioned subdirectories
-/// * `current_tag` - The currently active release tag (will never be deleted)
-/// * `retain` - Number of recent releases to keep. The current release is always preserved even if it falls outside this count.
-///
-/// # Returns
+/// Lists release directories under `releases_dir`, newest first.
 ///
-/// A tuple containing:
-/// - A vector of successfully deleted release tag names
-/// - A vector of tuples with (tag, `error_message`) for failed deletions
+/// Releases are ordered by modification time, with ties broken by tag name in
+/// descending order. Entries that are not directories are skipped. Returns an
+/// empty list if `releases_dir` does not exist.
 ///
 /// # Errors
 ///
-/// Returns `FsOpsError::Io` if:
-/// - The releases directory cannot be read
-/// - Directory metadata cannot be accessed
-/// - A release directory cannot be deleted
-pub fn prune_old_releases(
-    releases_dir: impl AsRef<Utf8Path>,
-    current_tag: &str,
-    retain: usize,
-) -> Result<PruneResult> {
+/// Returns `FsOpsError::Io` if the releases directory exists but cannot be read.
+pub fn list_releases(releases_dir: impl AsRef<Utf8Path>) -> Result<Vec<(String, SystemTime)>> {
     let releases_dir = releases_dir.as_ref();
 
     if !releases_dir.exists() {
-        return Ok((Vec::new(), Vec::new()));
+        return Ok(Vec::new());
     }
 
-    let entries = fs::read_dir(releases_dir)?
+    let mut entries = fs::read_dir(releases_dir)?
         .filter_map(|entry| {
             let entry = entry.ok()?;
             let path = Utf8PathBuf::try_from(entry.path()).ok()?;
@@ -334,20 +1127,178 @@ pub fn prune_old_releases(
         })
         .collect::<Vec<_>>();
 
-    let mut sorted_entries = entries;
-    sorted_entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| b.0.cmp(&a.0)));
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| b.0.cmp(&a.0)));
+    Ok(entries)
+}
 
-    let to_delete = sorted_entries
-        .iter()
-        .skip(retain)
-        .map(|(tag, _)| tag.clone())
-        .filter(|tag| tag != current_tag)
+/// Computes the total size in bytes of all files under `path`.
+///
+/// Symlinks are counted by their own size and never followed.
+///
+/// # Errors
+///
+/// Returns `FsOpsError::Io` if any directory cannot be read or any entry's metadata
+/// cannot be accessed.
+pub fn dir_size(path: impl AsRef<Utf8Path>) -> Result<u64> {
+    fn walk(path: &Utf8Path) -> io::Result<u64> {
+        let mut total = 0;
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let metadata = entry.path().symlink_metadata()?;
+            if metadata.is_dir() {
+                let path = Utf8PathBuf::try_from(entry.path())
+                    .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+                total += walk(&path)?;
+            } else {
+                total += metadata.len();
+            }
+        }
+        Ok(total)
+    }
+
+    Ok(walk(path.as_ref())?)
+}
+
+/// Lists the releases that [`prune_old_releases`] would delete, oldest last.
+///
+/// Releases beyond `retention.count` are listed, then, with a size budget, the
+/// oldest remaining ones until the releases that stay fit within it. The current
+/// release, the pinned one, and releases that a kept link in the sibling `bin/`
+/// directory still points into are never listed, even if that leaves the directory
+/// over budget.
+///
+/// # Errors
+///
+/// Returns `FsOpsError::Io` if the releases directory exists but cannot be read.
+pub fn releases_to_prune(
+    releases_dir: impl AsRef<Utf8Path>,
+    current_tag: &str,
+    retention: impl Into<Retention>,
+) -> Result<Vec<String>> {
+    let releases_dir = releases_dir.as_ref();
+    let retention = retention.into();
+    let in_use = match releases_dir.parent() {
+        Some(app_dir) => kept_links(app_dir.join("bin"))?
+            .iter()
+            .filter_map(|link| linked_release(link))
+            .collect::<HashSet<_>>(),
+        None => HashSet::new(),
+    };
+    let protected = |tag: &str| {
+        tag == current_tag || in_use.contains(tag) || retention.pinned.as_deref() == Some(tag)
+    };
+
+    let releases = list_releases(releases_dir)?
+        .into_iter()
+        .map(|(tag, _)| tag)
         .collect::<Vec<_>>();
+    let mut to_delete = releases
+        .iter()
+        .skip(retention.count)
+        .filter(|tag| !protected(tag))
+        .cloned()
+        .collect::<HashSet<_>>();
+
+    if let Some(max_size) = retention.max_size {
+        let mut sizes = Vec::new();
+        for tag in releases.iter().filter(|tag| !to_delete.contains(*tag)) {
+            sizes.push((tag, dir_size(releases_dir.join(tag))?));
+        }
+        let mut total = sizes.iter().map(|(_, size)| size).sum::<u64>();
+        for (tag, size) in sizes.into_iter().rev() {
+            if total <= max_size {
+                break;
+            }
+            if !protected(tag) {
+                to_delete.insert(tag.clone());
+                total -= size;
+            }
+        }
+    }
+
+    Ok(releases
+        .into_iter()
+        .filter(|tag| to_delete.contains(tag))
+        .collect())
+}
 
+/// Lists the releases that [`prune_old_releases`] would delete once `incoming_tag`,
+/// taking `incoming_size` bytes, is installed as the newest release.
+///
+/// This is [`releases_to_prune`] for a release that is not on disk yet: it takes one
+/// of the retained slots and its size comes out of the budget. A tag that is already
+/// installed is simply made current.
+///
+/// # Errors
+///
+/// Returns `FsOpsError::Io` if the releases directory exists but cannot be read.
+pub fn releases_to_prune_after(
+    releases_dir: impl AsRef<Utf8Path>,
+    incoming_tag: &str,
+    incoming_size: u64,
+    retention: impl Into<Retention>,
+) -> Result<Vec<String>> {
+    let releases_dir = releases_dir.as_ref();
+    let retention = retention.into();
+    if releases_dir.join(incoming_tag).is_dir() {
+        return releases_to_prune(releases_dir, incoming_tag, retention);
+    }
+    let retention = Retention {
+        count: retention.count.saturating_sub(1),
+        max_size: retention
+            .max_size
+            .map(|max_size| max_size.saturating_sub(incoming_size)),
+        pinned: retention.pinned,
+    };
+    releases_to_prune(releases_dir, incoming_tag, retention)
+}
+
+/// Prunes old releases from the releases directory, keeping only the most recent ones.
+///
+/// Sorts release directories by modification time (newest first) and deletes releases
+/// beyond the retained count, then the oldest ones over the size budget if there is
+/// one (see [`releases_to_prune`]). Always preserves `current_tag` regardless of its
+/// age.
+///
+/// # Arguments
+///
+/// * `releases_dir` - Path to the releases directory containing versioned subdirectories
+/// * `current_tag` - The currently active release tag (will never be deleted)
+/// * `retention` - Number of recent releases to keep (a plain count converts), and optionally a size budget and pinned release. The current release is always preserved even if it falls outside this count.
+///
+/// # Returns
+///
+/// A tuple containing:
+/// - A vector of successfully deleted release tag names
+/// - A vector of tuples with (tag, `error_message`) for failed deletions
+///
+/// # Errors
+///
+/// Returns `FsOpsError::Io` if:
+/// - The releases directory cannot be read
+/// - Directory metadata cannot be accessed
+/// - A release directory cannot be deleted
+pub fn prune_old_releases(
+    releases_dir: impl AsRef<Utf8Path>,
+    current_tag: &str,
+    retention: impl Into<Retention>,
+) -> Result<PruneResult> {
+    let releases_dir = releases_dir.as_ref();
+    let to_delete = releases_to_prune(releases_dir, current_tag, retention)?;
+    Ok(remove_releases(releases_dir, to_delete))
+}
+
+/// Deletes the releases named by `tags` from the releases directory, as chosen by
+/// [`releases_to_prune`].
+///
+/// Returns the deleted tags and the tags that could not be deleted with why.
+#[must_use]
+pub fn remove_releases(releases_dir: impl AsRef<Utf8Path>, tags: Vec<String>) -> PruneResult {
+    let releases_dir = releases_dir.as_ref();
     let mut deleted = Vec::new();
     let mut failed = Vec::new();
 
-    for tag in to_delete {
+    for tag in tags {
         let release_path = releases_dir.join(&tag);
         match fs::remove_dir_all(&release_path) {
             Ok(()) => {
@@ -362,12 +1313,73 @@ pub fn prune_old_releases(
         }
     }
 
-    Ok((deleted, failed))
+    (deleted, failed)
+}
+
+/// Lists staging directories under `staging_dir` last modified at least `max_age` ago.
+///
+/// Staging directories are normally removed once a release is installed; any that
+/// remain were left behind by an interrupted or failed update. Returns an empty list
+/// if `staging_dir` does not exist.
+///
+/// # Errors
+///
+/// Returns `FsOpsError::Io` if the staging directory exists but cannot be read.
+pub fn stale_staging(staging_dir: impl AsRef<Utf8Path>, max_age: Duration) -> Result<Vec<String>> {
+    let now = SystemTime::now();
+    let stale = list_releases(staging_dir)?
+        .into_iter()
+        .filter(|(_, modified)| {
+            now.duration_since(*modified)
+                .is_ok_and(|age| age >= max_age)
+        })
+        .map(|(name, _)| name)
+        .collect();
+    Ok(stale)
+}
+
+/// Removes staging directories last modified at least `max_age` ago.
+///
+/// Callers should hold the app lock so that an in-progress update's staging
+/// directory is never removed.
+///
+/// # Returns
+///
+/// A tuple containing:
+/// - A vector of removed staging directory names
+/// - A vector of tuples with (name, `error_message`) for failed removals
+///
+/// # Errors
+///
+/// Returns `FsOpsError::Io` if the staging directory exists but cannot be read.
+pub fn remove_stale_staging(
+    staging_dir: impl AsRef<Utf8Path>,
+    max_age: Duration,
+) -> Result<PruneResult> {
+    let staging_dir = staging_dir.as_ref();
+    let mut removed = Vec::new();
+    let mut failed = Vec::new();
+
+    for name in stale_staging(staging_dir, max_age)? {
+        match fs::remove_dir_all(staging_dir.join(&name)) {
+            Ok(()) => {
+                info!("removed stale staging directory: {}", name);
+                removed.push(name);
+            }
+            Err(e) => {
+                let error_msg = e.to_string();
+                warn!("failed to remove staging directory {}: {}", name, error_msg);
+                failed.push((name, error_msg));
+            }
+        }
+    }
+
+    Ok((removed, failed))
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{assert_matches, os::unix, thread, time::Duration};
+    use std::{assert_matches, os::unix, thread};
 
     use camino_tempfile::tempdir;
     use camino_tempfile_ext::prelude::*;
@@ -511,10 +1523,87 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn replace_release_swaps_existing_directory() {
+        let root = tempdir().unwrap();
+        let tag = "v1.2.3";
+
+        let src_dir = root.child("staging").child(tag);
+        src_dir.create_dir_all().unwrap();
+        src_dir.child("new.txt").write_str("new").unwrap();
+
+        let releases_dir = root.child("releases");
+        let existing = releases_dir.child(tag);
+        existing.create_dir_all().unwrap();
+        existing.child("old.txt").write_str("old").unwrap();
+
+        let result = replace_release(&src_dir, &releases_dir, tag).unwrap();
+
+        assert_eq!(result, releases_dir.join(tag));
+        assert!(result.join("new.txt").exists());
+        assert!(!result.join("old.txt").exists());
+        assert!(!src_dir.exists());
+    }
+
+    #[test]
+    fn replace_release_moves_when_target_missing() {
+        let root = tempdir().unwrap();
+        let tag = "v1.2.3";
+
+        let src_dir = root.child("staging").child(tag);
+        src_dir.create_dir_all().unwrap();
+        src_dir.child("file.txt").write_str("content").unwrap();
+
+        let releases_dir = root.child("releases");
+        releases_dir.create_dir_all().unwrap();
+
+        let result = replace_release(&src_dir, &releases_dir, tag).unwrap();
+
+        assert!(result.join("file.txt").exists());
+        assert!(!src_dir.exists());
+    }
+
+    #[test]
+    fn discard_release_removes_release_directory() {
+        let root = tempdir().unwrap();
+        let releases_dir = root.child("releases");
+        let release = releases_dir.child("v1.2.3");
+        release.create_dir_all().unwrap();
+        release.child("binary").write_str("data").unwrap();
+
+        discard_release(&releases_dir, "v1.2.3").unwrap();
+
+        assert!(!release.exists());
+        assert!(releases_dir.exists());
+    }
+
+    #[test]
+    fn discard_release_ignores_missing_directory() {
+        let root = tempdir().unwrap();
+        let releases_dir = root.child("releases");
+        releases_dir.create_dir_all().unwrap();
+
+        let result = discard_release(&releases_dir, "v1.2.3");
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn write_json_atomic_replaces_file_and_leaves_no_temp_files() {
+        let root = tempdir().unwrap();
+        let path = root.path().join("myapp").join("pin.json");
+
+        write_json_atomic::<_, Box<dyn std::error::Error>>(&path, &["v1.0.0"]).unwrap();
+        write_json_atomic::<_, Box<dyn std::error::Error>>(&path, &["v1.1.0"]).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[\n  \"v1.1.0\"\n]\n");
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+    }
+
     #[test]
     fn discover_executables_empty_directory() {
         let root = tempdir().unwrap();
-        let result = discover_executables(root.path()).unwrap();
+        let result = discover_executables(root.path(), LinkFilter::All).unwrap();
         assert!(result.is_empty());
     }
 
@@ -526,7 +1615,7 @@ mod tests {
         create_executable(root.child("exe2"), "#!/bin/sh");
         fs::write(root.child("regular.txt"), "not executable").unwrap();
 
-        let result = discover_executables(root.path()).unwrap();
+        let result = discover_executables(root.path(), LinkFilter::All).unwrap();
 
         assert_eq!(result.len(), 2);
         assert!(result.contains(&Utf8PathBuf::from("exe1")));
@@ -544,7 +1633,7 @@ mod tests {
         create_executable(root.child("bin/helper"), "#!/bin/sh");
         create_executable(root.child("tools/admin/cli"), "#!/bin/sh");
 
-        let result = discover_executables(root.path()).unwrap();
+        let result = discover_executables(root.path(), LinkFilter::All).unwrap();
 
         assert_eq!(result.len(), 3);
         assert!(result.contains(&Utf8PathBuf::from("main")));
@@ -560,7 +1649,7 @@ mod tests {
         fs::write(root.child("readme.txt"), "documentation").unwrap();
         fs::write(root.child("data.json"), "{}").unwrap();
 
-        let result = discover_executables(root.path()).unwrap();
+        let result = discover_executables(root.path(), LinkFilter::All).unwrap();
 
         assert_eq!(result.len(), 1);
         assert!(result.contains(&Utf8PathBuf::from("exe")));
@@ -573,7 +1662,7 @@ mod tests {
         root.child("subdir").create_dir_all().unwrap();
         create_executable(root.child("subdir/exe"), "#!/bin/sh");
 
-        let result = discover_executables(root.path()).unwrap();
+        let result = discover_executables(root.path(), LinkFilter::All).unwrap();
 
         assert_eq!(result.len(), 1);
         let path = &result[0];
@@ -594,7 +1683,7 @@ mod tests {
         let bin_dir = root.child("bin");
         bin_dir.create_dir_all().unwrap();
 
-        link_binaries(&tag_dir, &bin_dir).unwrap();
+        link_binaries(&tag_dir, &bin_dir, MissingBinary::Remove, LinkFilter::All).unwrap();
 
         let symlink = bin_dir.child("exe1");
         assert!(symlink.exists());
@@ -617,7 +1706,7 @@ mod tests {
         let bin_dir = root.child("bin");
         bin_dir.create_dir_all().unwrap();
 
-        link_binaries(&tag_dir, &bin_dir).unwrap();
+        link_binaries(&tag_dir, &bin_dir, MissingBinary::Remove, LinkFilter::All).unwrap();
 
         let symlink = bin_dir.child("cli");
         assert!(symlink.exists());
@@ -647,13 +1736,13 @@ mod tests {
         let bin_dir = root.child("bin");
         bin_dir.create_dir_all().unwrap();
 
-        link_binaries(&old_tag, &bin_dir).unwrap();
+        link_binaries(&old_tag, &bin_dir, MissingBinary::Remove, LinkFilter::All).unwrap();
 
         let symlink = bin_dir.child("exe");
         let old_target = fs::read_link(&symlink).unwrap();
         assert_eq!(old_target.to_str().unwrap(), "../releases/v1.0.0/exe");
 
-        link_binaries(&new_tag, &bin_dir).unwrap();
+        link_binaries(&new_tag, &bin_dir, MissingBinary::Remove, LinkFilter::All).unwrap();
 
         let new_target = fs::read_link(&symlink).unwrap();
         assert_eq!(new_target.to_str().unwrap(), "../releases/v2.0.0/exe");
@@ -674,7 +1763,7 @@ mod tests {
         let bin_dir = root.child("bin");
         bin_dir.create_dir_all().unwrap();
 
-        link_binaries(&tag_dir, &bin_dir).unwrap();
+        link_binaries(&tag_dir, &bin_dir, MissingBinary::Remove, LinkFilter::All).unwrap();
 
         assert!(bin_dir.child("exe1").is_symlink());
         assert!(bin_dir.child("exe2").is_symlink());
@@ -704,7 +1793,7 @@ mod tests {
         let bin_dir = root.child("bin");
         bin_dir.create_dir_all().unwrap();
 
-        link_binaries(&tag_dir, &bin_dir).unwrap();
+        link_binaries(&tag_dir, &bin_dir, MissingBinary::Remove, LinkFilter::All).unwrap();
 
         let symlink = bin_dir.child("cli");
         assert!(symlink.exists());
@@ -713,6 +1802,42 @@ mod tests {
         assert!(target.to_str().unwrap().contains("tools/cli"));
     }
 
+    #[test]
+    fn list_releases_newest_first() {
+        let temp = tempdir().unwrap();
+        let releases = temp.child("releases");
+
+        for tag in ["v1.0.0", "v1.1.0", "v1.2.0"] {
+            releases.child(tag).create_dir_all().unwrap();
+            thread::sleep(Duration::from_millis(10));
+        }
+        releases.child("not-a-release").write_str("file").unwrap();
+
+        let tags = list_releases(&releases)
+            .unwrap()
+            .into_iter()
+            .map(|(tag, _)| tag)
+            .collect::<Vec<_>>();
+        assert_eq!(tags, vec!["v1.2.0", "v1.1.0", "v1.0.0"]);
+    }
+
+    #[test]
+    fn list_releases_missing_directory() {
+        let temp = tempdir().unwrap();
+        assert!(list_releases(temp.child("releases")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn dir_size_sums_nested_files() {
+        let temp = tempdir().unwrap();
+        let release = temp.child("v1.0.0");
+        release.child("bin").create_dir_all().unwrap();
+        release.child("app").write_str("12345").unwrap();
+        release.child("bin").child("tool").write_str("123").unwrap();
+
+        assert_eq!(dir_size(&release).unwrap(), 8);
+    }
+
     #[test]
     fn prune_old_releases_keeps_most_recent() {
         let root = tempdir().unwrap();
@@ -878,6 +2003,233 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn managed_links_only_returns_release_links() {
+        let temp = tempdir().unwrap();
+        let bin_dir = temp.child("bin");
+        bin_dir.create_dir_all().unwrap();
+
+        unix::fs::symlink("../releases/v1.0.0/app", bin_dir.join("app")).unwrap();
+        unix::fs::symlink("/usr/bin/env", bin_dir.join("env")).unwrap();
+        fs::write(bin_dir.join("script"), "#!/bin/sh\n").unwrap();
+
+        let links = managed_links(&bin_dir).unwrap();
+        assert_eq!(links, vec![bin_dir.join("app")]);
+    }
+
+    #[test]
+    fn managed_links_reads_manifest() {
+        let temp = tempdir().unwrap();
+        let bin_dir = temp.child("bin");
+        bin_dir.create_dir_all().unwrap();
+
+        unix::fs::symlink("../releases/v1.0.0/app", bin_dir.join("app")).unwrap();
+        unix::fs::symlink("../releases/v1.0.0/manual", bin_dir.join("manual")).unwrap();
+        unix::fs::symlink("/opt/shared/tool", bin_dir.join("tool")).unwrap();
+        bin_dir
+            .child(LINK_MANIFEST)
+            .write_str("app\ntool\nmissing\n")
+            .unwrap();
+
+        let links = managed_links(&bin_dir).unwrap();
+        assert_eq!(links, vec![bin_dir.join("app"), bin_dir.join("tool")]);
+    }
+
+    #[test]
+    fn links_into_releases_resolves_hand_made_links() {
+        let temp = tempdir().unwrap();
+        let bin_dir = temp.child("bin");
+        bin_dir.create_dir_all().unwrap();
+        let release_dir = temp.child("releases/v1.0.0");
+        release_dir.create_dir_all().unwrap();
+        create_executable(release_dir.join("app"), "#!/bin/sh\n");
+        create_executable(release_dir.join("helper"), "#!/bin/sh\n");
+
+        unix::fs::symlink("../releases/v1.0.0/app", bin_dir.join("app")).unwrap();
+        unix::fs::symlink(release_dir.join("helper"), bin_dir.join("helper")).unwrap();
+        unix::fs::symlink("../releases/v0.9.0/app", bin_dir.join("broken")).unwrap();
+        unix::fs::symlink("/usr/bin/env", bin_dir.join("env")).unwrap();
+
+        let links = links_into_releases(&bin_dir, temp.child("releases")).unwrap();
+        assert_eq!(links, vec![bin_dir.join("app"), bin_dir.join("helper")]);
+
+        adopt_links(&bin_dir, &links).unwrap();
+        assert_eq!(
+            managed_links(&bin_dir).unwrap(),
+            vec![bin_dir.join("app"), bin_dir.join("helper")]
+        );
+    }
+
+    #[test]
+    fn convert_current_layout_moves_release() {
+        let temp = tempdir().unwrap();
+        let app_dir = temp.child("myapp");
+        app_dir.child("1.4.0/bin").create_dir_all().unwrap();
+        app_dir.child("1.3.0").create_dir_all().unwrap();
+        create_executable(app_dir.join("1.4.0/bin/myapp"), "#!/bin/sh\n");
+        unix::fs::symlink("1.4.0", app_dir.join("current")).unwrap();
+
+        let tag = convert_current_layout(&app_dir).unwrap();
+
+        assert_eq!(tag, "1.4.0");
+        assert!(!app_dir.join("1.4.0").exists());
+        assert!(app_dir.join("1.3.0").is_dir());
+        assert_eq!(
+            fs::read_link(app_dir.join("current")).unwrap(),
+            Utf8Path::new("releases/1.4.0")
+        );
+        assert!(app_dir.join("current/bin/myapp").is_file());
+        assert_eq!(
+            fs::read_link(app_dir.join("bin/myapp")).unwrap(),
+            Utf8Path::new("../releases/1.4.0/bin/myapp")
+        );
+
+        let not_a_release = temp.child("other");
+        not_a_release.create_dir_all().unwrap();
+        unix::fs::symlink("/tmp", not_a_release.join("current")).unwrap();
+        assert_matches!(
+            convert_current_layout(&not_a_release),
+            Err(FsOpsError::UnsupportedLayout(_))
+        );
+    }
+
+    #[test]
+    fn convert_flat_layout_keeps_old_paths() {
+        let temp = tempdir().unwrap();
+        let app_dir = temp.child("myapp");
+        app_dir.child("share").create_dir_all().unwrap();
+        create_executable(app_dir.join("myapp"), "#!/bin/sh\n");
+        fs::write(app_dir.join("config.toml"), "port = 80\n").unwrap();
+        fs::write(app_dir.join("share/data.txt"), "data").unwrap();
+
+        convert_flat_layout(&app_dir, "v2.0.0").unwrap();
+
+        let release_dir = app_dir.join("releases/v2.0.0");
+        assert!(release_dir.join("share/data.txt").is_file());
+        assert_eq!(
+            fs::read_link(app_dir.join("bin/myapp")).unwrap(),
+            Utf8Path::new("../releases/v2.0.0/myapp")
+        );
+        assert_eq!(
+            fs::read_link(app_dir.join("myapp")).unwrap(),
+            Utf8Path::new("bin/myapp")
+        );
+        assert_eq!(
+            fs::read_link(app_dir.join("share")).unwrap(),
+            Utf8Path::new("releases/v2.0.0/share")
+        );
+        assert_eq!(
+            fs::read_to_string(app_dir.join("config.toml")).unwrap(),
+            "port = 80\n"
+        );
+        assert_eq!(
+            managed_links(app_dir.join("bin")).unwrap(),
+            vec![app_dir.join("bin/myapp")]
+        );
+
+        assert_matches!(
+            convert_flat_layout(&app_dir, "v2.0.0"),
+            Err(FsOpsError::UnsupportedLayout(_))
+        );
+    }
+
+    #[test]
+    fn convert_flat_layout_replaces_bin_directory() {
+        let temp = tempdir().unwrap();
+        let app_dir = temp.child("myapp");
+        app_dir.child("bin").create_dir_all().unwrap();
+        create_executable(app_dir.join("bin/myapp"), "#!/bin/sh\n");
+
+        convert_flat_layout(&app_dir, "v1.0.0").unwrap();
+
+        assert_eq!(
+            fs::read_link(app_dir.join("bin/myapp")).unwrap(),
+            Utf8Path::new("../releases/v1.0.0/bin/myapp")
+        );
+        assert!(app_dir.join("bin/myapp").is_file());
+        assert_eq!(fs::read_dir(app_dir.join("staging")).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn discover_executables_filters_by_content() {
+        let root = tempdir().unwrap();
+        create_executable(root.child("server"), "\x7fELF\x02\x01\x01");
+        create_executable(root.child("wrapper.sh"), "#!/bin/sh\nexec server\n");
+        create_executable(root.child("README.md"), "# myapp\n");
+        create_executable(root.child("empty"), "");
+
+        let discover = |filter| discover_executables(root.path(), filter).unwrap();
+        assert_eq!(discover(LinkFilter::All).len(), 4);
+        assert_eq!(
+            discover(LinkFilter::Runnable),
+            vec![Utf8PathBuf::from("server"), Utf8PathBuf::from("wrapper.sh")]
+        );
+        assert_eq!(discover(LinkFilter::Elf), vec![Utf8PathBuf::from("server")]);
+        assert_eq!(
+            "runnable".parse::<LinkFilter>().unwrap(),
+            LinkFilter::Runnable
+        );
+        assert!("scripts".parse::<LinkFilter>().is_err());
+    }
+
+    #[test]
+    fn relocate_deep_executables_moves_into_libexec() {
+        let root = tempdir().unwrap();
+        let release = root.child("v1.0.0");
+        let deep = release.child("dist").child("linux-x86_64").child("bin");
+        deep.create_dir_all().unwrap();
+        release.child("tools").create_dir_all().unwrap();
+        create_executable(release.child("myapp"), "#!/bin/sh");
+        create_executable(release.child("tools").child("helper"), "#!/bin/sh");
+        create_executable(deep.child("myapp-server"), "#!/bin/sh\necho server\n");
+        create_executable(deep.child("helper"), "#!/bin/sh");
+
+        let relocated = relocate_deep_executables(&release, 1, LinkFilter::All).unwrap();
+
+        assert_eq!(
+            relocated,
+            vec![
+                Utf8PathBuf::from("dist/linux-x86_64/bin/helper"),
+                Utf8PathBuf::from("dist/linux-x86_64/bin/myapp-server"),
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(release.child("libexec").child("myapp-server")).unwrap(),
+            "#!/bin/sh\necho server\n"
+        );
+        assert_eq!(
+            fs::read_link(deep.child("myapp-server")).unwrap(),
+            std::path::Path::new("../../../libexec/myapp-server")
+        );
+        assert_eq!(
+            fs::read_to_string(deep.child("myapp-server")).unwrap(),
+            "#!/bin/sh\necho server\n"
+        );
+        assert_eq!(
+            discover_executables(&release, LinkFilter::All).unwrap(),
+            vec![
+                Utf8PathBuf::from("libexec/helper"),
+                Utf8PathBuf::from("libexec/myapp-server"),
+                Utf8PathBuf::from("myapp"),
+                Utf8PathBuf::from("tools/helper"),
+            ]
+        );
+        // tools/helper keeps its place because the deeper helper took libexec/helper.
+        assert!(
+            relocate_deep_executables(&release, 0, LinkFilter::All)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(release.child("tools").child("helper").is_file());
+    }
+
+    #[test]
+    fn managed_links_missing_bin_dir() {
+        let temp = tempdir().unwrap();
+        let links = managed_links(temp.child("bin")).unwrap();
+        assert!(links.is_empty());
+    }
+
     #[test]
     fn link_binaries_removes_stale_symlinks() {
         let root = tempdir().unwrap();
@@ -895,16 +2247,246 @@ mod tests {
         let bin_dir = root.child("bin");
         bin_dir.create_dir_all().unwrap();
 
-        link_binaries(&old_tag, &bin_dir).unwrap();
+        link_binaries(&old_tag, &bin_dir, MissingBinary::Remove, LinkFilter::All).unwrap();
         assert!(bin_dir.child("exe1").exists());
         assert!(bin_dir.child("exe2").exists());
 
-        link_binaries(&new_tag, &bin_dir).unwrap();
+        link_binaries(&new_tag, &bin_dir, MissingBinary::Remove, LinkFilter::All).unwrap();
 
         assert!(bin_dir.child("exe1").exists());
         assert!(!bin_dir.child("exe2").exists());
     }
 
+    #[test]
+    fn link_binaries_keeps_missing_commands() {
+        let root = tempdir().unwrap();
+
+        let releases = root.child("releases");
+        let old_tag = releases.child("v1.0.0");
+        old_tag.create_dir_all().unwrap();
+        create_executable(old_tag.child("exe1"), "#!/bin/sh");
+        create_executable(old_tag.child("exe2"), "#!/bin/sh");
+
+        let new_tag = releases.child("v2.0.0");
+        new_tag.create_dir_all().unwrap();
+        create_executable(new_tag.child("exe1"), "#!/bin/sh\nnew");
+
+        let bin_dir = root.child("bin");
+        bin_dir.create_dir_all().unwrap();
+
+        link_binaries(&old_tag, &bin_dir, MissingBinary::Remove, LinkFilter::All).unwrap();
+        link_binaries(&new_tag, &bin_dir, MissingBinary::Keep, LinkFilter::All).unwrap();
+
+        assert_eq!(
+            fs::read_link(bin_dir.child("exe1")).unwrap(),
+            Utf8Path::new("../releases/v2.0.0/exe1")
+        );
+        assert_eq!(
+            fs::read_link(bin_dir.child("exe2")).unwrap(),
+            Utf8Path::new("../releases/v1.0.0/exe2")
+        );
+        assert_eq!(
+            managed_links(&bin_dir).unwrap(),
+            vec![bin_dir.join("exe1"), bin_dir.join("exe2")]
+        );
+        assert_eq!(kept_links(&bin_dir).unwrap(), vec![bin_dir.join("exe2")]);
+    }
+
+    #[test]
+    fn check_required_binaries_rejects_commands_of_other_releases() {
+        let root = tempdir().unwrap();
+
+        let releases = root.child("releases");
+        let old_tag = releases.child("v1.0.0");
+        old_tag.create_dir_all().unwrap();
+        create_executable(old_tag.child("exe1"), "#!/bin/sh");
+        create_executable(old_tag.child("exe2"), "#!/bin/sh");
+
+        let new_tag = releases.child("v2.0.0");
+        new_tag.create_dir_all().unwrap();
+        create_executable(new_tag.child("exe1"), "#!/bin/sh\nnew");
+
+        let bin_dir = root.child("bin");
+        bin_dir.create_dir_all().unwrap();
+
+        link_binaries(&old_tag, &bin_dir, MissingBinary::Remove, LinkFilter::All).unwrap();
+        link_binaries(&new_tag, &bin_dir, MissingBinary::Keep, LinkFilter::All).unwrap();
+
+        check_required_binaries(&new_tag, &bin_dir, &["exe1".to_string()]).unwrap();
+        let required = ["exe1", "exe2", "exe3"].map(str::to_string);
+        assert_matches!(
+            check_required_binaries(&new_tag, &bin_dir, &required),
+            Err(FsOpsError::RequiredBinariesMissing(names)) if names == ["exe2", "exe3"]
+        );
+    }
+
+    #[test]
+    fn link_binaries_fails_on_missing_commands_without_changes() {
+        let root = tempdir().unwrap();
+
+        let releases = root.child("releases");
+        let old_tag = releases.child("v1.0.0");
+        old_tag.create_dir_all().unwrap();
+        create_executable(old_tag.child("exe1"), "#!/bin/sh");
+        create_executable(old_tag.child("exe2"), "#!/bin/sh");
+
+        let new_tag = releases.child("v2.0.0");
+        new_tag.create_dir_all().unwrap();
+        create_executable(new_tag.child("exe1"), "#!/bin/sh\nnew");
+
+        let bin_dir = root.child("bin");
+        bin_dir.create_dir_all().unwrap();
+
+        link_binaries(&old_tag, &bin_dir, MissingBinary::Remove, LinkFilter::All).unwrap();
+        let result = link_binaries(&new_tag, &bin_dir, MissingBinary::Fail, LinkFilter::All);
+
+        assert_matches!(result, Err(FsOpsError::MissingBinaries(names)) if names == ["exe2"]);
+        assert_eq!(
+            fs::read_link(bin_dir.child("exe1")).unwrap(),
+            Utf8Path::new("../releases/v1.0.0/exe1")
+        );
+        assert!(bin_dir.child("exe2").exists());
+    }
+
+    #[test]
+    fn missing_binary_parses_policies() {
+        assert_eq!(
+            "keep".parse::<MissingBinary>().unwrap(),
+            MissingBinary::Keep
+        );
+        assert_eq!(
+            "remove".parse::<MissingBinary>().unwrap(),
+            MissingBinary::Remove
+        );
+        assert_eq!(
+            "fail".parse::<MissingBinary>().unwrap(),
+            MissingBinary::Fail
+        );
+        assert_matches!(
+            "skip".parse::<MissingBinary>(),
+            Err(FsOpsError::UnknownMissingBinary(_))
+        );
+    }
+
+    #[test]
+    fn releases_to_prune_skips_releases_with_kept_links() {
+        let root = tempdir().unwrap();
+        let releases_dir = root.child("releases");
+
+        for tag in ["v1.0.0", "v1.0.1", "v1.0.2"] {
+            let release = releases_dir.child(tag);
+            release.create_dir_all().unwrap();
+            create_executable(release.child("exe1"), "#!/bin/sh");
+            thread::sleep(Duration::from_millis(10));
+        }
+        create_executable(releases_dir.child("v1.0.0").child("exe2"), "#!/bin/sh");
+
+        let bin_dir = root.child("bin");
+        bin_dir.create_dir_all().unwrap();
+        link_binaries(
+            releases_dir.child("v1.0.0"),
+            &bin_dir,
+            MissingBinary::Remove,
+            LinkFilter::All,
+        )
+        .unwrap();
+        link_binaries(
+            releases_dir.child("v1.0.2"),
+            &bin_dir,
+            MissingBinary::Keep,
+            LinkFilter::All,
+        )
+        .unwrap();
+
+        assert_eq!(
+            releases_to_prune(&releases_dir, "v1.0.2", 1).unwrap(),
+            vec!["v1.0.1".to_string()]
+        );
+    }
+
+    #[test]
+    fn releases_to_prune_after_counts_incoming_release() {
+        let root = tempdir().unwrap();
+        let releases_dir = root.child("releases");
+
+        for tag in ["v1.0.0", "v1.0.1", "v1.0.2"] {
+            let release = releases_dir.child(tag);
+            release.create_dir_all().unwrap();
+            release.child("app").write_str(&"x".repeat(100)).unwrap();
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(
+            releases_to_prune_after(&releases_dir, "v1.1.0", 0, 2).unwrap(),
+            vec!["v1.0.1".to_string(), "v1.0.0".to_string()]
+        );
+        assert_eq!(
+            releases_to_prune_after(&releases_dir, "v1.0.1", 0, 2).unwrap(),
+            vec!["v1.0.0".to_string()]
+        );
+        let retention = Retention {
+            count: 4,
+            max_size: Some(300),
+            pinned: None,
+        };
+        assert_eq!(
+            releases_to_prune_after(&releases_dir, "v1.1.0", 150, retention).unwrap(),
+            vec!["v1.0.1".to_string(), "v1.0.0".to_string()]
+        );
+    }
+
+    #[test]
+    fn releases_to_prune_trims_oldest_to_size_budget() {
+        let root = tempdir().unwrap();
+        let releases_dir = root.child("releases");
+
+        for tag in ["v1.0.0", "v1.0.1", "v1.0.2", "v1.0.3"] {
+            let release = releases_dir.child(tag);
+            release.create_dir_all().unwrap();
+            release.child("app").write_str(&"x".repeat(100)).unwrap();
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let retention = |max_size, pinned: Option<&str>| Retention {
+            count: 3,
+            max_size: Some(max_size),
+            pinned: pinned.map(str::to_string),
+        };
+        assert_eq!(
+            releases_to_prune(&releases_dir, "v1.0.3", retention(300, None)).unwrap(),
+            vec!["v1.0.0".to_string()]
+        );
+        assert_eq!(
+            releases_to_prune(&releases_dir, "v1.0.3", retention(250, None)).unwrap(),
+            vec!["v1.0.1".to_string(), "v1.0.0".to_string()]
+        );
+        assert_eq!(
+            releases_to_prune(&releases_dir, "v1.0.3", retention(150, Some("v1.0.1"))).unwrap(),
+            vec!["v1.0.2".to_string(), "v1.0.0".to_string()]
+        );
+        assert_eq!(
+            releases_to_prune(&releases_dir, "v1.0.3", retention(0, None)).unwrap(),
+            vec![
+                "v1.0.2".to_string(),
+                "v1.0.1".to_string(),
+                "v1.0.0".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_byte_sizes() {
+        assert_eq!("512".parse::<ByteSize>().unwrap(), ByteSize(512));
+        assert_eq!("500MB".parse::<ByteSize>().unwrap(), ByteSize(500_000_000));
+        assert_eq!("20GiB".parse::<ByteSize>().unwrap(), ByteSize(20 << 30));
+        assert_eq!("1.5 g".parse::<ByteSize>().unwrap(), ByteSize(3 << 29));
+        assert_matches!(
+            "20 gallons".parse::<ByteSize>(),
+            Err(FsOpsError::InvalidSize(_))
+        );
+        assert!("GiB".parse::<ByteSize>().is_err());
+    }
+
     #[test]
     fn link_binaries_preserves_non_managed_links() {
         let root = tempdir().unwrap();
@@ -922,9 +2504,92 @@ mod tests {
 
         unix::fs::symlink(&other_target, bin_dir.child("other")).unwrap();
 
-        link_binaries(&tag_dir, &bin_dir).unwrap();
+        link_binaries(&tag_dir, &bin_dir, MissingBinary::Remove, LinkFilter::All).unwrap();
 
         assert!(bin_dir.child("myapp").exists());
         assert!(bin_dir.child("other").symlink_metadata().is_ok());
     }
+
+    #[test]
+    fn link_binaries_writes_manifest() {
+        let root = tempdir().unwrap();
+
+        let tag_dir = root.child("releases").child("v1.0.0");
+        tag_dir.create_dir_all().unwrap();
+        create_executable(tag_dir.child("b"), "#!/bin/sh");
+        create_executable(tag_dir.child("a"), "#!/bin/sh");
+
+        let bin_dir = root.child("bin");
+        bin_dir.create_dir_all().unwrap();
+
+        link_binaries(&tag_dir, &bin_dir, MissingBinary::Remove, LinkFilter::All).unwrap();
+
+        let manifest = fs::read_to_string(bin_dir.child(LINK_MANIFEST)).unwrap();
+        assert_eq!(manifest, "a\nb\n");
+    }
+
+    #[test]
+    fn link_binaries_preserves_unlisted_release_links() {
+        let root = tempdir().unwrap();
+
+        let releases = root.child("releases");
+        let old_tag = releases.child("v1.0.0");
+        old_tag.create_dir_all().unwrap();
+        create_executable(old_tag.child("myapp"), "#!/bin/sh");
+        create_executable(old_tag.child("extra"), "#!/bin/sh");
+
+        let new_tag = releases.child("v2.0.0");
+        new_tag.create_dir_all().unwrap();
+        create_executable(new_tag.child("myapp"), "#!/bin/sh\nnew");
+
+        let bin_dir = root.child("bin");
+        bin_dir.create_dir_all().unwrap();
+
+        link_binaries(&old_tag, &bin_dir, MissingBinary::Remove, LinkFilter::All).unwrap();
+        fs::remove_file(bin_dir.child("extra")).unwrap();
+        unix::fs::symlink("../releases/v1.0.0/extra", bin_dir.child("pinned-extra")).unwrap();
+
+        link_binaries(&new_tag, &bin_dir, MissingBinary::Remove, LinkFilter::All).unwrap();
+
+        assert!(bin_dir.child("myapp").exists());
+        assert!(bin_dir.child("pinned-extra").symlink_metadata().is_ok());
+    }
+
+    #[test]
+    fn remove_stale_staging_keeps_recent_directories() {
+        let root = tempdir().unwrap();
+        let staging_dir = root.child("staging");
+        let old = staging_dir.join("v1.0.0.abc123");
+        let recent = staging_dir.join("v1.1.0.def456");
+        fs::create_dir_all(&old).unwrap();
+        fs::create_dir_all(&recent).unwrap();
+        fs::write(old.join("partial"), "data").unwrap();
+
+        let two_days_ago = SystemTime::now() - Duration::from_secs(2 * 24 * 60 * 60);
+        File::open(&old)
+            .unwrap()
+            .set_modified(two_days_ago)
+            .unwrap();
+
+        let day = Duration::from_secs(24 * 60 * 60);
+        assert_eq!(
+            stale_staging(&staging_dir, day).unwrap(),
+            vec!["v1.0.0.abc123"]
+        );
+
+        let (removed, failed) = remove_stale_staging(&staging_dir, day).unwrap();
+        assert_eq!(removed, vec!["v1.0.0.abc123"]);
+        assert!(failed.is_empty());
+        assert!(!old.exists());
+        assert!(recent.exists());
+    }
+
+    #[test]
+    fn remove_stale_staging_missing_directory() {
+        let root = tempdir().unwrap();
+        let (removed, failed) =
+            remove_stale_staging(root.path().join("staging"), Duration::ZERO).unwrap();
+        assert!(removed.is_empty());
+        assert!(failed.is_empty());
+    }
 }
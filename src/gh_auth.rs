@@ -0,0 +1,168 @@
+use std::{
+    env, fs, io,
+    process::{Command, Stdio},
+};
+
+use camino::Utf8PathBuf;
+use reqwest::Url;
+use thiserror::Error;
+use tracing::debug;
+
+#[derive(Debug, Error)]
+pub enum GhAuthError {
+    #[error("gh CLI has no token for {hostname}; run `gh auth login --hostname {hostname}`")]
+    NotLoggedIn { hostname: String },
+    #[error("failed to read gh CLI hosts file {path}: {source}")]
+    Io {
+        path: Utf8PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, GhAuthError>;
+
+/// The hostname `gh` files credentials under for a GitHub API base URL:
+/// `github.com` for the public API, otherwise the API URL's host, as for GitHub
+/// Enterprise Server.
+#[must_use]
+pub fn hostname(api_host: &str) -> String {
+    let host = Url::parse(api_host)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| api_host.to_string());
+    match host.as_str() {
+        "api.github.com" => "github.com".to_string(),
+        _ => host,
+    }
+}
+
+/// The `gh` CLI's `hosts.yml`: under `$GH_CONFIG_DIR`, `$XDG_CONFIG_HOME/gh`, or
+/// `~/.config/gh`, checked in that order.
+#[must_use]
+pub fn hosts_file() -> Option<Utf8PathBuf> {
+    let var = |name| env::var(name).ok().filter(|value| !value.is_empty());
+    let config_dir = var("GH_CONFIG_DIR")
+        .map(Utf8PathBuf::from)
+        .or_else(|| var("XDG_CONFIG_HOME").map(|dir| Utf8PathBuf::from(dir).join("gh")))
+        .or_else(|| var("HOME").map(|home| Utf8PathBuf::from(home).join(".config/gh")))?;
+    Some(config_dir.join("hosts.yml"))
+}
+
+/// Returns the token the `gh` CLI uses for the GitHub API at `api_host`.
+///
+/// Asks `gh auth token`, which also finds tokens kept in the system keyring, and
+/// falls back to the `oauth_token` in `hosts.yml` when `gh` is not on `PATH` or
+/// has no token.
+///
+/// # Errors
+///
+/// Returns `GhAuthError::NotLoggedIn` if neither source has a token for the host,
+/// or `GhAuthError::Io` if the hosts file exists but cannot be read.
+pub fn token(api_host: &str) -> Result<String> {
+    let hostname = hostname(api_host);
+    if let Some(token) = token_from_command(&hostname) {
+        return Ok(token);
+    }
+
+    if let Some(path) = hosts_file() {
+        match fs::read_to_string(&path) {
+            Ok(contents) => {
+                if let Some(token) = token_from_hosts(&contents, &hostname) {
+                    return Ok(token);
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(source) => return Err(GhAuthError::Io { path, source }),
+        }
+    }
+
+    Err(GhAuthError::NotLoggedIn { hostname })
+}
+
+fn token_from_command(hostname: &str) -> Option<String> {
+    let output = match Command::new("gh")
+        .args(["auth", "token", "--hostname", hostname])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) => {
+            debug!("Could not run gh auth token: {e}");
+            return None;
+        }
+    };
+    if !output.status.success() {
+        debug!("gh auth token exited with {}", output.status);
+        return None;
+    }
+    let token = String::from_utf8(output.stdout).ok()?;
+    let token = token.trim();
+    (!token.is_empty()).then(|| token.to_string())
+}
+
+/// Finds `oauth_token` directly under the `hostname:` entry of a `hosts.yml`.
+fn token_from_hosts(contents: &str, hostname: &str) -> Option<String> {
+    let mut lines = contents
+        .lines()
+        .skip_while(|line| {
+            line.trim_end().trim_end_matches(':') != hostname || line.starts_with(' ')
+        })
+        .skip(1)
+        .take_while(|line| line.is_empty() || line.starts_with([' ', '\t']));
+
+    // Only the first indentation level belongs to the host; deeper entries such as
+    // `users:` hold per-account settings.
+    let first = lines.find(|line| !line.trim().is_empty())?;
+    let indent = first.len() - first.trim_start().len();
+    std::iter::once(first)
+        .chain(lines)
+        .filter(|line| line.len() - line.trim_start().len() == indent)
+        .find_map(|line| line.trim().strip_prefix("oauth_token:"))
+        .map(|value| value.trim().trim_matches(['"', '\'']).to_string())
+        .filter(|token| !token.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hostname() {
+        assert_eq!(hostname("https://api.github.com"), "github.com");
+        assert_eq!(
+            hostname("https://ghe.example.com/api/v3"),
+            "ghe.example.com"
+        );
+        assert_eq!(hostname("ghe.example.com"), "ghe.example.com");
+    }
+
+    #[test]
+    fn test_token_from_hosts() {
+        let hosts = "\
+github.com:
+    users:
+        octocat:
+            oauth_token: gho_nested
+    git_protocol: https
+    oauth_token: gho_public
+    user: octocat
+ghe.example.com:
+    oauth_token: \"gho_enterprise\"
+keyring.example.com:
+    user: octocat
+";
+
+        assert_eq!(
+            token_from_hosts(hosts, "github.com").as_deref(),
+            Some("gho_public")
+        );
+        assert_eq!(
+            token_from_hosts(hosts, "ghe.example.com").as_deref(),
+            Some("gho_enterprise")
+        );
+        assert_eq!(token_from_hosts(hosts, "keyring.example.com"), None);
+        assert_eq!(token_from_hosts(hosts, "gitlab.com"), None);
+    }
+}
@@ -1,17 +1,87 @@
-use std::cmp::Reverse;
+use std::{fmt, str::FromStr, time::Duration};
 
-use anyhow::Result;
-use jiff::Timestamp;
+use jiff::{
+    SignedDuration, Span, SpanRelativeTo, Timestamp, civil::DateTime, fmt::rfc2822, tz::TimeZone,
+};
 use regex::Regex;
 use reqwest::{
     StatusCode,
-    header::{ACCEPT, AUTHORIZATION, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    header::{CACHE_CONTROL, HeaderMap, RETRY_AFTER},
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize, Serializer};
+use thiserror::Error;
+use tracing::warn;
 
-use crate::{DEFAULT_GITHUB_HOST, DEFAULT_TIMEOUT};
+use crate::pattern::Platform;
 
-#[derive(Debug, Clone, Deserialize)]
+#[cfg(feature = "github")]
+mod api;
+
+#[cfg(feature = "github")]
+pub use api::{Probe, fetch_latest, fetch_tag, list_releases, probe};
+
+/// REST API version requested with every call (`X-GitHub-Api-Version`).
+pub const API_VERSION: &str = "2022-11-28";
+
+/// Largest prefix of an unparseable response body saved for debugging.
+pub const MAX_DEBUG_BODY_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum GithubError {
+    #[error("GitHub API returned 404 Not Found for {url}")]
+    NotFound { url: String },
+
+    #[error("GitHub API rate limited until {reset}")]
+    RateLimited { reset: Timestamp },
+
+    #[error(
+        "GitHub API returned {status} for {url}; the token is missing, invalid, or lacks access"
+    )]
+    Unauthorized { status: StatusCode, url: String },
+
+    #[error("cannot parse GitHub response from {url}: {message}")]
+    Parse { url: String, message: String },
+
+    #[error("HTTP request error: {0}")]
+    Network(#[from] reqwest::Error),
+
+    #[error("No releases found")]
+    NoReleases,
+
+    #[error("No stable releases found (only prereleases)")]
+    NoStableRelease,
+
+    #[error("No releases or tags found")]
+    NoTags,
+
+    #[error("No release tag matches '{pattern}'")]
+    NoMatchingRelease { pattern: String },
+
+    #[error("No release version satisfies '{constraint}'")]
+    NoMatchingVersion { constraint: String },
+
+    #[error("No release was published at least {min_age} ago")]
+    NoReleaseOldEnough { min_age: ReleaseAge },
+
+    #[error("invalid release age '{0}'; expected a duration such as 48h or 7d")]
+    InvalidReleaseAge(String),
+
+    #[error("No release found on the {channel} channel")]
+    NoChannelRelease { channel: Channel },
+
+    #[error("invalid release channel '{0}'; expected stable, rc, beta, or prerelease")]
+    InvalidChannel(String),
+
+    #[error("{tag} has no generated source archive; @source only works with GitHub releases")]
+    NoSourceArchive { tag: String },
+
+    #[error("No asset of {tag} is an archive for {platform}; pass --pattern to choose one")]
+    NoPlatformAsset { tag: String, platform: Platform },
+}
+
+pub type Result<T> = std::result::Result<T, GithubError>;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Release {
     pub tag_name: String,
     pub assets: Vec<Asset>,
@@ -20,472 +90,740 @@ pub struct Release {
     pub draft: bool,
     #[serde(default)]
     pub created_at: Option<Timestamp>,
+    #[serde(default)]
+    pub published_at: Option<Timestamp>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub html_url: Option<String>,
+    /// The source archives GitHub generates for the release's tag.
+    #[serde(default)]
+    pub tarball_url: Option<String>,
+    #[serde(default)]
+    pub zipball_url: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Asset {
     pub name: String,
     pub url: String,
     pub browser_download_url: String,
     pub size: u64,
+    #[serde(default)]
+    pub digest: Option<String>,
+    #[serde(default)]
+    pub uploader: Option<Uploader>,
 }
 
-#[derive(Debug, Clone, Default)]
-pub struct Validators {
-    pub etag: Option<String>,
-    pub last_modified: Option<String>,
+/// The account that uploaded an asset.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Uploader {
+    pub login: String,
 }
 
-#[derive(Debug)]
-pub struct ValidatorsOut {
-    pub etag: Option<String>,
-    pub last_modified: Option<String>,
+/// A git tag, as listed by `/repos/{repo}/tags`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Tag {
+    pub name: String,
+    pub tarball_url: String,
+    #[serde(default)]
+    pub zipball_url: Option<String>,
 }
 
-#[derive(Debug)]
-pub struct FetchResult {
-    pub release: Option<Release>,
-    pub validators: ValidatorsOut,
-    pub was_modified: bool,
+impl Release {
+    /// A release synthesized from a bare tag, whose only asset is the source tarball
+    /// GitHub generates for it (see [`SourceArchive::asset`]).
+    ///
+    /// The tarball has no checksum file, and GitHub does not promise it stays
+    /// byte-identical, so installing it needs `--skip-verification`.
+    #[must_use]
+    pub fn from_tag(repo: &str, tag: Tag) -> Self {
+        let mut release = Self {
+            tag_name: tag.name,
+            assets: Vec::new(),
+            prerelease: false,
+            draft: false,
+            created_at: None,
+            published_at: None,
+            body: None,
+            html_url: None,
+            tarball_url: Some(tag.tarball_url),
+            zipball_url: tag.zipball_url,
+        };
+        release
+            .assets
+            .extend(SourceArchive::Tarball.asset(repo, &release));
+        release
+    }
+
+    /// When the release was published, or created if the source records no
+    /// publish time.
+    #[must_use]
+    pub fn published(&self) -> Option<Timestamp> {
+        self.published_at.or(self.created_at)
+    }
 }
 
-/// Fetches the latest release from GitHub.
-///
-/// Uses conditional requests via `ETag` and `Last-Modified` headers when validators
-/// are provided. Returns an optional release (None on 304), updated validators, and
-/// whether content changed.
-///
-/// # Errors
-///
-/// Returns an error if:
-/// - Network request fails
-/// - Response cannot be parsed as JSON
-/// - No releases are found when `allow_prerelease` is true
-#[bon::builder(derive(IntoFuture(Box)))]
-pub async fn fetch_latest(
-    repo: &str,
-    token: Option<&str>,
-    #[builder(default = crate::build_http_client(DEFAULT_TIMEOUT).unwrap())]
-    client: reqwest::Client,
-    #[builder(default = DEFAULT_GITHUB_HOST)] host: &str,
-    #[builder(default = false)] allow_prerelease: bool,
-    #[builder(default)] validators: Validators,
-) -> Result<FetchResult> {
-    let url = if allow_prerelease {
-        format!("{host}/repos/{repo}/releases")
-    } else {
-        format!("{host}/repos/{repo}/releases/latest")
-    };
+/// How long a release must have been public before it is installed, written as a
+/// duration such as `48h`, `7d`, or `1w 12h` (days are 24 hours).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ReleaseAge(pub SignedDuration);
 
-    let mut request = client
-        .get(&url)
-        .header(ACCEPT, "application/vnd.github+json");
+impl Serialize for ReleaseAge {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
 
-    if let Some(token) = token {
-        request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+impl ReleaseAge {
+    /// Whether `release` was published at least this long before `now`. A release
+    /// without a publish time never is, since its age cannot be told.
+    #[must_use]
+    pub fn allows(self, release: &Release, now: Timestamp) -> bool {
+        release
+            .published()
+            .is_some_and(|published| now.duration_since(published) >= self.0)
     }
 
-    if let Some(etag) = &validators.etag {
-        request = request.header(IF_NONE_MATCH, etag);
+    /// Drops the releases published less than this long ago.
+    ///
+    /// # Errors
+    ///
+    /// Returns `GithubError::NoReleaseOldEnough` if that drops every release.
+    pub fn retain(self, releases: &mut Vec<Release>) -> Result<()> {
+        if releases.is_empty() {
+            return Ok(());
+        }
+        let now = Timestamp::now();
+        releases.retain(|release| self.allows(release, now));
+        if releases.is_empty() {
+            return Err(GithubError::NoReleaseOldEnough { min_age: self });
+        }
+        Ok(())
     }
-    if let Some(last_modified) = &validators.last_modified {
-        request = request.header(IF_MODIFIED_SINCE, last_modified);
+}
+
+impl FromStr for ReleaseAge {
+    type Err = GithubError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || GithubError::InvalidReleaseAge(s.to_string());
+        let span: Span = s.trim().parse().map_err(|_| invalid())?;
+        let duration = span
+            .to_duration(SpanRelativeTo::days_are_24_hours())
+            .map_err(|_| invalid())?;
+        if duration.is_negative() {
+            return Err(invalid());
+        }
+        Ok(Self(duration))
     }
+}
 
-    let response = request.send().await?;
-    let status = response.status();
-    let headers = response.headers();
-    let validators_out = ValidatorsOut {
-        etag: headers
-            .get(ETAG)
-            .and_then(|h| h.to_str().ok())
-            .map(String::from),
-        last_modified: headers
-            .get(LAST_MODIFIED)
-            .and_then(|h| h.to_str().ok())
-            .map(String::from),
-    };
+impl TryFrom<String> for ReleaseAge {
+    type Error = GithubError;
 
-    if status == StatusCode::NOT_MODIFIED {
-        return Ok(FetchResult {
-            release: None,
-            validators: validators_out,
-            was_modified: false,
-        });
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
     }
+}
 
-    let response = response.error_for_status()?;
-
-    let release = if allow_prerelease {
-        let mut releases = response.json::<Vec<Release>>().await?;
-        releases.retain(|r| !r.draft);
-        releases.sort_by_key(|r| Reverse(r.created_at));
-        releases
-            .into_iter()
-            .next()
-            .ok_or_else(|| anyhow::anyhow!("No releases found"))?
-    } else {
-        response.json::<Release>().await?
-    };
+impl fmt::Display for ReleaseAge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const DAY: i64 = 24 * 60 * 60;
+        let secs = self.0.as_secs();
+        if secs > 0 && secs % DAY == 0 && self.0.subsec_nanos() == 0 {
+            write!(f, "{}d", secs / DAY)
+        } else {
+            write!(f, "{:#}", self.0)
+        }
+    }
+}
 
-    Ok(FetchResult {
-        release: Some(release),
-        validators: validators_out,
-        was_modified: true,
-    })
+/// The release channel an app follows. Each channel also takes the releases of
+/// the more conservative ones, so `beta` installs a release candidate or a
+/// stable release when that is the newest.
+///
+/// A release's channel comes from the suffix after the version in its tag
+/// (`v1.4.0-rc.1`, `cli-v2.0.0-beta2`), or, without one, from its prerelease
+/// flag.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Channel {
+    /// Releases without a prerelease flag or suffix.
+    #[default]
+    Stable,
+    /// Adds release candidates, tagged `-rc`.
+    Rc,
+    /// Adds betas, tagged `-beta`.
+    Beta,
+    /// Adds every other prerelease, such as `-alpha` or `-nightly` tags and
+    /// flagged releases without a suffix.
+    Prerelease,
 }
 
-#[must_use]
-pub fn select_asset<'a>(assets: &'a [Asset], pattern: &Regex) -> Option<&'a Asset> {
-    assets.iter().find(|asset| pattern.is_match(&asset.name))
+impl Serialize for Channel {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use wiremock::{
-        Mock, MockServer, ResponseTemplate,
-        matchers::{header, header_exists, method, path},
-    };
+impl Channel {
+    /// The most conservative channel a release tagged `tag`, flagged as a
+    /// prerelease or not, belongs to. Only a suffix starting with a letter
+    /// counts, so date tags such as `2025-10-28` are not prereleases.
+    #[must_use]
+    pub fn of_tag(tag: &str, prerelease: bool) -> Self {
+        let suffix = tag
+            .find(|c: char| c.is_ascii_digit())
+            .and_then(|start| tag[start..].split('+').next()?.split_once('-'))
+            .map(|(_, suffix)| suffix.to_ascii_lowercase())
+            .filter(|suffix| suffix.starts_with(|c: char| c.is_ascii_alphabetic()));
+        match suffix {
+            Some(suffix) if suffix.starts_with("rc") => Self::Rc,
+            Some(suffix) if suffix.starts_with("beta") => Self::Beta,
+            Some(_) => Self::Prerelease,
+            None if prerelease => Self::Prerelease,
+            None => Self::Stable,
+        }
+    }
 
-    use super::*;
+    /// Whether `release` is on this channel.
+    #[must_use]
+    pub fn includes(self, release: &Release) -> bool {
+        Self::of_tag(&release.tag_name, release.prerelease) <= self
+    }
+}
 
-    #[tokio::test]
-    async fn test_fetch_latest_returns_release_with_etag() {
-        let mock_server = MockServer::start().await;
+impl FromStr for Channel {
+    type Err = GithubError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "stable" => Ok(Self::Stable),
+            "rc" => Ok(Self::Rc),
+            "beta" => Ok(Self::Beta),
+            "prerelease" => Ok(Self::Prerelease),
+            _ => Err(GithubError::InvalidChannel(s.to_string())),
+        }
+    }
+}
 
-        let release_json = serde_json::json!({
-            "tag_name": "v0.1.3",
-            "prerelease": false,
-            "assets": [
-                {
-                    "name": "app-linux-amd64.tar.gz",
-                    "url": "https://api.github.com/repos/owner/repo/releases/assets/1",
-                    "browser_download_url": "https://github.com/owner/repo/releases/download/v0.1.3/app-linux-amd64.tar.gz",
-                    "size": 1024
-                }
-            ]
-        });
+impl TryFrom<String> for Channel {
+    type Error = GithubError;
 
-        Mock::given(method("GET"))
-            .and(path("/repos/owner/repo/releases/latest"))
-            .respond_with(
-                ResponseTemplate::new(200)
-                    .set_body_json(&release_json)
-                    .insert_header("etag", "\"abc123\"")
-                    .insert_header("last-modified", "Mon, 27 Oct 2025 12:00:00 GMT"),
-            )
-            .mount(&mock_server)
-            .await;
-
-        let result = fetch_latest()
-            .repo("owner/repo")
-            .host(&mock_server.uri())
-            .await;
-
-        assert!(result.is_ok());
-        let fetch_result = result.unwrap();
-
-        assert!(fetch_result.release.is_some());
-        let release = fetch_result.release.unwrap();
-        assert_eq!(release.tag_name, "v0.1.3");
-        assert!(!release.prerelease);
-        assert_eq!(release.assets.len(), 1);
-        assert_eq!(release.assets[0].name, "app-linux-amd64.tar.gz");
-        assert_eq!(release.assets[0].size, 1024);
-
-        assert_eq!(fetch_result.validators.etag, Some("\"abc123\"".to_string()));
-        assert_eq!(
-            fetch_result.validators.last_modified,
-            Some("Mon, 27 Oct 2025 12:00:00 GMT".to_string())
-        );
-        assert!(fetch_result.was_modified);
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
     }
+}
 
-    #[tokio::test]
-    async fn test_fetch_latest_returns_not_modified_on_304() {
-        let mock_server = MockServer::start().await;
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Stable => "stable",
+            Self::Rc => "rc",
+            Self::Beta => "beta",
+            Self::Prerelease => "prerelease",
+        })
+    }
+}
 
-        Mock::given(method("GET"))
-            .and(path("/repos/owner/repo/releases/latest"))
-            .respond_with(
-                ResponseTemplate::new(304)
-                    .insert_header("etag", "\"abc123\"")
-                    .insert_header("last-modified", "Mon, 27 Oct 2025 12:00:00 GMT"),
-            )
-            .mount(&mock_server)
-            .await;
+/// A source archive GitHub generates for every release, selected with the asset
+/// pattern `@source` (tarball) or `@source.zip` (zipball) instead of an uploaded
+/// asset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceArchive {
+    Tarball,
+    Zipball,
+}
 
-        let validators = Validators {
-            etag: Some("\"abc123\"".to_string()),
-            last_modified: Some("Mon, 27 Oct 2025 12:00:00 GMT".to_string()),
+impl SourceArchive {
+    /// The archive an asset pattern asks for, if it is `@source` or `@source.zip`.
+    #[must_use]
+    pub fn from_pattern(pattern: &str) -> Option<Self> {
+        match pattern {
+            "@source" => Some(Self::Tarball),
+            "@source.zip" => Some(Self::Zipball),
+            _ => None,
+        }
+    }
+
+    /// The archive of `release` as an asset named like GitHub's "Source code"
+    /// downloads, `<repo name>-<tag>.tar.gz` or `.zip`, or `None` when the source
+    /// does not offer it.
+    ///
+    /// Its contents sit under a single `<owner>-<repo>-<sha>/` directory, which
+    /// extraction strips.
+    #[must_use]
+    pub fn asset(self, repo: &str, release: &Release) -> Option<Asset> {
+        let (url, extension) = match self {
+            Self::Tarball => (release.tarball_url.as_ref()?, "tar.gz"),
+            Self::Zipball => (release.zipball_url.as_ref()?, "zip"),
         };
+        let repo_name = repo.rsplit('/').next().unwrap_or(repo);
+        Some(Asset {
+            name: format!("{repo_name}-{}.{extension}", release.tag_name),
+            url: url.clone(),
+            browser_download_url: url.clone(),
+            size: 0,
+            digest: None,
+            uploader: None,
+        })
+    }
 
-        let result = fetch_latest()
-            .repo("owner/repo")
-            .host(&mock_server.uri())
-            .validators(validators)
-            .await;
+    /// Adds the archive to the front of `release`'s assets and returns a pattern
+    /// matching exactly it, so that it goes through the same download and
+    /// verification as an uploaded asset.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the release has no such archive, as with sources other
+    /// than GitHub.
+    pub fn select(self, repo: &str, release: &mut Release) -> Result<Regex> {
+        let asset = self
+            .asset(repo, release)
+            .ok_or_else(|| GithubError::NoSourceArchive {
+                tag: release.tag_name.clone(),
+            })?;
+        let pattern = Regex::new(&format!("^{}$", regex::escape(&asset.name)))
+            .expect("an escaped asset name is a valid regex");
+        release.assets.insert(0, asset);
+        Ok(pattern)
+    }
+}
 
-        assert!(result.is_ok());
-        let fetch_result = result.unwrap();
+impl Asset {
+    /// The hex SHA-256 from a `sha256:<hex>` digest.
+    #[must_use]
+    pub fn sha256(&self) -> Option<&str> {
+        self.digest.as_deref()?.strip_prefix("sha256:")
+    }
+}
 
-        assert!(fetch_result.release.is_none());
-        assert_eq!(fetch_result.validators.etag, Some("\"abc123\"".to_string()));
-        assert_eq!(
-            fetch_result.validators.last_modified,
-            Some("Mon, 27 Oct 2025 12:00:00 GMT".to_string())
-        );
-        assert!(!fetch_result.was_modified);
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ValidatorsOut {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// How long the response may be reused without asking again, from its
+    /// `Cache-Control: max-age`.
+    pub max_age: Option<Duration>,
+}
+
+/// The `max-age` of a response's `Cache-Control` header, unless the header also
+/// says `no-cache` or `no-store`.
+#[must_use]
+pub fn cache_max_age(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(CACHE_CONTROL)?.to_str().ok()?;
+    let directives: Vec<_> = value.split(',').map(str::trim).collect();
+    if directives.iter().any(|directive| {
+        directive.eq_ignore_ascii_case("no-cache") || directive.eq_ignore_ascii_case("no-store")
+    }) {
+        return None;
     }
+    directives
+        .iter()
+        .find_map(|directive| directive.strip_prefix("max-age="))
+        .and_then(|secs| secs.parse().ok())
+        .map(Duration::from_secs)
+}
 
-    #[tokio::test]
-    async fn test_fetch_latest_sends_validators_in_request() {
-        let mock_server = MockServer::start().await;
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FetchResult {
+    pub release: Option<Release>,
+    pub validators: ValidatorsOut,
+    pub was_modified: bool,
+    /// The API rate-limit budget GitHub reported with the response.
+    pub rate_limit: Option<RateLimit>,
+}
 
-        let release_json = serde_json::json!({
-            "tag_name": "v0.1.0",
-            "prerelease": false,
-            "assets": []
-        });
+/// The `X-RateLimit-*` headers of a GitHub API response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub remaining: u64,
+    pub limit: Option<u64>,
+    /// When the current rate-limit window resets.
+    pub reset: Option<Timestamp>,
+}
 
-        Mock::given(method("GET"))
-            .and(path("/repos/owner/repo/releases/latest"))
-            .and(header_exists("if-none-match"))
-            .and(header_exists("if-modified-since"))
-            .respond_with(ResponseTemplate::new(200).set_body_json(&release_json))
-            .expect(1)
-            .mount(&mock_server)
-            .await;
-
-        let validators = Validators {
-            etag: Some("\"etag-value\"".to_string()),
-            last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
-        };
+impl RateLimit {
+    /// Reads the rate limit from response headers, or `None` when GitHub did not
+    /// report a remaining count.
+    #[must_use]
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| headers.get(name).and_then(|h| h.to_str().ok());
+        Some(Self {
+            remaining: header("x-ratelimit-remaining")?.parse().ok()?,
+            limit: header("x-ratelimit-limit").and_then(|n| n.parse().ok()),
+            reset: header("x-ratelimit-reset")
+                .and_then(|n| n.parse().ok())
+                .and_then(|secs| Timestamp::from_second(secs).ok()),
+        })
+    }
+}
 
-        let result = fetch_latest()
-            .repo("owner/repo")
-            .host(&mock_server.uri())
-            .validators(validators)
-            .await;
-
-        assert!(result.is_ok());
-    }
-
-    #[tokio::test]
-    async fn test_fetch_latest_selects_prerelease_when_newer() {
-        let mock_server = MockServer::start().await;
-
-        let releases_json = serde_json::json!([
-            {
-                "tag_name": "v0.2.0-beta.1",
-                "prerelease": true,
-                "created_at": "2025-10-27T12:00:00Z",
-                "assets": [
-                    {
-                        "name": "app-beta.tar.gz",
-                        "url": "https://api.github.com/repos/owner/repo/releases/assets/2",
-                        "browser_download_url": "https://github.com/owner/repo/releases/download/v0.2.0-beta.1/app-beta.tar.gz",
-                        "size": 2048
-                    }
-                ]
-            },
-            {
-                "tag_name": "v0.1.5",
-                "prerelease": false,
-                "created_at": "2025-10-20T12:00:00Z",
-                "assets": [
-                    {
-                        "name": "app-stable.tar.gz",
-                        "url": "https://api.github.com/repos/owner/repo/releases/assets/3",
-                        "browser_download_url": "https://github.com/owner/repo/releases/download/v0.1.5/app-stable.tar.gz",
-                        "size": 1536
-                    }
-                ]
-            }
-        ]);
+/// When the next request may be sent, if `status` and `headers` say a rate limit
+/// was hit, or `None` for any other response.
+///
+/// GitHub answers 429, or 403 with `Retry-After` or `X-RateLimit-Remaining: 0`.
+/// `Retry-After` (seconds or an HTTP date) wins over `X-RateLimit-Reset`; without
+/// either, GitHub advises waiting a minute.
+#[must_use]
+pub fn rate_limited_until(
+    status: StatusCode,
+    headers: &HeaderMap,
+    now: Timestamp,
+) -> Option<Timestamp> {
+    let header = |name: &str| headers.get(name).and_then(|h| h.to_str().ok());
+    let retry_after =
+        header(RETRY_AFTER.as_str()).and_then(|value| match value.trim().parse::<i64>() {
+            Ok(secs) => now.checked_add(jiff::SignedDuration::from_secs(secs)).ok(),
+            Err(_) => parse_http_date(value),
+        });
+    let exhausted = header("x-ratelimit-remaining").is_some_and(|n| n.trim() == "0");
+    let limited = status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::FORBIDDEN && (retry_after.is_some() || exhausted));
+    if !limited {
+        return None;
+    }
 
-        Mock::given(method("GET"))
-            .and(path("/repos/owner/repo/releases"))
-            .respond_with(
-                ResponseTemplate::new(200)
-                    .set_body_json(&releases_json)
-                    .insert_header("etag", "\"xyz789\""),
-            )
-            .mount(&mock_server)
-            .await;
+    retry_after
+        .or_else(|| RateLimit::from_headers(headers).and_then(|limit| limit.reset))
+        .or_else(|| now.checked_add(jiff::SignedDuration::from_mins(1)).ok())
+}
 
-        let result = fetch_latest()
-            .repo("owner/repo")
-            .host(&mock_server.uri())
-            .allow_prerelease(true)
-            .await;
+/// Sleeps until `until` if that is no further away than `budget`, which the wait is
+/// taken from, and returns whether it did.
+///
+/// Every wait lasts at least a second, so a server that keeps answering with a
+/// reset time in the past still exhausts the budget.
+pub async fn wait_for_rate_limit(until: Timestamp, budget: &mut Duration) -> bool {
+    let wait = Duration::try_from(until.duration_since(Timestamp::now()))
+        .unwrap_or_default()
+        .max(Duration::from_secs(1));
+    if wait > *budget {
+        return false;
+    }
+    warn!(
+        "Rate limited; waiting {}s until {until} before retrying",
+        wait.as_secs()
+    );
+    tokio::time::sleep(wait).await;
+    *budget -= wait;
+    true
+}
 
-        assert!(result.is_ok());
-        let fetch_result = result.unwrap();
+/// Returns the opaque part of an entity tag, dropping the weak `W/` prefix
+/// and surrounding quotes.
+fn etag_opaque(etag: &str) -> &str {
+    let etag = etag.trim();
+    let etag = etag.strip_prefix("W/").unwrap_or(etag);
+    etag.strip_prefix('"')
+        .and_then(|e| e.strip_suffix('"'))
+        .unwrap_or(etag)
+}
 
-        assert!(fetch_result.release.is_some());
-        let release = fetch_result.release.unwrap();
-        assert_eq!(release.tag_name, "v0.2.0-beta.1");
-        assert!(release.prerelease);
-        assert_eq!(release.assets[0].name, "app-beta.tar.gz");
+/// Compares two entity tags by opaque value, so `W/"abc"`, `"abc"` and `abc`
+/// are all considered the same validator.
+#[must_use]
+pub fn etag_matches(a: &str, b: &str) -> bool {
+    etag_opaque(a) == etag_opaque(b)
+}
 
-        assert_eq!(fetch_result.validators.etag, Some("\"xyz789\"".to_string()));
-        assert!(fetch_result.was_modified);
+/// Parses an HTTP date: the IMF-fixdate, RFC 850 and asctime forms of RFC 9110,
+/// or any other RFC 2822 date.
+///
+/// RFC 3339 timestamps are accepted too, since state written by older versions
+/// stored `Last-Modified` that way.
+#[must_use]
+pub fn parse_http_date(value: &str) -> Option<Timestamp> {
+    let value = value.trim();
+    if let Ok(timestamp) = rfc2822::DateTimeParser::new().parse_timestamp(value) {
+        return Some(timestamp);
+    }
+    for format in ["%A, %d-%b-%y %H:%M:%S GMT", "%a %b %e %H:%M:%S %Y"] {
+        if let Ok(datetime) = DateTime::strptime(format, value) {
+            return TimeZone::UTC.to_timestamp(datetime).ok();
+        }
     }
+    value.parse().ok()
+}
 
-    #[tokio::test]
-    async fn test_fetch_latest_includes_bearer_token_when_provided() {
-        let mock_server = MockServer::start().await;
+/// Formats `timestamp` as an RFC 9110 IMF-fixdate, e.g. `Tue, 28 Oct 2025 12:00:00 GMT`.
+#[must_use]
+pub fn format_http_date(timestamp: Timestamp) -> String {
+    rfc2822::DateTimePrinter::new()
+        .timestamp_to_rfc9110_string(&timestamp)
+        .unwrap_or_else(|_| timestamp.to_string())
+}
 
-        let release_json = serde_json::json!({
-            "tag_name": "v0.1.0",
-            "prerelease": false,
-            "assets": []
-        });
+/// Returned when no asset of a release matches the pattern selecting it.
+#[derive(Debug, Error)]
+#[error("No {what} matching pattern")]
+pub struct NoMatchingAsset {
+    /// What the pattern selects, such as `asset` or `checksum asset`.
+    pub what: &'static str,
+    pub pattern: String,
+    pub tag: String,
+}
 
-        Mock::given(method("GET"))
-            .and(path("/repos/owner/repo/releases/latest"))
-            .and(header("Authorization", "Bearer secret-token"))
-            .respond_with(ResponseTemplate::new(200).set_body_json(&release_json))
-            .expect(1)
-            .mount(&mock_server)
-            .await;
+impl NoMatchingAsset {
+    #[must_use]
+    pub fn new(what: &'static str, pattern: &Regex, tag: &str) -> Self {
+        Self {
+            what,
+            pattern: pattern.to_string(),
+            tag: tag.to_string(),
+        }
+    }
+}
 
-        let result = fetch_latest()
-            .repo("owner/repo")
-            .token("secret-token")
-            .host(&mock_server.uri())
-            .await;
+/// Selects the asset of a release to install on this host. See
+/// [`select_asset_for`].
+#[must_use]
+pub fn select_asset<'a>(assets: &'a [Asset], pattern: Option<&Regex>) -> Option<&'a Asset> {
+    select_asset_for(assets, pattern, &Platform::host())
+}
 
-        assert!(result.is_ok());
-        let fetch_result = result.unwrap();
-        assert!(fetch_result.release.is_some());
-        assert_eq!(fetch_result.release.unwrap().tag_name, "v0.1.0");
+/// Selects the asset of a release to install on `platform`.
+///
+/// With a pattern, the first matching asset is chosen, unless a later match names
+/// the platform's C library and the first does not, so a pattern matching both
+/// the `-gnu` and the `-musl` build picks the one for this host. Without a
+/// pattern, the asset with the highest [`Platform::score`] is chosen, the first
+/// of them on a tie, or `None` if no asset can be installed on `platform`.
+#[must_use]
+pub fn select_asset_for<'a>(
+    assets: &'a [Asset],
+    pattern: Option<&Regex>,
+    platform: &Platform,
+) -> Option<&'a Asset> {
+    let Some(pattern) = pattern else {
+        return assets
+            .iter()
+            .filter_map(|asset| Some((platform.score(&asset.name)?, asset)))
+            .fold(None, |best, (score, asset)| match best {
+                Some((best_score, _)) if best_score >= score => best,
+                _ => Some((score, asset)),
+            })
+            .map(|(_, asset)| asset);
+    };
+    let mut matches = assets.iter().filter(|asset| pattern.is_match(&asset.name));
+    let first = matches.next()?;
+    if platform.names_libc(&first.name) {
+        return Some(first);
     }
+    Some(
+        matches
+            .find(|asset| platform.names_libc(&asset.name))
+            .unwrap_or(first),
+    )
+}
 
-    #[tokio::test]
-    async fn test_fetch_latest_no_auth_header_when_token_absent() {
-        let mock_server = MockServer::start().await;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern::Libc;
 
-        let release_json = serde_json::json!({
-            "tag_name": "v0.1.0",
-            "prerelease": false,
-            "assets": []
-        });
+    #[test]
+    fn test_rate_limit_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(RateLimit::from_headers(&headers), None);
 
-        Mock::given(method("GET"))
-            .and(path("/repos/owner/repo/releases/latest"))
-            .respond_with(ResponseTemplate::new(200).set_body_json(&release_json))
-            .mount(&mock_server)
-            .await;
-
-        let result = fetch_latest()
-            .repo("owner/repo")
-            .host(&mock_server.uri())
-            .await;
-
-        assert!(result.is_ok());
-    }
-
-    #[tokio::test]
-    async fn test_fetch_latest_skips_draft_releases() {
-        let mock_server = MockServer::start().await;
-
-        let releases_json = serde_json::json!([
-            {
-                "tag_name": "v0.3.0",
-                "prerelease": false,
-                "draft": true,
-                "created_at": "2025-10-28T12:00:00Z",
-                "assets": [
-                    {
-                        "name": "app-draft.tar.gz",
-                        "url": "https://api.github.com/repos/owner/repo/releases/assets/4",
-                        "browser_download_url": "https://github.com/owner/repo/releases/download/v0.3.0/app-draft.tar.gz",
-                        "size": 3072
-                    }
-                ]
-            },
-            {
-                "tag_name": "v0.2.0",
-                "prerelease": false,
-                "draft": false,
-                "created_at": "2025-10-27T12:00:00Z",
-                "assets": [
-                    {
-                        "name": "app-stable.tar.gz",
-                        "url": "https://api.github.com/repos/owner/repo/releases/assets/5",
-                        "browser_download_url": "https://github.com/owner/repo/releases/download/v0.2.0/app-stable.tar.gz",
-                        "size": 2048
-                    }
-                ]
-            }
-        ]);
+        headers.insert("x-ratelimit-remaining", "59".parse().unwrap());
+        headers.insert("x-ratelimit-limit", "60".parse().unwrap());
+        headers.insert("x-ratelimit-reset", "1761656400".parse().unwrap());
+        assert_eq!(
+            RateLimit::from_headers(&headers),
+            Some(RateLimit {
+                remaining: 59,
+                limit: Some(60),
+                reset: Some("2025-10-28T13:00:00Z".parse().unwrap()),
+            })
+        );
+    }
 
-        Mock::given(method("GET"))
-            .and(path("/repos/owner/repo/releases"))
-            .respond_with(
-                ResponseTemplate::new(200)
-                    .set_body_json(&releases_json)
-                    .insert_header("etag", "\"draft789\""),
+    #[test]
+    fn test_release_age() {
+        assert_eq!(
+            "48h".parse::<ReleaseAge>().unwrap(),
+            ReleaseAge(SignedDuration::from_hours(48))
+        );
+        assert_eq!(
+            "1w 12h".parse::<ReleaseAge>().unwrap(),
+            ReleaseAge(SignedDuration::from_hours(180))
+        );
+        assert!("1 month".parse::<ReleaseAge>().is_err());
+        assert!("-2d".parse::<ReleaseAge>().is_err());
+        assert!("soon".parse::<ReleaseAge>().is_err());
+        assert_eq!("48h".parse::<ReleaseAge>().unwrap().to_string(), "2d");
+        assert_eq!("36h".parse::<ReleaseAge>().unwrap().to_string(), "36h");
+
+        let now: Timestamp = "2025-10-28T12:00:00Z".parse().unwrap();
+        let release = |published_at: Option<&str>| Release {
+            published_at: published_at.map(|published_at| published_at.parse().unwrap()),
+            ..Release::from_tag(
+                "owner/repo",
+                Tag {
+                    name: "v1.0.0".to_string(),
+                    tarball_url: "https://example.com/v1.0.0.tar.gz".to_string(),
+                    zipball_url: None,
+                },
             )
-            .mount(&mock_server)
-            .await;
+        };
+        let age: ReleaseAge = "2d".parse().unwrap();
+        assert!(age.allows(&release(Some("2025-10-26T12:00:00Z")), now));
+        assert!(!age.allows(&release(Some("2025-10-27T12:00:00Z")), now));
+        assert!(!age.allows(&release(None), now));
+    }
 
-        let result = fetch_latest()
-            .repo("owner/repo")
-            .host(&mock_server.uri())
-            .allow_prerelease(true)
-            .await;
+    #[test]
+    fn test_channel_of_tag() {
+        assert_eq!(Channel::of_tag("v1.4.0", false), Channel::Stable);
+        assert_eq!(Channel::of_tag("v1.4.0", true), Channel::Prerelease);
+        assert_eq!(Channel::of_tag("v1.4.0-rc.1", false), Channel::Rc);
+        assert_eq!(Channel::of_tag("cli-v2.0.0-Beta2", true), Channel::Beta);
+        assert_eq!(Channel::of_tag("v2.0.0-alpha.1", true), Channel::Prerelease);
+        assert_eq!(
+            Channel::of_tag("release-1.2+build-7", false),
+            Channel::Stable
+        );
+        assert_eq!(Channel::of_tag("2025-10-28", false), Channel::Stable);
+
+        assert!(Channel::Beta > Channel::Rc);
+        assert_eq!("RC".parse::<Channel>().unwrap(), Channel::Rc);
+        assert_eq!(Channel::Prerelease.to_string(), "prerelease");
+        assert!("nightly".parse::<Channel>().is_err());
+    }
 
-        assert!(result.is_ok());
-        let fetch_result = result.unwrap();
+    #[test]
+    fn test_cache_max_age() {
+        let cache_control = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(CACHE_CONTROL, value.parse().unwrap());
+            cache_max_age(&headers)
+        };
 
-        assert!(fetch_result.release.is_some());
-        let release = fetch_result.release.unwrap();
-        assert_eq!(release.tag_name, "v0.2.0");
-        assert!(!release.draft);
-        assert_eq!(release.assets[0].name, "app-stable.tar.gz");
+        assert_eq!(cache_max_age(&HeaderMap::new()), None);
+        assert_eq!(
+            cache_control("public, max-age=60, s-maxage=60"),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(cache_control("private, max-age=60, no-cache"), None);
+        assert_eq!(cache_control("no-store"), None);
     }
 
-    #[tokio::test]
-    async fn test_fetch_latest_returns_error_for_404() {
-        let mock_server = MockServer::start().await;
+    #[test]
+    fn test_rate_limited_until() {
+        let now: Timestamp = "2025-10-28T12:00:00Z".parse().unwrap();
+        let headers = |pairs: &[(&'static str, &str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.insert(*name, value.parse().unwrap());
+            }
+            headers
+        };
 
-        Mock::given(method("GET"))
-            .and(path("/repos/owner/repo/releases/latest"))
-            .respond_with(ResponseTemplate::new(404))
-            .mount(&mock_server)
-            .await;
+        let exhausted = headers(&[
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", "1761656400"),
+        ]);
+        assert_eq!(
+            rate_limited_until(StatusCode::FORBIDDEN, &exhausted, now),
+            Some("2025-10-28T13:00:00Z".parse().unwrap())
+        );
+        assert_eq!(rate_limited_until(StatusCode::OK, &exhausted, now), None);
 
-        let result = fetch_latest()
-            .repo("owner/repo")
-            .host(&mock_server.uri())
-            .await;
+        let secondary = headers(&[("retry-after", "30"), ("x-ratelimit-remaining", "12")]);
+        assert_eq!(
+            rate_limited_until(StatusCode::FORBIDDEN, &secondary, now),
+            Some("2025-10-28T12:00:30Z".parse().unwrap())
+        );
+        assert_eq!(
+            rate_limited_until(
+                StatusCode::TOO_MANY_REQUESTS,
+                &headers(&[("retry-after", "Tue, 28 Oct 2025 12:05:00 GMT")]),
+                now
+            ),
+            Some("2025-10-28T12:05:00Z".parse().unwrap())
+        );
+        assert_eq!(
+            rate_limited_until(StatusCode::TOO_MANY_REQUESTS, &HeaderMap::new(), now),
+            Some("2025-10-28T12:01:00Z".parse().unwrap())
+        );
 
-        assert!(result.is_err());
-        let err = result.unwrap_err();
-        assert!(err.to_string().contains("404"));
+        let forbidden = headers(&[("x-ratelimit-remaining", "12")]);
+        assert_eq!(
+            rate_limited_until(StatusCode::FORBIDDEN, &forbidden, now),
+            None
+        );
     }
 
-    #[tokio::test]
-    async fn test_fetch_latest_returns_error_for_403() {
-        let mock_server = MockServer::start().await;
+    #[test]
+    fn test_etag_matches_ignores_weakness_and_quotes() {
+        assert!(etag_matches("\"abc123\"", "\"abc123\""));
+        assert!(etag_matches("W/\"abc123\"", "\"abc123\""));
+        assert!(etag_matches("W/\"abc123\"", "abc123"));
+        assert!(etag_matches(" \"abc123\" ", "W/\"abc123\""));
+        assert!(!etag_matches("W/\"abc123\"", "\"abc124\""));
+        assert!(!etag_matches("\"\"", "\"abc123\""));
+    }
 
-        Mock::given(method("GET"))
-            .and(path("/repos/owner/repo/releases/latest"))
-            .respond_with(ResponseTemplate::new(403))
-            .mount(&mock_server)
-            .await;
+    #[test]
+    fn test_parse_http_date_formats() {
+        let expected: Timestamp = "1994-11-06T08:49:37Z".parse().unwrap();
+        for value in [
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 1994",
+            "Sun, 6 Nov 1994 09:49:37 +0100",
+            "1994-11-06T08:49:37Z",
+        ] {
+            assert_eq!(parse_http_date(value), Some(expected), "{value}");
+        }
+        assert_eq!(parse_http_date("yesterday"), None);
+        assert_eq!(parse_http_date(""), None);
+        assert_eq!(format_http_date(expected), "Sun, 06 Nov 1994 08:49:37 GMT");
+    }
 
-        let result = fetch_latest()
-            .repo("owner/repo")
-            .host(&mock_server.uri())
-            .await;
+    #[test]
+    fn test_source_archive_select() {
+        let mut release: Release = serde_json::from_value(serde_json::json!({
+            "tag_name": "v1.2.0",
+            "prerelease": false,
+            "draft": false,
+            "tarball_url": "https://api.github.com/repos/owner/myapp/tarball/v1.2.0",
+            "zipball_url": "https://api.github.com/repos/owner/myapp/zipball/v1.2.0",
+            "assets": []
+        }))
+        .unwrap();
 
-        assert!(result.is_err());
-        let err = result.unwrap_err();
-        assert!(err.to_string().contains("403"));
+        let pattern = SourceArchive::from_pattern("@source.zip")
+            .unwrap()
+            .select("owner/myapp", &mut release)
+            .unwrap();
+
+        assert_eq!(pattern.as_str(), r"^myapp\-v1\.2\.0\.zip$");
+        assert_eq!(release.assets[0].name, "myapp-v1.2.0.zip");
+        assert_eq!(
+            release.assets[0].url,
+            "https://api.github.com/repos/owner/myapp/zipball/v1.2.0"
+        );
+        assert_eq!(SourceArchive::from_pattern(r"myapp-.*\.tar\.gz"), None);
+
+        release.tarball_url = None;
+        let error = SourceArchive::Tarball
+            .select("owner/myapp", &mut release)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "v1.2.0 has no generated source archive; @source only works with GitHub releases"
+        );
     }
 
     #[test]
@@ -496,23 +834,29 @@ mod tests {
                 url: "https://api.github.com/repos/owner/repo/releases/assets/1".to_string(),
                 browser_download_url: "https://example.com/app-linux-amd64.tar.gz".to_string(),
                 size: 1024,
+                digest: None,
+                uploader: None,
             },
             Asset {
                 name: "app-darwin-amd64.tar.gz".to_string(),
                 url: "https://api.github.com/repos/owner/repo/releases/assets/2".to_string(),
                 browser_download_url: "https://example.com/app-darwin-amd64.tar.gz".to_string(),
                 size: 2048,
+                digest: None,
+                uploader: None,
             },
             Asset {
                 name: "app-linux-arm64.tar.gz".to_string(),
                 url: "https://api.github.com/repos/owner/repo/releases/assets/3".to_string(),
                 browser_download_url: "https://example.com/app-linux-arm64.tar.gz".to_string(),
                 size: 3072,
+                digest: None,
+                uploader: None,
             },
         ];
 
         let pattern = Regex::new(r"app-linux-.*\.tar\.gz").unwrap();
-        let result = select_asset(&assets, &pattern);
+        let result = select_asset(&assets, Some(&pattern));
 
         assert!(result.is_some());
         assert_eq!(result.unwrap().name, "app-linux-amd64.tar.gz");
@@ -526,17 +870,21 @@ mod tests {
                 url: "https://api.github.com/repos/owner/repo/releases/assets/1".to_string(),
                 browser_download_url: "https://example.com/app-darwin-amd64.tar.gz".to_string(),
                 size: 1024,
+                digest: None,
+                uploader: None,
             },
             Asset {
                 name: "app-windows-amd64.zip".to_string(),
                 url: "https://api.github.com/repos/owner/repo/releases/assets/2".to_string(),
                 browser_download_url: "https://example.com/app-windows-amd64.zip".to_string(),
                 size: 2048,
+                digest: None,
+                uploader: None,
             },
         ];
 
         let pattern = Regex::new(r"app-linux-.*\.tar\.gz").unwrap();
-        let result = select_asset(&assets, &pattern);
+        let result = select_asset(&assets, Some(&pattern));
 
         assert!(result.is_none());
     }
@@ -549,25 +897,96 @@ mod tests {
                 url: "https://api.github.com/repos/owner/repo/releases/assets/1".to_string(),
                 browser_download_url: "https://example.com/checksums.txt".to_string(),
                 size: 128,
+                digest: None,
+                uploader: None,
             },
             Asset {
                 name: "SHA256SUMS".to_string(),
                 url: "https://api.github.com/repos/owner/repo/releases/assets/2".to_string(),
                 browser_download_url: "https://example.com/SHA256SUMS".to_string(),
                 size: 256,
+                digest: None,
+                uploader: None,
             },
             Asset {
                 name: "checksums.sha256".to_string(),
                 url: "https://api.github.com/repos/owner/repo/releases/assets/3".to_string(),
                 browser_download_url: "https://example.com/checksums.sha256".to_string(),
                 size: 200,
+                digest: None,
+                uploader: None,
             },
         ];
 
         let pattern = Regex::new(r"checksum|SHA256").unwrap();
-        let result = select_asset(&assets, &pattern);
+        let result = select_asset(&assets, Some(&pattern));
 
         assert!(result.is_some());
         assert_eq!(result.unwrap().name, "checksums.txt");
     }
+
+    #[test]
+    fn test_select_asset_for_platform() {
+        let asset = |name: &str| Asset {
+            name: name.to_string(),
+            url: format!("https://api.github.com/repos/owner/repo/releases/assets/{name}"),
+            browser_download_url: format!("https://example.com/{name}"),
+            size: 1024,
+            digest: None,
+            uploader: None,
+        };
+        let assets = vec![
+            asset("checksums.txt"),
+            asset("tool-1.0.0-x86_64-unknown-linux-musl.tar.gz"),
+            asset("tool-1.0.0-x86_64-unknown-linux-gnu.tar.gz"),
+            asset("tool-1.0.0-x86_64-unknown-linux-gnu.tar.gz.sig"),
+            asset("tool-1.0.0-aarch64-unknown-linux-gnu.tar.gz"),
+            asset("tool-1.0.0-x86_64-apple-darwin.tar.gz"),
+        ];
+        let linux = Platform {
+            os: "linux",
+            arch: "x86_64",
+            libc: Some(Libc::Gnu),
+        };
+
+        assert_eq!(
+            select_asset_for(&assets, None, &linux).unwrap().name,
+            "tool-1.0.0-x86_64-unknown-linux-gnu.tar.gz"
+        );
+        let no_libc = Platform {
+            libc: None,
+            ..linux
+        };
+        assert_eq!(
+            select_asset_for(&assets, None, &no_libc).unwrap().name,
+            "tool-1.0.0-x86_64-unknown-linux-musl.tar.gz"
+        );
+
+        // A pattern matching both builds picks the one for the host's C library.
+        let both = Regex::new(r"^tool-.*-x86_64-unknown-linux-.*\.tar\.gz$").unwrap();
+        assert_eq!(
+            select_asset_for(&assets, Some(&both), &linux).unwrap().name,
+            "tool-1.0.0-x86_64-unknown-linux-gnu.tar.gz"
+        );
+        let musl = Platform {
+            libc: Some(Libc::Musl),
+            ..linux
+        };
+        assert_eq!(
+            select_asset_for(&assets, Some(&both), &musl).unwrap().name,
+            "tool-1.0.0-x86_64-unknown-linux-musl.tar.gz"
+        );
+        assert_eq!(
+            select_asset_for(&assets, Some(&both), &no_libc)
+                .unwrap()
+                .name,
+            "tool-1.0.0-x86_64-unknown-linux-musl.tar.gz"
+        );
+        let windows = Platform {
+            os: "windows",
+            arch: "x86_64",
+            libc: None,
+        };
+        assert!(select_asset_for(&assets, None, &windows).is_none());
+    }
 }
@@ -0,0 +1,1576 @@
+use std::{cmp::Reverse, fs, io, time::Duration};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use jiff::Timestamp;
+use regex::Regex;
+use reqwest::{
+    StatusCode,
+    header::{
+        ACCEPT, AUTHORIZATION, ETAG, HeaderMap, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+        LINK,
+    },
+};
+use serde::{Deserialize, de::DeserializeOwned};
+use tracing::warn;
+
+use super::{
+    API_VERSION, Channel, FetchResult, GithubError, MAX_DEBUG_BODY_BYTES, RateLimit, Release,
+    ReleaseAge, Result, Tag, Validators, ValidatorsOut, cache_max_age, rate_limited_until,
+    wait_for_rate_limit,
+};
+use crate::{DEFAULT_GITHUB_HOST, DEFAULT_TIMEOUT, constraint::VersionConstraint};
+
+/// Fetches the latest release from GitHub.
+///
+/// Uses conditional requests via `ETag` and `Last-Modified` headers when validators
+/// are provided. Returns an optional release (None on 304), updated validators, and
+/// whether content changed.
+///
+/// Only releases on `channel` (see [`Channel`]) are considered. On the stable
+/// channel this is `/releases/latest`, which answers 404 when a repository has no
+/// stable release marked as latest (for example when it only publishes
+/// prereleases); a 404 from it, or a latest release tagged as a prerelease, falls
+/// back to the newest stable, non-draft entry of the release listing. Other
+/// channels read the listing directly.
+///
+/// With `fallback_to_tags`, a repository without any published release falls back
+/// once more, to the highest-versioned tag of `/repos/{repo}/tags` (see
+/// [`Release::from_tag`]) on `channel`, read from its suffix.
+///
+/// With `tag_pattern`, as for a monorepo publishing releases of several
+/// components, only releases whose tag matches are considered: the newest matching
+/// entry of the release listing is used instead of `/releases/latest`, and the tag
+/// fallback reads versions from the part of each tag after the match.
+///
+/// With `version_constraint`, the release listing is searched the same way, and
+/// the release with the highest version satisfying the constraint is used, so
+/// updates stay within a major or minor series; the tag fallback only considers
+/// satisfying tags.
+///
+/// With `min_release_age`, the listing is searched too, skipping releases published
+/// more recently than that. The listing answers differently as releases age, so
+/// `validators` are not sent, and tags, which have no publish time, are never
+/// used.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Network request fails
+/// - Response cannot be parsed as JSON
+/// - The listing has no release on `channel`
+/// - No release tag matches `tag_pattern`
+/// - No release version satisfies `version_constraint`
+/// - No release is at least `min_release_age` old
+///
+/// When `debug_dir` is given, a response body that cannot be parsed is saved there
+/// (see [`MAX_DEBUG_BODY_BYTES`]) and the error names the saved file.
+///
+/// A request refused for an exhausted rate limit is retried once the limit resets
+/// if that is within `rate_limit_wait`; otherwise it fails with
+/// [`GithubError::RateLimited`].
+#[bon::builder(derive(IntoFuture(Box)))]
+pub async fn fetch_latest(
+    repo: &str,
+    token: Option<&str>,
+    #[builder(default = crate::build_http_client(DEFAULT_TIMEOUT).unwrap())]
+    client: reqwest::Client,
+    #[builder(default = DEFAULT_GITHUB_HOST)] host: &str,
+    #[builder(default)] channel: Channel,
+    #[builder(default = false)] fallback_to_tags: bool,
+    #[builder(default)] validators: Validators,
+    debug_dir: Option<&Utf8Path>,
+    #[builder(default)] rate_limit_wait: Duration,
+    tag_pattern: Option<&Regex>,
+    version_constraint: Option<&VersionConstraint>,
+    min_release_age: Option<ReleaseAge>,
+) -> Result<FetchResult> {
+    let list_url = format!("{host}/repos/{repo}/releases");
+    let shared = Request {
+        client: &client,
+        token,
+        debug_dir,
+        rate_limit_wait,
+        tag_pattern,
+        version_constraint,
+        min_release_age,
+        channel,
+    };
+    let validators = if min_release_age.is_some() {
+        Validators::default()
+    } else {
+        validators
+    };
+    let result = if tag_pattern.is_some()
+        || version_constraint.is_some()
+        || min_release_age.is_some()
+    {
+        // `/releases/latest` may belong to another component or series, or be too
+        // new, so search a full page of the listing instead.
+        let url = format!("{list_url}?per_page=100");
+        fetch_newest(&shared, &url, &validators, Endpoint::List).await
+    } else if channel != Channel::Stable {
+        fetch_newest(&shared, &list_url, &validators, Endpoint::List).await
+    } else {
+        let latest_url = format!("{list_url}/latest");
+        match fetch_newest(&shared, &latest_url, &validators, Endpoint::Latest).await {
+            Err(GithubError::NotFound { .. }) => {
+                warn!(
+                    "{repo} has no latest release; looking for the newest stable release instead"
+                );
+                fetch_newest(&shared, &list_url, &Validators::default(), Endpoint::List).await
+            }
+            Ok(FetchResult {
+                release: Some(release),
+                ..
+            }) if !channel.includes(&release) => {
+                warn!(
+                    "The latest release {} of {repo} is tagged as a prerelease; looking for the newest stable release instead",
+                    release.tag_name
+                );
+                fetch_newest(&shared, &list_url, &Validators::default(), Endpoint::List).await
+            }
+            result => result,
+        }
+    };
+
+    match result {
+        Err(GithubError::NoReleases) if fallback_to_tags => {
+            if let Some(min_age) = min_release_age {
+                return Err(GithubError::NoReleaseOldEnough { min_age });
+            }
+            warn!("{repo} has no releases; looking for its newest tag instead");
+            fetch_newest_tag(&shared, host, repo).await
+        }
+        result => result,
+    }
+}
+
+/// Which GitHub endpoint [`fetch_newest`] reads and how it picks a release.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Endpoint {
+    /// `/releases/latest`, a single release.
+    Latest,
+    /// The release listing, newest non-draft release on the channel.
+    List,
+}
+
+/// What every request made by [`fetch_newest`] shares.
+struct Request<'a> {
+    client: &'a reqwest::Client,
+    token: Option<&'a str>,
+    debug_dir: Option<&'a Utf8Path>,
+    rate_limit_wait: Duration,
+    /// Only releases and tags whose name matches are considered.
+    tag_pattern: Option<&'a Regex>,
+    /// Only releases and tags whose version satisfies this are considered.
+    version_constraint: Option<&'a VersionConstraint>,
+    /// Only releases published at least this long ago are considered.
+    min_release_age: Option<ReleaseAge>,
+    /// Only releases and tags on this channel are considered.
+    channel: Channel,
+}
+
+impl Request<'_> {
+    /// Sends `request`, waiting out rate limits that reset within `rate_limit_wait`.
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let mut budget = self.rate_limit_wait;
+        loop {
+            let attempt = request
+                .try_clone()
+                .expect("GET requests without a body can be cloned");
+            let response = attempt.send().await?;
+            match rate_limited_until(response.status(), response.headers(), Timestamp::now()) {
+                Some(reset) if wait_for_rate_limit(reset, &mut budget).await => {}
+                Some(reset) => return Err(GithubError::RateLimited { reset }),
+                None => return Ok(response),
+            }
+        }
+    }
+}
+
+/// A GET request to the GitHub API, authorized with `token` when given.
+fn api_get(client: &reqwest::Client, url: &str, token: Option<&str>) -> reqwest::RequestBuilder {
+    let request = client
+        .get(url)
+        .header(ACCEPT, "application/vnd.github+json")
+        .header("x-github-api-version", API_VERSION);
+    match token {
+        Some(token) => request.header(AUTHORIZATION, format!("Bearer {token}")),
+        None => request,
+    }
+}
+
+/// Turns a non-2xx `response` into the matching [`GithubError`].
+fn check_status(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    let url = response.url().to_string();
+    match status {
+        StatusCode::NOT_FOUND => Err(GithubError::NotFound { url }),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            Err(GithubError::Unauthorized { status, url })
+        }
+        _ => Ok(response.error_for_status()?),
+    }
+}
+
+async fn fetch_newest(
+    shared: &Request<'_>,
+    url: &str,
+    validators: &Validators,
+    endpoint: Endpoint,
+) -> Result<FetchResult> {
+    let mut request = api_get(shared.client, url, shared.token);
+    if let Some(etag) = &validators.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+
+    let response = shared.send(request).await?;
+    let status = response.status();
+    let headers = response.headers();
+    let validators_out = ValidatorsOut {
+        etag: headers
+            .get(ETAG)
+            .and_then(|h| h.to_str().ok())
+            .map(String::from),
+        last_modified: headers
+            .get(LAST_MODIFIED)
+            .and_then(|h| h.to_str().ok())
+            .map(String::from),
+        max_age: cache_max_age(headers),
+    };
+    let rate_limit = RateLimit::from_headers(headers);
+
+    if status == StatusCode::NOT_MODIFIED {
+        return Ok(FetchResult {
+            release: None,
+            validators: validators_out,
+            was_modified: false,
+            rate_limit,
+        });
+    }
+
+    let response = check_status(response)?;
+
+    let release = if endpoint == Endpoint::Latest {
+        parse_json::<Release>(response, shared.debug_dir).await?
+    } else {
+        let mut releases = parse_json::<Vec<Release>>(response, shared.debug_dir).await?;
+        releases.retain(|r| !r.draft);
+        if releases.is_empty() {
+            return Err(GithubError::NoReleases);
+        }
+        if let Some(pattern) = shared.tag_pattern {
+            releases.retain(|r| pattern.is_match(&r.tag_name));
+            if releases.is_empty() {
+                return Err(GithubError::NoMatchingRelease {
+                    pattern: pattern.to_string(),
+                });
+            }
+        }
+        releases.retain(|r| shared.channel.includes(r));
+        if releases.is_empty() {
+            return Err(match shared.channel {
+                Channel::Stable => GithubError::NoStableRelease,
+                channel => GithubError::NoChannelRelease { channel },
+            });
+        }
+        if let Some(min_age) = shared.min_release_age {
+            min_age.retain(&mut releases)?;
+        }
+        match shared.version_constraint {
+            Some(constraint) => {
+                constraint
+                    .select(releases)
+                    .ok_or_else(|| GithubError::NoMatchingVersion {
+                        constraint: constraint.to_string(),
+                    })?
+            }
+            None => {
+                releases.sort_by_key(|r| Reverse(r.created_at));
+                releases
+                    .into_iter()
+                    .next()
+                    .ok_or(GithubError::NoStableRelease)?
+            }
+        }
+    };
+
+    Ok(FetchResult {
+        release: Some(release),
+        validators: validators_out,
+        was_modified: true,
+        rate_limit,
+    })
+}
+
+/// Synthesizes a release from the highest-versioned tag on the first page of
+/// `/repos/{repo}/tags`, which GitHub lists newest first.
+async fn fetch_newest_tag(shared: &Request<'_>, host: &str, repo: &str) -> Result<FetchResult> {
+    let request = api_get(
+        shared.client,
+        &format!("{host}/repos/{repo}/tags?per_page=100"),
+        shared.token,
+    );
+    let response = check_status(shared.send(request).await?)?;
+    let rate_limit = RateLimit::from_headers(response.headers());
+    let tag = parse_json::<Vec<Tag>>(response, shared.debug_dir)
+        .await?
+        .into_iter()
+        .filter(|tag| {
+            shared
+                .version_constraint
+                .is_none_or(|constraint| constraint.matches(&tag.name))
+        })
+        .filter_map(|tag| {
+            let version = tag_version(&tag.name, shared.tag_pattern)?.to_string();
+            (Channel::of_tag(&version, false) <= shared.channel).then_some((version, tag))
+        })
+        .rev()
+        .max_by_key(|(version, _)| tag_version_key(version))
+        .map(|(_, tag)| tag)
+        .ok_or(GithubError::NoTags)?;
+
+    Ok(FetchResult {
+        release: Some(Release::from_tag(repo, tag)),
+        validators: ValidatorsOut {
+            etag: None,
+            last_modified: None,
+            max_age: None,
+        },
+        was_modified: true,
+        rate_limit,
+    })
+}
+
+/// The part of a tag name holding its version: what follows the `tag_pattern`
+/// match, so `^cli-v` leaves `1.2.0` of `cli-v1.2.0`, or the whole name without a
+/// pattern. `None` when the pattern does not match.
+fn tag_version<'a>(name: &'a str, tag_pattern: Option<&Regex>) -> Option<&'a str> {
+    match tag_pattern {
+        Some(pattern) => {
+            let found = pattern.find(name)?;
+            Some(name[found.end()..].trim_start_matches(['-', '_', '/', '@']))
+        }
+        None => Some(name),
+    }
+}
+
+/// Orders tags by the numbers in their version, before any `-` suffix, with a
+/// suffixed tag below the same version without one: `v1.10.0` > `v1.9.0` >
+/// `v1.9.0-rc.1`.
+fn tag_version_key(name: &str) -> (Vec<u64>, bool) {
+    let (version, suffix) = name
+        .split_once('-')
+        .map_or((name, None), |(version, suffix)| (version, Some(suffix)));
+    let numbers = version
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse().unwrap_or(u64::MAX))
+        .collect();
+    (numbers, suffix.is_none())
+}
+
+/// Fetches the release for a specific tag from GitHub.
+///
+/// Always performs a full request; drafts are never returned by this endpoint.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Network request fails or returns a non-2xx status (404 when no release has the tag)
+/// - Response cannot be parsed as JSON (saved to `debug_dir` when given)
+/// - A rate limit does not reset within `rate_limit_wait` ([`GithubError::RateLimited`])
+#[bon::builder(derive(IntoFuture(Box)))]
+pub async fn fetch_tag(
+    repo: &str,
+    tag: &str,
+    token: Option<&str>,
+    #[builder(default = crate::build_http_client(DEFAULT_TIMEOUT).unwrap())]
+    client: reqwest::Client,
+    #[builder(default = DEFAULT_GITHUB_HOST)] host: &str,
+    debug_dir: Option<&Utf8Path>,
+    #[builder(default)] rate_limit_wait: Duration,
+) -> Result<Release> {
+    let shared = Request {
+        client: &client,
+        token,
+        debug_dir,
+        rate_limit_wait,
+        tag_pattern: None,
+        version_constraint: None,
+        min_release_age: None,
+        channel: Channel::Prerelease,
+    };
+    let request = api_get(
+        &client,
+        &format!("{host}/repos/{repo}/releases/tags/{tag}"),
+        token,
+    );
+    let response = check_status(shared.send(request).await?)?;
+    parse_json(response, debug_dir).await
+}
+
+/// Lists releases for a repository, newest first, following pagination.
+///
+/// Requests pages of `per_page` releases and follows the `Link: rel="next"` header
+/// until `limit` releases have been collected or there are no more pages. Drafts are
+/// only returned by GitHub when the token has push access to the repository.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Network request fails or returns a non-2xx status
+/// - Response cannot be parsed as JSON
+#[bon::builder(derive(IntoFuture(Box)))]
+pub async fn list_releases(
+    repo: &str,
+    token: Option<&str>,
+    #[builder(default = crate::build_http_client(DEFAULT_TIMEOUT).unwrap())]
+    client: reqwest::Client,
+    #[builder(default = DEFAULT_GITHUB_HOST)] host: &str,
+    #[builder(default = 100)] per_page: u32,
+    limit: Option<usize>,
+) -> Result<Vec<Release>> {
+    let mut releases = Vec::new();
+    let mut next_url = Some(format!("{host}/repos/{repo}/releases?per_page={per_page}"));
+
+    while let Some(url) = next_url.take() {
+        let response = check_status(api_get(&client, &url, token).send().await?)?;
+        next_url = next_page_url(response.headers());
+        releases.extend(parse_json::<Vec<Release>>(response, None).await?);
+
+        if let Some(limit) = limit
+            && releases.len() >= limit
+        {
+            releases.truncate(limit);
+            break;
+        }
+    }
+
+    Ok(releases)
+}
+
+#[derive(Debug)]
+pub struct Probe {
+    /// Scopes granted to a classic token (`X-OAuth-Scopes`); `None` when GitHub
+    /// does not report them (anonymous requests and fine-grained tokens).
+    pub scopes: Option<Vec<String>>,
+    pub rate_limit_remaining: Option<u64>,
+    /// Whether the repository is private, when a repository was probed.
+    pub private: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct RepoInfo {
+    #[serde(default)]
+    private: bool,
+}
+
+/// Checks that the GitHub API is reachable and reports what the token can do.
+///
+/// Requests `/repos/{repo}` when a repository is given (confirming the token can see
+/// it), otherwise `/rate_limit`, and reads the token scopes and remaining rate limit
+/// from the response headers.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Network request fails or returns a non-2xx status
+/// - Response cannot be parsed as JSON
+#[bon::builder(derive(IntoFuture(Box)))]
+pub async fn probe(
+    repo: Option<&str>,
+    token: Option<&str>,
+    #[builder(default = crate::build_http_client(DEFAULT_TIMEOUT).unwrap())]
+    client: reqwest::Client,
+    #[builder(default = DEFAULT_GITHUB_HOST)] host: &str,
+) -> Result<Probe> {
+    let url = match repo {
+        Some(repo) => format!("{host}/repos/{repo}"),
+        None => format!("{host}/rate_limit"),
+    };
+
+    let response = api_get(&client, &url, token).send().await?;
+    if let Some(reset) = rate_limited_until(response.status(), response.headers(), Timestamp::now())
+    {
+        return Err(GithubError::RateLimited { reset });
+    }
+    let response = check_status(response)?;
+    let headers = response.headers();
+    let header = |name: &str| headers.get(name).and_then(|h| h.to_str().ok());
+    let scopes = header("x-oauth-scopes").map(|scopes| {
+        scopes
+            .split(',')
+            .map(str::trim)
+            .filter(|scope| !scope.is_empty())
+            .map(String::from)
+            .collect()
+    });
+    let rate_limit_remaining = RateLimit::from_headers(headers).map(|limit| limit.remaining);
+
+    let private = if repo.is_some() {
+        Some(parse_json::<RepoInfo>(response, None).await?.private)
+    } else {
+        None
+    };
+
+    Ok(Probe {
+        scopes,
+        rate_limit_remaining,
+        private,
+    })
+}
+
+/// Parses a response body as JSON.
+///
+/// On failure the error names the URL, and the body (capped at
+/// [`MAX_DEBUG_BODY_BYTES`]) is saved under `debug_dir` when given, so an unexpected
+/// response can be examined after the run.
+async fn parse_json<T: DeserializeOwned>(
+    response: reqwest::Response,
+    debug_dir: Option<&Utf8Path>,
+) -> Result<T> {
+    let url = response.url().to_string();
+    let body = response.bytes().await?;
+    let error = match serde_json::from_slice(&body) {
+        Ok(value) => return Ok(value),
+        Err(e) => e,
+    };
+
+    let message = match debug_dir.map(|dir| save_debug_body(dir, &body)) {
+        Some(Ok(path)) => format!("{error} (response saved to {path})"),
+        Some(Err(e)) => format!("{error} (could not save response: {e})"),
+        None => error.to_string(),
+    };
+    Err(GithubError::Parse { url, message })
+}
+
+fn save_debug_body(dir: &Utf8Path, body: &[u8]) -> io::Result<Utf8PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!(
+        "github-response-{}.json",
+        Timestamp::now().strftime("%Y%m%dT%H%M%S%.3fZ")
+    ));
+    fs::write(&path, &body[..body.len().min(MAX_DEBUG_BODY_BYTES)])?;
+    Ok(path)
+}
+
+/// Extracts the `rel="next"` URL from a GitHub `Link` header.
+fn next_page_url(headers: &HeaderMap) -> Option<String> {
+    let link = headers.get(LINK)?.to_str().ok()?;
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| param.trim() == r#"rel="next""#)
+            .then(|| {
+                url.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{header, header_exists, method, path, query_param, query_param_is_missing},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fetch_latest_returns_release_with_etag() {
+        let mock_server = MockServer::start().await;
+
+        let release_json = serde_json::json!({
+            "tag_name": "v0.1.3",
+            "prerelease": false,
+            "assets": [
+                {
+                    "name": "app-linux-amd64.tar.gz",
+                    "url": "https://api.github.com/repos/owner/repo/releases/assets/1",
+                    "browser_download_url": "https://github.com/owner/repo/releases/download/v0.1.3/app-linux-amd64.tar.gz",
+                    "size": 1024
+                }
+            ]
+        });
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases/latest"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(&release_json)
+                    .insert_header("etag", "\"abc123\"")
+                    .insert_header("last-modified", "Mon, 27 Oct 2025 12:00:00 GMT"),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let result = fetch_latest()
+            .repo("owner/repo")
+            .host(&mock_server.uri())
+            .await;
+
+        assert!(result.is_ok());
+        let fetch_result = result.unwrap();
+
+        assert!(fetch_result.release.is_some());
+        let release = fetch_result.release.unwrap();
+        assert_eq!(release.tag_name, "v0.1.3");
+        assert!(!release.prerelease);
+        assert_eq!(release.assets.len(), 1);
+        assert_eq!(release.assets[0].name, "app-linux-amd64.tar.gz");
+        assert_eq!(release.assets[0].size, 1024);
+
+        assert_eq!(fetch_result.validators.etag, Some("\"abc123\"".to_string()));
+        assert_eq!(
+            fetch_result.validators.last_modified,
+            Some("Mon, 27 Oct 2025 12:00:00 GMT".to_string())
+        );
+        assert!(fetch_result.was_modified);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_tag() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases/tags/v0.1.2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tag_name": "v0.1.2",
+                "prerelease": false,
+                "assets": []
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let release = fetch_tag()
+            .repo("owner/repo")
+            .tag("v0.1.2")
+            .host(&mock_server.uri())
+            .await
+            .unwrap();
+        assert_eq!(release.tag_name, "v0.1.2");
+
+        let missing = fetch_tag()
+            .repo("owner/repo")
+            .tag("v9.9.9")
+            .host(&mock_server.uri())
+            .await;
+        assert!(missing.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_tag_rate_limited() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(403)
+                    .insert_header("x-ratelimit-remaining", "0")
+                    .insert_header("x-ratelimit-reset", "4102444800"),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let error = fetch_tag()
+            .repo("owner/repo")
+            .tag("v0.1.2")
+            .host(&mock_server.uri())
+            .rate_limit_wait(Duration::from_secs(60))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "GitHub API rate limited until 2100-01-01T00:00:00Z"
+        );
+        assert!(matches!(error, GithubError::RateLimited { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_tag_waits_for_retry_after() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "1"))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases/tags/v0.1.2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "tag_name": "v0.1.2",
+                "prerelease": false,
+                "assets": []
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let release = fetch_tag()
+            .repo("owner/repo")
+            .tag("v0.1.2")
+            .host(&mock_server.uri())
+            .rate_limit_wait(Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(release.tag_name, "v0.1.2");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_latest_returns_not_modified_on_304() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases/latest"))
+            .respond_with(
+                ResponseTemplate::new(304)
+                    .insert_header("etag", "\"abc123\"")
+                    .insert_header("last-modified", "Mon, 27 Oct 2025 12:00:00 GMT"),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let validators = Validators {
+            etag: Some("\"abc123\"".to_string()),
+            last_modified: Some("Mon, 27 Oct 2025 12:00:00 GMT".to_string()),
+        };
+
+        let result = fetch_latest()
+            .repo("owner/repo")
+            .host(&mock_server.uri())
+            .validators(validators)
+            .await;
+
+        assert!(result.is_ok());
+        let fetch_result = result.unwrap();
+
+        assert!(fetch_result.release.is_none());
+        assert_eq!(fetch_result.validators.etag, Some("\"abc123\"".to_string()));
+        assert_eq!(
+            fetch_result.validators.last_modified,
+            Some("Mon, 27 Oct 2025 12:00:00 GMT".to_string())
+        );
+        assert!(!fetch_result.was_modified);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_latest_sends_validators_in_request() {
+        let mock_server = MockServer::start().await;
+
+        let release_json = serde_json::json!({
+            "tag_name": "v0.1.0",
+            "prerelease": false,
+            "assets": []
+        });
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases/latest"))
+            .and(header_exists("if-none-match"))
+            .and(header_exists("if-modified-since"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&release_json))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let validators = Validators {
+            etag: Some("\"etag-value\"".to_string()),
+            last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+        };
+
+        let result = fetch_latest()
+            .repo("owner/repo")
+            .host(&mock_server.uri())
+            .validators(validators)
+            .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_latest_selects_prerelease_when_newer() {
+        let mock_server = MockServer::start().await;
+
+        let releases_json = serde_json::json!([
+            {
+                "tag_name": "v0.2.0-beta.1",
+                "prerelease": true,
+                "created_at": "2025-10-27T12:00:00Z",
+                "assets": [
+                    {
+                        "name": "app-beta.tar.gz",
+                        "url": "https://api.github.com/repos/owner/repo/releases/assets/2",
+                        "browser_download_url": "https://github.com/owner/repo/releases/download/v0.2.0-beta.1/app-beta.tar.gz",
+                        "size": 2048
+                    }
+                ]
+            },
+            {
+                "tag_name": "v0.1.5",
+                "prerelease": false,
+                "created_at": "2025-10-20T12:00:00Z",
+                "assets": [
+                    {
+                        "name": "app-stable.tar.gz",
+                        "url": "https://api.github.com/repos/owner/repo/releases/assets/3",
+                        "browser_download_url": "https://github.com/owner/repo/releases/download/v0.1.5/app-stable.tar.gz",
+                        "size": 1536
+                    }
+                ]
+            }
+        ]);
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(&releases_json)
+                    .insert_header("etag", "\"xyz789\""),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let result = fetch_latest()
+            .repo("owner/repo")
+            .host(&mock_server.uri())
+            .channel(Channel::Prerelease)
+            .await;
+
+        assert!(result.is_ok());
+        let fetch_result = result.unwrap();
+
+        assert!(fetch_result.release.is_some());
+        let release = fetch_result.release.unwrap();
+        assert_eq!(release.tag_name, "v0.2.0-beta.1");
+        assert!(release.prerelease);
+        assert_eq!(release.assets[0].name, "app-beta.tar.gz");
+
+        assert_eq!(fetch_result.validators.etag, Some("\"xyz789\"".to_string()));
+        assert!(fetch_result.was_modified);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_latest_includes_bearer_token_when_provided() {
+        let mock_server = MockServer::start().await;
+
+        let release_json = serde_json::json!({
+            "tag_name": "v0.1.0",
+            "prerelease": false,
+            "assets": []
+        });
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases/latest"))
+            .and(header("Authorization", "Bearer secret-token"))
+            .and(header("X-GitHub-Api-Version", API_VERSION))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&release_json))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let result = fetch_latest()
+            .repo("owner/repo")
+            .token("secret-token")
+            .host(&mock_server.uri())
+            .await;
+
+        assert!(result.is_ok());
+        let fetch_result = result.unwrap();
+        assert!(fetch_result.release.is_some());
+        assert_eq!(fetch_result.release.unwrap().tag_name, "v0.1.0");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_latest_no_auth_header_when_token_absent() {
+        let mock_server = MockServer::start().await;
+
+        let release_json = serde_json::json!({
+            "tag_name": "v0.1.0",
+            "prerelease": false,
+            "assets": []
+        });
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases/latest"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&release_json))
+            .mount(&mock_server)
+            .await;
+
+        let result = fetch_latest()
+            .repo("owner/repo")
+            .host(&mock_server.uri())
+            .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_latest_skips_draft_releases() {
+        let mock_server = MockServer::start().await;
+
+        let releases_json = serde_json::json!([
+            {
+                "tag_name": "v0.3.0",
+                "prerelease": false,
+                "draft": true,
+                "created_at": "2025-10-28T12:00:00Z",
+                "assets": [
+                    {
+                        "name": "app-draft.tar.gz",
+                        "url": "https://api.github.com/repos/owner/repo/releases/assets/4",
+                        "browser_download_url": "https://github.com/owner/repo/releases/download/v0.3.0/app-draft.tar.gz",
+                        "size": 3072
+                    }
+                ]
+            },
+            {
+                "tag_name": "v0.2.0",
+                "prerelease": false,
+                "draft": false,
+                "created_at": "2025-10-27T12:00:00Z",
+                "assets": [
+                    {
+                        "name": "app-stable.tar.gz",
+                        "url": "https://api.github.com/repos/owner/repo/releases/assets/5",
+                        "browser_download_url": "https://github.com/owner/repo/releases/download/v0.2.0/app-stable.tar.gz",
+                        "size": 2048
+                    }
+                ]
+            }
+        ]);
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(&releases_json)
+                    .insert_header("etag", "\"draft789\""),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let result = fetch_latest()
+            .repo("owner/repo")
+            .host(&mock_server.uri())
+            .channel(Channel::Prerelease)
+            .await;
+
+        assert!(result.is_ok());
+        let fetch_result = result.unwrap();
+
+        assert!(fetch_result.release.is_some());
+        let release = fetch_result.release.unwrap();
+        assert_eq!(release.tag_name, "v0.2.0");
+        assert!(!release.draft);
+        assert_eq!(release.assets[0].name, "app-stable.tar.gz");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_latest_returns_error_for_404() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases/latest"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+
+        let result = fetch_latest()
+            .repo("owner/repo")
+            .host(&mock_server.uri())
+            .await;
+
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("404"));
+        assert!(matches!(err, GithubError::NotFound { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_latest_falls_back_to_newest_stable_on_404() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases/latest"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let releases_json = serde_json::json!([
+            {
+                "tag_name": "v0.3.0-rc.1",
+                "prerelease": true,
+                "created_at": "2025-10-27T12:00:00Z",
+                "assets": []
+            },
+            {
+                "tag_name": "v0.2.1",
+                "prerelease": false,
+                "draft": true,
+                "created_at": "2025-10-25T12:00:00Z",
+                "assets": []
+            },
+            {
+                "tag_name": "v0.2.0",
+                "prerelease": false,
+                "created_at": "2025-10-20T12:00:00Z",
+                "assets": []
+            }
+        ]);
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases"))
+            .and(|request: &wiremock::Request| !request.headers.contains_key(IF_NONE_MATCH))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&releases_json))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let fetch_result = fetch_latest()
+            .repo("owner/repo")
+            .host(&mock_server.uri())
+            .validators(Validators {
+                etag: Some("\"stale\"".to_string()),
+                last_modified: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(fetch_result.release.unwrap().tag_name, "v0.2.0");
+        assert!(fetch_result.was_modified);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_latest_fallback_errors_when_only_prereleases() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases/latest"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {
+                    "tag_name": "v0.3.0-rc.1",
+                    "prerelease": true,
+                    "assets": []
+                }
+            ])))
+            .mount(&mock_server)
+            .await;
+
+        let err = fetch_latest()
+            .repo("owner/repo")
+            .host(&mock_server.uri())
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("No stable releases found"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_latest_follows_channel() {
+        let mock_server = MockServer::start().await;
+
+        let release = |tag: &str, prerelease: bool, created_at: &str| {
+            serde_json::json!({
+                "tag_name": tag,
+                "prerelease": prerelease,
+                "created_at": created_at,
+                "assets": []
+            })
+        };
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases/latest"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(release(
+                "v1.4.0-rc.1",
+                false,
+                "2025-10-25T12:00:00Z",
+            )))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                release("v2.0.0-alpha.1", true, "2025-10-27T12:00:00Z"),
+                release("v1.5.0-beta.2", true, "2025-10-26T12:00:00Z"),
+                release("v1.4.0-rc.1", false, "2025-10-25T12:00:00Z"),
+                release("v1.3.0", false, "2025-10-20T12:00:00Z"),
+            ])))
+            .mount(&mock_server)
+            .await;
+
+        let host = mock_server.uri();
+        let host = host.as_str();
+        let fetch = |channel| async move {
+            fetch_latest()
+                .repo("owner/repo")
+                .host(host)
+                .channel(channel)
+                .await
+                .unwrap()
+                .release
+                .unwrap()
+                .tag_name
+        };
+
+        // The latest release is an unflagged release candidate.
+        assert_eq!(fetch(Channel::Stable).await, "v1.3.0");
+        assert_eq!(fetch(Channel::Rc).await, "v1.4.0-rc.1");
+        assert_eq!(fetch(Channel::Beta).await, "v1.5.0-beta.2");
+        assert_eq!(fetch(Channel::Prerelease).await, "v2.0.0-alpha.1");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_latest_with_tag_pattern_skips_other_components() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases/latest"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let releases_json = serde_json::json!([
+            {
+                "tag_name": "server-v2.0.0",
+                "prerelease": false,
+                "created_at": "2025-10-27T12:00:00Z",
+                "assets": []
+            },
+            {
+                "tag_name": "cli-v1.3.0-rc.1",
+                "prerelease": true,
+                "created_at": "2025-10-26T12:00:00Z",
+                "assets": []
+            },
+            {
+                "tag_name": "cli-v1.2.0",
+                "prerelease": false,
+                "created_at": "2025-10-20T12:00:00Z",
+                "assets": []
+            }
+        ]);
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases"))
+            .and(query_param("per_page", "100"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&releases_json))
+            .mount(&mock_server)
+            .await;
+
+        let host = mock_server.uri();
+        let pattern = Regex::new("^cli-v").unwrap();
+        let fetch = |channel| {
+            fetch_latest()
+                .repo("owner/repo")
+                .host(&host)
+                .channel(channel)
+                .tag_pattern(&pattern)
+        };
+
+        let stable = fetch(Channel::Stable).await.unwrap();
+        assert_eq!(stable.release.unwrap().tag_name, "cli-v1.2.0");
+        let newest = fetch(Channel::Rc).await.unwrap();
+        assert_eq!(newest.release.unwrap().tag_name, "cli-v1.3.0-rc.1");
+
+        let result = fetch_latest()
+            .repo("owner/repo")
+            .host(&mock_server.uri())
+            .tag_pattern(&Regex::new("^docs-v").unwrap())
+            .await;
+        assert!(matches!(
+            result,
+            Err(GithubError::NoMatchingRelease { pattern }) if pattern == "^docs-v"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_latest_with_version_constraint_stays_in_series() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases/latest"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let releases_json = serde_json::json!([
+            {
+                "tag_name": "v2.0.0",
+                "prerelease": false,
+                "created_at": "2025-10-27T12:00:00Z",
+                "assets": []
+            },
+            {
+                "tag_name": "v1.4.3",
+                "prerelease": false,
+                "created_at": "2025-10-26T12:00:00Z",
+                "assets": []
+            },
+            {
+                "tag_name": "v1.5.0",
+                "prerelease": false,
+                "created_at": "2025-10-20T12:00:00Z",
+                "assets": []
+            }
+        ]);
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases"))
+            .and(query_param("per_page", "100"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&releases_json))
+            .mount(&mock_server)
+            .await;
+
+        let host = mock_server.uri();
+        let fetch = |constraint: &str| {
+            let constraint = VersionConstraint::new(constraint, None).unwrap();
+            let host = host.clone();
+            async move {
+                fetch_latest()
+                    .repo("owner/repo")
+                    .host(&host)
+                    .version_constraint(&constraint)
+                    .await
+            }
+        };
+
+        let minor = fetch("~1.4").await.unwrap();
+        assert_eq!(minor.release.unwrap().tag_name, "v1.4.3");
+        let major = fetch("^1.4").await.unwrap();
+        assert_eq!(major.release.unwrap().tag_name, "v1.5.0");
+        assert!(matches!(
+            fetch("^3").await,
+            Err(GithubError::NoMatchingVersion { constraint }) if constraint == "^3"
+        ));
+    }
+
+    #[test]
+    fn test_tag_version() {
+        let pattern = Regex::new("^cli").unwrap();
+
+        assert_eq!(tag_version("cli-v1.2.0", Some(&pattern)), Some("v1.2.0"));
+        assert_eq!(tag_version("server-v1.2.0", Some(&pattern)), None);
+        assert_eq!(tag_version("v1.2.0-rc.1", None), Some("v1.2.0-rc.1"));
+    }
+
+    #[test]
+    fn test_tag_version_key_orders_versions() {
+        let mut tags = ["v1.9.0", "v1.10.0-rc.1", "v1.10.0", "v1.2.0", "nightly"];
+        tags.sort_by_key(|tag| Reverse(tag_version_key(tag)));
+
+        assert_eq!(
+            tags,
+            ["v1.10.0", "v1.10.0-rc.1", "v1.9.0", "v1.2.0", "nightly"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_latest_falls_back_to_newest_tag_without_releases() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/myapp/releases/latest"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/myapp/releases"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .mount(&mock_server)
+            .await;
+        let tarball = |tag: &str| {
+            format!(
+                "{}/repos/owner/myapp/tarball/refs/tags/{tag}",
+                mock_server.uri()
+            )
+        };
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/myapp/tags"))
+            .and(query_param("per_page", "100"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {"name": "v1.2.0-rc.1", "tarball_url": tarball("v1.2.0-rc.1")},
+                {"name": "v1.10.0", "tarball_url": tarball("v1.10.0")},
+                {"name": "v1.9.0", "tarball_url": tarball("v1.9.0")}
+            ])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let without_fallback = fetch_latest()
+            .repo("owner/myapp")
+            .host(&mock_server.uri())
+            .await
+            .unwrap_err();
+        assert_eq!(without_fallback.to_string(), "No releases found");
+
+        let release = fetch_latest()
+            .repo("owner/myapp")
+            .host(&mock_server.uri())
+            .fallback_to_tags(true)
+            .await
+            .unwrap()
+            .release
+            .unwrap();
+
+        assert_eq!(release.tag_name, "v1.10.0");
+        assert_eq!(release.assets.len(), 1);
+        assert_eq!(release.assets[0].name, "myapp-v1.10.0.tar.gz");
+        assert_eq!(release.assets[0].url, tarball("v1.10.0"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_tag_saves_unparseable_response() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases/tags/v1.0.0"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"tag_name": 42}"#))
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = camino_tempfile::tempdir().unwrap();
+        let debug_dir = temp_dir.path().join("debug");
+        let err = fetch_tag()
+            .repo("owner/repo")
+            .tag("v1.0.0")
+            .host(&mock_server.uri())
+            .debug_dir(&debug_dir)
+            .await
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.starts_with("cannot parse GitHub response from "));
+        let saved = fs::read_dir(&debug_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(saved.len(), 1);
+        assert!(err.ends_with(&format!("(response saved to {})", saved[0].display())));
+        assert_eq!(
+            fs::read_to_string(&saved[0]).unwrap(),
+            r#"{"tag_name": 42}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_saved_response_is_capped() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases/latest"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_bytes(vec![b'x'; MAX_DEBUG_BODY_BYTES + 10]),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let temp_dir = camino_tempfile::tempdir().unwrap();
+        let err = fetch_latest()
+            .repo("owner/repo")
+            .host(&mock_server.uri())
+            .debug_dir(temp_dir.path())
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("response saved to"));
+        let saved = fs::read_dir(temp_dir.path())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(saved.metadata().unwrap().len(), MAX_DEBUG_BODY_BYTES as u64);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_latest_returns_error_for_403() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases/latest"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&mock_server)
+            .await;
+
+        let result = fetch_latest()
+            .repo("owner/repo")
+            .host(&mock_server.uri())
+            .await;
+
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("403"));
+        assert!(matches!(
+            err,
+            GithubError::Unauthorized {
+                status: StatusCode::FORBIDDEN,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_list_releases_follows_pagination() {
+        let mock_server = MockServer::start().await;
+
+        let release = |tag: &str| {
+            serde_json::json!({
+                "tag_name": tag,
+                "prerelease": false,
+                "assets": []
+            })
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases"))
+            .and(query_param("page", "2"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!([release("v1.0.0")])),
+            )
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases"))
+            .and(query_param("per_page", "2"))
+            .and(query_param_is_missing("page"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!([release("v1.2.0"), release("v1.1.0")]))
+                    .insert_header(
+                        "link",
+                        format!(
+                            "<{0}/repos/owner/repo/releases?per_page=2&page=2>; rel=\"next\", <{0}/repos/owner/repo/releases?per_page=2&page=2>; rel=\"last\"",
+                            mock_server.uri()
+                        ),
+                    ),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let releases = list_releases()
+            .repo("owner/repo")
+            .host(&mock_server.uri())
+            .per_page(2)
+            .await
+            .unwrap();
+
+        let tags = releases
+            .iter()
+            .map(|r| r.tag_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(tags, vec!["v1.2.0", "v1.1.0", "v1.0.0"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_releases_stops_at_limit() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo/releases"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!([
+                        {"tag_name": "v1.2.0", "prerelease": false, "assets": []},
+                        {"tag_name": "v1.1.0", "prerelease": false, "assets": []}
+                    ]))
+                    .insert_header(
+                        "link",
+                        format!(
+                            "<{}/repos/owner/repo/releases?page=2>; rel=\"next\"",
+                            mock_server.uri()
+                        ),
+                    ),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let releases = list_releases()
+            .repo("owner/repo")
+            .host(&mock_server.uri())
+            .limit(1)
+            .await
+            .unwrap();
+
+        assert_eq!(releases.len(), 1);
+        assert_eq!(releases[0].tag_name, "v1.2.0");
+    }
+
+    #[tokio::test]
+    async fn test_probe_repo_reports_scopes_and_visibility() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/repo"))
+            .and(header("authorization", "Bearer test-token"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ "private": true }))
+                    .insert_header("x-oauth-scopes", "repo, read:org")
+                    .insert_header("x-ratelimit-remaining", "4999"),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let probe = probe()
+            .repo("owner/repo")
+            .token("test-token")
+            .host(&mock_server.uri())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            probe.scopes,
+            Some(vec!["repo".to_string(), "read:org".to_string()])
+        );
+        assert_eq!(probe.rate_limit_remaining, Some(4999));
+        assert_eq!(probe.private, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_probe_without_repo_uses_rate_limit() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/rate_limit"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({}))
+                    .insert_header("x-ratelimit-remaining", "59"),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let probe = probe().host(&mock_server.uri()).await.unwrap();
+
+        assert_eq!(probe.scopes, None);
+        assert_eq!(probe.rate_limit_remaining, Some(59));
+        assert_eq!(probe.private, None);
+    }
+
+    #[tokio::test]
+    async fn test_probe_fails_for_inaccessible_repo() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/owner/private"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+
+        let result = probe().repo("owner/private").host(&mock_server.uri()).await;
+
+        assert!(result.is_err());
+    }
+}
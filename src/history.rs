@@ -0,0 +1,160 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write as _},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::fsops;
+
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("invalid history entry on line {line}: {source}")]
+    Parse {
+        line: usize,
+        source: serde_json::Error,
+    },
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, HistoryError>;
+
+/// One successful install, as recorded in `history.jsonl`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub tag: String,
+    pub installed_at: Timestamp,
+    pub asset: String,
+    /// Lowercase hex SHA-256 of the downloaded asset.
+    pub sha256: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_tag: Option<String>,
+}
+
+/// Path of the install journal for `app`: `<state_directory>/<app>/history.jsonl`.
+#[must_use]
+pub fn path(state_directory: &Utf8Path, app: &str) -> Utf8PathBuf {
+    state_directory.join(app).join("history.jsonl")
+}
+
+/// Appends `entry` to `app`'s journal as a single JSON line and syncs it to disk.
+///
+/// # Errors
+///
+/// Returns an error if the state directory cannot be created or the journal cannot
+/// be opened, written, or synced.
+pub fn append(state_directory: &Utf8Path, app: &str, entry: &Entry) -> Result<()> {
+    let path = path(state_directory, app);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    file.write_all(line.as_bytes())?;
+    file.sync_all()?;
+
+    Ok(())
+}
+
+/// Atomically replaces `app`'s journal with `entries`, oldest first.
+///
+/// # Errors
+///
+/// Returns an error if the state directory cannot be created or the journal cannot
+/// be written, synced, or persisted.
+pub fn replace(state_directory: &Utf8Path, app: &str, entries: &[Entry]) -> Result<()> {
+    let mut contents = String::new();
+    for entry in entries {
+        contents.push_str(&serde_json::to_string(entry)?);
+        contents.push('\n');
+    }
+    fsops::write_atomic(path(state_directory, app), contents.as_bytes())?;
+
+    Ok(())
+}
+
+/// Loads `app`'s journal, oldest entry first.
+///
+/// Returns an empty list if nothing has been recorded. Blank lines are ignored.
+///
+/// # Errors
+///
+/// Returns an error if the journal cannot be read or a line is not a valid entry.
+pub fn load(state_directory: &Utf8Path, app: &str) -> Result<Vec<Entry>> {
+    let contents = match fs::read_to_string(path(state_directory, app)) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|source| HistoryError::Parse {
+                line: index + 1,
+                source,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches;
+
+    use camino_tempfile::tempdir;
+
+    use super::*;
+
+    fn entry(tag: &str, previous_tag: Option<&str>) -> Entry {
+        Entry {
+            tag: tag.to_string(),
+            installed_at: Timestamp::from_second(1_000_000_000).unwrap(),
+            asset: format!("myapp-{tag}.tar.gz"),
+            sha256: "ab".repeat(32),
+            previous_tag: previous_tag.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_append_and_load_in_order() {
+        let temp_dir = tempdir().unwrap();
+        assert!(load(temp_dir.path(), "myapp").unwrap().is_empty());
+
+        let first = entry("v1.0.0", None);
+        let second = entry("v1.1.0", Some("v1.0.0"));
+        append(temp_dir.path(), "myapp", &first).unwrap();
+        append(temp_dir.path(), "myapp", &second).unwrap();
+
+        assert_eq!(load(temp_dir.path(), "myapp").unwrap(), vec![first, second]);
+    }
+
+    #[test]
+    fn test_load_reports_bad_line() {
+        let temp_dir = tempdir().unwrap();
+        append(temp_dir.path(), "myapp", &entry("v1.0.0", None)).unwrap();
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(path(temp_dir.path(), "myapp"))
+            .unwrap();
+        file.write_all(b"\n{truncated\n").unwrap();
+
+        assert_matches!(
+            load(temp_dir.path(), "myapp"),
+            Err(HistoryError::Parse { line: 3, .. })
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use anyhow::{Result, anyhow, ensure};
+use jiff::Timestamp;
+use reqwest::{
+    StatusCode, Url,
+    header::{ACCEPT, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+};
+use serde::Deserialize;
+
+use crate::{
+    DEFAULT_TIMEOUT,
+    github::{Asset, FetchResult, Release, Validators, ValidatorsOut, cache_max_age},
+};
+
+const SHA256_HEX_LENGTH: usize = 64;
+
+/// A self-hosted JSON document describing the current release.
+///
+/// ```json
+/// {
+///   "version": "1.4.0",
+///   "assets": [
+///     {"name": "myapp-1.4.0-linux-x86_64.tar.gz", "url": "myapp-1.4.0-linux-x86_64.tar.gz", "sha256": "..."}
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct Feed {
+    pub version: String,
+    pub assets: Vec<FeedAsset>,
+    #[serde(default)]
+    pub published_at: Option<Timestamp>,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeedAsset {
+    pub name: String,
+    /// Absolute, or relative to the feed's own URL.
+    pub url: String,
+    #[serde(default)]
+    pub sha256: Option<String>,
+    #[serde(default)]
+    pub size: u64,
+}
+
+impl Feed {
+    /// Converts the feed into a release whose assets carry their SHA-256 as a
+    /// GitHub-style `sha256:<hex>` digest.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an asset URL cannot be resolved against `feed_url` or a
+    /// digest is not 64 hex characters.
+    pub fn into_release(self, feed_url: &Url) -> Result<Release> {
+        let assets = self
+            .assets
+            .into_iter()
+            .map(|asset| {
+                let url = feed_url.join(&asset.url)?.to_string();
+                if let Some(hex) = &asset.sha256 {
+                    ensure!(
+                        hex.len() == SHA256_HEX_LENGTH
+                            && hex.chars().all(|c| c.is_ascii_hexdigit()),
+                        "invalid SHA-256 digest for {}: {hex}",
+                        asset.name
+                    );
+                }
+                Ok(Asset {
+                    name: asset.name,
+                    browser_download_url: url.clone(),
+                    url,
+                    size: asset.size,
+                    digest: asset
+                        .sha256
+                        .map(|hex| format!("sha256:{}", hex.to_ascii_lowercase())),
+                    uploader: None,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Release {
+            tag_name: self.version,
+            assets,
+            prerelease: false,
+            draft: false,
+            created_at: self.published_at,
+            published_at: self.published_at,
+            body: self.notes,
+            html_url: None,
+            tarball_url: None,
+            zipball_url: None,
+        })
+    }
+}
+
+/// Fetches the feed at `url`, sending `validators` so an unchanged feed costs a
+/// `304 Not Modified`.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - `url` is not a valid URL
+/// - Network request fails or returns a non-2xx, non-304 status
+/// - Response is not a valid feed
+#[bon::builder(derive(IntoFuture(Box)))]
+pub async fn fetch_latest(
+    url: &str,
+    #[builder(default)] validators: Validators,
+    #[builder(default = crate::build_http_client(DEFAULT_TIMEOUT).unwrap())]
+    client: reqwest::Client,
+) -> Result<FetchResult> {
+    let feed_url = Url::parse(url).map_err(|e| anyhow!("invalid feed URL {url}: {e}"))?;
+    let mut request = client
+        .get(feed_url.clone())
+        .header(ACCEPT, "application/json");
+    if let Some(etag) = &validators.etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+
+    let response = request.send().await?;
+    let headers = response.headers();
+    let validators_out = ValidatorsOut {
+        etag: headers
+            .get(ETAG)
+            .and_then(|h| h.to_str().ok())
+            .map(String::from),
+        last_modified: headers
+            .get(LAST_MODIFIED)
+            .and_then(|h| h.to_str().ok())
+            .map(String::from),
+        max_age: cache_max_age(headers),
+    };
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(FetchResult {
+            release: None,
+            validators: validators_out,
+            was_modified: false,
+            rate_limit: None,
+        });
+    }
+
+    let feed: Feed = response.error_for_status()?.json().await?;
+    Ok(FetchResult {
+        release: Some(feed.into_release(&feed_url)?),
+        validators: validators_out,
+        was_modified: true,
+        rate_limit: None,
+    })
+}
+
+/// Fetches the feed at `url` and returns its release if it is `tag`.
+///
+/// A feed only describes one release, so older tags cannot be fetched.
+///
+/// # Errors
+///
+/// Returns an error if the feed cannot be fetched or describes another version.
+#[bon::builder(derive(IntoFuture(Box)))]
+pub async fn fetch_tag(
+    url: &str,
+    tag: &str,
+    #[builder(default = crate::build_http_client(DEFAULT_TIMEOUT).unwrap())]
+    client: reqwest::Client,
+) -> Result<Release> {
+    let release = fetch_latest()
+        .url(url)
+        .client(client)
+        .await?
+        .release
+        .ok_or_else(|| anyhow!("{url} returned no release"))?;
+    ensure!(
+        release.tag_name == tag,
+        "{url} describes version {}, not {tag}; a JSON feed only offers its current release",
+        release.tag_name
+    );
+    Ok(release)
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{header, method, path},
+    };
+
+    use super::*;
+
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+    fn feed(version: &str) -> serde_json::Value {
+        serde_json::json!({
+            "version": version,
+            "assets": [
+                {"name": "myapp.tar.gz", "url": format!("files/myapp-{version}.tar.gz"), "sha256": HELLO_SHA256.to_uppercase()},
+                {"name": "notes.txt", "url": "https://cdn.example.com/notes.txt"}
+            ]
+        })
+    }
+
+    #[test]
+    fn test_into_release_resolves_urls_and_digests() {
+        let feed: Feed = serde_json::from_value(feed("1.4.0")).unwrap();
+        let release = feed
+            .into_release(&Url::parse("https://deploy.example.com/myapp/latest.json").unwrap())
+            .unwrap();
+
+        assert_eq!(release.tag_name, "1.4.0");
+        assert_eq!(
+            release.assets[0].url,
+            "https://deploy.example.com/myapp/files/myapp-1.4.0.tar.gz"
+        );
+        assert_eq!(
+            release.assets[0].digest,
+            Some(format!("sha256:{HELLO_SHA256}"))
+        );
+        assert_eq!(release.assets[1].url, "https://cdn.example.com/notes.txt");
+        assert_eq!(release.assets[1].digest, None);
+    }
+
+    #[test]
+    fn test_into_release_rejects_bad_digest() {
+        let feed: Feed = serde_json::from_value(serde_json::json!({
+            "version": "1.0.0",
+            "assets": [{"name": "myapp.tar.gz", "url": "myapp.tar.gz", "sha256": "abc"}]
+        }))
+        .unwrap();
+
+        let error = feed
+            .into_release(&Url::parse("https://deploy.example.com/latest.json").unwrap())
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid SHA-256 digest for myapp.tar.gz: abc"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch_latest_not_modified() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/latest.json"))
+            .and(header("if-none-match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304).insert_header("etag", "\"v1\""))
+            .mount(&mock_server)
+            .await;
+
+        let result = fetch_latest()
+            .url(&format!("{}/latest.json", mock_server.uri()))
+            .validators(Validators {
+                etag: Some("\"v1\"".to_string()),
+                last_modified: None,
+            })
+            .await
+            .unwrap();
+
+        assert!(!result.was_modified);
+        assert!(result.release.is_none());
+        assert_eq!(result.validators.etag.as_deref(), Some("\"v1\""));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_tag_rejects_other_version() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/latest.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(feed("1.4.0")))
+            .mount(&mock_server)
+            .await;
+
+        let url = format!("{}/latest.json", mock_server.uri());
+        let error = fetch_tag().url(&url).tag("1.3.0").await.unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "{url} describes version 1.4.0, not 1.3.0; a JSON feed only offers its current release"
+            )
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! Reads GitHub tokens from the OS keyring for `--github-token-keyring`: the
+//! Secret Service (GNOME Keyring, KWallet) over D-Bus, or the kernel's keyutils.
+//! Each entry is read once per run, so `update-all` asks the keyring, and the
+//! user to unlock it, only once.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Mutex, PoisonError},
+    thread,
+};
+
+use thiserror::Error;
+use tracing::debug;
+
+/// The service entries are filed under in the Secret Service.
+pub const SERVICE: &str = "distronomicon";
+
+#[derive(Debug, Clone, Error)]
+pub enum KeyringError {
+    #[error("no token named {entry} in the {store}")]
+    NotFound { entry: String, store: Store },
+
+    #[error("cannot read {entry} from the {store}: {message}")]
+    Unavailable {
+        entry: String,
+        store: Store,
+        message: String,
+    },
+
+    #[error("this build cannot read the OS keyring (it needs Linux and the `keyring` feature)")]
+    Unsupported,
+}
+
+pub type Result<T> = std::result::Result<T, KeyringError>;
+
+/// A keyring tokens can be kept in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Store {
+    /// The Secret Service of the desktop session, filed under service
+    /// `distronomicon` and username `<entry>`.
+    SecretService,
+    /// A `user` key described as `distronomicon:<entry>` in the session keyring.
+    Keyutils,
+}
+
+impl fmt::Display for Store {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::SecretService => "Secret Service",
+            Self::Keyutils => "kernel keyring",
+        })
+    }
+}
+
+/// An entry as given to `--github-token-keyring`: `NAME`, looked up in the Secret
+/// Service and then the kernel keyring, or `secret-service:NAME` or
+/// `keyutils:NAME` for just one of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub store: Option<Store>,
+    pub name: String,
+}
+
+impl From<&str> for Entry {
+    fn from(s: &str) -> Self {
+        let (store, name) = match s.split_once(':') {
+            Some(("secret-service", name)) => (Some(Store::SecretService), name),
+            Some(("keyutils", name)) => (Some(Store::Keyutils), name),
+            _ => (None, s),
+        };
+        Self {
+            store,
+            name: name.to_string(),
+        }
+    }
+}
+
+static CACHE: Mutex<BTreeMap<String, Result<String>>> = Mutex::new(BTreeMap::new());
+
+/// The token stored under `entry`, read from the keyring on the first call and
+/// remembered, failure included, for the rest of the run.
+///
+/// # Errors
+///
+/// Returns `KeyringError::NotFound` if no keyring holds the entry, or
+/// `KeyringError::Unavailable` if the keyring cannot be reached or refuses access.
+pub fn token(entry: &str) -> Result<String> {
+    let mut cache = CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(result) = cache.get(entry) {
+        return result.clone();
+    }
+    let parsed = Entry::from(entry);
+    // The Secret Service client blocks on an async runtime of its own, which
+    // cannot start on a thread already driving one.
+    let result = thread::scope(|scope| {
+        scope
+            .spawn(|| lookup(&parsed))
+            .join()
+            .unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+    });
+    cache.insert(entry.to_string(), result.clone());
+    result
+}
+
+fn lookup(entry: &Entry) -> Result<String> {
+    match entry.store {
+        Some(store) => read(store, &entry.name),
+        None => read(Store::SecretService, &entry.name).or_else(|e| {
+            debug!("{e}; trying the kernel keyring");
+            read(Store::Keyutils, &entry.name)
+        }),
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "keyring"))]
+fn read(store: Store, name: &str) -> Result<String> {
+    use keyring::{
+        Credential, Entry as KeyringEntry, Error, keyutils::KeyutilsCredential,
+        secret_service::SsCredential,
+    };
+
+    let unavailable = |e: Error| KeyringError::Unavailable {
+        entry: name.to_string(),
+        store,
+        message: e.to_string(),
+    };
+    let credential: Box<Credential> = match store {
+        Store::SecretService => {
+            Box::new(SsCredential::new_with_target(None, SERVICE, name).map_err(unavailable)?)
+        }
+        Store::Keyutils => Box::new(
+            KeyutilsCredential::new_with_target(Some(&format!("{SERVICE}:{name}")), SERVICE, name)
+                .map_err(unavailable)?,
+        ),
+    };
+    match KeyringEntry::new_with_credential(credential).get_password() {
+        Ok(token) => Ok(token),
+        Err(Error::NoEntry) => Err(KeyringError::NotFound {
+            entry: name.to_string(),
+            store,
+        }),
+        Err(e) => Err(unavailable(e)),
+    }
+}
+
+#[cfg(not(all(target_os = "linux", feature = "keyring")))]
+fn read(_store: Store, _name: &str) -> Result<String> {
+    Err(KeyringError::Unsupported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_entry() {
+        assert_eq!(
+            Entry::from("github.com"),
+            Entry {
+                store: None,
+                name: "github.com".to_string(),
+            }
+        );
+        assert_eq!(
+            Entry::from("keyutils:ghe.example.com"),
+            Entry {
+                store: Some(Store::Keyutils),
+                name: "ghe.example.com".to_string(),
+            }
+        );
+        assert_eq!(
+            Entry::from("secret-service:work").store,
+            Some(Store::SecretService)
+        );
+        // Only a known store is a prefix.
+        assert_eq!(Entry::from("team:ci").name, "team:ci");
+    }
+
+    #[test]
+    fn test_result_is_remembered() {
+        let entry = "test_result_is_remembered";
+        CACHE
+            .lock()
+            .unwrap()
+            .insert(entry.to_string(), Ok("ghp_cached".to_string()));
+        assert_eq!(token(entry).unwrap(), "ghp_cached");
+    }
+}
@@ -1,29 +1,116 @@
+pub mod bin_diff;
+#[cfg(feature = "bitbucket")]
+pub mod bitbucket;
+pub mod change_record;
+#[cfg(feature = "cli")]
 pub mod cli;
+#[cfg(feature = "cluster")]
+pub mod cluster;
+#[cfg(feature = "cli")]
+pub mod completion;
+pub mod config;
+pub mod constraint;
 pub mod download;
+pub mod events;
+pub mod explain;
 pub mod extract;
 pub mod fsops;
+#[cfg(feature = "github")]
+pub mod gh_auth;
 pub mod github;
+pub mod history;
+#[cfg(feature = "json-feed")]
+pub mod json_feed;
+pub mod keyring_auth;
 pub mod lock;
+#[cfg(feature = "github")]
+pub mod login;
+pub mod maintenance;
+pub mod manifest;
+pub mod notices;
+#[cfg(feature = "notify")]
+pub mod notify;
+pub mod pattern;
+pub mod pending;
+pub mod pin;
+pub mod plan;
+pub mod privsep;
+pub mod proxy;
+pub mod release_cache;
 pub mod restart;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod scan;
+pub mod self_update;
 pub mod state;
+pub mod tls;
+pub mod trust;
 pub mod verify;
 pub mod version;
 
 use std::time::Duration;
 
+use reqwest::header::HeaderMap;
+
+#[cfg(any(feature = "cli", feature = "github"))]
 const DEFAULT_GITHUB_HOST: &str = "https://api.github.com";
+#[cfg(feature = "cli")]
 const DEFAULT_INSTALL_ROOT: &str = "/opt";
+#[cfg(feature = "cli")]
+const DEFAULT_RETAIN: u32 = 3;
 const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
 
-/// Builds a configured HTTP client with timeout and user agent.
+/// Builds a configured HTTP client with timeout and user agent, using the proxies
+/// named by the environment.
 ///
 /// # Errors
 ///
 /// Returns an error if the reqwest client builder fails.
 pub fn build_http_client(timeout: Duration) -> anyhow::Result<reqwest::Client> {
-    let client = reqwest::Client::builder()
+    build_pinned_http_client(timeout, &[], proxy::Mode::Env)
+}
+
+/// Builds an HTTP client like [`build_http_client`] that additionally rejects TLS
+/// connections unless the server's chain contains one of `spki_pins`, and takes its
+/// proxies from `proxy_mode`.
+///
+/// With no pins and [`proxy::Mode::Env`] this is identical to [`build_http_client`].
+///
+/// # Errors
+///
+/// Returns an error if the TLS or proxy configuration or the reqwest client builder
+/// fails.
+pub fn build_pinned_http_client(
+    timeout: Duration,
+    spki_pins: &[tls::SpkiPin],
+    proxy_mode: proxy::Mode,
+) -> anyhow::Result<reqwest::Client> {
+    build_http_client_with_headers(timeout, spki_pins, proxy_mode, HeaderMap::new())
+}
+
+/// Builds an HTTP client like [`build_pinned_http_client`] that sends `headers`
+/// with every request.
+///
+/// reqwest drops `Authorization` when a redirect leaves the original host, so
+/// credentials in `headers` do not follow downloads to a storage backend.
+///
+/// # Errors
+///
+/// Returns an error if the TLS or proxy configuration or the reqwest client builder
+/// fails.
+pub fn build_http_client_with_headers(
+    timeout: Duration,
+    spki_pins: &[tls::SpkiPin],
+    proxy_mode: proxy::Mode,
+    headers: HeaderMap,
+) -> anyhow::Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
         .user_agent(concat!("distronomicon/", env!("CARGO_PKG_VERSION")))
-        .timeout(timeout)
-        .build()?;
-    Ok(client)
+        .default_headers(headers)
+        .timeout(timeout);
+    if !spki_pins.is_empty() {
+        builder = builder.tls_backend_preconfigured(tls::pinned_client_config(spki_pins)?);
+    }
+    builder = proxy::configure(builder, proxy_mode)?;
+    Ok(builder.build()?)
 }
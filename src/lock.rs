@@ -1,7 +1,11 @@
 use std::{
     fs::{self, File},
-    io, thread,
-    time::{Duration, Instant},
+    io,
+    os::unix::fs::MetadataExt,
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use camino::{Utf8Path, Utf8PathBuf};
@@ -23,12 +27,112 @@ pub type Result<T> = std::result::Result<T, LockError>;
 pub struct LockGuard {
     file: File,
     path: Utf8PathBuf,
+    queue_dir: Utf8PathBuf,
+    waited: bool,
+}
+
+impl LockGuard {
+    /// Whether another process held the lock or was queued ahead when this one was
+    /// requested, so the app may have changed while waiting.
+    #[must_use]
+    pub fn waited(&self) -> bool {
+        self.waited
+    }
 }
 
 impl Drop for LockGuard {
     fn drop(&mut self) {
+        // Unlink while still holding the lock: a waiter that locks the old file
+        // once it is released then fails the identity check in `try_lock_file`,
+        // instead of holding it while another waiter locks a fresh file.
+        let _ = fs::remove_file(&self.path);
         let _ = self.file.unlock();
+        // Only succeeds once no one else is queued.
+        let _ = fs::remove_dir(&self.queue_dir);
+    }
+}
+
+static NEXT_TICKET: AtomicU64 = AtomicU64::new(0);
+
+/// A waiter's place in the queue for an app's lock.
+///
+/// Tickets are files in the queue directory whose names sort in arrival order. Each
+/// waiter keeps its ticket locked, so tickets left behind by a process that exited
+/// can be told apart from live ones and are removed.
+struct Ticket {
+    file: File,
+    path: Utf8PathBuf,
+    queue_dir: Utf8PathBuf,
+}
+
+impl Ticket {
+    fn take(queue_dir: &Utf8Path) -> io::Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let name = format!(
+            "{nanos:020}.{}.{}",
+            process::id(),
+            NEXT_TICKET.fetch_add(1, Ordering::Relaxed)
+        );
+        let temp_path = queue_dir.join(format!(".{name}"));
+        let path = queue_dir.join(name);
+
+        // A releasing holder removes the queue directory once it is empty, which can
+        // race with creating it here.
+        let mut attempts = 0;
+        let file = loop {
+            fs::create_dir_all(queue_dir)?;
+            match File::create(&temp_path) {
+                Ok(file) => break file,
+                Err(e) if e.kind() == io::ErrorKind::NotFound && attempts < 3 => attempts += 1,
+                Err(e) => return Err(e),
+            }
+        };
+
+        // Lock the ticket before it becomes visible so it is never mistaken for stale.
+        file.lock()?;
+        fs::rename(&temp_path, &path)?;
+
+        Ok(Self {
+            file,
+            path,
+            queue_dir: queue_dir.to_path_buf(),
+        })
+    }
+
+    /// Whether no live ticket is ahead of this one. Stale tickets are removed.
+    fn is_next(&self) -> io::Result<bool> {
+        let mut names = fs::read_dir(&self.queue_dir)?
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| !name.starts_with('.'))
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+
+        for name in names {
+            let path = self.queue_dir.join(name);
+            if path == self.path {
+                return Ok(true);
+            }
+            match File::open(&path) {
+                Ok(file) if file.try_lock().is_ok() => {
+                    let _ = fs::remove_file(&path);
+                }
+                Ok(_) => return Ok(false),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
         let _ = fs::remove_file(&self.path);
+        let _ = self.file.unlock();
     }
 }
 
@@ -39,14 +143,44 @@ fn lock_path(app: &str, lock_root: Option<&Utf8Path>) -> Utf8PathBuf {
     }
 }
 
+fn queue_dir(lock_path: &Utf8Path) -> Utf8PathBuf {
+    lock_path.with_extension("queue")
+}
+
+/// Opens and locks the lock file, returning `None` if another process holds it.
+///
+/// A holder removes the lock file when it releases the lock, so a lock taken on a file
+/// that has since been unlinked or replaced does not count.
+fn try_lock_file(path: &Utf8Path) -> io::Result<Option<File>> {
+    let file = File::create(path)?;
+    if file.try_lock().is_err() {
+        return Ok(None);
+    }
+
+    let locked = file.metadata()?;
+    let current = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if locked.dev() != current.dev() || locked.ino() != current.ino() {
+        return Ok(None);
+    }
+
+    Ok(Some(file))
+}
+
 /// Acquires an exclusive lock for the given application with retry logic.
 ///
-/// Creates or opens a lock file at `<lock_root>/distronomicon-<app>.lock` (or
+/// Creates or opens a lock file at `<lock_root>/<app>/lock` (or
 /// `/var/lock/distronomicon-<app>.lock` if `lock_root` is `None`) and attempts
 /// to acquire an exclusive lock. Uses non-blocking lock attempts with exponential
 /// backoff retry logic.
 ///
-/// If the lock is already held, this function will retry with exponential backoff
+/// Callers wait their turn in first-come, first-served order: each takes a ticket in
+/// a queue directory next to the lock file (`<lock_root>/<app>/lock.queue/`) and only
+/// tries the lock once every earlier caller has acquired it or given up. While it is
+/// not yet this caller's turn, it retries with exponential backoff
 /// (100ms → 200ms → 400ms → 800ms → 1s) until the timeout is reached.
 ///
 /// It's recommended to pass the state directory as `lock_root` to avoid permission
@@ -78,16 +212,23 @@ pub fn acquire(
         fs::create_dir_all(parent)?;
     }
 
-    let file = File::create(&lock_path)?;
+    let queue_dir = queue_dir(&lock_path);
+    let ticket = Ticket::take(&queue_dir)?;
     let start = Instant::now();
     let mut delay = Duration::from_millis(100);
     let max_delay = Duration::from_secs(1);
+    let mut waited = false;
 
     loop {
-        if let Ok(()) = file.try_lock() {
+        if ticket.is_next()?
+            && let Some(file) = try_lock_file(&lock_path)?
+        {
+            drop(ticket);
             return Ok(LockGuard {
                 file,
-                path: lock_path.clone(),
+                path: lock_path,
+                queue_dir,
+                waited,
             });
         }
 
@@ -97,6 +238,7 @@ pub fn acquire(
             });
         }
 
+        waited = true;
         thread::sleep(delay);
         delay = (delay * 2).min(max_delay);
     }
@@ -122,6 +264,42 @@ pub fn unlock(app: &str, lock_root: Option<&Utf8Path>) -> Result<()> {
     }
 }
 
+/// State of an application's lock file as seen by [`status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockStatus {
+    /// No lock file exists.
+    Free,
+    /// Another process currently holds the lock.
+    Held,
+    /// A lock file exists but no process holds it (e.g., left behind by a crash).
+    Stale,
+}
+
+/// Reports whether the lock for the given application is currently held.
+///
+/// Never creates the lock file and never blocks: an existing lock file is probed
+/// with a single non-blocking lock attempt that is released immediately.
+///
+/// # Errors
+///
+/// Returns `LockError::Io` if the lock file exists but cannot be opened.
+pub fn status(app: &str, lock_root: Option<&Utf8Path>) -> Result<LockStatus> {
+    let lock_path = lock_path(app, lock_root);
+
+    let file = match File::open(&lock_path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(LockStatus::Free),
+        Err(e) => return Err(LockError::Io(e)),
+    };
+
+    if file.try_lock().is_ok() {
+        let _ = file.unlock();
+        Ok(LockStatus::Stale)
+    } else {
+        Ok(LockStatus::Held)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{sync::mpsc, thread, time::Duration};
@@ -168,6 +346,58 @@ mod tests {
         handle.join().unwrap();
     }
 
+    #[test]
+    fn test_waiters_acquire_in_arrival_order() {
+        let temp_dir = tempdir().unwrap();
+        let lock_root = temp_dir.path().to_path_buf();
+
+        let guard = acquire("testapp", Some(&lock_root), None).unwrap();
+        assert!(!guard.waited());
+
+        let (tx, rx) = mpsc::channel();
+        let handles = ["first", "second", "third"]
+            .into_iter()
+            .map(|name| {
+                let lock_root = lock_root.clone();
+                let tx = tx.clone();
+                let handle = thread::spawn(move || {
+                    let guard = acquire("testapp", Some(&lock_root), Some(Duration::from_secs(10)))
+                        .unwrap();
+                    tx.send((name, guard.waited())).unwrap();
+                    thread::sleep(Duration::from_millis(20));
+                });
+                // Let each waiter take its ticket before the next one arrives.
+                thread::sleep(Duration::from_millis(20));
+                handle
+            })
+            .collect::<Vec<_>>();
+
+        drop(guard);
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let order = rx.try_iter().collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            vec![("first", true), ("second", true), ("third", true)]
+        );
+        assert!(!lock_root.join("testapp").join("lock.queue").exists());
+    }
+
+    #[test]
+    fn test_stale_ticket_does_not_block() {
+        let temp_dir = tempdir().unwrap();
+        let lock_root = temp_dir.path();
+
+        let queue_dir = lock_root.join("testapp").join("lock.queue");
+        fs::create_dir_all(&queue_dir).unwrap();
+        fs::write(queue_dir.join("00000000000000000000.1.0"), "").unwrap();
+
+        let guard = acquire("testapp", Some(lock_root), Some(Duration::from_millis(100))).unwrap();
+        assert!(!guard.waited());
+    }
+
     #[test]
     fn test_acquire_timeout() {
         let temp_dir = tempdir().unwrap();
@@ -251,4 +481,29 @@ mod tests {
 
         assert!(!lock_file.exists());
     }
+
+    #[test]
+    fn test_status_reports_free_held_and_stale() {
+        let temp_dir = tempdir().unwrap();
+        let lock_root = temp_dir.path();
+
+        assert_eq!(
+            status("testapp", Some(lock_root)).unwrap(),
+            LockStatus::Free
+        );
+
+        let guard = acquire("testapp", Some(lock_root), None).unwrap();
+        assert_eq!(
+            status("testapp", Some(lock_root)).unwrap(),
+            LockStatus::Held
+        );
+        drop(guard);
+
+        let lock_file = lock_root.join("testapp").join("lock");
+        fs::write(&lock_file, "").unwrap();
+        assert_eq!(
+            status("testapp", Some(lock_root)).unwrap(),
+            LockStatus::Stale
+        );
+    }
 }
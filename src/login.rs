@@ -0,0 +1,249 @@
+use std::{fs, io, io::Write as _, os::unix::fs::PermissionsExt as _, time::Duration};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use camino_tempfile::NamedUtf8TempFile;
+use reqwest::{Url, header::ACCEPT};
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::debug;
+
+/// Directory under the state directory holding one token file per GitHub host.
+pub const TOKENS_DIR: &str = "github-tokens";
+
+#[derive(Debug, Error)]
+pub enum LoginError {
+    #[error("HTTP request error: {0}")]
+    Network(#[from] reqwest::Error),
+    #[error("GitHub rejected the device flow request: {error}{}", description.as_deref().map(|d| format!(" ({d})")).unwrap_or_default())]
+    Rejected {
+        error: String,
+        description: Option<String>,
+    },
+    #[error("authorization was denied in the browser")]
+    Denied,
+    #[error("the code expired before authorization was granted; run login again")]
+    Expired,
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, LoginError>;
+
+/// A pending device authorization: the user enters `user_code` at
+/// `verification_uri` while [`poll_token`] waits with `device_code`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DeviceCode {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    /// Seconds until the codes expire.
+    pub expires_in: u64,
+    /// Minimum seconds between polls.
+    pub interval: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Response<T> {
+    Ok(T),
+    Error {
+        error: String,
+        error_description: Option<String>,
+        interval: Option<u64>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct AccessToken {
+    access_token: String,
+}
+
+/// The web origin serving the OAuth endpoints for a GitHub API base URL:
+/// `https://github.com` for the public API, otherwise the API URL's origin, as
+/// for GitHub Enterprise Server (`https://ghe.example.com/api/v3` becomes
+/// `https://ghe.example.com`).
+#[must_use]
+pub fn web_base(api_host: &str) -> String {
+    match Url::parse(api_host) {
+        Ok(url) if url.host_str() == Some("api.github.com") => "https://github.com".to_string(),
+        Ok(url) => url.origin().ascii_serialization(),
+        Err(_) => format!("https://{api_host}"),
+    }
+}
+
+/// Starts the device flow for the OAuth app `client_id`, requesting `scope`.
+///
+/// # Errors
+///
+/// Returns `LoginError::Network` if the request fails, or `LoginError::Rejected`
+/// if GitHub refuses it, such as for an unknown client ID or an app without the
+/// device flow enabled.
+pub async fn request_code(
+    client: &reqwest::Client,
+    web_base: &str,
+    client_id: &str,
+    scope: &str,
+) -> Result<DeviceCode> {
+    let response = client
+        .post(format!("{web_base}/login/device/code"))
+        .header(ACCEPT, "application/json")
+        .json(&serde_json::json!({ "client_id": client_id, "scope": scope }))
+        .send()
+        .await?
+        .error_for_status()?;
+    match response.json::<Response<DeviceCode>>().await? {
+        Response::Ok(code) => Ok(code),
+        Response::Error {
+            error,
+            error_description,
+            ..
+        } => Err(LoginError::Rejected {
+            error,
+            description: error_description,
+        }),
+    }
+}
+
+/// Polls until the user authorizes `code`, returning the access token.
+///
+/// Waits `code.interval` seconds between polls and backs off further when GitHub
+/// asks it to slow down.
+///
+/// # Errors
+///
+/// Returns `LoginError::Denied` if the user cancels, `LoginError::Expired` if the
+/// code expires first, `LoginError::Rejected` for other OAuth errors, or
+/// `LoginError::Network` if a request fails.
+pub async fn poll_token(
+    client: &reqwest::Client,
+    web_base: &str,
+    client_id: &str,
+    code: &DeviceCode,
+) -> Result<String> {
+    let mut interval = Duration::from_secs(code.interval);
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let response = client
+            .post(format!("{web_base}/login/oauth/access_token"))
+            .header(ACCEPT, "application/json")
+            .json(&serde_json::json!({
+                "client_id": client_id,
+                "device_code": code.device_code,
+                "grant_type": "urn:ietf:params:oauth:grant-type:device_code",
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        match response.json::<Response<AccessToken>>().await? {
+            Response::Ok(token) => return Ok(token.access_token),
+            Response::Error { error, .. } if error == "authorization_pending" => {
+                debug!("Authorization pending");
+            }
+            Response::Error {
+                error,
+                interval: next,
+                ..
+            } if error == "slow_down" => {
+                interval = next.map_or(interval + Duration::from_secs(5), Duration::from_secs);
+                debug!("Slowing down polling to every {}s", interval.as_secs());
+            }
+            Response::Error { error, .. } if error == "access_denied" => {
+                return Err(LoginError::Denied);
+            }
+            Response::Error { error, .. } if error == "expired_token" => {
+                return Err(LoginError::Expired);
+            }
+            Response::Error {
+                error,
+                error_description,
+                ..
+            } => {
+                return Err(LoginError::Rejected {
+                    error,
+                    description: error_description,
+                });
+            }
+        }
+    }
+}
+
+/// Path of the token stored by `login` for `hostname`:
+/// `<state_directory>/github-tokens/<hostname>`.
+#[must_use]
+pub fn token_path(state_directory: &Utf8Path, hostname: &str) -> Utf8PathBuf {
+    state_directory.join(TOKENS_DIR).join(hostname)
+}
+
+/// Atomically writes the token for `hostname`, readable only by its owner.
+///
+/// # Errors
+///
+/// Returns `LoginError::Io` if the directory cannot be created or the file cannot
+/// be written, synced, or persisted.
+pub fn save_token(state_directory: &Utf8Path, hostname: &str, token: &str) -> Result<Utf8PathBuf> {
+    let dir = state_directory.join(TOKENS_DIR);
+    fs::create_dir_all(&dir)?;
+    fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))?;
+
+    let path = token_path(state_directory, hostname);
+    let mut temp_file = NamedUtf8TempFile::new_in(&dir)?;
+    temp_file
+        .as_file()
+        .set_permissions(fs::Permissions::from_mode(0o600))?;
+    temp_file.write_all(format!("{token}\n").as_bytes())?;
+    temp_file.as_file().sync_all()?;
+    temp_file.persist(&path).map_err(|e| e.error)?;
+    fs::File::open(&dir)?.sync_all()?;
+
+    Ok(path)
+}
+
+/// Reads the token stored by `login` for `hostname`, if there is one.
+///
+/// # Errors
+///
+/// Returns `LoginError::Io` if the token file exists but cannot be read.
+pub fn load_token(state_directory: &Utf8Path, hostname: &str) -> Result<Option<String>> {
+    match fs::read_to_string(token_path(state_directory, hostname)) {
+        Ok(contents) => Ok(Some(contents.trim().to_string()).filter(|token| !token.is_empty())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use camino_tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_web_base() {
+        assert_eq!(web_base("https://api.github.com"), "https://github.com");
+        assert_eq!(
+            web_base("https://ghe.example.com/api/v3"),
+            "https://ghe.example.com"
+        );
+        assert_eq!(web_base("http://127.0.0.1:8080"), "http://127.0.0.1:8080");
+    }
+
+    #[test]
+    fn test_save_and_load_token() {
+        let temp = tempdir().unwrap();
+
+        assert_eq!(load_token(temp.path(), "github.com").unwrap(), None);
+
+        let path = save_token(temp.path(), "github.com", "gho_saved").unwrap();
+        assert_eq!(path, temp.path().join("github-tokens/github.com"));
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+        assert_eq!(
+            load_token(temp.path(), "github.com").unwrap().as_deref(),
+            Some("gho_saved")
+        );
+        assert_eq!(load_token(temp.path(), "ghe.example.com").unwrap(), None);
+    }
+}
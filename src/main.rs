@@ -1,33 +1,124 @@
-use std::time::Duration;
+use std::{io, process::ExitCode};
 
-use clap::Parser;
-use distronomicon::cli::{self, Args, Commands};
-use tracing::Level;
-use tracing_subscriber::FmtSubscriber;
+use clap::{CommandFactory, Parser};
+use clap_complete::CompleteEnv;
+use distronomicon::{
+    cli::{self, Args, Commands},
+    completion, explain,
+    pin::{self, PinnedError},
+};
+use tracing::{Level, warn};
+use tracing_subscriber::{FmtSubscriber, fmt::writer::BoxMakeWriter};
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> ExitCode {
+    CompleteEnv::with_factory(Args::command)
+        .var(completion::COMPLETE_VAR)
+        .complete();
     let args = Args::parse();
+    let explain = args.explain;
+    let app = args.app.clone();
 
+    match run(args).await {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            if explain && let Some(explanation) = explain::explain(&e, app.as_deref()) {
+                eprint!("\n{explanation}");
+            }
+            if e.is::<PinnedError>() {
+                ExitCode::from(pin::EXIT_PINNED)
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+    }
+}
+
+async fn run(mut args: Args) -> anyhow::Result<ExitCode> {
     let log_level = match args.verbose {
+        _ if args.quiet => Level::ERROR,
         0 => Level::INFO,
         1 => Level::DEBUG,
         _ => Level::TRACE,
     };
 
-    let subscriber = FmtSubscriber::builder().with_max_level(log_level).finish();
+    let writer = if args.machine_output() {
+        BoxMakeWriter::new(io::stderr)
+    } else {
+        BoxMakeWriter::new(io::stdout)
+    };
+    let subscriber = FmtSubscriber::builder()
+        .with_max_level(log_level)
+        .with_writer(writer)
+        .finish();
     tracing::subscriber::set_global_default(subscriber)?;
 
-    let http_client = distronomicon::build_http_client(Duration::from_secs(args.http_timeout))?;
+    // The helper is sent its settings and may not be allowed to read the config.
+    if let Commands::NetworkHelper = args.command {
+        cli::handle_network_helper().await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    for warning in args.flag_warnings() {
+        warn!("{warning}");
+    }
+    args.merge_config()?;
+    for warning in args.apply_sysroot() {
+        warn!("{warning}");
+    }
+    args.resolve_token()?;
+    for warning in args.ownership_warnings() {
+        warn!("{warning}");
+    }
+
+    let http_client = distronomicon::build_pinned_http_client(
+        args.http_timeout(),
+        &args.spki_pins,
+        args.proxy_mode(),
+    )?;
 
+    let mut exit_code = ExitCode::SUCCESS;
     match &args.command {
-        Commands::Check(check_args) => cli::handle_check(&args, check_args, http_client).await?,
+        Commands::Check(check_args) => {
+            exit_code = cli::handle_check(&args, check_args, http_client).await?;
+        }
         Commands::Update(update_args) => {
             cli::handle_update(&args, update_args, http_client).await?;
         }
         Commands::Version => cli::handle_version(&args)?,
+        Commands::Completions(completions_args) => cli::handle_completions(completions_args)?,
         Commands::Unlock(unlock_args) => cli::handle_unlock(&args, unlock_args)?,
+        Commands::Uninstall(uninstall_args) => cli::handle_uninstall(&args, uninstall_args)?,
+        Commands::Adopt(adopt_args) => cli::handle_adopt(&args, adopt_args)?,
+        Commands::Rollback(rollback_args) => cli::handle_rollback(&args, rollback_args)?,
+        Commands::List(list_args) => cli::handle_list(&args, list_args)?,
+        Commands::Gc(gc_args) => cli::handle_gc(&args, gc_args)?,
+        Commands::Prune(prune_args) => cli::handle_prune(&args, prune_args)?,
+        Commands::Maintenance(maintenance_args) => {
+            cli::handle_maintenance(&args, maintenance_args)?;
+        }
+        Commands::History(history_args) => cli::handle_history(&args, history_args)?,
+        Commands::Pin(pin_args) => cli::handle_pin(&args, pin_args)?,
+        Commands::Unpin(unpin_args) => cli::handle_unpin(&args, unpin_args)?,
+        Commands::Login(login_args) => cli::handle_login(&args, login_args, http_client).await?,
+        Commands::ListRemote(list_remote_args) => {
+            cli::handle_list_remote(list_remote_args, http_client).await?;
+        }
+        Commands::Status(status_args) => {
+            cli::handle_status(&args, status_args, http_client).await?;
+        }
+        Commands::Doctor(doctor_args) => {
+            cli::handle_doctor(&args, doctor_args, http_client).await?;
+        }
+        Commands::UpdateAll(update_all_args) => {
+            cli::handle_update_all(&args, update_all_args, http_client).await?;
+        }
+        Commands::SelfUpdate(self_update_args) => {
+            cli::handle_self_update(&args, self_update_args, http_client).await?;
+        }
+        Commands::NetworkHelper => unreachable!("the network helper returns early"),
     }
 
-    Ok(())
+    Ok(exit_code)
 }
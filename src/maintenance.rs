@@ -0,0 +1,285 @@
+use std::{fs, io, time::Duration};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use jiff::Timestamp;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    config::MaintenanceSettings,
+    history::{self, HistoryError},
+};
+
+#[derive(Debug, Error)]
+pub enum MaintenanceError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    History(#[from] HistoryError),
+}
+
+pub type Result<T> = std::result::Result<T, MaintenanceError>;
+
+/// Install history entries kept when no limit is configured.
+pub const DEFAULT_HISTORY_KEEP: u32 = 1000;
+
+/// Days a saved debug response is kept when no limit is configured.
+pub const DEFAULT_DEBUG_MAX_DAYS: u64 = 14;
+
+/// Bytes of saved debug responses kept when no limit is configured.
+pub const DEFAULT_DEBUG_MAX_SIZE: u64 = 16 << 20;
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+fn days(count: u64) -> Duration {
+    Duration::from_secs(DAY.as_secs().saturating_mul(count))
+}
+
+/// How much of an app's state directory [`trim`] keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Newest `history.jsonl` entries kept; the newest one is always kept.
+    pub history_keep: usize,
+    /// Older `history.jsonl` entries are dropped, except the newest.
+    pub history_max_age: Option<Duration>,
+    /// Older files in `debug/` are deleted.
+    pub debug_max_age: Duration,
+    /// Beyond this many bytes, the oldest files in `debug/` are deleted.
+    pub debug_max_size: u64,
+}
+
+impl From<MaintenanceSettings> for Limits {
+    fn from(settings: MaintenanceSettings) -> Self {
+        Self {
+            history_keep: usize::try_from(settings.history_keep.unwrap_or(DEFAULT_HISTORY_KEEP))
+                .unwrap_or(usize::MAX),
+            history_max_age: settings.history_max_days.map(days),
+            debug_max_age: days(settings.debug_max_days.unwrap_or(DEFAULT_DEBUG_MAX_DAYS)),
+            debug_max_size: settings
+                .debug_max_size
+                .map_or(DEFAULT_DEBUG_MAX_SIZE, |size| size.0),
+        }
+    }
+}
+
+/// What [`trim`] removed, or would remove in a dry run.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Report {
+    pub history_removed: usize,
+    pub debug_removed: Vec<Utf8PathBuf>,
+    /// Bytes taken by the removed debug files.
+    pub debug_freed: u64,
+}
+
+impl Report {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.history_removed == 0 && self.debug_removed.is_empty()
+    }
+}
+
+/// Drops install history entries and saved debug responses of `app` beyond
+/// `limits`. With `dry_run`, only reports what would be removed.
+///
+/// # Errors
+///
+/// Returns an error if the history cannot be read or rewritten, or a debug file
+/// cannot be inspected or deleted.
+pub fn trim(
+    state_directory: &Utf8Path,
+    app: &str,
+    limits: &Limits,
+    dry_run: bool,
+) -> Result<Report> {
+    let history_removed = trim_history(state_directory, app, limits, dry_run)?;
+    let (debug_removed, debug_freed) =
+        trim_debug(&state_directory.join(app).join("debug"), limits, dry_run)?;
+
+    Ok(Report {
+        history_removed,
+        debug_removed,
+        debug_freed,
+    })
+}
+
+fn trim_history(
+    state_directory: &Utf8Path,
+    app: &str,
+    limits: &Limits,
+    dry_run: bool,
+) -> Result<usize> {
+    let mut entries = history::load(state_directory, app)?;
+    let total = entries.len();
+    let keep = limits.history_keep.max(1);
+    entries.drain(..total.saturating_sub(keep));
+
+    if let Some(max_age) = limits.history_max_age
+        && let Ok(cutoff) = Timestamp::now().checked_sub(max_age)
+        && let Some(newest) = entries.pop()
+    {
+        entries.retain(|entry| entry.installed_at >= cutoff);
+        entries.push(newest);
+    }
+
+    let removed = total - entries.len();
+    if removed > 0 && !dry_run {
+        history::replace(state_directory, app, &entries)?;
+    }
+    Ok(removed)
+}
+
+fn trim_debug(
+    debug_dir: &Utf8Path,
+    limits: &Limits,
+    dry_run: bool,
+) -> Result<(Vec<Utf8PathBuf>, u64)> {
+    let read_dir = match debug_dir.read_dir_utf8() {
+        Ok(read_dir) => read_dir,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((Vec::new(), 0)),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut files = Vec::new();
+    for entry in read_dir {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            let age = metadata.modified()?.elapsed().unwrap_or_default();
+            files.push((entry.into_path(), age, metadata.len()));
+        }
+    }
+    // Oldest first, so the size budget removes them before newer ones.
+    files.sort_by_key(|(_, age, _)| std::cmp::Reverse(*age));
+
+    let mut remaining: u64 = files.iter().map(|(_, _, size)| size).sum();
+    let mut removed = Vec::new();
+    let mut freed = 0;
+    for (path, age, size) in files {
+        if age <= limits.debug_max_age && remaining <= limits.debug_max_size {
+            continue;
+        }
+        if !dry_run {
+            fs::remove_file(&path)?;
+        }
+        remaining -= size;
+        freed += size;
+        removed.push(path);
+    }
+    Ok((removed, freed))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{File, FileTimes};
+
+    use camino_tempfile::tempdir;
+    use jiff::SignedDuration;
+
+    use super::*;
+
+    fn limits() -> Limits {
+        Limits {
+            history_keep: 1000,
+            history_max_age: None,
+            debug_max_age: DAY,
+            debug_max_size: 1 << 20,
+        }
+    }
+
+    fn entry(tag: &str, age_days: i64) -> history::Entry {
+        history::Entry {
+            tag: tag.to_string(),
+            installed_at: Timestamp::now() - SignedDuration::from_hours(24 * age_days),
+            asset: format!("myapp-{tag}.tar.gz"),
+            sha256: "ab".repeat(32),
+            previous_tag: None,
+        }
+    }
+
+    #[test]
+    fn test_trim_history_keeps_newest_entries() {
+        let temp_dir = tempdir().unwrap();
+        for (tag, age) in [
+            ("v1.0.0", 90),
+            ("v1.1.0", 40),
+            ("v1.2.0", 10),
+            ("v1.3.0", 1),
+        ] {
+            history::append(temp_dir.path(), "myapp", &entry(tag, age)).unwrap();
+        }
+
+        let keep_three = Limits {
+            history_keep: 3,
+            ..limits()
+        };
+        let report = trim(temp_dir.path(), "myapp", &keep_three, true).unwrap();
+        assert_eq!(report.history_removed, 1);
+        assert_eq!(history::load(temp_dir.path(), "myapp").unwrap().len(), 4);
+
+        let month = Limits {
+            history_max_age: Some(days(30)),
+            ..keep_three
+        };
+        let report = trim(temp_dir.path(), "myapp", &month, false).unwrap();
+        assert_eq!(report.history_removed, 2);
+        let tags: Vec<_> = history::load(temp_dir.path(), "myapp")
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.tag)
+            .collect();
+        assert_eq!(tags, ["v1.2.0", "v1.3.0"]);
+
+        // The newest entry survives any age limit.
+        let none = Limits {
+            history_max_age: Some(Duration::ZERO),
+            ..limits()
+        };
+        trim(temp_dir.path(), "myapp", &none, false).unwrap();
+        assert_eq!(history::load(temp_dir.path(), "myapp").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_trim_debug_by_age_and_size() {
+        let temp_dir = tempdir().unwrap();
+        let debug_dir = temp_dir.path().join("myapp").join("debug");
+        fs::create_dir_all(&debug_dir).unwrap();
+        let now = std::time::SystemTime::now();
+        for (name, age_hours, size) in [
+            ("old.json", 48, 10),
+            ("mid.json", 2, 600),
+            ("new.json", 1, 600),
+        ] {
+            let path = debug_dir.join(name);
+            fs::write(&path, vec![b'x'; size]).unwrap();
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_times(
+                    FileTimes::new().set_modified(now - Duration::from_secs(age_hours * 3600)),
+                )
+                .unwrap();
+        }
+
+        let small = Limits {
+            debug_max_size: 1000,
+            ..limits()
+        };
+        let report = trim(temp_dir.path(), "myapp", &small, false).unwrap();
+        assert_eq!(
+            report.debug_removed,
+            [debug_dir.join("old.json"), debug_dir.join("mid.json")]
+        );
+        assert_eq!(report.debug_freed, 610);
+        assert!(debug_dir.join("new.json").exists());
+        assert!(!debug_dir.join("mid.json").exists());
+    }
+
+    #[test]
+    fn test_trim_without_artifacts() {
+        let temp_dir = tempdir().unwrap();
+        let report = trim(temp_dir.path(), "myapp", &limits(), false).unwrap();
+        assert!(report.is_empty());
+    }
+}
@@ -0,0 +1,296 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    io,
+};
+
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+use serde::Serialize;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::verify;
+
+/// Default pattern for the release asset that lists per-file digests.
+pub const DEFAULT_PATTERN: &str = r"^manifest\.json$";
+
+const SHA256_HEX_LENGTH: usize = 64;
+
+#[derive(Debug, Error)]
+pub enum ManifestError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("HTTP request failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("invalid manifest '{name}': {reason}")]
+    Parse { name: String, reason: String },
+}
+
+pub type Result<T> = std::result::Result<T, ManifestError>;
+
+/// Expected lowercase SHA-256 digests keyed by path relative to the release root.
+pub type Manifest = BTreeMap<Utf8PathBuf, String>;
+
+/// How an installed release differs from its manifest.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Drift {
+    /// Number of files whose digest matched.
+    pub verified: usize,
+    pub modified: Vec<Utf8PathBuf>,
+    pub missing: Vec<Utf8PathBuf>,
+}
+
+impl Drift {
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.modified.is_empty() && self.missing.is_empty()
+    }
+}
+
+/// Parses the manifest asset called `name`.
+///
+/// A `.json` asset is an object mapping paths to hex digests (or to objects with a
+/// `sha256` field), optionally nested under a `files` key. Anything else is read as
+/// `sha256sum` output; a file holding a bare digest, such as `myapp.sha256`, applies
+/// to the path named by the asset without its `.sha256` suffix.
+///
+/// # Errors
+///
+/// Returns `ManifestError::Parse` if the asset is malformed, a digest is not 64 hex
+/// characters, or a path is absolute or leaves the release directory.
+pub fn parse(name: &str, text: &str) -> Result<Manifest> {
+    let parse_error = |reason: String| ManifestError::Parse {
+        name: name.to_string(),
+        reason,
+    };
+
+    let entries = if name.ends_with(".json") {
+        json_entries(text).map_err(parse_error)?
+    } else if let Some(path) = name.strip_suffix(".sha256")
+        && is_sha256_hex(text.trim())
+    {
+        vec![(path.to_string(), text.trim().to_string())]
+    } else {
+        verify::parse_checksum_text(text)
+            .map_err(|e| parse_error(e.to_string()))?
+            .into_iter()
+            .map(|(hex, path)| (path, hex))
+            .collect()
+    };
+
+    let mut manifest = Manifest::new();
+    for (path, hex) in entries {
+        if !is_sha256_hex(&hex) {
+            return Err(parse_error(format!(
+                "invalid SHA-256 digest for {path}: {hex}"
+            )));
+        }
+        let path = Utf8PathBuf::from(path.trim_start_matches("./"));
+        if !path
+            .components()
+            .all(|component| matches!(component, Utf8Component::Normal(_)))
+        {
+            return Err(parse_error(format!("unsafe path: {path}")));
+        }
+        manifest.insert(path, hex.to_ascii_lowercase());
+    }
+    Ok(manifest)
+}
+
+fn json_entries(text: &str) -> std::result::Result<Vec<(String, String)>, String> {
+    let value: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let files = match value.get("files") {
+        Some(files) => files,
+        None => &value,
+    };
+    let files = files
+        .as_object()
+        .ok_or_else(|| "expected an object of paths to digests".to_string())?;
+
+    files
+        .iter()
+        .map(|(path, digest)| {
+            let hex = match digest {
+                Value::String(hex) => Some(hex.as_str()),
+                Value::Object(fields) => fields.get("sha256").and_then(Value::as_str),
+                _ => None,
+            }
+            .ok_or_else(|| format!("missing SHA-256 digest for {path}"))?;
+            Ok((path.clone(), hex.to_string()))
+        })
+        .collect()
+}
+
+fn is_sha256_hex(s: &str) -> bool {
+    s.len() == SHA256_HEX_LENGTH && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Downloads a manifest asset and returns its text.
+///
+/// # Errors
+///
+/// Returns `ManifestError::Request` if the request fails or returns a non-2xx status.
+pub async fn fetch_text(url: &str, token: Option<&str>, client: reqwest::Client) -> Result<String> {
+    let mut request = client.get(url).header("Accept", "application/octet-stream");
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    Ok(request.send().await?.error_for_status()?.text().await?)
+}
+
+/// Hashes every file listed in `manifest` under `release_dir` and reports the ones
+/// that are missing or differ.
+///
+/// When every path shares a first component that does not exist in `release_dir`,
+/// it is ignored: it names the archive's top-level directory, which extraction strips.
+///
+/// # Errors
+///
+/// Returns an error if a listed file exists but cannot be read.
+pub fn compare(release_dir: &Utf8Path, manifest: &Manifest) -> Result<Drift> {
+    let root = archive_root(release_dir, manifest);
+    let mut drift = Drift::default();
+
+    for (path, expected) in manifest {
+        let relative = root
+            .and_then(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path);
+        let local = release_dir.join(relative);
+        if !local.is_file() {
+            drift.missing.push(path.clone());
+            continue;
+        }
+        if verify::sha256_file(&local)? == *expected {
+            drift.verified += 1;
+        } else {
+            drift.modified.push(path.clone());
+        }
+    }
+    Ok(drift)
+}
+
+fn archive_root<'a>(release_dir: &Utf8Path, manifest: &'a Manifest) -> Option<&'a str> {
+    let roots: HashSet<_> = manifest
+        .keys()
+        .map(|path| {
+            let mut components = path.components();
+            let first = components.next()?.as_str();
+            components.next().map(|_| first)
+        })
+        .collect();
+
+    match roots.into_iter().collect::<Vec<_>>().as_slice() {
+        [Some(root)] if !release_dir.join(root).exists() => Some(*root),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use camino_tempfile::tempdir;
+
+    use super::*;
+
+    const HELLO_SHA256: &str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+    const EMPTY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    #[test]
+    fn test_parse_json_manifest() {
+        let text = format!(
+            r#"{{"files": {{"bin/myapp": "{HELLO_SHA256}", "./README": {{"sha256": "{}"}}}}}}"#,
+            EMPTY_SHA256.to_uppercase()
+        );
+
+        let manifest = parse("manifest.json", &text).unwrap();
+
+        assert_eq!(
+            manifest,
+            Manifest::from([
+                (Utf8PathBuf::from("README"), EMPTY_SHA256.to_string()),
+                (Utf8PathBuf::from("bin/myapp"), HELLO_SHA256.to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_sha256sum_and_bare_digest() {
+        let sums = format!("{HELLO_SHA256}  bin/myapp\n{EMPTY_SHA256} *README\n");
+        assert_eq!(parse("MANIFEST", &sums).unwrap().len(), 2);
+
+        let manifest = parse("myapp.sha256", &format!("{HELLO_SHA256}\n")).unwrap();
+        assert_eq!(
+            manifest,
+            Manifest::from([(Utf8PathBuf::from("myapp"), HELLO_SHA256.to_string())])
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_unsafe_paths_and_bad_digests() {
+        let escape = format!(r#"{{"../etc/passwd": "{HELLO_SHA256}"}}"#);
+        assert!(matches!(
+            parse("manifest.json", &escape),
+            Err(ManifestError::Parse { .. })
+        ));
+
+        let absolute = format!(r#"{{"/etc/passwd": "{HELLO_SHA256}"}}"#);
+        assert!(parse("manifest.json", &absolute).is_err());
+
+        assert!(parse("manifest.json", r#"{"myapp": "abc"}"#).is_err());
+        assert!(parse("manifest.json", "[]").is_err());
+    }
+
+    #[test]
+    fn test_compare_reports_modified_and_missing() {
+        let temp_dir = tempdir().unwrap();
+        let release_dir = temp_dir.path();
+        fs::create_dir_all(release_dir.join("bin")).unwrap();
+        fs::write(release_dir.join("bin/myapp"), "hello").unwrap();
+        fs::write(release_dir.join("README"), "edited").unwrap();
+
+        let manifest = Manifest::from([
+            (Utf8PathBuf::from("bin/myapp"), HELLO_SHA256.to_string()),
+            (Utf8PathBuf::from("README"), EMPTY_SHA256.to_string()),
+            (Utf8PathBuf::from("LICENSE"), EMPTY_SHA256.to_string()),
+        ]);
+
+        let drift = compare(release_dir, &manifest).unwrap();
+
+        assert_eq!(
+            drift,
+            Drift {
+                verified: 1,
+                modified: vec![Utf8PathBuf::from("README")],
+                missing: vec![Utf8PathBuf::from("LICENSE")],
+            }
+        );
+        assert!(!drift.is_clean());
+    }
+
+    #[test]
+    fn test_compare_ignores_stripped_archive_root() {
+        let temp_dir = tempdir().unwrap();
+        let release_dir = temp_dir.path();
+        fs::create_dir_all(release_dir.join("bin")).unwrap();
+        fs::write(release_dir.join("bin/myapp"), "hello").unwrap();
+        fs::write(release_dir.join("README"), "").unwrap();
+
+        let manifest = Manifest::from([
+            (
+                Utf8PathBuf::from("myapp-1.0/bin/myapp"),
+                HELLO_SHA256.to_string(),
+            ),
+            (
+                Utf8PathBuf::from("myapp-1.0/README"),
+                EMPTY_SHA256.to_string(),
+            ),
+        ]);
+
+        let drift = compare(release_dir, &manifest).unwrap();
+
+        assert!(drift.is_clean());
+        assert_eq!(drift.verified, 2);
+    }
+}
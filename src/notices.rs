@@ -0,0 +1,178 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use thiserror::Error;
+
+/// File name prefixes of license and notice files, compared case-insensitively
+/// and ignoring `-` and `_`.
+const NOTICE_PREFIXES: &[&str] = &[
+    "LICENSE",
+    "LICENCE",
+    "NOTICE",
+    "COPYING",
+    "COPYRIGHT",
+    "UNLICENSE",
+    "THIRDPARTY",
+];
+
+#[derive(Debug, Error)]
+pub enum NoticesError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, NoticesError>;
+
+/// Whether `name` looks like a license or notice file, e.g. `LICENSE`,
+/// `LICENSE-MIT`, `COPYING.txt`, `NOTICE.md`, or `THIRD-PARTY-NOTICES`.
+#[must_use]
+pub fn is_notice_file(name: &str) -> bool {
+    let upper: String = name
+        .chars()
+        .filter(|c| !matches!(c, '-' | '_'))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    NOTICE_PREFIXES
+        .iter()
+        .any(|prefix| upper.starts_with(prefix))
+}
+
+/// Finds every license and notice file under `release_dir`, as paths relative to it.
+///
+/// Symlinks are skipped so that a release cannot point the copy outside itself.
+///
+/// # Errors
+///
+/// Returns `NoticesError::Io` if a directory cannot be read.
+pub fn find(release_dir: &Utf8Path) -> Result<Vec<Utf8PathBuf>> {
+    fn walk(root: &Utf8Path, dir: &Utf8Path, found: &mut Vec<Utf8PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = Utf8PathBuf::try_from(entry.path())
+                .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                walk(root, &path, found)?;
+            } else if file_type.is_file()
+                && path.file_name().is_some_and(is_notice_file)
+                && let Ok(relative) = path.strip_prefix(root)
+            {
+                found.push(relative.to_path_buf());
+            }
+        }
+        Ok(())
+    }
+
+    let mut found = Vec::new();
+    walk(release_dir, release_dir, &mut found)?;
+    found.sort();
+    Ok(found)
+}
+
+/// Copies the license and notice files of `release_dir` to `dest`, keeping their
+/// relative paths, and returns them.
+///
+/// `dest` is replaced as a whole, so reinstalling a release leaves no stale files.
+/// Nothing is written when the release has no such files.
+///
+/// # Errors
+///
+/// Returns `NoticesError::Io` if the release cannot be read or `dest` cannot be written.
+pub fn collect(release_dir: &Utf8Path, dest: &Utf8Path) -> Result<Vec<Utf8PathBuf>> {
+    let files = find(release_dir)?;
+    if files.is_empty() {
+        return Ok(files);
+    }
+
+    let parent = dest.parent().unwrap_or(Utf8Path::new("."));
+    fs::create_dir_all(parent)?;
+    let staging = camino_tempfile::Builder::new()
+        .prefix(".notices-")
+        .tempdir_in(parent)?;
+    for file in &files {
+        let target = staging.path().join(file);
+        if let Some(dir) = target.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::copy(release_dir.join(file), &target)?;
+    }
+
+    match fs::remove_dir_all(dest) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    fs::rename(staging.keep(), dest)?;
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use camino_tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_is_notice_file() {
+        for name in [
+            "LICENSE",
+            "license.txt",
+            "LICENSE-APACHE",
+            "Licence.md",
+            "NOTICE",
+            "COPYING",
+            "ThirdPartyNotices.txt",
+            "THIRD-PARTY-NOTICES",
+        ] {
+            assert!(is_notice_file(name), "{name}");
+        }
+        assert!(!is_notice_file("myapp"));
+        assert!(!is_notice_file("README.md"));
+    }
+
+    #[test]
+    fn test_collect_copies_notices_and_replaces_previous() {
+        let temp_dir = tempdir().unwrap();
+        let release_dir = temp_dir.path().join("release");
+        fs::create_dir_all(release_dir.join("bin")).unwrap();
+        fs::create_dir_all(release_dir.join("vendor/zlib")).unwrap();
+        fs::write(release_dir.join("bin/myapp"), "binary").unwrap();
+        fs::write(release_dir.join("LICENSE-MIT"), "MIT").unwrap();
+        fs::write(release_dir.join("vendor/zlib/LICENSE"), "zlib").unwrap();
+        std::os::unix::fs::symlink("/etc/passwd", release_dir.join("NOTICE")).unwrap();
+
+        let dest = temp_dir.path().join("licenses/myapp/v1.0.0");
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(dest.join("stale"), "old").unwrap();
+
+        let files = collect(&release_dir, &dest).unwrap();
+
+        assert_eq!(
+            files,
+            [
+                Utf8PathBuf::from("LICENSE-MIT"),
+                Utf8PathBuf::from("vendor/zlib/LICENSE")
+            ]
+        );
+        assert_eq!(fs::read_to_string(dest.join("LICENSE-MIT")).unwrap(), "MIT");
+        assert_eq!(
+            fs::read_to_string(dest.join("vendor/zlib/LICENSE")).unwrap(),
+            "zlib"
+        );
+        assert!(!dest.join("stale").exists());
+        assert!(!dest.join("NOTICE").exists());
+    }
+
+    #[test]
+    fn test_collect_without_notices_writes_nothing() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("myapp"), "binary").unwrap();
+        let dest = temp_dir.path().join("licenses/myapp/v1.0.0");
+
+        assert!(collect(temp_dir.path(), &dest).unwrap().is_empty());
+        assert!(!dest.exists());
+    }
+}
@@ -0,0 +1,274 @@
+use std::{fs, io};
+
+use camino::Utf8Path;
+use reqwest::{StatusCode, Url, header::CONTENT_TYPE};
+use serde_json::Value;
+use thiserror::Error;
+
+/// Content type of notification bodies unless configured otherwise.
+pub const DEFAULT_CONTENT_TYPE: &str = "application/json";
+
+#[derive(Debug, Error)]
+pub enum NotifyError {
+    #[error("failed to read template {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("template syntax error at byte {offset}: {message}")]
+    Syntax { offset: usize, message: String },
+
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("endpoint responded with {0}")]
+    Status(StatusCode),
+}
+
+pub type Result<T> = std::result::Result<T, NotifyError>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Value { path: Vec<String>, tojson: bool },
+}
+
+/// A notification body template: text with `{{ name }}` placeholders.
+///
+/// This is the variable subset of Jinja syntax, so templates also render under
+/// minijinja: `{{ tag }}` inserts a value (strings unquoted, null or missing
+/// values as nothing), dotted names reach into objects (`{{ durations_ms.total }}`),
+/// and `{{ error | tojson }}` inserts the value as JSON, quoted and escaped, which is
+/// what JSON payloads need for anything that may hold a quote or newline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Parses a template.
+    ///
+    /// # Errors
+    ///
+    /// Returns `NotifyError::Syntax` for an unclosed `{{`, an empty or invalid name,
+    /// or a filter other than `tojson`.
+    pub fn parse(source: &str) -> Result<Self> {
+        let syntax = |offset, message: &str| NotifyError::Syntax {
+            offset,
+            message: message.to_string(),
+        };
+
+        let mut parts = Vec::new();
+        let mut rest = source;
+        while let Some(start) = rest.find("{{") {
+            let offset = source.len() - rest.len() + start;
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_string()));
+            }
+            let after = &rest[start + 2..];
+            let end = after
+                .find("}}")
+                .ok_or_else(|| syntax(offset, "unclosed '{{'"))?;
+
+            let (name, filter) = match after[..end].split_once('|') {
+                Some((name, filter)) => (name.trim(), Some(filter.trim())),
+                None => (after[..end].trim(), None),
+            };
+            let tojson = match filter {
+                None => false,
+                Some("tojson") => true,
+                Some(other) => {
+                    return Err(syntax(
+                        offset,
+                        &format!("unknown filter '{other}': only 'tojson' is supported"),
+                    ));
+                }
+            };
+            let path: Vec<String> = name.split('.').map(str::to_string).collect();
+            if path.iter().any(|segment| {
+                segment.is_empty()
+                    || !segment
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+            }) {
+                return Err(syntax(offset, &format!("invalid name '{name}'")));
+            }
+            parts.push(Part::Value { path, tojson });
+
+            rest = &after[end + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_string()));
+        }
+
+        Ok(Self { parts })
+    }
+
+    /// Reads and parses the template at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid template.
+    pub fn load(path: &Utf8Path) -> Result<Self> {
+        let source = fs::read_to_string(path).map_err(|source| NotifyError::Io {
+            path: path.to_string(),
+            source,
+        })?;
+        Self::parse(&source)
+    }
+
+    /// Renders the template with values from `context`.
+    #[must_use]
+    pub fn render(&self, context: &Value) -> String {
+        let mut output = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => output.push_str(text),
+                Part::Value { path, tojson } => {
+                    let value = path
+                        .iter()
+                        .try_fold(context, |value, segment| value.get(segment));
+                    match (value, tojson) {
+                        (None, true) => output.push_str("null"),
+                        (None | Some(Value::Null), false) => {}
+                        (Some(Value::String(s)), false) => output.push_str(s),
+                        (Some(value), _) => output.push_str(&value.to_string()),
+                    }
+                }
+            }
+        }
+        output
+    }
+}
+
+/// Where and how to send the outcome of an update.
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    pub url: Url,
+    /// Renders the body; without one, the context itself is sent as JSON.
+    pub template: Option<Template>,
+    pub content_type: String,
+}
+
+impl Notifier {
+    /// POSTs `context`, rendered through the template if there is one, to the
+    /// notification URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the endpoint responds with a
+    /// non-2xx status.
+    pub async fn send(&self, client: &reqwest::Client, context: &Value) -> Result<()> {
+        let body = match &self.template {
+            Some(template) => template.render(context),
+            None => context.to_string(),
+        };
+        let response = client
+            .post(self.url.clone())
+            .header(CONTENT_TYPE, &self.content_type)
+            .body(body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(NotifyError::Status(response.status()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{body_string, header, method, path},
+    };
+
+    use super::*;
+
+    fn context() -> Value {
+        serde_json::json!({
+            "app": "myapp",
+            "status": "failed",
+            "tag": null,
+            "error": "checksum mismatch for \"myapp.tar.gz\"",
+            "durations_ms": {"total": 1250}
+        })
+    }
+
+    #[test]
+    fn test_render_values() {
+        let template = Template::parse(
+            r#"{"summary": "{{app}} {{ status }} {{ tag }}", "error": {{ error | tojson }}, "tag": {{tag|tojson}}, "ms": {{ durations_ms.total }}, "host": {{ host | tojson }}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            template.render(&context()),
+            r#"{"summary": "myapp failed ", "error": "checksum mismatch for \"myapp.tar.gz\"", "tag": null, "ms": 1250, "host": null}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (source, expected) in [
+            (
+                "{{ app }} {{ tag",
+                "template syntax error at byte 10: unclosed '{{'",
+            ),
+            ("{{ }}", "template syntax error at byte 0: invalid name ''"),
+            (
+                "x{{ app | upper }}",
+                "template syntax error at byte 1: unknown filter 'upper': only 'tojson' is supported",
+            ),
+        ] {
+            assert_eq!(Template::parse(source).unwrap_err().to_string(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_rendered_body() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hooks/deploy"))
+            .and(header("content-type", "text/plain"))
+            .and(body_string("myapp failed"))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let notifier = Notifier {
+            url: Url::parse(&format!("{}/hooks/deploy", mock_server.uri())).unwrap(),
+            template: Some(Template::parse("{{ app }} {{ status }}").unwrap()),
+            content_type: "text/plain".to_string(),
+        };
+
+        notifier
+            .send(&reqwest::Client::new(), &context())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_send_rejected() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400))
+            .mount(&mock_server)
+            .await;
+
+        let notifier = Notifier {
+            url: Url::parse(&mock_server.uri()).unwrap(),
+            template: None,
+            content_type: DEFAULT_CONTENT_TYPE.to_string(),
+        };
+
+        let error = notifier
+            .send(&reqwest::Client::new(), &context())
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "endpoint responded with 400 Bad Request");
+    }
+}
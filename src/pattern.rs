@@ -0,0 +1,415 @@
+use std::{
+    borrow::Cow,
+    env::consts::{ARCH, OS},
+    fmt, fs,
+    str::FromStr,
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::extract::ArchiveFormat;
+
+/// Archive extensions that `extract` can unpack.
+pub(crate) const ARCHIVE_EXTENSIONS: &str = r"tar\.gz|tgz|tar\.bz2|tbz2|tar\.xz|txz|tar\.zst|zip";
+
+/// The platform release assets are chosen for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Platform {
+    pub os: &'static str,
+    pub arch: &'static str,
+    /// The C library on Linux.
+    pub libc: Option<Libc>,
+}
+
+/// A C library that Linux builds are linked against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Libc {
+    Gnu,
+    Musl,
+}
+
+/// Returned when parsing a C library other than `gnu` or `musl`.
+#[derive(Debug, Error)]
+#[error("unknown C library '{0}'; expected gnu or musl")]
+pub struct UnknownLibc(String);
+
+impl Libc {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Gnu => "gnu",
+            Self::Musl => "musl",
+        }
+    }
+}
+
+impl FromStr for Libc {
+    type Err = UnknownLibc;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gnu" | "glibc" => Ok(Self::Gnu),
+            "musl" => Ok(Self::Musl),
+            other => Err(UnknownLibc(other.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Libc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Tokens naming each OS in asset names.
+const OS_TOKENS: &[(&str, &[&str])] = &[
+    ("linux", &["linux"]),
+    ("macos", &["macos", "darwin", "apple", "osx", "mac"]),
+    (
+        "windows",
+        &["windows", "win", "win32", "win64", "msvc", "mingw"],
+    ),
+    ("freebsd", &["freebsd"]),
+    ("netbsd", &["netbsd"]),
+    ("openbsd", &["openbsd"]),
+    ("android", &["android"]),
+    ("illumos", &["illumos", "solaris"]),
+];
+
+/// Tokens naming each architecture in asset names, after `x86_64` and `x86-64`
+/// were rewritten to `amd64`.
+const ARCH_TOKENS: &[(&str, &[&str])] = &[
+    ("x86_64", &["amd64", "x64"]),
+    ("aarch64", &["aarch64", "arm64"]),
+    ("x86", &["x86", "i386", "i486", "i586", "i686", "386"]),
+    (
+        "arm",
+        &[
+            "arm", "armv6", "armv6l", "armv7", "armv7l", "armhf", "armel",
+        ],
+    ),
+    ("riscv64", &["riscv64", "riscv64gc"]),
+    (
+        "powerpc64",
+        &["ppc64", "ppc64le", "powerpc64", "powerpc64le"],
+    ),
+    ("s390x", &["s390x"]),
+    ("loongarch64", &["loongarch64", "loong64"]),
+];
+
+/// Tokens naming each C library, including the ARM ABI suffixes of Rust targets.
+const LIBC_TOKENS: &[(&str, &[&str])] = &[
+    ("gnu", &["gnu", "glibc", "gnueabi", "gnueabihf"]),
+    ("musl", &["musl", "musleabi", "musleabihf"]),
+];
+
+/// The words of an asset name, lowercased, with `x86_64` and `x86-64` kept
+/// whole as `amd64`.
+fn words(name: &str) -> String {
+    name.to_ascii_lowercase()
+        .replace("x86_64", "amd64")
+        .replace("x86-64", "amd64")
+}
+
+fn tokens(words: &str) -> Vec<&str> {
+    words.split(|c: char| !c.is_ascii_alphanumeric()).collect()
+}
+
+/// Which of `families` the `tokens` name: `Some(true)` if `own` is among them,
+/// `Some(false)` if only others are, and `None` if none is.
+fn names(families: &[(&str, &[&str])], own: &str, tokens: &[&str]) -> Option<bool> {
+    let named = |family: &(&str, &[&str])| family.1.iter().any(|alias| tokens.contains(alias));
+    if families
+        .iter()
+        .any(|family| family.0 == own && named(family))
+    {
+        Some(true)
+    } else if families.iter().any(named) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+impl Platform {
+    /// The platform of this host.
+    #[must_use]
+    pub fn host() -> Self {
+        Self {
+            os: OS,
+            arch: ARCH,
+            libc: host_libc(),
+        }
+    }
+
+    /// This platform with its C library replaced by `libc`, when given.
+    #[must_use]
+    pub fn with_libc(self, libc: Option<Libc>) -> Self {
+        Self {
+            libc: libc.or(self.libc),
+            ..self
+        }
+    }
+
+    /// Whether the asset `name` names this platform's C library.
+    #[must_use]
+    pub fn names_libc(&self, name: &str) -> bool {
+        let words = words(name);
+        self.libc
+            .is_some_and(|libc| names(LIBC_TOKENS, libc.as_str(), &tokens(&words)) == Some(true))
+    }
+
+    /// Scores how well the asset `name` suits this platform, or `None` when it
+    /// cannot be installed here: it is no archive `extract` unpacks (checksums,
+    /// signatures, and packages are not), or it names another OS or
+    /// architecture, or glibc on a musl host.
+    ///
+    /// Naming the OS, the architecture (or `universal` on macOS), and the C
+    /// library each raise the score, and a `.tar.gz` or `.zip` beats other
+    /// archives, so an asset that names nothing only wins when it is the sole
+    /// candidate.
+    #[must_use]
+    pub fn score(&self, name: &str) -> Option<u32> {
+        let format = ArchiveFormat::from_file_name(name)?;
+        let words = words(name);
+        let tokens = tokens(&words);
+
+        let mut score = 0;
+        match names(OS_TOKENS, self.os, &tokens) {
+            Some(true) => score += 4,
+            Some(false) => return None,
+            None => {}
+        }
+        let universal = self.os == "macos"
+            && tokens
+                .iter()
+                .any(|token| matches!(*token, "universal" | "universal2"));
+        match names(ARCH_TOKENS, self.arch, &tokens) {
+            _ if universal => score += 4,
+            Some(true) => score += 4,
+            Some(false) => return None,
+            None => {}
+        }
+        match self
+            .libc
+            .and_then(|libc| Some((libc, names(LIBC_TOKENS, libc.as_str(), &tokens)?)))
+        {
+            Some((_, true)) => score += 2,
+            // glibc builds do not run on musl; static musl builds run anywhere.
+            Some((Libc::Musl, false)) => return None,
+            _ => {}
+        }
+        if matches!(format, ArchiveFormat::TarGzip | ArchiveFormat::Zip) {
+            score += 1;
+        }
+        Some(score)
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.os, self.arch)?;
+        match self.libc {
+            Some(libc) => write!(f, " ({libc})"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Expands the platform placeholders of an asset pattern for the host: `{os}`,
+/// `{arch}`, and `{libc}`, plus `{version}`. See [`expand_for`].
+#[must_use]
+pub fn expand(pattern: &str) -> Cow<'_, str> {
+    expand_for(pattern, &Platform::host())
+}
+
+/// Expands the placeholders of an asset pattern for the given platform.
+///
+/// `{os}` and `{arch}` match the OS and architecture with their usual aliases
+/// (`amd64` for `x86_64`, `darwin` for `macos`, ...), and `{libc}` matches the
+/// C library (`gnu`, also written `glibc`, or `musl`), or nothing when the
+/// platform has none. `{version}` matches any version, starting with a digit
+/// after an optional `v`. Other text is left as it is, so regex repetitions such
+/// as `{2}` still work.
+#[must_use]
+pub fn expand_for<'a>(pattern: &'a str, platform: &Platform) -> Cow<'a, str> {
+    if !pattern.contains('{') {
+        return Cow::Borrowed(pattern);
+    }
+    let libc = match platform.libc {
+        Some(Libc::Gnu) => "(?:gnu|glibc)",
+        Some(Libc::Musl) => "musl",
+        None => "",
+    };
+    Cow::Owned(
+        pattern
+            .replace(
+                "{os}",
+                &format!("(?:{})", os_aliases(platform.os).join("|")),
+            )
+            .replace(
+                "{arch}",
+                &format!("(?:{})", arch_aliases(platform.arch).join("|")),
+            )
+            .replace("{libc}", libc)
+            .replace("{version}", r"v?\d[^/]*?"),
+    )
+}
+
+/// The C library of this Linux host: musl when its dynamic loader is installed,
+/// otherwise glibc. `None` on other systems.
+fn host_libc() -> Option<Libc> {
+    if OS != "linux" {
+        return None;
+    }
+    let musl = fs::read_dir("/lib").is_ok_and(|entries| {
+        entries
+            .flatten()
+            .any(|entry| entry.file_name().to_string_lossy().starts_with("ld-musl-"))
+    });
+    Some(if musl { Libc::Musl } else { Libc::Gnu })
+}
+
+fn os_aliases(os: &str) -> Vec<String> {
+    match os {
+        "macos" => vec![
+            "darwin".into(),
+            "macos".into(),
+            "apple".into(),
+            "osx".into(),
+        ],
+        other => vec![regex::escape(other)],
+    }
+}
+
+fn arch_aliases(arch: &str) -> Vec<String> {
+    match arch {
+        "x86_64" => vec![
+            "x86_64".into(),
+            "x86-64".into(),
+            "amd64".into(),
+            "x64".into(),
+        ],
+        "aarch64" => vec!["aarch64".into(), "arm64".into()],
+        "x86" => vec!["i[3-6]86".into(), "x86".into(), "386".into()],
+        other => vec![regex::escape(other)],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::*;
+
+    const LINUX_GNU: Platform = Platform {
+        os: "linux",
+        arch: "x86_64",
+        libc: Some(Libc::Gnu),
+    };
+
+    #[test]
+    fn test_scores_common_naming_schemes() {
+        for name in [
+            "myapp-1.2.0-linux-amd64.tar.gz",
+            "myapp_1.2.0_Linux_x86_64.tar.gz",
+            "myapp-v1.2.0-x86_64-unknown-linux-musl.tar.gz",
+            "rg-linux-x64.zip",
+            "MyApp.linux.amd64.tar.zst",
+            "myapp-1.2.0.tar.gz",
+        ] {
+            assert!(
+                LINUX_GNU.score(name).is_some(),
+                "{name} should be installable"
+            );
+        }
+
+        for name in [
+            "myapp-1.2.0-linux-amd64.tar.gz.sha256",
+            "myapp-1.2.0-linux-amd64.tar.gz.sig",
+            "myapp-1.2.0-linux-arm64.tar.gz",
+            "myapp-1.2.0-linux-i686.tar.gz",
+            "myapp-1.2.0-darwin-amd64.tar.gz",
+            "myapp-1.2.0-x86_64-pc-windows-msvc.zip",
+            "myapp-1.2.0-linux-amd64.deb",
+            "SHA256SUMS",
+        ] {
+            assert_eq!(LINUX_GNU.score(name), None, "{name} should be rejected");
+        }
+    }
+
+    #[test]
+    fn test_prefers_closest_match() {
+        let score = |name| LINUX_GNU.score(name).unwrap();
+
+        assert!(score("myapp-linux-amd64.tar.gz") > score("myapp-linux.tar.gz"));
+        assert!(score("myapp-linux.tar.gz") > score("myapp.tar.gz"));
+        assert!(
+            score("myapp-x86_64-unknown-linux-gnu.tar.gz")
+                > score("myapp-x86_64-unknown-linux-musl.tar.gz")
+        );
+        assert!(score("myapp-linux-amd64.tar.gz") > score("myapp-linux-amd64.tar.xz"));
+
+        let musl = Platform {
+            libc: Some(Libc::Musl),
+            ..LINUX_GNU
+        };
+        assert_eq!(musl.score("myapp-x86_64-unknown-linux-gnu.tar.gz"), None);
+        assert!(
+            musl.score("myapp-x86_64-unknown-linux-musl.tar.gz")
+                .is_some()
+        );
+        assert!(musl.names_libc("myapp-armv7-unknown-linux-musleabihf.tar.gz"));
+        assert!(!musl.names_libc("myapp-x86_64-unknown-linux-gnu.tar.gz"));
+        assert_eq!(LINUX_GNU.with_libc(Some(Libc::Musl)), musl);
+        assert_eq!(musl.with_libc(None), musl);
+        assert_eq!("glibc".parse::<Libc>().unwrap(), Libc::Gnu);
+        assert!("uclibc".parse::<Libc>().is_err());
+    }
+
+    #[test]
+    fn test_macos_aliases() {
+        let mac = Platform {
+            os: "macos",
+            arch: "aarch64",
+            libc: None,
+        };
+
+        assert!(
+            mac.score("tool-1.0.0-aarch64-apple-darwin.tar.gz")
+                .is_some()
+        );
+        assert!(mac.score("tool_darwin_arm64.zip").is_some());
+        assert!(mac.score("tool-macos-universal.tar.gz").is_some());
+        assert_eq!(mac.score("tool_linux_arm64.zip"), None);
+        assert!(!mac.names_libc("tool-aarch64-apple-darwin-gnu.tar.gz"));
+        assert_eq!(mac.to_string(), "macos/aarch64");
+        assert_eq!(LINUX_GNU.to_string(), "linux/x86_64 (gnu)");
+    }
+
+    #[test]
+    fn test_expand_placeholders() {
+        let pattern = expand_for(
+            r"^myapp-{version}-{os}-{arch}-{libc}\.tar\.gz$",
+            &Platform {
+                os: "linux",
+                arch: "aarch64",
+                libc: Some(Libc::Musl),
+            },
+        );
+        let pattern = Regex::new(&pattern).unwrap();
+
+        assert!(pattern.is_match("myapp-1.2.0-linux-arm64-musl.tar.gz"));
+        assert!(pattern.is_match("myapp-v1.2.0-rc.1-linux-aarch64-musl.tar.gz"));
+        assert!(!pattern.is_match("myapp-1.2.0-linux-arm64-gnu.tar.gz"));
+        assert!(!pattern.is_match("myapp-1.2.0-linux-amd64-musl.tar.gz"));
+
+        let gnu = Regex::new(&expand_for("{arch}-unknown-{os}-{libc}", &LINUX_GNU)).unwrap();
+        assert!(gnu.is_match("x86_64-unknown-linux-gnu"));
+        assert!(gnu.is_match("amd64-unknown-linux-glibc"));
+
+        assert_eq!(expand_for(r"^a{2}\.zip$", &LINUX_GNU), r"^a{2}\.zip$");
+        assert!(matches!(expand_for("plain", &LINUX_GNU), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,127 @@
+use std::{fs, io};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{fsops, history, state::State};
+
+#[derive(Debug, Error)]
+pub enum PendingError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("invalid pending update file: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, PendingError>;
+
+/// The state and install record an update will commit once its release is active.
+///
+/// Written before the `bin/` symlinks are switched and removed once the state and
+/// history have been saved, so an update interrupted in between can be finished (or
+/// discarded) by the next run instead of leaving the new release active under stale
+/// state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pending {
+    pub state: State,
+    pub history: history::Entry,
+}
+
+/// Path of the staged update for `app`: `<state_directory>/<app>/pending.json`.
+#[must_use]
+pub fn path(state_directory: &Utf8Path, app: &str) -> Utf8PathBuf {
+    state_directory.join(app).join("pending.json")
+}
+
+/// Loads the staged update for `app`, returning `Ok(None)` if there is none.
+///
+/// # Errors
+///
+/// Returns an error if the file exists but cannot be read or parsed.
+pub fn load(state_directory: &Utf8Path, app: &str) -> Result<Option<Pending>> {
+    match fs::read_to_string(path(state_directory, app)) {
+        Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Atomically writes the staged update for `app`, replacing any previous one.
+///
+/// # Errors
+///
+/// Returns an error if the state directory cannot be created or the file cannot be
+/// written, synced, or persisted.
+pub fn save(state_directory: &Utf8Path, app: &str, pending: &Pending) -> Result<()> {
+    fsops::write_json_atomic(path(state_directory, app), pending)
+}
+
+/// Removes the staged update for `app`, returning whether one existed.
+///
+/// # Errors
+///
+/// Returns an error if the file exists but cannot be removed.
+pub fn remove(state_directory: &Utf8Path, app: &str) -> Result<bool> {
+    match fs::remove_file(path(state_directory, app)) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches;
+
+    use camino_tempfile::tempdir;
+    use jiff::Timestamp;
+
+    use super::*;
+
+    #[test]
+    fn test_save_load_remove() {
+        let temp_dir = tempdir().unwrap();
+        let state_dir = temp_dir.path();
+        assert_eq!(load(state_dir, "myapp").unwrap(), None);
+
+        let installed_at = Timestamp::from_second(1_000_000_000).unwrap();
+        let pending = Pending {
+            state: State {
+                latest_tag: "v1.1.0".to_string(),
+                etag: "\"abc\"".to_string(),
+                last_modified: "Sun, 09 Sep 2001 01:46:40 GMT".to_string(),
+                installed_at,
+                previous_tag: Some("v1.0.0".to_string()),
+                repo: Some("owner/myapp".to_string()),
+                host: Some("https://api.github.com".to_string()),
+            },
+            history: history::Entry {
+                tag: "v1.1.0".to_string(),
+                installed_at,
+                asset: "myapp-v1.1.0.tar.gz".to_string(),
+                sha256: "ab".repeat(32),
+                previous_tag: Some("v1.0.0".to_string()),
+            },
+        };
+        save(state_dir, "myapp", &pending).unwrap();
+
+        assert_eq!(load(state_dir, "myapp").unwrap(), Some(pending));
+        assert!(remove(state_dir, "myapp").unwrap());
+        assert!(!remove(state_dir, "myapp").unwrap());
+    }
+
+    #[test]
+    fn test_load_invalid_pending() {
+        let temp_dir = tempdir().unwrap();
+        let pending_path = path(temp_dir.path(), "myapp");
+        fs::create_dir_all(pending_path.parent().unwrap()).unwrap();
+        fs::write(&pending_path, "not json").unwrap();
+
+        assert_matches!(
+            load(temp_dir.path(), "myapp"),
+            Err(PendingError::Serialization(_))
+        );
+    }
+}
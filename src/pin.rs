@@ -0,0 +1,134 @@
+use std::{fs, io};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::fsops;
+
+/// Exit status used when `update` refuses to run because the app is pinned.
+pub const EXIT_PINNED: u8 = 3;
+
+#[derive(Debug, Error)]
+pub enum PinError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("invalid pin file: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, PinError>;
+
+/// A hold on an app at a specific release, written by the `pin` subcommand.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pin {
+    pub tag: String,
+    pub pinned_at: Timestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl Pin {
+    /// Describes the pin as `<tag>` or `<tag> (<reason>)` for messages.
+    #[must_use]
+    pub fn describe(&self) -> String {
+        match &self.reason {
+            Some(reason) => format!("{} ({reason})", self.tag),
+            None => self.tag.clone(),
+        }
+    }
+}
+
+/// Returned by `update` when the app is pinned, so callers can exit with [`EXIT_PINNED`].
+#[derive(Debug, Error)]
+#[error("{app} is pinned at {}; run `unpin` to allow updates", pin.describe())]
+pub struct PinnedError {
+    pub app: String,
+    pub pin: Pin,
+}
+
+/// Path of the pin marker for `app`: `<state_directory>/<app>/pin.json`.
+#[must_use]
+pub fn path(state_directory: &Utf8Path, app: &str) -> Utf8PathBuf {
+    state_directory.join(app).join("pin.json")
+}
+
+/// Loads the pin for `app`, returning `Ok(None)` if it is not pinned.
+///
+/// # Errors
+///
+/// Returns an error if the pin file exists but cannot be read or parsed.
+pub fn load(state_directory: &Utf8Path, app: &str) -> Result<Option<Pin>> {
+    match fs::read_to_string(path(state_directory, app)) {
+        Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Atomically writes the pin for `app`, replacing any existing pin.
+///
+/// # Errors
+///
+/// Returns an error if the state directory cannot be created or the file cannot be
+/// written, synced, or persisted.
+pub fn save(state_directory: &Utf8Path, app: &str, pin: &Pin) -> Result<()> {
+    fsops::write_json_atomic(path(state_directory, app), pin)
+}
+
+/// Removes the pin for `app`, returning whether it was pinned.
+///
+/// # Errors
+///
+/// Returns an error if the pin file exists but cannot be removed.
+pub fn remove(state_directory: &Utf8Path, app: &str) -> Result<bool> {
+    match fs::remove_file(path(state_directory, app)) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches;
+
+    use camino_tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn test_save_load_remove() {
+        let temp_dir = tempdir().unwrap();
+        let state_dir = temp_dir.path();
+        assert_eq!(load(state_dir, "myapp").unwrap(), None);
+
+        let pin = Pin {
+            tag: "v1.0.0".to_string(),
+            pinned_at: Timestamp::from_second(1_000_000_000).unwrap(),
+            reason: Some("change freeze".to_string()),
+        };
+        save(state_dir, "myapp", &pin).unwrap();
+
+        assert_eq!(load(state_dir, "myapp").unwrap(), Some(pin.clone()));
+        assert_eq!(pin.describe(), "v1.0.0 (change freeze)");
+        assert!(remove(state_dir, "myapp").unwrap());
+        assert!(!remove(state_dir, "myapp").unwrap());
+        assert_eq!(load(state_dir, "myapp").unwrap(), None);
+    }
+
+    #[test]
+    fn test_load_invalid_pin() {
+        let temp_dir = tempdir().unwrap();
+        let pin_path = path(temp_dir.path(), "myapp");
+        fs::create_dir_all(pin_path.parent().unwrap()).unwrap();
+        fs::write(&pin_path, "not json").unwrap();
+
+        assert_matches!(
+            load(temp_dir.path(), "myapp"),
+            Err(PinError::Serialization(_))
+        );
+    }
+}
@@ -0,0 +1,356 @@
+use std::{fmt::Display, fs, io};
+
+use camino::Utf8Path;
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{config::ReleaseSubdir, fsops};
+
+/// Version of the plan format written by this build.
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum PlanError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("invalid plan file: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("unsupported plan format version {0} (expected {FORMAT_VERSION})")]
+    UnsupportedVersion(u32),
+
+    #[error("plan no longer applies: {0}")]
+    Diverged(String),
+
+    #[error("invalid plan file: extra asset {0} has no dest")]
+    MissingDest(String),
+}
+
+pub type Result<T> = std::result::Result<T, PlanError>;
+
+/// What `update` will do, written by `update --plan` for review and carried out
+/// by `update --apply-plan`, which fails if anything it lists has changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Plan {
+    pub format_version: u32,
+    pub app: String,
+    pub repo: String,
+    pub host: String,
+    pub created_at: Timestamp,
+    /// The release installed when the plan was made, which must still be.
+    pub from_tag: Option<String>,
+    pub tag: String,
+    pub asset: PlannedAsset,
+    #[serde(default)]
+    pub extra_assets: Vec<PlannedAsset>,
+    pub restart_command: Option<String>,
+    pub health_command: Option<String>,
+    /// Releases deleted after installing, newest first. Nothing else is deleted.
+    #[serde(default)]
+    pub prune: Vec<String>,
+    pub steps: Vec<Step>,
+}
+
+/// An asset the plan downloads.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedAsset {
+    pub name: String,
+    pub size: u64,
+    pub url: String,
+    /// SHA-256 the asset must have, when a checksum file or feed gives it.
+    pub sha256: Option<String>,
+    /// Directory inside the release an extra asset is extracted into, which
+    /// every extra asset must have.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dest: Option<ReleaseSubdir>,
+}
+
+/// One step of a plan: what it acts on and what it produces.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Step {
+    pub action: String,
+    pub input: String,
+    pub output: String,
+}
+
+impl Step {
+    #[must_use]
+    pub fn new(action: &str, input: impl Display, output: impl Display) -> Self {
+        Self {
+            action: action.to_string(),
+            input: input.to_string(),
+            output: output.to_string(),
+        }
+    }
+}
+
+fn expect(what: &str, planned: Option<&str>, actual: Option<&str>) -> Result<()> {
+    if planned == actual {
+        Ok(())
+    } else {
+        Err(PlanError::Diverged(format!(
+            "{what} is {}, but the plan expects {}",
+            actual.unwrap_or("(none)"),
+            planned.unwrap_or("(none)")
+        )))
+    }
+}
+
+impl Plan {
+    /// Checks that the plan is for `app` from `repo` on `host`, and that the
+    /// release it was made against is still installed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlanError::Diverged`] naming the first difference.
+    pub fn check_source(
+        &self,
+        app: &str,
+        repo: &str,
+        host: &str,
+        current_tag: Option<&str>,
+    ) -> Result<()> {
+        expect("the app", Some(&self.app), Some(app))?;
+        expect("the repository", Some(&self.repo), Some(repo))?;
+        expect("the host", Some(&self.host), Some(host))?;
+        expect(
+            "the installed release",
+            self.from_tag.as_deref(),
+            current_tag,
+        )
+    }
+
+    /// Checks that the restart and health commands that will run are the ones
+    /// the plan lists.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlanError::Diverged`] naming the first difference.
+    pub fn check_commands(&self, restart: Option<&str>, health: Option<&str>) -> Result<()> {
+        expect(
+            "the restart command",
+            self.restart_command.as_deref(),
+            restart,
+        )?;
+        expect("the health command", self.health_command.as_deref(), health)
+    }
+
+    /// The combined size of the planned assets, which stands in for the size of
+    /// the release when choosing what to prune.
+    #[must_use]
+    pub fn incoming_size(&self) -> u64 {
+        self.asset.size
+            + self
+                .extra_assets
+                .iter()
+                .map(|extra| extra.size)
+                .sum::<u64>()
+    }
+
+    /// Checks that the releases retention would prune are the ones the plan lists.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlanError::Diverged`] naming both lists.
+    pub fn check_prune(&self, prune: &[String]) -> Result<()> {
+        let describe = |tags: &[String]| (!tags.is_empty()).then(|| tags.join(", "));
+        expect(
+            "the prune list",
+            describe(&self.prune).as_deref(),
+            describe(prune).as_deref(),
+        )
+    }
+}
+
+impl PlannedAsset {
+    /// The directory inside the release this extra asset is extracted into.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlanError::MissingDest`] if the plan gives none.
+    pub fn dest(&self) -> Result<&ReleaseSubdir> {
+        self.dest
+            .as_ref()
+            .ok_or_else(|| PlanError::MissingDest(self.name.clone()))
+    }
+
+    /// Checks a downloaded asset against the planned one: its name and size, and
+    /// its SHA-256 when the plan records one.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PlanError::Diverged`] naming the first difference.
+    pub fn check(&self, name: &str, size: u64, sha256: &str) -> Result<()> {
+        expect("the asset", Some(&self.name), Some(name))?;
+        if size != self.size {
+            return Err(PlanError::Diverged(format!(
+                "{name} is {size} bytes, but the plan expects {}",
+                self.size
+            )));
+        }
+        match &self.sha256 {
+            Some(planned) if !planned.eq_ignore_ascii_case(sha256) => Err(PlanError::Diverged(
+                format!("{name} has sha256 {sha256}, but the plan expects {planned}"),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Reads the plan at `path`.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or parsed, was written in another
+/// format version, or gives an extra asset no directory inside the release.
+pub fn load(path: &Utf8Path) -> Result<Plan> {
+    let plan: Plan = serde_json::from_str(&fs::read_to_string(path)?)?;
+    if plan.format_version != FORMAT_VERSION {
+        return Err(PlanError::UnsupportedVersion(plan.format_version));
+    }
+    for extra in &plan.extra_assets {
+        extra.dest()?;
+    }
+    Ok(plan)
+}
+
+/// Atomically writes `plan` to `path` as pretty-printed JSON.
+///
+/// # Errors
+///
+/// Returns an error if the parent directory cannot be created or the file cannot
+/// be written, synced, or persisted.
+pub fn save(path: &Utf8Path, plan: &Plan) -> Result<()> {
+    fsops::write_json_atomic(path, plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches;
+
+    use camino_tempfile::tempdir;
+
+    use super::*;
+
+    fn plan() -> Plan {
+        Plan {
+            format_version: FORMAT_VERSION,
+            app: "myapp".to_string(),
+            repo: "owner/repo".to_string(),
+            host: "https://api.github.com".to_string(),
+            created_at: Timestamp::now(),
+            from_tag: Some("v1.0.0".to_string()),
+            tag: "v1.1.0".to_string(),
+            asset: PlannedAsset {
+                name: "myapp-1.1.0.tar.gz".to_string(),
+                size: 1024,
+                url: "https://github.com/owner/repo/releases/download/v1.1.0/myapp-1.1.0.tar.gz"
+                    .to_string(),
+                sha256: Some("ab".repeat(32)),
+                dest: None,
+            },
+            extra_assets: Vec::new(),
+            restart_command: Some("systemctl restart myapp".to_string()),
+            health_command: None,
+            prune: vec!["v0.9.0".to_string()],
+            steps: vec![Step::new(
+                "extract",
+                "myapp-1.1.0.tar.gz",
+                "releases/v1.1.0",
+            )],
+        }
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("plans/myapp.json");
+
+        let plan = plan();
+        save(&path, &plan).unwrap();
+        assert_eq!(load(&path).unwrap(), plan);
+
+        let future = Plan {
+            format_version: FORMAT_VERSION + 1,
+            ..plan
+        };
+        save(&path, &future).unwrap();
+        assert_matches!(load(&path), Err(PlanError::UnsupportedVersion(2)));
+    }
+
+    #[test]
+    fn test_load_refuses_extra_asset_outside_release() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("plan.json");
+        let extra = PlannedAsset {
+            name: "docs.tar.gz".to_string(),
+            dest: Some(ReleaseSubdir::try_from("share/doc".to_string()).unwrap()),
+            ..plan().asset
+        };
+        let with_extra = Plan {
+            extra_assets: vec![extra.clone()],
+            ..plan()
+        };
+        save(&path, &with_extra).unwrap();
+        assert_eq!(load(&path).unwrap(), with_extra);
+
+        let json = fs::read_to_string(&path).unwrap();
+        for dest in ["/etc/cron.d", "../../bin", "share/../../bin"] {
+            fs::write(&path, json.replace("share/doc", dest)).unwrap();
+            assert_matches!(load(&path), Err(PlanError::Serialization(_)));
+        }
+
+        let without_dest = Plan {
+            extra_assets: vec![PlannedAsset {
+                dest: None,
+                ..extra
+            }],
+            ..plan()
+        };
+        save(&path, &without_dest).unwrap();
+        assert_matches!(load(&path), Err(PlanError::MissingDest(_)));
+    }
+
+    #[test]
+    fn test_checks_report_divergence() {
+        let plan = plan();
+        let host = "https://api.github.com";
+
+        plan.check_source("myapp", "owner/repo", host, Some("v1.0.0"))
+            .unwrap();
+        let err = plan
+            .check_source("myapp", "owner/repo", host, Some("v1.0.5"))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "plan no longer applies: the installed release is v1.0.5, but the plan expects v1.0.0"
+        );
+
+        plan.check_commands(Some("systemctl restart myapp"), None)
+            .unwrap();
+        assert!(plan.check_commands(None, None).is_err());
+
+        plan.check_prune(&["v0.9.0".to_string()]).unwrap();
+        let err = plan.check_prune(&[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "plan no longer applies: the prune list is (none), but the plan expects v0.9.0"
+        );
+
+        plan.asset
+            .check("myapp-1.1.0.tar.gz", 1024, &"AB".repeat(32))
+            .unwrap();
+        assert!(
+            plan.asset
+                .check("myapp-1.1.0.tar.gz", 1024, &"cd".repeat(32))
+                .is_err()
+        );
+        assert!(
+            plan.asset
+                .check("myapp-1.1.0.tar.gz", 2048, &"ab".repeat(32))
+                .is_err()
+        );
+    }
+}
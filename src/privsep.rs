@@ -0,0 +1,476 @@
+//! Privilege separation for the network requests of an update. Started as root
+//! with a network user, the binary runs itself again as that user to talk to the
+//! release source, so a flaw in HTTP or TLS handling cannot act as root. The
+//! helper reads one JSON request on stdin, answers on stdout, and leaves any
+//! downloaded file or unparseable response in a directory made for it; the root
+//! process copies them out before verifying and installing the download.
+
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, Read, Write as _},
+    os::unix::process::CommandExt,
+    path::PathBuf,
+    process::{Command, ExitStatus, Stdio},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use camino_tempfile::{NamedUtf8TempFile, Utf8TempDir};
+use rustix::{
+    fd::AsFd,
+    fs::{Dir, FileType, Gid, Mode, OFlags, Uid},
+    io::Errno,
+    process,
+};
+use serde::{Serialize, de::DeserializeOwned};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::github::MAX_DEBUG_BODY_BYTES;
+
+/// The hidden subcommand that runs the helper.
+pub const HELPER_COMMAND: &str = "network-helper";
+
+/// Name the helper gives a file it downloaded, inside its working directory.
+pub const DOWNLOAD_FILE: &str = "download";
+
+/// Directory the helper saves unparseable responses in, inside its working
+/// directory.
+pub const DEBUG_DIR: &str = "debug";
+
+const PASSWD: &str = "/etc/passwd";
+
+#[derive(Debug, Error)]
+pub enum PrivsepError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("invalid network helper message: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("no user named {0} in {PASSWD}")]
+    UnknownUser(String),
+
+    #[error("network user {0} is root, which would drop no privileges")]
+    RootUser(String),
+
+    #[error("cannot run {path} as {user} for network requests")]
+    Spawn {
+        path: PathBuf,
+        user: String,
+        source: io::Error,
+    },
+
+    #[error("network helper failed: {0}")]
+    Helper(String),
+
+    #[error("network helper exited with {0} without answering")]
+    HelperExited(ExitStatus),
+
+    #[error("network helper left {0}, which is not a regular file")]
+    NotAFile(Utf8PathBuf),
+
+    #[error("network helper left {path}, owned by uid {owner} rather than {user}")]
+    ForeignFile {
+        path: Utf8PathBuf,
+        owner: u32,
+        user: String,
+    },
+
+    #[error("network helper left {0}, which has other hard links")]
+    HardLinked(Utf8PathBuf),
+}
+
+pub type Result<T> = std::result::Result<T, PrivsepError>;
+
+/// The user network requests run as, given by name or as `uid` or `uid:gid`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkUser {
+    pub name: String,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl NetworkUser {
+    /// Resolves `user` against the password database, unless it is numeric.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `/etc/passwd` cannot be read, names no such user, or
+    /// the user is root.
+    pub fn lookup(user: &str) -> Result<Self> {
+        let user = match Self::from_ids(user) {
+            Some(user) => user,
+            None => Self::lookup_in(&fs::read_to_string(PASSWD)?, user)?,
+        };
+        if user.uid == 0 {
+            return Err(PrivsepError::RootUser(user.name));
+        }
+        Ok(user)
+    }
+
+    fn lookup_in(passwd: &str, name: &str) -> Result<Self> {
+        passwd
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split(':');
+                let user = fields.next()?;
+                let uid = fields.nth(1)?.parse().ok()?;
+                let gid = fields.next()?.parse().ok()?;
+                (user == name).then(|| Self {
+                    name: name.to_string(),
+                    uid,
+                    gid,
+                })
+            })
+            .next()
+            .ok_or_else(|| PrivsepError::UnknownUser(name.to_string()))
+    }
+
+    /// Parses `uid` or `uid:gid`, where a lone uid is also the gid.
+    fn from_ids(s: &str) -> Option<Self> {
+        let (uid, gid) = s.split_once(':').unwrap_or((s, s));
+        Some(Self {
+            name: s.to_string(),
+            uid: uid.parse().ok()?,
+            gid: gid.parse().ok()?,
+        })
+    }
+}
+
+/// Whether this process runs as root, and so has privileges to drop.
+#[must_use]
+pub fn is_root() -> bool {
+    process::geteuid().is_root()
+}
+
+/// Runs one request in a helper process as `user` and returns its answer.
+///
+/// The helper works in a fresh directory owned by `user`, which is passed to
+/// `take` once the helper has exited, so a downloaded file can be collected
+/// with [`take_download`] before the directory is removed. Given `debug_dir`,
+/// the responses the helper saved under [`DEBUG_DIR`] are copied there with
+/// [`take_debug_responses`], whether or not the request succeeded, and a failure
+/// message names the copies.
+///
+/// # Errors
+///
+/// Returns an error if the working directory cannot be set up, the helper
+/// cannot be run or exits without answering, its answer cannot be read, or it
+/// reports that the request failed.
+pub fn call<T: DeserializeOwned, R>(
+    user: &NetworkUser,
+    request: &impl Serialize,
+    debug_dir: Option<&Utf8Path>,
+    take: impl FnOnce(T, &Utf8Path) -> Result<R>,
+) -> Result<R> {
+    let request = serde_json::to_vec(request)?;
+    let work_dir = work_dir(user)?;
+    let path = env::current_exe()?;
+    let mut child = Command::new(&path)
+        .arg(HELPER_COMMAND)
+        .uid(user.uid)
+        .gid(user.gid)
+        .current_dir(work_dir.path())
+        .env("TMPDIR", work_dir.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|source| PrivsepError::Spawn {
+            path: path.clone(),
+            user: user.name.clone(),
+            source,
+        })?;
+
+    let written = child
+        .stdin
+        .take()
+        .map_or(Ok(()), |mut stdin| stdin.write_all(&request));
+    let mut answer = String::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        stdout.read_to_string(&mut answer)?;
+    }
+    let status = child.wait()?;
+    let helper_debug_dir = work_dir.path().join(DEBUG_DIR);
+    let debug_dir = debug_dir.filter(|debug_dir| {
+        take_debug_responses(work_dir.path(), user, debug_dir)
+            .inspect_err(|e| warn!("Could not collect the network helper's responses: {e}"))
+            .is_ok()
+    });
+    if answer.trim().is_empty() {
+        return Err(PrivsepError::HelperExited(status));
+    }
+    written?;
+    match serde_json::from_str::<std::result::Result<T, String>>(&answer)? {
+        Ok(value) => take(value, work_dir.path()),
+        Err(message) => Err(PrivsepError::Helper(match debug_dir {
+            Some(debug_dir) => message.replace(helper_debug_dir.as_str(), debug_dir.as_str()),
+            None => message,
+        })),
+    }
+}
+
+fn work_dir(user: &NetworkUser) -> Result<Utf8TempDir> {
+    let dir = camino_tempfile::Builder::new()
+        .prefix("distronomicon-network-")
+        .tempdir()?;
+    rustix::fs::chown(
+        dir.path().as_std_path(),
+        Some(Uid::from_raw(user.uid)),
+        Some(Gid::from_raw(user.gid)),
+    )
+    .map_err(io::Error::from)?;
+    Ok(dir)
+}
+
+/// Copies the file `user`'s helper downloaded in `work_dir` to a temporary file
+/// of this process, so the helper's user cannot change it once it is verified.
+///
+/// # Errors
+///
+/// Returns an error if the helper left no regular file of its own, the file has
+/// other hard links, or it cannot be copied.
+pub fn take_download(work_dir: &Utf8Path, user: &NetworkUser) -> Result<NamedUtf8TempFile> {
+    let path = work_dir.join(DOWNLOAD_FILE);
+    let mut source = open_helper_file(rustix::fs::CWD, &path, path.clone(), user)?;
+
+    let mut temp_file = NamedUtf8TempFile::new()?;
+    io::copy(&mut source, &mut temp_file)?;
+    temp_file.as_file().sync_all()?;
+    Ok(temp_file)
+}
+
+/// Copies the responses `user`'s helper saved under [`DEBUG_DIR`] in `work_dir`
+/// into `debug_dir`, each cut off at [`MAX_DEBUG_BODY_BYTES`], refusing what
+/// [`take_download`] refuses.
+///
+/// # Errors
+///
+/// Returns an error if the helper's debug directory is a symlink or cannot be
+/// read, holds a file that is not the helper's own, or a file cannot be copied.
+pub fn take_debug_responses(
+    work_dir: &Utf8Path,
+    user: &NetworkUser,
+    debug_dir: &Utf8Path,
+) -> Result<()> {
+    let helper_debug_dir = work_dir.join(DEBUG_DIR);
+    let dir = match rustix::fs::open(
+        helper_debug_dir.as_std_path(),
+        OFlags::RDONLY | OFlags::DIRECTORY | OFlags::NOFOLLOW | OFlags::CLOEXEC,
+        Mode::empty(),
+    ) {
+        Ok(dir) => dir,
+        Err(Errno::NOENT) => return Ok(()),
+        Err(e) => return Err(io::Error::from(e).into()),
+    };
+    for entry in Dir::read_from(&dir).map_err(io::Error::from)? {
+        let entry = entry.map_err(io::Error::from)?;
+        let Ok(name) = entry.file_name().to_str() else {
+            continue;
+        };
+        if name == "." || name == ".." {
+            continue;
+        }
+        let source =
+            open_helper_file(&dir, Utf8Path::new(name), helper_debug_dir.join(name), user)?;
+        fs::create_dir_all(debug_dir)?;
+        let mut file = File::create(debug_dir.join(name))?;
+        io::copy(&mut source.take(MAX_DEBUG_BODY_BYTES as u64), &mut file)?;
+    }
+    Ok(())
+}
+
+/// Opens `name`, relative to `dir`, as a regular file the helper running as
+/// `user` left behind at `path`.
+fn open_helper_file(
+    dir: impl AsFd,
+    name: &Utf8Path,
+    path: Utf8PathBuf,
+    user: &NetworkUser,
+) -> Result<File> {
+    // Refuse a symlink the helper may have left to a file only root can read, and
+    // do not block on a FIFO; the type is checked once the file is open.
+    let fd = rustix::fs::openat(
+        dir,
+        name.as_std_path(),
+        OFlags::RDONLY | OFlags::NOFOLLOW | OFlags::NONBLOCK | OFlags::CLOEXEC,
+        Mode::empty(),
+    )
+    .map_err(io::Error::from)?;
+    let stat = rustix::fs::fstat(&fd).map_err(io::Error::from)?;
+    if FileType::from_raw_mode(stat.st_mode) != FileType::RegularFile {
+        return Err(PrivsepError::NotAFile(path));
+    }
+    // A hard link to a root-owned file would be owned by root, or, if the helper
+    // linked one of its own, could still be changed through the other link.
+    if stat.st_uid != user.uid {
+        return Err(PrivsepError::ForeignFile {
+            path,
+            owner: stat.st_uid,
+            user: user.name.clone(),
+        });
+    }
+    if stat.st_nlink != 1 {
+        return Err(PrivsepError::HardLinked(path));
+    }
+    Ok(File::from(fd))
+}
+
+/// Reads the helper's request from stdin.
+///
+/// # Errors
+///
+/// Returns an error if stdin cannot be read or holds no valid request.
+pub fn read_request<T: DeserializeOwned>() -> Result<T> {
+    Ok(serde_json::from_reader(io::stdin().lock())?)
+}
+
+/// Writes the helper's answer to stdout, with a failure as its message.
+///
+/// # Errors
+///
+/// Returns an error if the answer cannot be serialized or written.
+pub fn write_answer<T: Serialize>(answer: &std::result::Result<T, String>) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    serde_json::to_writer(&mut stdout, answer)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches;
+
+    use camino_tempfile::tempdir;
+
+    use super::*;
+
+    const PASSWD_FILE: &str = "\
+root:x:0:0:root:/root:/bin/bash
+nobody:x:65534:65534:nobody:/nonexistent:/usr/sbin/nologin
+distronomicon:x:998:997::/var/empty:/usr/sbin/nologin
+";
+
+    #[test]
+    fn test_lookup_user() {
+        assert_eq!(
+            NetworkUser::lookup_in(PASSWD_FILE, "distronomicon").unwrap(),
+            NetworkUser {
+                name: "distronomicon".to_string(),
+                uid: 998,
+                gid: 997,
+            }
+        );
+        assert_matches!(
+            NetworkUser::lookup_in(PASSWD_FILE, "nobod"),
+            Err(PrivsepError::UnknownUser(_))
+        );
+    }
+
+    #[test]
+    fn test_numeric_user() {
+        let user = NetworkUser::lookup("65534").unwrap();
+        assert_eq!((user.uid, user.gid), (65534, 65534));
+        let user = NetworkUser::lookup("1000:100").unwrap();
+        assert_eq!((user.uid, user.gid), (1000, 100));
+        assert_matches!(NetworkUser::lookup("0:0"), Err(PrivsepError::RootUser(_)));
+    }
+
+    fn current_user() -> NetworkUser {
+        NetworkUser {
+            name: "helper".to_string(),
+            uid: process::geteuid().as_raw(),
+            gid: process::getegid().as_raw(),
+        }
+    }
+
+    #[test]
+    fn test_take_download_refuses_symlink() {
+        let temp_dir = tempdir().unwrap();
+        let secret = temp_dir.path().join("secret");
+        fs::write(&secret, b"root only").unwrap();
+        std::os::unix::fs::symlink(&secret, temp_dir.path().join(DOWNLOAD_FILE)).unwrap();
+        assert!(take_download(temp_dir.path(), &current_user()).is_err());
+
+        fs::remove_file(temp_dir.path().join(DOWNLOAD_FILE)).unwrap();
+        fs::write(temp_dir.path().join(DOWNLOAD_FILE), b"archive").unwrap();
+        let file = take_download(temp_dir.path(), &current_user()).unwrap();
+        assert_eq!(fs::read(file.path()).unwrap(), b"archive");
+    }
+
+    #[test]
+    fn test_take_debug_responses() {
+        let work_dir = tempdir().unwrap();
+        let debug_dir = tempdir().unwrap();
+        let dest = debug_dir.path().join("app");
+        take_debug_responses(work_dir.path(), &current_user(), &dest).unwrap();
+        assert!(!dest.exists());
+
+        let helper_debug_dir = work_dir.path().join(DEBUG_DIR);
+        fs::create_dir(&helper_debug_dir).unwrap();
+        fs::write(helper_debug_dir.join("github-response-1.json"), b"<html>").unwrap();
+        take_debug_responses(work_dir.path(), &current_user(), &dest).unwrap();
+        assert_eq!(
+            fs::read(dest.join("github-response-1.json")).unwrap(),
+            b"<html>"
+        );
+
+        let secret = work_dir.path().join("secret");
+        fs::write(&secret, b"root only").unwrap();
+        std::os::unix::fs::symlink(&secret, helper_debug_dir.join("github-response-2.json"))
+            .unwrap();
+        assert!(take_debug_responses(work_dir.path(), &current_user(), &dest).is_err());
+        assert!(!dest.join("github-response-2.json").exists());
+    }
+
+    #[test]
+    fn test_take_debug_responses_refuses_symlinked_directory() {
+        let work_dir = tempdir().unwrap();
+        let elsewhere = tempdir().unwrap();
+        fs::write(elsewhere.path().join("github-response-1.json"), b"{}").unwrap();
+        std::os::unix::fs::symlink(elsewhere.path(), work_dir.path().join(DEBUG_DIR)).unwrap();
+        let dest = work_dir.path().join("copied");
+        assert!(take_debug_responses(work_dir.path(), &current_user(), &dest).is_err());
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn test_take_download_refuses_fifo() {
+        let temp_dir = tempdir().unwrap();
+        rustix::fs::mknodat(
+            rustix::fs::CWD,
+            temp_dir.path().join(DOWNLOAD_FILE).as_std_path(),
+            FileType::Fifo,
+            Mode::from_raw_mode(0o600),
+            0,
+        )
+        .unwrap();
+        assert_matches!(
+            take_download(temp_dir.path(), &current_user()),
+            Err(PrivsepError::NotAFile(_))
+        );
+    }
+
+    #[test]
+    fn test_take_download_refuses_hard_link_and_foreign_owner() {
+        let temp_dir = tempdir().unwrap();
+        let other = temp_dir.path().join("other");
+        fs::write(&other, b"root only").unwrap();
+        fs::hard_link(&other, temp_dir.path().join(DOWNLOAD_FILE)).unwrap();
+        assert_matches!(
+            take_download(temp_dir.path(), &current_user()),
+            Err(PrivsepError::HardLinked(_))
+        );
+
+        let stranger = NetworkUser {
+            uid: current_user().uid + 1,
+            ..current_user()
+        };
+        fs::remove_file(&other).unwrap();
+        assert_matches!(
+            take_download(temp_dir.path(), &stranger),
+            Err(PrivsepError::ForeignFile { .. })
+        );
+    }
+}
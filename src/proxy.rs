@@ -0,0 +1,260 @@
+use std::{
+    fmt, fs,
+    io::{self, ErrorKind},
+    str::FromStr,
+};
+
+use camino::Utf8Path;
+use reqwest::{ClientBuilder, NoProxy, Proxy};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The system-wide environment read by `pam_env` for login sessions, but not by
+/// systemd services.
+pub const SYSTEM_ENVIRONMENT: &str = "/etc/environment";
+
+#[derive(Debug, Error)]
+pub enum ProxyError {
+    #[error("failed to read {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("invalid proxy {name}={value:?} in {path}: {source}")]
+    InvalidProxy {
+        name: &'static str,
+        value: String,
+        path: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    #[error("unknown proxy mode '{0}': expected 'env', 'direct', or 'system'")]
+    UnknownMode(String),
+}
+
+pub type Result<T> = std::result::Result<T, ProxyError>;
+
+/// Where HTTP clients take their proxy settings from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// The `http_proxy`, `https_proxy`, `all_proxy`, and `no_proxy` variables of
+    /// this process, which differ between shells and systemd units.
+    #[default]
+    Env,
+    /// Connect directly, ignoring every proxy setting.
+    Direct,
+    /// The proxy variables in `/etc/environment`, ignoring this process's own, so
+    /// a shell and a systemd unit on the same host connect the same way.
+    System,
+}
+
+impl FromStr for Mode {
+    type Err = ProxyError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "env" => Ok(Self::Env),
+            "direct" => Ok(Self::Direct),
+            "system" => Ok(Self::System),
+            other => Err(ProxyError::UnknownMode(other.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Env => f.write_str("env"),
+            Self::Direct => f.write_str("direct"),
+            Self::System => f.write_str("system"),
+        }
+    }
+}
+
+/// Proxy variables found in an environment file.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Settings {
+    pub http: Option<String>,
+    pub https: Option<String>,
+    pub all: Option<String>,
+    pub no_proxy: Option<String>,
+}
+
+impl Settings {
+    /// Parses the `KEY=value` lines of an environment file such as
+    /// `/etc/environment`, keeping the proxy variables.
+    ///
+    /// Values may be quoted, `export` prefixes and `#` comments are ignored, and
+    /// empty values count as unset. As with curl, lowercase names win over
+    /// uppercase ones.
+    #[must_use]
+    pub fn parse(contents: &str) -> Self {
+        let mut lowercase = Self::default();
+        let mut uppercase = Self::default();
+        for line in contents.lines() {
+            let line = line.trim();
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            if line.starts_with('#') {
+                continue;
+            }
+            let Some((name, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                .unwrap_or(value);
+            if value.is_empty() {
+                continue;
+            }
+
+            let name = name.trim();
+            let settings = if name.bytes().any(|b| b.is_ascii_lowercase()) {
+                &mut lowercase
+            } else {
+                &mut uppercase
+            };
+            let slot = match name.to_ascii_lowercase().as_str() {
+                "http_proxy" => &mut settings.http,
+                "https_proxy" => &mut settings.https,
+                "all_proxy" => &mut settings.all,
+                "no_proxy" => &mut settings.no_proxy,
+                _ => continue,
+            };
+            *slot = Some(value.to_string());
+        }
+
+        Self {
+            http: lowercase.http.or(uppercase.http),
+            https: lowercase.https.or(uppercase.https),
+            all: lowercase.all.or(uppercase.all),
+            no_proxy: lowercase.no_proxy.or(uppercase.no_proxy),
+        }
+    }
+
+    /// Reads the settings from the environment file at `path`; a missing file has
+    /// no settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProxyError::Io` if the file exists but cannot be read.
+    pub fn load(path: &Utf8Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(Self::parse(&contents)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(source) => Err(ProxyError::Io {
+                path: path.to_string(),
+                source,
+            }),
+        }
+    }
+
+    fn apply(self, mut builder: ClientBuilder, path: &Utf8Path) -> Result<ClientBuilder> {
+        let no_proxy = self.no_proxy.as_deref().and_then(NoProxy::from_string);
+        for (name, value, proxy) in [
+            (
+                "http_proxy",
+                self.http,
+                Proxy::http as fn(String) -> reqwest::Result<Proxy>,
+            ),
+            ("https_proxy", self.https, Proxy::https),
+            ("all_proxy", self.all, Proxy::all),
+        ] {
+            let Some(value) = value else {
+                continue;
+            };
+            let proxy = proxy(value.clone()).map_err(|source| ProxyError::InvalidProxy {
+                name,
+                value,
+                path: path.to_string(),
+                source,
+            })?;
+            builder = builder.proxy(proxy.no_proxy(no_proxy.clone()));
+        }
+        Ok(builder)
+    }
+}
+
+/// Configures `builder`'s proxies according to `mode`.
+///
+/// # Errors
+///
+/// Returns an error if, for [`Mode::System`], `/etc/environment` cannot be read or
+/// names an invalid proxy URL.
+pub fn configure(builder: ClientBuilder, mode: Mode) -> Result<ClientBuilder> {
+    match mode {
+        Mode::Env => Ok(builder),
+        Mode::Direct => Ok(builder.no_proxy()),
+        Mode::System => {
+            let path = Utf8Path::new(SYSTEM_ENVIRONMENT);
+            Settings::load(path)?.apply(builder.no_proxy(), path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_environment_file() {
+        let settings = Settings::parse(
+            r#"
+PATH="/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin"
+# http_proxy=http://commented.example.com:3128
+HTTP_PROXY=http://upper.example.com:3128
+http_proxy="http://proxy.example.com:3128"
+export HTTPS_PROXY='http://proxy.example.com:3128'
+ALL_PROXY=
+no_proxy=localhost,.internal.example.com
+"#,
+        );
+
+        assert_eq!(
+            settings,
+            Settings {
+                http: Some("http://proxy.example.com:3128".to_string()),
+                https: Some("http://proxy.example.com:3128".to_string()),
+                all: None,
+                no_proxy: Some("localhost,.internal.example.com".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_load_missing_file_has_no_settings() {
+        let temp_dir = camino_tempfile::tempdir().unwrap();
+
+        assert_eq!(
+            Settings::load(&temp_dir.path().join("environment")).unwrap(),
+            Settings::default()
+        );
+    }
+
+    #[test]
+    fn test_apply_rejects_invalid_proxy() {
+        let settings = Settings {
+            http: Some("not a url".to_string()),
+            ..Settings::default()
+        };
+
+        let error = settings
+            .apply(
+                reqwest::Client::builder(),
+                Utf8Path::new(SYSTEM_ENVIRONMENT),
+            )
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("invalid proxy http_proxy=\"not a url\" in /etc/environment"),
+            "{error}"
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use std::{fs, io, time::Duration};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use jiff::{SignedDuration, Timestamp};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    fsops,
+    github::{FetchResult, Release, Validators, ValidatorsOut},
+};
+
+#[derive(Debug, Error)]
+pub enum ReleaseCacheError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("invalid cached release: {0}")]
+    Serialization(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, ReleaseCacheError>;
+
+/// The release last fetched for an app, with the validators and freshness of the
+/// response it came in, so an unchanged release costs no API call while it is
+/// fresh and no second request once a conditional one answers `304`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedRelease {
+    /// What was asked for (host, repo, and release selection), so a change of
+    /// settings never reuses another query's answer.
+    pub query: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    pub fetched_at: Timestamp,
+    /// Seconds the response may be reused without asking again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age_secs: Option<u64>,
+    pub release: Release,
+}
+
+impl CachedRelease {
+    /// Caches `release` from a response with `validators`, fetched now.
+    #[must_use]
+    pub fn new(query: String, validators: &ValidatorsOut, release: Release) -> Self {
+        Self {
+            query,
+            etag: validators.etag.clone(),
+            last_modified: validators.last_modified.clone(),
+            fetched_at: Timestamp::now(),
+            max_age_secs: validators.max_age.map(|max_age| max_age.as_secs()),
+            release,
+        }
+    }
+
+    /// Whether the response is still within its `max-age` at `now`.
+    #[must_use]
+    pub fn is_fresh(&self, now: Timestamp) -> bool {
+        self.max_age_secs.is_some_and(|max_age| {
+            let age = now.duration_since(self.fetched_at);
+            age >= SignedDuration::ZERO && age < SignedDuration::from_secs(max_age.cast_signed())
+        })
+    }
+
+    /// The validators for a conditional request about the cached release.
+    #[must_use]
+    pub fn validators(&self) -> Validators {
+        Validators {
+            etag: self.etag.clone(),
+            last_modified: self.last_modified.clone(),
+        }
+    }
+
+    /// Records that a conditional request confirmed the release at `now`, with the
+    /// `max-age` of that response.
+    pub fn revalidated(&mut self, now: Timestamp, max_age: Option<Duration>) {
+        self.fetched_at = now;
+        self.max_age_secs = max_age.map(|max_age| max_age.as_secs());
+    }
+
+    /// The cached release as the unmodified result of a fetch.
+    #[must_use]
+    pub fn into_fetch_result(self) -> FetchResult {
+        FetchResult {
+            validators: ValidatorsOut {
+                etag: self.etag,
+                last_modified: self.last_modified,
+                max_age: self.max_age_secs.map(Duration::from_secs),
+            },
+            release: Some(self.release),
+            was_modified: false,
+            rate_limit: None,
+        }
+    }
+}
+
+/// Path of the cached release for `app`: `<state_directory>/<app>/release.json`.
+#[must_use]
+pub fn path(state_directory: &Utf8Path, app: &str) -> Utf8PathBuf {
+    state_directory.join(app).join("release.json")
+}
+
+/// Loads the cached release for `app`, returning `Ok(None)` if there is none.
+///
+/// # Errors
+///
+/// Returns an error if the cache file exists but cannot be read or parsed.
+pub fn load(state_directory: &Utf8Path, app: &str) -> Result<Option<CachedRelease>> {
+    match fs::read_to_string(path(state_directory, app)) {
+        Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Atomically writes the cached release for `app`, replacing any earlier one.
+///
+/// # Errors
+///
+/// Returns an error if the state directory cannot be created or the file cannot be
+/// written, synced, or persisted.
+pub fn save(state_directory: &Utf8Path, app: &str, cached: &CachedRelease) -> Result<()> {
+    fsops::write_json_atomic(path(state_directory, app), cached)
+}
+
+#[cfg(test)]
+mod tests {
+    use camino_tempfile::tempdir;
+
+    use super::*;
+
+    fn cached(max_age: Option<Duration>) -> CachedRelease {
+        let release = serde_json::from_value(serde_json::json!({
+            "tag_name": "v1.2.0",
+            "prerelease": false,
+            "assets": [{
+                "name": "myapp.tar.gz",
+                "url": "https://api.github.com/assets/1",
+                "browser_download_url": "https://github.com/download/myapp.tar.gz",
+                "size": 1024
+            }]
+        }))
+        .unwrap();
+        let validators = ValidatorsOut {
+            etag: Some("\"abc\"".to_string()),
+            last_modified: None,
+            max_age,
+        };
+        CachedRelease::new("query".to_string(), &validators, release)
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp_dir = tempdir().unwrap();
+        assert!(load(temp_dir.path(), "myapp").unwrap().is_none());
+
+        save(temp_dir.path(), "myapp", &cached(None)).unwrap();
+
+        let loaded = load(temp_dir.path(), "myapp").unwrap().unwrap();
+        assert_eq!(loaded.query, "query");
+        assert_eq!(loaded.etag.as_deref(), Some("\"abc\""));
+        assert_eq!(loaded.release.tag_name, "v1.2.0");
+        assert_eq!(loaded.release.assets[0].name, "myapp.tar.gz");
+    }
+
+    #[test]
+    fn test_is_fresh() {
+        let mut cached = cached(Some(Duration::from_secs(60)));
+        let fetched_at = cached.fetched_at;
+
+        assert!(cached.is_fresh(fetched_at));
+        assert!(cached.is_fresh(fetched_at + SignedDuration::from_secs(59)));
+        assert!(!cached.is_fresh(fetched_at + SignedDuration::from_secs(60)));
+        assert!(!cached.is_fresh(fetched_at - SignedDuration::from_secs(1)));
+
+        cached.revalidated(fetched_at + SignedDuration::from_secs(120), None);
+        assert!(!cached.is_fresh(fetched_at + SignedDuration::from_secs(121)));
+    }
+}
@@ -17,6 +17,19 @@ pub enum RestartError {
 
 pub type Result<T> = std::result::Result<T, RestartError>;
 
+/// Returned by `update` when the new release was activated but a command run
+/// after activation failed.
+#[derive(Debug, Error)]
+pub enum PostInstallError {
+    #[error("Update completed but restart command failed")]
+    Restart,
+    #[error("Health check did not pass within {timeout_secs}s: {error}")]
+    Health {
+        timeout_secs: u64,
+        error: RestartError,
+    },
+}
+
 /// Execute a shell command via `/bin/sh -c`.
 ///
 /// Empty commands are treated as no-op and return `Ok(())`.
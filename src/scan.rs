@@ -0,0 +1,101 @@
+use std::{io, process::Command};
+
+use camino::Utf8Path;
+use thiserror::Error;
+
+/// Environment variable holding the path of the artifact being scanned.
+pub const ARTIFACT_ENV: &str = "DISTRONOMICON_ARTIFACT";
+
+#[derive(Debug, Error)]
+pub enum ScanError {
+    #[error("scan command '{command}' rejected artifact with exit code {code}")]
+    Rejected {
+        command: String,
+        code: i32,
+        stdout: String,
+        stderr: String,
+    },
+    #[error("failed to execute scan command: {0}")]
+    Io(#[from] io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, ScanError>;
+
+/// Runs a scan command against a downloaded artifact via `/bin/sh -c`.
+///
+/// The artifact path is passed as `$1` and exported as `DISTRONOMICON_ARTIFACT`, so
+/// commands can be written as `clamscan --no-summary "$1"`. A zero exit status means
+/// the artifact is accepted.
+///
+/// # Errors
+///
+/// Returns `ScanError::Rejected` if the command exits with a non-zero status code.
+/// Stdout and stderr are captured in the error struct for debugging but not shown in
+/// the error message.
+///
+/// Returns `ScanError::Io` if the command cannot be executed (e.g., `/bin/sh` not found).
+pub fn execute(cmd: &str, artifact: &Utf8Path) -> Result<()> {
+    let output = Command::new("/bin/sh")
+        .arg("-c")
+        .arg(cmd)
+        .arg("distronomicon-scan")
+        .arg(artifact)
+        .env(ARTIFACT_ENV, artifact)
+        .output()?;
+
+    if !output.status.success() {
+        let code = output.status.code().unwrap_or(-1);
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+        return Err(ScanError::Rejected {
+            command: cmd.to_string(),
+            code,
+            stdout,
+            stderr,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches;
+
+    use camino_tempfile::tempdir;
+    use camino_tempfile_ext::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn test_execute_accepts_on_success() {
+        let temp_dir = tempdir().unwrap();
+        let artifact = temp_dir.child("asset.tar.gz");
+        artifact.write_str("data").unwrap();
+
+        let result = execute("test -f \"$1\"", &artifact);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_execute_rejects_on_failure() {
+        let temp_dir = tempdir().unwrap();
+        let artifact = temp_dir.child("asset.tar.gz");
+
+        let result = execute("exit 3", &artifact);
+        assert_matches!(result, Err(ScanError::Rejected { code: 3, .. }));
+    }
+
+    #[test]
+    fn test_execute_exports_artifact_env() {
+        let temp_dir = tempdir().unwrap();
+        let artifact = temp_dir.child("asset.tar.gz");
+
+        let result = execute("echo \"$DISTRONOMICON_ARTIFACT\" && false", &artifact);
+        assert_matches!(
+            result,
+            Err(ScanError::Rejected { ref stdout, .. }) if stdout.trim() == artifact.as_str()
+        );
+    }
+}
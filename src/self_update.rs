@@ -0,0 +1,123 @@
+use std::{
+    env::consts::ARCH,
+    fs::{self, File},
+    io,
+};
+
+use camino::Utf8Path;
+use camino_tempfile::NamedUtf8TempFile;
+
+use crate::pattern::ARCHIVE_EXTENSIONS;
+
+/// Repository that distronomicon's own releases are published to.
+pub const DEFAULT_REPO: &str = "jtdowney/distronomicon";
+
+/// Name of the executable inside a release archive.
+pub const BINARY_NAME: &str = "distronomicon";
+
+/// Checksum file published alongside every release.
+pub const CHECKSUM_PATTERN: &str = r"^sha256\.sum$";
+
+/// The target triple this binary was built for, as used in release asset names.
+#[must_use]
+pub fn target_triple() -> String {
+    let env = if cfg!(target_env = "musl") {
+        "musl"
+    } else {
+        "gnu"
+    };
+    format!("{ARCH}-unknown-linux-{env}")
+}
+
+/// Builds the pattern for the release archive matching this binary's target, such as
+/// `distronomicon-x86_64-unknown-linux-gnu.tar.xz`.
+#[must_use]
+pub fn asset_pattern() -> String {
+    format!(
+        r"^{}-{}\.(?:{ARCHIVE_EXTENSIONS})$",
+        BINARY_NAME,
+        regex::escape(&target_triple())
+    )
+}
+
+/// Whether `tag` names the release this binary was built from.
+#[must_use]
+pub fn is_current(tag: &str) -> bool {
+    tag.strip_prefix('v').unwrap_or(tag) == env!("CARGO_PKG_VERSION")
+}
+
+/// Atomically replaces the executable at `target` with `source`.
+///
+/// The new binary is copied into a temporary file next to `target`, given the same
+/// permissions, synced, and renamed over `target`, so the path always refers to a
+/// complete executable. A running process keeps its already-open copy.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - `target` has no parent directory or its metadata cannot be read
+/// - The temporary file cannot be created, written, synced, or renamed
+pub fn replace_executable(source: &Utf8Path, target: &Utf8Path) -> io::Result<()> {
+    let parent = target
+        .parent()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "target has no parent"))?;
+    let permissions = fs::metadata(target)?.permissions();
+
+    let temp_file = NamedUtf8TempFile::new_in(parent)?;
+    fs::copy(source, temp_file.path())?;
+    fs::set_permissions(temp_file.path(), permissions)?;
+    temp_file.as_file().sync_all()?;
+    temp_file.persist(target).map_err(|e| e.error)?;
+    File::open(parent)?.sync_all()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use camino_tempfile::tempdir;
+    use regex::Regex;
+
+    use super::*;
+
+    #[test]
+    fn test_asset_pattern_matches_own_target_only() {
+        let pattern = Regex::new(&asset_pattern()).unwrap();
+        let target = target_triple();
+
+        assert!(pattern.is_match(&format!("distronomicon-{target}.tar.xz")));
+        assert!(pattern.is_match(&format!("distronomicon-{target}.tar.gz")));
+        assert!(!pattern.is_match(&format!("distronomicon-{target}.tar.xz.sha256")));
+        assert!(!pattern.is_match("distronomicon-riscv64gc-unknown-linux-gnu.tar.xz"));
+        assert!(!pattern.is_match("distronomicon-installer.sh"));
+    }
+
+    #[test]
+    fn test_is_current() {
+        let version = env!("CARGO_PKG_VERSION");
+        assert!(is_current(version));
+        assert!(is_current(&format!("v{version}")));
+        assert!(!is_current("v0.0.0-never"));
+    }
+
+    #[test]
+    fn test_replace_executable_keeps_permissions() {
+        let temp_dir = tempdir().unwrap();
+        let source = temp_dir.path().join("new");
+        let target = temp_dir.path().join("distronomicon");
+        fs::write(&source, "new binary").unwrap();
+        fs::write(&target, "old binary").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o755)).unwrap();
+
+        replace_executable(&source, &target).unwrap();
+
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new binary");
+        assert_eq!(
+            fs::metadata(&target).unwrap().permissions().mode() & 0o777,
+            0o755
+        );
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 2);
+    }
+}
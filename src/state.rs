@@ -1,13 +1,23 @@
-use std::{
-    fs,
-    io::{self, Write},
-};
+#[cfg(feature = "sqlite")]
+use std::time::Duration;
+use std::{fmt, fs, io, str::FromStr};
 
-use camino::Utf8Path;
-use camino_tempfile::NamedUtf8TempFile;
+use camino::{Utf8Path, Utf8PathBuf};
+#[cfg(feature = "sqlite")]
+use rusqlite::{Connection, OpenFlags, OptionalExtension as _};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::{fsops, github};
+
+/// File name of the shared sqlite database under the state directory.
+#[cfg(feature = "sqlite")]
+pub const SQLITE_FILE: &str = "state.db";
+
+/// How long a sqlite operation waits for another process holding the database.
+#[cfg(feature = "sqlite")]
+const SQLITE_BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Error)]
 pub enum StateError {
     #[error("IO error: {0}")]
@@ -15,6 +25,13 @@ pub enum StateError {
 
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
+
+    #[cfg(feature = "sqlite")]
+    #[error("sqlite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("unknown state backend '{0}': expected 'json' or 'sqlite'")]
+    UnknownBackend(String),
 }
 
 pub type Result<T> = std::result::Result<T, StateError>;
@@ -23,8 +40,263 @@ pub type Result<T> = std::result::Result<T, StateError>;
 pub struct State {
     pub latest_tag: String,
     pub etag: String,
-    pub last_modified: jiff::Timestamp,
+    /// The `Last-Modified` header exactly as GitHub sent it (empty if it sent none).
+    #[serde(deserialize_with = "deserialize_last_modified")]
+    pub last_modified: String,
     pub installed_at: jiff::Timestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+}
+
+impl State {
+    /// Returns true if the recorded repo or host differs from the given source.
+    ///
+    /// State written before the source was recorded matches any source.
+    #[must_use]
+    pub fn source_changed(&self, repo: &str, host: &str) -> bool {
+        let repo_changed = self
+            .repo
+            .as_deref()
+            .is_some_and(|recorded| !recorded.eq_ignore_ascii_case(repo));
+        let host_changed = self
+            .host
+            .as_deref()
+            .is_some_and(|recorded| recorded.trim_end_matches('/') != host.trim_end_matches('/'));
+        repo_changed || host_changed
+    }
+
+    /// The `If-Modified-Since` value for the next conditional request, if any.
+    #[must_use]
+    pub fn if_modified_since(&self) -> Option<String> {
+        (!self.last_modified.is_empty()).then(|| self.last_modified.clone())
+    }
+
+    /// The recorded `Last-Modified` as a timestamp, for display.
+    #[must_use]
+    pub fn last_modified_at(&self) -> Option<jiff::Timestamp> {
+        github::parse_http_date(&self.last_modified)
+    }
+
+    /// Describes the recorded source as `<repo> on <host>` for messages.
+    #[must_use]
+    pub fn source(&self) -> String {
+        format!(
+            "{} on {}",
+            self.repo.as_deref().unwrap_or("(unknown repo)"),
+            self.host.as_deref().unwrap_or("(unknown host)")
+        )
+    }
+}
+
+/// Reads `last_modified`, migrating the RFC 3339 timestamps older versions stored
+/// to the HTTP date GitHub sent, so they can be replayed as `If-Modified-Since`.
+///
+/// The file itself is rewritten in the new form the next time the state is saved.
+fn deserialize_last_modified<'de, D>(deserializer: D) -> std::result::Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    Ok(match value.parse() {
+        Ok(timestamp) => github::format_http_date(timestamp),
+        Err(_) => value,
+    })
+}
+
+/// Where per-app state is persisted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// One `<state-dir>/<app>/state.json` file per app.
+    #[default]
+    Json,
+    /// A single `<state-dir>/state.db` sqlite database shared by all apps.
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
+
+impl FromStr for Backend {
+    type Err = StateError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(Self::Json),
+            #[cfg(feature = "sqlite")]
+            "sqlite" => Ok(Self::Sqlite),
+            other => Err(StateError::UnknownBackend(other.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json => f.write_str("json"),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite => f.write_str("sqlite"),
+        }
+    }
+}
+
+/// Per-app state storage rooted at a state directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Store {
+    backend: Backend,
+    directory: Utf8PathBuf,
+}
+
+impl Store {
+    #[must_use]
+    pub fn new(backend: Backend, directory: impl Into<Utf8PathBuf>) -> Self {
+        Self {
+            backend,
+            directory: directory.into(),
+        }
+    }
+
+    /// The file holding `app`'s state, for messages and cleanup.
+    #[must_use]
+    pub fn location(&self, app: &str) -> Utf8PathBuf {
+        match self.backend {
+            Backend::Json => self.directory.join(app).join("state.json"),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite => self.directory.join(SQLITE_FILE),
+        }
+    }
+
+    /// Loads `app`'s state, returning `Ok(None)` if none has been saved.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state file or database cannot be read, or the
+    /// stored state is not valid.
+    pub fn load(&self, app: &str) -> Result<Option<State>> {
+        match self.backend {
+            Backend::Json => load(self.location(app)),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite => {
+                let path = self.location(app);
+                if !path.exists() {
+                    return Ok(None);
+                }
+                let conn = open_sqlite(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+                let data: Option<String> = conn
+                    .query_row("SELECT data FROM state WHERE app = ?1", [app], |row| {
+                        row.get(0)
+                    })
+                    .optional()?;
+                data.map(|data| serde_json::from_str(&data))
+                    .transpose()
+                    .map_err(StateError::from)
+            }
+        }
+    }
+
+    /// Whether any state is stored for `app`, without parsing it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be queried.
+    pub fn contains(&self, app: &str) -> Result<bool> {
+        let path = self.location(app);
+        match self.backend {
+            Backend::Json => Ok(path.symlink_metadata().is_ok()),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite => {
+                if !path.exists() {
+                    return Ok(false);
+                }
+                let conn = open_sqlite(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+                if !has_state_table(&conn)? {
+                    return Ok(false);
+                }
+                let found = conn
+                    .query_row("SELECT 1 FROM state WHERE app = ?1", [app], |_| Ok(()))
+                    .optional()?;
+                Ok(found.is_some())
+            }
+        }
+    }
+
+    /// Saves `app`'s state, replacing any previous state atomically.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state cannot be serialized or written.
+    pub fn save(&self, app: &str, state: &State) -> Result<()> {
+        match self.backend {
+            Backend::Json => save_atomic(self.location(app), state),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite => {
+                fs::create_dir_all(&self.directory)?;
+                let conn = open_sqlite(
+                    &self.location(app),
+                    OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
+                )?;
+                conn.execute(
+                    "CREATE TABLE IF NOT EXISTS state (app TEXT PRIMARY KEY, data TEXT NOT NULL)",
+                    (),
+                )?;
+                conn.execute(
+                    "INSERT INTO state (app, data) VALUES (?1, ?2) \
+                     ON CONFLICT(app) DO UPDATE SET data = excluded.data",
+                    (app, serde_json::to_string(state)?),
+                )?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Deletes `app`'s state, returning whether there was any.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state file or database row cannot be removed.
+    pub fn remove(&self, app: &str) -> Result<bool> {
+        let path = self.location(app);
+        match self.backend {
+            Backend::Json => match fs::remove_file(&path) {
+                Ok(()) => Ok(true),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+                Err(e) => Err(e.into()),
+            },
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite => {
+                if !path.exists() {
+                    return Ok(false);
+                }
+                let conn = open_sqlite(&path, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+                if !has_state_table(&conn)? {
+                    return Ok(false);
+                }
+                let removed = conn.execute("DELETE FROM state WHERE app = ?1", [app])?;
+                Ok(removed > 0)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn has_state_table(conn: &Connection) -> Result<bool> {
+    let found = conn
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'state'",
+            (),
+            |_| Ok(()),
+        )
+        .optional()?;
+    Ok(found.is_some())
+}
+
+#[cfg(feature = "sqlite")]
+fn open_sqlite(path: &Utf8Path, flags: OpenFlags) -> Result<Connection> {
+    let conn = Connection::open_with_flags(path, flags | OpenFlags::SQLITE_OPEN_NO_MUTEX)?;
+    conn.busy_timeout(SQLITE_BUSY_TIMEOUT)?;
+    Ok(conn)
 }
 
 /// Loads state from a JSON file.
@@ -49,37 +321,17 @@ pub fn load<P: AsRef<Utf8Path>>(path: P) -> Result<Option<State>> {
 
 /// Atomically saves state to a JSON file.
 ///
-/// Creates a temporary file in the parent directory, writes the state as JSON,
-/// syncs both the file and parent directory, then atomically renames to the target path.
+/// Writes through [`fsops::write_json_atomic`], which syncs both the file and the
+/// parent directory around the rename to the target path.
 ///
 /// # Errors
 ///
 /// Returns an error if:
-/// - The path has no parent directory
-/// - A temporary file cannot be created
+/// - The parent directory cannot be created or synced
 /// - The state cannot be serialized to JSON
 /// - Writing, syncing, or persisting the file fails
 pub fn save_atomic<P: AsRef<Utf8Path>>(path: P, state: &State) -> Result<()> {
-    let path = path.as_ref();
-    let parent = path.parent().ok_or_else(|| {
-        StateError::Io(io::Error::new(
-            io::ErrorKind::InvalidInput,
-            "path has no parent directory",
-        ))
-    })?;
-
-    fs::create_dir_all(parent)?;
-
-    let mut temp_file = NamedUtf8TempFile::new_in(parent)?;
-
-    let json = serde_json::to_string_pretty(state)?;
-    temp_file.write_all(json.as_bytes())?;
-    temp_file.as_file().sync_all()?;
-    temp_file.persist(path).map_err(|e| e.error)?;
-
-    fs::File::open(parent)?.sync_all()?;
-
-    Ok(())
+    fsops::write_json_atomic(path, state)
 }
 
 #[cfg(test)]
@@ -109,8 +361,11 @@ mod tests {
         let original = State {
             latest_tag: "v1.2.3".to_string(),
             etag: "abc123".to_string(),
-            last_modified: jiff::Timestamp::from_second(1_234_567_890).unwrap(),
+            last_modified: "Fri, 13 Feb 2009 23:31:30 GMT".to_string(),
             installed_at: jiff::Timestamp::from_second(1_234_567_900).unwrap(),
+            previous_tag: Some("v1.2.2".to_string()),
+            repo: Some("owner/repo".to_string()),
+            host: Some("https://api.github.com".to_string()),
         };
 
         save_atomic(&state_path, &original).unwrap();
@@ -119,6 +374,146 @@ mod tests {
         assert_eq!(loaded, original);
     }
 
+    #[test]
+    fn test_load_without_previous_tag() {
+        let temp_dir = tempdir().unwrap();
+        let state_path = temp_dir.child("state.json");
+
+        state_path
+            .write_str(
+                r#"{"latest_tag":"v1.0.0","etag":"abc","last_modified":"2025-10-28T12:00:00Z","installed_at":"2025-10-28T12:00:00Z"}"#,
+            )
+            .unwrap();
+
+        let loaded = load(&state_path).unwrap().expect("state should exist");
+        assert_eq!(loaded.previous_tag, None);
+    }
+
+    #[test]
+    fn test_load_migrates_rfc3339_last_modified() {
+        let temp_dir = tempdir().unwrap();
+        let state_path = temp_dir.child("state.json");
+
+        state_path
+            .write_str(
+                r#"{"latest_tag":"v1.0.0","etag":"abc","last_modified":"2025-10-28T12:00:00Z","installed_at":"2025-10-28T12:00:00Z"}"#,
+            )
+            .unwrap();
+
+        let loaded = load(&state_path).unwrap().expect("state should exist");
+        assert_eq!(loaded.last_modified, "Tue, 28 Oct 2025 12:00:00 GMT");
+        assert_eq!(
+            loaded.if_modified_since().as_deref(),
+            Some("Tue, 28 Oct 2025 12:00:00 GMT")
+        );
+
+        let mut state = loaded;
+        state.last_modified = "Tue, 28 Oct 2025 12:00:00 gmt".to_string();
+        save_atomic(&state_path, &state).unwrap();
+        let loaded = load(&state_path).unwrap().expect("state should exist");
+        assert_eq!(loaded.last_modified, "Tue, 28 Oct 2025 12:00:00 gmt");
+    }
+
+    #[test]
+    fn test_source_changed() {
+        let state = State {
+            latest_tag: "v1.0.0".to_string(),
+            etag: "abc".to_string(),
+            last_modified: "Sun, 09 Sep 2001 01:46:40 GMT".to_string(),
+            installed_at: jiff::Timestamp::from_second(1_000_000_000).unwrap(),
+            previous_tag: None,
+            repo: Some("owner/repo".to_string()),
+            host: Some("https://api.github.com".to_string()),
+        };
+
+        assert!(!state.source_changed("owner/repo", "https://api.github.com/"));
+        assert!(!state.source_changed("Owner/Repo", "https://api.github.com"));
+        assert!(state.source_changed("owner/other", "https://api.github.com"));
+        assert!(state.source_changed("owner/repo", "https://github.example.com/api/v3"));
+
+        let legacy = State {
+            repo: None,
+            host: None,
+            ..state
+        };
+        assert!(!legacy.source_changed("owner/other", "https://github.example.com/api/v3"));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_store_roundtrip_per_app() {
+        let temp_dir = tempdir().unwrap();
+        let store = Store::new(Backend::Sqlite, temp_dir.path());
+        assert_eq!(store.load("myapp").unwrap(), None);
+        assert!(!store.contains("myapp").unwrap());
+        assert!(!store.remove("myapp").unwrap());
+
+        let state = State {
+            latest_tag: "v1.0.0".to_string(),
+            etag: "abc".to_string(),
+            last_modified: "Sun, 09 Sep 2001 01:46:40 GMT".to_string(),
+            installed_at: jiff::Timestamp::from_second(1_000_000_000).unwrap(),
+            previous_tag: None,
+            repo: Some("owner/repo".to_string()),
+            host: None,
+        };
+        let other = State {
+            latest_tag: "v2.0.0".to_string(),
+            ..state.clone()
+        };
+        store.save("myapp", &state).unwrap();
+        store.save("other", &other).unwrap();
+
+        let updated = State {
+            latest_tag: "v1.1.0".to_string(),
+            previous_tag: Some("v1.0.0".to_string()),
+            ..state
+        };
+        store.save("myapp", &updated).unwrap();
+
+        assert_eq!(store.location("myapp"), temp_dir.path().join(SQLITE_FILE));
+        assert_eq!(store.load("myapp").unwrap(), Some(updated));
+        assert_eq!(store.load("other").unwrap(), Some(other));
+
+        assert!(store.contains("myapp").unwrap());
+        assert!(store.remove("myapp").unwrap());
+        assert_eq!(store.load("myapp").unwrap(), None);
+        assert!(store.contains("other").unwrap());
+    }
+
+    #[test]
+    fn test_json_store_uses_per_app_files() {
+        let temp_dir = tempdir().unwrap();
+        let store = Store::new(Backend::Json, temp_dir.path());
+        let state = State {
+            latest_tag: "v1.0.0".to_string(),
+            etag: "abc".to_string(),
+            last_modified: "Sun, 09 Sep 2001 01:46:40 GMT".to_string(),
+            installed_at: jiff::Timestamp::from_second(1_000_000_000).unwrap(),
+            previous_tag: None,
+            repo: None,
+            host: None,
+        };
+
+        store.save("myapp", &state).unwrap();
+
+        assert!(temp_dir.path().join("myapp").join("state.json").is_file());
+        assert_eq!(store.load("myapp").unwrap(), Some(state));
+        assert!(store.remove("myapp").unwrap());
+        assert!(!store.remove("myapp").unwrap());
+    }
+
+    #[test]
+    fn test_parse_backend() {
+        assert_eq!("json".parse::<Backend>().unwrap(), Backend::Json);
+        #[cfg(feature = "sqlite")]
+        assert_eq!("sqlite".parse::<Backend>().unwrap(), Backend::Sqlite);
+        assert_matches!(
+            "redis".parse::<Backend>(),
+            Err(StateError::UnknownBackend(_))
+        );
+    }
+
     #[test]
     fn test_load_invalid_json_syntax() {
         let temp_dir = tempdir().unwrap();
@@ -146,8 +541,11 @@ mod tests {
         let state = State {
             latest_tag: "v1.0.0".to_string(),
             etag: "xyz789".to_string(),
-            last_modified: jiff::Timestamp::from_second(1_000_000_000).unwrap(),
+            last_modified: "Sun, 09 Sep 2001 01:46:40 GMT".to_string(),
             installed_at: jiff::Timestamp::from_second(1_000_000_010).unwrap(),
+            previous_tag: None,
+            repo: None,
+            host: None,
         };
 
         let result = save_atomic("/", &state);
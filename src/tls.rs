@@ -0,0 +1,298 @@
+use std::{fmt, str::FromStr, sync::Arc};
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use rustls::{
+    ClientConfig, DigitallySignedStruct, SignatureScheme,
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{CryptoProvider, aws_lc_rs},
+    pki_types::{CertificateDer, ServerName, SignatureVerificationAlgorithm, UnixTime},
+};
+use serde::{Deserialize, Serialize, Serializer};
+use sha2::{Digest as _, Sha256};
+use thiserror::Error;
+
+/// Prefix of a pin string, matching curl's `--pinnedpubkey` format.
+const PIN_PREFIX: &str = "sha256//";
+
+#[derive(Debug, Error)]
+pub enum TlsError {
+    #[error("invalid SPKI pin '{0}': expected sha256//<base64 SHA-256 digest>")]
+    InvalidPin(String),
+    #[error("TLS configuration error: {0}")]
+    Rustls(#[from] rustls::Error),
+}
+
+pub type Result<T> = std::result::Result<T, TlsError>;
+
+/// SHA-256 digest of a certificate's DER-encoded `SubjectPublicKeyInfo`.
+///
+/// Written as `sha256//<base64>`, the format used by curl's `--pinnedpubkey` and
+/// printed by `openssl x509 -pubkey -noout | openssl pkey -pubin -outform der |
+/// openssl dgst -sha256 -binary | base64`.
+#[derive(Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct SpkiPin([u8; 32]);
+
+impl Serialize for SpkiPin {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl SpkiPin {
+    fn of_certificate(cert: &CertificateDer<'_>) -> Option<Self> {
+        let cert = webpki::EndEntityCert::try_from(cert).ok()?;
+        let spki = cert.subject_public_key_info();
+        Some(Self(Sha256::digest(spki.as_ref()).into()))
+    }
+}
+
+impl FromStr for SpkiPin {
+    type Err = TlsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        s.strip_prefix(PIN_PREFIX)
+            .and_then(|encoded| STANDARD.decode(encoded).ok())
+            .and_then(|digest| <[u8; 32]>::try_from(digest).ok())
+            .map(Self)
+            .ok_or_else(|| TlsError::InvalidPin(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for SpkiPin {
+    type Error = TlsError;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for SpkiPin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{PIN_PREFIX}{}", STANDARD.encode(self.0))
+    }
+}
+
+impl fmt::Debug for SpkiPin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SpkiPin({self})")
+    }
+}
+
+/// Certificate verifier that requires a pinned key on the server's chain.
+///
+/// The chain is first validated normally against the platform trust store; the
+/// pins only narrow which chains are accepted, they never replace CA validation.
+#[derive(Debug)]
+struct PinnedVerifier {
+    inner: Arc<dyn ServerCertVerifier>,
+    pins: Vec<SpkiPin>,
+    sig_algs: &'static [&'static dyn SignatureVerificationAlgorithm],
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+
+        if chain_matches(&self.pins, self.sig_algs, end_entity, intermediates, now) {
+            Ok(verified)
+        } else {
+            Err(rustls::Error::General(format!(
+                "no certificate presented by {} matches a pinned SPKI hash",
+                server_name.to_str()
+            )))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// Whether the end entity's key is pinned, or it chains up through
+/// `intermediates` to a pinned certificate.
+///
+/// A pinned certificate merely sent alongside the chain does not count: the path
+/// is built with only the pinned certificates as trust anchors, so a pinned key
+/// must have signed its way down to the end entity.
+fn chain_matches(
+    pins: &[SpkiPin],
+    sig_algs: &[&dyn SignatureVerificationAlgorithm],
+    end_entity: &CertificateDer<'_>,
+    intermediates: &[CertificateDer<'_>],
+    now: UnixTime,
+) -> bool {
+    if SpkiPin::of_certificate(end_entity).is_some_and(|pin| pins.contains(&pin)) {
+        return true;
+    }
+    let anchors: Vec<_> = intermediates
+        .iter()
+        .filter(|cert| SpkiPin::of_certificate(cert).is_some_and(|pin| pins.contains(&pin)))
+        .filter_map(|cert| webpki::anchor_from_trusted_cert(cert).ok())
+        .collect();
+    if anchors.is_empty() {
+        return false;
+    }
+    webpki::EndEntityCert::try_from(end_entity).is_ok_and(|cert| {
+        cert.verify_for_usage(
+            sig_algs,
+            &anchors,
+            intermediates,
+            now,
+            webpki::KeyUsage::server_auth(),
+            None,
+            None,
+        )
+        .is_ok()
+    })
+}
+
+/// Builds a rustls client config that fails closed unless the server presents a
+/// certificate whose public key matches one of `pins`.
+///
+/// # Errors
+///
+/// Returns `TlsError::Rustls` if the platform verifier or protocol versions cannot
+/// be configured.
+pub fn pinned_client_config(pins: &[SpkiPin]) -> Result<ClientConfig> {
+    let provider: Arc<CryptoProvider> = Arc::new(aws_lc_rs::default_provider());
+    let inner = rustls_platform_verifier::Verifier::new(provider.clone())?;
+    let verifier = PinnedVerifier {
+        inner: Arc::new(inner),
+        pins: pins.to_vec(),
+        sig_algs: provider.signature_verification_algorithms.all,
+    };
+
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::assert_matches;
+
+    use super::*;
+
+    const TEST_CERT: &[u8] = include_bytes!("../tests/fixtures/pinned-cert.der");
+    const TEST_CERT_PIN: &str = "sha256//hb+cNSS4MdmqkhVs//LTRmEkUgFoxbr3JEURh/MTP+E=";
+    const PINNED_CA: &[u8] = include_bytes!("../tests/fixtures/pinned-ca.der");
+    const PINNED_CA_LEAF: &[u8] = include_bytes!("../tests/fixtures/pinned-ca-leaf.der");
+    const PINNED_CA_PIN: &str = "sha256//hFIFpA/nyEiGnJcNuZgadM/dbs22HGrr8JB66SqbhBE=";
+    const OTHER_CA: &[u8] = include_bytes!("../tests/fixtures/other-ca.der");
+    const OTHER_CA_LEAF: &[u8] = include_bytes!("../tests/fixtures/other-ca-leaf.der");
+
+    fn sig_algs() -> &'static [&'static dyn SignatureVerificationAlgorithm] {
+        aws_lc_rs::default_provider()
+            .signature_verification_algorithms
+            .all
+    }
+
+    #[test]
+    fn test_parse_pin_round_trip() {
+        let pin: SpkiPin = TEST_CERT_PIN.parse().unwrap();
+        assert_eq!(pin.to_string(), TEST_CERT_PIN);
+    }
+
+    #[test]
+    fn test_parse_pin_rejects_invalid() {
+        for invalid in [
+            "hb+cNSS4MdmqkhVs//LTRmEkUgFoxbr3JEURh/MTP+E=",
+            "sha1//hb+cNSS4MdmqkhVs//LTRmEkUgFoxbr3JEURh/MTP+E=",
+            "sha256//not-base64!",
+            "sha256//AAAA",
+        ] {
+            assert_matches!(
+                invalid.parse::<SpkiPin>(),
+                Err(TlsError::InvalidPin(_)),
+                "{invalid} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_pin_of_certificate_matches_openssl() {
+        let cert = CertificateDer::from(TEST_CERT);
+        let pin = SpkiPin::of_certificate(&cert).unwrap();
+        assert_eq!(pin.to_string(), TEST_CERT_PIN);
+    }
+
+    #[test]
+    fn test_chain_matches() {
+        let cert = CertificateDer::from(TEST_CERT);
+        let pinned: SpkiPin = TEST_CERT_PIN.parse().unwrap();
+        let other = SpkiPin([0; 32]);
+        let now = UnixTime::now();
+
+        assert!(chain_matches(&[other, pinned], sig_algs(), &cert, &[], now));
+        assert!(!chain_matches(&[other], sig_algs(), &cert, &[], now));
+    }
+
+    #[test]
+    fn test_chain_matches_pinned_issuer() {
+        let pin: SpkiPin = PINNED_CA_PIN.parse().unwrap();
+        assert!(chain_matches(
+            &[pin],
+            sig_algs(),
+            &CertificateDer::from(PINNED_CA_LEAF),
+            &[CertificateDer::from(PINNED_CA)],
+            UnixTime::now(),
+        ));
+    }
+
+    #[test]
+    fn test_chain_matches_rejects_pinned_extra_certificate() {
+        // A leaf from another CA, with the pinned CA appended but not on its path.
+        let pin: SpkiPin = PINNED_CA_PIN.parse().unwrap();
+        assert!(!chain_matches(
+            &[pin],
+            sig_algs(),
+            &CertificateDer::from(OTHER_CA_LEAF),
+            &[
+                CertificateDer::from(OTHER_CA),
+                CertificateDer::from(PINNED_CA)
+            ],
+            UnixTime::now(),
+        ));
+    }
+
+    #[test]
+    fn test_pinned_client_config_builds() {
+        let pin: SpkiPin = TEST_CERT_PIN.parse().unwrap();
+        assert!(pinned_client_config(&[pin]).is_ok());
+    }
+}
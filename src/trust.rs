@@ -0,0 +1,335 @@
+use std::{fmt, fs, io, str::FromStr};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use jiff::Timestamp;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{fsops, github::Asset};
+
+/// Placeholder for the release version in recorded checksum file names.
+const VERSION_PLACEHOLDER: &str = "{version}";
+
+#[derive(Debug, Error)]
+pub enum TrustError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("invalid trust file: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error("unknown trust policy '{0}': expected 'warn', 'fail', or 'off'")]
+    UnknownPolicy(String),
+}
+
+pub type Result<T> = std::result::Result<T, TrustError>;
+
+/// What `update` does when a release's publisher differs from the one recorded at
+/// first install.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Policy {
+    /// Log a warning and install anyway.
+    #[default]
+    Warn,
+    /// Refuse to install.
+    Fail,
+    /// Neither record nor compare publishers.
+    Off,
+}
+
+impl FromStr for Policy {
+    type Err = TrustError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "warn" => Ok(Self::Warn),
+            "fail" => Ok(Self::Fail),
+            "off" => Ok(Self::Off),
+            other => Err(TrustError::UnknownPolicy(other.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warn => f.write_str("warn"),
+            Self::Fail => f.write_str("fail"),
+            Self::Off => f.write_str("off"),
+        }
+    }
+}
+
+/// How a release vouches for its asset.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind", content = "name")]
+pub enum Attestation {
+    /// A checksum file, named with the version replaced by `{version}`.
+    ChecksumFile(String),
+    /// A digest the source lists for the asset itself.
+    AssetDigest,
+    /// Nothing; verification was skipped.
+    None,
+}
+
+impl fmt::Display for Attestation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ChecksumFile(name) => write!(f, "checksum file {name}"),
+            Self::AssetDigest => f.write_str("asset digest"),
+            Self::None => f.write_str("no checksum"),
+        }
+    }
+}
+
+/// Who published a release, as far as the source reveals it.
+///
+/// Upstreams without signing infrastructure still leave traces of their publisher:
+/// the account that uploaded the asset and checksum file, where they are served from,
+/// and the checksum file's naming scheme. A change in any of them deserves a look.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Identity {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset_host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset_uploader: Option<String>,
+    pub attestation: Attestation,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum_host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum_uploader: Option<String>,
+}
+
+impl Identity {
+    /// The identity of a release of `tag` whose `asset` is vouched for by
+    /// `checksum_asset`, by its own digest (`asset_digest`), or by nothing.
+    #[must_use]
+    pub fn observe(
+        tag: &str,
+        asset: &Asset,
+        checksum_asset: Option<&Asset>,
+        asset_digest: bool,
+    ) -> Self {
+        let attestation = match checksum_asset {
+            Some(checksum_asset) => {
+                Attestation::ChecksumFile(version_template(&checksum_asset.name, tag))
+            }
+            None if asset_digest => Attestation::AssetDigest,
+            None => Attestation::None,
+        };
+        Self {
+            asset_host: host(&asset.browser_download_url),
+            asset_uploader: asset.uploader.as_ref().map(|u| u.login.clone()),
+            attestation,
+            checksum_host: checksum_asset.and_then(|a| host(&a.browser_download_url)),
+            checksum_uploader: checksum_asset
+                .and_then(|a| a.uploader.as_ref())
+                .map(|u| u.login.clone()),
+        }
+    }
+
+    /// Describes each way `observed` differs from `self`, e.g.
+    /// `checksum file uploader changed from ci-bot to mallory`.
+    #[must_use]
+    pub fn changes(&self, observed: &Self) -> Vec<String> {
+        fn describe(value: Option<&str>) -> &str {
+            value.unwrap_or("(unknown)")
+        }
+
+        let mut changes = Vec::new();
+        for (label, recorded, now) in [
+            (
+                "asset host",
+                self.asset_host.as_deref(),
+                observed.asset_host.as_deref(),
+            ),
+            (
+                "asset uploader",
+                self.asset_uploader.as_deref(),
+                observed.asset_uploader.as_deref(),
+            ),
+            (
+                "checksum file host",
+                self.checksum_host.as_deref(),
+                observed.checksum_host.as_deref(),
+            ),
+            (
+                "checksum file uploader",
+                self.checksum_uploader.as_deref(),
+                observed.checksum_uploader.as_deref(),
+            ),
+        ] {
+            if recorded != now {
+                changes.push(format!(
+                    "{label} changed from {} to {}",
+                    describe(recorded),
+                    describe(now)
+                ));
+            }
+        }
+        if self.attestation != observed.attestation {
+            changes.push(format!(
+                "verified by {} instead of {}",
+                observed.attestation, self.attestation
+            ));
+        }
+        changes
+    }
+}
+
+/// The publisher identity trusted on first use, written by `update`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    pub identity: Identity,
+    /// The release the identity was first seen on.
+    pub tag: String,
+    pub recorded_at: Timestamp,
+}
+
+/// `name` with the version in `tag` (with or without a leading `v`) replaced by
+/// `{version}`, so that `myapp-1.2.0.sha256` and `myapp-1.3.0.sha256` compare equal.
+fn version_template(name: &str, tag: &str) -> String {
+    let version = tag.strip_prefix('v').unwrap_or(tag);
+    if version.is_empty() {
+        return name.to_string();
+    }
+    name.replace(tag, VERSION_PLACEHOLDER)
+        .replace(version, VERSION_PLACEHOLDER)
+}
+
+fn host(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_string(),
+    })
+}
+
+/// Path of the trusted identity for `app`: `<state_directory>/<app>/trust.json`.
+#[must_use]
+pub fn path(state_directory: &Utf8Path, app: &str) -> Utf8PathBuf {
+    state_directory.join(app).join("trust.json")
+}
+
+/// Loads the trusted identity for `app`, returning `Ok(None)` if none was recorded.
+///
+/// # Errors
+///
+/// Returns an error if the trust file exists but cannot be read or parsed.
+pub fn load(state_directory: &Utf8Path, app: &str) -> Result<Option<Record>> {
+    match fs::read_to_string(path(state_directory, app)) {
+        Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Atomically writes the trusted identity for `app`, replacing any previous one.
+///
+/// # Errors
+///
+/// Returns an error if the state directory cannot be created or the file cannot be
+/// written, synced, or persisted.
+pub fn save(state_directory: &Utf8Path, app: &str, record: &Record) -> Result<()> {
+    fsops::write_json_atomic(path(state_directory, app), record)
+}
+
+#[cfg(test)]
+mod tests {
+    use camino_tempfile::tempdir;
+
+    use super::*;
+    use crate::github::Uploader;
+
+    fn asset(name: &str, uploader: Option<&str>) -> Asset {
+        Asset {
+            name: name.to_string(),
+            url: format!("https://api.github.com/repos/owner/myapp/releases/assets/{name}"),
+            browser_download_url: format!(
+                "https://github.com/owner/myapp/releases/download/v1.2.0/{name}"
+            ),
+            size: 1024,
+            digest: None,
+            uploader: uploader.map(|login| Uploader {
+                login: login.to_string(),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_observe_templates_checksum_file_name() {
+        let identity = Identity::observe(
+            "v1.2.0",
+            &asset("myapp-1.2.0-linux.tar.gz", Some("ci-bot")),
+            Some(&asset("myapp-1.2.0.sha256", Some("ci-bot"))),
+            false,
+        );
+
+        assert_eq!(identity.asset_host.as_deref(), Some("github.com"));
+        assert_eq!(
+            identity.attestation,
+            Attestation::ChecksumFile("myapp-{version}.sha256".to_string())
+        );
+        assert_eq!(identity.checksum_uploader.as_deref(), Some("ci-bot"));
+    }
+
+    #[test]
+    fn test_changes_between_releases() {
+        let trusted = Identity::observe(
+            "v1.2.0",
+            &asset("myapp.tar.gz", Some("ci-bot")),
+            Some(&asset("myapp-v1.2.0-SHA256SUMS", Some("ci-bot"))),
+            false,
+        );
+        let same = Identity::observe(
+            "v1.3.0",
+            &asset("myapp.tar.gz", Some("ci-bot")),
+            Some(&asset("myapp-v1.3.0-SHA256SUMS", Some("ci-bot"))),
+            false,
+        );
+        let changed = Identity::observe(
+            "v1.3.0",
+            &asset("myapp.tar.gz", Some("ci-bot")),
+            Some(&asset("myapp-v1.3.0-SHA256SUMS", Some("mallory"))),
+            false,
+        );
+
+        assert!(trusted.changes(&same).is_empty());
+        assert_eq!(
+            trusted.changes(&changed),
+            ["checksum file uploader changed from ci-bot to mallory"]
+        );
+        assert_eq!(
+            trusted.changes(&Identity::observe(
+                "v1.3.0",
+                &asset("myapp.tar.gz", None),
+                None,
+                false
+            )),
+            [
+                "asset uploader changed from ci-bot to (unknown)",
+                "checksum file host changed from github.com to (unknown)",
+                "checksum file uploader changed from ci-bot to (unknown)",
+                "verified by no checksum instead of checksum file myapp-{version}-SHA256SUMS",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_save_load() {
+        let temp_dir = tempdir().unwrap();
+        assert_eq!(load(temp_dir.path(), "myapp").unwrap(), None);
+
+        let record = Record {
+            identity: Identity::observe("v1.2.0", &asset("myapp.tar.gz", None), None, true),
+            tag: "v1.2.0".to_string(),
+            recorded_at: Timestamp::from_second(1_000_000_000).unwrap(),
+        };
+        save(temp_dir.path(), "myapp", &record).unwrap();
+
+        assert_eq!(load(temp_dir.path(), "myapp").unwrap(), Some(record));
+    }
+}
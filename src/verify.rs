@@ -93,11 +93,33 @@ pub fn parse_checksum_text(s: &str) -> Result<Vec<(String, String)>> {
     Ok(result)
 }
 
-/// Fetches a checksum file from a URL and verifies a local file against it.
+/// Computes the lowercase hex SHA-256 digest of a file.
 ///
-/// Downloads the checksum file (e.g., SHA256SUMS), finds the entry matching
-/// `asset_filename`, computes the SHA256 hash of the file at `downloaded_path`,
-/// and compares them.
+/// # Errors
+///
+/// Returns an error if the file cannot be opened or read.
+pub fn sha256_file(path: &Utf8Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 8192];
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        }))
+}
+
+/// Fetches a checksum file from a URL and returns the expected hex digest for
+/// `asset_filename`.
 ///
 /// # Errors
 ///
@@ -105,15 +127,12 @@ pub fn parse_checksum_text(s: &str) -> Result<Vec<(String, String)>> {
 /// - `VerifyError::Request` - HTTP request fails, times out, or returns non-2xx status
 /// - `VerifyError::ParseError` - Checksum file format is invalid
 /// - `VerifyError::NotFound` - `asset_filename` is not found in the checksum file
-/// - `VerifyError::Mismatch` - Computed hash does not match expected hash
-/// - `VerifyError::Io` - File reading fails
-pub async fn fetch_and_verify_checksum(
+pub async fn fetch_expected_checksum(
     asset_filename: &str,
     checksum_url: &str,
     token: Option<&str>,
     client: reqwest::Client,
-    downloaded_path: &Utf8Path,
-) -> Result<()> {
+) -> Result<String> {
     let mut request = client
         .get(checksum_url)
         .header("Accept", "application/octet-stream");
@@ -125,41 +144,62 @@ pub async fn fetch_and_verify_checksum(
     let response = request.send().await?.error_for_status()?;
     let checksum_text = response.text().await?;
 
-    let checksums: HashMap<_, _> = parse_checksum_text(&checksum_text)?
+    let mut checksums: HashMap<_, _> = parse_checksum_text(&checksum_text)?
         .into_iter()
         .map(|(hex, filename)| (filename, hex))
         .collect();
 
-    let expected_hex = checksums
-        .get(asset_filename)
-        .ok_or_else(|| VerifyError::NotFound(asset_filename.to_string()))?;
+    checksums
+        .remove(asset_filename)
+        .ok_or_else(|| VerifyError::NotFound(asset_filename.to_string()))
+}
 
+/// Fetches a checksum file from a URL and verifies a local file against it.
+///
+/// Downloads the checksum file (e.g., SHA256SUMS), finds the entry matching
+/// `asset_filename`, computes the SHA256 hash of the file at `downloaded_path`,
+/// and compares them.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - `VerifyError::Request` - HTTP request fails, times out, or returns non-2xx status
+/// - `VerifyError::ParseError` - Checksum file format is invalid
+/// - `VerifyError::NotFound` - `asset_filename` is not found in the checksum file
+/// - `VerifyError::Mismatch` - Computed hash does not match expected hash
+/// - `VerifyError::Io` - File reading fails
+pub async fn fetch_and_verify_checksum(
+    asset_filename: &str,
+    checksum_url: &str,
+    token: Option<&str>,
+    client: reqwest::Client,
+    downloaded_path: &Utf8Path,
+) -> Result<()> {
+    let expected_hex = fetch_expected_checksum(asset_filename, checksum_url, token, client).await?;
+    verify_digest(asset_filename, downloaded_path, &expected_hex).await
+}
+
+/// Verifies a local file against an expected hex SHA-256 digest.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - `VerifyError::Mismatch` - Computed hash does not match `expected_hex`
+/// - `VerifyError::Io` - File reading fails
+pub async fn verify_digest(
+    asset_filename: &str,
+    downloaded_path: &Utf8Path,
+    expected_hex: &str,
+) -> Result<()> {
     let path = downloaded_path.to_owned();
-    let actual_hex = tokio::task::spawn_blocking(move || {
-        let mut file = File::open(&path)?;
-        let mut hasher = Sha256::new();
-        let mut buffer = [0u8; 8192];
-        loop {
-            let n = file.read(&mut buffer)?;
-            if n == 0 {
-                break;
-            }
-            hasher.update(&buffer[..n]);
-        }
-        let actual_hash = hasher.finalize();
-        let actual_hex = actual_hash.iter().fold(String::new(), |mut hex, byte| {
-            let _ = write!(hex, "{byte:02x}");
-            hex
-        });
-        Ok::<String, io::Error>(actual_hex)
-    })
-    .await
-    .map_err(io::Error::other)??;
+    let actual_hex = tokio::task::spawn_blocking(move || sha256_file(&path))
+        .await
+        .map_err(io::Error::other)??;
 
     if !actual_hex.eq_ignore_ascii_case(expected_hex) {
         return Err(VerifyError::Mismatch {
             filename: asset_filename.to_string(),
-            expected: expected_hex.clone(),
+            expected: expected_hex.to_string(),
             actual: actual_hex,
         });
     }
@@ -178,6 +218,18 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_sha256_file() {
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.child("data");
+        file.write_str("hello\n").unwrap();
+
+        assert_eq!(
+            sha256_file(&file).unwrap(),
+            "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03"
+        );
+    }
+
     #[test]
     fn test_parse_valid_two_space_format() {
         let input = "a".repeat(64) + "  file.tar.gz";
@@ -3,10 +3,14 @@ use std::{fs, io};
 use camino::{Utf8Path, Utf8PathBuf};
 use thiserror::Error;
 
+use crate::fsops;
+
 #[derive(Debug, Error)]
 pub enum VersionError {
     #[error("IO error: {0}")]
     Io(#[from] io::Error),
+    #[error("filesystem error: {0}")]
+    FsOps(#[from] fsops::FsOpsError),
 }
 
 pub type Result<T> = std::result::Result<T, VersionError>;
@@ -15,7 +19,8 @@ pub type Result<T> = std::result::Result<T, VersionError>;
 ///
 /// Looks under `<prefix>/<app>/bin/` for symlinks that point into `../releases/<tag>/...`
 /// and extracts the `<tag>` component. When multiple symlinks exist, returns the tag from
-/// the lexicographically last symlink name.
+/// the lexicographically last symlink name. Links kept from an older release (see
+/// [`fsops::kept_links`]) are ignored.
 ///
 /// Returns `Ok(None)` if:
 /// - The bin directory does not exist
@@ -37,10 +42,15 @@ pub fn current_tag<P: AsRef<Utf8Path>>(prefix: P, app: &str) -> Result<Option<St
         return Ok(None);
     }
 
+    let kept = fsops::kept_links(&bin_dir)?;
+
     let mut symlinks = fs::read_dir(&bin_dir)?
         .map(|entry| {
             let entry = entry?;
             let path = entry.path();
+            if kept.iter().any(|link| link.as_std_path() == path) {
+                return Ok(None);
+            }
 
             let metadata = fs::symlink_metadata(&path)?;
             if !metadata.is_symlink() {
@@ -259,6 +269,30 @@ mod tests {
         assert_eq!(result, Some("v1.2.4".to_string()));
     }
 
+    #[test]
+    fn test_current_tag_ignores_kept_links() {
+        let temp_dir = tempdir().unwrap();
+        let opt_root = temp_dir.child("opt");
+        let app = "myapp";
+
+        let bin_dir = opt_root.child(app).child("bin");
+        bin_dir.create_dir_all().unwrap();
+
+        symlink("../releases/v1.2.4/app", bin_dir.child("app").as_std_path()).unwrap();
+        symlink(
+            "../releases/v1.2.3/zhelper",
+            bin_dir.child("zhelper").as_std_path(),
+        )
+        .unwrap();
+        bin_dir
+            .child(fsops::KEPT_MANIFEST)
+            .write_str("zhelper\n")
+            .unwrap();
+
+        let result = current_tag(&opt_root, app).unwrap();
+        assert_eq!(result, Some("v1.2.4".to_string()));
+    }
+
     #[test]
     fn test_current_tag_absolute_path_symlink() {
         let temp_dir = tempdir().unwrap();
@@ -0,0 +1,199 @@
+use std::{fs, os::unix, os::unix::fs::PermissionsExt};
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use camino::Utf8Path;
+use camino_tempfile::Utf8TempDir;
+
+fn create_release(install_root: &Utf8Path, app: &str, tag: &str) {
+    let release_dir = install_root.join(app).join("releases").join(tag);
+    fs::create_dir_all(&release_dir).unwrap();
+    let binary = release_dir.join(app);
+    fs::write(&binary, format!("#!/bin/sh\necho '{app} {tag}'\n")).unwrap();
+    fs::set_permissions(&binary, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+fn run(install_root: &Utf8Path, command: &str, args: &[&str]) -> std::process::Output {
+    cargo_bin_cmd!("distronomicon")
+        .arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg(command)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn adopt_records_hand_installed_app() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+    create_release(&install_root, "myapp", "v1.0.0");
+    create_release(&install_root, "myapp", "v1.1.0");
+    let bin_dir = install_root.join("myapp/bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    // An install script that links by absolute path.
+    unix::fs::symlink(
+        install_root.join("myapp/releases/v1.1.0/myapp"),
+        bin_dir.join("myapp"),
+    )
+    .unwrap();
+
+    let output = run(
+        &install_root,
+        "adopt",
+        &[
+            "--repo",
+            "owner/myapp",
+            "--state-directory",
+            state_dir.as_str(),
+        ],
+    );
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        String::from_utf8_lossy(&output.stdout)
+            .contains("Adopted myapp at v1.1.0 from owner/myapp (1 managed link(s))")
+    );
+
+    let state: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(state_dir.join("myapp/state.json")).unwrap())
+            .unwrap();
+    assert_eq!(state["latest_tag"], "v1.1.0");
+    assert_eq!(state["previous_tag"], "v1.0.0");
+    assert_eq!(state["repo"], "owner/myapp");
+    assert_eq!(state["etag"], "");
+    assert_eq!(
+        fs::read_to_string(bin_dir.join(".distronomicon-links")).unwrap(),
+        "myapp\n"
+    );
+
+    let output = run(
+        &install_root,
+        "adopt",
+        &[
+            "--repo",
+            "owner/myapp",
+            "--state-directory",
+            state_dir.as_str(),
+        ],
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("myapp is already managed by distronomicon (v1.1.0 from owner/myapp")
+    );
+
+    let output = run(
+        &install_root,
+        "rollback",
+        &["--state-directory", state_dir.as_str()],
+    );
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        fs::read_link(bin_dir.join("myapp")).unwrap(),
+        Utf8Path::new("../releases/v1.0.0/myapp")
+    );
+}
+
+#[test]
+fn adopt_requires_links_into_releases() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+    create_release(&install_root, "myapp", "v1.0.0");
+
+    let output = run(
+        &install_root,
+        "adopt",
+        &[
+            "--repo",
+            "owner/myapp",
+            "--state-directory",
+            state_dir.as_str(),
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("adopt expects each release"));
+    assert!(!state_dir.join("myapp/state.json").exists());
+}
+
+#[test]
+fn adopt_converts_flat_install() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+    let app_dir = install_root.join("myapp");
+    fs::create_dir_all(&app_dir).unwrap();
+    let binary = app_dir.join("myapp");
+    fs::write(&binary, "#!/bin/sh\necho 'myapp v0.9.0'\n").unwrap();
+    fs::set_permissions(&binary, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = run(
+        &install_root,
+        "adopt",
+        &[
+            "--repo",
+            "owner/myapp",
+            "--state-directory",
+            state_dir.as_str(),
+            "--from-layout",
+            "flat",
+            "--tag",
+            "v0.9.0",
+        ],
+    );
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        String::from_utf8_lossy(&output.stdout)
+            .contains("Adopted myapp at v0.9.0 from owner/myapp (1 managed link(s))")
+    );
+
+    assert_eq!(fs::read_link(&binary).unwrap(), Utf8Path::new("bin/myapp"));
+    assert_eq!(
+        fs::read_link(app_dir.join("bin/myapp")).unwrap(),
+        Utf8Path::new("../releases/v0.9.0/myapp")
+    );
+    let state: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(state_dir.join("myapp/state.json")).unwrap())
+            .unwrap();
+    assert_eq!(state["latest_tag"], "v0.9.0");
+}
+
+#[test]
+fn adopt_flat_layout_requires_tag() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let install_root = temp_dir.path().join("opt");
+
+    let output = run(
+        &install_root,
+        "adopt",
+        &[
+            "--repo",
+            "owner/myapp",
+            "--state-directory",
+            temp_dir.path().join("state").as_str(),
+            "--from-layout",
+            "flat",
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--tag <TAG>"));
+}
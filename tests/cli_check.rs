@@ -1,12 +1,15 @@
-use std::{fs, os::unix};
+use std::{
+    fs,
+    os::unix::{self, fs::PermissionsExt as _},
+};
 
 use assert_cmd::cargo::cargo_bin_cmd;
 use camino::Utf8PathBuf;
 use camino_tempfile::Utf8TempDir;
-use jiff::Timestamp;
+use jiff::{SignedDuration, Timestamp};
 use wiremock::{
     Mock, MockServer, ResponseTemplate,
-    matchers::{method, path},
+    matchers::{header, method, path},
 };
 
 fn create_state_file(state_dir: &Utf8PathBuf, app: &str, tag: &str, etag: &str) {
@@ -81,6 +84,112 @@ async fn check_with_304_not_modified() {
     insta::assert_snapshot!(stdout);
 }
 
+#[tokio::test]
+async fn verbose_check_shows_rate_limit_budget() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/latest"))
+        .respond_with(
+            ResponseTemplate::new(304)
+                .insert_header("etag", "\"abc123\"")
+                .insert_header("x-ratelimit-limit", "5000")
+                .insert_header("x-ratelimit-remaining", "4321")
+                .insert_header("x-ratelimit-reset", "1761656400"),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    let install_root = temp_dir.path().join("opt");
+
+    create_state_file(&state_dir, "myapp", "v1.0.0", "\"abc123\"");
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+
+    let output = cargo_bin_cmd!("distronomicon")
+        .arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg("-v")
+        .arg("check")
+        .arg("--repo")
+        .arg("owner/repo")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .arg("--github-host")
+        .arg(mock_server.uri())
+        .env("TZ", "UTC")
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("up-to-date: v1.0.0"));
+    assert!(
+        stdout.contains("rate-limit: 4321/5000 remaining, resets at 2025-10-28 13:00:00 +00:00")
+    );
+}
+
+#[tokio::test]
+async fn quiet_check_prints_only_available_updates() {
+    let unchanged_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/latest"))
+        .respond_with(ResponseTemplate::new(304).insert_header("etag", "\"abc123\""))
+        .mount(&unchanged_server)
+        .await;
+
+    let updated_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/latest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "tag_name": "v1.1.0",
+            "prerelease": false,
+            "draft": false,
+            "assets": []
+        })))
+        .mount(&updated_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    let install_root = temp_dir.path().join("opt");
+
+    create_state_file(&state_dir, "myapp", "v1.0.0", "\"abc123\"");
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+
+    let check = |server: &MockServer| {
+        cargo_bin_cmd!("distronomicon")
+            .arg("--app")
+            .arg("myapp")
+            .arg("--install-root")
+            .arg(install_root.as_str())
+            .arg("--quiet")
+            .arg("check")
+            .arg("--repo")
+            .arg("owner/repo")
+            .arg("--state-directory")
+            .arg(state_dir.as_str())
+            .arg("--github-host")
+            .arg(server.uri())
+            .output()
+            .unwrap()
+    };
+
+    let output = check(&unchanged_server);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+
+    let output = check(&updated_server);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "update-available: v1.0.0 -> v1.1.0\n"
+    );
+}
+
 #[tokio::test]
 async fn check_with_update_available() {
     let mock_server = MockServer::start().await;
@@ -131,12 +240,116 @@ async fn check_with_update_available() {
         .output()
         .unwrap();
 
-    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.status.code(), Some(2));
 
     let stdout = String::from_utf8_lossy(&output.stdout);
     insta::assert_snapshot!(stdout);
 }
 
+#[tokio::test]
+async fn check_reports_download_size() {
+    let mock_server = MockServer::start().await;
+
+    let release_json = serde_json::json!({
+        "tag_name": "v1.1.0",
+        "prerelease": false,
+        "assets": [{
+            "name": "myapp.tar.gz",
+            "url": "https://api.github.com/repos/owner/repo/releases/assets/1",
+            "browser_download_url": "https://github.com/owner/repo/releases/download/v1.1.0/myapp.tar.gz",
+            "size": 52_428_800
+        }]
+    });
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/latest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&release_json))
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    let install_root = temp_dir.path().join("opt");
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+
+    let output = cargo_bin_cmd!("distronomicon")
+        .arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg("check")
+        .arg("--repo")
+        .arg("owner/repo")
+        .arg("--pattern")
+        .arg(r"myapp\.tar\.gz")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .arg("--github-host")
+        .arg(mock_server.uri())
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "update-available: v1.0.0 -> v1.1.0\n  download: myapp.tar.gz (50.0 MiB)\n"
+    );
+}
+
+#[tokio::test]
+async fn check_exit_code_on_update_is_configurable() {
+    let mock_server = MockServer::start().await;
+
+    let release_json = serde_json::json!({
+        "tag_name": "v1.1.0",
+        "prerelease": false,
+        "draft": false,
+        "assets": []
+    });
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/latest"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(&release_json)
+                .insert_header("etag", "\"def456\"")
+                .insert_header("last-modified", "Tue, 28 Oct 2025 12:00:00 GMT"),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    let install_root = temp_dir.path().join("opt");
+
+    create_state_file(&state_dir, "myapp", "v1.0.0", "\"abc123\"");
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+
+    for (exit_code, expected) in [("0", 0), ("10", 10)] {
+        let output = cargo_bin_cmd!("distronomicon")
+            .arg("--app")
+            .arg("myapp")
+            .arg("--install-root")
+            .arg(install_root.as_str())
+            .arg("check")
+            .arg("--repo")
+            .arg("owner/repo")
+            .arg("--state-directory")
+            .arg(state_dir.as_str())
+            .arg("--github-host")
+            .arg(mock_server.uri())
+            .arg("--exit-code-on-update")
+            .arg(exit_code)
+            .output()
+            .unwrap();
+
+        assert_eq!(output.status.code(), Some(expected));
+        assert!(
+            String::from_utf8_lossy(&output.stdout).contains("update-available: v1.0.0 -> v1.1.0")
+        );
+    }
+}
+
 #[tokio::test]
 async fn check_no_current_version() {
     let mock_server = MockServer::start().await;
@@ -184,7 +397,7 @@ async fn check_no_current_version() {
         .output()
         .unwrap();
 
-    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.status.code(), Some(2));
 
     let stdout = String::from_utf8_lossy(&output.stdout);
     insta::assert_snapshot!(stdout);
@@ -223,6 +436,49 @@ async fn check_network_error_exits_1() {
     assert_eq!(output.status.code(), Some(1));
 }
 
+#[tokio::test]
+async fn check_direct_proxy_mode_ignores_proxy_environment() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/latest"))
+        .respond_with(ResponseTemplate::new(304).insert_header("etag", "\"abc123\""))
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    let install_root = temp_dir.path().join("opt");
+
+    create_state_file(&state_dir, "myapp", "v1.0.0", "\"abc123\"");
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+
+    let check = |proxy_mode: &str| {
+        cargo_bin_cmd!("distronomicon")
+            .env("http_proxy", "http://127.0.0.1:9")
+            .env_remove("no_proxy")
+            .env_remove("NO_PROXY")
+            .arg("--app")
+            .arg("myapp")
+            .arg("--install-root")
+            .arg(install_root.as_str())
+            .arg("--proxy-mode")
+            .arg(proxy_mode)
+            .arg("check")
+            .arg("--repo")
+            .arg("owner/repo")
+            .arg("--state-directory")
+            .arg(state_dir.as_str())
+            .arg("--github-host")
+            .arg(mock_server.uri())
+            .output()
+            .unwrap()
+    };
+
+    assert_eq!(check("env").status.code(), Some(1));
+    assert_eq!(check("direct").status.code(), Some(0));
+}
+
 #[tokio::test]
 async fn check_first_run_no_state() {
     let mock_server = MockServer::start().await;
@@ -270,7 +526,7 @@ async fn check_first_run_no_state() {
         .output()
         .unwrap();
 
-    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.status.code(), Some(2));
 
     let stdout = String::from_utf8_lossy(&output.stdout);
     insta::assert_snapshot!(stdout);
@@ -329,3 +585,777 @@ async fn state_validators_updated_on_304() {
 
     assert_eq!(state["etag"].as_str(), Some("\"new-etag\""));
 }
+
+#[tokio::test]
+async fn legacy_last_modified_is_sent_as_http_date_and_replaced_verbatim() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/latest"))
+        // `header()` splits values on commas, which HTTP dates contain.
+        .and(|request: &wiremock::Request| {
+            request
+                .headers
+                .get("if-modified-since")
+                .is_some_and(|value| value == "Tue, 28 Oct 2025 12:00:00 GMT")
+        })
+        .respond_with(
+            ResponseTemplate::new(304)
+                .insert_header("etag", "\"abc123\"")
+                .insert_header("last-modified", "Wed, 29 Oct 2025 08:00:00 GMT"),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    let install_root = temp_dir.path().join("opt");
+
+    let state_path = state_dir.join("myapp").join("state.json");
+    fs::create_dir_all(state_path.parent().unwrap()).unwrap();
+    fs::write(
+        &state_path,
+        r#"{"latest_tag":"v1.0.0","etag":"\"abc123\"","last_modified":"2025-10-28T12:00:00Z","installed_at":"2025-10-28T12:00:00Z"}"#,
+    )
+    .unwrap();
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+
+    let output = cargo_bin_cmd!("distronomicon")
+        .arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg("check")
+        .arg("--repo")
+        .arg("owner/repo")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .arg("--github-host")
+        .arg(mock_server.uri())
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+
+    let state: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&state_path).unwrap()).unwrap();
+    assert_eq!(
+        state["last_modified"].as_str(),
+        Some("Wed, 29 Oct 2025 08:00:00 GMT")
+    );
+}
+
+#[tokio::test]
+async fn check_json_output_reports_update() {
+    let mock_server = MockServer::start().await;
+
+    let release_json = serde_json::json!({
+        "tag_name": "v1.1.0",
+        "prerelease": false,
+        "draft": false,
+        "published_at": "2025-10-28T12:00:00Z",
+        "assets": [
+            {
+                "name": "myapp.tar.gz",
+                "url": "https://api.github.com/repos/owner/repo/releases/assets/1",
+                "browser_download_url": "https://github.com/owner/repo/releases/download/v1.1.0/myapp.tar.gz",
+                "size": 1024
+            },
+            {
+                "name": "SHA256SUMS",
+                "url": "https://api.github.com/repos/owner/repo/releases/assets/2",
+                "browser_download_url": "https://github.com/owner/repo/releases/download/v1.1.0/SHA256SUMS",
+                "size": 80
+            }
+        ]
+    });
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/latest"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(&release_json)
+                .insert_header("etag", "\"def456\"")
+                .insert_header("x-ratelimit-limit", "60")
+                .insert_header("x-ratelimit-remaining", "42")
+                .insert_header("x-ratelimit-reset", "1761656400"),
+        )
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    let install_root = temp_dir.path().join("opt");
+
+    create_state_file(&state_dir, "myapp", "v1.0.0", "\"abc123\"");
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+
+    let output = cargo_bin_cmd!("distronomicon")
+        .arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg("check")
+        .arg("--repo")
+        .arg("owner/repo")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .arg("--github-host")
+        .arg(mock_server.uri())
+        .arg("--pattern")
+        .arg(r"myapp\.tar\.gz")
+        .arg("--output")
+        .arg("json")
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        report,
+        serde_json::json!({
+            "app": "myapp",
+            "repo": "owner/repo",
+            "installed_tag": "v1.0.0",
+            "latest_tag": "v1.1.0",
+            "update_available": true,
+            "held": false,
+            "blocked": null,
+            "etag": "\"def456\"",
+            "release_date": "2025-10-28T12:00:00Z",
+            "assets": ["myapp.tar.gz", "SHA256SUMS"],
+            "download": {"asset": "myapp.tar.gz", "size": 1024},
+            "pinned": null,
+            "rate_limit": {
+                "remaining": 42,
+                "limit": 60,
+                "reset": "2025-10-28T13:00:00Z",
+            },
+            "drift": null,
+        })
+    );
+}
+
+#[tokio::test]
+async fn weak_etag_matching_state_does_not_rewrite_state() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/latest"))
+        .respond_with(
+            ResponseTemplate::new(304)
+                .insert_header("etag", "W/\"abc123\"")
+                .insert_header("last-modified", "Wed, 29 Oct 2025 12:00:00 GMT"),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    let install_root = temp_dir.path().join("opt");
+
+    let state_path = state_dir.join("myapp").join("state.json");
+    fs::create_dir_all(state_path.parent().unwrap()).unwrap();
+    let state = serde_json::json!({
+        "latest_tag": "v1.0.0",
+        "etag": "\"abc123\"",
+        "last_modified": "Wed, 29 Oct 2025 12:00:00 GMT",
+        "installed_at": "2025-10-29T12:00:00Z",
+    });
+    let original = serde_json::to_string_pretty(&state).unwrap();
+    fs::write(&state_path, &original).unwrap();
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+
+    let mut cmd = cargo_bin_cmd!("distronomicon");
+    let output = cmd
+        .arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg("check")
+        .arg("--repo")
+        .arg("owner/repo")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .arg("--github-host")
+        .arg(mock_server.uri())
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(fs::read_to_string(&state_path).unwrap(), original);
+}
+
+#[tokio::test]
+async fn check_exports_change_record_when_update_available() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/latest"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({
+                    "tag_name": "v1.1.0",
+                    "prerelease": false,
+                    "draft": false,
+                    "body": "Security fix for CVE-2025-12345.",
+                    "html_url": "https://github.com/owner/repo/releases/tag/v1.1.0",
+                    "assets": [{
+                        "name": "myapp-1.1.0.tar.gz",
+                        "url": "https://api.github.com/repos/owner/repo/releases/assets/1",
+                        "browser_download_url": "https://github.com/owner/repo/releases/download/v1.1.0/myapp-1.1.0.tar.gz",
+                        "size": 1024,
+                        "digest": "sha256:abc123"
+                    }]
+                }))
+                .insert_header("etag", "\"abc123\""),
+        )
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    let install_root = temp_dir.path().join("opt");
+    let record_path = temp_dir.path().join("change.json");
+
+    create_state_file(&state_dir, "myapp", "v1.0.0", "\"abc123\"");
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+
+    let mut cmd = cargo_bin_cmd!("distronomicon");
+    let output = cmd
+        .arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg("check")
+        .arg("--repo")
+        .arg("owner/repo")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .arg("--github-host")
+        .arg(mock_server.uri())
+        .arg("--export-change-record")
+        .arg(record_path.as_str())
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("update-available: v1.0.0 -> v1.1.0"));
+
+    let requests = mock_server.received_requests().await.unwrap();
+    assert!(requests[0].headers.get("if-none-match").is_none());
+
+    let record: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&record_path).unwrap()).unwrap();
+    assert_eq!(record["app"], "myapp");
+    assert_eq!(record["current_tag"], "v1.0.0");
+    assert_eq!(record["target_tag"], "v1.1.0");
+    assert_eq!(record["cve_references"][0], "CVE-2025-12345");
+    assert_eq!(record["assets"][0]["digest"], "sha256:abc123");
+}
+
+#[tokio::test]
+async fn deep_check_reports_drift_from_release_manifest() {
+    const FAKE_BINARY_SHA256: &str =
+        "17a815baf7efd5341b39e803d557cea4b127e125af8a5f92f0edd6322a0c38e5";
+
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/latest"))
+        .respond_with(ResponseTemplate::new(304).insert_header("etag", "\"abc123\""))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/tags/v1.0.0"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "tag_name": "v1.0.0",
+            "prerelease": false,
+            "draft": false,
+            "assets": [{
+                "name": "manifest.json",
+                "url": format!("{}/assets/manifest", mock_server.uri()),
+                "browser_download_url": "https://github.com/owner/repo/releases/download/v1.0.0/manifest.json",
+                "size": 100
+            }]
+        })))
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/assets/manifest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "myapp-v1.0.0/myapp": FAKE_BINARY_SHA256,
+            "myapp-v1.0.0/README.md": FAKE_BINARY_SHA256,
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    let install_root = temp_dir.path().join("opt");
+
+    create_state_file(&state_dir, "myapp", "v1.0.0", "\"abc123\"");
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+    let readme = install_root.join("myapp/releases/v1.0.0/README.md");
+
+    let check = || {
+        cargo_bin_cmd!("distronomicon")
+            .arg("--app")
+            .arg("myapp")
+            .arg("--install-root")
+            .arg(install_root.as_str())
+            .arg("check")
+            .arg("--repo")
+            .arg("owner/repo")
+            .arg("--state-directory")
+            .arg(state_dir.as_str())
+            .arg("--github-host")
+            .arg(mock_server.uri())
+            .arg("--deep")
+            .output()
+            .unwrap()
+    };
+
+    let output = check();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("drift: 1 file(s) differ from the v1.0.0 manifest"));
+    assert!(stdout.contains("  missing:  myapp-v1.0.0/README.md"));
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("Installed files of myapp differ from the release manifest")
+    );
+
+    fs::write(&readme, "edited").unwrap();
+    let output = check();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("  modified: myapp-v1.0.0/README.md"));
+
+    fs::write(&readme, "fake binary").unwrap();
+    let output = check();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("manifest: 2 file(s) match v1.0.0"));
+}
+
+async fn check_with_token_source(source: &[&str], envs: &[(&str, &str)]) -> std::process::Output {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/latest"))
+        .and(header("authorization", "Bearer ghp_from_source"))
+        .respond_with(ResponseTemplate::new(304).insert_header("etag", "\"abc123\""))
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    let install_root = temp_dir.path().join("opt");
+    create_state_file(&state_dir, "myapp", "v1.0.0", "\"abc123\"");
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+
+    cargo_bin_cmd!("distronomicon")
+        .env_remove("GITHUB_TOKEN")
+        .arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg("check")
+        .arg("--repo")
+        .arg("owner/repo")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .arg("--github-host")
+        .arg(mock_server.uri())
+        .args(source)
+        .envs(envs.iter().copied())
+        .output()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn check_reads_token_from_file() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let token_file = temp_dir.path().join("token");
+    fs::write(&token_file, "ghp_from_source\n").unwrap();
+
+    let output = check_with_token_source(&["--github-token-file", token_file.as_str()], &[]).await;
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("up-to-date: v1.0.0"));
+}
+
+#[tokio::test]
+async fn check_reads_token_from_command() {
+    let output = check_with_token_source(
+        &["--github-token-command", "printf 'ghp_from_source\\n'"],
+        &[],
+    )
+    .await;
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("up-to-date: v1.0.0"));
+
+    let output = check_with_token_source(&["--github-token-command", "exit 3"], &[]).await;
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("token command failed"));
+}
+
+#[tokio::test]
+async fn check_reads_token_from_gh_cli() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let gh = temp_dir.path().join("gh");
+    fs::write(
+        &gh,
+        "#!/bin/sh\n[ \"$*\" = \"auth token --hostname 127.0.0.1\" ] && echo ghp_from_source\n",
+    )
+    .unwrap();
+    fs::set_permissions(&gh, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = check_with_token_source(
+        &["--github-token-from-gh"],
+        &[("PATH", temp_dir.path().as_str())],
+    )
+    .await;
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("up-to-date: v1.0.0"));
+}
+
+#[tokio::test]
+async fn check_reads_token_from_gh_hosts_file() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    fs::write(
+        temp_dir.path().join("hosts.yml"),
+        "127.0.0.1:\n    oauth_token: ghp_from_source\n    user: octocat\n",
+    )
+    .unwrap();
+    let empty_path = temp_dir.path().join("no-gh");
+    fs::create_dir(&empty_path).unwrap();
+
+    let envs = [
+        ("PATH", empty_path.as_str()),
+        ("GH_CONFIG_DIR", temp_dir.path().as_str()),
+    ];
+    let output = check_with_token_source(&["--github-token-from-gh"], &envs).await;
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("up-to-date: v1.0.0"));
+
+    fs::remove_file(temp_dir.path().join("hosts.yml")).unwrap();
+    let output = check_with_token_source(&["--github-token-from-gh"], &envs).await;
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains(
+            "gh CLI has no token for 127.0.0.1; run `gh auth login --hostname 127.0.0.1`"
+        )
+    );
+}
+
+#[tokio::test]
+async fn check_tag_pattern_ignores_other_components() {
+    let mock_server = MockServer::start().await;
+
+    let release = |tag: &str, created_at: &str| {
+        serde_json::json!({
+            "tag_name": tag,
+            "prerelease": false,
+            "created_at": created_at,
+            "assets": [{
+                "name": "myapp.tar.gz",
+                "url": format!("https://api.github.com/repos/owner/repo/releases/assets/{tag}"),
+                "browser_download_url": format!("https://github.com/owner/repo/releases/download/{tag}/myapp.tar.gz"),
+                "size": 1024
+            }]
+        })
+    };
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            release("server-v2.0.0", "2025-10-28T12:00:00Z"),
+            release("cli-v1.1.0", "2025-10-27T12:00:00Z"),
+            release("cli-v1.0.0", "2025-10-20T12:00:00Z"),
+        ])))
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    let install_root = temp_dir.path().join("opt");
+
+    create_state_file(&state_dir, "myapp", "cli-v1.0.0", "\"abc123\"");
+    create_installed_version(&install_root, "myapp", "cli-v1.0.0");
+
+    let output = cargo_bin_cmd!("distronomicon")
+        .arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg("check")
+        .arg("--repo")
+        .arg("owner/repo")
+        .arg("--tag-pattern")
+        .arg("^cli-v")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .arg("--github-host")
+        .arg(mock_server.uri())
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("cli-v1.1.0"), "{stdout}");
+    assert!(!stdout.contains("server-v2.0.0"), "{stdout}");
+}
+
+#[tokio::test]
+async fn check_version_constraint_picks_highest_version_in_range() {
+    let mock_server = MockServer::start().await;
+
+    let release = |tag: &str, created_at: &str| {
+        serde_json::json!({
+            "tag_name": tag,
+            "prerelease": false,
+            "created_at": created_at,
+            "assets": [{
+                "name": "myapp.tar.gz",
+                "url": format!("https://api.github.com/repos/owner/repo/releases/assets/{tag}"),
+                "browser_download_url": format!("https://github.com/owner/repo/releases/download/{tag}/myapp.tar.gz"),
+                "size": 1024
+            }]
+        })
+    };
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            release("v2.0.0", "2025-10-28T12:00:00Z"),
+            release("v1.4.9", "2025-10-27T12:00:00Z"),
+            release("v1.5.1", "2025-10-25T12:00:00Z"),
+            release("v1.4.0", "2025-10-20T12:00:00Z"),
+        ])))
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    let install_root = temp_dir.path().join("opt");
+
+    create_state_file(&state_dir, "myapp", "v1.4.0", "\"abc123\"");
+    create_installed_version(&install_root, "myapp", "v1.4.0");
+
+    let output = cargo_bin_cmd!("distronomicon")
+        .arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg("check")
+        .arg("--repo")
+        .arg("owner/repo")
+        .arg("--version-constraint")
+        .arg("^1.4")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .arg("--github-host")
+        .arg(mock_server.uri())
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("v1.5.1"), "{stdout}");
+    assert!(!stdout.contains("v2.0.0"), "{stdout}");
+}
+
+#[tokio::test]
+async fn check_min_release_age_skips_recent_releases() {
+    let mock_server = MockServer::start().await;
+
+    let now = Timestamp::now();
+    let release = |tag: &str, age_hours: i64| {
+        serde_json::json!({
+            "tag_name": tag,
+            "prerelease": false,
+            "published_at": now - SignedDuration::from_hours(age_hours),
+            "assets": [{
+                "name": "myapp.tar.gz",
+                "url": format!("https://api.github.com/repos/owner/repo/releases/assets/{tag}"),
+                "browser_download_url": format!("https://github.com/owner/repo/releases/download/{tag}/myapp.tar.gz"),
+                "size": 1024
+            }]
+        })
+    };
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            release("v1.2.0", 1),
+            release("v1.1.0", 72),
+            release("v1.0.0", 240),
+        ])))
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    let install_root = temp_dir.path().join("opt");
+
+    create_state_file(&state_dir, "myapp", "v1.0.0", "\"abc123\"");
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+
+    let check = |min_age: &str| {
+        cargo_bin_cmd!("distronomicon")
+            .arg("--app")
+            .arg("myapp")
+            .arg("--install-root")
+            .arg(install_root.as_str())
+            .arg("check")
+            .arg("--repo")
+            .arg("owner/repo")
+            .arg("--min-release-age")
+            .arg(min_age)
+            .arg("--state-directory")
+            .arg(state_dir.as_str())
+            .arg("--github-host")
+            .arg(mock_server.uri())
+            .output()
+            .unwrap()
+    };
+
+    let output = check("48h");
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("v1.1.0"), "{stdout}");
+    assert!(!stdout.contains("v1.2.0"), "{stdout}");
+
+    let output = check("30d");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("No release was published at least 30d ago"),
+        "{stderr}"
+    );
+}
+
+#[tokio::test]
+async fn check_reports_blocked_release() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/latest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "tag_name": "v1.1.0",
+            "prerelease": false,
+            "assets": []
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    let install_root = temp_dir.path().join("opt");
+
+    create_state_file(&state_dir, "myapp", "v1.0.0", "\"abc123\"");
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+
+    let check = |filter: &[&str], output: &str| {
+        cargo_bin_cmd!("distronomicon")
+            .arg("--app")
+            .arg("myapp")
+            .arg("--install-root")
+            .arg(install_root.as_str())
+            .arg("check")
+            .arg("--repo")
+            .arg("owner/repo")
+            .args(filter)
+            .arg("--output")
+            .arg(output)
+            .arg("--state-directory")
+            .arg(state_dir.as_str())
+            .arg("--github-host")
+            .arg(mock_server.uri())
+            .output()
+            .unwrap()
+    };
+
+    let output = check(&["--skip-version", "1.1.0"], "text");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "update-blocked: v1.0.0 -> v1.1.0 (listed in skip_versions)\n"
+    );
+
+    let output = check(&["--allow-versions", r"^v1\.0\."], "json");
+    assert_eq!(output.status.code(), Some(0));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["latest_tag"], "v1.1.0");
+    assert_eq!(report["update_available"], false);
+    assert_eq!(
+        report["blocked"],
+        r"does not match allow_versions '^v1\.0\.'"
+    );
+}
+
+#[tokio::test]
+async fn check_shows_release_notes() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/latest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "tag_name": "v1.1.0",
+            "prerelease": false,
+            "body": "## Fixes\n\n* Stop **leaking** file handles ([#42](https://github.com/owner/repo/pull/42))\n",
+            "assets": []
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    let install_root = temp_dir.path().join("opt");
+
+    create_state_file(&state_dir, "myapp", "v1.0.0", "\"abc123\"");
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+
+    let check = |output: &str| {
+        cargo_bin_cmd!("distronomicon")
+            .arg("--app")
+            .arg("myapp")
+            .arg("--install-root")
+            .arg(install_root.as_str())
+            .arg("check")
+            .arg("--repo")
+            .arg("owner/repo")
+            .arg("--show-notes")
+            .arg("--output")
+            .arg(output)
+            .arg("--state-directory")
+            .arg(state_dir.as_str())
+            .arg("--github-host")
+            .arg(mock_server.uri())
+            .output()
+            .unwrap()
+    };
+
+    let output = check("text");
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(
+            "update-available: v1.0.0 -> v1.1.0\n  notes:\n    Fixes\n\n    - Stop leaking file handles (#42)\n"
+        ),
+        "{stdout}"
+    );
+
+    let output = check("json");
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        report["notes"],
+        "Fixes\n\n- Stop leaking file handles (#42)"
+    );
+}
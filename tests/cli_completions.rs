@@ -0,0 +1,97 @@
+use std::{fs, os::unix::fs::symlink, thread, time::Duration};
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use camino::Utf8Path;
+use camino_tempfile::tempdir;
+
+fn completions(shell: &str) -> std::process::Output {
+    cargo_bin_cmd!("distronomicon")
+        .arg("completions")
+        .arg(shell)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn bash_completions_cover_subcommands_and_flags() {
+    let output = completions("bash");
+
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("_distronomicon()"));
+    assert!(stdout.contains("self-update"));
+    assert!(stdout.contains("--install-root"));
+    assert!(stdout.contains("--checksum-pattern"));
+}
+
+#[test]
+fn zsh_and_fish_completions_are_generated() {
+    let output = completions("zsh");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("#compdef distronomicon"));
+
+    let output = completions("fish");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("complete -c distronomicon"));
+}
+
+#[test]
+fn unknown_shell_is_rejected() {
+    let output = completions("tcsh");
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid value 'tcsh'"));
+}
+
+fn complete(line: &[&str], install_root: &Utf8Path) -> String {
+    let output = cargo_bin_cmd!("distronomicon")
+        .env("COMPLETE", "fish")
+        .env("DISTRONOMICON_INSTALL_ROOT", install_root)
+        .env_remove("STATE_DIRECTORY")
+        .arg("--")
+        .args(line)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn dynamic_completion_lists_installed_apps_and_retained_tags() {
+    let temp_dir = tempdir().unwrap();
+    let install_root = temp_dir.path();
+    let releases = install_root.join("myapp/releases");
+    fs::create_dir_all(releases.join("v1.0.0")).unwrap();
+    thread::sleep(Duration::from_millis(10));
+    fs::create_dir_all(releases.join("v1.1.0")).unwrap();
+    fs::write(releases.join("v1.1.0/myapp"), "").unwrap();
+    fs::create_dir_all(install_root.join("myapp/bin")).unwrap();
+    symlink(
+        releases.join("v1.1.0/myapp"),
+        install_root.join("myapp/bin/myapp"),
+    )
+    .unwrap();
+    fs::create_dir_all(install_root.join("unmanaged")).unwrap();
+
+    let apps = complete(&["distronomicon", "--app", ""], install_root);
+    assert_eq!(apps, "myapp\n");
+
+    let tags = complete(
+        &["distronomicon", "--app", "myapp", "rollback", "--to", ""],
+        install_root,
+    );
+    assert_eq!(tags, "v1.1.0\tactive\nv1.0.0\n");
+}
+
+#[test]
+fn dynamic_registration_calls_back_into_binary() {
+    let output = cargo_bin_cmd!("distronomicon")
+        .args(["completions", "bash", "--dynamic"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("COMPLETE=\"bash\""));
+    assert!(stdout.contains("-F _clap_complete_distronomicon distronomicon"));
+}
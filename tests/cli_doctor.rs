@@ -0,0 +1,136 @@
+use std::{fs, os::unix};
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use camino::Utf8Path;
+use camino_tempfile::Utf8TempDir;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+};
+
+fn create_installed_version(install_root: &Utf8Path, app: &str, tag: &str) {
+    let releases_dir = install_root.join(app).join("releases").join(tag);
+    let bin_dir = install_root.join(app).join("bin");
+
+    fs::create_dir_all(&releases_dir).unwrap();
+    fs::create_dir_all(&bin_dir).unwrap();
+    fs::write(releases_dir.join(app), "fake binary").unwrap();
+    unix::fs::symlink(format!("../releases/{tag}/{app}"), bin_dir.join(app)).unwrap();
+}
+
+async fn mount_repo(mock_server: &MockServer) {
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({ "private": false }))
+                .insert_header("x-ratelimit-remaining", "4999"),
+        )
+        .mount(mock_server)
+        .await;
+}
+
+fn run_doctor(
+    install_root: &Utf8Path,
+    state_dir: &Utf8Path,
+    mock_server: &MockServer,
+    min_free_space: &str,
+) -> std::process::Output {
+    let mut cmd = cargo_bin_cmd!("distronomicon");
+    cmd.arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg("doctor")
+        .arg("--repo")
+        .arg("owner/repo")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .arg("--github-host")
+        .arg(mock_server.uri())
+        .arg("--min-free-space")
+        .arg(min_free_space)
+        .env_remove("GITHUB_TOKEN")
+        .output()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn doctor_passes_for_healthy_install() {
+    let mock_server = MockServer::start().await;
+    mount_repo(&mock_server).await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+
+    let output = run_doctor(&install_root, &state_dir, &mock_server, "1");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(output.status.code(), Some(0), "stdout: {stdout}");
+    assert!(stdout.contains(&format!(
+        "ok    install directory {} is writable",
+        install_root.join("myapp")
+    )));
+    assert!(stdout.contains("ok    1 symlink(s) in bin/ point to v1.0.0"));
+    assert!(stdout.contains("ok    owner/repo is accessible (public)"));
+    assert!(!stdout.contains("FAIL"));
+}
+
+#[tokio::test]
+async fn doctor_reports_broken_symlink() {
+    let mock_server = MockServer::start().await;
+    mount_repo(&mock_server).await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+    fs::remove_dir_all(install_root.join("myapp").join("releases").join("v1.0.0")).unwrap();
+
+    let output = run_doctor(&install_root, &state_dir, &mock_server, "1");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        String::from_utf8_lossy(&output.stdout)
+            .contains("points to missing ../releases/v1.0.0/myapp")
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("doctor found 1 problem(s)"));
+}
+
+#[tokio::test]
+async fn doctor_reports_unreachable_repo() {
+    let mock_server = MockServer::start().await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+
+    let output = run_doctor(&install_root, &state_dir, &mock_server, "1");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.contains("ok    myapp is not installed yet"));
+    assert!(stdout.contains("FAIL  owner/repo not found on"));
+}
+
+#[tokio::test]
+async fn doctor_fails_when_free_space_is_insufficient() {
+    let mock_server = MockServer::start().await;
+    mount_repo(&mock_server).await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+
+    let output = run_doctor(
+        &install_root,
+        &state_dir,
+        &mock_server,
+        "18446744073709551615",
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("FAIL  only "));
+}
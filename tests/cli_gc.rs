@@ -0,0 +1,78 @@
+use std::{
+    fs::{self, File},
+    time::{Duration, SystemTime},
+};
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use camino::{Utf8Path, Utf8PathBuf};
+use camino_tempfile::tempdir;
+
+fn create_staging(install_root: &Utf8Path, name: &str, age: Duration) -> Utf8PathBuf {
+    let dir = install_root.join("myapp").join("staging").join(name);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("partial"), "data").unwrap();
+    File::open(&dir)
+        .unwrap()
+        .set_modified(SystemTime::now() - age)
+        .unwrap();
+    dir
+}
+
+fn run_gc(install_root: &Utf8Path, state_dir: &Utf8Path, extra: &[&str]) -> String {
+    let mut cmd = cargo_bin_cmd!();
+    let output = cmd
+        .arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg("gc")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .args(extra)
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn gc_removes_only_stale_staging_directories() {
+    let temp_dir = tempdir().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+
+    let old = create_staging(
+        &install_root,
+        "v1.0.0.abc123",
+        Duration::from_secs(2 * 86400),
+    );
+    let recent = create_staging(&install_root, "v1.1.0.def456", Duration::ZERO);
+
+    let stdout = run_gc(&install_root, &state_dir, &[]);
+
+    assert!(stdout.contains("Removed 1 stale staging directory for myapp"));
+    assert!(!old.exists());
+    assert!(recent.exists());
+}
+
+#[test]
+fn gc_max_age_and_dry_run() {
+    let temp_dir = tempdir().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+
+    let dir = create_staging(&install_root, "v1.1.0.def456", Duration::from_secs(120));
+
+    let stdout = run_gc(&install_root, &state_dir, &["--max-age", "60", "--dry-run"]);
+    assert!(stdout.contains(&format!("Would remove {dir}")));
+    assert!(dir.exists());
+
+    run_gc(&install_root, &state_dir, &["--max-age", "60"]);
+    assert!(!dir.exists());
+}
@@ -0,0 +1,92 @@
+use std::fs;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use camino::Utf8Path;
+use camino_tempfile::Utf8TempDir;
+
+fn write_history(state_dir: &Utf8Path, app: &str) {
+    let app_dir = state_dir.join(app);
+    fs::create_dir_all(&app_dir).unwrap();
+    let lines = [
+        serde_json::json!({
+            "tag": "v1.0.0",
+            "installed_at": "2025-10-01T12:00:00Z",
+            "asset": "myapp-1.0.0.tar.gz",
+            "sha256": "a".repeat(64),
+        }),
+        serde_json::json!({
+            "tag": "v1.1.0",
+            "installed_at": "2025-10-28T12:00:00Z",
+            "asset": "myapp-1.1.0.tar.gz",
+            "sha256": "b".repeat(64),
+            "previous_tag": "v1.0.0",
+        }),
+    ]
+    .map(|line| line.to_string() + "\n")
+    .concat();
+    fs::write(app_dir.join("history.jsonl"), lines).unwrap();
+}
+
+fn run_history(state_dir: &Utf8Path, extra: &[&str]) -> std::process::Output {
+    cargo_bin_cmd!("distronomicon")
+        .env("TZ", "UTC")
+        .arg("--app")
+        .arg("myapp")
+        .arg("history")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .args(extra)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn history_prints_entries_oldest_first() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    write_history(&state_dir, "myapp");
+
+    let output = run_history(&state_dir, &[]);
+
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec![
+            format!(
+                "2025-10-01 12:00:00 +00:00  v1.0.0  -       myapp-1.0.0.tar.gz  sha256:{}",
+                "a".repeat(64)
+            ),
+            format!(
+                "2025-10-28 12:00:00 +00:00  v1.1.0  v1.0.0  myapp-1.1.0.tar.gz  sha256:{}",
+                "b".repeat(64)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn history_json_with_limit() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    write_history(&state_dir, "myapp");
+
+    let output = run_history(&state_dir, &["--limit", "1", "--json"]);
+
+    assert_eq!(output.status.code(), Some(0));
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(entries.as_array().unwrap().len(), 1);
+    assert_eq!(entries[0]["tag"], "v1.1.0");
+}
+
+#[test]
+fn history_without_installs() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+
+    let output = run_history(&state_dir, &[]);
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No installs recorded for myapp"));
+}
@@ -0,0 +1,92 @@
+use std::{fs, os::unix, thread, time::Duration};
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use camino::Utf8Path;
+use camino_tempfile::tempdir;
+
+fn create_release(install_root: &Utf8Path, app: &str, tag: &str, content: &str) {
+    let release_dir = install_root.join(app).join("releases").join(tag);
+    fs::create_dir_all(&release_dir).unwrap();
+    fs::write(release_dir.join(app), content).unwrap();
+}
+
+fn list(install_root: &Utf8Path, extra: &[&str]) -> String {
+    list_in_zone("UTC", install_root, extra)
+}
+
+fn list_in_zone(tz: &str, install_root: &Utf8Path, extra: &[&str]) -> String {
+    let mut cmd = cargo_bin_cmd!();
+    let output = cmd
+        .env("TZ", tz)
+        .arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg("list")
+        .args(extra)
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(0));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn list_with_no_releases() {
+    let temp_dir = tempdir().unwrap();
+    let install_root = temp_dir.path().join("opt");
+
+    assert_eq!(list(&install_root, &[]), "No releases installed\n");
+}
+
+#[test]
+fn list_marks_active_release() {
+    let temp_dir = tempdir().unwrap();
+    let install_root = temp_dir.path().join("opt");
+
+    create_release(&install_root, "myapp", "v1.0.0", "old");
+    thread::sleep(Duration::from_millis(10));
+    create_release(&install_root, "myapp", "v1.10.0", "newer");
+
+    let bin_dir = install_root.join("myapp").join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    unix::fs::symlink("../releases/v1.0.0/myapp", bin_dir.join("myapp")).unwrap();
+
+    let stdout = list(&install_root, &[]);
+    let lines = stdout.lines().collect::<Vec<_>>();
+
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("  v1.10.0  "));
+    assert!(lines[0].ends_with("  5 B"));
+    assert!(lines[1].starts_with("* v1.0.0   "));
+    assert!(lines[1].ends_with("  3 B"));
+}
+
+#[test]
+fn list_uses_state_install_time_for_active_release() {
+    let temp_dir = tempdir().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+
+    create_release(&install_root, "myapp", "v1.0.0", "binary");
+    let bin_dir = install_root.join("myapp").join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    unix::fs::symlink("../releases/v1.0.0/myapp", bin_dir.join("myapp")).unwrap();
+
+    fs::create_dir_all(state_dir.join("myapp")).unwrap();
+    fs::write(
+        state_dir.join("myapp").join("state.json"),
+        r#"{"latest_tag":"v1.0.0","etag":"\"abc\"","last_modified":"2025-10-28T12:00:00Z","installed_at":"2025-10-28T12:34:56Z"}"#,
+    )
+    .unwrap();
+
+    let stdout = list(&install_root, &["--state-directory", state_dir.as_str()]);
+    assert_eq!(stdout, "* v1.0.0  2025-10-28 12:34:56 +00:00  6 B\n");
+
+    let stdout = list_in_zone(
+        "EST5EDT,M3.2.0,M11.1.0",
+        &install_root,
+        &["--state-directory", state_dir.as_str()],
+    );
+    assert_eq!(stdout, "* v1.0.0  2025-10-28 08:34:56 -04:00  6 B\n");
+}
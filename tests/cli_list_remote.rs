@@ -0,0 +1,118 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+};
+
+async fn mount_releases(mock_server: &MockServer) {
+    let releases = serde_json::json!([
+        {
+            "tag_name": "v1.2.0-rc1",
+            "prerelease": true,
+            "published_at": "2025-10-30T12:00:00Z",
+            "assets": [
+                {"name": "myapp-1.2.0-rc1.tar.gz", "url": "u", "browser_download_url": "b", "size": 1}
+            ]
+        },
+        {
+            "tag_name": "v1.1.0",
+            "prerelease": false,
+            "published_at": "2025-10-28T12:00:00Z",
+            "assets": [
+                {"name": "myapp-1.1.0.tar.gz", "url": "u", "browser_download_url": "b", "size": 1},
+                {"name": "SHA256SUMS", "url": "u", "browser_download_url": "b", "size": 1}
+            ]
+        },
+        {
+            "tag_name": "v1.0.0",
+            "prerelease": false,
+            "published_at": "2025-10-01T12:00:00Z",
+            "assets": []
+        }
+    ]);
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(releases))
+        .mount(mock_server)
+        .await;
+}
+
+fn list_remote(mock_server: &MockServer, extra: &[&str]) -> String {
+    let mut cmd = cargo_bin_cmd!("distronomicon");
+    let output = cmd
+        .arg("list-remote")
+        .arg("--repo")
+        .arg("owner/repo")
+        .arg("--github-host")
+        .arg(mock_server.uri())
+        .args(extra)
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[tokio::test]
+async fn list_remote_shows_matching_assets() {
+    let mock_server = MockServer::start().await;
+    mount_releases(&mock_server).await;
+
+    let stdout = list_remote(&mock_server, &["--pattern", r"myapp-.*\.tar\.gz"]);
+
+    assert_eq!(
+        stdout,
+        "v1.1.0  2025-10-28              myapp-1.1.0.tar.gz\n\
+         v1.0.0  2025-10-01              (no matching asset)\n"
+    );
+}
+
+#[tokio::test]
+async fn list_remote_includes_prereleases_when_allowed() {
+    let mock_server = MockServer::start().await;
+    mount_releases(&mock_server).await;
+
+    let stdout = list_remote(&mock_server, &["--allow-prerelease"]);
+    let lines = stdout.lines().collect::<Vec<_>>();
+
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], "v1.2.0-rc1  2025-10-30  prerelease  1 asset(s)");
+}
+
+#[tokio::test]
+async fn list_remote_filters_by_channel() {
+    let mock_server = MockServer::start().await;
+    let release = |tag: &str, prerelease: bool| {
+        serde_json::json!({
+            "tag_name": tag,
+            "prerelease": prerelease,
+            "published_at": "2025-10-28T12:00:00Z",
+            "assets": []
+        })
+    };
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            release("v2.0.0-alpha.1", true),
+            release("v1.2.0-rc1", true),
+            release("v1.1.0", false),
+        ])))
+        .mount(&mock_server)
+        .await;
+
+    let stdout = list_remote(&mock_server, &["--channel", "rc"]);
+    let tags = stdout
+        .lines()
+        .map(|line| line.split_whitespace().next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(tags, ["v1.2.0-rc1", "v1.1.0"]);
+
+    let stdout = list_remote(&mock_server, &["--channel", "prerelease"]);
+    assert_eq!(stdout.lines().count(), 3);
+}
@@ -0,0 +1,134 @@
+use std::{fs, os::unix::fs::PermissionsExt as _};
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use camino::Utf8Path;
+use camino_tempfile::Utf8TempDir;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_partial_json, header, method, path},
+};
+
+async fn mount_device_flow(mock_server: &MockServer, final_response: serde_json::Value) {
+    Mock::given(method("POST"))
+        .and(path("/login/device/code"))
+        .and(body_partial_json(
+            serde_json::json!({ "client_id": "Iv1.test", "scope": "repo" }),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "device_code": "device-123",
+            "user_code": "WDJB-MJHT",
+            "verification_uri": "https://github.com/login/device",
+            "expires_in": 900,
+            "interval": 0
+        })))
+        .mount(mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/login/oauth/access_token"))
+        .and(body_partial_json(serde_json::json!({
+            "device_code": "device-123",
+            "grant_type": "urn:ietf:params:oauth:grant-type:device_code"
+        })))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({ "error": "authorization_pending" })),
+        )
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/login/oauth/access_token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(final_response))
+        .mount(mock_server)
+        .await;
+}
+
+fn login(state_dir: &Utf8Path, host: &str) -> std::process::Output {
+    cargo_bin_cmd!("distronomicon")
+        .arg("login")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .arg("--github-host")
+        .arg(host)
+        .arg("--client-id")
+        .arg("Iv1.test")
+        .output()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn login_stores_token_used_by_later_commands() {
+    let mock_server = MockServer::start().await;
+    mount_device_flow(
+        &mock_server,
+        serde_json::json!({ "access_token": "gho_device", "token_type": "bearer", "scope": "repo" }),
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/latest"))
+        .and(header("authorization", "Bearer gho_device"))
+        .respond_with(ResponseTemplate::new(304).insert_header("etag", "\"abc123\""))
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+
+    let output = login(&state_dir, &mock_server.uri());
+
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("First copy your one-time code: WDJB-MJHT"));
+    assert!(stdout.contains("Then open https://github.com/login/device in a browser"));
+    assert!(stdout.contains("Logged in to 127.0.0.1"));
+    let token_path = state_dir.join("github-tokens/127.0.0.1");
+    assert_eq!(fs::read_to_string(&token_path).unwrap(), "gho_device\n");
+    assert_eq!(
+        fs::metadata(&token_path).unwrap().permissions().mode() & 0o777,
+        0o600
+    );
+
+    let output = cargo_bin_cmd!("distronomicon")
+        .env_remove("GITHUB_TOKEN")
+        .arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(temp_dir.path().join("opt").as_str())
+        .arg("check")
+        .arg("--repo")
+        .arg("owner/repo")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .arg("--github-host")
+        .arg(mock_server.uri())
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[tokio::test]
+async fn login_reports_denied_authorization() {
+    let mock_server = MockServer::start().await;
+    mount_device_flow(
+        &mock_server,
+        serde_json::json!({ "error": "access_denied", "error_description": "The user has denied your application access." }),
+    )
+    .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+
+    let output = login(&state_dir, &mock_server.uri());
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("authorization was denied in the browser")
+    );
+    assert!(!state_dir.join("github-tokens").exists());
+}
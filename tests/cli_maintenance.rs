@@ -0,0 +1,103 @@
+use std::fs;
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use camino::Utf8Path;
+use camino_tempfile::Utf8TempDir;
+
+fn write_state(state_dir: &Utf8Path, app: &str) {
+    let app_dir = state_dir.join(app);
+    fs::create_dir_all(app_dir.join("debug")).unwrap();
+    let lines = ["v1.0.0", "v1.1.0", "v1.2.0"]
+        .map(|tag| {
+            serde_json::json!({
+                "tag": tag,
+                "installed_at": "2025-10-01T12:00:00Z",
+                "asset": format!("myapp-{tag}.tar.gz"),
+                "sha256": "a".repeat(64),
+            })
+            .to_string()
+                + "\n"
+        })
+        .concat();
+    fs::write(app_dir.join("history.jsonl"), lines).unwrap();
+    fs::write(app_dir.join("debug/response-1.json"), vec![b'x'; 4096]).unwrap();
+}
+
+fn run_maintenance(temp_dir: &Utf8TempDir, extra: &[&str]) -> std::process::Output {
+    cargo_bin_cmd!("distronomicon")
+        .arg("--app")
+        .arg("myapp")
+        .arg("--config")
+        .arg(temp_dir.path().join("myapp.toml").as_str())
+        .arg("maintenance")
+        .arg("--state-directory")
+        .arg(temp_dir.path().join("state").as_str())
+        .args(extra)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn maintenance_dry_run_reports_without_removing() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    write_state(&state_dir, "myapp");
+    fs::write(temp_dir.path().join("myapp.toml"), "").unwrap();
+
+    let output = run_maintenance(
+        &temp_dir,
+        &[
+            "--history-keep",
+            "1",
+            "--debug-max-size",
+            "1KiB",
+            "--dry-run",
+            "--json",
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["dry_run"], true);
+    assert_eq!(report["history_removed"], 2);
+    assert_eq!(report["debug_freed"], 4096);
+    assert_eq!(
+        fs::read_to_string(state_dir.join("myapp/history.jsonl"))
+            .unwrap()
+            .lines()
+            .count(),
+        3
+    );
+    assert!(state_dir.join("myapp/debug/response-1.json").exists());
+}
+
+#[test]
+fn maintenance_applies_config_limits() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let state_dir = temp_dir.path().join("state");
+    write_state(&state_dir, "myapp");
+    fs::write(
+        temp_dir.path().join("myapp.toml"),
+        "[maintenance]\nhistory_keep = 2\ndebug_max_size = \"1KiB\"\n",
+    )
+    .unwrap();
+
+    let output = run_maintenance(&temp_dir, &[]);
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Dropped 1 history entry and 1 debug file(s)"),
+        "{stdout}"
+    );
+    let history = fs::read_to_string(state_dir.join("myapp/history.jsonl")).unwrap();
+    assert_eq!(history.lines().count(), 2);
+    assert!(history.contains("v1.2.0"));
+    assert!(!history.contains("v1.0.0"));
+    assert!(!state_dir.join("myapp/debug/response-1.json").exists());
+}
@@ -0,0 +1,188 @@
+use std::{fs, os::unix};
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use camino::Utf8Path;
+use camino_tempfile::Utf8TempDir;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+};
+
+fn create_installed_version(install_root: &Utf8Path, app: &str, tag: &str) {
+    let releases_dir = install_root.join(app).join("releases").join(tag);
+    let bin_dir = install_root.join(app).join("bin");
+
+    fs::create_dir_all(&releases_dir).unwrap();
+    fs::create_dir_all(&bin_dir).unwrap();
+    fs::write(releases_dir.join(app), "fake binary").unwrap();
+    unix::fs::symlink(format!("../releases/{tag}/{app}"), bin_dir.join(app)).unwrap();
+}
+
+fn run(install_root: &Utf8Path, command: &str, args: &[&str]) -> std::process::Output {
+    cargo_bin_cmd!("distronomicon")
+        .arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg(command)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn pinned_app_refuses_update_until_unpinned() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/latest"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+
+    let output = run(
+        &install_root,
+        "pin",
+        &[
+            "--state-directory",
+            state_dir.as_str(),
+            "--reason",
+            "change freeze",
+        ],
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert!(
+        String::from_utf8_lossy(&output.stdout).contains("Pinned myapp at v1.0.0 (change freeze)")
+    );
+
+    let output = run(
+        &install_root,
+        "update",
+        &[
+            "--repo",
+            "owner/repo",
+            "--pattern",
+            "myapp-.*",
+            "--skip-verification",
+            "--state-directory",
+            state_dir.as_str(),
+            "--github-host",
+            &mock_server.uri(),
+        ],
+    );
+    assert_eq!(output.status.code(), Some(3));
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("myapp is pinned at v1.0.0 (change freeze); run `unpin` to allow updates")
+    );
+
+    let output = run(
+        &install_root,
+        "status",
+        &["--state-directory", state_dir.as_str()],
+    );
+    assert!(
+        String::from_utf8_lossy(&output.stdout).contains("pinned:    v1.0.0 (change freeze) since")
+    );
+
+    let output = run(
+        &install_root,
+        "unpin",
+        &["--state-directory", state_dir.as_str()],
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Unpinned myapp"));
+    assert!(!state_dir.join("myapp").join("pin.json").exists());
+
+    let output = run(
+        &install_root,
+        "unpin",
+        &["--state-directory", state_dir.as_str()],
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("myapp is not pinned"));
+}
+
+#[tokio::test]
+async fn check_reports_update_held_by_pin() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/latest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "tag_name": "v1.1.0",
+            "prerelease": false,
+            "draft": false,
+            "assets": []
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+    let output = run(
+        &install_root,
+        "pin",
+        &[
+            "--state-directory",
+            state_dir.as_str(),
+            "--reason",
+            "change freeze",
+        ],
+    );
+    assert_eq!(output.status.code(), Some(0));
+
+    let check = |extra: &[&str]| {
+        cargo_bin_cmd!("distronomicon")
+            .arg("--app")
+            .arg("myapp")
+            .arg("--install-root")
+            .arg(install_root.as_str())
+            .arg("--quiet")
+            .arg("check")
+            .arg("--repo")
+            .arg("owner/repo")
+            .arg("--state-directory")
+            .arg(state_dir.as_str())
+            .arg("--github-host")
+            .arg(mock_server.uri())
+            .args(extra)
+            .output()
+            .unwrap()
+    };
+
+    let output = check(&[]);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "update-held: v1.0.0 -> v1.1.0\npinned: v1.0.0 (change freeze)\n"
+    );
+
+    let output = check(&["--output", "json"]);
+    assert_eq!(output.status.code(), Some(3));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["update_available"], false);
+    assert_eq!(report["held"], true);
+    assert_eq!(report["pinned"]["tag"], "v1.0.0");
+}
+
+#[test]
+fn pin_requires_installed_release() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+
+    let output = run(
+        &install_root,
+        "pin",
+        &["--state-directory", state_dir.as_str()],
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("nothing to pin"));
+}
@@ -0,0 +1,135 @@
+use std::{fs, os::unix};
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use camino::Utf8Path;
+use camino_tempfile::Utf8TempDir;
+
+fn create_releases(install_root: &Utf8Path, app: &str, tags: &[&str], current: &str) {
+    let app_dir = install_root.join(app);
+    let bin_dir = app_dir.join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+
+    for tag in tags {
+        let release_dir = app_dir.join("releases").join(tag);
+        fs::create_dir_all(&release_dir).unwrap();
+        fs::write(release_dir.join(app), "fake binary").unwrap();
+    }
+    unix::fs::symlink(format!("../releases/{current}/{app}"), bin_dir.join(app)).unwrap();
+}
+
+fn run_prune(
+    install_root: &Utf8Path,
+    state_dir: &Utf8Path,
+    extra: &[&str],
+) -> std::process::Output {
+    let mut cmd = cargo_bin_cmd!("distronomicon");
+    cmd.arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg("prune")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .args(extra)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn prune_dry_run_lists_without_deleting() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+    let tags = ["v1.0.0", "v1.1.0", "v1.2.0"];
+    create_releases(&install_root, "myapp", &tags, "v1.2.0");
+
+    let output = run_prune(&install_root, &state_dir, &["--retain", "1", "--dry-run"]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Would delete"));
+    assert!(stdout.contains("v1.0.0"));
+    assert!(stdout.contains("v1.1.0"));
+    for tag in tags {
+        assert!(install_root.join("myapp/releases").join(tag).exists());
+    }
+}
+
+#[test]
+fn prune_keeps_active_release() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+    create_releases(
+        &install_root,
+        "myapp",
+        &["v1.0.0", "v1.1.0", "v1.2.0"],
+        "v1.0.0",
+    );
+
+    let output = run_prune(&install_root, &state_dir, &["--retain", "1"]);
+
+    assert!(output.status.success());
+    let releases_dir = install_root.join("myapp/releases");
+    assert!(releases_dir.join("v1.0.0").exists());
+    assert!(releases_dir.join("v1.2.0").exists());
+    assert!(!releases_dir.join("v1.1.0").exists());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Pruned 1 release(s) of myapp"));
+}
+
+#[test]
+fn prune_json_output_is_parseable() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+    create_releases(
+        &install_root,
+        "myapp",
+        &["v1.0.0", "v1.1.0", "v1.2.0"],
+        "v1.2.0",
+    );
+
+    let output = run_prune(&install_root, &state_dir, &["--retain", "2", "--json"]);
+
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["app"], "myapp");
+    assert_eq!(report["retain"], 2);
+    assert_eq!(report["dry_run"], false);
+    assert_eq!(report["pruned"], serde_json::json!(["v1.0.0"]));
+    assert_eq!(report["failed"], serde_json::json!([]));
+}
+
+#[test]
+fn prune_retain_max_size_keeps_pinned_release() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+    create_releases(
+        &install_root,
+        "myapp",
+        &["v1.0.0", "v1.1.0", "v1.2.0"],
+        "v1.2.0",
+    );
+    fs::create_dir_all(state_dir.join("myapp")).unwrap();
+    fs::write(
+        state_dir.join("myapp/pin.json"),
+        r#"{"tag":"v1.0.0","pinned_at":"2026-01-01T00:00:00Z"}"#,
+    )
+    .unwrap();
+
+    let output = run_prune(
+        &install_root,
+        &state_dir,
+        &["--retain", "3", "--retain-max-size", "12", "--json"],
+    );
+
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["retain_max_size"], 12);
+    assert_eq!(report["pruned"], serde_json::json!(["v1.1.0"]));
+    let releases_dir = install_root.join("myapp/releases");
+    assert!(releases_dir.join("v1.0.0").exists());
+    assert!(releases_dir.join("v1.2.0").exists());
+}
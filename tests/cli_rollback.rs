@@ -0,0 +1,256 @@
+use std::{
+    fs,
+    os::unix::{self, fs::PermissionsExt as _},
+    thread,
+    time::Duration,
+};
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use camino::Utf8Path;
+use camino_tempfile::tempdir;
+use distronomicon::state::{Backend, State, Store};
+
+fn create_release(install_root: &Utf8Path, app: &str, tag: &str) {
+    let release_dir = install_root.join(app).join("releases").join(tag);
+    fs::create_dir_all(&release_dir).unwrap();
+    fs::write(release_dir.join(app), "#!/bin/sh\n").unwrap();
+    let mut perms = fs::metadata(release_dir.join(app)).unwrap().permissions();
+    perms.set_mode(0o755);
+    fs::set_permissions(release_dir.join(app), perms).unwrap();
+}
+
+fn activate(install_root: &Utf8Path, app: &str, tag: &str) {
+    let bin_dir = install_root.join(app).join("bin");
+    fs::create_dir_all(&bin_dir).unwrap();
+    let _ = fs::remove_file(bin_dir.join(app));
+    unix::fs::symlink(format!("../releases/{tag}/{app}"), bin_dir.join(app)).unwrap();
+}
+
+fn write_state(state_dir: &Utf8Path, app: &str, latest: &str, previous: Option<&str>) {
+    let state_app_dir = state_dir.join(app);
+    fs::create_dir_all(&state_app_dir).unwrap();
+    let state = serde_json::json!({
+        "latest_tag": latest,
+        "etag": "\"etag\"",
+        "last_modified": "2025-10-28T12:00:00Z",
+        "installed_at": "2025-10-28T12:00:00Z",
+        "previous_tag": previous,
+    });
+    fs::write(state_app_dir.join("state.json"), state.to_string()).unwrap();
+}
+
+fn read_state(state_dir: &Utf8Path, app: &str) -> serde_json::Value {
+    let contents = fs::read_to_string(state_dir.join(app).join("state.json")).unwrap();
+    serde_json::from_str(&contents).unwrap()
+}
+
+fn rollback(install_root: &Utf8Path, state_dir: &Utf8Path, extra: &[&str]) -> std::process::Output {
+    let mut cmd = cargo_bin_cmd!();
+    cmd.arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg("rollback")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .args(extra)
+        .output()
+        .unwrap()
+}
+
+fn active_target(install_root: &Utf8Path) -> String {
+    fs::read_link(install_root.join("myapp").join("bin").join("myapp"))
+        .unwrap()
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[test]
+fn rollback_to_previous_tag_from_state() {
+    let temp_dir = tempdir().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+
+    create_release(&install_root, "myapp", "v1.0.0");
+    create_release(&install_root, "myapp", "v1.1.0");
+    activate(&install_root, "myapp", "v1.1.0");
+    write_state(&state_dir, "myapp", "v1.1.0", Some("v1.0.0"));
+
+    let output = rollback(&install_root, &state_dir, &[]);
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        String::from_utf8_lossy(&output.stdout).contains("Rolled back myapp from v1.1.0 to v1.0.0")
+    );
+
+    assert_eq!(active_target(&install_root), "../releases/v1.0.0/myapp");
+    let state = read_state(&state_dir, "myapp");
+    assert_eq!(state["latest_tag"], "v1.0.0");
+    assert_eq!(state["previous_tag"], "v1.1.0");
+}
+
+#[test]
+fn rollback_falls_back_to_newest_other_release() {
+    let temp_dir = tempdir().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+
+    create_release(&install_root, "myapp", "v1.0.0");
+    thread::sleep(Duration::from_millis(10));
+    create_release(&install_root, "myapp", "v1.1.0");
+    thread::sleep(Duration::from_millis(10));
+    create_release(&install_root, "myapp", "v1.2.0");
+    activate(&install_root, "myapp", "v1.2.0");
+
+    let output = rollback(&install_root, &state_dir, &[]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(active_target(&install_root), "../releases/v1.1.0/myapp");
+}
+
+#[test]
+fn rollback_to_explicit_tag_runs_restart_command() {
+    let temp_dir = tempdir().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+    let marker = temp_dir.path().join("restarted");
+
+    create_release(&install_root, "myapp", "v1.0.0");
+    create_release(&install_root, "myapp", "v1.1.0");
+    create_release(&install_root, "myapp", "v1.2.0");
+    activate(&install_root, "myapp", "v1.2.0");
+    write_state(&state_dir, "myapp", "v1.2.0", Some("v1.1.0"));
+
+    let output = rollback(
+        &install_root,
+        &state_dir,
+        &[
+            "--to",
+            "v1.0.0",
+            "--restart-command",
+            &format!("touch {marker}"),
+        ],
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(active_target(&install_root), "../releases/v1.0.0/myapp");
+    assert!(marker.exists());
+}
+
+#[test]
+fn rollback_fails_without_other_release() {
+    let temp_dir = tempdir().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+
+    create_release(&install_root, "myapp", "v1.0.0");
+    activate(&install_root, "myapp", "v1.0.0");
+
+    let output = rollback(&install_root, &state_dir, &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No previous release"));
+    assert_eq!(active_target(&install_root), "../releases/v1.0.0/myapp");
+}
+
+#[test]
+fn rollback_fails_for_missing_tag() {
+    let temp_dir = tempdir().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+
+    create_release(&install_root, "myapp", "v1.0.0");
+    activate(&install_root, "myapp", "v1.0.0");
+
+    let output = rollback(&install_root, &state_dir, &["--to", "v0.9.0"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Release v0.9.0 not found"));
+}
+
+#[test]
+fn rollback_with_sqlite_state_backend() {
+    let temp_dir = tempdir().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+
+    create_release(&install_root, "myapp", "v1.0.0");
+    create_release(&install_root, "myapp", "v1.1.0");
+    activate(&install_root, "myapp", "v1.1.0");
+
+    let store = Store::new(Backend::Sqlite, &state_dir);
+    let state: State = serde_json::from_value(serde_json::json!({
+        "latest_tag": "v1.1.0",
+        "etag": "\"etag\"",
+        "last_modified": "2025-10-28T12:00:00Z",
+        "installed_at": "2025-10-28T12:00:00Z",
+        "previous_tag": "v1.0.0",
+    }))
+    .unwrap();
+    store.save("myapp", &state).unwrap();
+
+    let output = cargo_bin_cmd!()
+        .arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg("--state-backend")
+        .arg("sqlite")
+        .arg("rollback")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .output()
+        .unwrap();
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let state = store.load("myapp").unwrap().unwrap();
+    assert_eq!(state.latest_tag, "v1.0.0");
+    assert_eq!(state.previous_tag.as_deref(), Some("v1.1.0"));
+    assert!(!state_dir.join("myapp").join("state.json").exists());
+}
+
+#[test]
+fn rollback_uses_install_history_without_previous_tag() {
+    let temp_dir = tempdir().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+
+    for tag in ["v1.0.0", "v1.1.0", "v1.2.0"] {
+        create_release(&install_root, "myapp", tag);
+    }
+    activate(&install_root, "myapp", "v1.2.0");
+    write_state(&state_dir, "myapp", "v1.2.0", None);
+    let history = [
+        ("v1.1.0", "v1.0.0"),
+        ("v1.0.0", "v1.1.0"),
+        ("v1.2.0", "v1.0.0"),
+    ]
+    .map(|(tag, previous)| {
+        serde_json::json!({
+            "tag": tag,
+            "installed_at": "2025-10-28T12:00:00Z",
+            "asset": format!("myapp-{tag}.tar.gz"),
+            "sha256": "a".repeat(64),
+            "previous_tag": previous,
+        })
+        .to_string()
+            + "\n"
+    })
+    .concat();
+    fs::write(state_dir.join("myapp").join("history.jsonl"), history).unwrap();
+
+    let output = rollback(&install_root, &state_dir, &[]);
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    assert_eq!(active_target(&install_root), "../releases/v1.0.0/myapp");
+}
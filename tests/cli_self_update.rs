@@ -0,0 +1,188 @@
+use std::{fmt::Write as _, fs, io::Write as _, os::unix::fs::PermissionsExt};
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use camino::Utf8Path;
+use camino_tempfile::tempdir;
+use distronomicon::self_update;
+use flate2::{Compression, write::GzEncoder};
+use sha2::{Digest as _, Sha256};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+};
+
+fn create_release_archive(root: &str, content: &[u8]) -> Vec<u8> {
+    let mut tar_data = Vec::new();
+    {
+        let mut tar = tar::Builder::new(&mut tar_data);
+        let mut header = tar::Header::new_gnu();
+        header.set_path(format!("{root}/distronomicon")).unwrap();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        tar.append(&header, content).unwrap();
+        tar.finish().unwrap();
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&tar_data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+async fn mount_release(mock_server: &MockServer, tag: &str, archive: &[u8]) {
+    let root = format!("distronomicon-{}", self_update::target_triple());
+    let asset_name = format!("{root}.tar.gz");
+    let checksums = format!("{}  {asset_name}\n", sha256_hex(archive));
+
+    let release_json = serde_json::json!({
+        "tag_name": tag,
+        "prerelease": false,
+        "draft": false,
+        "assets": [
+            {
+                "name": asset_name,
+                "url": format!("{}/download/{asset_name}", mock_server.uri()),
+                "browser_download_url": format!("{}/download/{asset_name}", mock_server.uri()),
+                "size": archive.len()
+            },
+            {
+                "name": "sha256.sum",
+                "url": format!("{}/download/sha256.sum", mock_server.uri()),
+                "browser_download_url": format!("{}/download/sha256.sum", mock_server.uri()),
+                "size": checksums.len()
+            }
+        ]
+    });
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/distronomicon/releases/latest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&release_json))
+        .mount(mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/download/{asset_name}")))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(archive.to_vec()))
+        .mount(mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/download/sha256.sum"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(checksums))
+        .mount(mock_server)
+        .await;
+}
+
+fn create_old_binary(path: &Utf8Path) {
+    fs::write(path, "#!/bin/sh\necho old\n").unwrap();
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+fn self_update_cmd(mock_server: &MockServer, target: &Utf8Path) -> std::process::Output {
+    cargo_bin_cmd!("distronomicon")
+        .arg("self-update")
+        .arg("--repo")
+        .arg("owner/distronomicon")
+        .arg("--github-host")
+        .arg(mock_server.uri())
+        .arg("--path")
+        .arg(target.as_str())
+        .output()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn self_update_replaces_binary() {
+    let mock_server = MockServer::start().await;
+    let new_binary = b"#!/bin/sh\necho new\n";
+    let root = format!("distronomicon-{}", self_update::target_triple());
+    mount_release(
+        &mock_server,
+        "v99.0.0",
+        &create_release_archive(&root, new_binary),
+    )
+    .await;
+
+    let temp_dir = tempdir().unwrap();
+    let target = temp_dir.path().join("distronomicon");
+    create_old_binary(&target);
+
+    let output = self_update_cmd(&mock_server, &target);
+
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!(
+        "Updated {target} from v{} to v99.0.0",
+        env!("CARGO_PKG_VERSION")
+    )));
+    assert_eq!(fs::read(&target).unwrap(), new_binary);
+    assert_eq!(
+        fs::metadata(&target).unwrap().permissions().mode() & 0o777,
+        0o755
+    );
+    assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+}
+
+#[tokio::test]
+async fn self_update_noop_when_current() {
+    let mock_server = MockServer::start().await;
+    let tag = format!("v{}", env!("CARGO_PKG_VERSION"));
+    let root = format!("distronomicon-{}", self_update::target_triple());
+    mount_release(
+        &mock_server,
+        &tag,
+        &create_release_archive(&root, b"#!/bin/sh\necho new\n"),
+    )
+    .await;
+
+    let temp_dir = tempdir().unwrap();
+    let target = temp_dir.path().join("distronomicon");
+    create_old_binary(&target);
+
+    let output = self_update_cmd(&mock_server, &target);
+
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("Already up-to-date: {tag}")));
+    assert_eq!(
+        fs::read_to_string(&target).unwrap(),
+        "#!/bin/sh\necho old\n"
+    );
+}
+
+#[tokio::test]
+async fn self_update_rejects_checksum_mismatch() {
+    let mock_server = MockServer::start().await;
+    let root = format!("distronomicon-{}", self_update::target_triple());
+    let asset_name = format!("{root}.tar.gz");
+    let archive = create_release_archive(&root, b"#!/bin/sh\necho new\n");
+    mount_release(&mock_server, "v99.0.0", &archive).await;
+    Mock::given(method("GET"))
+        .and(path("/download/sha256.sum"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(format!("{}  {asset_name}\n", "0".repeat(64))),
+        )
+        .with_priority(1)
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = tempdir().unwrap();
+    let target = temp_dir.path().join("distronomicon");
+    create_old_binary(&target);
+
+    let output = self_update_cmd(&mock_server, &target);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        fs::read_to_string(&target).unwrap(),
+        "#!/bin/sh\necho old\n"
+    );
+}
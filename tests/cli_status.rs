@@ -0,0 +1,130 @@
+use std::{fs, os::unix};
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use camino::Utf8Path;
+use camino_tempfile::Utf8TempDir;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+};
+
+fn create_installed_version(install_root: &Utf8Path, app: &str, tag: &str) {
+    let releases_dir = install_root.join(app).join("releases").join(tag);
+    let bin_dir = install_root.join(app).join("bin");
+
+    fs::create_dir_all(&releases_dir).unwrap();
+    fs::create_dir_all(&bin_dir).unwrap();
+    fs::write(releases_dir.join(app), "fake binary").unwrap();
+    unix::fs::symlink(format!("../releases/{tag}/{app}"), bin_dir.join(app)).unwrap();
+}
+
+fn create_state_file(state_dir: &Utf8Path, app: &str, tag: &str) {
+    let state_path = state_dir.join(app).join("state.json");
+    fs::create_dir_all(state_path.parent().unwrap()).unwrap();
+
+    let state = serde_json::json!({
+        "latest_tag": tag,
+        "etag": "\"abc123\"",
+        "last_modified": "2025-10-28T12:00:00Z",
+        "installed_at": "2025-10-28T12:00:00Z",
+        "previous_tag": "v0.9.0",
+        "repo": "owner/repo",
+        "host": "https://api.github.com",
+    });
+    fs::write(state_path, state.to_string()).unwrap();
+}
+
+fn run_status(install_root: &Utf8Path, state_dir: &Utf8Path, extra: &[&str]) -> String {
+    let mut cmd = cargo_bin_cmd!("distronomicon");
+    let output = cmd
+        .arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg("status")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .args(extra)
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn status_reports_local_state_offline() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+    create_state_file(&state_dir, "myapp", "v1.0.0");
+    let leftover = install_root
+        .join("myapp")
+        .join("staging")
+        .join("v1.1.0.abc123");
+    fs::create_dir_all(&leftover).unwrap();
+    fs::write(state_dir.join("myapp").join("lock"), "").unwrap();
+
+    let stdout = run_status(&install_root, &state_dir, &["--offline"]);
+
+    assert!(stdout.contains("installed: v1.0.0"));
+    assert!(stdout.contains("latest_tag:    v1.0.0"));
+    assert!(stdout.contains("previous_tag:  v0.9.0"));
+    assert!(stdout.contains("source:        owner/repo on https://api.github.com"));
+    assert!(stdout.contains("lock:      stale lock file"));
+    assert!(stdout.contains("staging:   1 leftover"));
+    assert!(stdout.contains(leftover.as_str()));
+    assert!(stdout.contains("remote:    skipped (--offline)"));
+}
+
+#[test]
+fn status_without_installation() {
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+
+    let stdout = run_status(&install_root, &state_dir, &[]);
+
+    assert!(stdout.contains("installed: (none)"));
+    assert!(stdout.contains("state:     (none)"));
+    assert!(stdout.contains("lock:      free"));
+    assert!(stdout.contains("staging:   clean"));
+    assert!(stdout.contains("remote:    skipped (no --repo)"));
+    assert!(!state_dir.exists());
+}
+
+#[tokio::test]
+async fn status_reports_available_update() {
+    let mock_server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases/latest"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "tag_name": "v1.1.0",
+            "prerelease": false,
+            "draft": false,
+            "assets": []
+        })))
+        .mount(&mock_server)
+        .await;
+
+    let temp_dir = Utf8TempDir::new().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+    create_installed_version(&install_root, "myapp", "v1.0.0");
+
+    let stdout = run_status(
+        &install_root,
+        &state_dir,
+        &["--repo", "owner/repo", "--github-host", &mock_server.uri()],
+    );
+
+    assert!(stdout.contains("remote:    update-available: v1.0.0 -> v1.1.0"));
+}
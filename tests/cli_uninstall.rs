@@ -0,0 +1,144 @@
+use std::{fs, os::unix};
+
+use assert_cmd::cargo::cargo_bin_cmd;
+use camino::Utf8Path;
+use camino_tempfile::tempdir;
+
+fn create_installed_app(install_root: &Utf8Path, state_dir: &Utf8Path, app: &str, tag: &str) {
+    let releases_dir = install_root.join(app).join("releases").join(tag);
+    let bin_dir = install_root.join(app).join("bin");
+    let staging_dir = install_root
+        .join(app)
+        .join("staging")
+        .join(format!("{tag}.abc123"));
+
+    fs::create_dir_all(&releases_dir).unwrap();
+    fs::create_dir_all(&bin_dir).unwrap();
+    fs::create_dir_all(&staging_dir).unwrap();
+    fs::write(releases_dir.join(app), "fake binary").unwrap();
+    unix::fs::symlink(format!("../releases/{tag}/{app}"), bin_dir.join(app)).unwrap();
+
+    let state_app_dir = state_dir.join(app);
+    fs::create_dir_all(&state_app_dir).unwrap();
+    fs::write(state_app_dir.join("state.json"), "{}").unwrap();
+}
+
+fn run_uninstall(install_root: &Utf8Path, state_dir: &Utf8Path, extra: &[&str]) -> String {
+    let mut cmd = cargo_bin_cmd!();
+    let output = cmd
+        .arg("--app")
+        .arg("myapp")
+        .arg("--install-root")
+        .arg(install_root.as_str())
+        .arg("uninstall")
+        .arg("--state-directory")
+        .arg(state_dir.as_str())
+        .args(extra)
+        .output()
+        .unwrap();
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn uninstall_removes_managed_paths() {
+    let temp_dir = tempdir().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+    create_installed_app(&install_root, &state_dir, "myapp", "v1.0.0");
+
+    let stdout = run_uninstall(&install_root, &state_dir, &[]);
+
+    assert!(stdout.contains("Uninstalled myapp"));
+    assert!(!install_root.join("myapp").exists());
+    assert!(!state_dir.join("myapp").exists());
+}
+
+#[test]
+fn uninstall_leaves_unmanaged_files_in_bin() {
+    let temp_dir = tempdir().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+    create_installed_app(&install_root, &state_dir, "myapp", "v1.0.0");
+
+    let bin_dir = install_root.join("myapp").join("bin");
+    fs::write(bin_dir.join("helper"), "#!/bin/sh\n").unwrap();
+
+    run_uninstall(&install_root, &state_dir, &[]);
+
+    assert!(!bin_dir.join("myapp").exists());
+    assert!(bin_dir.join("helper").exists());
+    assert!(!install_root.join("myapp").join("releases").exists());
+}
+
+#[test]
+fn uninstall_keep_state_and_releases() {
+    let temp_dir = tempdir().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+    create_installed_app(&install_root, &state_dir, "myapp", "v1.0.0");
+
+    run_uninstall(
+        &install_root,
+        &state_dir,
+        &["--keep-state", "--keep-releases"],
+    );
+
+    let app_dir = install_root.join("myapp");
+    assert!(!app_dir.join("bin").exists());
+    assert!(!app_dir.join("staging").exists());
+    assert!(app_dir.join("releases").join("v1.0.0").exists());
+    assert!(state_dir.join("myapp").join("state.json").exists());
+}
+
+#[test]
+fn uninstall_dry_run_removes_nothing() {
+    let temp_dir = tempdir().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+    create_installed_app(&install_root, &state_dir, "myapp", "v1.0.0");
+
+    let stdout = run_uninstall(&install_root, &state_dir, &["--dry-run"]);
+
+    assert!(stdout.contains(&format!(
+        "Would remove {}",
+        install_root.join("myapp").join("bin").join("myapp")
+    )));
+    assert!(stdout.contains(&format!(
+        "Would remove {}",
+        state_dir.join("myapp").join("state.json")
+    )));
+    assert!(
+        install_root
+            .join("myapp")
+            .join("bin")
+            .join("myapp")
+            .exists()
+    );
+    assert!(install_root.join("myapp").join("releases").exists());
+    assert!(state_dir.join("myapp").join("state.json").exists());
+}
+
+#[test]
+fn uninstall_only_removes_links_listed_in_manifest() {
+    let temp_dir = tempdir().unwrap();
+    let install_root = temp_dir.path().join("opt");
+    let state_dir = temp_dir.path().join("state");
+    create_installed_app(&install_root, &state_dir, "myapp", "v1.0.0");
+
+    let bin_dir = install_root.join("myapp").join("bin");
+    fs::write(bin_dir.join(".distronomicon-links"), "myapp\n").unwrap();
+    unix::fs::symlink("../releases/v1.0.0/myapp", bin_dir.join("manual")).unwrap();
+
+    run_uninstall(&install_root, &state_dir, &[]);
+
+    assert!(bin_dir.join("manual").symlink_metadata().is_ok());
+    assert!(bin_dir.join("myapp").symlink_metadata().is_err());
+    assert!(!bin_dir.join(".distronomicon-links").exists());
+}